@@ -23,15 +23,25 @@ Subcommands:
     nextest                  Run tests with nextest (requires cargo-nextest installed)
     setup                    Only perform automatic setup, but without asking questions (for getting a proper libstd)
     clean                    Clean the Miri cache & target directory
+    debug                    Run binaries or tests in the Priroda debugger (see below)
 
 The cargo options are exactly the same as for `cargo run` and `cargo test`, respectively.
 Furthermore, the following environment variables are recognized for `run` and `test`:
 
     MIRIFLAGS                Extra flags to pass to the Miri driver. Use this to pass `-Zmiri-...` flags.
 
+`cargo miri debug` takes Priroda's flags, then `run` or `test` and their cargo options:
+
+    cargo miri debug [--dap] [--port <port>] (run|test) [<cargo options>...] [--] [<program/test suite options>...]
+
+Priroda is taken from the PRIRODA environment variable, or from next to `cargo-miri`.
+It honors MIRIFLAGS like Miri does. `--port` cannot be used with `test`.
+
 Examples:
     cargo miri run
     cargo miri test -- test-suite-filter
+    cargo miri debug test --test integration -- test-suite-filter
+    cargo miri debug --dap --port 4711 run --bin server
 
     cargo miri setup --print-sysroot
         This will print the path to the generated sysroot (and nothing else) on stdout.
@@ -67,6 +77,44 @@ pub fn phase_cargo_miri(mut args: impl Iterator<Item = String>) {
         "setup" => MiriCommand::Setup,
         "test" | "t" | "run" | "r" | "nextest" => MiriCommand::Forward(subcommand),
         "clean" => MiriCommand::Clean,
+        "debug" => {
+            // Priroda's own flags come first, since everything after the verb is for cargo.
+            let mut priroda_flags = Vec::new();
+            let verb = loop {
+                let Some(arg) = args.next() else {
+                    show_error!("`cargo miri debug` expects a verb (`run` or `test`)");
+                };
+                match &*arg {
+                    "run" | "r" | "test" | "t" => break arg,
+                    "--dap" => priroda_flags.push(arg),
+                    "--port" => {
+                        let port = args.next().unwrap_or_else(|| {
+                            show_error!("`cargo miri debug --port` expects a port number")
+                        });
+                        priroda_flags.push(arg);
+                        priroda_flags.push(port);
+                    }
+                    _ if arg.starts_with("--port=") => priroda_flags.push(arg),
+                    "--help" | "-h" => {
+                        show_help();
+                        return;
+                    }
+                    _ =>
+                        show_error!(
+                            "`cargo miri debug` supports the verbs `run` and `test`, after the flags `--dap` and `--port`, but got `{arg}`"
+                        ),
+                }
+            };
+            // Every test binary would try to listen on the same port.
+            if matches!(&*verb, "test" | "t")
+                && priroda_flags.iter().any(|flag| flag.starts_with("--port"))
+            {
+                show_error!(
+                    "`cargo miri debug test` cannot be combined with `--port`; use `--dap` to talk DAP over stdio instead"
+                );
+            }
+            MiriCommand::Debug { verb, priroda_flags }
+        }
         // For use by the `./miri test` dependency builder.
         "build" if env::var_os("MIRI_BUILD_TEST_DEPS").is_some() =>
             MiriCommand::Forward("build".into()),
@@ -81,13 +129,13 @@ pub fn phase_cargo_miri(mut args: impl Iterator<Item = String>) {
                 return;
             }
             show_error!(
-                "`cargo miri` supports the following subcommands: `run`, `test`, `nextest`, `clean`, and `setup`."
+                "`cargo miri` supports the following subcommands: `run`, `test`, `nextest`, `debug`, `clean`, and `setup`."
             )
         }
     };
     if has_arg_flag("--help") || has_arg_flag("-h") {
         match subcommand {
-            MiriCommand::Forward(verb) => {
+            MiriCommand::Forward(verb) | MiriCommand::Debug { verb, .. } => {
                 println!("`cargo miri {verb}` supports the same flags as `cargo {verb}`:\n");
                 let mut cmd = cargo();
                 cmd.arg(verb);
@@ -151,8 +199,9 @@ pub fn phase_cargo_miri(mut args: impl Iterator<Item = String>) {
         .into_os_string()
         .into_string()
         .expect("current executable path is not valid UTF-8");
-    let cargo_cmd = match subcommand {
-        MiriCommand::Forward(s) => s,
+    let (cargo_cmd, priroda_flags) = match subcommand {
+        MiriCommand::Forward(s) => (s, None),
+        MiriCommand::Debug { verb, priroda_flags } => (verb, Some(priroda_flags)),
         MiriCommand::Setup => return, // `cargo miri setup` stops here.
         MiriCommand::Clean => unreachable!(),
     };
@@ -257,6 +306,11 @@ pub fn phase_cargo_miri(mut args: impl Iterator<Item = String>) {
     if verbose > 0 {
         cmd.env("MIRI_VERBOSE", verbose.to_string()); // This makes the other phases verbose.
     }
+    // This makes the runner phase start Priroda instead of Miri.
+    // We encode the flags as JSON so that they can contain spaces.
+    if let Some(priroda_flags) = priroda_flags {
+        cmd.env("MIRI_PRIRODA_FLAGS", serde_json::to_string(&priroda_flags).unwrap());
+    }
 
     // Run cargo.
     debug_cmd("[cargo-miri cargo]", verbose, &cmd);
@@ -570,7 +624,10 @@ pub fn phase_runner(mut binary_args: impl Iterator<Item = String>, phase: Runner
         show_error!("file {binary:?} contains outdated or invalid JSON; try `cargo clean`")
     };
 
-    let mut cmd = miri();
+    // `cargo miri debug` wants to debug binaries and tests. Doctests are not worth a debugger
+    // session each, so they still run in Miri.
+    let priroda_flags = env::var("MIRI_PRIRODA_FLAGS").ok().filter(|_| phase == RunnerPhase::Cargo);
+    let mut cmd = if priroda_flags.is_some() { priroda() } else { miri() };
 
     // Set missing env vars. We prefer build-time env vars over run-time ones; see
     // <https://github.com/rust-lang/miri/issues/1661> for the kind of issue that fixes.
@@ -617,7 +674,12 @@ pub fn phase_runner(mut binary_args: impl Iterator<Item = String>, phase: Runner
             cmd.arg(arg);
         }
     }
-    // Respect `MIRIFLAGS`.
+    if let Some(priroda_flags) = priroda_flags {
+        let priroda_flags: Vec<String> = serde_json::from_str(&priroda_flags)
+            .unwrap_or_else(|_| show_error!("`MIRI_PRIRODA_FLAGS` contains invalid JSON"));
+        cmd.args(priroda_flags);
+    }
+    // Respect `MIRIFLAGS`. Priroda understands the same `-Zmiri-...` flags as Miri.
     if let Ok(a) = env::var("MIRIFLAGS") {
         let args = flagsplit(&a);
         cmd.args(args);
@@ -631,6 +693,9 @@ pub fn phase_runner(mut binary_args: impl Iterator<Item = String>, phase: Runner
     // But then we need to switch to the run-time one, which we instruct Miri to do by setting `MIRI_CWD`.
     cmd.current_dir(&info.current_dir);
     cmd.env("MIRI_CWD", env::current_dir().unwrap());
+    // The program might spawn other binaries of this workspace. Those are JSON files like the one
+    // we just read, so Miri needs to know how to invoke us to interpret them.
+    cmd.env("MIRI_CARGO_MIRI", env::current_exe().unwrap());
 
     // Run it.
     debug_cmd("[cargo-miri runner]", verbose, &cmd);
@@ -65,6 +65,9 @@ pub enum MiriCommand {
     Setup,
     /// A command to be forwarded to cargo.
     Forward(String),
+    /// A `run` or `test` command to be forwarded to cargo, with the binaries started under
+    /// Priroda instead of Miri.
+    Debug { verb: String, priroda_flags: Vec<String> },
     /// Clean the miri cache
     Clean,
 }
@@ -97,6 +100,23 @@ pub fn miri() -> Command {
     cmd
 }
 
+pub fn find_priroda() -> PathBuf {
+    if let Some(path) = env::var_os("PRIRODA") {
+        return path.into();
+    }
+    // Assume it is installed next to Miri.
+    let mut path = std::env::current_exe().expect("current executable path invalid");
+    path.set_file_name(format!("priroda{}", env::consts::EXE_SUFFIX));
+    path
+}
+
+pub fn priroda() -> Command {
+    let mut cmd = Command::new(find_priroda());
+    // Priroda is a Miri driver as well, so the same applies as in `miri()`.
+    cmd.env_remove("MIRI_BE_RUSTC");
+    cmd
+}
+
 pub fn miri_for_host() -> Command {
     let mut cmd = miri();
     cmd.env("MIRI_BE_RUSTC", "host");
@@ -1,4 +1,4 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use std::ops::Range;
 use std::path::PathBuf;
 
@@ -6,12 +6,26 @@ use miri::Immediate::Uninit;
 use miri::{InterpErrorInfo, InterpErrorKind, TerminationInfo, *};
 use rustc_abi::{FIRST_VARIANT, FieldIdx, Size};
 use rustc_hir::def::CtorKind;
-use rustc_middle::mir::interpret::AllocId;
+use rustc_middle::mir::interpret::{AllocId, CheckInAllocMsg};
 use rustc_middle::mir::{self, Local, ProjectionElem, VarDebugInfoContents, VarDebugInfoFragment};
-use rustc_middle::ty::{self, TyKind};
+use rustc_middle::ty::{self, Ty, TyKind};
 use rustc_span::source_map::SourceMap;
 use rustc_span::{Span, Symbol};
 
+pub(super) use self::condition::Evaluation;
+use self::condition::{Condition, HitCondition};
+pub(super) use self::disassembly::InstructionAddress;
+pub(super) use self::memory::{AllocDesc, AllocFrame, MemoryRef};
+use self::replay::{LineEntry, ReplayAction};
+use self::watchpoint::Watchpoint;
+pub(super) use self::watchpoint::{WatchAccess, WatchRange, WatchTarget, WatchedAccess};
+
+mod condition;
+mod disassembly;
+mod memory;
+mod replay;
+mod watchpoint;
+
 /// Structured source information for frontends.
 pub(super) struct SourceLocation {
     // Keep the span so each frontend can resolve paths with its own rendering
@@ -29,16 +43,169 @@ impl SourceLocation {
 }
 
 /// Source-level breakpoints indexed by normalized path, then line.
-type BreakpointTable = HashMap<PathBuf, HashSet<usize>>;
+type BreakpointTable = HashMap<PathBuf, HashMap<usize, Breakpoint>>;
+
+/// Identifies a breakpoint for the lifetime of the session.
+pub(super) type BreakpointId = u64;
+
+/// Where a breakpoint stops.
+#[derive(Clone, PartialEq, Eq)]
+pub(super) enum BreakpointLocation {
+    /// A source line.
+    Source(PathBuf, usize),
+    /// Entry to any instance of the function with this path, or with this path
+    /// as a suffix, e.g. `parse` or `Parser::parse`.
+    Function(String),
+}
+
+/// The conditions a breakpoint checks before stopping, as entered by the user.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub(super) struct BreakpointOptions {
+    /// An expression over the frame's locals that has to hold for the breakpoint
+    /// to stop; see [`condition`] for the syntax.
+    pub(super) condition: Option<String>,
+    /// Which hits stop, e.g. `3` (from the 3rd hit on), `==3`, or `%3`. Hits
+    /// where the condition does not hold are not counted.
+    pub(super) hit_condition: Option<String>,
+}
+
+struct Breakpoint {
+    id: BreakpointId,
+    options: BreakpointOptions,
+    condition: Option<Condition>,
+    hit_condition: Option<HitCondition>,
+    /// How often the breakpoint was reached with its condition holding.
+    hits: u64,
+}
+
+impl Breakpoint {
+    fn new(id: BreakpointId, options: BreakpointOptions) -> Result<Self, String> {
+        let condition = options
+            .condition
+            .as_deref()
+            .map(Condition::parse)
+            .transpose()
+            .map_err(|err| format!("invalid condition: {err}"))?;
+        let hit_condition =
+            options.hit_condition.as_deref().map(HitCondition::parse).transpose()?;
+        Ok(Breakpoint { id, options, condition, hit_condition, hits: 0 })
+    }
+}
+
+/// Names a breakpoint in the breakpoint tables.
+enum BreakpointKey {
+    Source(PathBuf, usize),
+    /// Index into `function_breakpoints`.
+    Function(usize),
+    /// Index into `watchpoints`.
+    Watch(usize),
+}
+
+/// Identifies one interpreter frame across frontend requests.
+///
+/// `depth` counts from the innermost frame of `thread`, so depth `0` is the frame
+/// that thread is currently executing. A frame reference is only meaningful while
+/// execution is stopped.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(super) struct FrameRef {
+    pub(super) thread: ThreadId,
+    pub(super) depth: usize,
+}
+
+/// Structured stack frame information for frontends.
+pub(super) struct FrameDesc {
+    pub(super) frame: FrameRef,
+    /// Display-rendered function instance.
+    pub(super) name: String,
+    pub(super) location: Option<SourceLocation>,
+    /// Whether the frame belongs to a user-relevant crate.
+    pub(super) user_relevant: bool,
+}
+
+/// Run state of one interpreted thread.
+pub(super) enum ThreadStateDesc {
+    Enabled,
+    Blocked(BlockReason),
+    Terminated,
+}
+
+impl ThreadStateDesc {
+    pub(super) fn render(&self) -> String {
+        match self {
+            ThreadStateDesc::Enabled => "enabled".to_string(),
+            ThreadStateDesc::Blocked(reason) =>
+                format!("blocked on {}", Self::render_block_reason(reason)),
+            ThreadStateDesc::Terminated => "terminated".to_string(),
+        }
+    }
+
+    fn render_block_reason(reason: &BlockReason) -> String {
+        match reason {
+            BlockReason::Join(thread) => format!("join of thread {}", thread.to_u32()),
+            BlockReason::Sleep => "sleep".to_string(),
+            BlockReason::Mutex => "mutex".to_string(),
+            BlockReason::Condvar => "condvar".to_string(),
+            BlockReason::RwLock => "rwlock".to_string(),
+            BlockReason::Futex => "futex".to_string(),
+            BlockReason::InitOnce => "init once".to_string(),
+            BlockReason::Readiness => "readiness".to_string(),
+            BlockReason::Eventfd => "eventfd".to_string(),
+            BlockReason::TimerFd => "timerfd".to_string(),
+            BlockReason::SignalFd => "signalfd".to_string(),
+            BlockReason::Inotify => "inotify".to_string(),
+            BlockReason::VirtualSocket => "virtual socket".to_string(),
+            BlockReason::IO => "I/O".to_string(),
+            BlockReason::ChildProcess => "child process".to_string(),
+            BlockReason::Genmc => "GenMC".to_string(),
+        }
+    }
+}
+
+/// Structured thread information for frontends.
+pub(super) struct ThreadDesc {
+    pub(super) id: ThreadId,
+    pub(super) name: String,
+    pub(super) state: ThreadStateDesc,
+    /// Whether this is the thread the interpreter is currently running.
+    pub(super) active: bool,
+}
 
 /// Owns one interpreter session and its debugger state.
 ///
 /// Frontend rendering should eventually live outside this type.
 pub(super) struct PrirodaContext<'tcx> {
     pub(super) ecx: MiriInterpCx<'tcx>,
+    /// The configuration the program was started with, to start it over for replays.
+    config: MiriConfig,
     breakpoints: BreakpointTable,
+    function_breakpoints: Vec<(String, Breakpoint)>,
+    watchpoints: Vec<Watchpoint>,
+    next_breakpoint_id: BreakpointId,
+    /// Stack depth of each thread after it last took a step, used to notice
+    /// when a step pushes a frame.
+    stack_depths: HashMap<ThreadId, usize>,
+    /// Whether the last step pushed a new frame onto the active thread's stack.
+    entered_frame: bool,
     pub(super) current_location: Option<SourceLocation>,
     last_location: Option<SourceLocation>,
+    /// Thread that CLI stepping and inspection commands act on. Reset to the
+    /// active thread whenever execution stops.
+    pub(super) selected_thread: ThreadId,
+    /// Whether CLI stepping pins the scheduler to the selected thread.
+    scheduler_locking: bool,
+    /// Interpreter steps taken since the program started, which is where
+    /// [`replay`] has to run it to in order to get back here.
+    steps: u64,
+    /// The most steps the program has run for in this session. Its output up
+    /// to there has already been shown, so replays discard it.
+    max_steps: u64,
+    /// Where each thread started executing a new source line, oldest first.
+    line_entries: Vec<LineEntry>,
+    /// Debugger actions that replays repeat, with the step they were taken at.
+    replay_log: Vec<(u64, ReplayAction)>,
+    /// Bodies that instruction addresses were handed out for, in the order
+    /// they were first stopped in.
+    mir_bodies: Vec<ty::Instance<'tcx>>,
     // FIXME: add restart and other post-exit commands, similar to GDB and
     // old Priroda, instead of only replaying the saved exit code.
     exit_code: Option<i32>,
@@ -82,6 +249,13 @@ pub(super) struct LocalDesc {
 
     /// Run-time state for now; will be expanded later
     pub(super) value: String,
+
+    /// Set if the value has children that can be listed with
+    /// [`PrirodaContext::value_children`].
+    pub(super) children: Option<ValuePath>,
+
+    /// Where the value points to, if it is a pointer into a live allocation.
+    pub(super) pointee: Option<MemoryRef>,
 }
 
 impl LocalDesc {
@@ -97,10 +271,77 @@ impl LocalDesc {
     }
 }
 
+/// Where a frame variable is stored.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ValueRoot {
+    /// A whole MIR local.
+    Local(Local),
+    /// The place of the `var_debug_info` entry with this index, which projects
+    /// into a MIR local.
+    DebugInfo(usize),
+}
+
+/// One step from a value to one of its children.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ValueStep {
+    /// A field of a struct or tuple, or of the active variant of an enum.
+    Field(FieldIdx),
+    /// An element of an array or slice.
+    Index(u64),
+    /// The pointee of a reference, raw pointer, or `Box`.
+    Deref,
+    /// A shared reference to the value, which must live in memory.
+    Ref,
+}
+
+/// Identifies a value nested inside a frame variable, so that frontends can
+/// expand it on demand.
+///
+/// The path is evaluated again whenever it is expanded, so like [`FrameRef`] it
+/// is only meaningful while execution is stopped.
+#[derive(Clone, PartialEq, Eq)]
+pub(super) struct ValuePath {
+    frame: FrameRef,
+    root: ValueRoot,
+    steps: Vec<ValueStep>,
+}
+
+impl ValuePath {
+    fn child(&self, step: ValueStep) -> ValuePath {
+        let mut steps = self.steps.clone();
+        steps.push(step);
+        ValuePath { frame: self.frame, root: self.root, steps }
+    }
+}
+
+/// Structured description of one value nested inside a frame variable.
+pub(super) struct ValueDesc {
+    /// Field name, `[index]` for elements, or `*` for a pointee.
+    pub(super) name: String,
+    /// Display-rendered type of the value.
+    pub(super) ty: String,
+    pub(super) value: String,
+    /// Set if the value has children of its own.
+    pub(super) children: Option<ValuePath>,
+    /// Where the value points to, if it is a pointer into a live allocation.
+    pub(super) pointee: Option<MemoryRef>,
+}
+
+/// A value together with its expanded children, for frontends that print
+/// whole value trees instead of expanding them on demand.
+pub(super) struct ValueTree {
+    pub(super) value: ValueDesc,
+    pub(super) children: Vec<ValueTree>,
+}
+
 /// Controls when execution returns to the frontend.
+///
+/// Stepping modes carry the thread they step. Other threads may run in between,
+/// but only the stepped thread can complete the step; breakpoints still stop
+/// execution on any thread.
 enum ResumeMode {
-    /// Stop at the next visible MIR instruction.
-    MirInstruction,
+    /// Stop at the next visible MIR instruction of `thread`.
+    MirInstruction { thread: ThreadId },
     /// Step over the source position `start_position`, entered from a stack of
     /// depth `start_stack_depth`.
     ///
@@ -109,10 +350,18 @@ enum ResumeMode {
     /// at that depth or shallower and the displayed source position has changed.
     /// A `start_stack_depth` of `usize::MAX` means execution is never deeper,
     /// turning this into a plain source step that also stops inside called functions.
-    StepOver { start_position: Option<(PathBuf, usize)>, start_stack_depth: usize },
+    StepOver {
+        thread: ThreadId,
+        start_position: Option<(PathBuf, usize)>,
+        start_stack_depth: usize,
+    },
     /// Step out of the current user frame, stopping once execution returns to a
     /// shallower user-frame depth.
-    StepOut { start_position: Option<(PathBuf, usize)>, start_user_frame_depth: usize },
+    StepOut {
+        thread: ThreadId,
+        start_position: Option<(PathBuf, usize)>,
+        start_user_frame_depth: usize,
+    },
     /// Stop at the first mapped source location from a user-relevant frame.
     ///
     /// This is the DAP entry-stop primitive: it skips over interpreter startup
@@ -137,13 +386,49 @@ impl ResumeMode {
             _ => None,
         }
     }
+
+    /// The thread a stepping mode is bound to, if any.
+    fn stepped_thread(&self) -> Option<ThreadId> {
+        match self {
+            ResumeMode::MirInstruction { thread }
+            | ResumeMode::StepOver { thread, .. }
+            | ResumeMode::StepOut { thread, .. } => Some(*thread),
+            ResumeMode::FirstUserSourceLocation | ResumeMode::Continue => None,
+        }
+    }
 }
 
 /// Describes why execution stopped and returned control to the frontend.
 pub(super) enum StepResult {
     Step,
-    Breakpoint,
-    Exception { message: String },
+    Breakpoint(BreakpointHit),
+    Exception(ExceptionDesc),
+}
+
+pub(super) struct BreakpointHit {
+    pub(super) id: BreakpointId,
+    /// Set if the breakpoint's condition could not be evaluated. Such
+    /// breakpoints stop so that the error is not silently ignored.
+    pub(super) condition_error: Option<String>,
+    /// For watchpoints, the access that triggered it.
+    pub(super) access: Option<WatchedAccess>,
+}
+
+/// An interpreter error that execution stopped at. The frames are left intact,
+/// so the frontend can still inspect the state that caused it.
+pub(super) struct ExceptionDesc {
+    /// Whether Miri reports this error as Undefined Behavior, which includes
+    /// borrow-tracker violations and data races.
+    pub(super) undefined_behavior: bool,
+    pub(super) message: String,
+    /// The labels and help notes Miri would print along with `message`.
+    pub(super) notes: Vec<ExceptionNote>,
+}
+
+pub(super) struct ExceptionNote {
+    pub(super) message: String,
+    /// The related source location a note points at, if any.
+    pub(super) location: Option<SourceLocation>,
 }
 
 pub(super) enum ExecutionResult {
@@ -157,12 +442,27 @@ fn normalize_path(path: PathBuf) -> PathBuf {
 }
 
 impl<'tcx> PrirodaContext<'tcx> {
-    pub(super) fn new(ecx: MiriInterpCx<'tcx>) -> Self {
+    pub(super) fn new(mut ecx: MiriInterpCx<'tcx>, config: MiriConfig) -> Self {
+        // Replays use this to find watched allocations again.
+        ecx.machine.local_alloc_ids = Some(Default::default());
         Self {
             ecx,
+            config,
             breakpoints: HashMap::new(),
+            function_breakpoints: Vec::new(),
+            watchpoints: Vec::new(),
+            next_breakpoint_id: 1,
+            stack_depths: HashMap::new(),
+            entered_frame: false,
             current_location: None,
             last_location: None,
+            selected_thread: ThreadId::MAIN_THREAD,
+            scheduler_locking: false,
+            steps: 0,
+            max_steps: 0,
+            line_entries: Vec::new(),
+            replay_log: Vec::new(),
+            mir_bodies: Vec::new(),
             exit_code: None,
         }
     }
@@ -177,6 +477,21 @@ impl<'tcx> PrirodaContext<'tcx> {
         Some((self.local_path(location)?, location.line))
     }
 
+    /// Source position of `thread`, which need not be the active thread.
+    fn thread_source_position(&self, thread: ThreadId) -> Option<(PathBuf, usize)> {
+        if thread == self.ecx.active_thread() {
+            return self.current_source_position();
+        }
+        let span = self.ecx.thread_manager().thread_ref(thread).current_user_relevant_span();
+        let location = self.resolve_location(span)?;
+        Some((self.local_path(&location)?, location.line))
+    }
+
+    /// The thread to pin while stepping `thread`, if CLI scheduler locking is enabled.
+    fn cli_pinned_thread(&self, thread: ThreadId) -> Option<ThreadId> {
+        self.scheduler_locking.then_some(thread)
+    }
+
     // Used to treat `continue` like a source-level step for breakpoint checks:
     // several MIR locations can point at one source line, but they should only
     // report that source breakpoint once.
@@ -189,12 +504,23 @@ impl<'tcx> PrirodaContext<'tcx> {
         self.exit_code.map(|code| ExecutionResult::ProgramExited { code })
     }
 
-    /// Step to the next visible MIR instruction.
+    /// Step to the next visible MIR instruction of the selected thread.
     fn stepi(&mut self) -> InterpResult<'tcx, ExecutionResult> {
+        let thread = self.selected_thread;
+        self.step_instruction(thread, self.scheduler_locking)
+    }
+
+    /// Step `thread` to its next visible MIR instruction. With
+    /// `single_thread`, the scheduler is pinned to `thread` for the step.
+    pub(super) fn step_instruction(
+        &mut self,
+        thread: ThreadId,
+        single_thread: bool,
+    ) -> InterpResult<'tcx, ExecutionResult> {
         if let Some(result) = self.already_finished() {
             return interp_ok(result);
         }
-        self.resume(ResumeMode::MirInstruction)
+        self.resume(ResumeMode::MirInstruction { thread }, single_thread.then_some(thread))
     }
 
     /// Step until the displayed source file or line changes.
@@ -202,35 +528,53 @@ impl<'tcx> PrirodaContext<'tcx> {
     /// This is the CLI source-level step; it shares its stepping semantics with
     /// [`Self::step_in_source`].
     pub(super) fn step(&mut self) -> InterpResult<'tcx, ExecutionResult> {
-        self.step_in_source()
+        let thread = self.selected_thread;
+        self.step_in_source(thread, self.scheduler_locking)
     }
 
-    /// Step into the next source location.
+    /// Step `thread` into the next source location.
     ///
     /// This can enter calls that have a distinct displayed source position,
-    /// while `next` uses [`Self::step_over_source`].
-    pub(super) fn step_in_source(&mut self) -> InterpResult<'tcx, ExecutionResult> {
+    /// while `next` uses [`Self::step_over_source`]. With `single_thread`, the
+    /// scheduler is pinned to `thread` for the duration of the step.
+    pub(super) fn step_in_source(
+        &mut self,
+        thread: ThreadId,
+        single_thread: bool,
+    ) -> InterpResult<'tcx, ExecutionResult> {
         if let Some(result) = self.already_finished() {
             return interp_ok(result);
         }
-        self.resume(ResumeMode::StepOver {
-            start_position: self.current_source_position(),
-            start_stack_depth: usize::MAX,
-        })
+        self.resume(
+            ResumeMode::StepOver {
+                thread,
+                start_position: self.thread_source_position(thread),
+                start_stack_depth: usize::MAX,
+            },
+            single_thread.then_some(thread),
+        )
     }
 
-    /// Step over the current source position, not stopping inside any call it makes.
+    /// Step `thread` over its current source position, not stopping inside any
+    /// call it makes.
     ///
     /// Records the current source position and stack depth before advancing,
     /// then keeps stepping until execution is back at that depth (or shallower)
     /// and the displayed source position has changed.
-    pub(super) fn step_over_source(&mut self) -> InterpResult<'tcx, ExecutionResult> {
+    pub(super) fn step_over_source(
+        &mut self,
+        thread: ThreadId,
+        single_thread: bool,
+    ) -> InterpResult<'tcx, ExecutionResult> {
         if let Some(result) = self.already_finished() {
             return interp_ok(result);
         }
-        let start_position = self.current_source_position();
-        let start_stack_depth = self.active_thread_stack_depth();
-        self.resume(ResumeMode::StepOver { start_position, start_stack_depth })
+        let start_position = self.thread_source_position(thread);
+        let start_stack_depth = self.thread_stack_depth(thread);
+        self.resume(
+            ResumeMode::StepOver { thread, start_position, start_stack_depth },
+            single_thread.then_some(thread),
+        )
     }
 
     /// Number of frames on the active thread's stack.
@@ -238,60 +582,237 @@ impl<'tcx> PrirodaContext<'tcx> {
         self.ecx.active_thread_stack().len()
     }
 
-    /// Step out of the current user frame.
+    /// Number of frames on the stack of `thread`.
+    fn thread_stack_depth(&self, thread: ThreadId) -> usize {
+        self.ecx.thread_manager().thread_ref(thread).stack().len()
+    }
+
+    /// Step `thread` out of its current user frame.
     ///
     /// Records the current user-frame depth and runs until execution reaches a
     /// source location in a shallower user frame.
-    pub(super) fn step_out_source(&mut self) -> InterpResult<'tcx, ExecutionResult> {
+    pub(super) fn step_out_source(
+        &mut self,
+        thread: ThreadId,
+        single_thread: bool,
+    ) -> InterpResult<'tcx, ExecutionResult> {
         if let Some(result) = self.already_finished() {
             return interp_ok(result);
         }
-        let start_user_frame_depth = self.active_user_frame_depth();
+        let start_user_frame_depth = self.user_frame_depth(thread);
         if start_user_frame_depth <= 1 {
             return interp_ok(ExecutionResult::Rejected {
                 message: "stepOut is not meaningful in the outermost user frame",
             });
         }
-        let start_position = self.current_source_position();
-        self.resume(ResumeMode::StepOut { start_position, start_user_frame_depth })
+        let start_position = self.thread_source_position(thread);
+        self.resume(
+            ResumeMode::StepOut { thread, start_position, start_user_frame_depth },
+            single_thread.then_some(thread),
+        )
     }
 
     /// Run until the initial editor-visible stop point.
     pub(super) fn stop_at_first_user_location(&mut self) -> InterpResult<'tcx, ExecutionResult> {
-        self.resume(ResumeMode::FirstUserSourceLocation)
-    }
-
-    /// Return the active frame name while DAP still reports only one frame.
-    pub(super) fn current_frame_name(&self) -> Option<String> {
-        let frame = self.ecx.active_thread_stack().last()?;
-        Some(frame.instance().to_string())
+        self.resume(ResumeMode::FirstUserSourceLocation, None)
     }
 
     /// Continue execution until reaching a breakpoint or propagating termination.
-    pub(super) fn continue_execution(&mut self) -> InterpResult<'tcx, ExecutionResult> {
+    ///
+    /// With `pinned_thread`, the scheduler keeps running that thread for as long
+    /// as it is enabled.
+    pub(super) fn continue_execution(
+        &mut self,
+        pinned_thread: Option<ThreadId>,
+    ) -> InterpResult<'tcx, ExecutionResult> {
         if let Some(result) = self.already_finished() {
             return interp_ok(result);
         }
-        self.resume(ResumeMode::Continue)
+        self.resume(ResumeMode::Continue, pinned_thread)
+    }
+
+    /// Look up a thread that has not terminated by its numeric id.
+    pub(super) fn live_thread(&self, thread: u32) -> Option<ThreadId> {
+        self.ecx.thread_manager().thread_id_try_from(thread).ok()
+    }
+
+    /// Whether `thread` names a thread that has not terminated.
+    fn is_live_thread(&self, thread: ThreadId) -> bool {
+        self.live_thread(thread.to_u32()).is_some()
+    }
+
+    /// Describe every thread the interpreted program has created, including
+    /// terminated ones.
+    pub(super) fn list_threads(&self) -> Vec<ThreadDesc> {
+        let threads = self.ecx.thread_manager();
+        threads
+            .all_threads()
+            .map(|(id, thread)| {
+                let state = if thread.is_terminated() {
+                    ThreadStateDesc::Terminated
+                } else if let Some(reason) = thread.block_reason() {
+                    ThreadStateDesc::Blocked(reason)
+                } else {
+                    ThreadStateDesc::Enabled
+                };
+                ThreadDesc {
+                    id,
+                    name: threads.get_thread_display_name(id),
+                    state,
+                    active: id == threads.active_thread(),
+                }
+            })
+            .collect()
+    }
+
+    /// Select the thread that CLI stepping and inspection commands act on.
+    fn select_thread(&mut self, thread: u32) -> Option<ThreadDesc> {
+        let thread = self.live_thread(thread)?;
+        self.selected_thread = thread;
+        self.list_threads().into_iter().find(|desc| desc.id == thread)
+    }
+
+    /// Describe the frames of `thread`, innermost first.
+    ///
+    /// Like Miri's short backtraces, frames below the outermost user-relevant
+    /// frame (the Rust runtime that calls `main` or a thread's closure) are left
+    /// out. If the thread has no user-relevant frame, all frames are kept.
+    pub(super) fn stack_frames(&self, thread: ThreadId) -> Vec<FrameDesc> {
+        let stack = self.ecx.thread_manager().thread_ref(thread).stack();
+        let outermost =
+            stack.iter().position(|frame| frame.extra.user_relevance == u8::MAX).unwrap_or(0);
+        stack[outermost..]
+            .iter()
+            .rev()
+            .enumerate()
+            .map(|(depth, frame)| {
+                FrameDesc {
+                    frame: FrameRef { thread, depth },
+                    name: frame.instance().to_string(),
+                    location: self.resolve_location(frame.current_span()),
+                    user_relevant: frame.extra.user_relevance == u8::MAX,
+                }
+            })
+            .collect()
+    }
+
+    /// Whether `frame` is one of the frames reported by [`Self::stack_frames`].
+    pub(super) fn is_visible_frame(&self, frame: FrameRef) -> bool {
+        self.is_live_thread(frame.thread) && frame.depth < self.stack_frames(frame.thread).len()
+    }
+
+    /// The innermost frame of the selected thread.
+    fn selected_frame(&self) -> FrameRef {
+        FrameRef { thread: self.selected_thread, depth: 0 }
+    }
+
+    fn frame(&self, frame: FrameRef) -> Option<&Frame<'tcx, Provenance, FrameExtra<'tcx>>> {
+        let stack = self.ecx.thread_manager().thread_ref(frame.thread).stack();
+        let idx = stack.len().checked_sub(frame.depth.checked_add(1)?)?;
+        stack.get(idx)
     }
 
     pub(super) fn finish_session(&mut self) -> InterpResult<'tcx, ()> {
         interp_ok(())
     }
 
-    pub(super) fn set_breakpoint(&mut self, path: PathBuf, line: usize) -> BreakpointSetResult {
+    /// Add a breakpoint, or replace the options of the one already at `location`.
+    pub(super) fn set_breakpoint(
+        &mut self,
+        location: BreakpointLocation,
+        options: BreakpointOptions,
+    ) -> BreakpointSetResult {
         // FIXME: validate breakpoints here so every frontend gets the same behavior.
         // Reject empty paths, missing files, directories, and line 0. Decide whether
         // out-of-range lines should be rejected or kept as pending breakpoints.
-        // Report duplicate registrations separately.
 
-        let path = normalize_path(path);
-        match self.breakpoints.entry(path.clone()).or_default().insert(line) {
-            true => BreakpointSetResult::Added(path, line),
-            false => BreakpointSetResult::Duplicate,
+        let location = match location {
+            BreakpointLocation::Source(path, line) =>
+                BreakpointLocation::Source(normalize_path(path), line),
+            location @ BreakpointLocation::Function(_) => location,
+        };
+        let existing = match &location {
+            BreakpointLocation::Source(path, line) =>
+                self.breakpoints.get(path).and_then(|lines| lines.get(line)),
+            BreakpointLocation::Function(name) =>
+                self.function_breakpoints
+                    .iter()
+                    .find(|(function, _)| function == name)
+                    .map(|(_, breakpoint)| breakpoint),
+        };
+        let id = match existing {
+            Some(breakpoint) if breakpoint.options == options =>
+                return BreakpointSetResult::Duplicate,
+            Some(breakpoint) => Some(breakpoint.id),
+            None => None,
+        };
+        let breakpoint = match Breakpoint::new(id.unwrap_or(self.next_breakpoint_id), options) {
+            Ok(breakpoint) => breakpoint,
+            Err(message) => return BreakpointSetResult::Invalid(message),
+        };
+        if id.is_none() {
+            self.next_breakpoint_id += 1;
+        }
+
+        match &location {
+            BreakpointLocation::Source(path, line) => {
+                self.breakpoints.entry(path.clone()).or_default().insert(*line, breakpoint);
+            }
+            BreakpointLocation::Function(name) => {
+                self.function_breakpoints.retain(|(function, _)| function != name);
+                self.function_breakpoints.push((name.clone(), breakpoint));
+            }
+        }
+        match id {
+            Some(_) => BreakpointSetResult::Updated(location),
+            None => BreakpointSetResult::Added(location),
         }
     }
 
+    /// Replace all breakpoints in `path` with the given ones, returning the id
+    /// of each new breakpoint or why it could not be set.
+    pub(super) fn set_source_breakpoints(
+        &mut self,
+        path: PathBuf,
+        breakpoints: Vec<(usize, BreakpointOptions)>,
+    ) -> Vec<Result<BreakpointId, String>> {
+        let path = normalize_path(path);
+        self.breakpoints.remove(&path);
+        breakpoints
+            .into_iter()
+            .map(|(line, options)| {
+                let breakpoint = self.new_breakpoint(options)?;
+                let id = breakpoint.id;
+                self.breakpoints.entry(path.clone()).or_default().insert(line, breakpoint);
+                Ok(id)
+            })
+            .collect()
+    }
+
+    /// Replace all function breakpoints with the given ones, returning the id
+    /// of each new breakpoint or why it could not be set.
+    pub(super) fn set_function_breakpoints(
+        &mut self,
+        breakpoints: Vec<(String, BreakpointOptions)>,
+    ) -> Vec<Result<BreakpointId, String>> {
+        self.function_breakpoints.clear();
+        breakpoints
+            .into_iter()
+            .map(|(name, options)| {
+                let breakpoint = self.new_breakpoint(options)?;
+                let id = breakpoint.id;
+                self.function_breakpoints.push((name, breakpoint));
+                Ok(id)
+            })
+            .collect()
+    }
+
+    fn new_breakpoint(&mut self, options: BreakpointOptions) -> Result<Breakpoint, String> {
+        let breakpoint = Breakpoint::new(self.next_breakpoint_id, options)?;
+        self.next_breakpoint_id += 1;
+        Ok(breakpoint)
+    }
+
     fn program_exit(err: &InterpErrorInfo<'tcx>) -> Option<i32> {
         let InterpErrorKind::MachineStop(info) = err.kind() else {
             return None;
@@ -306,34 +827,89 @@ impl<'tcx> PrirodaContext<'tcx> {
         Some(*code)
     }
 
+    /// Program exits are not debugger exceptions. Preserve all other
+    /// interpreter errors as stopped debugger events.
+    fn stop_at_error(&mut self, err: InterpErrorInfo<'tcx>) -> ExecutionResult {
+        if let Some(code) = Self::program_exit(&err) {
+            self.exit_code = Some(code);
+            return ExecutionResult::ProgramExited { code };
+        }
+        ExecutionResult::Stopped(self.stop_at_exception(err))
+    }
+
     fn stop_at_exception(&mut self, err: InterpErrorInfo<'tcx>) -> StepResult {
-        let message = err.kind().to_string();
+        let exception = match miri::undefined_behavior_diagnostic(&self.ecx, &err) {
+            Some(diagnostic) => {
+                let labels = diagnostic
+                    .labels
+                    .into_iter()
+                    .map(|message| ExceptionNote { message, location: None });
+                let helps = diagnostic.helps.into_iter().map(|(span, message)| {
+                    ExceptionNote {
+                        message,
+                        location: span.and_then(|span| self.resolve_location(span.span())),
+                    }
+                });
+                ExceptionDesc {
+                    undefined_behavior: true,
+                    message: diagnostic.message,
+                    notes: labels.chain(helps).collect(),
+                }
+            }
+            None =>
+                ExceptionDesc {
+                    undefined_behavior: false,
+                    message: err.kind().to_string(),
+                    notes: Vec::new(),
+                },
+        };
         self.last_location = self.current_location.take();
         self.current_location = self.resolve_current_location();
-        StepResult::Exception { message }
+        StepResult::Exception(exception)
     }
 
-    /// Advance execution until the selected resume mode reaches a stopping point.
-    fn resume(&mut self, mode: ResumeMode) -> InterpResult<'tcx, ExecutionResult> {
+    /// Advance execution until the selected resume mode reaches a stopping point,
+    /// with the scheduler pinned to `pinned_thread` in the meantime.
+    fn resume(
+        &mut self,
+        mode: ResumeMode,
+        pinned_thread: Option<ThreadId>,
+    ) -> InterpResult<'tcx, ExecutionResult> {
+        if pinned_thread.is_some() {
+            self.log_replay_action(ReplayAction::PinThread(pinned_thread));
+        }
+        self.ecx.set_pinned_thread(pinned_thread);
+        let result = self.resume_unpinned(mode);
+        self.ecx.set_pinned_thread(None);
+        if pinned_thread.is_some() {
+            self.log_replay_action(ReplayAction::PinThread(None));
+        }
+        // Inspection commands follow the thread that execution stopped on.
+        self.selected_thread = self.ecx.active_thread();
+        result
+    }
+
+    fn resume_unpinned(&mut self, mode: ResumeMode) -> InterpResult<'tcx, ExecutionResult> {
         loop {
-            // Program exits are not debugger exceptions. Preserve all other
-            // interpreter errors as stopped debugger events.
             if let Err(err) = self.advance().report_err() {
-                if let Some(code) = Self::program_exit(&err) {
-                    self.exit_code = Some(code);
-                    return interp_ok(ExecutionResult::ProgramExited { code });
-                }
-                return interp_ok(ExecutionResult::Stopped(self.stop_at_exception(err)));
+                return interp_ok(self.stop_at_error(err));
             }
 
             // An explicit breakpoint should stop execution even when the current
             // MIR instruction would normally be hidden during manual stepping.
-            if self.is_at_breakpoint(mode.skipped_breakpoint()) {
-                return interp_ok(ExecutionResult::Stopped(StepResult::Breakpoint));
+            if let Some(hit) =
+                self.watchpoint_hit().or_else(|| self.breakpoint_hit(mode.skipped_breakpoint()))
+            {
+                return interp_ok(ExecutionResult::Stopped(StepResult::Breakpoint(hit)));
+            }
+
+            // Only the stepped thread can complete a step.
+            if mode.stepped_thread().is_some_and(|thread| thread != self.ecx.active_thread()) {
+                continue;
             }
 
             match mode {
-                ResumeMode::MirInstruction
+                ResumeMode::MirInstruction { .. }
                     if matches!(
                         self.current_instruction_visibility(),
                         InstructionVisibility::Visible
@@ -342,7 +918,7 @@ impl<'tcx> PrirodaContext<'tcx> {
                     return interp_ok(ExecutionResult::Stopped(StepResult::Step));
                 }
 
-                ResumeMode::StepOver { ref start_position, start_stack_depth } => {
+                ResumeMode::StepOver { ref start_position, start_stack_depth, .. } => {
                     // While deeper than where we started, we are inside a call
                     // made from the stepped-over line; keep going.
                     if self.active_thread_stack_depth() > start_stack_depth {
@@ -378,8 +954,8 @@ impl<'tcx> PrirodaContext<'tcx> {
                     }
                 }
 
-                ResumeMode::StepOut { start_user_frame_depth, .. }
-                    if self.active_user_frame_depth() < start_user_frame_depth
+                ResumeMode::StepOut { thread, start_user_frame_depth, .. }
+                    if self.user_frame_depth(thread) < start_user_frame_depth
                         && self.current_location.is_some() =>
                 {
                     return interp_ok(ExecutionResult::Stopped(StepResult::Step));
@@ -391,7 +967,7 @@ impl<'tcx> PrirodaContext<'tcx> {
                     return interp_ok(ExecutionResult::Stopped(StepResult::Step));
                 }
 
-                ResumeMode::MirInstruction
+                ResumeMode::MirInstruction { .. }
                 | ResumeMode::FirstUserSourceLocation
                 | ResumeMode::StepOut { .. }
                 | ResumeMode::Continue => {}
@@ -400,29 +976,42 @@ impl<'tcx> PrirodaContext<'tcx> {
     }
 
     fn has_user_relevant_frame(&self) -> bool {
-        self.active_user_frame_depth() > 0
+        self.user_frame_depth(self.ecx.active_thread()) > 0
     }
 
-    fn active_user_frame_depth(&self) -> usize {
+    fn user_frame_depth(&self, thread: ThreadId) -> usize {
         // Walk the whole stack, not just the top frame: during interpreter
         // startup the user's `main` can sit under Miri-internal frames that
         // have no source span, so checking only `last()` would miss it.
         self.ecx
-            .active_thread_stack()
+            .thread_manager()
+            .thread_ref(thread)
+            .stack()
             .iter()
             .filter(|frame| frame.extra.user_relevance == u8::MAX)
             .count()
     }
 
     /// Advance Miri by one interpreter-loop transition.
+    ///
+    /// This goes through Miri's scheduler, so the step may happen on a different
+    /// thread than the previous one.
     fn advance(&mut self) -> InterpResult<'tcx> {
-        // FIXME: use a Miri-owned scheduler-aware debugger step API before
-        // claiming support for multi-threaded interpreted programs.
-
+        // Only accesses made by this step may trigger watchpoints.
+        self.ecx.machine.watched_accesses.get_mut().clear();
+        // Output up to `max_steps` was already shown before going back.
+        self.ecx.machine.discard_stdout_stderr = self.steps < self.max_steps;
+        self.steps += 1;
+        self.max_steps = self.max_steps.max(self.steps);
         // State inspection should happen only after a successful step.
-        self.ecx.step_current_thread()?;
+        self.ecx.step_scheduled_thread()?;
+        let depth = self.active_thread_stack_depth();
+        let previous_depth = self.stack_depths.insert(self.ecx.active_thread(), depth);
+        self.entered_frame = previous_depth.is_some_and(|previous_depth| depth > previous_depth);
         self.last_location = self.current_location.take();
         self.current_location = self.resolve_current_location();
+        self.record_line_entry();
+        self.resolve_watched_allocs();
         interp_ok(())
     }
 
@@ -454,27 +1043,119 @@ impl<'tcx> PrirodaContext<'tcx> {
         }
     }
 
-    fn is_at_breakpoint(&self, skipped_breakpoint: Option<&(PathBuf, usize)>) -> bool {
-        let Some(bp) = self.current_breakpoint() else {
-            return false;
-        };
+    /// Check the breakpoints reached by the last step, and return the one that
+    /// stops execution, if any. Every breakpoint whose condition holds counts
+    /// a hit, even if its hit condition then lets execution go on.
+    fn breakpoint_hit(
+        &mut self,
+        skipped_breakpoint: Option<&(PathBuf, usize)>,
+    ) -> Option<BreakpointHit> {
+        let thread = self.ecx.active_thread();
+        if self.entered_frame {
+            // The conditions of function breakpoints see the new frame's arguments.
+            let frame = FrameRef { thread, depth: 0 };
+            for idx in self.entered_function_breakpoints() {
+                if let Some(hit) = self.check_breakpoint(BreakpointKey::Function(idx), frame) {
+                    return Some(hit);
+                }
+            }
+        }
+
+        let (path, line) = self.reached_source_breakpoint(skipped_breakpoint)?;
+        self.check_breakpoint(BreakpointKey::Source(path, line), self.condition_frame(thread))
+    }
+
+    /// The frame whose locals the conditions of source breakpoints and
+    /// watchpoints reached on `thread` see: the innermost user-relevant frame,
+    /// which is where the displayed line belongs.
+    fn condition_frame(&self, thread: ThreadId) -> FrameRef {
+        let thread_ref = self.ecx.thread_manager().thread_ref(thread);
+        let depth = thread_ref
+            .top_user_relevant_frame()
+            .map_or(0, |frame_idx| thread_ref.stack().len() - 1 - frame_idx);
+        FrameRef { thread, depth }
+    }
+
+    fn reached_source_breakpoint(
+        &self,
+        skipped_breakpoint: Option<&(PathBuf, usize)>,
+    ) -> Option<(PathBuf, usize)> {
+        let bp = self.current_breakpoint()?;
         if skipped_breakpoint == Some(&bp) {
-            return false;
+            return None;
         }
 
         // If the previous interpreter step had the same source position, this
         // is another MIR location for the breakpoint we just reported.
-        self.last_source_position().as_ref() != Some(&bp)
+        if self.last_source_position().as_ref() == Some(&bp) {
+            return None;
+        }
+        Some(bp)
     }
 
     fn current_breakpoint(&self) -> Option<(PathBuf, usize)> {
         let (path, line) = self.current_source_position()?;
         let lines = self.breakpoints.get(&path)?;
-        if lines.contains(&line) { Some((path, line)) } else { None }
+        if lines.contains_key(&line) { Some((path, line)) } else { None }
+    }
+
+    /// Indices of the function breakpoints that match the function whose frame
+    /// the last step pushed.
+    fn entered_function_breakpoints(&self) -> Vec<usize> {
+        let Some(frame) = self.ecx.active_thread_stack().last() else {
+            return Vec::new();
+        };
+        let instance = frame.instance();
+        // Shims forward to the function item, which stops on its own.
+        if !matches!(instance.def, ty::InstanceKind::Item(_)) {
+            return Vec::new();
+        }
+        let path = self.ecx.tcx.def_path_str(instance.def_id());
+        self.function_breakpoints
+            .iter()
+            .enumerate()
+            .filter(|(_, (name, _))| {
+                path.strip_suffix(name.as_str())
+                    .is_some_and(|prefix| prefix.is_empty() || prefix.ends_with("::"))
+            })
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+
+    /// Count a hit of the breakpoint `key` reached in `frame` if its condition
+    /// holds, and decide whether it stops.
+    fn check_breakpoint(&mut self, key: BreakpointKey, frame: FrameRef) -> Option<BreakpointHit> {
+        let condition = match &key {
+            BreakpointKey::Source(path, line) => &self.breakpoints[path][line].condition,
+            BreakpointKey::Function(idx) => &self.function_breakpoints[*idx].1.condition,
+            BreakpointKey::Watch(idx) => &self.watchpoints[*idx].breakpoint.condition,
+        };
+        let condition_error = match condition.as_ref().map(|c| self.eval_condition(c, frame)) {
+            None | Some(Ok(true)) => None,
+            Some(Ok(false)) => return None,
+            Some(Err(err)) => Some(err),
+        };
+
+        let breakpoint = match key {
+            BreakpointKey::Source(path, line) =>
+                self.breakpoints.get_mut(&path).and_then(|lines| lines.get_mut(&line)).unwrap(),
+            BreakpointKey::Function(idx) => &mut self.function_breakpoints[idx].1,
+            BreakpointKey::Watch(idx) => &mut self.watchpoints[idx].breakpoint,
+        };
+        breakpoint.hits += 1;
+        if condition_error.is_none()
+            && breakpoint.hit_condition.is_some_and(|hit| !hit.should_stop(breakpoint.hits))
+        {
+            return None;
+        }
+        Some(BreakpointHit { id: breakpoint.id, condition_error, access: None })
     }
 
     fn resolve_current_location(&self) -> Option<SourceLocation> {
-        let span = self.ecx.machine.current_user_relevant_span();
+        self.resolve_location(self.ecx.machine.current_user_relevant_span())
+    }
+
+    fn resolve_location(&self, span: Span) -> Option<SourceLocation> {
         if span.is_dummy() {
             return None;
         }
@@ -493,16 +1174,46 @@ impl<'tcx> PrirodaContext<'tcx> {
         match command {
             DebuggerCommand::StepI => self.stepi().map(CommandResult::Execution),
             DebuggerCommand::Step => self.step().map(CommandResult::Execution),
-            DebuggerCommand::Next => self.step_over_source().map(CommandResult::Execution),
-            DebuggerCommand::StepOut => self.step_out_source().map(CommandResult::Execution),
-            DebuggerCommand::Continue => self.continue_execution().map(CommandResult::Execution),
-            DebuggerCommand::Breakpoint(path, line) =>
-                interp_ok(CommandResult::BreakpointResult(self.set_breakpoint(path, line))),
-            DebuggerCommand::ListLocals => interp_ok(CommandResult::Locals(self.list_locals())),
+            DebuggerCommand::Next =>
+                self.step_over_source(self.selected_thread, self.scheduler_locking)
+                    .map(CommandResult::Execution),
+            DebuggerCommand::StepOut =>
+                self.step_out_source(self.selected_thread, self.scheduler_locking)
+                    .map(CommandResult::Execution),
+            DebuggerCommand::Continue =>
+                self.continue_execution(self.cli_pinned_thread(self.selected_thread))
+                    .map(CommandResult::Execution),
+            DebuggerCommand::ReverseStep =>
+                self.step_back(self.selected_thread).map(CommandResult::Execution),
+            DebuggerCommand::ReverseContinue =>
+                self.reverse_continue().map(CommandResult::Execution),
+            DebuggerCommand::Breakpoint(location, options) =>
+                interp_ok(CommandResult::BreakpointResult(self.set_breakpoint(location, options))),
+            DebuggerCommand::Watch(target, access, options) => {
+                let result = self
+                    .watch_range(target)
+                    .and_then(|range| self.add_watchpoint(range, access, options).map(|_id| range));
+                interp_ok(CommandResult::WatchpointResult(result))
+            }
+            DebuggerCommand::ListLocals =>
+                interp_ok(CommandResult::Locals(self.list_locals(self.selected_frame()))),
             DebuggerCommand::Print(local) =>
-                interp_ok(CommandResult::SingleLocal(self.get_local(local))),
+                interp_ok(CommandResult::SingleLocal(self.print_local(local))),
             DebuggerCommand::Follow(alloc_id, offset) =>
                 self.follow_alloc(alloc_id, offset).map(CommandResult::Memory),
+            DebuggerCommand::Allocations =>
+                interp_ok(CommandResult::Allocations(self.live_allocations())),
+            DebuggerCommand::Evaluate(expr) =>
+                interp_ok(CommandResult::Evaluation(self.evaluate(&expr, self.selected_frame()))),
+            DebuggerCommand::Threads => interp_ok(CommandResult::Threads(self.list_threads())),
+            DebuggerCommand::SelectThread(thread) =>
+                interp_ok(CommandResult::ThreadSelected(self.select_thread(thread))),
+            DebuggerCommand::Backtrace =>
+                interp_ok(CommandResult::Backtrace(self.stack_frames(self.selected_thread))),
+            DebuggerCommand::SchedulerLocking(enabled) => {
+                self.scheduler_locking = enabled;
+                interp_ok(CommandResult::SchedulerLocking(enabled))
+            }
             DebuggerCommand::TerminateSession =>
                 self.finish_session().map(|()| CommandResult::TerminateSession),
         }
@@ -521,22 +1232,298 @@ impl<'tcx> PrirodaContext<'tcx> {
         interp_ok(format!("Allocation {alloc_id}+{offset}: {memory}"))
     }
 
-    fn get_local(&self, local: usize) -> Option<LocalDesc> {
-        let frame = self.ecx.active_thread_stack().last()?;
-
-        self.make_mir_local_desc(frame, local)
+    /// Describe one MIR local of the selected frame, with its children expanded
+    /// a few levels deep.
+    fn print_local(&self, local: usize) -> Option<(LocalDesc, Vec<ValueTree>)> {
+        /// How many levels of children `print` shows below the local itself.
+        const PRINT_TREE_DEPTH: usize = 3;
+
+        let frame_ref = self.selected_frame();
+        let frame = self.frame(frame_ref)?;
+        self.ecx.run_for_validation_ref(|_| {
+            let local_desc = self.make_mir_local_desc(frame_ref, frame, local)?;
+            let children = local_desc
+                .children
+                .as_ref()
+                .map(|path| self.value_tree(path, PRINT_TREE_DEPTH))
+                .unwrap_or_default();
+            Some((local_desc, children))
+        })
     }
 
-    /// Returns structured descriptions for locals in the innermost stack frame.
+    /// Returns structured descriptions for locals in the given stack frame.
     ///
     /// Starts from all MIR locals, then enriches them with source names from
     /// `var_debug_info` when a debug entry maps directly to a whole local.
-    pub(super) fn list_locals(&self) -> Vec<LocalDesc> {
-        let Some(frame) = self.ecx.active_thread_stack().last() else {
+    pub(super) fn list_locals(&self, frame_ref: FrameRef) -> Vec<LocalDesc> {
+        let Some(frame) = self.frame(frame_ref) else {
             return Vec::new();
         };
 
-        self.build_local_descs(frame)
+        // The debugger's own reads must not show up as program accesses in the
+        // borrow tracker or the data race detector.
+        self.ecx.run_for_validation_ref(|_| self.build_local_descs(frame_ref, frame))
+    }
+
+    /// Lists the children of the value at `path`: fields, elements, or the
+    /// pointee. Returns nothing if `path` no longer evaluates to a value.
+    pub(super) fn value_children(&self, path: &ValuePath) -> Vec<ValueDesc> {
+        self.ecx.run_for_validation_ref(|_| self.read_value_children(path))
+    }
+
+    /// Like [`Self::value_children`], but also expands the children up to
+    /// `depth` levels below `path`. Memory hooks must already be suppressed.
+    fn value_tree(&self, path: &ValuePath, depth: usize) -> Vec<ValueTree> {
+        if depth == 0 {
+            return Vec::new();
+        }
+        self.read_value_children(path)
+            .into_iter()
+            .map(|value| {
+                let children = value
+                    .children
+                    .as_ref()
+                    .map(|path| self.value_tree(path, depth - 1))
+                    .unwrap_or_default();
+                ValueTree { value, children }
+            })
+            .collect()
+    }
+
+    fn read_value_children(&self, path: &ValuePath) -> Vec<ValueDesc> {
+        self.value_path_op(path)
+            .and_then(|op| self.value_children_of(&op, path))
+            .discard_err()
+            .unwrap_or_default()
+    }
+
+    /// Evaluate `path` against the current interpreter state.
+    fn value_path_op(&self, path: &ValuePath) -> InterpResult<'tcx, OpTy<'tcx>> {
+        let Some(frame) = self.frame(path.frame) else {
+            return Err(miri::err_unsup_format!("stack frame is no longer live")).into();
+        };
+        let mut op = match path.root {
+            ValueRoot::Local(local) => self.ecx.local_at_frame_to_op(frame, local, None)?,
+            ValueRoot::DebugInfo(idx) => {
+                let VarDebugInfoContents::Place(place) = frame.body().var_debug_info[idx].value
+                else {
+                    bug!("value paths are only created for debug info places");
+                };
+                self.place_to_op_in_frame(frame, place)?
+            }
+        };
+        for step in &path.steps {
+            op = match *step {
+                ValueStep::Field(field_idx) => self.project_value_field(&op, field_idx)?,
+                ValueStep::Index(idx) => self.ecx.project_index(&op, idx)?,
+                ValueStep::Deref => self.deref_value(&op)?.into(),
+                ValueStep::Ref => self.ref_to_value(&op)?,
+            };
+        }
+        interp_ok(op)
+    }
+
+    /// Project to a field of a struct or tuple, or of the active variant of an enum.
+    fn project_value_field(
+        &self,
+        op: &OpTy<'tcx>,
+        field_idx: FieldIdx,
+    ) -> InterpResult<'tcx, OpTy<'tcx>> {
+        let base = match op.layout.ty.kind() {
+            ty::Adt(def, _) if def.is_enum() => {
+                let variant_idx = self.ecx.read_discriminant(op)?;
+                self.ecx.project_downcast(op, variant_idx)?
+            }
+            _ => op.clone(),
+        };
+        self.ecx.project_field(&base, field_idx)
+    }
+
+    /// Find the field named `name` of a struct, of the active variant of an
+    /// enum, or, by index, of a tuple.
+    fn field_by_name(&self, op: &OpTy<'tcx>, name: &str) -> InterpResult<'tcx, Option<FieldIdx>> {
+        let field_idx = match op.layout.ty.kind() {
+            ty::Adt(def, _) if !def.is_union() => {
+                let variant = if def.is_enum() {
+                    &def.variants()[self.ecx.read_discriminant(op)?]
+                } else {
+                    def.non_enum_variant()
+                };
+                variant
+                    .fields
+                    .iter_enumerated()
+                    .find_map(|(idx, field_def)| (field_def.name.as_str() == name).then_some(idx))
+            }
+            ty::Tuple(args) =>
+                name.parse::<usize>().ok().filter(|&idx| idx < args.len()).map(FieldIdx::from),
+            _ => None,
+        };
+        interp_ok(field_idx)
+    }
+
+    /// Follow a reference, raw pointer, or `Box` to its pointee, checking that
+    /// the pointee can actually be read.
+    fn deref_value(&self, op: &OpTy<'tcx>) -> InterpResult<'tcx, MPlaceTy<'tcx>> {
+        let mplace = self.ecx.deref_pointer(op)?;
+        // `deref_pointer` only checks references and boxes; raw pointers may
+        // dangle, so check dereferenceability for every pointer kind.
+        let Some((size, _)) = self.ecx.size_and_align_of_val(&mplace)? else {
+            return Err(miri::err_unsup_format!("cannot inspect extern type pointees")).into();
+        };
+        self.ecx.check_ptr_access(
+            mplace.ptr(),
+            size,
+            CheckInAllocMsg::Dereferenceable("pointee"),
+        )?;
+        interp_ok(mplace)
+    }
+
+    /// A shared reference to `op`, which must live in memory.
+    fn ref_to_value(&self, op: &OpTy<'tcx>) -> InterpResult<'tcx, OpTy<'tcx>> {
+        let Some(mplace) = op.as_mplace_or_imm().left() else {
+            return Err(miri::err_unsup_format!(
+                "cannot borrow a value that does not live in memory"
+            ))
+            .into();
+        };
+        let tcx = self.ecx.tcx.tcx;
+        let ty = Ty::new_imm_ref(tcx, tcx.lifetimes.re_erased, op.layout.ty);
+        interp_ok(ImmTy::from_immediate(mplace.to_ref(&self.ecx), self.ecx.layout_of(ty)?).into())
+    }
+
+    /// Whether `op` is a pointer-like type whose pointee can be expanded.
+    fn is_pointer_like(&self, op: &OpTy<'tcx>) -> bool {
+        match op.layout.ty.kind() {
+            ty::Ref(..) | ty::RawPtr(..) => true,
+            ty::Adt(..) => op.layout.ty.is_box_global(self.ecx.tcx.tcx),
+            _ => false,
+        }
+    }
+
+    /// Whether `op` has any children to list.
+    fn has_children(&self, op: &OpTy<'tcx>) -> bool {
+        if self.is_pointer_like(op) {
+            return self.deref_value(op).discard_err().is_some();
+        }
+        match op.layout.ty.kind() {
+            ty::Adt(def, _) if def.variants().is_empty() || def.is_union() => false,
+            ty::Adt(def, _) if def.is_enum() =>
+                self.ecx
+                    .read_discriminant(op)
+                    .discard_err()
+                    .is_some_and(|variant_idx| !def.variants()[variant_idx].fields.is_empty()),
+            ty::Adt(def, _) => !def.non_enum_variant().fields.is_empty(),
+            ty::Tuple(args) => !args.is_empty(),
+            ty::Array(..) | ty::Slice(_) =>
+                op.len(&self.ecx).discard_err().is_some_and(|len| len > 0),
+            _ => false,
+        }
+    }
+
+    /// Describe one child value reached from `parent` by `step`.
+    fn child_value_desc(
+        &self,
+        parent: &ValuePath,
+        name: String,
+        step: ValueStep,
+        op: OpTy<'tcx>,
+    ) -> ValueDesc {
+        self.value_desc(name, parent.child(step), op)
+    }
+
+    /// Describe the value `op` that `path` evaluates to.
+    fn value_desc(&self, name: String, path: ValuePath, op: OpTy<'tcx>) -> ValueDesc {
+        let children = self.has_children(&op).then_some(path);
+        ValueDesc {
+            name,
+            ty: op.layout.ty.to_string(),
+            pointee: self.pointee_memory(&op),
+            value: self.render_source_shaped_op(op),
+            children,
+        }
+    }
+
+    /// List the children of `op`, which `path` evaluates to.
+    ///
+    /// Structs, tuples, and the active variant of an enum list their fields,
+    /// arrays and slices their elements, and pointers their pointee.
+    fn value_children_of(
+        &self,
+        op: &OpTy<'tcx>,
+        path: &ValuePath,
+    ) -> InterpResult<'tcx, Vec<ValueDesc>> {
+        if self.is_pointer_like(op) {
+            let pointee = self.deref_value(op)?;
+            return interp_ok(vec![self.child_value_desc(
+                path,
+                "*".to_string(),
+                ValueStep::Deref,
+                pointee.into(),
+            )]);
+        }
+
+        let mut children = Vec::new();
+        match op.layout.ty.kind() {
+            // Like in the inline rendering, unions have no known active field.
+            ty::Adt(def, _) if def.variants().is_empty() || def.is_union() => {}
+            ty::Adt(def, _) => {
+                let (variant_def, down) = if def.is_enum() {
+                    let variant_idx = self.ecx.read_discriminant(op)?;
+                    (&def.variants()[variant_idx], self.ecx.project_downcast(op, variant_idx)?)
+                } else {
+                    (def.non_enum_variant(), op.clone())
+                };
+                for (field_idx, field_def) in variant_def.fields.iter_enumerated() {
+                    let field_op = self.ecx.project_field(&down, field_idx)?;
+                    children.push(self.child_value_desc(
+                        path,
+                        field_def.name.to_string(),
+                        ValueStep::Field(field_idx),
+                        field_op,
+                    ));
+                }
+            }
+            ty::Tuple(args) =>
+                for i in 0..args.len() {
+                    let field_idx = FieldIdx::from_usize(i);
+                    let field_op = self.ecx.project_field(op, field_idx)?;
+                    children.push(self.child_value_desc(
+                        path,
+                        i.to_string(),
+                        ValueStep::Field(field_idx),
+                        field_op,
+                    ));
+                },
+            ty::Array(..) | ty::Slice(_) => {
+                let mut iter = self.ecx.project_array_fields(op)?;
+                while let Some((idx, elem_op)) = iter.next(&self.ecx)? {
+                    children.push(self.child_value_desc(
+                        path,
+                        format!("[{idx}]"),
+                        ValueStep::Index(idx),
+                        elem_op,
+                    ));
+                }
+            }
+            _ => {}
+        }
+        interp_ok(children)
+    }
+
+    /// Evaluate a MIR place relative to `frame` instead of the active frame.
+    fn place_to_op_in_frame(
+        &self,
+        frame: &Frame<'tcx, Provenance, FrameExtra<'tcx>>,
+        place: mir::Place<'tcx>,
+    ) -> InterpResult<'tcx, OpTy<'tcx>> {
+        let mut op = self.ecx.local_at_frame_to_op(frame, place.local, None)?;
+        for elem in place.projection {
+            op = match elem {
+                ProjectionElem::Deref => self.ecx.deref_pointer(&op)?.into(),
+                elem => self.ecx.project(&op, elem)?,
+            };
+        }
+        interp_ok(op)
     }
 
     /// Renders the current byte range of an indirect MIR value.
@@ -622,22 +1609,20 @@ impl<'tcx> PrirodaContext<'tcx> {
         interp_ok(format!("[{}]", rendered.join(" ")))
     }
 
-    /// Render an evaluated operand using Rust-source-shaped containers with raw leaves.
+    /// Render an evaluated operand using Rust-source-shaped containers with typed leaves.
     ///
     /// The operand is produced from live interpreter state, usually via `local_to_op`
     /// for a whole MIR local or `eval_place_to_op` for a projected debug-info place.
     ///
-    /// This intentionally does not call user `Debug` / `Display`, and it does not
-    /// try to make every scalar leaf pretty yet. Unsupported cases and leaf values
-    /// fall back to `render_op`, preserving the old raw byte/provenance renderer.
+    /// This intentionally does not call user `Debug` / `Display`. Primitive
+    /// scalars are read according to their type, and pointers are shown with
+    /// their provenance. Unsupported cases, and leaves that cannot be read as
+    /// their type (e.g. uninitialized bytes), fall back to `render_op`.
     ///
-    /// FIXME: teach the leaf renderer about simple Rust scalars (`bool`, integers,
-    /// chars, raw pointers/references) once the source-shaped container output is
-    /// stable enough to stop depending on byte dumps for every field.
+    /// Pointers are never followed here; their pointees are reachable as
+    /// children through [`Self::value_children`].
     ///
-    /// FIXME: decide how much dereferencing belongs in this renderer. References
-    /// currently stay as raw pointer leaves; following them may belong in the
-    /// existing `follow` command instead of automatic local rendering.
+    /// Callers must suppress memory hooks, see [`Self::list_locals`].
     fn render_source_shaped_op(&self, op: OpTy<'tcx>) -> String {
         self.render_source_shaped_op_inner(op, 0)
     }
@@ -658,6 +1643,10 @@ impl<'tcx> PrirodaContext<'tcx> {
             return self.render_op(op);
         }
 
+        if self.is_pointer_like(&op) {
+            return self.render_pointer(op);
+        }
+
         match op.layout.ty.kind() {
             // Empty enums have no active variant to format. Unions do not record
             // which field is currently active, so choosing one would be misleading.
@@ -786,13 +1775,46 @@ impl<'tcx> PrirodaContext<'tcx> {
                 format!("[{}]", fields.join(", "))
             }
 
-            // FIXME: consider source-shaped special cases for strings, closures,
+            ty::Bool | ty::Char | ty::Int(_) | ty::Uint(_) | ty::Float(_) =>
+                match self.ecx.read_immediate(&op).discard_err() {
+                    Some(imm) => format!("{imm}"),
+                    None => self.render_op(op),
+                },
+
+            ty::Str => {
+                let Some(bytes) = op
+                    .as_mplace_or_imm()
+                    .left()
+                    .and_then(|mplace| self.ecx.read_str(&mplace).discard_err())
+                else {
+                    return self.render_op(op);
+                };
+                format!("{bytes:?}")
+            }
+
+            // FIXME: consider source-shaped special cases for closures,
             // generators/coroutines, trait objects, and SIMD/vector-like types.
             // Until then these stay on the raw renderer path.
             _ => self.render_op(op),
         }
     }
 
+    /// Render a reference, raw pointer, or `Box` as its address with provenance
+    /// (allocation id and borrow tag), plus the metadata of wide pointers.
+    fn render_pointer(&self, op: OpTy<'tcx>) -> String {
+        let Some(imm) = self.ecx.read_immediate(&op).discard_err() else {
+            return self.render_op(op);
+        };
+        let (ptr, meta) = imm.to_scalar_and_meta();
+        let ptr = ptr.to_pointer(&self.ecx);
+        match meta {
+            MemPlaceMeta::None => format!("{ptr:?}"),
+            MemPlaceMeta::Meta(Scalar::Int(len)) => format!("{ptr:?} (len: {len})"),
+            MemPlaceMeta::Meta(vtable) =>
+                format!("{ptr:?} (vtable: {:?})", vtable.to_pointer(&self.ecx)),
+        }
+    }
+
     /// Render an evaluated operand using the same raw representation for
     /// whole locals and projected MIR places.
     fn render_op(&self, op: OpTy<'tcx>) -> String {
@@ -873,6 +1895,7 @@ impl<'tcx> PrirodaContext<'tcx> {
     /// Builds the baseline debugger row for one MIR local without scanning debug info.
     fn make_mir_local_desc(
         &self,
+        frame_ref: FrameRef,
         frame: &Frame<'tcx, Provenance, FrameExtra<'tcx>>,
         local: usize,
     ) -> Option<LocalDesc> {
@@ -888,6 +1911,8 @@ impl<'tcx> PrirodaContext<'tcx> {
             storage_projection: Vec::new(),
             ty: local_decl.ty.to_string(),
             value: "<unsupported>".to_string(),
+            children: None,
+            pointee: None,
         };
 
         match &frame.locals[local].as_mplace_or_imm() {
@@ -899,8 +1924,16 @@ impl<'tcx> PrirodaContext<'tcx> {
             Some(Either::Left(_) | Either::Right(_)) => {
                 let op = self
                     .ecx
-                    .local_to_op(local, None)
+                    .local_at_frame_to_op(frame, local, None)
                     .expect("this error can only occur in CTFE on generic code");
+                if self.has_children(&op) {
+                    local_desc.children = Some(ValuePath {
+                        frame: frame_ref,
+                        root: ValueRoot::Local(local),
+                        steps: Vec::new(),
+                    });
+                }
+                local_desc.pointee = self.pointee_memory(&op);
                 local_desc.value = self.render_source_shaped_op(op);
             }
         };
@@ -910,6 +1943,7 @@ impl<'tcx> PrirodaContext<'tcx> {
 
     fn build_local_descs(
         &self,
+        frame_ref: FrameRef,
         frame: &Frame<'tcx, Provenance, FrameExtra<'tcx>>,
     ) -> Vec<LocalDesc> {
         let local_decls = &frame.body().local_decls;
@@ -918,7 +1952,8 @@ impl<'tcx> PrirodaContext<'tcx> {
 
         // Start with one baseline row for every MIR local, then layer debug info on top.
         for (local_idx, _) in local_decls.iter_enumerated() {
-            local_descs.push(self.make_mir_local_desc(frame, local_idx.index()).unwrap());
+            local_descs
+                .push(self.make_mir_local_desc(frame_ref, frame, local_idx.index()).unwrap());
         }
 
         // FIXME: Finish classifying `var_debug_info` by keeping the source path
@@ -954,7 +1989,7 @@ impl<'tcx> PrirodaContext<'tcx> {
         // Whole-place debug entries enrich the direct storage-local description.
         // Projected places are evaluated from their original MIR Place and use
         // the same raw renderer as ordinary locals.
-        for var_debug_info in &frame.body().var_debug_info {
+        for (debug_info_idx, var_debug_info) in frame.body().var_debug_info.iter().enumerate() {
             if let VarDebugInfoContents::Place(place) = &var_debug_info.value {
                 if let Some(local_idx) = place.as_local()
                     && local_descs[local_idx.index()].source_name.is_none()
@@ -967,11 +2002,21 @@ impl<'tcx> PrirodaContext<'tcx> {
                     let storage_projection = Self::render_storage_projection(place.projection);
                     let source_projection =
                         Self::render_source_projection(var_debug_info.composite.as_deref());
-                    let value = self
-                        .ecx
-                        .eval_place_to_op(*place, None)
-                        .map(|op| self.render_source_shaped_op(op))
-                        .unwrap_or_else(|err| format!("<error: {}>", err.to_string()));
+                    let (value, children, pointee) =
+                        match self.place_to_op_in_frame(frame, *place).report_err() {
+                            Ok(op) => {
+                                let children = self.has_children(&op).then(|| {
+                                    ValuePath {
+                                        frame: frame_ref,
+                                        root: ValueRoot::DebugInfo(debug_info_idx),
+                                        steps: Vec::new(),
+                                    }
+                                });
+                                let pointee = self.pointee_memory(&op);
+                                (self.render_source_shaped_op(op), children, pointee)
+                            }
+                            Err(err) => (format!("<error: {}>", err.to_string()), None, None),
+                        };
 
                     local_descs.push(LocalDesc {
                         source_name: Some(var_debug_info.name),
@@ -980,6 +2025,8 @@ impl<'tcx> PrirodaContext<'tcx> {
                         storage_projection,
                         ty: place.ty(local_decls, self.ecx.tcx.tcx).ty.to_string(),
                         value,
+                        children,
+                        pointee,
                     });
                 }
             }
@@ -996,24 +2043,52 @@ pub(super) enum DebuggerCommand {
     StepOut,
     TerminateSession,
     Continue,
-    Breakpoint(PathBuf, usize),
+    /// Go back to the start of the selected thread's current or previous source line.
+    ReverseStep,
+    /// Go back to the last breakpoint hit.
+    ReverseContinue,
+    Breakpoint(BreakpointLocation, BreakpointOptions),
+    Watch(WatchTarget, WatchAccess, BreakpointOptions),
     ListLocals,
     Print(usize),
     Follow(AllocId, usize),
+    /// List the live allocations.
+    Allocations,
+    /// Evaluate an expression in the innermost frame of the selected thread.
+    Evaluate(String),
+    Threads,
+    SelectThread(u32),
+    Backtrace,
+    SchedulerLocking(bool),
 }
 
 pub(super) enum BreakpointSetResult {
-    Added(PathBuf, usize),
+    Added(BreakpointLocation),
+    /// There already was a breakpoint at this location; its options were replaced.
+    Updated(BreakpointLocation),
     Duplicate,
+    /// The options could not be parsed.
+    Invalid(String),
     // FIXME: add pending breakpoint support later if needed.
 }
 
 pub(super) enum CommandResult {
     Execution(ExecutionResult),
     BreakpointResult(BreakpointSetResult),
+    /// The watched memory, or why it cannot be watched.
+    WatchpointResult(Result<WatchRange, String>),
     Locals(Vec<LocalDesc>),
-    SingleLocal(Option<LocalDesc>),
+    /// The printed local with its children expanded, or `None` if there is no such local.
+    SingleLocal(Option<(LocalDesc, Vec<ValueTree>)>),
     Memory(String),
+    Allocations(Vec<AllocDesc>),
+    /// The value of the evaluated expression, or why it could not be evaluated.
+    Evaluation(Result<Evaluation, String>),
+    Threads(Vec<ThreadDesc>),
+    /// The newly selected thread, or `None` if no live thread has the requested id.
+    ThreadSelected(Option<ThreadDesc>),
+    Backtrace(Vec<FrameDesc>),
+    SchedulerLocking(bool),
     // FIXME: distinguish terminating the debugger session from disconnecting a
     // frontend and terminating the interpreted program once multiple frontends exist.
     TerminateSession,
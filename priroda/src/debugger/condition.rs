@@ -0,0 +1,747 @@
+//! Breakpoint conditions, hit conditions, and debug console expressions.
+//!
+//! Conditions and the debug console share a small expression language,
+//! evaluated against the locals of a frame:
+//!
+//! ```text
+//! expr    := and ("||" and)*
+//! and     := cmp ("&&" cmp)*
+//! cmp     := unary (("==" | "!=" | "<" | "<=" | ">" | ">=") unary)?
+//! unary   := ("!" | "-" | "*" | "&") unary | postfix
+//! postfix := atom ("." (ident | integer) | "[" expr "]")*
+//! atom    := integer | "true" | "false" | ident | call | "(" expr ")"
+//! call    := ident "(" (expr ("," expr)*)? ")"
+//! ```
+//!
+//! `*`, `&`, fields, and indexing only apply to places. Like in Rust, fields
+//! and indexing look through references, raw pointers, and `Box`. The only
+//! functions are the debugger's versions of Miri's `miri_get_alloc_id` and
+//! `miri_print_borrow_state`.
+
+use std::num::NonZero;
+
+use miri::*;
+use rustc_middle::mir::interpret::AllocId;
+use rustc_middle::mir::{self, VarDebugInfoContents};
+use rustc_middle::ty;
+
+use super::{FrameRef, PrirodaContext, ValueDesc, ValuePath, ValueRoot, ValueStep};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum BinOp {
+    And,
+    Or,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl BinOp {
+    fn symbol(self) -> &'static str {
+        match self {
+            BinOp::And => "&&",
+            BinOp::Or => "||",
+            BinOp::Eq => "==",
+            BinOp::Ne => "!=",
+            BinOp::Lt => "<",
+            BinOp::Le => "<=",
+            BinOp::Gt => ">",
+            BinOp::Ge => ">=",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Expr {
+    Int(i128),
+    Bool(bool),
+    Place(Place),
+    /// A call of one of the debugger's built-in functions.
+    Call(String, Vec<Expr>),
+    Not(Box<Expr>),
+    Neg(Box<Expr>),
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+}
+
+/// A place in the program's memory, or a reference to one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Place {
+    Variable(String),
+    /// A named or tuple field.
+    Field(Box<Place>, String),
+    /// An element of an array or slice.
+    Index(Box<Place>, Box<Expr>),
+    Deref(Box<Place>),
+    Ref(Box<Place>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    Int(i128),
+    Ident(String),
+    Dot,
+    Comma,
+    Not,
+    Minus,
+    Star,
+    Amp,
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    Op(BinOp),
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        if c.is_ascii_digit() {
+            let mut end = start;
+            while let Some(&(idx, c)) = chars.peek()
+                && (c.is_ascii_alphanumeric() || c == '_')
+            {
+                end = idx + c.len_utf8();
+                chars.next();
+            }
+            tokens.push(Token::Int(parse_int(&input[start..end])?));
+            continue;
+        }
+        if c.is_alphabetic() || c == '_' {
+            let mut end = start;
+            while let Some(&(idx, c)) = chars.peek()
+                && (c.is_alphanumeric() || c == '_')
+            {
+                end = idx + c.len_utf8();
+                chars.next();
+            }
+            tokens.push(Token::Ident(input[start..end].to_string()));
+            continue;
+        }
+
+        chars.next();
+        let next = chars.peek().map(|&(_, c)| c);
+        let (token, two_chars) = match (c, next) {
+            ('&', Some('&')) => (Token::Op(BinOp::And), true),
+            ('|', Some('|')) => (Token::Op(BinOp::Or), true),
+            ('=', Some('=')) => (Token::Op(BinOp::Eq), true),
+            ('!', Some('=')) => (Token::Op(BinOp::Ne), true),
+            ('<', Some('=')) => (Token::Op(BinOp::Le), true),
+            ('>', Some('=')) => (Token::Op(BinOp::Ge), true),
+            ('<', _) => (Token::Op(BinOp::Lt), false),
+            ('>', _) => (Token::Op(BinOp::Gt), false),
+            ('!', _) => (Token::Not, false),
+            ('-', _) => (Token::Minus, false),
+            ('*', _) => (Token::Star, false),
+            ('&', _) => (Token::Amp, false),
+            ('.', _) => (Token::Dot, false),
+            (',', _) => (Token::Comma, false),
+            ('(', _) => (Token::LParen, false),
+            (')', _) => (Token::RParen, false),
+            ('[', _) => (Token::LBracket, false),
+            (']', _) => (Token::RBracket, false),
+            _ => return Err(format!("unexpected character `{c}`")),
+        };
+        if two_chars {
+            chars.next();
+        }
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+/// Parse a decimal or `0x`-prefixed hexadecimal integer, allowing `_` separators.
+fn parse_int(literal: &str) -> Result<i128, String> {
+    let digits = literal.replace('_', "");
+    let parsed = match digits.strip_prefix("0x") {
+        Some(hex) => i128::from_str_radix(hex, 16),
+        None => digits.parse(),
+    };
+    parsed.map_err(|_| format!("invalid integer literal `{literal}`"))
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.and()?;
+        while self.eat(&Token::Op(BinOp::Or)) {
+            lhs = Expr::Binary(BinOp::Or, Box::new(lhs), Box::new(self.and()?));
+        }
+        Ok(lhs)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.comparison()?;
+        while self.eat(&Token::Op(BinOp::And)) {
+            lhs = Expr::Binary(BinOp::And, Box::new(lhs), Box::new(self.comparison()?));
+        }
+        Ok(lhs)
+    }
+
+    fn comparison(&mut self) -> Result<Expr, String> {
+        let lhs = self.unary()?;
+        match self.peek() {
+            Some(&Token::Op(op)) if !matches!(op, BinOp::And | BinOp::Or) => {
+                self.pos += 1;
+                Ok(Expr::Binary(op, Box::new(lhs), Box::new(self.unary()?)))
+            }
+            _ => Ok(lhs),
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.eat(&Token::Not) {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        if self.eat(&Token::Minus) {
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        if self.eat(&Token::Star) {
+            let place = Self::expect_place(self.unary()?, "only places can be dereferenced")?;
+            return Ok(Expr::Place(Place::Deref(Box::new(place))));
+        }
+        // `&&x` is two borrows, not a logical and.
+        let refs = match self.peek() {
+            Some(Token::Amp) => 1,
+            Some(Token::Op(BinOp::And)) => 2,
+            _ => return self.postfix(),
+        };
+        self.pos += 1;
+        let mut place = Self::expect_place(self.unary()?, "only places can be borrowed")?;
+        for _ in 0..refs {
+            place = Place::Ref(Box::new(place));
+        }
+        Ok(Expr::Place(place))
+    }
+
+    fn postfix(&mut self) -> Result<Expr, String> {
+        let mut expr = self.atom()?;
+        loop {
+            if self.eat(&Token::Dot) {
+                let field = match self.next() {
+                    Some(Token::Ident(field)) => field,
+                    Some(Token::Int(idx)) => idx.to_string(),
+                    _ => return Err("expected a field name after `.`".to_string()),
+                };
+                let place = Self::expect_place(expr, "only places have fields")?;
+                expr = Expr::Place(Place::Field(Box::new(place), field));
+            } else if self.eat(&Token::LBracket) {
+                let index = self.expr()?;
+                if !self.eat(&Token::RBracket) {
+                    return Err("expected `]`".to_string());
+                }
+                let place = Self::expect_place(expr, "only places can be indexed")?;
+                expr = Expr::Place(Place::Index(Box::new(place), Box::new(index)));
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    fn expect_place(expr: Expr, error: &str) -> Result<Place, String> {
+        match expr {
+            Expr::Place(place) => Ok(place),
+            _ => Err(error.to_string()),
+        }
+    }
+
+    fn atom(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Int(value)) => Ok(Expr::Int(value)),
+            Some(Token::Ident(name)) if name == "true" => Ok(Expr::Bool(true)),
+            Some(Token::Ident(name)) if name == "false" => Ok(Expr::Bool(false)),
+            Some(Token::Ident(name)) if self.eat(&Token::LParen) => {
+                let mut args = Vec::new();
+                if !self.eat(&Token::RParen) {
+                    loop {
+                        args.push(self.expr()?);
+                        if self.eat(&Token::RParen) {
+                            break;
+                        }
+                        if !self.eat(&Token::Comma) {
+                            return Err("expected `,` or `)`".to_string());
+                        }
+                    }
+                }
+                Ok(Expr::Call(name, args))
+            }
+            Some(Token::Ident(name)) => Ok(Expr::Place(Place::Variable(name))),
+            Some(Token::LParen) => {
+                let expr = self.expr()?;
+                if !self.eat(&Token::RParen) {
+                    return Err("expected `)`".to_string());
+                }
+                Ok(expr)
+            }
+            Some(_) => Err("expected a value".to_string()),
+            None => Err("unexpected end of expression".to_string()),
+        }
+    }
+}
+
+fn parse_expr(input: &str) -> Result<Expr, String> {
+    let mut parser = Parser { tokens: tokenize(input)?, pos: 0 };
+    let expr = parser.expr()?;
+    if parser.peek().is_some() {
+        return Err("unexpected trailing input".to_string());
+    }
+    Ok(expr)
+}
+
+/// A parsed breakpoint condition.
+#[derive(Clone, Debug)]
+pub(super) struct Condition(Expr);
+
+impl Condition {
+    pub(super) fn parse(input: &str) -> Result<Self, String> {
+        parse_expr(input).map(Condition)
+    }
+}
+
+/// When a breakpoint whose condition holds actually stops, based on how often
+/// it has been reached so far.
+#[derive(Clone, Copy, Debug)]
+pub(super) enum HitCondition {
+    Eq(u64),
+    Ge(u64),
+    Gt(u64),
+    Le(u64),
+    Lt(u64),
+    /// Every `n`th hit.
+    Multiple(u64),
+}
+
+impl HitCondition {
+    /// Parse `N`, `==N`, `>=N`, `>N`, `<=N`, `<N`, or `%N`. A bare `N` stops from
+    /// the `N`th hit on.
+    pub(super) fn parse(input: &str) -> Result<Self, String> {
+        let input = input.trim();
+        let (ctor, count): (fn(u64) -> Self, &str) = if let Some(n) = input.strip_prefix("==") {
+            (HitCondition::Eq, n)
+        } else if let Some(n) = input.strip_prefix(">=") {
+            (HitCondition::Ge, n)
+        } else if let Some(n) = input.strip_prefix("<=") {
+            (HitCondition::Le, n)
+        } else if let Some(n) = input.strip_prefix('>') {
+            (HitCondition::Gt, n)
+        } else if let Some(n) = input.strip_prefix('<') {
+            (HitCondition::Lt, n)
+        } else if let Some(n) = input.strip_prefix('%') {
+            (HitCondition::Multiple, n)
+        } else {
+            (HitCondition::Ge, input)
+        };
+        let count: u64 =
+            count.trim().parse().map_err(|_| format!("invalid hit condition `{input}`"))?;
+        let condition = ctor(count);
+        if let HitCondition::Multiple(0) = condition {
+            return Err("hit condition `%0` never stops".to_string());
+        }
+        Ok(condition)
+    }
+
+    /// Whether the `hits`th hit should stop.
+    pub(super) fn should_stop(self, hits: u64) -> bool {
+        match self {
+            HitCondition::Eq(n) => hits == n,
+            HitCondition::Ge(n) => hits >= n,
+            HitCondition::Gt(n) => hits > n,
+            HitCondition::Le(n) => hits <= n,
+            HitCondition::Lt(n) => hits < n,
+            HitCondition::Multiple(n) => hits.is_multiple_of(n),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Value {
+    Int(i128),
+    Bool(bool),
+}
+
+impl Value {
+    fn describe(self) -> &'static str {
+        match self {
+            Value::Int(_) => "an integer",
+            Value::Bool(_) => "a bool",
+        }
+    }
+}
+
+/// The result of evaluating an expression in the debug console.
+pub(crate) enum Evaluation {
+    /// A typed value. Places can be expanded like variables.
+    Value(ValueDesc),
+    /// Text printed by a built-in function, like the borrow state printed by
+    /// `miri_print_borrow_state`.
+    Text(String),
+}
+
+impl<'tcx> PrirodaContext<'tcx> {
+    /// Evaluate `condition` in `frame`.
+    pub(super) fn eval_condition(
+        &self,
+        condition: &Condition,
+        frame: FrameRef,
+    ) -> Result<bool, String> {
+        self.ecx.run_for_validation_ref(|_| {
+            match self.eval_expr(&condition.0, frame)? {
+                Value::Bool(value) => Ok(value),
+                value => Err(format!("condition is {}, not a bool", value.describe())),
+            }
+        })
+    }
+
+    /// Evaluate the debug console expression `input` in `frame`.
+    ///
+    /// Places evaluate to the value stored there, with its type and children;
+    /// other expressions evaluate like conditions.
+    pub(crate) fn evaluate(&self, input: &str, frame: FrameRef) -> Result<Evaluation, String> {
+        let expr = parse_expr(input)?;
+        let name = input.trim().to_string();
+        self.ecx.run_for_validation_ref(|_| {
+            match &expr {
+                Expr::Place(place) => {
+                    let path = self.eval_place(place, frame)?;
+                    let op =
+                        self.value_path_op(&path).report_err().map_err(|err| err.to_string())?;
+                    Ok(Evaluation::Value(self.value_desc(name, path, op)))
+                }
+                Expr::Call(func, args) if func == "miri_print_borrow_state" =>
+                    self.eval_print_borrow_state(args, frame).map(Evaluation::Text),
+                expr => {
+                    let (ty, value) = match self.eval_expr(expr, frame)? {
+                        Value::Int(value) => ("{integer}", value.to_string()),
+                        Value::Bool(value) => ("bool", value.to_string()),
+                    };
+                    Ok(Evaluation::Value(ValueDesc {
+                        name,
+                        ty: ty.to_string(),
+                        value,
+                        children: None,
+                        pointee: None,
+                    }))
+                }
+            }
+        })
+    }
+
+    fn eval_expr(&self, expr: &Expr, frame: FrameRef) -> Result<Value, String> {
+        match expr {
+            Expr::Int(value) => Ok(Value::Int(*value)),
+            Expr::Bool(value) => Ok(Value::Bool(*value)),
+            Expr::Place(place) => {
+                let path = self.eval_place(place, frame)?;
+                self.read_value(&path).report_err().map_err(|err| err.to_string())
+            }
+            Expr::Call(func, args) => self.eval_call(func, args, frame),
+            Expr::Not(operand) =>
+                match self.eval_expr(operand, frame)? {
+                    Value::Bool(value) => Ok(Value::Bool(!value)),
+                    value => Err(format!("cannot apply `!` to {}", value.describe())),
+                },
+            Expr::Neg(operand) =>
+                match self.eval_expr(operand, frame)? {
+                    Value::Int(value) =>
+                        value
+                            .checked_neg()
+                            .map(Value::Int)
+                            .ok_or_else(|| "integer overflow while negating".to_string()),
+                    value => Err(format!("cannot negate {}", value.describe())),
+                },
+            Expr::Binary(op @ (BinOp::And | BinOp::Or), lhs, rhs) => {
+                let Value::Bool(lhs) = self.eval_expr(lhs, frame)? else {
+                    return Err("operands of `&&` and `||` must be bools".to_string());
+                };
+                // Short-circuit like Rust, so the right-hand side may rely on the left.
+                if lhs == (*op == BinOp::Or) {
+                    return Ok(Value::Bool(lhs));
+                }
+                match self.eval_expr(rhs, frame)? {
+                    Value::Bool(rhs) => Ok(Value::Bool(rhs)),
+                    Value::Int(_) => Err("operands of `&&` and `||` must be bools".to_string()),
+                }
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                let lhs = self.eval_expr(lhs, frame)?;
+                let rhs = self.eval_expr(rhs, frame)?;
+                let ordering = match (lhs, rhs) {
+                    (Value::Int(lhs), Value::Int(rhs)) => lhs.cmp(&rhs),
+                    (Value::Bool(lhs), Value::Bool(rhs)) if matches!(op, BinOp::Eq | BinOp::Ne) =>
+                        lhs.cmp(&rhs),
+                    _ =>
+                        return Err(format!(
+                            "cannot compare {} with {} using `{}`",
+                            lhs.describe(),
+                            rhs.describe(),
+                            op.symbol()
+                        )),
+                };
+                Ok(Value::Bool(match op {
+                    BinOp::Eq => ordering.is_eq(),
+                    BinOp::Ne => ordering.is_ne(),
+                    BinOp::Lt => ordering.is_lt(),
+                    BinOp::Le => ordering.is_le(),
+                    BinOp::Gt => ordering.is_gt(),
+                    BinOp::Ge => ordering.is_ge(),
+                    BinOp::And | BinOp::Or => unreachable!(),
+                }))
+            }
+        }
+    }
+
+    /// Resolve `place` to the path of the value it refers to.
+    fn eval_place(&self, place: &Place, frame: FrameRef) -> Result<ValuePath, String> {
+        let path = match place {
+            Place::Variable(name) => self.resolve_variable(name, frame),
+            Place::Field(base, field) => {
+                let base = self.eval_place(base, frame)?;
+                self.place_field(base, field)
+            }
+            Place::Index(base, index) => {
+                let base = self.eval_place(base, frame)?;
+                let index = match self.eval_expr(index, frame)? {
+                    Value::Int(index) =>
+                        u64::try_from(index).map_err(|_| format!("invalid index {index}"))?,
+                    value => return Err(format!("index is {}, not an integer", value.describe())),
+                };
+                self.place_index(base, index)
+            }
+            Place::Deref(base) => {
+                let base = self.eval_place(base, frame)?;
+                self.place_deref(base)
+            }
+            Place::Ref(base) => {
+                let base = self.eval_place(base, frame)?;
+                self.place_ref(base)
+            }
+        };
+        path.report_err().map_err(|err| err.to_string())
+    }
+
+    fn place_field(&self, base: ValuePath, field: &str) -> InterpResult<'tcx, ValuePath> {
+        let (base, op) = self.auto_deref(base)?;
+        let Some(field_idx) = self.field_by_name(&op, field)? else {
+            return Err(miri::err_unsup_format!("`{}` has no field `{field}`", op.layout.ty))
+                .into();
+        };
+        interp_ok(base.child(ValueStep::Field(field_idx)))
+    }
+
+    fn place_index(&self, base: ValuePath, index: u64) -> InterpResult<'tcx, ValuePath> {
+        let (base, op) = self.auto_deref(base)?;
+        if !matches!(op.layout.ty.kind(), ty::Array(..) | ty::Slice(_)) {
+            return Err(miri::err_unsup_format!(
+                "cannot index into a value of type `{}`",
+                op.layout.ty
+            ))
+            .into();
+        }
+        let len = op.len(&self.ecx)?;
+        if index >= len {
+            return Err(miri::err_unsup_format!(
+                "index out of bounds: the len is {len} but the index is {index}"
+            ))
+            .into();
+        }
+        interp_ok(base.child(ValueStep::Index(index)))
+    }
+
+    fn place_deref(&self, base: ValuePath) -> InterpResult<'tcx, ValuePath> {
+        let op = self.value_path_op(&base)?;
+        if !self.is_pointer_like(&op) {
+            return Err(miri::err_unsup_format!("type `{}` cannot be dereferenced", op.layout.ty))
+                .into();
+        }
+        self.deref_value(&op)?;
+        interp_ok(base.child(ValueStep::Deref))
+    }
+
+    fn place_ref(&self, base: ValuePath) -> InterpResult<'tcx, ValuePath> {
+        self.ref_to_value(&self.value_path_op(&base)?)?;
+        interp_ok(base.child(ValueStep::Ref))
+    }
+
+    /// Follow pointers from `path` until it reaches a value that is not one,
+    /// like Rust does for field accesses and indexing.
+    fn auto_deref(&self, mut path: ValuePath) -> InterpResult<'tcx, (ValuePath, OpTy<'tcx>)> {
+        let mut op = self.value_path_op(&path)?;
+        while self.is_pointer_like(&op) {
+            op = self.deref_value(&op)?.into();
+            path = path.child(ValueStep::Deref);
+        }
+        interp_ok((path, op))
+    }
+
+    /// Read the integer or bool at `path`.
+    fn read_value(&self, path: &ValuePath) -> InterpResult<'tcx, Value> {
+        let op = self.value_path_op(path)?;
+        let ty = op.layout.ty;
+        let scalar = self.ecx.read_scalar(&op)?;
+        let value = match ty.kind() {
+            ty::Bool => Value::Bool(scalar.to_bool()?),
+            ty::Int(_) => Value::Int(scalar.to_int(op.layout.size)?),
+            ty::Uint(_) => {
+                let value = scalar.to_uint(op.layout.size)?;
+                let Ok(value) = value.try_into() else {
+                    return Err(miri::err_unsup_format!("{value} does not fit in an i128")).into();
+                };
+                Value::Int(value)
+            }
+            _ =>
+                return Err(miri::err_unsup_format!(
+                    "expressions can only compute with integers and bools, not `{ty}`"
+                ))
+                .into(),
+        };
+        interp_ok(value)
+    }
+
+    fn eval_call(&self, func: &str, args: &[Expr], frame: FrameRef) -> Result<Value, String> {
+        match func {
+            "miri_get_alloc_id" => {
+                let [Expr::Place(place)] = args else {
+                    return Err("`miri_get_alloc_id` takes a single pointer".to_string());
+                };
+                let path = self.eval_place(place, frame)?;
+                let alloc_id =
+                    self.alloc_id_of(&path).report_err().map_err(|err| err.to_string())?;
+                Ok(Value::Int(alloc_id.0.get().into()))
+            }
+            "miri_print_borrow_state" =>
+                Err("`miri_print_borrow_state` does not return a value".to_string()),
+            _ => Err(format!("cannot find function `{func}`")),
+        }
+    }
+
+    /// Like Miri's `miri_get_alloc_id`: the allocation the pointer at `path` points into.
+    fn alloc_id_of(&self, path: &ValuePath) -> InterpResult<'tcx, AllocId> {
+        let op = self.value_path_op(path)?;
+        if !self.is_pointer_like(&op) {
+            return Err(miri::err_unsup_format!(
+                "`miri_get_alloc_id` takes a pointer, not `{}`",
+                op.layout.ty
+            ))
+            .into();
+        }
+        let ptr = self.ecx.read_immediate(&op)?.to_scalar_and_meta().0.to_pointer(&self.ecx);
+        let (alloc_id, _, _) = self.ecx.ptr_get_alloc_id(ptr, 0).map_err_kind(|_err| {
+            miri::err_unsup_format!(
+                "pointer passed to `miri_get_alloc_id` must not be dangling, got {ptr:?}"
+            )
+        })?;
+        interp_ok(alloc_id)
+    }
+
+    /// Like Miri's `miri_print_borrow_state`, but returns the borrow state
+    /// instead of printing it. The second argument, `show_unnamed`, defaults to
+    /// `false`.
+    fn eval_print_borrow_state(&self, args: &[Expr], frame: FrameRef) -> Result<String, String> {
+        let (id, show_unnamed) = match args {
+            [id] => (id, None),
+            [id, show_unnamed] => (id, Some(show_unnamed)),
+            _ =>
+                return Err(
+                    "`miri_print_borrow_state` takes an allocation id and optionally a bool"
+                        .to_string(),
+                ),
+        };
+        let Value::Int(id) = self.eval_expr(id, frame)? else {
+            return Err("the allocation id must be an integer".to_string());
+        };
+        let show_unnamed = match show_unnamed.map(|expr| self.eval_expr(expr, frame)).transpose()? {
+            None => false,
+            Some(Value::Bool(show_unnamed)) => show_unnamed,
+            Some(Value::Int(_)) => return Err("`show_unnamed` must be a bool".to_string()),
+        };
+        let alloc_id = u64::try_from(id)
+            .ok()
+            .and_then(NonZero::new)
+            .map(AllocId)
+            .filter(|&alloc_id| self.ecx.get_alloc_info(alloc_id).kind == AllocKind::LiveData);
+        let Some(alloc_id) = alloc_id else {
+            return Err(format!("{id} is not the ID of a live data allocation"));
+        };
+        match self.ecx.render_borrow_state(alloc_id, show_unnamed).report_err() {
+            Ok(Some(state)) => Ok(state.trim_end().to_string()),
+            Ok(None) =>
+                Err("attempted to print borrow state, but no borrow state is being tracked"
+                    .to_string()),
+            Err(err) => Err(err.to_string()),
+        }
+    }
+
+    /// The innermost variable named `name` that is in scope at the current
+    /// location of `frame`.
+    pub(super) fn resolve_variable(
+        &self,
+        name: &str,
+        frame_ref: FrameRef,
+    ) -> InterpResult<'tcx, ValuePath> {
+        let Some(frame) = self.frame(frame_ref) else {
+            return Err(miri::err_unsup_format!("stack frame is no longer live")).into();
+        };
+        let body = frame.body();
+        let current_scope = frame.current_source_info().map(|info| info.scope);
+        let in_scope = |scope: mir::SourceScope| {
+            let Some(mut current) = current_scope else { return true };
+            loop {
+                if current == scope {
+                    return true;
+                }
+                match body.source_scopes[current].parent_scope {
+                    Some(parent) => current = parent,
+                    None => return false,
+                }
+            }
+        };
+
+        let variable = body.var_debug_info.iter().enumerate().rfind(|(_, info)| {
+            info.name.as_str() == name
+                && info.composite.is_none()
+                && in_scope(info.source_info.scope)
+        });
+        let Some((idx, info)) = variable else {
+            return Err(miri::err_unsup_format!("no variable `{name}` in scope")).into();
+        };
+        let VarDebugInfoContents::Place(place) = info.value else {
+            return Err(miri::err_unsup_format!("`{name}` is a constant, not a place")).into();
+        };
+        let root = if place.projection.is_empty() {
+            ValueRoot::Local(place.local)
+        } else {
+            ValueRoot::DebugInfo(idx)
+        };
+        interp_ok(ValuePath { frame: frame_ref, root, steps: Vec::new() })
+    }
+}
@@ -0,0 +1,168 @@
+//! MIR as the machine code of a debug adapter's disassembly view.
+//!
+//! Every statement and terminator of a body is one instruction. Editors
+//! expect instruction addresses to be numbers, so an [`InstructionAddress`]
+//! packs the body and the MIR location into one: rendered in hex, the digits
+//! read as the body number, then five digits of basic block and five digits
+//! of statement index.
+
+use std::fmt;
+use std::str::FromStr;
+
+use miri::*;
+use rustc_middle::{mir, ty};
+
+use super::{FrameRef, PrirodaContext, SourceLocation};
+
+/// Bits of an [`InstructionAddress`] used for each MIR location index.
+const INDEX_BITS: u32 = 20;
+
+/// Identifies one MIR statement or terminator.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) struct InstructionAddress {
+    /// One plus the index of the body in [`PrirodaContext::mir_bodies`], so
+    /// that the invalid instructions before a body still have distinct
+    /// addresses.
+    body: usize,
+    location: mir::Location,
+}
+
+impl InstructionAddress {
+    fn to_bits(self) -> u64 {
+        let block = self.location.block.as_usize().to_u64();
+        let statement = self.location.statement_index.to_u64();
+        (self.body.to_u64() << (2 * INDEX_BITS)) | (block << INDEX_BITS) | statement
+    }
+}
+
+impl fmt::Display for InstructionAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#x}", self.to_bits())
+    }
+}
+
+impl FromStr for InstructionAddress {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        let bits = u64::from_str_radix(s.strip_prefix("0x").ok_or(())?, 16).map_err(|_| ())?;
+        let index =
+            |shift: u32| usize::try_from((bits >> shift) & ((1 << INDEX_BITS) - 1)).map_err(|_| ());
+        Ok(InstructionAddress {
+            body: usize::try_from(bits >> (2 * INDEX_BITS)).map_err(|_| ())?,
+            location: mir::Location {
+                block: mir::BasicBlock::from_usize(index(INDEX_BITS)?),
+                statement_index: index(0)?,
+            },
+        })
+    }
+}
+
+/// Structured description of one MIR instruction.
+pub(crate) struct InstructionDesc {
+    /// The rendered [`InstructionAddress`].
+    pub(crate) address: String,
+    /// The statement or terminator, prefixed with its MIR location.
+    pub(crate) text: String,
+    /// The body's function instance, set on its first instruction only.
+    pub(crate) symbol: Option<String>,
+    pub(crate) location: Option<SourceLocation>,
+}
+
+impl InstructionDesc {
+    /// Stands in for instructions before the start or after the end of a
+    /// body, which debug adapters still have to list.
+    fn invalid(bits: u64) -> Self {
+        InstructionDesc {
+            address: format!("{bits:#x}"),
+            text: "<no instruction>".to_string(),
+            symbol: None,
+            location: None,
+        }
+    }
+}
+
+impl<'tcx> PrirodaContext<'tcx> {
+    /// Where `frame` is in its body, if it is at a MIR instruction. Shims
+    /// without a precise location have none.
+    pub(crate) fn instruction_address(&mut self, frame: FrameRef) -> Option<InstructionAddress> {
+        let frame = self.frame(frame)?;
+        let location = frame.current_loc().left()?;
+        let instance = frame.instance();
+        if location.block.as_usize() >= 1 << INDEX_BITS
+            || location.statement_index >= 1 << INDEX_BITS
+        {
+            return None;
+        }
+        let body = match self.mir_bodies.iter().position(|&body| body == instance) {
+            Some(idx) => idx + 1,
+            None => {
+                self.mir_bodies.push(instance);
+                self.mir_bodies.len()
+            }
+        };
+        Some(InstructionAddress { body, location })
+    }
+
+    /// List `count` instructions, starting `offset` instructions after
+    /// `reference`. The window may reach past either end of the body, which
+    /// is filled with invalid instructions.
+    ///
+    /// Returns `None` if `reference` is not an instruction the debugger
+    /// handed out an address for.
+    pub(crate) fn disassemble(
+        &self,
+        reference: InstructionAddress,
+        offset: i64,
+        count: usize,
+    ) -> Option<Vec<InstructionDesc>> {
+        let instructions = self.body_instructions(reference.body)?;
+        let reference_idx = instructions.iter().position(|(address, _)| *address == reference)?;
+        let first = instructions.first()?.0.to_bits();
+        let last = instructions.last()?.0.to_bits();
+        let len = i64::try_from(instructions.len()).ok()?;
+        let start = i64::try_from(reference_idx).ok()?.checked_add(offset)?;
+        let end = start.saturating_add(i64::try_from(count).ok()?);
+
+        let before = (start..end.min(0))
+            .map(|idx| InstructionDesc::invalid(first.saturating_sub(idx.unsigned_abs())));
+        let inside = instructions
+            .into_iter()
+            .map(|(_, desc)| desc)
+            .skip(usize::try_from(start.clamp(0, len)).unwrap())
+            .take(usize::try_from(end.clamp(0, len) - start.clamp(0, len)).unwrap());
+        let after = (len.max(start)..end).map(|idx| {
+            InstructionDesc::invalid(last.saturating_add((idx - len + 1).unsigned_abs()))
+        });
+        Some(before.chain(inside).chain(after).collect())
+    }
+
+    /// All instructions of the body with number `body`, block by block.
+    fn body_instructions(&self, body: usize) -> Option<Vec<(InstructionAddress, InstructionDesc)>> {
+        let instance: ty::Instance<'tcx> = *self.mir_bodies.get(body.checked_sub(1)?)?;
+        let mir = self.ecx.load_mir(instance.def, None).discard_err()?;
+        let mut instructions = Vec::new();
+        for (block, data) in mir.basic_blocks.iter_enumerated() {
+            let statements = data
+                .statements
+                .iter()
+                .map(|statement| (format!("{:?}", statement.kind), statement.source_info.span));
+            let terminator = data.terminator();
+            let terminator = (format!("{:?}", terminator.kind), terminator.source_info.span);
+            for (statement_index, (text, span)) in statements.chain([terminator]).enumerate() {
+                let location = mir::Location { block, statement_index };
+                let address = InstructionAddress { body, location };
+                instructions.push((
+                    address,
+                    InstructionDesc {
+                        address: address.to_string(),
+                        text: format!("{location:?}: {text}"),
+                        symbol: (location == mir::Location::START).then(|| instance.to_string()),
+                        location: self.resolve_location(span),
+                    },
+                ));
+            }
+        }
+        Some(instructions)
+    }
+}
@@ -0,0 +1,208 @@
+//! Memory inspection: the live allocations, and their raw bytes for memory
+//! views.
+//!
+//! Frontends name memory with a [`MemoryRef`] into an allocation. Miri only
+//! assigns addresses to allocations once the program needs them, and doing so
+//! from the debugger would change the execution, so positions are always
+//! offsets into an allocation.
+
+use std::fmt;
+use std::num::NonZeroU64;
+use std::str::FromStr;
+
+use miri::*;
+use rustc_abi::Size;
+use rustc_middle::mir::interpret::AllocId;
+
+use super::{PrirodaContext, SourceLocation};
+
+/// A position `offset` bytes into the allocation `alloc_id`.
+///
+/// Renders as `alloc<id>+<offset>`, like a [`super::WatchRange`] without the
+/// size, and also parses from a bare `alloc<id>`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) struct MemoryRef {
+    pub(crate) alloc_id: AllocId,
+    pub(crate) offset: u64,
+}
+
+impl fmt::Display for MemoryRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}+{}", self.alloc_id, self.offset)
+    }
+}
+
+impl FromStr for MemoryRef {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        let s = s.strip_prefix("alloc").ok_or(())?;
+        let (alloc_id, offset) = s.split_once('+').unwrap_or((s, "0"));
+        let alloc_id = AllocId(NonZeroU64::new(alloc_id.parse().map_err(|_| ())?).ok_or(())?);
+        Ok(MemoryRef { alloc_id, offset: offset.parse().map_err(|_| ())? })
+    }
+}
+
+/// Structured information about one live allocation.
+pub(crate) struct AllocDesc {
+    pub(crate) id: AllocId,
+    pub(crate) size: u64,
+    pub(crate) align: u64,
+    /// Display-rendered memory kind, like `Rust heap` or `stack variable`.
+    pub(crate) kind: String,
+    /// Where the allocation was created, innermost frame first. Miri only
+    /// records this for allocations that must not leak.
+    pub(crate) backtrace: Option<Vec<AllocFrame>>,
+}
+
+/// One frame of an allocation backtrace.
+pub(crate) struct AllocFrame {
+    /// Display-rendered function instance.
+    pub(crate) name: String,
+    pub(crate) location: Option<SourceLocation>,
+}
+
+/// The result of reading raw memory.
+pub(crate) struct MemoryRead {
+    /// Offset into the allocation of the first byte read.
+    pub(crate) offset: i64,
+    /// The initialized bytes read, up to the first byte that could not be read.
+    pub(crate) data: Vec<u8>,
+    /// How many bytes after `data` cannot be read, because they are
+    /// uninitialized or outside of the allocation.
+    pub(crate) unreadable: u64,
+}
+
+impl<'tcx> PrirodaContext<'tcx> {
+    /// Describe all live allocations, ordered by id.
+    pub(crate) fn live_allocations(&self) -> Vec<AllocDesc> {
+        let mut allocs = self
+            .ecx
+            .memory
+            .alloc_map()
+            .filter_map_collect(|&id, (kind, alloc)| Some(self.alloc_desc(id, kind, alloc)));
+        allocs.sort_by_key(|alloc| alloc.id);
+        allocs
+    }
+
+    /// Describe the live allocation `alloc_id`, if there is one.
+    pub(crate) fn allocation(&self, alloc_id: AllocId) -> Option<AllocDesc> {
+        let (kind, alloc) = self.ecx.memory.alloc_map().get(alloc_id)?;
+        Some(self.alloc_desc(alloc_id, kind, alloc))
+    }
+
+    fn alloc_desc(
+        &self,
+        id: AllocId,
+        kind: &MemoryKind,
+        alloc: &Allocation<Provenance, AllocExtra<'tcx>, MiriAllocBytes>,
+    ) -> AllocDesc {
+        let backtrace = alloc.extra.backtrace.clone().map(|backtrace| {
+            // Like in Miri's leak reports, leave out the runtime frames.
+            let (backtrace, _pruned) = prune_stacktrace(backtrace, &self.ecx.machine);
+            backtrace
+                .iter()
+                .map(|frame| {
+                    AllocFrame {
+                        name: frame.instance.to_string(),
+                        location: self.resolve_location(frame.span),
+                    }
+                })
+                .collect()
+        });
+        AllocDesc {
+            id,
+            size: alloc.size().bytes(),
+            align: alloc.align.bytes(),
+            kind: kind.to_string(),
+            backtrace,
+        }
+    }
+
+    /// Render the bytes of `alloc_id` in rows of 16, like the `follow` command
+    /// does, along with where each row starts.
+    ///
+    /// Only the start of large allocations is rendered; memory views can read
+    /// the rest with [`Self::read_memory`].
+    pub(crate) fn allocation_rows(&self, alloc_id: AllocId) -> Vec<(MemoryRef, String)> {
+        const ROW_SIZE: usize = 16;
+        const MAX_ROWS: usize = 256;
+
+        let Some(alloc) = self.ecx.get_alloc_raw(alloc_id).discard_err() else {
+            return Vec::new();
+        };
+        let size = alloc.len();
+        (0..size.div_ceil(ROW_SIZE).min(MAX_ROWS))
+            .filter_map(|row| {
+                let start = row * ROW_SIZE;
+                let range = start..size.min(start + ROW_SIZE);
+                let bytes = self.render_alloc_bytes(alloc_id, range).discard_err()?;
+                Some((MemoryRef { alloc_id, offset: start.to_u64() }, bytes))
+            })
+            .collect()
+    }
+
+    /// Read up to `count` bytes starting `offset` bytes after `reference`.
+    ///
+    /// Reading stops at the first uninitialized byte, or at the end of the
+    /// allocation, and the bytes after that which cannot be read are counted
+    /// instead, so that a memory view can skip them.
+    pub(crate) fn read_memory(
+        &self,
+        reference: MemoryRef,
+        offset: i64,
+        count: u64,
+    ) -> Result<MemoryRead, String> {
+        let alloc =
+            self.ecx.get_alloc_raw(reference.alloc_id).report_err().map_err(|e| e.to_string())?;
+        let start = i64::try_from(reference.offset)
+            .ok()
+            .and_then(|base| base.checked_add(offset))
+            .ok_or_else(|| "memory offset is out of range".to_string())?;
+        let size = alloc.size().bytes();
+        let Ok(first) = u64::try_from(start) else {
+            // Everything before the allocation is unreadable.
+            let unreadable = count.min(start.unsigned_abs());
+            return Ok(MemoryRead { offset: start, data: Vec::new(), unreadable });
+        };
+        let end = first.saturating_add(count).min(size);
+        if first >= end {
+            return Ok(MemoryRead { offset: start, data: Vec::new(), unreadable: count });
+        }
+
+        let range = alloc_range(Size::from_bytes(first), Size::from_bytes(end - first));
+        let mut chunks = alloc.init_mask().range_as_init_chunks(range);
+        let first_chunk = chunks.next().unwrap();
+        let (data, unreadable) = if first_chunk.is_init() {
+            let chunk = first_chunk.range();
+            let data = alloc
+                .inspect_with_uninit_and_ptr_outside_interpreter(
+                    chunk.start.bytes_usize()..chunk.end.bytes_usize(),
+                )
+                .to_vec();
+            let unreadable = match chunks.next() {
+                Some(uninit) => (uninit.range().end - uninit.range().start).bytes(),
+                // Bytes past the end of the allocation.
+                None => count - (end - first),
+            };
+            (data, unreadable)
+        } else {
+            let chunk = first_chunk.range();
+            (Vec::new(), (chunk.end - chunk.start).bytes())
+        };
+        Ok(MemoryRead { offset: start, data, unreadable })
+    }
+
+    /// Where the reference, raw pointer, or `Box` `op` points to, if that is
+    /// inside a live allocation. Memory hooks must already be suppressed.
+    pub(super) fn pointee_memory(&self, op: &OpTy<'tcx>) -> Option<MemoryRef> {
+        if !self.is_pointer_like(op) {
+            return None;
+        }
+        let imm = self.ecx.read_immediate(op).discard_err()?;
+        let ptr = imm.to_scalar_and_meta().0.to_pointer(&self.ecx);
+        let (alloc_id, offset, _) = self.ecx.ptr_try_get_alloc_id(ptr, 0).ok()?;
+        (self.ecx.get_alloc_info(alloc_id).kind == AllocKind::LiveData)
+            .then_some(MemoryRef { alloc_id, offset: offset.bytes() })
+    }
+}
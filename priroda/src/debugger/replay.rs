@@ -0,0 +1,184 @@
+//! Reverse execution by replaying the program.
+//!
+//! Miri executions are deterministic, so instead of undoing steps, going back
+//! to step `n` means starting the program over and running it for `n` steps.
+//! The only things that make a replay differ from the original run are the
+//! debugger's own actions, so those are logged and repeated at the same step.
+//!
+//! Host state the program observes, like stdin or files outside of isolation,
+//! is not recorded, so programs that depend on it can diverge during a replay.
+
+use std::path::PathBuf;
+
+use miri::*;
+use rustc_middle::mir::Local;
+
+use super::{BreakpointHit, ExecutionResult, PrirodaContext, StepResult};
+
+/// A debugger action that changes how the program executes.
+#[derive(Clone, Copy)]
+pub(super) enum ReplayAction {
+    /// Pin the scheduler to a thread, or unpin it.
+    PinThread(Option<ThreadId>),
+    /// Move a local of the active thread's innermost frame to memory, which
+    /// creates an allocation.
+    MoveToMemory(Local),
+}
+
+/// The step at which `thread` started executing the source line `position`.
+pub(super) struct LineEntry {
+    step: u64,
+    thread: ThreadId,
+    position: (PathBuf, usize),
+}
+
+impl<'tcx> PrirodaContext<'tcx> {
+    /// Log `action`, taken now, so that replays repeat it.
+    pub(super) fn log_replay_action(&mut self, action: ReplayAction) {
+        self.replay_log.push((self.steps, action));
+        // From here on the program may not do what it did before, so its
+        // output is new.
+        self.max_steps = self.steps;
+    }
+
+    /// Record that the active thread started a new source line, if the last
+    /// step made it do that.
+    pub(super) fn record_line_entry(&mut self) {
+        let thread = self.ecx.active_thread();
+        let Some(position) = self.current_source_position() else {
+            return;
+        };
+        let last_entry = self.line_entries.iter().rev().find(|entry| entry.thread == thread);
+        if last_entry.is_some_and(|entry| entry.position == position)
+            || !self.has_user_relevant_frame()
+        {
+            return;
+        }
+        self.line_entries.push(LineEntry { step: self.steps, thread, position });
+    }
+
+    /// Go back to where `thread` started its current source line, or to the
+    /// start of the line before if it is already there.
+    pub(crate) fn step_back(&mut self, thread: ThreadId) -> InterpResult<'tcx, ExecutionResult> {
+        let Some(target) = self
+            .line_entries
+            .iter()
+            .rev()
+            .find(|entry| entry.thread == thread && entry.step < self.steps)
+            .map(|entry| entry.step)
+        else {
+            return interp_ok(ExecutionResult::Rejected {
+                message: "there is no earlier source line to step back to",
+            });
+        };
+        self.rewind_to(target)
+    }
+
+    /// Go back to the last breakpoint or watchpoint that would have stopped
+    /// execution, or to the first source line if there is none.
+    pub(crate) fn reverse_continue(&mut self) -> InterpResult<'tcx, ExecutionResult> {
+        let first_entry = self.line_entries.first().map(|entry| entry.step);
+        let Some(first_entry) = first_entry.filter(|&step| step < self.steps) else {
+            return interp_ok(ExecutionResult::Rejected {
+                message: "there is no earlier source line to go back to",
+            });
+        };
+        let last_hit = match self.replay(self.steps - 1).report_err() {
+            Ok(last_hit) => last_hit,
+            Err(err) => return interp_ok(self.stop_at_error(err)),
+        };
+        // Breakpoints before the first source line are part of the runtime
+        // startup, which forward execution never stops in either.
+        let target = last_hit.map_or(first_entry, |(step, _)| step.max(first_entry));
+        self.rewind_to(target)
+    }
+
+    /// Replay the program up to `target` steps and stop there, as if execution
+    /// had never gone further.
+    fn rewind_to(&mut self, target: u64) -> InterpResult<'tcx, ExecutionResult> {
+        let last_hit = match self.replay(target).report_err() {
+            Ok(last_hit) => last_hit,
+            Err(err) => return interp_ok(self.stop_at_error(err)),
+        };
+
+        // Pins are re-applied by whatever resumes execution next, but moving a
+        // local to memory while stopped here still has to happen in replays.
+        self.replay_log.retain(|&(step, action)| {
+            step < target || (step == target && matches!(action, ReplayAction::MoveToMemory(_)))
+        });
+        let pinned = self.replay_log.iter().rev().find_map(|&(_, action)| {
+            match action {
+                ReplayAction::PinThread(thread) => Some(thread),
+                ReplayAction::MoveToMemory(_) => None,
+            }
+        });
+        if pinned.flatten().is_some() {
+            self.replay_log.push((target, ReplayAction::PinThread(None)));
+        }
+
+        let result = match last_hit {
+            Some((step, hit)) if step == target => StepResult::Breakpoint(hit),
+            _ => StepResult::Step,
+        };
+        interp_ok(ExecutionResult::Stopped(result))
+    }
+
+    /// Start the program over and run it for `target` steps, repeating the
+    /// logged debugger actions and counting breakpoint hits along the way.
+    ///
+    /// Returns the last breakpoint hit that would have stopped execution, and
+    /// the step it happened at.
+    fn replay(&mut self, target: u64) -> InterpResult<'tcx, Option<(u64, BreakpointHit)>> {
+        self.restart();
+        let mut last_hit = None;
+        let mut next_action = 0;
+        loop {
+            while let Some(&(step, action)) = self.replay_log.get(next_action)
+                && step == self.steps
+            {
+                match action {
+                    ReplayAction::PinThread(thread) => self.ecx.set_pinned_thread(thread),
+                    ReplayAction::MoveToMemory(local) => {
+                        let place = self.ecx.local_to_place(local)?;
+                        self.ecx.force_allocation(&place)?;
+                    }
+                }
+                next_action += 1;
+            }
+            if self.steps >= target {
+                break;
+            }
+            self.advance()?;
+            if let Some(hit) = self.watchpoint_hit().or_else(|| self.breakpoint_hit(None)) {
+                last_hit = Some((self.steps, hit));
+            }
+        }
+        self.ecx.set_pinned_thread(None);
+        self.selected_thread = self.ecx.active_thread();
+        interp_ok(last_hit)
+    }
+
+    /// Replace the interpreter with a fresh one that has not run the program
+    /// yet, keeping breakpoints and watchpoints but resetting their hit counts.
+    fn restart(&mut self) {
+        self.ecx = crate::create_ecx(self.ecx.tcx.tcx, &self.config);
+        self.ecx.machine.local_alloc_ids = Some(Default::default());
+        self.update_watched_allocs();
+        for breakpoint in self.breakpoints.values_mut().flat_map(|lines| lines.values_mut()) {
+            breakpoint.hits = 0;
+        }
+        for (_, breakpoint) in &mut self.function_breakpoints {
+            breakpoint.hits = 0;
+        }
+        for watchpoint in &mut self.watchpoints {
+            watchpoint.breakpoint.hits = 0;
+        }
+        self.stack_depths.clear();
+        self.entered_frame = false;
+        self.current_location = None;
+        self.last_location = None;
+        self.exit_code = None;
+        self.steps = 0;
+        self.line_entries.clear();
+    }
+}
@@ -0,0 +1,340 @@
+//! Data watchpoints, which stop execution when a range of memory is accessed.
+//!
+//! Watchpoints are built on the allocation access tracking Miri uses for
+//! `-Zmiri-track-alloc-accesses`: the watched allocations are registered in
+//! `MiriMachine::watched_alloc_ids`, and every access to them is checked against
+//! the watched ranges after the step that made it.
+
+use std::fmt;
+use std::num::NonZeroU64;
+use std::str::FromStr;
+
+use miri::*;
+use rustc_abi::Size;
+use rustc_middle::mir::interpret::AllocId;
+use rustc_middle::mir::{self, VarDebugInfoContents};
+
+use super::{
+    Breakpoint, BreakpointHit, BreakpointId, BreakpointKey, BreakpointOptions, FrameRef,
+    PrirodaContext, ReplayAction, ValuePath, ValueRoot, ValueStep,
+};
+
+/// Which accesses a watchpoint stops on.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum WatchAccess {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+impl WatchAccess {
+    fn matches(self, kind: AccessKind) -> bool {
+        match self {
+            WatchAccess::Read => kind == AccessKind::Read,
+            WatchAccess::Write => kind == AccessKind::Write,
+            WatchAccess::ReadWrite => true,
+        }
+    }
+}
+
+/// `size` bytes of the allocation `alloc_id`, starting at `offset`.
+///
+/// Renders and parses as `alloc<id>+<offset>:<size>`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) struct WatchRange {
+    pub(crate) alloc_id: AllocId,
+    pub(crate) offset: u64,
+    pub(crate) size: u64,
+}
+
+impl WatchRange {
+    fn overlaps(&self, alloc_id: AllocId, range: AllocRange) -> bool {
+        self.alloc_id == alloc_id
+            && self.offset < range.end().bytes()
+            && range.start.bytes() < self.offset + self.size
+    }
+}
+
+impl fmt::Display for WatchRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}+{}:{}", self.alloc_id, self.offset, self.size)
+    }
+}
+
+impl FromStr for WatchRange {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        let (alloc_id, rest) = s.strip_prefix("alloc").ok_or(())?.split_once('+').ok_or(())?;
+        let (offset, size) = rest.split_once(':').ok_or(())?;
+        let alloc_id = AllocId(NonZeroU64::new(alloc_id.parse().map_err(|_| ())?).ok_or(())?);
+        Ok(WatchRange {
+            alloc_id,
+            offset: offset.parse().map_err(|_| ())?,
+            size: size.parse().map_err(|_| ())?,
+        })
+    }
+}
+
+/// What a frontend asks to watch.
+pub(crate) enum WatchTarget {
+    Memory(WatchRange),
+    /// The variable with this source name, or the MIR local `_N`, in the given
+    /// frame, or in the selected frame if there is none.
+    Variable(Option<FrameRef>, String),
+    /// The child with this name of the value at the path, as listed by
+    /// [`PrirodaContext::value_children`].
+    Child(ValuePath, String),
+}
+
+pub(super) struct Watchpoint {
+    range: WatchRange,
+    access: WatchAccess,
+    pub(super) breakpoint: Breakpoint,
+    /// Where the watched allocation is in `MiriMachine::local_alloc_ids`, if the
+    /// program created it, so that it can be found again in a replay.
+    local_index: Option<usize>,
+}
+
+/// The memory access that triggered a watchpoint.
+pub(crate) struct WatchedAccess {
+    pub(crate) kind: AccessKind,
+    pub(crate) alloc_id: AllocId,
+    pub(crate) range: AllocRange,
+}
+
+impl fmt::Display for WatchedAccess {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Same as Miri's report for `-Zmiri-track-alloc-accesses`.
+        write!(f, "{} at {}{}", self.kind, self.alloc_id, self.range)
+    }
+}
+
+impl<'tcx> PrirodaContext<'tcx> {
+    /// Find the memory that `target` refers to.
+    ///
+    /// A local that does not live in memory yet is moved there, like Miri does
+    /// once its address is taken. That is only possible in the innermost frame
+    /// of the active thread.
+    pub(crate) fn watch_range(&mut self, target: WatchTarget) -> Result<WatchRange, String> {
+        let path = match target {
+            WatchTarget::Memory(range) => {
+                let len = self
+                    .ecx
+                    .get_alloc_raw(range.alloc_id)
+                    .report_err()
+                    .map_err(|err| err.to_string())?
+                    .len();
+                if range.size == 0 || range.offset + range.size > len.to_u64() {
+                    return Err(format!(
+                        "{range} is outside of {}, which has {len} bytes",
+                        range.alloc_id
+                    ));
+                }
+                return Ok(range);
+            }
+            WatchTarget::Variable(frame, name) => {
+                let frame = frame.unwrap_or_else(|| self.selected_frame());
+                self.resolve_watched_variable(&name, frame).report_err()
+            }
+            WatchTarget::Child(parent, name) =>
+                self.ecx.run_for_validation_ref(|_| self.child_path(&parent, &name)).report_err(),
+        }
+        .map_err(|err| err.to_string())?;
+
+        if let Some(range) = self.value_range(&path).map_err(|err| err.to_string())? {
+            return Ok(range);
+        }
+        self.move_to_memory(&path).report_err().map_err(|err| err.to_string())?;
+        self.value_range(&path)
+            .map_err(|err| err.to_string())?
+            .ok_or_else(|| bug!("moving a local to memory left it immediate"))
+    }
+
+    /// Resolve `name` like a condition would, but also accept unnamed MIR locals.
+    fn resolve_watched_variable(
+        &self,
+        name: &str,
+        frame: FrameRef,
+    ) -> InterpResult<'tcx, ValuePath> {
+        if let Some(local) = name.strip_prefix('_').and_then(|idx| idx.parse::<usize>().ok()) {
+            let Some(body) = self.frame(frame).map(|frame| frame.body()) else {
+                return Err(miri::err_unsup_format!("stack frame is no longer live")).into();
+            };
+            if local >= body.local_decls.len() {
+                return Err(miri::err_unsup_format!("no local `{name}` in this frame")).into();
+            }
+            let root = ValueRoot::Local(mir::Local::from_usize(local));
+            return interp_ok(ValuePath { frame, root, steps: Vec::new() });
+        }
+        self.resolve_variable(name, frame)
+    }
+
+    /// The path to the child of `parent` named `name`, as rendered by
+    /// `value_children`: a field name or tuple index, `[i]`, or `*`.
+    fn child_path(&self, parent: &ValuePath, name: &str) -> InterpResult<'tcx, ValuePath> {
+        let op = self.value_path_op(parent)?;
+        let step = if name == "*" && self.is_pointer_like(&op) {
+            Some(ValueStep::Deref)
+        } else if let Some(idx) = name.strip_prefix('[').and_then(|name| name.strip_suffix(']')) {
+            idx.parse().ok().map(ValueStep::Index)
+        } else {
+            self.field_by_name(&op, name)?.map(ValueStep::Field)
+        };
+        let Some(step) = step else {
+            return Err(miri::err_unsup_format!("`{}` has no child `{name}`", op.layout.ty)).into();
+        };
+        interp_ok(parent.child(step))
+    }
+
+    /// Where the value at `path` lives, or `None` if it is not in memory.
+    fn value_range(&self, path: &ValuePath) -> Result<Option<WatchRange>, InterpErrorInfo<'tcx>> {
+        self.ecx
+            .run_for_validation_ref(|_| {
+                let op = self.value_path_op(path)?;
+                let Some(mplace) = op.as_mplace_or_imm().left() else {
+                    return interp_ok(None);
+                };
+                let Some((size, _)) = self.ecx.size_and_align_of_val(&mplace)? else {
+                    return Err(miri::err_unsup_format!("cannot watch extern types")).into();
+                };
+                if size == Size::ZERO {
+                    return Err(miri::err_unsup_format!("cannot watch a zero-sized value")).into();
+                }
+                let (alloc_id, offset, _) =
+                    self.ecx.ptr_get_alloc_id(mplace.ptr(), size.bytes().try_into().unwrap())?;
+                interp_ok(Some(WatchRange { alloc_id, offset: offset.bytes(), size: size.bytes() }))
+            })
+            .report_err()
+    }
+
+    /// Move the local that `path` is rooted in to memory.
+    fn move_to_memory(&mut self, path: &ValuePath) -> InterpResult<'tcx> {
+        let active_thread = self.ecx.active_thread();
+        if path.frame != (FrameRef { thread: active_thread, depth: 0 }) {
+            return Err(miri::err_unsup_format!(
+                "this value is not in memory, and can only be moved there from the innermost \
+                 frame of the active thread"
+            ))
+            .into();
+        }
+        let local = match path.root {
+            ValueRoot::Local(local) => local,
+            ValueRoot::DebugInfo(idx) => {
+                let VarDebugInfoContents::Place(place) =
+                    self.ecx.frame().body().var_debug_info[idx].value
+                else {
+                    bug!("value paths are only created for debug info places");
+                };
+                place.local
+            }
+        };
+        let place = self.ecx.local_to_place(local)?;
+        self.ecx.force_allocation(&place)?;
+        // This creates an allocation, which shifts the ids of all later ones.
+        self.log_replay_action(ReplayAction::MoveToMemory(local));
+        interp_ok(())
+    }
+
+    /// Add a watchpoint on `range`.
+    pub(super) fn add_watchpoint(
+        &mut self,
+        range: WatchRange,
+        access: WatchAccess,
+        options: BreakpointOptions,
+    ) -> Result<BreakpointId, String> {
+        let watchpoint = self.new_watchpoint(range, access, options)?;
+        let id = watchpoint.breakpoint.id;
+        self.watchpoints.push(watchpoint);
+        self.update_watched_allocs();
+        Ok(id)
+    }
+
+    /// Replace all watchpoints with the given ones, returning the id of each new
+    /// watchpoint or why it could not be set.
+    pub(crate) fn set_watchpoints(
+        &mut self,
+        watchpoints: Vec<(WatchRange, WatchAccess, BreakpointOptions)>,
+    ) -> Vec<Result<BreakpointId, String>> {
+        self.watchpoints.clear();
+        let ids = watchpoints
+            .into_iter()
+            .map(|(range, access, options)| {
+                let watchpoint = self.new_watchpoint(range, access, options)?;
+                let id = watchpoint.breakpoint.id;
+                self.watchpoints.push(watchpoint);
+                Ok(id)
+            })
+            .collect();
+        self.update_watched_allocs();
+        ids
+    }
+
+    fn new_watchpoint(
+        &mut self,
+        range: WatchRange,
+        access: WatchAccess,
+        options: BreakpointOptions,
+    ) -> Result<Watchpoint, String> {
+        let breakpoint = self.new_breakpoint(options)?;
+        let local_index = self
+            .ecx
+            .machine
+            .local_alloc_ids
+            .as_ref()
+            .and_then(|local_alloc_ids| local_alloc_ids.borrow().index_of(range.alloc_id));
+        Ok(Watchpoint { range, access, breakpoint, local_index })
+    }
+
+    pub(super) fn update_watched_allocs(&mut self) {
+        self.ecx.machine.watched_alloc_ids =
+            self.watchpoints.iter().map(|watchpoint| watchpoint.range.alloc_id).collect();
+    }
+
+    /// Move watchpoints over to the allocations a replay re-created under new ids.
+    pub(super) fn resolve_watched_allocs(&mut self) {
+        if self.watchpoints.is_empty() {
+            return;
+        }
+        let Some(local_alloc_ids) = &self.ecx.machine.local_alloc_ids else {
+            return;
+        };
+        let local_alloc_ids = local_alloc_ids.borrow();
+        let mut moved = false;
+        for watchpoint in &mut self.watchpoints {
+            if let Some(alloc_id) = watchpoint.local_index.and_then(|idx| local_alloc_ids.get(idx))
+                && watchpoint.range.alloc_id != alloc_id
+            {
+                watchpoint.range.alloc_id = alloc_id;
+                moved = true;
+            }
+        }
+        drop(local_alloc_ids);
+        if moved {
+            self.update_watched_allocs();
+        }
+    }
+
+    /// Check the accesses the last step made to watched memory, and return the
+    /// watchpoint that stops execution, if any.
+    pub(super) fn watchpoint_hit(&mut self) -> Option<BreakpointHit> {
+        let accesses = self.ecx.machine.watched_accesses.take();
+        if accesses.is_empty() {
+            return None;
+        }
+        let frame = self.condition_frame(self.ecx.active_thread());
+        for (alloc_id, range, kind) in accesses {
+            for idx in 0..self.watchpoints.len() {
+                let watchpoint = &self.watchpoints[idx];
+                if !watchpoint.access.matches(kind) || !watchpoint.range.overlaps(alloc_id, range) {
+                    continue;
+                }
+                if let Some(hit) = self.check_breakpoint(BreakpointKey::Watch(idx), frame) {
+                    let access = WatchedAccess { kind, alloc_id, range };
+                    return Some(BreakpointHit { access: Some(access), ..hit });
+                }
+            }
+        }
+        None
+    }
+}
@@ -6,8 +6,9 @@ use miri::{InterpResult, interp_ok};
 use rustc_middle::mir::interpret::AllocId;
 
 use crate::debugger::{
-    BreakpointSetResult, CommandResult, DebuggerCommand, ExecutionResult, PrirodaContext,
-    StepResult,
+    BreakpointLocation, BreakpointOptions, BreakpointSetResult, CommandResult, DebuggerCommand,
+    Evaluation, ExceptionDesc, ExecutionResult, PrirodaContext, SourceLocation, StepResult,
+    ValueTree, WatchAccess, WatchTarget,
 };
 
 pub(crate) struct Cli;
@@ -52,12 +53,18 @@ impl Cli {
                     ExecutionResult::Stopped(step) =>
                         match step {
                             StepResult::Step => Self::print_location(session),
-                            StepResult::Breakpoint => {
-                                println!("Hit breakpoint");
+                            StepResult::Breakpoint(hit) => {
+                                match &hit.access {
+                                    Some(access) => println!("Hit watchpoint: {access}"),
+                                    None => println!("Hit breakpoint"),
+                                }
+                                if let Some(err) = hit.condition_error {
+                                    println!("error evaluating breakpoint condition: {err}");
+                                }
                                 Self::print_location(session);
                             }
-                            StepResult::Exception { ref message } =>
-                                Self::print_exception_stop(message, session),
+                            StepResult::Exception(ref exception) =>
+                                Self::print_exception_stop(exception, session),
                         },
                     ExecutionResult::ProgramExited { code } => {
                         println!("program finished with exit code {code}");
@@ -66,11 +73,24 @@ impl Cli {
                 },
             CommandResult::BreakpointResult(res) =>
                 match res {
-                    BreakpointSetResult::Added(path, line) => {
-                        println!("breakpoint added: {}:{}", path.display(), line)
-                    }
-
+                    BreakpointSetResult::Added(location) =>
+                        println!(
+                            "breakpoint added: {}",
+                            Self::render_breakpoint_location(&location)
+                        ),
+                    BreakpointSetResult::Updated(location) =>
+                        println!(
+                            "breakpoint updated: {}",
+                            Self::render_breakpoint_location(&location)
+                        ),
                     BreakpointSetResult::Duplicate => println!("Duplicate breakpoint"),
+                    BreakpointSetResult::Invalid(message) =>
+                        println!("invalid breakpoint: {message}"),
+                },
+            CommandResult::WatchpointResult(res) =>
+                match res {
+                    Ok(range) => println!("watchpoint added: {range}"),
+                    Err(message) => println!("invalid watchpoint: {message}"),
                 },
             CommandResult::Locals(locals_desc) =>
                 if locals_desc.is_empty() {
@@ -100,17 +120,67 @@ impl Cli {
                 },
             CommandResult::SingleLocal(local_desc) =>
                 match local_desc {
-                    Some(local_desc) => {
+                    Some((local_desc, children)) => {
                         println!(
                             "Id: _{}, Ty: {}, Value: {}",
                             local_desc.local.unwrap().index(),
                             local_desc.ty,
                             local_desc.value
                         );
+                        Self::print_value_trees(&children, 1);
                     }
                     None => println!("no local for this id"),
                 },
             CommandResult::Memory(memory) => println!("{memory}"),
+            CommandResult::Allocations(allocs) =>
+                for alloc in &allocs {
+                    println!(
+                        "{}: {} bytes, align {}, {}",
+                        alloc.id, alloc.size, alloc.align, alloc.kind
+                    );
+                    for frame in alloc.backtrace.iter().flatten() {
+                        let location = frame.location.as_ref().map_or_else(
+                            || "<unknown>".to_string(),
+                            |location| Self::render_location(location, session),
+                        );
+                        println!("    allocated in {} at {}", frame.name, location);
+                    }
+                },
+            CommandResult::Evaluation(res) =>
+                match res {
+                    Ok(Evaluation::Value(value)) =>
+                        println!("{}: {} = {}", value.name, value.ty, value.value),
+                    Ok(Evaluation::Text(text)) => println!("{text}"),
+                    Err(message) => println!("error evaluating expression: {message}"),
+                },
+            CommandResult::Threads(threads) =>
+                for thread in &threads {
+                    let marker = if thread.id == session.selected_thread { '*' } else { ' ' };
+                    println!(
+                        "{marker} thread {} \"{}\": {}{}",
+                        thread.id.to_u32(),
+                        thread.name,
+                        thread.state.render(),
+                        if thread.active { " (active)" } else { "" }
+                    );
+                },
+            CommandResult::ThreadSelected(thread) =>
+                match thread {
+                    Some(thread) => {
+                        println!("selected thread {} \"{}\"", thread.id.to_u32(), thread.name);
+                    }
+                    None => println!("no live thread for this id"),
+                },
+            CommandResult::Backtrace(frames) =>
+                for frame in &frames {
+                    let location = frame.location.as_ref().map_or_else(
+                        || "<unknown>".to_string(),
+                        |location| Self::render_location(location, session),
+                    );
+                    println!("#{} {} at {}", frame.frame.depth, frame.name, location);
+                },
+            CommandResult::SchedulerLocking(enabled) =>
+                println!("scheduler locking {}", if enabled { "on" } else { "off" }),
             CommandResult::TerminateSession => {
                 println!("quitting");
                 return interp_ok(false);
@@ -119,8 +189,36 @@ impl Cli {
         interp_ok(true)
     }
 
-    fn print_exception_stop<'tcx>(message: &str, session: &PrirodaContext<'tcx>) {
-        println!("program stopped with error: {message}");
+    fn render_breakpoint_location(location: &BreakpointLocation) -> String {
+        match location {
+            BreakpointLocation::Source(path, line) => format!("{}:{line}", path.display()),
+            BreakpointLocation::Function(name) => format!("fn {name}"),
+        }
+    }
+
+    fn print_value_trees(trees: &[ValueTree], indent: usize) {
+        for tree in trees {
+            let value = &tree.value;
+            println!(
+                "{:indent$}{}: {} = {}",
+                "",
+                value.name,
+                value.ty,
+                value.value,
+                indent = indent * 2
+            );
+            Self::print_value_trees(&tree.children, indent + 1);
+        }
+    }
+
+    fn print_exception_stop<'tcx>(exception: &ExceptionDesc, session: &PrirodaContext<'tcx>) {
+        println!("program stopped with error: {}", exception.message);
+        for note in &exception.notes {
+            println!("note: {}", note.message);
+            if let Some(location) = &note.location {
+                println!("  --> {}", Self::render_location(location, session));
+            }
+        }
         Self::print_location(session);
     }
 
@@ -142,36 +240,96 @@ impl Cli {
             "out" | "stepout" => Some(DebuggerCommand::StepOut),
             "q" | "quit" => Some(DebuggerCommand::TerminateSession),
             "c" | "continue" => Some(DebuggerCommand::Continue),
+            "rs" | "reverse-step" => Some(DebuggerCommand::ReverseStep),
+            "rc" | "reverse-continue" => Some(DebuggerCommand::ReverseContinue),
             "b" | "break" => self.parse_breakpoint(args),
+            "watch" => self.parse_watchpoint(args, WatchAccess::Write),
+            "rwatch" => self.parse_watchpoint(args, WatchAccess::Read),
+            "awatch" => self.parse_watchpoint(args, WatchAccess::ReadWrite),
             "l" | "locals" => Some(DebuggerCommand::ListLocals),
             "p" | "print" => self.parse_print_local(args),
             "f" | "follow" => self.parse_follow(args),
+            "allocs" => Some(DebuggerCommand::Allocations),
+            "e" | "eval" if !args.is_empty() => Some(DebuggerCommand::Evaluate(args.to_string())),
+            "threads" => Some(DebuggerCommand::Threads),
+            "t" | "thread" => self.parse_select_thread(args),
+            "bt" | "backtrace" => Some(DebuggerCommand::Backtrace),
+            "lock" => self.parse_scheduler_locking(args),
             _ => None,
         }
     }
 
     fn print_location<'tcx>(session: &PrirodaContext<'tcx>) {
         match &session.current_location {
-            Some(location) =>
-                if let Some(path) = session.local_path(location) {
-                    println!("{}:{}", path.display(), location.line);
-                } else {
-                    let source_map = session.ecx.tcx.sess.source_map();
-                    println!("{}", source_map.span_to_diagnostic_string(location.span));
-                },
+            Some(location) => println!("{}", Self::render_location(location, session)),
             None => println!("no-location"),
         }
         io::stdout().flush().unwrap();
     }
 
+    fn render_location<'tcx>(location: &SourceLocation, session: &PrirodaContext<'tcx>) -> String {
+        if let Some(path) = session.local_path(location) {
+            format!("{}:{}", path.display(), location.line)
+        } else {
+            let source_map = session.ecx.tcx.sess.source_map();
+            source_map.span_to_diagnostic_string(location.span)
+        }
+    }
+
+    /// Parse `<path>:<line>` or a function path, followed by breakpoint options.
     fn parse_breakpoint(&self, input: &str) -> Option<DebuggerCommand> {
         // FIXME: return a typed CommandError so malformed breakpoint input is
         // distinguishable from an unknown command. Semantic validation belongs
         // in PrirodaContext::set_breakpoint so non-CLI frontends cannot bypass it.
-        let (path, line) = input.rsplit_once(':')?;
-        let line = line.parse().ok()?;
+        let (location, rest) = input.split_once(char::is_whitespace).unwrap_or((input, ""));
+        let options = self.parse_breakpoint_options(rest)?;
+
+        let source_location =
+            location.rsplit_once(':').and_then(|(path, line)| Some((path, line.parse().ok()?)));
+        let location = match source_location {
+            Some((path, line)) => BreakpointLocation::Source(PathBuf::from(path), line),
+            None if location.split("::").all(|segment| {
+                !segment.is_empty() && segment.chars().all(|c| c.is_alphanumeric() || c == '_')
+            }) =>
+                BreakpointLocation::Function(location.to_string()),
+            None => return None,
+        };
+
+        Some(DebuggerCommand::Breakpoint(location, options))
+    }
+
+    /// Parse `<local>` or `alloc<id> <offset> <size>`, followed by breakpoint options.
+    fn parse_watchpoint(&self, input: &str, access: WatchAccess) -> Option<DebuggerCommand> {
+        let (target, rest) = input.split_once(char::is_whitespace).unwrap_or((input, ""));
+        let (target, rest) = if let Some(alloc_id) = target.strip_prefix("alloc") {
+            let mut parts = rest.trim_start().splitn(3, char::is_whitespace);
+            let range = format!("alloc{alloc_id}+{}:{}", parts.next()?, parts.next()?);
+            (WatchTarget::Memory(range.parse().ok()?), parts.next().unwrap_or(""))
+        } else if !target.is_empty() {
+            (WatchTarget::Variable(None, target.to_string()), rest)
+        } else {
+            return None;
+        };
+        let options = self.parse_breakpoint_options(rest)?;
+        Some(DebuggerCommand::Watch(target, access, options))
+    }
 
-        Some(DebuggerCommand::Breakpoint(PathBuf::from(path), line))
+    /// Parse an optional `hit <hit-condition>`, followed by an optional `if <condition>`.
+    fn parse_breakpoint_options(&self, mut input: &str) -> Option<BreakpointOptions> {
+        let mut options = BreakpointOptions::default();
+        if let Some(hit) = input.trim_start().strip_prefix("hit ") {
+            let (hit_condition, after) =
+                hit.trim_start().split_once(char::is_whitespace).unwrap_or((hit.trim_start(), ""));
+            options.hit_condition = Some(hit_condition.to_string());
+            input = after;
+        }
+        let input = input.trim();
+        if let Some(condition) = input.strip_prefix("if ") {
+            options.condition = Some(condition.trim().to_string());
+        } else if !input.is_empty() {
+            return None;
+        }
+        Some(options)
     }
 
     fn parse_print_local(&self, input: &str) -> Option<DebuggerCommand> {
@@ -179,6 +337,19 @@ impl Cli {
         Some(DebuggerCommand::Print(local))
     }
 
+    fn parse_select_thread(&self, input: &str) -> Option<DebuggerCommand> {
+        let thread = input.parse().ok()?;
+        Some(DebuggerCommand::SelectThread(thread))
+    }
+
+    fn parse_scheduler_locking(&self, input: &str) -> Option<DebuggerCommand> {
+        match input {
+            "on" => Some(DebuggerCommand::SchedulerLocking(true)),
+            "off" => Some(DebuggerCommand::SchedulerLocking(false)),
+            _ => None,
+        }
+    }
+
     fn parse_follow(&self, input: &str) -> Option<DebuggerCommand> {
         let mut parts = input.split_whitespace();
         let alloc_id = parts.next()?;
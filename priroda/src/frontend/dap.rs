@@ -3,25 +3,36 @@ use std::net::{TcpListener, TcpStream};
 
 use emmy_dap_types::errors::ServerError;
 use emmy_dap_types::prelude::events::{ExitedEventBody, StoppedEventBody};
-use emmy_dap_types::prelude::requests::SetBreakpointsArguments;
+use emmy_dap_types::prelude::requests::{
+    DataBreakpointInfoArguments, DisassembleArguments, EvaluateArguments, ReadMemoryArguments,
+    SetBreakpointsArguments, SetDataBreakpointsArguments, SetFunctionBreakpointsArguments,
+    StackTraceArguments, VariablesArguments,
+};
 use emmy_dap_types::prelude::responses::{
-    ContinueResponse, ScopesResponse, SetBreakpointsResponse, StackTraceResponse, ThreadsResponse,
-    VariablesResponse,
+    ContinueResponse, DataBreakpointInfoResponse, DisassembleResponse, EvaluateResponse,
+    ExceptionInfoResponse, ReadMemoryResponse, ScopesResponse, SetBreakpointsResponse,
+    SetDataBreakpointsResponse, SetFunctionBreakpointsResponse, StackTraceResponse,
+    ThreadsResponse, VariablesResponse,
 };
 use emmy_dap_types::prelude::types::{
-    Breakpoint as DapBreakpoint, Capabilities, Scope, ScopePresentationhint, Source, StackFrame,
-    StoppedEventReason, Thread, Variable,
+    Breakpoint as DapBreakpoint, Capabilities, DataBreakpointAccessType, DisassembledInstruction,
+    ExceptionBreakMode, Scope, ScopePresentationhint, Source, StackFrame,
+    StackFramePresentationhint, SteppingGranularity, StoppedEventReason, Thread, Variable,
 };
 use emmy_dap_types::prelude::{Command, Event, Request, ResponseBody, Server};
-use miri::{InterpErrorInfo, InterpErrorKind, InterpResult, TerminationInfo, bug};
+use miri::{InterpErrorInfo, InterpErrorKind, InterpResult, TerminationInfo, ThreadId, bug};
+use rustc_middle::mir::interpret::AllocId;
 
-use crate::debugger::{ExecutionResult, LocalDesc, PrirodaContext, StepResult};
+use crate::debugger::{
+    AllocDesc, AllocFrame, BreakpointId, BreakpointOptions, Evaluation, ExceptionDesc,
+    ExecutionResult, FrameRef, InstructionAddress, LocalDesc, MemoryRef, PrirodaContext,
+    SourceLocation, StepResult, ThreadStateDesc, ValueDesc, ValuePath, WatchAccess, WatchTarget,
+};
 
-// Priroda still exposes one interpreted thread and one selected frame to DAP.
-// Keep the ids stable so editor follow-up requests can address the stopped state.
-const THREAD_ID: i64 = 1;
-const STACK_FRAME_ID: i64 = 1;
-const LOCALS_VARIABLES_REFERENCE: i64 = 1;
+// DAP ids must be positive, so Miri thread `n` is DAP thread `n + 1`.
+// Frame ids pack the Miri thread into the high 32 bits and `depth + 1` into
+// the low 32 bits, so the innermost frame of the main thread is 1.
+const FRAME_DEPTH_BITS: u32 = 32;
 
 enum HandlerResponse {
     Success(ResponseBody),
@@ -57,11 +68,35 @@ enum ExecutionOutcome {
     Failed(String),
 }
 
+/// What a `variablesReference` handed out to the editor refers to.
+///
+/// DAP only requires these to stay valid while execution is stopped, so the
+/// table is cleared whenever execution resumes. `variablesReference` `n` names
+/// entry `n - 1`.
+#[derive(Clone, PartialEq, Eq)]
+enum VariablesHandle {
+    /// The locals of a stack frame.
+    Locals(FrameRef),
+    /// The children of a structured value.
+    Children(ValuePath),
+    /// The live allocations.
+    Memory,
+    /// The bytes of an allocation, and where it was created.
+    Allocation(AllocId),
+    /// The frames of an allocation's backtrace.
+    AllocationBacktrace(AllocId),
+}
+
 #[derive(Clone, Copy)]
 enum StepKind {
     In,
+    /// One MIR instruction, for `stepIn` with instruction granularity.
+    Instruction,
     Over,
     Out,
+    Back,
+    /// Not a step, but it reports its stop the same way.
+    ReverseContinue,
 }
 
 /// Debug Adapter Protocol frontend.
@@ -93,6 +128,9 @@ impl Dap {
 struct DapSession<R: Read, W: Write> {
     server: Server<R, W>,
     state: DapState,
+    variables_handles: Vec<VariablesHandle>,
+    /// The error execution is stopped at, answered by `exceptionInfo`.
+    exception: Option<ExceptionInfoResponse>,
 }
 
 impl DapSession<io::StdinLock<'static>, io::StdoutLock<'static>> {
@@ -103,6 +141,8 @@ impl DapSession<io::StdinLock<'static>, io::StdoutLock<'static>> {
                 BufWriter::new(io::stdout().lock()),
             ),
             state: DapState::Fresh,
+            variables_handles: Vec::new(),
+            exception: None,
         }
     }
 }
@@ -126,6 +166,8 @@ impl DapSession<TcpStream, TcpStream> {
         Self {
             server: Server::new(BufReader::new(reader), BufWriter::new(stream)),
             state: DapState::Fresh,
+            variables_handles: Vec::new(),
+            exception: None,
         }
     }
 }
@@ -181,7 +223,7 @@ impl<R: Read, W: Write> DapSession<R, W> {
     }
 
     fn dispatch_request<'tcx>(
-        &self,
+        &mut self,
         request: &Request,
         session: &mut PrirodaContext<'tcx>,
     ) -> Result<HandlerSuccess, &'static str> {
@@ -194,43 +236,80 @@ impl<R: Read, W: Write> DapSession<R, W> {
             Command::Launch(_) => self.handle_launch(),
             Command::Attach(_) => self.handle_attach(),
             Command::ConfigurationDone => self.handle_configuration_done(session),
-            Command::Threads => self.handle_threads(),
-            Command::StackTrace(args) => self.handle_stack_trace(args.thread_id, session),
+            Command::Threads => self.handle_threads(session),
+            Command::StackTrace(args) => self.handle_stack_trace(args, session),
             Command::Scopes(args) => self.handle_scopes(args.frame_id, session),
-            Command::Variables(args) => self.handle_variables(args.variables_reference, session),
-            Command::Continue(args) => self.handle_continue(args.thread_id, session),
+            Command::Variables(args) => self.handle_variables(args, session),
+            Command::Continue(args) =>
+                self.handle_continue(args.thread_id, args.single_thread, session),
             Command::SetBreakpoints(args) => self.handle_set_breakpoints(args, session),
             Command::Next(args) =>
-                self.handle_step(ResponseBody::Next, args.thread_id, session, StepKind::Over),
+                self.handle_step(
+                    ResponseBody::Next,
+                    args.thread_id,
+                    args.single_thread,
+                    session,
+                    StepKind::Over,
+                ),
             Command::StepIn(args) =>
-                self.handle_step(ResponseBody::StepIn, args.thread_id, session, StepKind::In),
+                self.handle_step(
+                    ResponseBody::StepIn,
+                    args.thread_id,
+                    args.single_thread,
+                    session,
+                    match args.granularity {
+                        Some(SteppingGranularity::Instruction) => StepKind::Instruction,
+                        _ => StepKind::In,
+                    },
+                ),
             Command::StepOut(args) =>
-                self.handle_step(ResponseBody::StepOut, args.thread_id, session, StepKind::Out),
+                self.handle_step(
+                    ResponseBody::StepOut,
+                    args.thread_id,
+                    args.single_thread,
+                    session,
+                    StepKind::Out,
+                ),
+            Command::StepBack(args) =>
+                self.handle_step(
+                    ResponseBody::StepBack,
+                    args.thread_id,
+                    args.single_thread,
+                    session,
+                    StepKind::Back,
+                ),
+            Command::ReverseContinue(args) =>
+                self.handle_step(
+                    ResponseBody::ReverseContinue,
+                    args.thread_id,
+                    args.single_thread,
+                    session,
+                    StepKind::ReverseContinue,
+                ),
+            Command::ExceptionInfo(_) => self.handle_exception_info(),
+            Command::SetFunctionBreakpoints(args) =>
+                self.handle_set_function_breakpoints(args, session),
+            Command::DataBreakpointInfo(args) => self.handle_data_breakpoint_info(args, session),
+            Command::SetDataBreakpoints(args) => self.handle_set_data_breakpoints(args, session),
+            Command::Evaluate(args) => self.handle_evaluate(args, session),
+            Command::ReadMemory(args) => self.handle_read_memory(args, session),
+            Command::Disassemble(args) => self.handle_disassemble(args, session),
             Command::Disconnect(_) => self.handle_disconnect(),
             Command::BreakpointLocations(_)
             | Command::Cancel(_)
             | Command::Completions(_)
-            | Command::DataBreakpointInfo(_)
-            | Command::Disassemble(_)
-            | Command::Evaluate(_)
-            | Command::ExceptionInfo(_)
             | Command::Goto(_)
             | Command::GotoTargets(_)
             | Command::LoadedSources
             | Command::Modules(_)
             | Command::Pause(_)
-            | Command::ReadMemory(_)
             | Command::Restart(_)
             | Command::RestartFrame(_)
-            | Command::ReverseContinue(_)
-            | Command::SetDataBreakpoints(_)
             | Command::SetExceptionBreakpoints(_)
             | Command::SetExpression(_)
-            | Command::SetFunctionBreakpoints(_)
             | Command::SetInstructionBreakpoints(_)
             | Command::SetVariable(_)
             | Command::Source(_)
-            | Command::StepBack(_)
             | Command::StepInTargets(_)
             | Command::Terminate(_)
             | Command::TerminateThreads(_)
@@ -264,51 +343,58 @@ impl<R: Read, W: Write> DapSession<R, W> {
     }
 
     fn handle_scopes<'tcx>(
-        &self,
+        &mut self,
         frame_id: i64,
         session: &PrirodaContext<'tcx>,
     ) -> Result<HandlerSuccess, &'static str> {
         self.require_stopped()?;
-        Self::require_frame_id(frame_id)?;
-
-        let (source, line, column) = match &session.current_location {
+        let frame = Self::require_frame_id(frame_id, session)?;
+
+        let location = session
+            .stack_frames(frame.thread)
+            .into_iter()
+            .nth(frame.depth)
+            .and_then(|frame| frame.location);
+        let (source, line, column) = match &location {
             Some(location) => {
-                let source = session.local_path(location).as_ref().map(|path| {
-                    Source {
-                        name: path.file_name().map(|name| name.to_string_lossy().into_owned()),
-                        path: Some(path.display().to_string()),
-                        source_reference: Some(0),
-                        presentation_hint: None,
-                        origin: None,
-                        sources: None,
-                        checksums: None,
-                    }
-                });
-                let line =
-                    location.line.try_into().unwrap_or_else(|_| bug!("source line exceeds i64"));
-                let column = location
-                    .column
-                    .try_into()
-                    .unwrap_or_else(|_| bug!("source column exceeds i64"));
-                (source, Some(line), Some(column))
+                let (line, column) = Self::location_line_column(location);
+                (Self::location_source(location, session), Some(line), Some(column))
             }
             None => (None, None, None),
         };
         Ok(HandlerSuccess {
             response: HandlerResponse::Success(ResponseBody::Scopes(ScopesResponse {
-                scopes: vec![Scope {
-                    name: "Locals".to_string(),
-                    presentation_hint: Some(ScopePresentationhint::Locals),
-                    variables_reference: LOCALS_VARIABLES_REFERENCE,
-                    named_variables: None,
-                    indexed_variables: Some(0),
-                    expensive: false,
-                    source,
-                    line,
-                    column,
-                    end_line: None,
-                    end_column: None,
-                }],
+                scopes: vec![
+                    Scope {
+                        name: "Locals".to_string(),
+                        presentation_hint: Some(ScopePresentationhint::Locals),
+                        variables_reference: self
+                            .variables_reference(VariablesHandle::Locals(frame)),
+                        named_variables: None,
+                        indexed_variables: Some(0),
+                        expensive: false,
+                        source,
+                        line,
+                        column,
+                        end_line: None,
+                        end_column: None,
+                    },
+                    // Not tied to the frame, but scopes are where editors
+                    // look for variables.
+                    Scope {
+                        name: "Memory".to_string(),
+                        presentation_hint: None,
+                        variables_reference: self.variables_reference(VariablesHandle::Memory),
+                        named_variables: None,
+                        indexed_variables: None,
+                        expensive: true,
+                        source: None,
+                        line: None,
+                        column: None,
+                        end_line: None,
+                        end_column: None,
+                    },
+                ],
             })),
             state: None,
             events: Vec::new(),
@@ -317,17 +403,64 @@ impl<R: Read, W: Write> DapSession<R, W> {
     }
 
     fn handle_variables<'tcx>(
-        &self,
-        variables_reference: i64,
+        &mut self,
+        args: &VariablesArguments,
         session: &PrirodaContext<'tcx>,
     ) -> Result<HandlerSuccess, &'static str> {
         self.require_stopped()?;
-        Self::require_variables_reference(variables_reference)?;
 
-        let variables = if variables_reference == LOCALS_VARIABLES_REFERENCE {
-            session.list_locals().into_iter().map(Self::local_to_variable).collect()
-        } else {
-            Vec::new()
+        // Editors page through large arrays with `start` and `count`.
+        let start = args.start.and_then(|start| usize::try_from(start).ok()).unwrap_or(0);
+        let count = args
+            .count
+            .and_then(|count| usize::try_from(count).ok())
+            .filter(|&count| count > 0)
+            .unwrap_or(usize::MAX);
+        let page =
+            |variables: Vec<Variable>| variables.into_iter().skip(start).take(count).collect();
+        let variables = match self.require_variables_reference(args.variables_reference)? {
+            VariablesHandle::Locals(frame) =>
+                page(
+                    session
+                        .list_locals(frame)
+                        .into_iter()
+                        .map(|local| self.local_to_variable(local))
+                        .collect(),
+                ),
+            VariablesHandle::Children(path) =>
+                page(
+                    session
+                        .value_children(&path)
+                        .into_iter()
+                        .map(|value| self.value_to_variable(value))
+                        .collect(),
+                ),
+            // There are hundreds of allocations, so only the requested page
+            // gets references for its children.
+            VariablesHandle::Memory =>
+                session
+                    .live_allocations()
+                    .into_iter()
+                    .skip(start)
+                    .take(count)
+                    .map(|alloc| self.alloc_to_variable(alloc))
+                    .collect(),
+            VariablesHandle::Allocation(alloc_id) =>
+                page(self.allocation_variables(alloc_id, session)),
+            VariablesHandle::AllocationBacktrace(alloc_id) =>
+                page(
+                    session
+                        .allocation(alloc_id)
+                        .and_then(|alloc| alloc.backtrace)
+                        .unwrap_or_default()
+                        .into_iter()
+                        .enumerate()
+                        .map(|(idx, frame)| {
+                            let frame = Self::alloc_frame_text(&frame, session);
+                            Self::leaf_variable(format!("#{idx}"), frame, None)
+                        })
+                        .collect(),
+                ),
         };
 
         Ok(HandlerSuccess {
@@ -341,18 +474,21 @@ impl<R: Read, W: Write> DapSession<R, W> {
     }
 
     fn handle_configuration_done<'tcx>(
-        &self,
+        &mut self,
         session: &mut PrirodaContext<'tcx>,
     ) -> Result<HandlerSuccess, &'static str> {
         self.require_state(DapState::Launched)?;
+        self.variables_handles.clear();
+        self.exception = None;
 
         match Self::execution_outcome(session.stop_at_first_user_location()) {
             ExecutionOutcome::Stopped(result) => {
                 // A normal startup stop is an entry event, but an interpreter
                 // error before the first user location is an exception stop.
                 let stopped = match result {
-                    StepResult::Step => Self::stopped_event_body(StoppedEventReason::Entry),
-                    result => Self::stopped_event_for(result),
+                    StepResult::Step =>
+                        Self::stopped_event_body(StoppedEventReason::Entry, session),
+                    result => self.stopped_event_for(result, session),
                 };
                 Ok(HandlerSuccess {
                     response: HandlerResponse::Success(ResponseBody::ConfigurationDone),
@@ -388,67 +524,81 @@ impl<R: Read, W: Write> DapSession<R, W> {
         }
     }
 
-    /// FIXME: replace this with Miri thread state once Priroda exposes a
-    /// frontend-facing thread model.
-    fn handle_threads(&self) -> Result<HandlerSuccess, &'static str> {
+    fn handle_threads<'tcx>(
+        &self,
+        session: &PrirodaContext<'tcx>,
+    ) -> Result<HandlerSuccess, &'static str> {
         self.reject_after_termination()?;
 
+        // DAP has no notion of terminated threads, so only report live ones.
+        let threads = session
+            .list_threads()
+            .into_iter()
+            .filter_map(|thread| {
+                let name = match thread.state {
+                    ThreadStateDesc::Enabled => thread.name,
+                    ThreadStateDesc::Blocked(_) =>
+                        format!("{} ({})", thread.name, thread.state.render()),
+                    ThreadStateDesc::Terminated => return None,
+                };
+                Some(Thread { id: Self::dap_thread_id(thread.id), name })
+            })
+            .collect();
+
         Ok(HandlerSuccess {
-            response: HandlerResponse::Success(ResponseBody::Threads(ThreadsResponse {
-                threads: vec![Thread { id: THREAD_ID, name: "main".to_string() }],
-            })),
+            response: HandlerResponse::Success(ResponseBody::Threads(ThreadsResponse { threads })),
             state: None,
             events: Vec::new(),
             outcome: HandlerOutcome::Continue,
         })
     }
 
-    /// FIXME: report all frames once Priroda exposes a frontend-facing stack model.
     fn handle_stack_trace<'tcx>(
         &self,
-        thread_id: i64,
-        session: &PrirodaContext<'tcx>,
+        args: &StackTraceArguments,
+        session: &mut PrirodaContext<'tcx>,
     ) -> Result<HandlerSuccess, &'static str> {
         self.require_stopped()?;
-        Self::require_thread_id(thread_id)?;
+        let thread = Self::require_thread_id(args.thread_id, session)?;
 
-        let stack_frames = match &session.current_location {
-            Some(location) => {
-                let path = session.local_path(location);
-                vec![StackFrame {
-                    id: STACK_FRAME_ID,
-                    name: session.current_frame_name().unwrap_or_else(|| "<unknown>".to_string()),
-                    source: path.as_ref().map(|path| {
-                        Source {
-                            name: path.file_name().map(|name| name.to_string_lossy().into_owned()),
-                            path: Some(path.display().to_string()),
-                            source_reference: Some(0),
-                            presentation_hint: None,
-                            origin: None,
-                            sources: None,
-                            checksums: None,
-                        }
-                    }),
-                    line: location
-                        .line
-                        .try_into()
-                        .unwrap_or_else(|_| bug!("source line exceeds i64")),
-                    column: location
-                        .column
-                        .try_into()
-                        .unwrap_or_else(|_| bug!("source column exceeds i64")),
+        let frames = session.stack_frames(thread);
+        let total_frames: i64 =
+            frames.len().try_into().unwrap_or_else(|_| bug!("frame count exceeds i64"));
+        let start = args.start_frame.and_then(|start| usize::try_from(start).ok()).unwrap_or(0);
+        // A missing or zero `levels` asks for all remaining frames.
+        let levels = args
+            .levels
+            .and_then(|levels| usize::try_from(levels).ok())
+            .filter(|&levels| levels > 0)
+            .unwrap_or(usize::MAX);
+        let stack_frames = frames
+            .into_iter()
+            .skip(start)
+            .take(levels)
+            .map(|frame| {
+                let (line, column) =
+                    frame.location.as_ref().map_or((0, 0), Self::location_line_column);
+                StackFrame {
+                    id: Self::dap_frame_id(frame.frame),
+                    name: frame.name,
+                    source: frame
+                        .location
+                        .as_ref()
+                        .and_then(|location| Self::location_source(location, session)),
+                    line,
+                    column,
                     end_line: None,
                     end_column: None,
                     can_restart: None,
-                    instruction_pointer_reference: None,
+                    instruction_pointer_reference: session
+                        .instruction_address(frame.frame)
+                        .map(|address| address.to_string()),
                     module_id: None,
-                    presentation_hint: None,
-                }]
-            }
-            None => Vec::new(),
-        };
-        let total_frames: i64 =
-            stack_frames.len().try_into().unwrap_or_else(|_| bug!("frame count exceeds i64"));
+                    presentation_hint: (!frame.user_relevant)
+                        .then_some(StackFramePresentationhint::Subtle),
+                }
+            })
+            .collect();
         Ok(HandlerSuccess {
             response: HandlerResponse::Success(ResponseBody::StackTrace(StackTraceResponse {
                 stack_frames,
@@ -470,6 +620,16 @@ impl<R: Read, W: Write> DapSession<R, W> {
             response: HandlerResponse::Success(ResponseBody::Initialize(Capabilities {
                 supports_configuration_done_request: Some(true),
                 supports_single_thread_execution_requests: Some(true),
+                supports_exception_info_request: Some(true),
+                supports_function_breakpoints: Some(true),
+                supports_conditional_breakpoints: Some(true),
+                supports_hit_conditional_breakpoints: Some(true),
+                supports_data_breakpoints: Some(true),
+                supports_step_back: Some(true),
+                supports_evaluate_for_hovers: Some(true),
+                supports_read_memory_request: Some(true),
+                supports_disassemble_request: Some(true),
+                supports_stepping_granularity: Some(true),
                 ..Capabilities::default()
             })),
             state: Some(DapState::Initialized),
@@ -479,19 +639,28 @@ impl<R: Read, W: Write> DapSession<R, W> {
     }
 
     fn handle_step<'tcx>(
-        &self,
+        &mut self,
         body: ResponseBody,
         thread_id: i64,
+        single_thread: Option<bool>,
         session: &mut PrirodaContext<'tcx>,
         step: StepKind,
     ) -> Result<HandlerSuccess, &'static str> {
         self.require_stopped()?;
-        Self::require_thread_id(thread_id)?;
+        let thread = Self::require_thread_id(thread_id, session)?;
+        let single_thread = single_thread.unwrap_or(false);
+        self.variables_handles.clear();
+        self.exception = None;
 
         let result = match step {
-            StepKind::In => session.step_in_source(),
-            StepKind::Over => session.step_over_source(),
-            StepKind::Out => session.step_out_source(),
+            StepKind::In => session.step_in_source(thread, single_thread),
+            StepKind::Instruction => session.step_instruction(thread, single_thread),
+            StepKind::Over => session.step_over_source(thread, single_thread),
+            StepKind::Out => session.step_out_source(thread, single_thread),
+            // Going back replays the execution as it was, so there is nothing
+            // to pin.
+            StepKind::Back => session.step_back(thread),
+            StepKind::ReverseContinue => session.reverse_continue(),
         };
 
         match Self::execution_outcome(result) {
@@ -499,7 +668,7 @@ impl<R: Read, W: Write> DapSession<R, W> {
                 Ok(HandlerSuccess {
                     response: HandlerResponse::Success(body),
                     state: Some(DapState::Stopped),
-                    events: vec![Event::Stopped(Self::stopped_event_for(result))],
+                    events: vec![Event::Stopped(self.stopped_event_for(result, session))],
                     outcome: HandlerOutcome::Continue,
                 }),
             ExecutionOutcome::Terminated { code } =>
@@ -530,21 +699,27 @@ impl<R: Read, W: Write> DapSession<R, W> {
     }
 
     fn handle_continue<'tcx>(
-        &self,
+        &mut self,
         thread_id: i64,
+        single_thread: Option<bool>,
         session: &mut PrirodaContext<'tcx>,
     ) -> Result<HandlerSuccess, &'static str> {
         self.require_stopped()?;
-        Self::require_thread_id(thread_id)?;
+        let thread = Self::require_thread_id(thread_id, session)?;
+        self.variables_handles.clear();
+        self.exception = None;
 
+        // Other threads still run once the pinned thread blocks, so from the
+        // editor's point of view all threads have been continued either way.
         let body = ResponseBody::Continue(ContinueResponse { all_threads_continued: Some(true) });
 
-        match Self::execution_outcome(session.continue_execution()) {
+        let pinned_thread = single_thread.unwrap_or(false).then_some(thread);
+        match Self::execution_outcome(session.continue_execution(pinned_thread)) {
             ExecutionOutcome::Stopped(result) =>
                 Ok(HandlerSuccess {
                     response: HandlerResponse::Success(body),
                     state: Some(DapState::Stopped),
-                    events: vec![Event::Stopped(Self::stopped_event_for(result))],
+                    events: vec![Event::Stopped(self.stopped_event_for(result, session))],
                     outcome: HandlerOutcome::Continue,
                 }),
             ExecutionOutcome::Terminated { code } =>
@@ -587,26 +762,30 @@ impl<R: Read, W: Write> DapSession<R, W> {
             );
         };
 
-        let path = std::path::PathBuf::from(path_str);
-        let mut breakpoints = Vec::new();
-        if let Some(ref req_bps) = args.breakpoints {
-            for req_bp in req_bps {
-                let line = usize::try_from(req_bp.line).unwrap();
-                session.set_breakpoint(path.clone(), line);
-                breakpoints.push(DapBreakpoint {
-                    verified: true,
-                    message: None,
+        // Each request replaces all breakpoints previously set in this source.
+        let requested = args.breakpoints.as_deref().unwrap_or_default();
+        let ids = session.set_source_breakpoints(
+            std::path::PathBuf::from(path_str),
+            requested
+                .iter()
+                .map(|bp| {
+                    let line = usize::try_from(bp.line).unwrap();
+                    (line, Self::breakpoint_options(&bp.condition, &bp.hit_condition))
+                })
+                .collect(),
+        );
+        let breakpoints = requested
+            .iter()
+            .zip(ids)
+            .map(|(bp, id)| {
+                DapBreakpoint {
                     source: Some(args.source.clone()),
-                    line: Some(req_bp.line),
-                    column: req_bp.column,
-                    end_line: None,
-                    end_column: None,
-                    id: None,
-                    instruction_reference: None,
-                    offset: None,
-                });
-            }
-        }
+                    line: Some(bp.line),
+                    column: bp.column,
+                    ..Self::dap_breakpoint(id)
+                }
+            })
+            .collect();
 
         Ok(HandlerSuccess {
             response: HandlerResponse::Success(ResponseBody::SetBreakpoints(
@@ -618,6 +797,316 @@ impl<R: Read, W: Write> DapSession<R, W> {
         })
     }
 
+    fn handle_set_function_breakpoints<'tcx>(
+        &self,
+        args: &SetFunctionBreakpointsArguments,
+        session: &mut PrirodaContext<'tcx>,
+    ) -> Result<HandlerSuccess, &'static str> {
+        self.reject_after_termination()?;
+
+        // Each request replaces all previously set function breakpoints.
+        let ids = session.set_function_breakpoints(
+            args.breakpoints
+                .iter()
+                .map(|bp| {
+                    (bp.name.clone(), Self::breakpoint_options(&bp.condition, &bp.hit_condition))
+                })
+                .collect(),
+        );
+        let breakpoints = ids.into_iter().map(Self::dap_breakpoint).collect();
+
+        Ok(HandlerSuccess {
+            response: HandlerResponse::Success(ResponseBody::SetFunctionBreakpoints(
+                SetFunctionBreakpointsResponse { breakpoints },
+            )),
+            state: None,
+            events: Vec::new(),
+            outcome: HandlerOutcome::Continue,
+        })
+    }
+
+    fn handle_data_breakpoint_info<'tcx>(
+        &mut self,
+        args: &DataBreakpointInfoArguments,
+        session: &mut PrirodaContext<'tcx>,
+    ) -> Result<HandlerSuccess, &'static str> {
+        self.require_stopped()?;
+
+        // Without a container, `name` is either memory as `alloc<id>+<offset>:<size>`,
+        // or a variable of the given frame.
+        let target = match args.variables_reference {
+            Some(variables_reference) =>
+                match self.require_variables_reference(variables_reference)? {
+                    VariablesHandle::Locals(frame) =>
+                        Ok(WatchTarget::Variable(Some(frame), args.name.clone())),
+                    VariablesHandle::Children(path) =>
+                        Ok(WatchTarget::Child(path, args.name.clone())),
+                    VariablesHandle::Memory
+                    | VariablesHandle::Allocation(_)
+                    | VariablesHandle::AllocationBacktrace(_) =>
+                        Err("watch memory as `alloc<id>+<offset>:<size>` instead".to_string()),
+                },
+            None =>
+                match args.name.parse() {
+                    Ok(range) => Ok(WatchTarget::Memory(range)),
+                    Err(()) => {
+                        let frame = args
+                            .frame_id
+                            .map(|frame_id| Self::require_frame_id(frame_id, session))
+                            .transpose()?;
+                        Ok(WatchTarget::Variable(frame, args.name.clone()))
+                    }
+                },
+        };
+        let response = match target.and_then(|target| session.watch_range(target)) {
+            Ok(range) =>
+                DataBreakpointInfoResponse {
+                    data_id: Some(range.to_string()),
+                    description: format!(
+                        "{} ({} bytes at {}+{})",
+                        args.name, range.size, range.alloc_id, range.offset
+                    ),
+                    access_types: Some(vec![
+                        DataBreakpointAccessType::Read,
+                        DataBreakpointAccessType::Write,
+                        DataBreakpointAccessType::ReadWrite,
+                    ]),
+                    can_persist: Some(false),
+                },
+            Err(description) =>
+                DataBreakpointInfoResponse {
+                    data_id: None,
+                    description,
+                    access_types: None,
+                    can_persist: None,
+                },
+        };
+
+        Ok(HandlerSuccess {
+            response: HandlerResponse::Success(ResponseBody::DataBreakpointInfo(response)),
+            state: None,
+            events: Vec::new(),
+            outcome: HandlerOutcome::Continue,
+        })
+    }
+
+    fn handle_set_data_breakpoints<'tcx>(
+        &self,
+        args: &SetDataBreakpointsArguments,
+        session: &mut PrirodaContext<'tcx>,
+    ) -> Result<HandlerSuccess, &'static str> {
+        self.reject_after_termination()?;
+
+        // Each request replaces all previously set data breakpoints. The data ids
+        // we hand out are the watched ranges themselves, so they stay valid
+        // after execution resumes.
+        let mut requested = Vec::new();
+        let mut invalid = Vec::new();
+        for (idx, bp) in args.breakpoints.iter().enumerate() {
+            let Ok(range) = bp.data_id.parse() else {
+                invalid.push(idx);
+                continue;
+            };
+            let access = match bp.access_type {
+                Some(DataBreakpointAccessType::Read) => WatchAccess::Read,
+                Some(DataBreakpointAccessType::ReadWrite) => WatchAccess::ReadWrite,
+                Some(DataBreakpointAccessType::Write) | None => WatchAccess::Write,
+            };
+            requested.push((
+                range,
+                access,
+                Self::breakpoint_options(&bp.condition, &bp.hit_condition),
+            ));
+        }
+        let mut ids = session.set_watchpoints(requested).into_iter();
+        let breakpoints = (0..args.breakpoints.len())
+            .map(|idx| {
+                if invalid.contains(&idx) {
+                    Self::dap_breakpoint(Err("unknown dataId".to_string()))
+                } else {
+                    Self::dap_breakpoint(ids.next().unwrap())
+                }
+            })
+            .collect();
+
+        Ok(HandlerSuccess {
+            response: HandlerResponse::Success(ResponseBody::SetDataBreakpoints(
+                SetDataBreakpointsResponse { breakpoints },
+            )),
+            state: None,
+            events: Vec::new(),
+            outcome: HandlerOutcome::Continue,
+        })
+    }
+
+    fn handle_evaluate<'tcx>(
+        &mut self,
+        args: &EvaluateArguments,
+        session: &PrirodaContext<'tcx>,
+    ) -> Result<HandlerSuccess, &'static str> {
+        self.require_stopped()?;
+        // Without a frame, evaluate in the innermost frame of the stopped thread.
+        let frame = match args.frame_id {
+            Some(frame_id) => Self::require_frame_id(frame_id, session)?,
+            None => FrameRef { thread: session.selected_thread, depth: 0 },
+        };
+
+        let (result, type_field, variables_reference, memory_reference) =
+            match session.evaluate(&args.expression, frame) {
+                Ok(Evaluation::Value(value)) =>
+                    (
+                        value.value,
+                        Some(value.ty),
+                        self.children_reference(value.children),
+                        value.pointee.map(|pointee| pointee.to_string()),
+                    ),
+                Ok(Evaluation::Text(text)) => (text, None, 0, None),
+                Err(message) =>
+                    return Ok(HandlerSuccess {
+                        response: HandlerResponse::Error(message),
+                        state: None,
+                        events: Vec::new(),
+                        outcome: HandlerOutcome::Continue,
+                    }),
+            };
+        Ok(HandlerSuccess {
+            response: HandlerResponse::Success(ResponseBody::Evaluate(EvaluateResponse {
+                result,
+                type_field,
+                presentation_hint: None,
+                variables_reference,
+                named_variables: None,
+                indexed_variables: None,
+                memory_reference,
+            })),
+            state: None,
+            events: Vec::new(),
+            outcome: HandlerOutcome::Continue,
+        })
+    }
+
+    fn handle_read_memory<'tcx>(
+        &self,
+        args: &ReadMemoryArguments,
+        session: &PrirodaContext<'tcx>,
+    ) -> Result<HandlerSuccess, &'static str> {
+        self.require_stopped()?;
+        let reference: MemoryRef =
+            args.memory_reference.parse().map_err(|()| "unknown memoryReference")?;
+        let count = u64::try_from(args.count).map_err(|_| "count must not be negative")?;
+
+        let response = match session.read_memory(reference, args.offset.unwrap_or(0), count) {
+            // Addresses are offsets into the referenced allocation.
+            Ok(read) =>
+                HandlerResponse::Success(ResponseBody::ReadMemory(ReadMemoryResponse {
+                    address: read.offset.to_string(),
+                    unreadable_bytes: Some(i64::try_from(read.unreadable).unwrap_or(i64::MAX)),
+                    data: Some(base64_encode(&read.data)),
+                })),
+            Err(message) => HandlerResponse::Error(message),
+        };
+        Ok(HandlerSuccess {
+            response,
+            state: None,
+            events: Vec::new(),
+            outcome: HandlerOutcome::Continue,
+        })
+    }
+
+    fn handle_disassemble<'tcx>(
+        &self,
+        args: &DisassembleArguments,
+        session: &PrirodaContext<'tcx>,
+    ) -> Result<HandlerSuccess, &'static str> {
+        self.require_stopped()?;
+        let reference: InstructionAddress =
+            args.memory_reference.parse().map_err(|()| "unknown memoryReference")?;
+        let count = usize::try_from(args.instruction_count)
+            .map_err(|_| "instructionCount must not be negative")?;
+        // Every MIR instruction counts as one byte.
+        let offset = args.offset.unwrap_or(0).saturating_add(args.instruction_offset.unwrap_or(0));
+
+        let Some(instructions) = session.disassemble(reference, offset, count) else {
+            return Err("memoryReference is not a MIR instruction of a stopped frame");
+        };
+        let instructions = instructions
+            .into_iter()
+            .map(|instruction| {
+                let (line, column) =
+                    instruction.location.as_ref().map(Self::location_line_column).unzip();
+                DisassembledInstruction {
+                    address: instruction.address,
+                    instruction_bytes: None,
+                    instruction: instruction.text,
+                    symbol: instruction.symbol,
+                    location: instruction
+                        .location
+                        .as_ref()
+                        .and_then(|location| Self::location_source(location, session)),
+                    line,
+                    column,
+                    end_line: None,
+                    end_column: None,
+                }
+            })
+            .collect();
+        Ok(HandlerSuccess {
+            response: HandlerResponse::Success(ResponseBody::Disassemble(DisassembleResponse {
+                instructions,
+            })),
+            state: None,
+            events: Vec::new(),
+            outcome: HandlerOutcome::Continue,
+        })
+    }
+
+    fn breakpoint_options(
+        condition: &Option<String>,
+        hit_condition: &Option<String>,
+    ) -> BreakpointOptions {
+        // Editors send empty strings for cleared conditions.
+        let non_empty = |text: &Option<String>| text.clone().filter(|text| !text.trim().is_empty());
+        BreakpointOptions {
+            condition: non_empty(condition),
+            hit_condition: non_empty(hit_condition),
+        }
+    }
+
+    /// Describe a breakpoint the session accepted or rejected.
+    fn dap_breakpoint(id: Result<BreakpointId, String>) -> DapBreakpoint {
+        let (id, message) = match id {
+            Ok(id) =>
+                (Some(id.try_into().unwrap_or_else(|_| bug!("breakpoint id exceeds i64"))), None),
+            Err(message) => (None, Some(message)),
+        };
+        DapBreakpoint {
+            verified: id.is_some(),
+            message,
+            source: None,
+            line: None,
+            column: None,
+            end_line: None,
+            end_column: None,
+            id,
+            instruction_reference: None,
+            offset: None,
+        }
+    }
+
+    fn handle_exception_info(&self) -> Result<HandlerSuccess, &'static str> {
+        self.require_stopped()?;
+        let Some(exception) = &self.exception else {
+            return Err("execution is not stopped at an exception");
+        };
+
+        Ok(HandlerSuccess {
+            response: HandlerResponse::Success(ResponseBody::ExceptionInfo(exception.clone())),
+            state: None,
+            events: Vec::new(),
+            outcome: HandlerOutcome::Continue,
+        })
+    }
+
     fn handle_disconnect(&self) -> Result<HandlerSuccess, &'static str> {
         Ok(HandlerSuccess {
             response: HandlerResponse::Success(ResponseBody::Disconnect),
@@ -667,25 +1156,88 @@ impl<R: Read, W: Write> DapSession<R, W> {
         Ok(())
     }
 
-    fn require_thread_id(thread_id: i64) -> Result<(), &'static str> {
-        if thread_id != THREAD_ID {
-            return Err("unknown threadId");
-        }
-        Ok(())
+    fn dap_thread_id(thread: ThreadId) -> i64 {
+        i64::from(thread.to_u32()) + 1
     }
 
-    fn require_frame_id(frame_id: i64) -> Result<(), &'static str> {
-        if frame_id != STACK_FRAME_ID {
-            return Err("unknown frameId");
-        }
-        Ok(())
+    fn dap_frame_id(frame: FrameRef) -> i64 {
+        let depth: i64 = frame.depth.try_into().unwrap_or_else(|_| bug!("frame depth exceeds i64"));
+        (i64::from(frame.thread.to_u32()) << FRAME_DEPTH_BITS) | (depth + 1)
     }
 
-    fn require_variables_reference(variables_reference: i64) -> Result<(), &'static str> {
-        if variables_reference != LOCALS_VARIABLES_REFERENCE {
-            return Err("unknown variablesReference");
-        }
-        Ok(())
+    fn frame_ref<'tcx>(frame_id: i64, session: &PrirodaContext<'tcx>) -> Option<FrameRef> {
+        let thread = u32::try_from(frame_id >> FRAME_DEPTH_BITS).ok()?;
+        let depth = frame_id & ((1 << FRAME_DEPTH_BITS) - 1);
+        let depth = usize::try_from(depth.checked_sub(1)?).ok()?;
+        let frame = FrameRef { thread: session.live_thread(thread)?, depth };
+        session.is_visible_frame(frame).then_some(frame)
+    }
+
+    fn require_thread_id<'tcx>(
+        thread_id: i64,
+        session: &PrirodaContext<'tcx>,
+    ) -> Result<ThreadId, &'static str> {
+        thread_id
+            .checked_sub(1)
+            .and_then(|thread| u32::try_from(thread).ok())
+            .and_then(|thread| session.live_thread(thread))
+            .ok_or("unknown threadId")
+    }
+
+    fn require_frame_id<'tcx>(
+        frame_id: i64,
+        session: &PrirodaContext<'tcx>,
+    ) -> Result<FrameRef, &'static str> {
+        Self::frame_ref(frame_id, session).ok_or("unknown frameId")
+    }
+
+    fn require_variables_reference(
+        &self,
+        variables_reference: i64,
+    ) -> Result<VariablesHandle, &'static str> {
+        variables_reference
+            .checked_sub(1)
+            .and_then(|idx| usize::try_from(idx).ok())
+            .and_then(|idx| self.variables_handles.get(idx))
+            .cloned()
+            .ok_or("unknown variablesReference")
+    }
+
+    /// Hand out a `variablesReference` for `handle`, reusing the existing one
+    /// if the editor already asked for the same variables during this stop.
+    fn variables_reference(&mut self, handle: VariablesHandle) -> i64 {
+        let idx = match self.variables_handles.iter().position(|existing| *existing == handle) {
+            Some(idx) => idx,
+            None => {
+                self.variables_handles.push(handle);
+                self.variables_handles.len() - 1
+            }
+        };
+        i64::try_from(idx + 1).unwrap_or_else(|_| bug!("variablesReference exceeds i64"))
+    }
+
+    fn location_source<'tcx>(
+        location: &SourceLocation,
+        session: &PrirodaContext<'tcx>,
+    ) -> Option<Source> {
+        session.local_path(location).map(|path| {
+            Source {
+                name: path.file_name().map(|name| name.to_string_lossy().into_owned()),
+                path: Some(path.display().to_string()),
+                source_reference: Some(0),
+                presentation_hint: None,
+                origin: None,
+                sources: None,
+                checksums: None,
+            }
+        })
+    }
+
+    fn location_line_column(location: &SourceLocation) -> (i64, i64) {
+        let line = location.line.try_into().unwrap_or_else(|_| bug!("source line exceeds i64"));
+        let column =
+            location.column.try_into().unwrap_or_else(|_| bug!("source column exceeds i64"));
+        (line, column)
     }
 
     fn execution_outcome<'tcx>(result: InterpResult<'tcx, ExecutionResult>) -> ExecutionOutcome {
@@ -709,28 +1261,80 @@ impl<R: Read, W: Write> DapSession<R, W> {
         ExecutionOutcome::Failed(kind.to_string())
     }
 
-    fn stopped_event_for(result: StepResult) -> StoppedEventBody {
+    fn stopped_event_for<'tcx>(
+        &mut self,
+        result: StepResult,
+        session: &PrirodaContext<'tcx>,
+    ) -> StoppedEventBody {
+        let mut hit_breakpoint_ids = None;
         let (reason, text) = match result {
             StepResult::Step => (StoppedEventReason::Step, None),
-            StepResult::Breakpoint => (StoppedEventReason::Breakpoint, None),
-            StepResult::Exception { message } => (StoppedEventReason::Exception, Some(message)),
+            StepResult::Breakpoint(hit) => {
+                hit_breakpoint_ids = Some(vec![
+                    hit.id.try_into().unwrap_or_else(|_| bug!("breakpoint id exceeds i64")),
+                ]);
+                let reason = match hit.access {
+                    Some(_) => StoppedEventReason::Data,
+                    None => StoppedEventReason::Breakpoint,
+                };
+                let text = match (hit.condition_error, hit.access) {
+                    (Some(err), _) => Some(format!("error evaluating breakpoint condition: {err}")),
+                    (None, Some(access)) => Some(access.to_string()),
+                    (None, None) => None,
+                };
+                (reason, text)
+            }
+            StepResult::Exception(exception) => {
+                let text = Self::exception_text(&exception, session);
+                self.exception = Some(ExceptionInfoResponse {
+                    exception_id: if exception.undefined_behavior {
+                        "Undefined Behavior".to_string()
+                    } else {
+                        "error".to_string()
+                    },
+                    description: Some(text.clone()),
+                    break_mode: ExceptionBreakMode::Always,
+                    details: None,
+                });
+                (StoppedEventReason::Exception, Some(text))
+            }
         };
         StoppedEventBody {
             reason,
             description: None,
-            thread_id: Some(THREAD_ID),
+            thread_id: Some(Self::dap_thread_id(session.selected_thread)),
             preserve_focus_hint: None,
             text,
             all_threads_stopped: Some(true),
-            hit_breakpoint_ids: None,
+            hit_breakpoint_ids,
+        }
+    }
+
+    /// Render an exception like Miri's diagnostic: the message, then one line
+    /// per note, each followed by the location it points at.
+    fn exception_text<'tcx>(exception: &ExceptionDesc, session: &PrirodaContext<'tcx>) -> String {
+        let mut text = exception.message.clone();
+        for note in &exception.notes {
+            text.push_str("\nnote: ");
+            text.push_str(&note.message);
+            if let Some(location) = &note.location
+                && let Some(path) = session.local_path(location)
+            {
+                let (line, column) = Self::location_line_column(location);
+                text.push_str(&format!("\n  --> {}:{line}:{column}", path.display()));
+            }
         }
+        text
     }
 
-    fn stopped_event_body(reason: StoppedEventReason) -> StoppedEventBody {
+    fn stopped_event_body<'tcx>(
+        reason: StoppedEventReason,
+        session: &PrirodaContext<'tcx>,
+    ) -> StoppedEventBody {
         StoppedEventBody {
             reason,
             description: None,
-            thread_id: Some(THREAD_ID),
+            thread_id: Some(Self::dap_thread_id(session.selected_thread)),
             preserve_focus_hint: None,
             text: None,
             all_threads_stopped: Some(true),
@@ -785,21 +1389,121 @@ impl<R: Read, W: Write> DapSession<R, W> {
         }
     }
 
-    fn local_to_variable(local: LocalDesc) -> Variable {
+    fn local_to_variable(&mut self, local: LocalDesc) -> Variable {
         Variable {
             name: Self::local_name(&local),
             value: local.value,
             type_field: Some(local.ty),
             presentation_hint: None,
             evaluate_name: None,
-            // FIXME: add child handles once Priroda can identify places across requests.
+            variables_reference: self.children_reference(local.children),
+            named_variables: None,
+            indexed_variables: None,
+            memory_reference: local.pointee.map(|pointee| pointee.to_string()),
+        }
+    }
+
+    fn value_to_variable(&mut self, value: ValueDesc) -> Variable {
+        Variable {
+            name: value.name,
+            value: value.value,
+            type_field: Some(value.ty),
+            presentation_hint: None,
+            evaluate_name: None,
+            variables_reference: self.children_reference(value.children),
+            named_variables: None,
+            indexed_variables: None,
+            memory_reference: value.pointee.map(|pointee| pointee.to_string()),
+        }
+    }
+
+    fn alloc_to_variable(&mut self, alloc: AllocDesc) -> Variable {
+        let has_children = alloc.size > 0 || alloc.backtrace.is_some();
+        Variable {
+            variables_reference: if has_children {
+                self.variables_reference(VariablesHandle::Allocation(alloc.id))
+            } else {
+                0
+            },
+            ..Self::leaf_variable(
+                alloc.id.to_string(),
+                format!("{} bytes, align {}, {}", alloc.size, alloc.align, alloc.kind),
+                Some(MemoryRef { alloc_id: alloc.id, offset: 0 }),
+            )
+        }
+    }
+
+    /// The children of an allocation in the Memory scope: its backtrace, if
+    /// Miri recorded one, then its bytes.
+    fn allocation_variables<'tcx>(
+        &mut self,
+        alloc_id: AllocId,
+        session: &PrirodaContext<'tcx>,
+    ) -> Vec<Variable> {
+        let backtrace = session.allocation(alloc_id).and_then(|alloc| alloc.backtrace);
+        let backtrace = backtrace.map(|frames| {
+            Variable {
+                variables_reference: self
+                    .variables_reference(VariablesHandle::AllocationBacktrace(alloc_id)),
+                ..Self::leaf_variable(
+                    "backtrace".to_string(),
+                    frames
+                        .first()
+                        .map(|frame| Self::alloc_frame_text(frame, session))
+                        .unwrap_or_default(),
+                    None,
+                )
+            }
+        });
+        let rows = session
+            .allocation_rows(alloc_id)
+            .into_iter()
+            .map(|(row, bytes)| Self::leaf_variable(format!("+{}", row.offset), bytes, Some(row)));
+        backtrace.into_iter().chain(rows).collect()
+    }
+
+    fn alloc_frame_text<'tcx>(frame: &AllocFrame, session: &PrirodaContext<'tcx>) -> String {
+        let Some(location) = &frame.location else {
+            return frame.name.clone();
+        };
+        match session.local_path(location) {
+            Some(path) =>
+                format!(
+                    "{} at {}:{}:{}",
+                    frame.name,
+                    path.display(),
+                    location.line,
+                    location.column
+                ),
+            None =>
+                format!(
+                    "{} at {}",
+                    frame.name,
+                    session.ecx.tcx.sess.source_map().span_to_diagnostic_string(location.span)
+                ),
+        }
+    }
+
+    /// A variable without a type or children.
+    fn leaf_variable(name: String, value: String, memory: Option<MemoryRef>) -> Variable {
+        Variable {
+            name,
+            value,
+            type_field: None,
+            presentation_hint: None,
+            evaluate_name: None,
             variables_reference: 0,
             named_variables: None,
             indexed_variables: None,
-            memory_reference: None,
+            memory_reference: memory.map(|memory| memory.to_string()),
         }
     }
 
+    /// `variablesReference` for a value's children, or 0 if it has none.
+    fn children_reference(&mut self, children: Option<ValuePath>) -> i64 {
+        children.map_or(0, |path| self.variables_reference(VariablesHandle::Children(path)))
+    }
+
     fn local_name(local: &LocalDesc) -> String {
         let source_projection = local.source_projection_str();
 
@@ -816,3 +1520,27 @@ impl<R: Read, W: Write> DapSession<R, W> {
         format!("{local_id}{}", local.storage_projection_str())
     }
 }
+
+/// Encode `bytes` as standard base64 with padding, which is how DAP transfers
+/// memory contents.
+fn base64_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |group, (idx, &byte)| group | u32::from(byte) << (16 - 8 * idx));
+        // A chunk of `n` bytes fills `n + 1` characters; the rest is padding.
+        for idx in 0..4 {
+            if idx <= chunk.len() {
+                let sextet = usize::try_from(group >> (18 - 6 * idx) & 0x3f).unwrap();
+                encoded.push(char::from(ALPHABET[sextet]));
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
@@ -12,6 +12,8 @@ extern crate rustc_structures;
 mod debugger;
 mod frontend;
 
+use std::env;
+
 use debugger::PrirodaContext;
 use miri::*;
 use rustc_driver::Compilation;
@@ -22,7 +24,7 @@ use rustc_session::config::ErrorOutputType;
 use rustc_structures::CrateType;
 
 fn find_sysroot() -> String {
-    std::env::var("MIRI_SYSROOT")
+    env::var("MIRI_SYSROOT")
         .expect("set MIRI_SYSROOT to the path from `cargo miri setup --print-sysroot`")
 }
 
@@ -30,9 +32,25 @@ fn main() {
     let early_dcx = EarlyDiagCtxt::new(ErrorOutputType::default());
     rustc_driver::init_rustc_env_logger(&early_dcx);
 
-    let mut args: Vec<String> = std::env::args().collect();
+    // Take the snapshot before rustc gets a chance to change the environment.
+    let env_snapshot = env::vars_os().collect();
+    let mut args: Vec<String> = env::args().collect();
+    // Like for the Miri driver, everything after `--` is for the interpreted program.
+    let program_args = match args.iter().position(|arg| arg == "--") {
+        Some(idx) => args.split_off(idx).split_off(1),
+        None => Vec::new(),
+    };
     let frontend = Frontend::parse_from_args(&mut args);
 
+    // The `-Zmiri-...` flags configure the interpreter just like they do for Miri.
+    let mut config = MiriConfig { env: env_snapshot, args: program_args, ..MiriConfig::default() };
+    args.retain(|arg| {
+        !config.parse_flag(arg).unwrap_or_else(|err| Frontend::fatal_arg_error(&err))
+    });
+    if let Err(err) = config.check_flags() {
+        Frontend::fatal_arg_error(&err);
+    }
+
     args.splice(1..1, miri::MIRI_DEFAULT_ARGS.iter().map(ToString::to_string));
 
     let sysroot_flag = String::from("--sysroot");
@@ -40,8 +58,7 @@ fn main() {
         args.push(sysroot_flag);
         args.push(find_sysroot());
     }
-    // FIXME: handle the same `-Z` flags that Miri accepts.
-    rustc_driver::run_compiler(&args, &mut PrirodaCompilerCalls::new(frontend));
+    rustc_driver::run_compiler(&args, &mut PrirodaCompilerCalls::new(frontend, config));
 }
 
 /// Frontend selected by Priroda-specific CLI flags.
@@ -56,7 +73,6 @@ impl Frontend {
     fn parse_from_args(args: &mut Vec<String>) -> Self {
         let mut frontend = Frontend::Cli;
         let mut rustc_args = Vec::with_capacity(args.len());
-        let mut parsing_priroda_args = true;
 
         let mut arg_iter = std::mem::take(args).into_iter();
         if let Some(program) = arg_iter.next() {
@@ -64,30 +80,24 @@ impl Frontend {
         }
 
         while let Some(arg) = arg_iter.next() {
-            if parsing_priroda_args {
-                if arg == "--dap" {
-                    if matches!(frontend, Frontend::Cli) {
-                        frontend = Frontend::Dap { port: None };
-                    }
-                    continue;
-                }
-
-                if arg == "--port" {
-                    let port_str = arg_iter
-                        .next()
-                        .unwrap_or_else(|| Self::fatal_arg_error("--port requires a value"));
-                    frontend = Frontend::Dap { port: Some(Self::parse_port(&port_str)) };
-                    continue;
+            if arg == "--dap" {
+                if matches!(frontend, Frontend::Cli) {
+                    frontend = Frontend::Dap { port: None };
                 }
+                continue;
+            }
 
-                if let Some(port_str) = arg.strip_prefix("--port=") {
-                    frontend = Frontend::Dap { port: Some(Self::parse_port(port_str)) };
-                    continue;
-                }
+            if arg == "--port" {
+                let port_str = arg_iter
+                    .next()
+                    .unwrap_or_else(|| Self::fatal_arg_error("--port requires a value"));
+                frontend = Frontend::Dap { port: Some(Self::parse_port(&port_str)) };
+                continue;
+            }
 
-                if arg == "--" {
-                    parsing_priroda_args = false;
-                }
+            if let Some(port_str) = arg.strip_prefix("--port=") {
+                frontend = Frontend::Dap { port: Some(Self::parse_port(port_str)) };
+                continue;
             }
 
             rustc_args.push(arg);
@@ -110,11 +120,12 @@ impl Frontend {
 
 struct PrirodaCompilerCalls {
     frontend: Frontend,
+    config: MiriConfig,
 }
 
 impl PrirodaCompilerCalls {
-    fn new(frontend: Frontend) -> Self {
-        Self { frontend }
+    fn new(frontend: Frontend, config: MiriConfig) -> Self {
+        Self { frontend, config }
     }
 }
 
@@ -128,9 +139,16 @@ impl rustc_driver::Callbacks for PrirodaCompilerCalls {
             tcx.dcx().fatal("priroda only makes sense on bin crates");
         }
 
-        let ecx = create_ecx(tcx);
+        // Same as the Miri driver: the program name comes first, and cargo-miri
+        // tells us where the program would have been started.
+        self.config.args.insert(0, tcx.sess.io.input.filestem().to_string());
+        if let Some(cwd) = env::var_os("MIRI_CWD") {
+            env::set_current_dir(cwd).unwrap();
+        }
+
+        let ecx = create_ecx(tcx, &self.config);
 
-        let mut session = PrirodaContext::new(ecx);
+        let mut session = PrirodaContext::new(ecx, self.config.clone());
         let result = match self.frontend {
             Frontend::Cli => frontend::Cli {}.run_cli_loop(&mut session),
             Frontend::Dap { port } => frontend::Dap { port }.run_dap_loop(&mut session),
@@ -148,11 +166,8 @@ impl rustc_driver::Callbacks for PrirodaCompilerCalls {
     }
 }
 
-fn create_ecx<'tcx>(tcx: TyCtxt<'tcx>) -> MiriInterpCx<'tcx> {
+fn create_ecx<'tcx>(tcx: TyCtxt<'tcx>, config: &MiriConfig) -> MiriInterpCx<'tcx> {
     let (entry_id, entry_type) = miri::entry_fn(tcx);
-    // FIXME: share Miri launcher configuration so interpreted programs receive
-    // their program name, arguments, environment snapshot, and `MIRI_CWD`.
-    let config = MiriConfig::default();
     // FIXME: report interpreter initialization failures instead of panicking.
-    miri::create_ecx(tcx, entry_id, entry_type, &config, None).unwrap()
+    miri::create_ecx(tcx, entry_id, entry_type, config, None).unwrap()
 }
@@ -0,0 +1,9 @@
+//@ normalize-stdout-test: "\{RUSTC_SYSROOT\}[^\n]*\.rs:[0-9]+" -> "$$SYSROOT"
+// Verifies that `allocs` lists the live allocations with their kind and, for
+// those that must not leak, where they were allocated.
+// Keep the breakpoint line number in the .stdin file in sync with this file.
+fn main() {
+    let boxed = Box::new([0u8; 32]);
+    let total: u32 = boxed.iter().map(|&b| u32::from(b)).sum();
+    let _ = total;
+}
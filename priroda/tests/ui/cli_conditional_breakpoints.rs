@@ -0,0 +1,22 @@
+// Verifies conditional, hit-count, and function breakpoints.
+// Keep the breakpoint line numbers in the .stdin file in sync with this file.
+struct Point {
+    x: i32,
+    y: i32,
+}
+
+fn visit(point: &Point) -> i32 {
+    point.x + point.y
+}
+
+fn main() {
+    let mut total = 0;
+    for i in 0..10 {
+        total += i;
+    }
+    let points = [Point { x: 1, y: 2 }, Point { x: 3, y: -4 }, Point { x: 5, y: 6 }];
+    for point in &points {
+        total += visit(point);
+    }
+    let _ = total;
+}
@@ -0,0 +1,13 @@
+//@ normalize-stdout-test: "alloc[0-9]+" -> "ALLOC"
+//@ normalize-stdout-test: "<[0-9]+>" -> "<TAG>"
+// Verifies `eval` of place expressions and printing the borrow state of an
+// allocation from the debugger.
+// Keep the breakpoint line in the .stdin file in sync with this file.
+
+fn main() {
+    let mut pair = (1u8, [2u8, 3]);
+    let first = &mut pair.0;
+    *first += 1;
+    let second = &pair.1;
+    println!("{}", pair.0 + second[1]);
+}
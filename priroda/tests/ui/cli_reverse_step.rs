@@ -0,0 +1,23 @@
+//@ normalize-stdout-test: "alloc[0-9]+" -> "ALLOC"
+#![allow(dangling_pointers_from_locals)]
+// Verifies stepping and continuing backwards to breakpoints and watchpoints,
+// including from an Undefined Behavior stop back into the function that
+// created the dangling pointer, without repeating the program's output.
+// Keep the breakpoint line numbers in the .stdin file in sync with this file.
+fn dangling() -> *const i32 {
+    let x = 42;
+    &x as *const i32
+}
+
+fn main() {
+    println!("start");
+    let mut total = 0;
+    let total_ref = &mut total;
+    for i in 0..3 {
+        *total_ref += i;
+    }
+    println!("total {total}");
+    let ptr = dangling();
+    let value = unsafe { *ptr };
+    println!("{value}");
+}
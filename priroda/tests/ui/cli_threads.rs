@@ -0,0 +1,13 @@
+// Verifies stopping in a spawned thread, listing threads, printing per-thread
+// backtraces, switching the selected thread, and stepping with scheduler locking.
+use std::thread;
+
+fn worker(n: u32) -> u32 {
+    let doubled = n * 2;
+    doubled + 1
+}
+
+fn main() {
+    let handle = thread::spawn(|| worker(20));
+    let _result = handle.join().unwrap();
+}
@@ -0,0 +1,18 @@
+//@ normalize-stdout-test: "alloc[0-9]+" -> "ALLOC"
+// Verifies write, read, and conditional watchpoints on locals and on raw
+// allocation ranges, including a local that is moved to memory to be watched.
+// Keep the breakpoint line numbers in the .stdin file in sync with this file.
+fn corrupt(buffer: &mut [u8; 4]) {
+    buffer[2] = 0xff;
+}
+
+fn main() {
+    let mut buffer = [1u8, 2, 3, 4];
+    let mut counter = 0;
+    for _ in 0..3 {
+        counter += 1;
+    }
+    corrupt(&mut buffer);
+    let sum: u32 = buffer.iter().map(|&b| u32::from(b)).sum();
+    let _ = (sum, counter);
+}
@@ -0,0 +1,15 @@
+//@ compile-flags: --dap
+// Verifies source breakpoints with `condition` and `hitCondition`, rejection
+// of malformed conditions, and function breakpoints matching a generic fn.
+fn describe<T: Copy>(value: T) -> T {
+    value
+}
+
+fn main() {
+    let mut sum = 0u64;
+    for step in 1..=8u64 {
+        sum += step;
+    }
+    describe(sum);
+    describe(true);
+}
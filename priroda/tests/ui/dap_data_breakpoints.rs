@@ -0,0 +1,13 @@
+//@ compile-flags: --dap
+// Verifies dataBreakpointInfo for locals and their children, and that data
+// breakpoints stop with reason `data` on writes and on reads.
+// The dataIds in the .stdin file name the allocation `values` lives in; update
+// them if changes to program startup shift the allocation numbering.
+fn main() {
+    let mut values = [0u32; 3];
+    for i in 0..3 {
+        values[i] = i as u32 * 10;
+    }
+    let picked = values[2];
+    std::hint::black_box(picked);
+}
@@ -0,0 +1,13 @@
+//@ compile-flags: --dap
+// Verifies that a data race is reported as an `exception` stop with Miri's
+// help notes, that `exceptionInfo` describes it, and that the racing thread's
+// frames are still there to inspect.
+use std::thread;
+
+static mut COUNTER: u32 = 0;
+
+fn main() {
+    let handle = thread::spawn(|| unsafe { COUNTER += 1 });
+    unsafe { COUNTER += 1 };
+    handle.join().unwrap();
+}
@@ -0,0 +1,14 @@
+//@ compile-flags: --dap
+// Verifies the MIR disassembly of a frame, including the invalid instructions
+// around the body, and that `stepIn` with instruction granularity takes one
+// MIR step. Keep the breakpoint line in the .stdin file in sync with this file.
+
+fn add(a: u32, b: u32) -> u32 {
+    let sum = a + b;
+    sum
+}
+
+fn main() {
+    let total = add(1, 2);
+    let _ = total;
+}
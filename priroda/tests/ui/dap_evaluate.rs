@@ -0,0 +1,21 @@
+//@ compile-flags: --dap
+//@ normalize-stdout-test: "alloc[0-9]+" -> "ALLOC"
+//@ normalize-stdout-test: "<[0-9]+>" -> "<TAG>"
+// Verifies `evaluate` of place expressions, conditions, and the debugger's
+// versions of `miri_get_alloc_id` and `miri_print_borrow_state`.
+// Keep the breakpoint line in the .stdin file in sync with this file.
+
+struct Point {
+    x: i32,
+    y: i32,
+}
+
+fn main() {
+    let mut values = [10, 20, 30, 40];
+    let boxed = Box::new(Point { x: 1, y: 2 });
+    let slice: &[i32] = &values[1..];
+    let r = &boxed.y;
+    let n = slice.len();
+    values[0] = *r + n as i32;
+    println!("{}", values[0]);
+}
@@ -0,0 +1,18 @@
+//@ compile-flags: --dap
+// Verifies `readMemory` and the Memory scope: memory references of pointers,
+// reads that stop at uninitialized bytes and at the allocation's bounds, and
+// the byte rows and backtrace of an allocation.
+// Keep the breakpoint line and the alloc ids in the .stdin file in sync with
+// this file.
+
+use std::mem::MaybeUninit;
+
+fn main() {
+    let bytes = [1u8, 2, 3, 4];
+    let boxed = Box::new(0x0a0b_0c0du32);
+    let mut pair = MaybeUninit::<[u16; 2]>::uninit();
+    unsafe { pair.as_mut_ptr().cast::<u16>().write(7) };
+    let r = &bytes[1];
+    let p = pair.as_ptr();
+    println!("{} {:?}", *boxed + u32::from(*r), p.is_null());
+}
@@ -0,0 +1,15 @@
+//@ compile-flags: --dap
+// Verifies that a breakpoint hit in a spawned thread is reported on that
+// thread, and that threads, stack traces, scopes, and variables can address
+// both the spawned thread and the blocked main thread.
+use std::thread;
+
+fn worker(n: u32) -> u32 {
+    let doubled = n * 2;
+    doubled + 1
+}
+
+fn main() {
+    let handle = thread::spawn(|| worker(20));
+    let _result = handle.join().unwrap();
+}
@@ -0,0 +1,14 @@
+//@ compile-flags: --dap
+// Verifies `stepBack` and `reverseContinue`, which replay the execution.
+// Keep the breakpoint line in the .stdin file in sync with this file.
+
+fn double(value: i32) -> i32 {
+    value * 2
+}
+
+fn main() {
+    let mut value = 1;
+    value = double(value);
+    value = double(value);
+    let _ = value;
+}
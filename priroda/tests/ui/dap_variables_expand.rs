@@ -0,0 +1,25 @@
+//@ compile-flags: --dap
+// Verifies that structured locals can be expanded through variablesReference:
+// struct fields, the active enum variant, slice elements, and the pointees of
+// `Box`, `&`, and `*const`.
+#[allow(dead_code)]
+struct Point {
+    x: i32,
+    y: i32,
+}
+
+#[allow(dead_code)]
+enum Shape {
+    Dot(Point),
+    Empty,
+}
+
+fn main() {
+    let point = Point { x: 1, y: -2 };
+    let shape = Shape::Dot(Point { x: 3, y: 4 });
+    let boxed = Box::new(7u8);
+    let numbers = [10u16, 20, 30];
+    let slice: &[u16] = &numbers[1..];
+    let raw: *const Point = &point;
+    std::hint::black_box((&point, &shape, &boxed, slice, raw));
+}
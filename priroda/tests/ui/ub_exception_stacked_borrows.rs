@@ -0,0 +1,11 @@
+//@ normalize-stdout-test: "<[0-9]+>" -> "<TAG>"
+//@ normalize-stdout-test: "alloc[0-9]+" -> "ALLOC"
+// Verifies that a Stacked Borrows violation stops execution with Miri's help
+// notes, and that the locals at the violating access can still be inspected.
+fn main() {
+    let mut x = 0u8;
+    let shared = &x as *const u8;
+    let unique = &mut x;
+    *unique = 1;
+    let _val = unsafe { *shared };
+}
@@ -25,6 +25,9 @@ pub struct AddressGenerator {
 }
 
 impl AddressGenerator {
+    /// The range `random_slack` picks from.
+    pub const SLACK: Range<u64> = 0..16;
+
     pub fn new(addr_range: Range<u64>) -> Self {
         Self { next_base_addr: addr_range.start, end: addr_range.end }
     }
@@ -43,9 +46,22 @@ impl AddressGenerator {
         align: Align,
         rng: &mut R,
     ) -> InterpResult<'tcx, u64> {
-        // Leave some space to the previous allocation, to give it some chance to be less aligned.
-        // We ensure that `(self.next_base_addr + slack) % 16` is uniformly distributed.
-        let slack = rng.random_range(0..16);
+        self.generate_with_slack(size, align, Self::random_slack(rng))
+    }
+
+    /// The space to leave to the previous allocation, to give it some chance to be less aligned.
+    /// We ensure that `(self.next_base_addr + slack) % 16` is uniformly distributed.
+    pub fn random_slack<R: Rng>(rng: &mut R) -> u64 {
+        rng.random_range(Self::SLACK)
+    }
+
+    /// Like `generate`, but with a given slack to the previous allocation.
+    pub fn generate_with_slack<'tcx>(
+        &mut self,
+        size: Size,
+        align: Align,
+        slack: u64,
+    ) -> InterpResult<'tcx, u64> {
         // From next_base_addr + slack, round up to adjust for alignment.
         let base_addr =
             self.next_base_addr.checked_add(slack).ok_or_else(|| err_exhaust!(AddressSpaceFull))?;
@@ -6,6 +6,7 @@ mod reuse_pool;
 
 use std::cell::RefCell;
 
+use rand::RngExt;
 use rustc_abi::{Align, Size};
 use rustc_data_structures::fx::{FxHashMap, FxHashSet};
 use rustc_middle::ty::TyCtxt;
@@ -197,10 +198,31 @@ trait EvalContextExtPriv<'tcx>: crate::MiriInterpCxExt<'tcx> {
         }
         // We are not in native lib or genmc mode, so we control the addresses ourselves.
         let (addr_gen, reuse) = global_state.address_generation.as_mut().unwrap();
+        let thread = this.active_thread();
+        // When replaying a schedule, the trace says which address to use.
+        let replayed = this.machine.replay_decision("an allocation", |decision| {
+            match decision {
+                Decision::ReuseAddr(_) | Decision::FreshAddr(_) => Some(decision),
+                _ => None,
+            }
+        });
         let mut rng = this.machine.rng.borrow_mut();
-        if let Some((reuse_addr, clock)) =
-            reuse.take_addr(&mut *rng, info.size, info.align, memory_kind, this.active_thread())
-        {
+        let reused = match replayed {
+            Some(Decision::ReuseAddr(addr)) =>
+                match reuse.take_given_addr(addr, info.size, info.align, memory_kind, thread) {
+                    Some(clock) => Some((addr, clock)),
+                    None => {
+                        this.machine.replay_rejected(format!(
+                            "address {addr:#x} cannot be reused for this allocation"
+                        ));
+                        reuse.take_addr(&mut *rng, info.size, info.align, memory_kind, thread)
+                    }
+                },
+            Some(Decision::FreshAddr(_)) => None,
+            _ => reuse.take_addr(&mut *rng, info.size, info.align, memory_kind, thread),
+        };
+        if let Some((reuse_addr, clock)) = reused {
+            this.machine.record_decision(Decision::ReuseAddr(reuse_addr));
             // If we use some other thread's address, that implies a happens-before.
             if let Some(clock) = clock {
                 this.acquire_clock(&clock)?;
@@ -208,7 +230,17 @@ trait EvalContextExtPriv<'tcx>: crate::MiriInterpCxExt<'tcx> {
             interp_ok(reuse_addr)
         } else {
             // We have to pick a fresh address.
-            let new_addr = addr_gen.generate(info.size, info.align, &mut rng)?;
+            let slack = match replayed {
+                Some(Decision::FreshAddr(slack)) if AddressGenerator::SLACK.contains(&slack) =>
+                    slack,
+                Some(Decision::FreshAddr(slack)) => {
+                    this.machine.replay_rejected(format!("{slack} is not a valid slack"));
+                    AddressGenerator::random_slack(&mut *rng)
+                }
+                _ => AddressGenerator::random_slack(&mut *rng),
+            };
+            this.machine.record_decision(Decision::FreshAddr(slack));
+            let new_addr = addr_gen.generate_with_slack(info.size, info.align, slack)?;
 
             // If we filled up more than half the address space, start aggressively reusing
             // addresses to avoid running out.
@@ -483,8 +515,7 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
 
 impl<'tcx> MiriMachine<'tcx> {
     pub fn free_alloc_id(&mut self, dead_id: AllocId, size: Size, align: Align, kind: MemoryKind) {
-        let global_state = self.alloc_addresses.get_mut();
-        let rng = self.rng.get_mut();
+        let mut global_state = self.alloc_addresses.borrow_mut();
 
         // We can *not* remove this from `base_addr`, since the interpreter design requires that we
         // be able to retrieve an AllocId + offset for any memory access *before* we check if the
@@ -509,7 +540,20 @@ impl<'tcx> MiriMachine<'tcx> {
         // Also remember this address for future reuse.
         if let Some((_addr_gen, reuse)) = global_state.address_generation.as_mut() {
             let thread = self.threads.active_thread();
-            reuse.add_addr(rng, addr, size, align, kind, thread, || {
+            let remember = |rate: f64| {
+                // When replaying a schedule, the trace says whether to remember this address.
+                let remember = self
+                    .replay_decision("a deallocation", |decision| {
+                        match decision {
+                            Decision::RememberAddr(remember) => Some(remember),
+                            _ => None,
+                        }
+                    })
+                    .unwrap_or_else(|| self.rng.borrow_mut().random_bool(rate));
+                self.record_decision(Decision::RememberAddr(remember));
+                remember
+            };
+            reuse.add_addr(remember, addr, size, align, kind, thread, || {
                 // We cannot be in GenMC mode as then `address_generation` is `None`. We cannot use
                 // `self.release_clock` as `self.alloc_addresses` is borrowed.
                 if let Some(data_race) = self.data_race.as_vclocks_ref() {
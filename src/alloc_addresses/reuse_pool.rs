@@ -54,9 +54,10 @@ impl ReusePool {
         &mut self.pool[pool_idx]
     }
 
+    /// `remember` decides whether to add a non-stack address to the pool, given the reuse rate.
     pub fn add_addr(
         &mut self,
-        rng: &mut impl Rng,
+        remember: impl FnOnce(f64) -> bool,
         addr: u64,
         size: Size,
         align: Align,
@@ -66,7 +67,7 @@ impl ReusePool {
     ) {
         // Let's see if we even want to remember this address.
         // We don't remember stack addresses since there's so many of them (so the perf impact is big).
-        if kind == MemoryKind::Stack || !rng.random_bool(self.address_reuse_rate) {
+        if kind == MemoryKind::Stack || !remember(self.address_reuse_rate) {
             return;
         }
         let clock = clock();
@@ -134,4 +135,25 @@ impl ReusePool {
         // No synchronization needed if we reused from the current thread.
         Some((chosen_addr, if chosen_thread == thread { None } else { Some(clock) }))
     }
+
+    /// Like `take_addr`, but takes the given address, if the pool has it for an allocation of this
+    /// size. Used to replay a schedule.
+    pub fn take_given_addr(
+        &mut self,
+        addr: u64,
+        size: Size,
+        align: Align,
+        kind: MemoryKind,
+        thread: ThreadId,
+    ) -> Option<Option<VClock>> {
+        if kind == MemoryKind::Stack {
+            return None;
+        }
+        let subpool = self.subpool(align);
+        let idx = subpool.iter().position(|(other_addr, other_size, _other_thread, _)| {
+            *other_addr == addr && *other_size == size
+        })?;
+        let (_addr, _size, chosen_thread, clock) = subpool.remove(idx);
+        Some(if chosen_thread == thread { None } else { Some(clock) })
+    }
 }
@@ -25,17 +25,13 @@ mod log;
 
 use std::any::Any;
 use std::env;
-use std::num::{NonZero, NonZeroI32};
+use std::num::NonZeroI32;
 use std::ops::Range;
 use std::process::ExitCode;
 use std::rc::Rc;
-use std::str::FromStr;
 use std::sync::atomic::{AtomicU32, Ordering};
 
-use miri::{
-    BacktraceStyle, BorrowTrackerMethod, GenmcConfig, GenmcCtx, MiriConfig, ProvenanceMode,
-    TreeBorrowsParams, ValidationMode, entry_fn, run_genmc_mode,
-};
+use miri::{GenmcCtx, MiriConfig, entry_fn, run_genmc_mode};
 use rustc_codegen_ssa::traits::CodegenBackend;
 use rustc_codegen_ssa::{CompiledModules, CrateInfo, TargetConfig};
 use rustc_data_structures::sync::{self, DynSync};
@@ -192,9 +188,15 @@ impl rustc_driver::Callbacks for MiriCompilerCalls {
             run_many_seeds(many_seeds, |seed| {
                 let mut config = config.clone();
                 config.seed = Some(seed);
+                // Every seed records its own schedule.
+                if let Some(path) = &mut config.record_schedule {
+                    path.as_mut_os_string().push(format!(".{seed}"));
+                }
                 eprintln!("Trying seed: {seed}");
                 miri::eval_entry(tcx, entry_def_id, entry_type, &config, /* genmc_ctx */ None)
             })
+        } else if config.minimize_schedule {
+            miri::minimize_schedule(tcx, entry_def_id, entry_type, &config)
         } else {
             miri::eval_entry(tcx, entry_def_id, entry_type, &config, None)
         };
@@ -373,21 +375,6 @@ fn run_compiler_and_exit(
     })
 }
 
-/// Parses a comma separated list of `T` from the given string:
-/// `<value1>,<value2>,<value3>,...`
-fn parse_comma_list<T: FromStr>(input: &str) -> Result<Vec<T>, T::Err> {
-    input.split(',').map(str::parse::<T>).collect()
-}
-
-/// Parses the input as a float in the range from 0.0 to 1.0 (inclusive).
-fn parse_rate(input: &str) -> Result<f64, &'static str> {
-    match input.parse::<f64>() {
-        Ok(rate) if rate >= 0.0 && rate <= 1.0 => Ok(rate),
-        Ok(_) => Err("must be between `0.0` and `1.0`"),
-        Err(_) => Err("requires a `f64` between `0.0` and `1.0`"),
-    }
-}
-
 /// Parses a seed range
 ///
 /// This function is used for the `-Zmiri-many-seeds` flag. It expects the range in the form
@@ -463,111 +450,6 @@ fn main() -> ExitCode {
             miri_config.args.push(arg);
         } else if arg == "--" {
             after_dashdash = true;
-        } else if arg == "-Zmiri-disable-validation" {
-            miri_config.validation = ValidationMode::No;
-        } else if arg == "-Zmiri-recursive-validation" {
-            miri_config.validation = ValidationMode::Deep;
-        } else if arg == "-Zmiri-disable-stacked-borrows" {
-            miri_config.borrow_tracker = None;
-        } else if arg == "-Zmiri-tree-borrows" {
-            miri_config.borrow_tracker =
-                Some(BorrowTrackerMethod::TreeBorrows(TreeBorrowsParams {
-                    precise_interior_mut: true,
-                    implicit_writes: false,
-                    // We default this to "unique" for now to keep the design space open.
-                    box_custom_allocator_unique: true,
-                }));
-        } else if arg == "-Zmiri-tree-borrows-no-precise-interior-mut" {
-            match &mut miri_config.borrow_tracker {
-                Some(BorrowTrackerMethod::TreeBorrows(params)) => {
-                    params.precise_interior_mut = false;
-                }
-                _ =>
-                    fatal_error!(
-                        "`-Zmiri-tree-borrows` is required before `-Zmiri-tree-borrows-no-precise-interior-mut`"
-                    ),
-            };
-        } else if arg == "-Zmiri-tree-borrows-implicit-writes" {
-            match &mut miri_config.borrow_tracker {
-                Some(BorrowTrackerMethod::TreeBorrows(params)) => {
-                    params.implicit_writes = true;
-                }
-                _ =>
-                    fatal_error!(
-                        "`-Zmiri-tree-borrows` is required before `-Zmiri-tree-borrows-implicit-writes`"
-                    ),
-            };
-        } else if arg == "-Zmiri-tree-borrows-relax-custom-allocator-uniqueness" {
-            match &mut miri_config.borrow_tracker {
-                Some(BorrowTrackerMethod::TreeBorrows(params)) => {
-                    params.box_custom_allocator_unique = false;
-                }
-                _ =>
-                    fatal_error!(
-                        "`-Zmiri-tree-borrows` is required before `-Zmiri-tree-borrows-relax-custom-allocator-uniqueness`"
-                    ),
-            };
-        } else if arg == "-Zmiri-disable-data-race-detector" {
-            miri_config.data_race_detector = false;
-            miri_config.weak_memory_emulation = false;
-        } else if arg == "-Zmiri-disable-alignment-check" {
-            miri_config.check_alignment = miri::AlignmentCheck::None;
-        } else if arg == "-Zmiri-symbolic-alignment-check" {
-            miri_config.check_alignment = miri::AlignmentCheck::Symbolic;
-        } else if arg == "-Zmiri-disable-isolation" {
-            miri_config.isolated_op = miri::IsolatedOp::Allow;
-        } else if arg == "-Zmiri-disable-leak-backtraces" {
-            miri_config.collect_leak_backtraces = false;
-        } else if arg == "-Zmiri-disable-weak-memory-emulation" {
-            miri_config.weak_memory_emulation = false;
-        } else if arg == "-Zmiri-track-weak-memory-loads" {
-            miri_config.track_outdated_loads = true;
-        } else if let Some(param) = arg.strip_prefix("-Zmiri-isolation-error=") {
-            miri_config.isolated_op = match param {
-                "abort" => miri::IsolatedOp::Reject(miri::RejectOpWith::Abort),
-                "hide" => miri::IsolatedOp::Reject(miri::RejectOpWith::NoWarning),
-                "warn" => miri::IsolatedOp::Reject(miri::RejectOpWith::Warning),
-                "warn-nobacktrace" =>
-                    miri::IsolatedOp::Reject(miri::RejectOpWith::WarningWithoutBacktrace),
-                _ =>
-                    fatal_error!(
-                        "-Zmiri-isolation-error must be `abort`, `hide`, `warn`, or `warn-nobacktrace`"
-                    ),
-            };
-        } else if arg == "-Zmiri-ignore-leaks" {
-            miri_config.ignore_leaks = true;
-            miri_config.collect_leak_backtraces = false;
-        } else if arg == "-Zmiri-deterministic-floats" {
-            miri_config.float_nondet = false;
-        } else if arg == "-Zmiri-no-extra-rounding-error" {
-            miri_config.float_rounding_error = miri::FloatRoundingErrorMode::None;
-        } else if arg == "-Zmiri-max-extra-rounding-error" {
-            miri_config.float_rounding_error = miri::FloatRoundingErrorMode::Max;
-        } else if arg == "-Zmiri-no-short-fd-operations" {
-            miri_config.short_fd_operations = false;
-        } else if arg == "-Zmiri-strict-provenance" {
-            miri_config.provenance_mode = ProvenanceMode::Strict;
-        } else if arg == "-Zmiri-permissive-provenance" {
-            miri_config.provenance_mode = ProvenanceMode::Permissive;
-        } else if arg == "-Zmiri-mute-stdout-stderr" {
-            miri_config.mute_stdout_stderr = true;
-        } else if arg == "-Zmiri-retag-fields" {
-            eprintln!(
-                "warning: `-Zmiri-retag-fields` is a NOP and will be removed in a future version of Miri.\n\
-                Field retagging has been on-by-default for a long time."
-            );
-        } else if arg == "-Zmiri-fixed-schedule" {
-            miri_config.fixed_scheduling = true;
-        } else if arg == "-Zmiri-deterministic-concurrency" {
-            miri_config.fixed_scheduling = true;
-            miri_config.address_reuse_cross_thread_rate = 0.0;
-            miri_config.cmpxchg_weak_failure_rate = 0.0;
-            miri_config.weak_memory_emulation = false;
-        } else if let Some(param) = arg.strip_prefix("-Zmiri-seed=") {
-            let seed = param.parse::<u64>().unwrap_or_else(|_| {
-                fatal_error!("-Zmiri-seed must be an integer that fits into u64")
-            });
-            miri_config.seed = Some(seed);
         } else if let Some(param) = arg.strip_prefix("-Zmiri-many-seeds=") {
             let range = parse_range(param).unwrap_or_else(|err| {
                 fatal_error!(
@@ -579,124 +461,16 @@ fn main() -> ExitCode {
             many_seeds = Some(0..64);
         } else if arg == "-Zmiri-many-seeds-keep-going" {
             many_seeds_keep_going = true;
-        } else if let Some(trimmed_arg) = arg.strip_prefix("-Zmiri-genmc") {
-            if let Err(msg) = GenmcConfig::parse_arg(&mut miri_config.genmc_config, trimmed_arg) {
-                fatal_error!("{msg}");
-            }
-        } else if let Some(param) = arg.strip_prefix("-Zmiri-env-forward=") {
-            miri_config.forwarded_env_vars.push(param.to_owned());
-        } else if let Some(param) = arg.strip_prefix("-Zmiri-env-set=") {
-            let Some((name, value)) = param.split_once('=') else {
-                fatal_error!("-Zmiri-env-set requires an argument of the form <name>=<value>");
-            };
-            miri_config.set_env_vars.insert(name.to_owned(), value.to_owned());
-        } else if let Some(param) = arg.strip_prefix("-Zmiri-track-pointer-tag=") {
-            let ids: Vec<u64> = parse_comma_list(param).unwrap_or_else(|err| {
-                fatal_error!("-Zmiri-track-pointer-tag requires a comma separated list of valid `u64` arguments: {err}")
-            });
-            for id in ids.into_iter().map(miri::BorTag::new) {
-                if let Some(id) = id {
-                    miri_config.tracked_pointer_tags.insert(id);
-                } else {
-                    fatal_error!("-Zmiri-track-pointer-tag requires nonzero arguments");
-                }
-            }
-        } else if let Some(param) = arg.strip_prefix("-Zmiri-track-alloc-id=") {
-            let ids = parse_comma_list::<NonZero<u64>>(param).unwrap_or_else(|err| {
-                fatal_error!("-Zmiri-track-alloc-id requires a comma separated list of valid non-zero `u64` arguments: {err}")
-            });
-            miri_config.tracked_alloc_ids.extend(ids.into_iter().map(miri::AllocId));
-        } else if arg == "-Zmiri-track-alloc-accesses" {
-            miri_config.track_alloc_accesses = true;
-        } else if let Some(param) = arg.strip_prefix("-Zmiri-address-reuse-rate=") {
-            miri_config.address_reuse_rate = parse_rate(param)
-                .unwrap_or_else(|err| fatal_error!("-Zmiri-address-reuse-rate {err}"));
-        } else if let Some(param) = arg.strip_prefix("-Zmiri-address-reuse-cross-thread-rate=") {
-            miri_config.address_reuse_cross_thread_rate = parse_rate(param)
-                .unwrap_or_else(|err| fatal_error!("-Zmiri-address-reuse-cross-thread-rate {err}"));
-        } else if let Some(param) = arg.strip_prefix("-Zmiri-compare-exchange-weak-failure-rate=") {
-            miri_config.cmpxchg_weak_failure_rate = parse_rate(param).unwrap_or_else(|err| {
-                fatal_error!("-Zmiri-compare-exchange-weak-failure-rate {err}")
-            });
-        } else if let Some(param) = arg.strip_prefix("-Zmiri-preemption-rate=") {
-            miri_config.preemption_rate = parse_rate(param)
-                .unwrap_or_else(|err| fatal_error!("-Zmiri-preemption-rate {err}"));
-        } else if arg == "-Zmiri-report-progress" {
-            // This makes it take a few seconds between progress reports on my laptop.
-            miri_config.report_progress = Some(1_000_000);
-        } else if let Some(param) = arg.strip_prefix("-Zmiri-report-progress=") {
-            let interval = param.parse::<u32>().unwrap_or_else(|err| {
-                fatal_error!("-Zmiri-report-progress requires a `u32`: {}", err)
-            });
-            miri_config.report_progress = Some(interval);
-        } else if let Some(param) = arg.strip_prefix("-Zmiri-provenance-gc=") {
-            let interval = param.parse::<u32>().unwrap_or_else(|err| {
-                fatal_error!("-Zmiri-provenance-gc requires a `u32`: {}", err)
-            });
-            miri_config.gc_interval = interval;
-        } else if let Some(param) = arg.strip_prefix("-Zmiri-measureme=") {
-            miri_config.measureme_out = Some(param.to_string());
-        } else if let Some(param) = arg.strip_prefix("-Zmiri-backtrace=") {
-            miri_config.backtrace_style = match param {
-                "0" => BacktraceStyle::Off,
-                "1" => BacktraceStyle::Short,
-                "full" => BacktraceStyle::Full,
-                _ => fatal_error!("-Zmiri-backtrace may only be 0, 1, or full"),
-            };
-        } else if let Some(param) = arg.strip_prefix("-Zmiri-native-lib=") {
-            let filename = param.to_string();
-            let file_path = std::path::Path::new(&filename);
-            if file_path.exists() {
-                // For directories, nonrecursively add all normal files inside
-                if let Ok(dir) = file_path.read_dir() {
-                    for lib in dir.filter_map(|res| res.ok()) {
-                        if lib.file_type().unwrap().is_file() {
-                            miri_config.native_lib.push(lib.path().to_owned());
-                        }
-                    }
-                } else {
-                    miri_config.native_lib.push(filename.into());
-                }
-            } else {
-                fatal_error!("-Zmiri-native-lib `{}` does not exist", filename);
-            }
-        } else if arg == "-Zmiri-native-lib-enable-tracing" {
-            miri_config.native_lib_enable_tracing = true;
-        } else if let Some(param) = arg.strip_prefix("-Zmiri-num-cpus=") {
-            let num_cpus = param
-                .parse::<u32>()
-                .unwrap_or_else(|err| fatal_error!("-Zmiri-num-cpus requires a `u32`: {}", err));
-            if !(1..=miri::MAX_CPUS).contains(&usize::try_from(num_cpus).unwrap()) {
-                fatal_error!("-Zmiri-num-cpus must be in the range 1..={}", miri::MAX_CPUS);
-            }
-            miri_config.num_cpus = num_cpus;
-        } else if let Some(param) = arg.strip_prefix("-Zmiri-force-page-size=") {
-            let page_size = param.parse::<u64>().unwrap_or_else(|err| {
-                fatal_error!("-Zmiri-force-page-size requires a `u64`: {}", err)
-            });
-            // Convert from kilobytes to bytes.
-            let page_size = if page_size.is_power_of_two() {
-                page_size * 1024
-            } else {
-                fatal_error!("-Zmiri-force-page-size requires a power of 2: {page_size}");
-            };
-            miri_config.page_size = Some(page_size);
-        } else if let Some(param) = arg.strip_prefix("-Zmiri-user-relevant-crates=") {
-            miri_config.user_relevant_crates.extend(param.split(',').map(|s| s.to_owned()));
+        } else if miri_config.parse_flag(&arg).unwrap_or_else(|err| fatal_error!("{err}")) {
+            // Handled by `parse_flag`.
         } else {
             // Forward to rustc.
             rustc_args.push(arg);
         }
     }
 
-    // Disabling validation also disables aliasing checks (as retags are done during validation).
-    if miri_config.validation == ValidationMode::No {
-        miri_config.borrow_tracker = None;
-    }
-
-    // Native calls and strict provenance are not compatible.
-    if !miri_config.native_lib.is_empty() && miri_config.provenance_mode == ProvenanceMode::Strict {
-        fatal_error!("strict provenance is not compatible with calling native functions");
+    if let Err(err) = miri_config.check_flags() {
+        fatal_error!("{err}");
     }
     // Native calls and many-seeds are an "interesting" combination.
     if !miri_config.native_lib.is_empty() && many_seeds.is_some() {
@@ -705,23 +479,13 @@ fn main() -> ExitCode {
             so if the native library has global state, it will leak across execution bundaries"
         );
     }
+    if miri_config.minimize_schedule && many_seeds.is_some() {
+        fatal_error!("`-Zmiri-minimize-schedule` cannot be used with `-Zmiri-many-seeds`");
+    }
     // You can set either one seed or many.
     if many_seeds.is_some() && miri_config.seed.is_some() {
         fatal_error!("Only one of `-Zmiri-seed` and `-Zmiri-many-seeds can be set");
     }
-    // We cannot emulate weak memory without the data race detector.
-    if miri_config.weak_memory_emulation && !miri_config.data_race_detector {
-        fatal_error!(
-            "Weak memory emulation cannot be enabled when the data race detector is disabled"
-        );
-    };
-
-    // Validate GenMC settings.
-    if miri_config.genmc_config.is_some()
-        && let Err(err) = GenmcConfig::validate(&mut miri_config)
-    {
-        fatal_error!("Invalid settings: {err}");
-    }
 
     // Ensure we have parallelism for many-seeds mode.
     if many_seeds.is_some() && !rustc_args.iter().any(|arg| arg.starts_with("-Zthreads=")) {
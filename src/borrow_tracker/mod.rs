@@ -348,15 +348,35 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
 
     fn print_borrow_state(&mut self, alloc_id: AllocId, show_unnamed: bool) -> InterpResult<'tcx> {
         let this = self.eval_context_mut();
-        let Some(borrow_tracker) = &mut this.machine.borrow_tracker else {
+        let Some(state) = this.render_borrow_state(alloc_id, show_unnamed)? else {
             eprintln!("attempted to print borrow state, but no borrow state is being tracked");
             return interp_ok(());
         };
-        let method = borrow_tracker.get_mut().borrow_tracker_method;
-        match method {
-            BorrowTrackerMethod::StackedBorrows => this.print_stacks(alloc_id),
-            BorrowTrackerMethod::TreeBorrows { .. } => this.print_tree(alloc_id, show_unnamed),
+        // Tests expect stacks on stdout but trees on stderr.
+        match this.machine.borrow_tracker.as_ref().unwrap().borrow().borrow_tracker_method {
+            BorrowTrackerMethod::StackedBorrows => print!("{state}"),
+            BorrowTrackerMethod::TreeBorrows { .. } => eprint!("{state}"),
         }
+        interp_ok(())
+    }
+
+    /// Render the borrow state of `alloc_id` the way `miri_print_borrow_state` prints it.
+    /// Returns `None` if no borrow state is being tracked.
+    fn render_borrow_state(
+        &self,
+        alloc_id: AllocId,
+        show_unnamed: bool,
+    ) -> InterpResult<'tcx, Option<String>> {
+        let this = self.eval_context_ref();
+        let Some(borrow_tracker) = &this.machine.borrow_tracker else {
+            return interp_ok(None);
+        };
+        let method = borrow_tracker.borrow().borrow_tracker_method;
+        let state = match method {
+            BorrowTrackerMethod::StackedBorrows => this.render_stacks(alloc_id)?,
+            BorrowTrackerMethod::TreeBorrows { .. } => this.render_tree(alloc_id, show_unnamed)?,
+        };
+        interp_ok(Some(state))
     }
 
     fn on_stack_pop(
@@ -924,21 +924,22 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         interp_ok(())
     }
 
-    fn print_stacks(&mut self, alloc_id: AllocId) -> InterpResult<'tcx> {
-        let this = self.eval_context_mut();
+    fn render_stacks(&self, alloc_id: AllocId) -> InterpResult<'tcx, String> {
+        let this = self.eval_context_ref();
         let alloc_extra = this.get_alloc_extra(alloc_id)?;
         let stacks = alloc_extra.borrow_tracker_sb().borrow();
+        let mut out = String::new();
         for (range, stack) in stacks.stacks.iter_all() {
-            print!("{range:?}: [");
+            write!(out, "{range:?}: [").unwrap();
             if let Some(bottom) = stack.unknown_bottom() {
-                print!(" unknown-bottom(..{bottom:?})");
+                write!(out, " unknown-bottom(..{bottom:?})").unwrap();
             }
             for i in 0..stack.len() {
                 let item = stack.get(i).unwrap();
-                print!(" {:?}{:?}", item.perm(), item.tag());
+                write!(out, " {:?}{:?}", item.perm(), item.tag()).unwrap();
             }
-            println!(" ]");
+            writeln!(out, " ]").unwrap();
         }
-        interp_ok(())
+        interp_ok(out)
     }
 }
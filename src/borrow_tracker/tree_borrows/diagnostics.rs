@@ -1,4 +1,4 @@
-use std::fmt;
+use std::fmt::{self, Write};
 use std::ops::Range;
 
 use rustc_data_structures::fx::FxHashMap;
@@ -693,7 +693,7 @@ impl DisplayRepr {
             }
         }
     }
-    fn print(
+    fn render(
         main_root: &Option<DisplayRepr>,
         wildcard_subtrees: &[DisplayRepr],
         fmt: &DisplayFmt,
@@ -701,6 +701,7 @@ impl DisplayRepr {
         protected_tags: &FxHashMap<BorTag, ProtectorKind>,
         ranges: Vec<Range<u64>>,
         print_warning: bool,
+        out: &mut String,
     ) {
         let mut block = Vec::new();
         // Push the header and compute the required paddings for the body.
@@ -778,14 +779,14 @@ impl DisplayRepr {
                     block_width
                 }
             };
-            eprintln!("{}", char_repeat(wr.top, max_width));
+            writeln!(out, "{}", char_repeat(wr.top, max_width)).unwrap();
             if print_warning {
-                eprintln!("{}", wr.warning_text,);
+                writeln!(out, "{}", wr.warning_text).unwrap();
             }
             for line in block {
-                eprintln!("{line}");
+                writeln!(out, "{line}").unwrap();
             }
-            eprintln!("{}", char_repeat(wr.bot, max_width));
+            writeln!(out, "{}", char_repeat(wr.bot, max_width)).unwrap();
         }
 
         // Here is the function that does the heavy lifting
@@ -876,13 +877,14 @@ const DEFAULT_FORMATTER: DisplayFmt = DisplayFmt {
     accessed: DisplayFmtAccess { yes: " ", no: "?", meh: "-" },
 };
 
-impl<'tcx> Tree {
-    /// Display the contents of the tree.
-    pub fn print_tree(
+impl Tree {
+    /// Render the contents of the tree for display.
+    pub fn render_tree(
         &self,
         protected_tags: &FxHashMap<BorTag, ProtectorKind>,
         show_unnamed: bool,
-    ) -> InterpResult<'tcx> {
+    ) -> String {
+        let mut out = String::new();
         let mut indenter = DisplayIndent::new();
         let ranges = self.locations.iter_all().map(|(range, _loc)| range).collect::<Vec<_>>();
         let main_tree = DisplayRepr::from(self, self.roots[0], show_unnamed);
@@ -892,12 +894,14 @@ impl<'tcx> Tree {
             .collect::<Vec<_>>();
 
         if main_tree.is_none() && wildcard_subtrees.is_empty() {
-            eprintln!(
+            writeln!(
+                out,
                 "This allocation does not contain named tags. Use `miri_print_borrow_state(_, true)` to also print unnamed tags."
-            );
+            )
+            .unwrap();
         }
 
-        DisplayRepr::print(
+        DisplayRepr::render(
             &main_tree,
             wildcard_subtrees.as_slice(),
             &DEFAULT_FORMATTER,
@@ -905,7 +909,8 @@ impl<'tcx> Tree {
             protected_tags,
             ranges,
             /* print warning message about tags not shown */ !show_unnamed,
+            &mut out,
         );
-        interp_ok(())
+        out
     }
 }
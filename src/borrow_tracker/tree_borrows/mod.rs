@@ -562,13 +562,13 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         interp_ok(())
     }
 
-    /// Display the tree.
-    fn print_tree(&mut self, alloc_id: AllocId, show_unnamed: bool) -> InterpResult<'tcx> {
-        let this = self.eval_context_mut();
+    /// Render the tree for display.
+    fn render_tree(&self, alloc_id: AllocId, show_unnamed: bool) -> InterpResult<'tcx, String> {
+        let this = self.eval_context_ref();
         let alloc_extra = this.get_alloc_extra(alloc_id)?;
         let tree_borrows = alloc_extra.borrow_tracker_tb().borrow();
         let borrow_tracker = &this.machine.borrow_tracker.as_ref().unwrap().borrow();
-        tree_borrows.print_tree(&borrow_tracker.protected_tags, show_unnamed)
+        interp_ok(tree_borrows.render_tree(&borrow_tracker.protected_tags, show_unnamed))
     }
 
     /// Give a name to the pointer, usually the name it has in the source code (for debugging).
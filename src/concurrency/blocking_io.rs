@@ -40,12 +40,15 @@ impl BlockingIoInterest {
     /// Check whether the [`Readiness`] fulfills this blocking I/O interest.
     /// This function also returns `true` if the error readiness is set
     /// even when the requested interest might not be fulfilled.
+    /// A closed direction counts as ready since operations on it no longer block.
+    /// (Pipes report only that when their peer is gone, not readability.)
     fn is_fulfilled_by(&self, readiness: &Readiness) -> bool {
+        let readable = readiness.readable || readiness.read_closed;
+        let writable = readiness.writable || readiness.write_closed;
         match self {
-            BlockingIoInterest::Read => readiness.readable || readiness.error,
-            BlockingIoInterest::Write => readiness.writable || readiness.error,
-            BlockingIoInterest::ReadWrite =>
-                readiness.readable || readiness.writable || readiness.error,
+            BlockingIoInterest::Read => readable || readiness.error,
+            BlockingIoInterest::Write => writable || readiness.error,
+            BlockingIoInterest::ReadWrite => readable || writable || readiness.error,
         }
     }
 }
@@ -151,16 +154,18 @@ impl BlockingIoManager {
                 };
 
                 assert_eq!(fd.id(), fd_id);
-                // Update the readiness of the source.
-                *fd.get_readiness_mut() |= Readiness::from(event);
                 // Put FD into `event_fds` list.
-                fd
+                (fd, Readiness::from(event))
             })
             .collect::<Vec<_>>();
 
         // Update the readiness for all source file descriptions which received an event. Also,
         // unblock the threads which are blocked on such a source and whose interests are now fulfilled.
-        for fd in event_fds.into_iter() {
+        for (fd, readiness) in event_fds.into_iter() {
+            // Update the readiness of the source. We only do this right before announcing the
+            // change, since announcing it can wake up threads that expect the readiness of all
+            // other watched file descriptions to be announced already.
+            *fd.get_readiness_mut() |= readiness;
             // Update readiness for the `fd` source. This is no a "release" event since it was
             // not triggered by the current thread, it was triggered by the outside world.
             ecx.update_fd_readiness(fd.clone(), ReadinessUpdateFlags::NO_RELEASE_CLOCK)?;
@@ -221,6 +226,22 @@ impl BlockingIoManager {
             .unwrap_or_else(|_| panic!("Source should not already be registered"));
     }
 
+    /// Remove a source file description from the blocking I/O poll, e.g. because it is about to
+    /// be switched to blocking mode. Returns `false` and does nothing if some thread is blocked
+    /// on the source.
+    pub fn deregister(&mut self, source_fd: FileDescriptionRef<dyn SourceFileDescription>) -> bool {
+        let poll =
+            self.poll.as_ref().expect("Blocking I/O should not be called with isolation enabled");
+
+        let source = self.sources.get(&source_fd.id()).expect("Source should be registered");
+        if !source.blocked_threads.is_empty() {
+            return false;
+        }
+        source_fd.with_source(&mut |source| poll.registry().deregister(source)).unwrap();
+        self.sources.remove(&source_fd.id());
+        true
+    }
+
     /// Add a new blocked thread to a registered source. The thread gets unblocked
     /// once its [`BlockingIoInterest`] is fulfilled when calling
     /// [`BlockingIoManager::poll`].
@@ -927,7 +927,17 @@ pub trait EvalContextExt<'tcx>: MiriInterpCxExt<'tcx> {
         let success_rate = 1.0 - this.machine.cmpxchg_weak_failure_rate;
         let cmpxchg_success = eq.to_scalar().to_bool()?
             && if can_fail_spuriously {
-                this.machine.rng.get_mut().random_bool(success_rate)
+                let fail = this
+                    .machine
+                    .replay_decision("a `compare_exchange_weak`", |decision| {
+                        match decision {
+                            Decision::CmpxchgWeakFail(fail) => Some(fail),
+                            _ => None,
+                        }
+                    })
+                    .unwrap_or_else(|| !this.machine.rng.get_mut().random_bool(success_rate));
+                this.machine.record_decision(Decision::CmpxchgWeakFail(fail));
+                !fail
             } else {
                 true
             };
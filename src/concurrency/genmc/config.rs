@@ -96,6 +96,10 @@ impl GenmcConfig {
             return Err("native-lib not supported in GenMC mode.");
         } else if miri_config.isolated_op != IsolatedOp::Reject(RejectOpWith::Abort) {
             return Err("Cannot disable isolation in GenMC mode");
+        } else if miri_config.pct_scheduling.is_some() {
+            return Err("PCT scheduling not supported in GenMC mode.");
+        } else if miri_config.record_schedule.is_some() || miri_config.replay_schedule.is_some() {
+            return Err("Recording and replaying schedules is not supported in GenMC mode.");
         }
 
         // Adjust settings where needed.
@@ -0,0 +1,277 @@
+//! Detection of lock-order inversions for `-Zmiri-check-lock-order`.
+//!
+//! A deadlock between threads that take several locks only happens under some schedules, so most
+//! executions do not run into it. Like ThreadSanitizer's deadlock detector, we therefore keep a
+//! graph with an edge from lock `A` to lock `B` whenever a thread acquires `B` while holding `A`.
+//! An edge that closes a cycle in this graph means that the program acquires these locks in
+//! inconsistent orders, which can deadlock under a different schedule even if this one did not.
+//! When a lock gets deallocated, we remove it from the graph again.
+//!
+//! The locks that Miri implements itself take part: pthread mutexes and reader-writer locks, and
+//! macOS `os_unfair_lock`. The locks of the standard library are built on futexes on most targets;
+//! for those, we guess from the atomic operations on the futex word which thread holds the lock
+//! (see `futex_owner_update`). We only do that for words that were used with `futex_wait` or
+//! `futex_wake`, so that ordinary atomic flags do not count as locks; a futex-based lock therefore
+//! only takes part once it has been contended.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+
+use rustc_data_structures::fx::FxHashMap;
+use rustc_span::SpanData;
+
+use crate::*;
+
+/// Identifies a lock in the lock-order graph. Ids are handed out in the order in which the locks
+/// are first acquired.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LockId(u64);
+
+impl fmt::Display for LockId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "lock #{}", self.0)
+    }
+}
+
+/// The acquisition of a lock while holding another one, i.e., an edge of the lock-order graph.
+#[derive(Clone, Debug)]
+pub struct LockAcquisition {
+    pub thread: ThreadId,
+    pub held: LockId,
+    pub acquired: LockId,
+    /// Where the acquisition happened.
+    pub span: SpanData,
+}
+
+#[derive(Debug, Default)]
+pub struct LockOrder {
+    next_id: u64,
+    /// The locks each thread currently holds, in the order in which it acquired them.
+    held: FxHashMap<ThreadId, Vec<LockId>>,
+    /// For every lock, the locks that got acquired while holding it, together with the first
+    /// acquisition that did so.
+    edges: FxHashMap<LockId, BTreeMap<LockId, LockAcquisition>>,
+}
+
+impl LockOrder {
+    /// Returns the id stored in `slot`, assigning a fresh one if the lock does not have one yet.
+    pub fn lock_id(&mut self, slot: &mut Option<LockId>) -> LockId {
+        *slot.get_or_insert_with(|| {
+            self.next_id = self.next_id.strict_add(1);
+            LockId(self.next_id)
+        })
+    }
+
+    /// Records that `thread` acquired `lock` at `span`.
+    ///
+    /// For every new edge that closes a cycle, returns the lock the thread holds and the earlier
+    /// acquisitions that lead from `lock` back to it.
+    pub fn acquire(
+        &mut self,
+        thread: ThreadId,
+        lock: LockId,
+        span: SpanData,
+    ) -> Vec<(LockId, Vec<LockAcquisition>)> {
+        let held = self.held.entry(thread).or_default();
+        let new_edges: Vec<LockId> = held
+            .iter()
+            .copied()
+            .filter(|&h| {
+                h != lock && !self.edges.get(&h).is_some_and(|edges| edges.contains_key(&lock))
+            })
+            .collect();
+        held.push(lock);
+        if new_edges.is_empty() {
+            return vec![];
+        }
+
+        let mut inversions = vec![];
+        for held in new_edges {
+            // If `lock` already (transitively) comes before `held`, the new edge closes a cycle.
+            if let Some(path) = self.path(lock, held) {
+                inversions.push((held, path));
+            }
+            let acquisition = LockAcquisition { thread, held, acquired: lock, span };
+            self.edges.entry(held).or_default().insert(lock, acquisition);
+        }
+        inversions
+    }
+
+    /// Records that `thread` released `lock`.
+    pub fn release(&mut self, thread: ThreadId, lock: LockId) {
+        // A terminated thread does not hold anything any more.
+        if let Some(held) = self.held.get_mut(&thread)
+            && let Some(pos) = held.iter().rposition(|&h| h == lock)
+        {
+            held.remove(pos);
+        }
+    }
+
+    /// Forgets the locks that `thread` holds, since it terminated.
+    pub fn thread_terminated(&mut self, thread: ThreadId) {
+        self.held.remove(&thread);
+    }
+
+    /// Removes `lock` and all its edges from the graph, since it got deallocated. A lock that
+    /// later gets created at the same address gets a fresh id, so it does not inherit this order.
+    pub fn forget(&mut self, lock: LockId) {
+        self.edges.remove(&lock);
+        for edges in self.edges.values_mut() {
+            edges.remove(&lock);
+        }
+        for held in self.held.values_mut() {
+            held.retain(|&h| h != lock);
+        }
+    }
+
+    /// Finds a shortest path of acquisitions from `from` to `to`.
+    fn path(&self, from: LockId, to: LockId) -> Option<Vec<LockAcquisition>> {
+        // Breadth-first search, remembering the predecessor of every lock we reached.
+        let mut pred: FxHashMap<LockId, LockId> = FxHashMap::default();
+        let mut queue = VecDeque::from([from]);
+        while let Some(lock) = queue.pop_front() {
+            if lock == to {
+                let mut path = vec![];
+                let mut cur = to;
+                while cur != from {
+                    let prev = pred[&cur];
+                    path.push(self.edges[&prev][&cur].clone());
+                    cur = prev;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for &next in self.edges.get(&lock).into_iter().flat_map(|edges| edges.keys()) {
+                if next != from && !pred.contains_key(&next) {
+                    pred.insert(next, lock);
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rustc_span::DUMMY_SP;
+
+    use super::*;
+
+    fn new_lock(order: &mut LockOrder) -> LockId {
+        order.lock_id(&mut None)
+    }
+
+    /// Returns the held lock and the path of every inversion as `(held, acquired)` pairs.
+    fn acquire(
+        order: &mut LockOrder,
+        thread: u32,
+        lock: LockId,
+    ) -> Vec<(LockId, Vec<(LockId, LockId)>)> {
+        order
+            .acquire(ThreadId::new_unchecked(thread), lock, DUMMY_SP.data())
+            .into_iter()
+            .map(|(held, path)| (held, path.iter().map(|a| (a.held, a.acquired)).collect()))
+            .collect()
+    }
+
+    fn release(order: &mut LockOrder, thread: u32, lock: LockId) {
+        order.release(ThreadId::new_unchecked(thread), lock);
+    }
+
+    #[test]
+    fn lock_ids() {
+        let mut order = LockOrder::default();
+        let mut slot = None;
+        let a = order.lock_id(&mut slot);
+        assert_eq!(order.lock_id(&mut slot), a);
+        assert_ne!(order.lock_id(&mut None), a);
+    }
+
+    #[test]
+    fn consistent_order() {
+        let mut order = LockOrder::default();
+        let (a, b) = (new_lock(&mut order), new_lock(&mut order));
+        for thread in [1, 2] {
+            assert!(acquire(&mut order, thread, a).is_empty());
+            assert!(acquire(&mut order, thread, b).is_empty());
+            release(&mut order, thread, b);
+            release(&mut order, thread, a);
+        }
+    }
+
+    #[test]
+    fn inversion() {
+        let mut order = LockOrder::default();
+        let (a, b) = (new_lock(&mut order), new_lock(&mut order));
+        acquire(&mut order, 1, a);
+        acquire(&mut order, 1, b);
+        release(&mut order, 1, a);
+        release(&mut order, 1, b);
+
+        acquire(&mut order, 2, b);
+        assert_eq!(acquire(&mut order, 2, a), vec![(b, vec![(a, b)])]);
+        release(&mut order, 2, a);
+        release(&mut order, 2, b);
+
+        // Every edge is only reported once.
+        acquire(&mut order, 3, b);
+        assert!(acquire(&mut order, 3, a).is_empty());
+    }
+
+    #[test]
+    fn released_locks_do_not_count() {
+        let mut order = LockOrder::default();
+        let (a, b) = (new_lock(&mut order), new_lock(&mut order));
+        acquire(&mut order, 1, a);
+        release(&mut order, 1, a);
+        acquire(&mut order, 1, b);
+        release(&mut order, 1, b);
+
+        acquire(&mut order, 2, b);
+        assert!(acquire(&mut order, 2, a).is_empty());
+    }
+
+    #[test]
+    fn terminated_thread() {
+        let mut order = LockOrder::default();
+        let a = new_lock(&mut order);
+        acquire(&mut order, 1, a);
+        order.thread_terminated(ThreadId::new_unchecked(1));
+        assert!(order.held.is_empty());
+    }
+
+    #[test]
+    fn forgotten_lock() {
+        let mut order = LockOrder::default();
+        let (a, b) = (new_lock(&mut order), new_lock(&mut order));
+        acquire(&mut order, 1, a);
+        acquire(&mut order, 1, b);
+        release(&mut order, 1, b);
+        order.forget(a);
+        assert!(order.held.values().all(|held| held.is_empty()));
+        assert!(order.edges.values().all(|edges| edges.is_empty()));
+
+        // A new lock in place of `a` does not inherit its order.
+        let c = new_lock(&mut order);
+        acquire(&mut order, 2, b);
+        assert!(acquire(&mut order, 2, c).is_empty());
+    }
+
+    #[test]
+    fn transitive_inversion() {
+        let mut order = LockOrder::default();
+        let (a, b, c) = (new_lock(&mut order), new_lock(&mut order), new_lock(&mut order));
+        acquire(&mut order, 1, a);
+        acquire(&mut order, 1, b);
+        release(&mut order, 1, b);
+        release(&mut order, 1, a);
+        acquire(&mut order, 2, b);
+        acquire(&mut order, 2, c);
+        release(&mut order, 2, c);
+        release(&mut order, 2, b);
+
+        acquire(&mut order, 3, c);
+        assert_eq!(acquire(&mut order, 3, a), vec![(c, vec![(a, b), (b, c)])]);
+    }
+}
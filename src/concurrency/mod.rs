@@ -3,6 +3,9 @@ pub mod cpu_affinity;
 pub mod data_race;
 mod data_race_handler;
 pub mod init_once;
+pub mod lock_order;
+pub mod pct;
+pub mod schedule_trace;
 pub mod scheduler;
 pub mod sync;
 pub mod thread;
@@ -0,0 +1,592 @@
+//! Recording and replaying the decisions that determine how the threads of a program interleave,
+//! for `-Zmiri-record-schedule` and `-Zmiri-replay-schedule`.
+//!
+//! A seed only reproduces an execution as long as every random number gets drawn in the same order,
+//! which stops being the case as soon as the program is edited. A schedule trace instead lists the
+//! decisions themselves: which thread the scheduler switches to, when the active thread gets
+//! preempted, which store a weak memory load reads from, whether a `compare_exchange_weak` fails
+//! spuriously, which addresses get reused, and which copy of a constant, vtable or function an
+//! allocation refers to. Replaying a trace forces these decisions in order.
+//! When the program makes a decision that does not match the trace, we report where the replay
+//! diverged and make all further decisions randomly again.
+//!
+//! Other non-determinism, like the bytes returned by `getrandom`, is not part of the trace and
+//! still comes from the seed.
+
+use std::borrow::Cow;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::{fmt, fs, io, mem};
+
+use rustc_span::Span;
+use rustc_span::source_map::SourceMap;
+
+use crate::*;
+
+/// A single decision in a schedule trace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    /// The scheduler switched to the given thread.
+    Switch(ThreadId),
+    /// The active thread got preempted. The number says at how many preemption points since the
+    /// previous decision the active thread was *not* preempted.
+    Preempt(u64),
+    /// A weak memory load read from the given store among those it could read from, counting
+    /// backwards from the latest one.
+    Load(usize),
+    /// Whether a `compare_exchange_weak` that could have succeeded failed spuriously.
+    CmpxchgWeakFail(bool),
+    /// Whether the address of a freed allocation was added to the pool of reusable addresses.
+    RememberAddr(bool),
+    /// An allocation reused the given address.
+    ReuseAddr(u64),
+    /// An allocation got a fresh address, leaving the given slack to the previous allocation.
+    FreshAddr(u64),
+    /// A mention of a constant, vtable or function that can exist several times got the copy
+    /// with the given salt.
+    Salt(usize),
+}
+
+impl fmt::Display for Decision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Decision::Switch(thread) => write!(f, "switch {}", thread.to_u32()),
+            Decision::Preempt(skipped) => write!(f, "preempt {skipped}"),
+            Decision::Load(index) => write!(f, "load {index}"),
+            Decision::CmpxchgWeakFail(fail) =>
+                write!(f, "cmpxchg-weak {}", if fail { "fail" } else { "ok" }),
+            Decision::RememberAddr(remember) =>
+                write!(f, "remember-addr {}", if remember { "yes" } else { "no" }),
+            Decision::ReuseAddr(addr) => write!(f, "reuse-addr {addr:#x}"),
+            Decision::FreshAddr(slack) => write!(f, "fresh-addr {slack}"),
+            Decision::Salt(salt) => write!(f, "salt {salt}"),
+        }
+    }
+}
+
+impl FromStr for Decision {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, value) = s.split_once(' ').ok_or_else(|| format!("invalid decision `{s}`"))?;
+        let value = value.trim();
+        let invalid_value = || format!("invalid value `{value}` for `{kind}`");
+        let bool_value = |yes: &str, no: &str| {
+            if value == yes {
+                Ok(true)
+            } else if value == no {
+                Ok(false)
+            } else {
+                Err(invalid_value())
+            }
+        };
+        Ok(match kind {
+            "switch" =>
+                Decision::Switch(ThreadId::new_unchecked(
+                    value.parse().map_err(|_| invalid_value())?,
+                )),
+            "preempt" => Decision::Preempt(value.parse().map_err(|_| invalid_value())?),
+            "load" => Decision::Load(value.parse().map_err(|_| invalid_value())?),
+            "cmpxchg-weak" => Decision::CmpxchgWeakFail(bool_value("fail", "ok")?),
+            "remember-addr" => Decision::RememberAddr(bool_value("yes", "no")?),
+            "reuse-addr" =>
+                Decision::ReuseAddr(
+                    value
+                        .strip_prefix("0x")
+                        .and_then(|hex| u64::from_str_radix(hex, 16).ok())
+                        .ok_or_else(invalid_value)?,
+                ),
+            "fresh-addr" => Decision::FreshAddr(value.parse().map_err(|_| invalid_value())?),
+            "salt" => Decision::Salt(value.parse().map_err(|_| invalid_value())?),
+            _ => return Err(format!("unknown decision `{kind}`")),
+        })
+    }
+}
+
+/// The span the program was executing when it made a decision.
+#[derive(Debug, Clone)]
+enum DecisionSpan {
+    /// A span of the current execution. Turning it into a string is expensive, so we only do
+    /// that when the span actually gets printed.
+    Span(Span),
+    /// A span read from a trace file.
+    Text(String),
+}
+
+impl DecisionSpan {
+    fn format(&self, source_map: &SourceMap) -> Cow<'_, str> {
+        match self {
+            DecisionSpan::Span(span) => Cow::Owned(source_map.span_to_diagnostic_string(*span)),
+            DecisionSpan::Text(text) => Cow::Borrowed(text),
+        }
+    }
+}
+
+/// Where the program made a decision: the active thread, and the span it was executing.
+#[derive(Debug, Clone)]
+struct Location {
+    thread: ThreadId,
+    span: DecisionSpan,
+}
+
+impl Location {
+    fn format(&self, source_map: &SourceMap) -> String {
+        format!("thread {} at {}", self.thread.to_u32(), self.span.format(source_map))
+    }
+}
+
+impl FromStr for Location {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (thread, span) =
+            s.strip_prefix("thread ").and_then(|s| s.split_once(" at ")).ok_or(())?;
+        let thread = ThreadId::new_unchecked(thread.parse().map_err(|_| ())?);
+        Ok(Location { thread, span: DecisionSpan::Text(span.to_owned()) })
+    }
+}
+
+/// A decision together with where the program made it, if we know that.
+#[derive(Debug, Clone)]
+struct RecordedDecision {
+    decision: Decision,
+    location: Option<Location>,
+}
+
+/// The decisions of one execution, in the order in which they were made.
+///
+/// In the file, every line holds one decision, optionally followed by a `#` and the location
+/// where it was made. Empty lines and lines starting with `#` are ignored. A final `end` line says
+/// that the execution finished after these decisions.
+#[derive(Debug, Clone, Default)]
+pub struct ScheduleTrace {
+    decisions: Vec<RecordedDecision>,
+    /// Whether the execution finished after these decisions, so that it did not get preempted
+    /// any more. Otherwise, the trace got cut off.
+    complete: bool,
+}
+
+impl ScheduleTrace {
+    pub fn read(path: &Path) -> Result<ScheduleTrace, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|err| format!("cannot read `{}`: {err}", path.display()))?;
+        let mut decisions = Vec::new();
+        let mut complete = false;
+        for (line_number, line) in contents.lines().enumerate() {
+            let (decision, location) = match line.split_once('#') {
+                Some((decision, location)) => (decision.trim(), Some(location.trim())),
+                None => (line.trim(), None),
+            };
+            if decision.is_empty() {
+                continue;
+            }
+            let line_number = line_number.strict_add(1);
+            if complete {
+                return Err(format!("{}:{line_number}: decision after `end`", path.display()));
+            }
+            if decision == "end" {
+                complete = true;
+                continue;
+            }
+            let decision = decision
+                .parse()
+                .map_err(|err| format!("{}:{line_number}: {err}", path.display()))?;
+            // The location is just a comment, so we ignore it if it got mangled.
+            let location = location.and_then(|location| location.parse().ok());
+            decisions.push(RecordedDecision { decision, location });
+        }
+        Ok(ScheduleTrace { decisions, complete })
+    }
+
+    fn write(&self, path: &Path, source_map: &SourceMap) -> io::Result<()> {
+        let mut contents = String::from(
+            "# Miri schedule trace: one decision per line, followed by where it was made.\n",
+        );
+        for RecordedDecision { decision, location } in &self.decisions {
+            match location {
+                Some(location) =>
+                    writeln!(contents, "{decision} # {}", location.format(source_map)).unwrap(),
+                None => writeln!(contents, "{decision}").unwrap(),
+            }
+        }
+        if self.complete {
+            contents.push_str("end\n");
+        }
+        fs::write(path, contents)
+    }
+
+    /// The number of times the active thread changes in this trace.
+    pub fn context_switches(&self) -> usize {
+        self.switches().count()
+    }
+
+    /// Returns, for every context switch, the index of its decision in the trace and the thread
+    /// that was active before.
+    fn switches(&self) -> impl Iterator<Item = (usize, ThreadId)> {
+        let mut active = ThreadId::MAIN_THREAD;
+        self.decisions.iter().enumerate().filter_map(move |(index, recorded)| {
+            match recorded.decision {
+                Decision::Switch(thread) if thread != active =>
+                    Some((index, mem::replace(&mut active, thread))),
+                _ => None,
+            }
+        })
+    }
+
+    /// Returns traces that might lead to an execution with fewer context switches, roughly
+    /// ordered by how many context switches they remove: the trace ending before a context switch
+    /// (so that the thread keeps running without being preempted), the trace without the
+    /// preemption that caused a context switch, and the trace where a thread that yielded keeps
+    /// running.
+    pub fn simplifications(&self) -> Vec<ScheduleTrace> {
+        let switches: Vec<_> = self.switches().collect();
+        let mut simplifications = Vec::new();
+        for &(index, _previous) in &switches {
+            let end = match index.checked_sub(1).map(|before| self.decisions[before].decision) {
+                Some(Decision::Preempt(_)) => index.strict_sub(1),
+                _ => index,
+            };
+            simplifications
+                .push(ScheduleTrace { decisions: self.decisions[..end].to_vec(), complete: true });
+        }
+        for &(index, previous) in switches.iter().rev() {
+            let mut decisions = self.decisions.clone();
+            if index > 0 && matches!(decisions[index.strict_sub(1)].decision, Decision::Preempt(_))
+            {
+                decisions.drain(index.strict_sub(1)..=index);
+            } else {
+                decisions[index].decision = Decision::Switch(previous);
+            }
+            simplifications.push(ScheduleTrace { decisions, complete: self.complete });
+        }
+        simplifications
+    }
+
+    /// Describes which thread ran up to which point of the program.
+    pub fn describe_interleaving(&self, source_map: &SourceMap) -> String {
+        let mut description = String::new();
+        let mut active = ThreadId::MAIN_THREAD;
+        for (index, previous) in self.switches() {
+            let until = match &self.decisions[index].location {
+                Some(location) if location.thread == previous => location.span.format(source_map),
+                _ => Cow::Borrowed("an unknown location"),
+            };
+            writeln!(description, "thread {} runs until {until}", previous.to_u32()).unwrap();
+            let Decision::Switch(thread) = self.decisions[index].decision else { unreachable!() };
+            active = thread;
+        }
+        write!(description, "thread {} runs until the program fails", active.to_u32()).unwrap();
+        description
+    }
+}
+
+/// The trace we are replaying, and how far we got.
+#[derive(Debug)]
+struct Replay {
+    trace: ScheduleTrace,
+    /// The index of the next decision to replay.
+    next: usize,
+    /// The number of preemption points since the last replayed decision.
+    preemption_points: u64,
+}
+
+impl Replay {
+    /// Returns whether the active thread gets preempted at this preemption point, or `None` if the
+    /// trace got cut off before it.
+    fn preemption(&mut self) -> Option<bool> {
+        match self.trace.decisions.get(self.next) {
+            Some(RecordedDecision { decision: Decision::Preempt(points), .. })
+                if *points == self.preemption_points =>
+            {
+                self.next = self.next.strict_add(1);
+                self.preemption_points = 0;
+                Some(true)
+            }
+            // The trace got cut off, so it does not say.
+            None if !self.trace.complete => None,
+            // Either the next decision is still a few preemption points away, or some other decision
+            // comes first, or the recorded execution finished without being preempted again. In the
+            // second case, we will notice a divergence when we get there.
+            _ => {
+                self.preemption_points = self.preemption_points.strict_add(1);
+                Some(false)
+            }
+        }
+    }
+}
+
+/// The per-execution state of recording and replaying.
+#[derive(Debug, Default)]
+pub struct ScheduleTracer {
+    /// The decisions made so far, if we are recording them.
+    record: Option<ScheduleTrace>,
+    /// The file to write the recorded decisions to.
+    record_path: Option<PathBuf>,
+    /// The number of preemption points since the last recorded decision.
+    preemption_points: u64,
+    /// The trace we are replaying. This is reset to `None` once the replay diverged.
+    replay: Option<Replay>,
+}
+
+impl ScheduleTracer {
+    pub fn new(config: &MiriConfig) -> Self {
+        ScheduleTracer {
+            // Schedule minimization looks at the decisions of every execution it tries.
+            record: (config.record_schedule.is_some() || config.minimize_schedule)
+                .then(ScheduleTrace::default),
+            record_path: config.record_schedule.clone(),
+            preemption_points: 0,
+            replay: config
+                .replay_schedule
+                .clone()
+                .map(|trace| Replay { trace, next: 0, preemption_points: 0 }),
+        }
+    }
+
+    /// Returns the decisions of the execution that just finished.
+    pub fn take_recorded(&mut self) -> ScheduleTrace {
+        let mut trace = self.record.take().expect("decisions are not being recorded");
+        trace.complete = true;
+        trace
+    }
+}
+
+impl<'tcx> MiriMachine<'tcx> {
+    /// If we are replaying a schedule, returns the next decision of the trace, provided that
+    /// `matches` accepts it. Otherwise the replay diverged; `what` describes the decision the
+    /// program is making instead. Returns `None` if the decision has to be made randomly.
+    pub(crate) fn replay_decision<T>(
+        &self,
+        what: &str,
+        matches: impl FnOnce(Decision) -> Option<T>,
+    ) -> Option<T> {
+        let mut tracer = self.schedule_tracer.borrow_mut();
+        let replay = tracer.replay.as_mut()?;
+        let index = replay.next;
+        let Some(recorded) = replay.trace.decisions.get(index) else {
+            drop(tracer);
+            self.replay_diverged(None, format!("the trace ended, but the program reached {what}"));
+            return None;
+        };
+        if let Some(value) = matches(recorded.decision) {
+            replay.next = index.strict_add(1);
+            replay.preemption_points = 0;
+            return Some(value);
+        }
+        let recorded = recorded.clone();
+        drop(tracer);
+        self.replay_diverged(
+            Some((index, recorded)),
+            format!("the program reached {what} instead"),
+        );
+        None
+    }
+
+    /// Reports that the decision just returned by `replay_decision` could not be applied.
+    pub(crate) fn replay_rejected(&self, reason: String) {
+        let recorded = {
+            let tracer = self.schedule_tracer.borrow();
+            let replay = tracer.replay.as_ref().unwrap();
+            let index = replay.next.strict_sub(1);
+            (index, replay.trace.decisions[index].clone())
+        };
+        self.replay_diverged(Some(recorded), reason);
+    }
+
+    /// If we are replaying a schedule, returns whether the active thread gets preempted at this
+    /// preemption point. Returns `None` if the decision has to be made randomly.
+    pub(crate) fn replay_preemption(&self) -> Option<bool> {
+        let mut tracer = self.schedule_tracer.borrow_mut();
+        let preempt = tracer.replay.as_mut()?.preemption();
+        if preempt.is_none() {
+            drop(tracer);
+            self.replay_diverged(
+                None,
+                "the trace ended, but the program reached a preemption point".to_owned(),
+            );
+        }
+        preempt
+    }
+
+    fn replay_diverged(&self, recorded: Option<(usize, RecordedDecision)>, reason: String) {
+        self.schedule_tracer.borrow_mut().replay = None;
+        let recorded = recorded.map(|(index, RecordedDecision { decision, location })| {
+            let location = match location {
+                Some(location) => location.format(self.tcx.sess.source_map()),
+                None => "an unknown location".to_owned(),
+            };
+            (index.strict_add(1), decision.to_string(), location)
+        });
+        self.emit_diagnostic(NonHaltingDiagnostic::ScheduleReplayDiverged { recorded, reason });
+    }
+
+    /// Adds a decision to the trace if we are recording one.
+    pub(crate) fn record_decision(&self, decision: Decision) {
+        let mut tracer = self.schedule_tracer.borrow_mut();
+        let ScheduleTracer { record: Some(trace), preemption_points, .. } = &mut *tracer else {
+            return;
+        };
+        *preemption_points = 0;
+        let thread = self.threads.active_thread();
+        // A thread that just terminated has no stack left to point into.
+        let span = if self.threads.thread_ref(thread).is_terminated() {
+            DecisionSpan::Text("the end of the thread".to_owned())
+        } else {
+            DecisionSpan::Span(self.current_user_relevant_span())
+        };
+        let location = Location { thread, span };
+        trace.decisions.push(RecordedDecision { decision, location: Some(location) });
+    }
+
+    /// Records whether the active thread got preempted at this preemption point.
+    pub(crate) fn record_preemption(&self, preempt: bool) {
+        let mut tracer = self.schedule_tracer.borrow_mut();
+        if tracer.record.is_none() {
+            return;
+        }
+        if preempt {
+            let points = mem::take(&mut tracer.preemption_points);
+            drop(tracer);
+            self.record_decision(Decision::Preempt(points));
+        } else {
+            tracer.preemption_points = tracer.preemption_points.strict_add(1);
+        }
+    }
+
+    /// Writes the recorded trace, and warns about decisions that were never replayed.
+    pub(crate) fn finish_schedule_trace(&self) {
+        let mut tracer = self.schedule_tracer.borrow_mut();
+        if let Some(trace) = &mut tracer.record {
+            trace.complete = true;
+        }
+        if let Some(path) = &tracer.record_path
+            && let Some(trace) = &tracer.record
+            && let Err(err) = trace.write(path, self.tcx.sess.source_map())
+        {
+            self.tcx
+                .dcx()
+                .warn(format!("failed to write the schedule trace to `{}`: {err}", path.display()));
+        }
+        if let Some(replay) = &tracer.replay
+            && replay.next < replay.trace.decisions.len()
+            && !self.quiet
+        {
+            self.tcx.dcx().warn(format!(
+                "schedule replay: the program finished after {} of the {} recorded decisions",
+                replay.next,
+                replay.trace.decisions.len()
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rustc_span::source_map::FilePathMapping;
+
+    use super::*;
+
+    #[test]
+    fn decision_roundtrip() {
+        let decisions = [
+            Decision::Switch(ThreadId::new_unchecked(3)),
+            Decision::Preempt(42),
+            Decision::Load(1),
+            Decision::CmpxchgWeakFail(true),
+            Decision::CmpxchgWeakFail(false),
+            Decision::RememberAddr(true),
+            Decision::RememberAddr(false),
+            Decision::ReuseAddr(0x1a2b0),
+            Decision::FreshAddr(7),
+            Decision::Salt(5),
+        ];
+        for decision in decisions {
+            assert_eq!(decision.to_string().parse::<Decision>(), Ok(decision));
+        }
+    }
+
+    #[test]
+    fn invalid_decisions() {
+        assert!("switch".parse::<Decision>().is_err());
+        assert!("switch main".parse::<Decision>().is_err());
+        assert!("cmpxchg-weak yes".parse::<Decision>().is_err());
+        assert!("reuse-addr 1234".parse::<Decision>().is_err());
+        assert!("yield 1".parse::<Decision>().is_err());
+    }
+
+    fn trace(decisions: &[Decision]) -> ScheduleTrace {
+        ScheduleTrace {
+            decisions: decisions
+                .iter()
+                .map(|&decision| RecordedDecision { decision, location: None })
+                .collect(),
+            complete: true,
+        }
+    }
+
+    fn decisions(trace: &ScheduleTrace) -> Vec<Decision> {
+        trace.decisions.iter().map(|recorded| recorded.decision).collect()
+    }
+
+    #[test]
+    fn simplifications() {
+        let main = ThreadId::MAIN_THREAD;
+        let other = ThreadId::new_unchecked(1);
+        let original = [
+            Decision::Switch(other),
+            Decision::Preempt(3),
+            Decision::Switch(main),
+            Decision::Switch(main),
+        ];
+        let original = trace(&original);
+        assert_eq!(original.context_switches(), 2);
+
+        let simplifications: Vec<_> = original.simplifications().iter().map(decisions).collect();
+        assert_eq!(
+            simplifications,
+            [
+                vec![],
+                vec![Decision::Switch(other)],
+                vec![Decision::Switch(other), Decision::Switch(main)],
+                vec![
+                    Decision::Switch(main),
+                    Decision::Preempt(3),
+                    Decision::Switch(main),
+                    Decision::Switch(main),
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn replay_preemptions() {
+        let mut complete =
+            Replay { trace: trace(&[Decision::Preempt(1)]), next: 0, preemption_points: 0 };
+        let mut truncated = Replay { trace: complete.trace.clone(), next: 0, preemption_points: 0 };
+        truncated.trace.complete = false;
+        for replay in [&mut complete, &mut truncated] {
+            assert_eq!(replay.preemption(), Some(false));
+            assert_eq!(replay.preemption(), Some(true));
+        }
+        // The recorded execution was not preempted again...
+        assert_eq!(complete.preemption(), Some(false));
+        assert_eq!(complete.preemption(), Some(false));
+        // ...but a trace that got cut off does not say.
+        assert_eq!(truncated.preemption(), None);
+    }
+
+    #[test]
+    fn describe_interleaving() {
+        let mut trace = trace(&[Decision::Switch(ThreadId::new_unchecked(1))]);
+        trace.decisions[0].location = Some(Location {
+            thread: ThreadId::MAIN_THREAD,
+            span: DecisionSpan::Text("main.rs:3:5: 3:20".to_owned()),
+        });
+        let source_map = SourceMap::new(FilePathMapping::empty());
+        assert_eq!(
+            trace.describe_interleaving(&source_map),
+            "thread 0 runs until main.rs:3:5: 3:20\nthread 1 runs until the program fails"
+        );
+    }
+}
@@ -11,6 +11,7 @@ use rustc_const_eval::CTRL_C_RECEIVED;
 use rustc_index::Idx;
 use rustc_span::DUMMY_SP;
 
+use crate::shims::TimerFdEvalContextExt as _;
 use crate::shims::readiness::DelayedReadinessUpdates;
 use crate::*;
 
@@ -41,12 +42,34 @@ trait EvalContextPrivExt<'tcx>: MiriInterpCxExt<'tcx> {
         interp_ok(res)
     }
 
+    /// Execute one step on the active thread.
+    fn step_current_thread(&mut self) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+
+        // If a signal is pending for this thread, this pushes the frame of its handler, so the
+        // step below is the first step of the handler.
+        this.deliver_pending_signal()?;
+        if !this.step()? {
+            // See if this thread can do something else.
+            match this.run_on_stack_empty()? {
+                Poll::Pending => {} // keep going
+                Poll::Ready(()) => {
+                    this.terminate_active_thread(TlsAllocAction::Deallocate)?;
+                }
+            }
+        }
+
+        interp_ok(())
+    }
+
     /// Decide which action to take next and on which thread.
     ///
     /// The currently implemented scheduling policy is the one that is commonly
     /// used in stateless model checkers such as Loom: run the active thread as
     /// long as we can and switch only when we have to (the active thread was
     /// blocked, terminated, or has explicitly asked to be preempted).
+    /// With PCT, the thread we switch to is the one with the highest priority. A thread pinned by a
+    /// debugger is picked over all others whenever it is enabled.
     ///
     /// If GenMC mode is active, the scheduling is instead handled by GenMC.
     fn schedule(&mut self) -> InterpResult<'tcx, SchedulingAction> {
@@ -79,9 +102,13 @@ trait EvalContextPrivExt<'tcx>: MiriInterpCxExt<'tcx> {
         }
 
         // We are not in GenMC mode, so we control the scheduling.
-        let thread_manager = &this.machine.threads;
-        // Check if we can just keep running the current thread.
-        if thread_manager.active_thread_ref().is_enabled() && !thread_manager.yield_active_thread {
+        let thread_manager = &mut this.machine.threads;
+        // Check if we can just keep running the current thread. If a debugger pinned the scheduler
+        // to another thread, we instead go pick that one below.
+        if thread_manager.active_thread_ref().is_enabled()
+            && !thread_manager.yield_active_thread
+            && thread_manager.pinned_thread().is_none_or(|id| id == thread_manager.active_thread())
+        {
             // The currently active thread is still enabled, just continue with it.
             return interp_ok(SchedulingAction::ExecuteStep);
         }
@@ -113,6 +140,43 @@ trait EvalContextPrivExt<'tcx>: MiriInterpCxExt<'tcx> {
         // abstime has already been passed at the time of the call".
         // <https://pubs.opengroup.org/onlinepubs/9699919799/functions/pthread_cond_timedwait.html>
         let potential_sleep_time = this.unblock_expired_deadlines()?;
+        // Expired `timerfd` timers may wake up threads blocked on reading them or on epoll.
+        let timer_sleep_time = this.fire_expired_timerfds()?;
+        let potential_sleep_time = match (potential_sleep_time, timer_sleep_time) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+
+        // If a debugger pinned the scheduler to a thread, keep running that thread for as long as
+        // it can make progress. This overrides yields, preemption and the scheduling policy.
+        let pinned = this
+            .machine
+            .threads
+            .pinned_thread()
+            .filter(|&id| this.machine.threads.thread_ref(id).is_enabled());
+
+        // When replaying a schedule, the trace says which thread to switch to.
+        let mut replayed = None;
+        if pinned.is_none()
+            && this.machine.threads.all_threads().any(|(_id, thread)| thread.is_enabled())
+        {
+            replayed = this.machine.replay_decision("a thread switch", |decision| {
+                match decision {
+                    Decision::Switch(id) => Some(id),
+                    _ => None,
+                }
+            });
+            if let Some(id) = replayed
+                && !this
+                    .machine
+                    .threads
+                    .all_threads()
+                    .any(|(other, thread)| other == id && thread.is_enabled())
+            {
+                this.machine.replay_rejected(format!("thread {} cannot run", id.to_u32()));
+                replayed = None;
+            }
+        }
 
         let thread_manager = &mut this.machine.threads;
         let rng = this.machine.rng.get_mut();
@@ -129,19 +193,32 @@ trait EvalContextPrivExt<'tcx>: MiriInterpCxExt<'tcx> {
             .chain(thread_manager.all_threads().take(thread_manager.active_thread().index() + 1))
             .filter(|(_id, thread)| thread.is_enabled());
         // Pick a new thread, and switch to it.
-        let new_thread = if thread_manager.fixed_scheduling() {
+        let new_thread = if pinned.is_some() {
+            drop(threads_iter);
+            pinned
+        } else if replayed.is_some() {
+            drop(threads_iter);
+            replayed
+        } else if thread_manager.fixed_scheduling() {
             let next = threads_iter.next();
             drop(threads_iter);
-            next
+            next.map(|(id, _thread)| id)
+        } else if let Some(pct) = thread_manager.pct() {
+            drop(threads_iter);
+            // PCT always runs the enabled thread with the highest priority.
+            pct.highest_priority(|id| thread_manager.thread_ref(id).is_enabled())
         } else {
-            threads_iter.choose(rng)
+            threads_iter.choose(rng).map(|(id, _thread)| id)
         };
 
-        if let Some((id, _thread)) = new_thread {
+        if let Some(id) = new_thread {
+            this.machine.record_decision(Decision::Switch(id));
+            let thread_manager = &mut this.machine.threads;
             if thread_manager.active_thread() != id {
                 thread_manager.set_active_thread(id);
             }
         }
+        let thread_manager = &mut this.machine.threads;
         // This completes the `yield`, if any was requested.
         thread_manager.yield_active_thread = false;
 
@@ -170,20 +247,30 @@ trait EvalContextPrivExt<'tcx>: MiriInterpCxExt<'tcx> {
 
 impl<'tcx> EvalContextExt<'tcx> for crate::MiriInterpCx<'tcx> {}
 pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
+    /// Make one scheduling decision and carry it out: either execute a step on the thread picked
+    /// by the scheduler, or wait for timeouts and I/O if no thread can currently run.
+    ///
     /// Public because this is used by Priroda.
-    fn step_current_thread(&mut self) -> InterpResult<'tcx> {
+    fn step_scheduled_thread(&mut self) -> InterpResult<'tcx> {
         let this = self.eval_context_mut();
+        match this.schedule()? {
+            SchedulingAction::ExecuteStep => {
+                this.step_current_thread()?;
+            }
+            SchedulingAction::SleepAndWaitForIo(duration) => {
+                if this.machine.communicate() {
+                    // When we're running with isolation disabled, instead of
+                    // strictly sleeping the duration we allow waking up
+                    // early for I/O events from the OS.
 
-        if !this.step()? {
-            // See if this thread can do something else.
-            match this.run_on_stack_empty()? {
-                Poll::Pending => {} // keep going
-                Poll::Ready(()) => {
-                    this.terminate_active_thread(TlsAllocAction::Deallocate)?;
+                    this.poll_and_unblock(duration)?;
+                } else {
+                    let duration = duration
+                        .expect("Infinite sleep should not be triggered when isolation is enabled");
+                    this.machine.monotonic_clock.sleep(duration);
                 }
             }
         }
-
         interp_ok(())
     }
 
@@ -196,25 +283,7 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
                 this.machine.handle_abnormal_termination();
                 throw_machine_stop!(TerminationInfo::Interrupted);
             }
-            match this.schedule()? {
-                SchedulingAction::ExecuteStep => {
-                    this.step_current_thread()?;
-                }
-                SchedulingAction::SleepAndWaitForIo(duration) => {
-                    if this.machine.communicate() {
-                        // When we're running with isolation disabled, instead of
-                        // strictly sleeping the duration we allow waking up
-                        // early for I/O events from the OS.
-
-                        this.poll_and_unblock(duration)?;
-                    } else {
-                        let duration = duration.expect(
-                            "Infinite sleep should not be triggered when isolation is enabled",
-                        );
-                        this.machine.monotonic_clock.sleep(duration);
-                    }
-                }
-            }
+            this.step_scheduled_thread()?;
         }
     }
 }
@@ -43,6 +43,12 @@ pub trait SyncObj: Any {
     fn delete_on_write(&self) -> bool {
         false
     }
+
+    /// The id of the lock this object implements in the lock-order graph, if any. The lock is
+    /// removed from the graph when this object gets deleted.
+    fn lock_order_id(&self) -> Option<LockId> {
+        None
+    }
 }
 
 impl dyn SyncObj {
@@ -70,6 +76,8 @@ struct Mutex {
     queue: VecDeque<ThreadId>,
     /// Mutex clock. This tracks the moment of the last unlock.
     clock: VClock,
+    /// The id of this mutex in the lock-order graph, once it got acquired.
+    lock_order_id: Option<LockId>,
 }
 
 #[derive(Default, Clone, Debug)]
@@ -88,6 +96,10 @@ impl MutexRef {
     pub fn queue_is_empty(&self) -> bool {
         self.0.borrow().queue.is_empty()
     }
+
+    pub fn lock_order_id(&self) -> Option<LockId> {
+        self.0.borrow().lock_order_id
+    }
 }
 
 impl VisitProvenance for MutexRef {
@@ -127,6 +139,8 @@ struct RwLock {
     /// locks.
     /// This is only relevant when there is an active reader.
     clock_current_readers: VClock,
+    /// The id of this lock in the lock-order graph, once it got acquired.
+    lock_order_id: Option<LockId>,
 }
 
 impl RwLock {
@@ -169,6 +183,10 @@ impl RwLockRef {
         let inner = self.0.borrow();
         inner.reader_queue.is_empty() && inner.writer_queue.is_empty()
     }
+
+    pub fn lock_order_id(&self) -> Option<LockId> {
+        self.0.borrow().lock_order_id
+    }
 }
 
 impl VisitProvenance for RwLockRef {
@@ -176,6 +194,54 @@ impl VisitProvenance for RwLockRef {
     fn visit_provenance(&self, _visit: &mut VisitWith<'_>) {}
 }
 
+/// A lock that a blocked thread is waiting to acquire. Deadlock reports use this to show which
+/// threads are in each other's way.
+#[derive(Clone, Debug)]
+pub enum AwaitedLock {
+    Mutex(MutexRef),
+    RwLock {
+        lock: RwLockRef,
+        write: bool,
+    },
+    /// The futex word at this address, usually the state of a lock built on futexes (like the
+    /// locks in `std`).
+    Futex(Pointer),
+    /// A condition variable; once it is signaled, the thread re-locks this mutex.
+    Condvar(MutexRef),
+}
+
+impl AwaitedLock {
+    /// The threads that hold the lock in a way that keeps the waiting thread from acquiring it,
+    /// ordered by id. For a condition variable, these are the threads holding the mutex that will
+    /// be re-locked.
+    pub fn holders<'tcx>(&self, ecx: &MiriInterpCx<'tcx>) -> Vec<ThreadId> {
+        match self {
+            AwaitedLock::Mutex(mutex_ref) | AwaitedLock::Condvar(mutex_ref) =>
+                mutex_ref.owner().into_iter().collect(),
+            AwaitedLock::RwLock { lock, write } => {
+                let rwlock = lock.0.borrow();
+                let mut holders: Vec<_> = rwlock.writer.into_iter().collect();
+                // Readers only keep out writers.
+                if *write {
+                    holders.extend(rwlock.readers.keys().copied());
+                    holders.sort();
+                }
+                holders
+            }
+            AwaitedLock::Futex(ptr) => ecx.futex_owner(*ptr).into_iter().collect(),
+        }
+    }
+}
+
+impl VisitProvenance for AwaitedLock {
+    fn visit_provenance(&self, visit: &mut VisitWith<'_>) {
+        match self {
+            AwaitedLock::Futex(ptr) => ptr.visit_provenance(visit),
+            AwaitedLock::Mutex(_) | AwaitedLock::RwLock { .. } | AwaitedLock::Condvar(_) => {}
+        }
+    }
+}
+
 /// The conditional variable state.
 #[derive(Default, Debug)]
 struct Condvar {
@@ -245,6 +311,21 @@ struct FutexWaiter {
     bitset: u32,
 }
 
+/// What we know about the lock stored in a futex word. See `futex_owner_update`.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct FutexLock {
+    /// The thread that we believe holds the lock.
+    owner: Option<ThreadId>,
+    /// The id of this lock in the lock-order graph, once it got acquired.
+    lock_order_id: Option<LockId>,
+}
+
+impl FutexLock {
+    pub fn lock_order_id(&self) -> Option<LockId> {
+        self.lock_order_id
+    }
+}
+
 // Private extension trait for local helper methods
 impl<'tcx> EvalContextExtPriv<'tcx> for crate::MiriInterpCx<'tcx> {}
 pub(super) trait EvalContextExtPriv<'tcx>: crate::MiriInterpCxExt<'tcx> {
@@ -266,6 +347,52 @@ pub(super) trait EvalContextExtPriv<'tcx>: crate::MiriInterpCxExt<'tcx> {
         }
         interp_ok(())
     }
+
+    /// Records that the active thread acquired the lock with the given lock-order id, and warns
+    /// about every lock-order inversion this creates. Does nothing without
+    /// `-Zmiri-check-lock-order`.
+    fn lock_order_acquire(&mut self, lock_order_id: &mut Option<LockId>) {
+        let this = self.eval_context_mut();
+        if this.machine.lock_order.is_none() {
+            return;
+        }
+        let span = this.machine.current_user_relevant_span().data();
+        let thread = this.active_thread();
+        let lock_order = this.machine.lock_order.as_mut().unwrap();
+        let lock = lock_order.lock_id(lock_order_id);
+        let inversions = lock_order.acquire(thread, lock, span);
+        for (held, cycle) in inversions {
+            this.emit_diagnostic(NonHaltingDiagnostic::LockOrderInversion {
+                held,
+                acquired: lock,
+                cycle,
+            });
+        }
+    }
+
+    /// Records that the active thread released the lock with the given lock-order id.
+    fn lock_order_release(&mut self, lock_order_id: Option<LockId>) {
+        let this = self.eval_context_mut();
+        if let Some(lock_order) = &mut this.machine.lock_order
+            && let Some(lock) = lock_order_id
+        {
+            lock_order.release(this.machine.threads.active_thread(), lock);
+        }
+    }
+
+    /// Records that the word at `ptr` is used as a futex, so that `futex_owner_update` tracks the
+    /// lock stored in it from now on.
+    fn futex_word_used(&mut self, ptr: Pointer) {
+        let this = self.eval_context_mut();
+        let Ok((alloc_id, offset, _)) = this.ptr_try_get_alloc_id(ptr, 0) else {
+            return;
+        };
+        let Some((alloc_extra, machine)) = this.get_alloc_extra_mut(alloc_id).discard_err() else {
+            return;
+        };
+        alloc_extra.futex_locks.entry(offset).or_default();
+        machine.futex_allocs.insert(alloc_id);
+    }
 }
 
 impl<'tcx> AllocExtra<'tcx> {
@@ -426,6 +553,7 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
             );
         } else {
             mutex.owner = Some(thread);
+            this.lock_order_acquire(&mut mutex.lock_order_id);
         }
         mutex.lock_count = mutex.lock_count.strict_add(1);
         this.acquire_clock(&mutex.clock)?;
@@ -449,6 +577,7 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
             mutex.lock_count = old_lock_count.strict_sub(1);
             if mutex.lock_count == 0 {
                 mutex.owner = None;
+                this.lock_order_release(mutex.lock_order_id);
                 // The mutex is completely unlocked. Try transferring ownership
                 // to another thread.
 
@@ -484,6 +613,7 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         mutex.queue.push_back(thread);
         assert!(mutex.owner.is_some(), "queuing on unlocked mutex");
         drop(mutex);
+        this.active_thread_mut().awaited_lock = Some(AwaitedLock::Mutex(mutex_ref.clone()));
         this.block_thread(
             BlockReason::Mutex,
             None,
@@ -518,6 +648,9 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         assert!(!rwlock.is_write_locked(), "the lock is write locked");
         let count = rwlock.readers.entry(thread).or_insert(0);
         *count = count.strict_add(1);
+        if *count == 1 {
+            this.lock_order_acquire(&mut rwlock.lock_order_id);
+        }
         this.acquire_clock(&rwlock.clock_unlocked)?;
         interp_ok(())
     }
@@ -536,6 +669,7 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
                 if *count == 0 {
                     trace!("rwlock_reader_unlock: no longer held by {:?}", thread);
                     entry.remove();
+                    this.lock_order_release(rwlock.lock_order_id);
                 } else {
                     trace!("rwlock_reader_unlock: held one less time by {:?}", thread);
                 }
@@ -576,6 +710,8 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         rwlock.reader_queue.push_back(thread);
         assert!(rwlock.is_write_locked(), "read-queueing on not write locked rwlock");
         drop(rwlock);
+        this.active_thread_mut().awaited_lock =
+            Some(AwaitedLock::RwLock { lock: rwlock_ref.clone(), write: false });
         this.block_thread(
             BlockReason::RwLock,
             None,
@@ -605,6 +741,7 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         let mut rwlock = rwlock_ref.0.borrow_mut();
         assert!(!rwlock.is_locked(), "the rwlock is already locked");
         rwlock.writer = Some(thread);
+        this.lock_order_acquire(&mut rwlock.lock_order_id);
         this.acquire_clock(&rwlock.clock_unlocked)?;
         interp_ok(())
     }
@@ -622,6 +759,7 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
                 return interp_ok(false);
             }
             rwlock.writer = None;
+            this.lock_order_release(rwlock.lock_order_id);
             trace!("rwlock_writer_unlock: unlocked by {:?}", thread);
             // Record release clock for next lock holder.
             this.release_clock(|clock| rwlock.clock_unlocked.clone_from(clock))?;
@@ -663,6 +801,8 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         rwlock.writer_queue.push_back(thread);
         assert!(rwlock.is_locked(), "write-queueing on unlocked rwlock");
         drop(rwlock);
+        this.active_thread_mut().awaited_lock =
+            Some(AwaitedLock::RwLock { lock: rwlock_ref.clone(), write: true });
         this.block_thread(
             BlockReason::RwLock,
             None,
@@ -709,6 +849,7 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         let thread = this.active_thread();
 
         condvar_ref.0.borrow_mut().waiters.push_back(thread);
+        this.active_thread_mut().awaited_lock = Some(AwaitedLock::Condvar(mutex_ref.clone()));
         this.block_thread(
             BlockReason::Condvar,
             deadline,
@@ -763,21 +904,25 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
     }
 
     /// Wait for the futex to be signaled, or a timeout. Once the thread is
-    /// unblocked, `callback` is called with the unblock reason.
+    /// unblocked, `callback` is called with the unblock reason. `ptr` is the address of the futex
+    /// word; it is only used to track the lock stored in it.
     fn futex_wait(
         &mut self,
+        ptr: Pointer,
         futex_ref: FutexRef,
         bitset: u32,
         deadline: Option<Deadline>,
         callback: DynUnblockCallback<'tcx>,
     ) {
         let this = self.eval_context_mut();
+        this.futex_word_used(ptr);
         let thread = this.active_thread();
         let mut futex = futex_ref.0.borrow_mut();
         let waiters = &mut futex.waiters;
         assert!(waiters.iter().all(|waiter| waiter.thread != thread), "thread is already waiting");
         waiters.push(FutexWaiter { thread, bitset });
         drop(futex);
+        this.active_thread_mut().awaited_lock = Some(AwaitedLock::Futex(ptr));
 
         this.block_thread(
             BlockReason::Futex,
@@ -809,14 +954,17 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
     }
 
     /// Wake up `count` of the threads in the queue that match any of the bits
-    /// in the bitset. Returns how many threads were woken.
+    /// in the bitset. Returns how many threads were woken. `ptr` is the address of the futex word;
+    /// it is only used to track the lock stored in it.
     fn futex_wake(
         &mut self,
+        ptr: Pointer,
         futex_ref: &FutexRef,
         bitset: u32,
         count: usize,
     ) -> InterpResult<'tcx, usize> {
         let this = self.eval_context_mut();
+        this.futex_word_used(ptr);
         let mut futex = futex_ref.0.borrow_mut();
 
         // Each futex-wake happens-before the end of the futex wait
@@ -836,4 +984,74 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
 
         interp_ok(woken)
     }
+
+    /// Tracks which thread holds the lock stored in a futex word, given that an atomic operation
+    /// of the active thread modified `place`. `exchange` is the old and new value for swaps and
+    /// successful compare-exchanges, and `None` for all other modifications. Only words that were
+    /// used with `futex_wait` or `futex_wake` are tracked, so a lock is only known once it was
+    /// contended. Locks built on futexes take the lock by exchanging the word from zero to
+    /// something else, so that is when we record the owner; other threads may then exchange the
+    /// word to announce that they are waiting. We do not know how any other modification hands the
+    /// lock around, so it makes us forget the owner. This is only a guess, used to explain
+    /// deadlocks and to check the lock order.
+    fn futex_owner_update(
+        &mut self,
+        place: &MPlaceTy<'tcx>,
+        exchange: Option<(Scalar, Scalar)>,
+    ) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+        // This runs on every atomic write, so first rule out the common case cheaply.
+        if this.machine.futex_allocs.is_empty() || !place.layout.ty.is_integral() {
+            return interp_ok(());
+        }
+        let Ok((alloc_id, offset, _)) = this.ptr_try_get_alloc_id(place.ptr(), 0) else {
+            return interp_ok(());
+        };
+        if !this.machine.futex_allocs.contains(&alloc_id) {
+            return interp_ok(());
+        }
+        let is_zero = |val: Scalar| matches!(val, Scalar::Int(int) if int.is_null());
+        let thread = this.active_thread();
+        let (alloc_extra, _machine) = this.get_alloc_extra_mut(alloc_id)?;
+        let Some(FutexLock { owner: old_owner, mut lock_order_id }) =
+            alloc_extra.futex_locks.get(&offset).copied()
+        else {
+            return interp_ok(());
+        };
+        let new_owner = match exchange {
+            Some((old, new)) if is_zero(old) && !is_zero(new) => Some(thread),
+            // Another thread announced that it waits for the lock. When the owner itself changes
+            // the word without releasing it to zero, it is probably not a lock (e.g. a `Once` that
+            // completed), so we forget the owner in that case.
+            Some((_old, new))
+                if !is_zero(new) && old_owner.is_some_and(|owner| owner != thread) =>
+                old_owner,
+            _ => None,
+        };
+        if new_owner == old_owner {
+            return interp_ok(());
+        }
+
+        if let Some(owner) = old_owner
+            && let Some(lock_order) = &mut this.machine.lock_order
+            && let Some(lock) = lock_order_id
+        {
+            lock_order.release(owner, lock);
+        }
+        if new_owner.is_some() {
+            this.lock_order_acquire(&mut lock_order_id);
+        }
+        let (alloc_extra, _machine) = this.get_alloc_extra_mut(alloc_id)?;
+        alloc_extra.futex_locks.insert(offset, FutexLock { owner: new_owner, lock_order_id });
+        interp_ok(())
+    }
+
+    /// The thread that holds the lock stored in the futex word at `ptr`, as far as
+    /// `futex_owner_update` could tell.
+    fn futex_owner(&self, ptr: Pointer) -> Option<ThreadId> {
+        let this = self.eval_context_ref();
+        let (alloc_id, offset, _) = this.ptr_try_get_alloc_id(ptr, 0).ok()?;
+        let alloc_extra = this.get_alloc_extra(alloc_id).discard_err()?;
+        alloc_extra.futex_locks.get(&offset)?.owner
+    }
 }
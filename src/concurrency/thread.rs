@@ -4,7 +4,7 @@ use std::mem;
 use std::task::Poll;
 use std::time::{Duration, SystemTime};
 
-use rand::RngExt;
+use rand::{Rng, RngExt};
 use rustc_abi::ExternAbi;
 use rustc_data_structures::either::Either;
 use rustc_data_structures::fx::FxHashMap;
@@ -16,6 +16,7 @@ use rustc_span::{DUMMY_SP, Span};
 use rustc_target::spec::Os;
 
 use crate::concurrency::GlobalDataRaceHandler;
+use crate::concurrency::pct::PctScheduler;
 use crate::shims::tls;
 use crate::*;
 
@@ -97,10 +98,18 @@ pub enum BlockReason {
     Readiness,
     /// Blocked on eventfd.
     Eventfd,
+    /// Blocked on timerfd.
+    TimerFd,
+    /// Blocked on signalfd.
+    SignalFd,
+    /// Blocked on inotify.
+    Inotify,
     /// Blocked on virtual socket.
     VirtualSocket,
     /// Blocked on an IO operation.
     IO,
+    /// Blocked waiting for a child process to exit.
+    ChildProcess,
     /// Blocked for any reason related to GenMC, such as `assume` statements (GenMC mode only).
     /// Will be implicitly unblocked when GenMC schedules this thread again.
     Genmc,
@@ -186,6 +195,9 @@ pub struct Thread<'tcx> {
     /// The join status.
     join_status: ThreadJoinStatus,
 
+    /// The lock this thread is waiting for, if it is blocked on a mutex or reader-writer lock.
+    pub(crate) awaited_lock: Option<AwaitedLock>,
+
     /// Stack of active unwind payloads for the current thread. Used for storing
     /// the argument of the call to `miri_start_unwind` (the payload) when unwinding.
     /// This is pointer-sized, and matches the `Payload` type in `src/libpanic_unwind/miri.rs`.
@@ -222,6 +234,19 @@ impl<'tcx> Thread<'tcx> {
         self.state.is_blocked_on(reason)
     }
 
+    /// Return what this thread is blocked on, or `None` if it is not blocked.
+    pub fn block_reason(&self) -> Option<BlockReason> {
+        match self.state {
+            ThreadState::Blocked { reason, .. } => Some(reason),
+            ThreadState::Enabled | ThreadState::Terminated => None,
+        }
+    }
+
+    /// Borrow the virtual call stack of this thread.
+    pub fn stack(&self) -> &[Frame<'tcx, Provenance, FrameExtra<'tcx>>] {
+        &self.stack
+    }
+
     /// Get the name of the current thread for display purposes; will include thread ID if not set.
     fn thread_display_name(&self, id: ThreadId) -> String {
         if let Some(ref thread_name) = self.thread_name {
@@ -311,6 +336,7 @@ impl<'tcx> Thread<'tcx> {
             origin_span: DUMMY_SP,
             top_user_relevant_frame: None,
             join_status: ThreadJoinStatus::Joinable,
+            awaited_lock: None,
             unwind_payloads: Vec::new(),
             last_error: None,
             on_stack_empty,
@@ -329,6 +355,7 @@ impl VisitProvenance for Thread<'_> {
             state: _,
             thread_name: _,
             join_status: _,
+            awaited_lock,
             on_stack_empty: _, // we assume the closure captures no GC-relevant state
         } = self;
 
@@ -336,6 +363,7 @@ impl VisitProvenance for Thread<'_> {
             payload.visit_provenance(visit);
         }
         last_error.visit_provenance(visit);
+        awaited_lock.visit_provenance(visit);
         for frame in stack {
             frame.visit_provenance(visit)
         }
@@ -397,6 +425,12 @@ pub struct ThreadManager<'tcx> {
     pub(super) yield_active_thread: bool,
     /// A flag that indicates that we should do round robin scheduling of threads else randomized scheduling is used.
     fixed_scheduling: bool,
+    /// The state of PCT scheduling, if enabled. Completely ignored in GenMC mode.
+    pct: Option<PctScheduler>,
+    /// A thread the scheduler keeps running for as long as it is enabled, ignoring yields and
+    /// preemption. Used by debuggers to keep stepping on one thread.
+    /// Completely ignored in GenMC mode.
+    pinned_thread: Option<ThreadId>,
 }
 
 impl VisitProvenance for ThreadManager<'_> {
@@ -407,6 +441,8 @@ impl VisitProvenance for ThreadManager<'_> {
             active_thread: _,
             yield_active_thread: _,
             fixed_scheduling: _,
+            pct: _,
+            pinned_thread: _,
         } = self;
 
         for thread in threads {
@@ -419,7 +455,7 @@ impl VisitProvenance for ThreadManager<'_> {
 }
 
 impl<'tcx> ThreadManager<'tcx> {
-    pub(crate) fn new(config: &MiriConfig) -> Self {
+    pub(crate) fn new(config: &MiriConfig, rng: &mut impl Rng) -> Self {
         let mut threads = IndexVec::new();
         // Create the main thread and add it to the list of threads.
         threads.push(Thread::new(Some("main"), None));
@@ -429,6 +465,8 @@ impl<'tcx> ThreadManager<'tcx> {
             thread_local_allocs: Default::default(),
             yield_active_thread: false,
             fixed_scheduling: config.fixed_scheduling,
+            pct: config.pct_scheduling.map(|pct| PctScheduler::new(pct, rng)),
+            pinned_thread: None,
         }
     }
 
@@ -489,7 +527,7 @@ impl<'tcx> ThreadManager<'tcx> {
         &mut self.threads[self.active_thread].stack
     }
 
-    pub(super) fn all_threads(&self) -> impl Iterator<Item = (ThreadId, &Thread<'tcx>)> {
+    pub fn all_threads(&self) -> impl Iterator<Item = (ThreadId, &Thread<'tcx>)> {
         self.threads.iter_enumerated()
     }
 
@@ -503,9 +541,16 @@ impl<'tcx> ThreadManager<'tcx> {
     }
 
     /// Create a new thread and returns its id.
-    fn create_thread(&mut self, on_stack_empty: StackEmptyCallback<'tcx>) -> ThreadId {
+    fn create_thread(
+        &mut self,
+        on_stack_empty: StackEmptyCallback<'tcx>,
+        rng: &mut impl Rng,
+    ) -> ThreadId {
         let new_thread_id = ThreadId::new(self.threads.len());
         self.threads.push(Thread::new(None, Some(on_stack_empty)));
+        if let Some(pct) = &mut self.pct {
+            pct.add_thread(new_thread_id, rng);
+        }
         new_thread_id
     }
 
@@ -621,6 +666,22 @@ impl<'tcx> ThreadManager<'tcx> {
     pub fn fixed_scheduling(&self) -> bool {
         self.fixed_scheduling
     }
+
+    pub fn pct(&self) -> Option<&PctScheduler> {
+        self.pct.as_ref()
+    }
+
+    pub fn pinned_thread(&self) -> Option<ThreadId> {
+        self.pinned_thread
+    }
+
+    /// Pin the scheduler to the given thread, or unpin it with `None`.
+    ///
+    /// While the pinned thread is enabled, the scheduler will not switch away from it. Once it
+    /// blocks or terminates, the other threads get scheduled as usual.
+    pub fn set_pinned_thread(&mut self, thread: Option<ThreadId>) {
+        self.pinned_thread = thread;
+    }
 }
 
 // Public interface to thread management.
@@ -673,6 +734,75 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         }
     }
 
+    /// Describes what the given blocked thread is waiting for, and returns the threads it waits
+    /// for, as far as we know them. Used to explain deadlocks.
+    fn describe_wait(&self, thread: ThreadId) -> (String, Vec<ThreadId>) {
+        let this = self.eval_context_ref();
+        let threads = &this.machine.threads;
+        let thread_ref = threads.thread_ref(thread);
+        let reason = thread_ref.block_reason().expect("thread is not blocked");
+        if let Some(lock) = &thread_ref.awaited_lock {
+            let holders = lock.holders(this);
+            let held_by = match holders.as_slice() {
+                [] => String::new(),
+                [holder] =>
+                    format!(" held by thread `{}`", threads.get_thread_display_name(*holder)),
+                [holders @ .., last] => {
+                    let holders: Vec<_> = holders
+                        .iter()
+                        .map(|holder| format!("`{}`", threads.get_thread_display_name(*holder)))
+                        .collect();
+                    format!(
+                        " held by threads {} and `{}`",
+                        holders.join(", "),
+                        threads.get_thread_display_name(*last)
+                    )
+                }
+            };
+            return match lock {
+                AwaitedLock::Mutex(_) => (format!("waiting to lock a mutex{held_by}"), holders),
+                AwaitedLock::RwLock { write: false, .. } =>
+                    (format!("waiting to read-lock a reader-writer lock{held_by}"), holders),
+                AwaitedLock::RwLock { write: true, .. } =>
+                    (format!("waiting to write-lock a reader-writer lock{held_by}"), holders),
+                AwaitedLock::Futex(_) => (format!("waiting on a futex{held_by}"), holders),
+                // The thread waits for a signal, not for the threads holding the mutex.
+                AwaitedLock::Condvar(_) =>
+                    (
+                        format!(
+                            "waiting on a condition variable, and will then re-lock a mutex{held_by}"
+                        ),
+                        vec![],
+                    ),
+            };
+        }
+        let description = match reason {
+            BlockReason::Join(joined) => {
+                let description = format!(
+                    "waiting for thread `{}` to terminate",
+                    threads.get_thread_display_name(joined)
+                );
+                return (description, vec![joined]);
+            }
+            BlockReason::Sleep => "sleeping",
+            BlockReason::Mutex => "waiting to lock a mutex",
+            BlockReason::Condvar => "waiting on a condition variable",
+            BlockReason::RwLock => "waiting to lock a reader-writer lock",
+            BlockReason::Futex => "waiting on a futex",
+            BlockReason::InitOnce => "waiting for a one-time initialization to complete",
+            BlockReason::Readiness => "waiting for a file descriptor to become ready",
+            BlockReason::Eventfd => "waiting on an eventfd",
+            BlockReason::TimerFd => "waiting on a timerfd",
+            BlockReason::SignalFd => "waiting on a signalfd",
+            BlockReason::Inotify => "waiting on an inotify instance",
+            BlockReason::VirtualSocket => "waiting on a socket or pipe",
+            BlockReason::IO => "waiting for host I/O",
+            BlockReason::ChildProcess => "waiting for a child process to exit",
+            BlockReason::Genmc => "waiting for GenMC",
+        };
+        (description.to_owned(), vec![])
+    }
+
     /// Start a regular (non-main) thread.
     #[inline]
     fn start_regular_thread(
@@ -687,10 +817,13 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
 
         // Create the new thread
         let current_span = this.machine.current_user_relevant_span();
-        let new_thread_id = this.machine.threads.create_thread({
-            let mut state = tls::TlsDtorsState::default();
-            Box::new(move |m| state.on_stack_empty(m))
-        });
+        let new_thread_id = this.machine.threads.create_thread(
+            {
+                let mut state = tls::TlsDtorsState::default();
+                Box::new(move |m| state.on_stack_empty(m))
+            },
+            this.machine.rng.get_mut(),
+        );
         match &mut this.machine.data_race {
             GlobalDataRaceHandler::None => {}
             GlobalDataRaceHandler::Vclocks(data_race) =>
@@ -789,6 +922,10 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
             }
         }
 
+        if let Some(lock_order) = &mut this.machine.lock_order {
+            lock_order.thread_terminated(gone_thread);
+        }
+
         match &mut this.machine.data_race {
             GlobalDataRaceHandler::None => {}
             GlobalDataRaceHandler::Vclocks(data_race) =>
@@ -840,6 +977,7 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         let this = self.eval_context_mut();
         let old_state =
             mem::replace(&mut this.machine.threads.threads[thread].state, ThreadState::Enabled);
+        this.machine.threads.threads[thread].awaited_lock = None;
         let callback = match old_state {
             ThreadState::Blocked { reason: actual_reason, callback, .. } => {
                 assert_eq!(
@@ -908,6 +1046,18 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
             let old_thread = this.machine.threads.set_active_thread(thread);
             callback.call(this, UnblockKind::TimedOut)?;
             this.machine.threads.set_active_thread(old_thread);
+            // The callback might have blocked the thread again, with a new deadline.
+            if let ThreadState::Blocked { deadline: Some(deadline), .. } =
+                &this.machine.threads.threads[thread].state
+            {
+                let wait_time = match deadline {
+                    Deadline::Monotonic(instant) =>
+                        instant.duration_since(this.machine.monotonic_clock.now()),
+                    Deadline::RealTime(time) =>
+                        time.duration_since(SystemTime::now()).unwrap_or(Duration::ZERO),
+                };
+                min_wait_time = Some(wait_time.min(min_wait_time.unwrap_or(Duration::MAX)));
+            }
         }
 
         interp_ok(min_wait_time)
@@ -1061,6 +1211,13 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         this.machine.threads.enable_thread(thread_id);
     }
 
+    /// Borrow the thread manager. Public because this is used by Priroda.
+    #[inline]
+    fn thread_manager<'a>(&'a self) -> &'a ThreadManager<'tcx> {
+        let this = self.eval_context_ref();
+        &this.machine.threads
+    }
+
     #[inline]
     fn active_thread_stack<'a>(&'a self) -> &'a [Frame<'tcx, Provenance, FrameExtra<'tcx>>] {
         let this = self.eval_context_ref();
@@ -1089,21 +1246,52 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         self.eval_context_ref().machine.threads.get_thread_name(thread)
     }
 
+    /// Pin the scheduler to the given thread. Public because this is used by Priroda.
+    #[inline]
+    fn set_pinned_thread(&mut self, thread: Option<ThreadId>) {
+        self.eval_context_mut().machine.threads.set_pinned_thread(thread);
+    }
+
     #[inline]
     fn yield_active_thread(&mut self) {
+        let threads = &mut self.eval_context_mut().machine.threads;
+        // With PCT, a thread that yields gives up its priority. Otherwise a spin loop in a
+        // high-priority thread would keep waiting for a lower-priority thread that never runs.
+        if let Some(pct) = &mut threads.pct {
+            pct.thread_yielded(threads.active_thread);
+        }
         // We do not yield immediately, as swapping out the current stack while executing a MIR statement
         // could lead to all sorts of confusion.
         // We should only switch stacks between steps.
-        self.eval_context_mut().machine.threads.yield_active_thread = true;
+        threads.yield_active_thread = true;
     }
 
     #[inline]
     fn maybe_preempt_active_thread(&mut self) {
         let this = self.eval_context_mut();
-        if !this.machine.threads.fixed_scheduling
-            && this.machine.rng.get_mut().random_bool(this.machine.preemption_rate)
-        {
-            this.yield_active_thread();
+        // When replaying a schedule, the trace says whether to preempt here.
+        let preempt = if let Some(preempt) = this.machine.replay_preemption() {
+            preempt
+        } else if let Some(pct) = &mut this.machine.threads.pct {
+            let threads = &this.machine.threads.threads;
+            let active_thread = this.machine.threads.active_thread;
+            // Only steps where some other thread could run instead count towards the change
+            // points, everything else would be wasted on steps that cannot lead to a preemption.
+            let mut enabled = threads.iter().filter(|thread| thread.is_enabled());
+            if enabled.nth(1).is_some() {
+                pct.step(active_thread);
+            }
+            // The scheduler switches to the thread with the highest priority.
+            let next = pct.highest_priority(|id| threads[id].is_enabled());
+            next.is_some_and(|next| next != active_thread)
+        } else {
+            !this.machine.threads.fixed_scheduling
+                && this.machine.rng.get_mut().random_bool(this.machine.preemption_rate)
+        };
+        this.machine.record_preemption(preempt);
+        if preempt {
+            // Unlike `yield_active_thread`, this keeps the PCT priority of the active thread.
+            this.machine.threads.yield_active_thread = true;
         }
     }
 }
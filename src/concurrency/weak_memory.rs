@@ -297,17 +297,18 @@ impl<'tcx> StoreBuffer {
         thread_mgr: &ThreadManager<'_>,
         is_seqcst: bool,
         rng: &mut (impl rand::Rng + ?Sized),
+        replayed: Option<usize>,
         validate: impl FnOnce(Option<&VClock>) -> InterpResult<'tcx>,
-    ) -> InterpResult<'tcx, (Option<Scalar>, LoadRecency)> {
+    ) -> InterpResult<'tcx, (Option<Scalar>, LoadRecency, usize)> {
         // Having a live borrow to store_buffer while calling validate_atomic_load is fine
         // because the race detector doesn't touch store_buffer
 
-        let (store_elem, recency) = {
+        let (store_elem, recency, choice) = {
             // The `clocks` we got here must be dropped before calling validate_atomic_load
             // as the race detector will update it
             let (.., clocks) = global.active_thread_state(thread_mgr);
             // Load from a valid entry in the store buffer
-            self.fetch_store(is_seqcst, &clocks, &mut *rng)
+            self.fetch_store(is_seqcst, &clocks, &mut *rng, replayed)
         };
 
         // Unlike in buffered_atomic_write, thread clock updates have to be done
@@ -318,7 +319,7 @@ impl<'tcx> StoreBuffer {
 
         let (index, clocks) = global.active_thread_state(thread_mgr);
         let loaded = store_elem.load_impl(index, &clocks, is_seqcst);
-        interp_ok((loaded, recency))
+        interp_ok((loaded, recency, choice))
     }
 
     fn buffered_write(
@@ -335,22 +336,47 @@ impl<'tcx> StoreBuffer {
         interp_ok(())
     }
 
-    /// Selects a valid store element in the buffer.
+    /// Selects a valid store element in the buffer. Uses the `replayed` one if it exists, and
+    /// picks a random one otherwise. Also returns the index of the chosen element among the valid
+    /// ones, counting backwards from the latest.
     fn fetch_store<R: rand::Rng + ?Sized>(
         &self,
         is_seqcst: bool,
         clocks: &ThreadClockSet,
         rng: &mut R,
-    ) -> (&StoreElement, LoadRecency) {
+        replayed: Option<usize>,
+    ) -> (&StoreElement, LoadRecency, usize) {
         use rand::seq::IteratorRandom;
+        let (choice, chosen) = replayed
+            .and_then(|choice| {
+                self.load_candidates(is_seqcst, clocks).nth(choice).map(|chosen| (choice, chosen))
+            })
+            .unwrap_or_else(|| {
+                self.load_candidates(is_seqcst, clocks)
+                    .enumerate()
+                    .choose(rng)
+                    .expect("store buffer cannot be empty")
+            });
+        if std::ptr::eq(chosen, self.buffer.back().expect("store buffer cannot be empty")) {
+            (chosen, LoadRecency::Latest, choice)
+        } else {
+            (chosen, LoadRecency::Outdated, choice)
+        }
+    }
+
+    /// Returns the store elements a load can read from, latest first.
+    fn load_candidates<'a>(
+        &'a self,
+        is_seqcst: bool,
+        clocks: &'a ThreadClockSet,
+    ) -> impl Iterator<Item = &'a StoreElement> {
         let mut found_sc = false;
         // FIXME: we want an inclusive take_while (stops after a false predicate, but
         // includes the element that gave the false), but such function doesn't yet
         // exist in the standard library https://github.com/rust-lang/rust/issues/62208
         // so we have to hack around it with keep_searching
         let mut keep_searching = true;
-        let candidates = self
-            .buffer
+        self.buffer
             .iter()
             .rev()
             .take_while(move |&store_elem| {
@@ -398,7 +424,7 @@ impl<'tcx> StoreBuffer {
 
                 true
             })
-            .filter(|&store_elem| {
+            .filter(move |&store_elem| {
                 if is_seqcst && store_elem.is_seqcst {
                     // An SC load needs to ignore all but last store marked SC (stores not marked SC are not
                     // affected)
@@ -408,14 +434,7 @@ impl<'tcx> StoreBuffer {
                 } else {
                     true
                 }
-            });
-
-        let chosen = candidates.choose(rng).expect("store buffer cannot be empty");
-        if std::ptr::eq(chosen, self.buffer.back().expect("store buffer cannot be empty")) {
-            (chosen, LoadRecency::Latest)
-        } else {
-            (chosen, LoadRecency::Outdated)
-        }
+            })
     }
 
     /// ATOMIC STORE IMPL in the paper
@@ -566,13 +585,29 @@ pub(super) trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
                         // No old writes available, fall back to base case.
                         break 'fallback;
                     };
-                    let (loaded, recency) = buffer.buffered_read(
+                    let replayed = this.machine.replay_decision("a weak memory load", |decision| {
+                        match decision {
+                            Decision::Load(choice) => Some(choice),
+                            _ => None,
+                        }
+                    });
+                    let (loaded, recency, choice) = buffer.buffered_read(
                         global,
                         &this.machine.threads,
                         atomic == AtomicReadOrd::SeqCst,
                         &mut *rng,
+                        replayed,
                         validate,
                     )?;
+                    if let Some(replayed) = replayed
+                        && replayed != choice
+                    {
+                        this.machine.replay_rejected(format!(
+                            "this load can read from fewer than {} stores",
+                            replayed.strict_add(1)
+                        ));
+                    }
+                    this.machine.record_decision(Decision::Load(choice));
                     if global.track_outdated_loads && recency == LoadRecency::Outdated {
                         this.emit_diagnostic(NonHaltingDiagnostic::WeakMemoryOutdatedLoad {
                             ptr: place.ptr(),
@@ -20,6 +20,8 @@ pub enum TerminationInfo {
     Abort(String),
     /// Miri was interrupted by a Ctrl+C from the user.
     Interrupted,
+    /// The program executed more basic blocks than `MiriConfig::max_basic_blocks` allows.
+    BasicBlockLimit,
     UnsupportedInIsolation(String),
     StackedBorrowsUb {
         msg: String,
@@ -74,6 +76,7 @@ impl fmt::Display for TerminationInfo {
             Exit { code, .. } => write!(f, "the evaluated program completed with exit code {code}"),
             Abort(msg) => write!(f, "{msg}"),
             Interrupted => write!(f, "interpretation was interrupted"),
+            BasicBlockLimit => write!(f, "the program executed too many basic blocks"),
             UnsupportedInIsolation(msg) => write!(f, "{msg}"),
             Int2PtrWithStrictProvenance =>
                 write!(
@@ -159,6 +162,19 @@ pub enum NonHaltingDiagnostic {
     SocketAddressResolution {
         error: std::io::Error,
     },
+    /// The replayed schedule did not match the program. `recorded` is the number, the text and the
+    /// location of the recorded decision that could not be replayed, if any.
+    ScheduleReplayDiverged {
+        recorded: Option<(usize, String, String)>,
+        reason: String,
+    },
+    /// A thread acquired `acquired` while holding `held`. `cycle` are the earlier acquisitions
+    /// that lead from `acquired` back to `held`.
+    LockOrderInversion {
+        held: LockId,
+        acquired: LockId,
+        cycle: Vec<LockAcquisition>,
+    },
 }
 
 /// Level of Miri specific diagnostics
@@ -253,7 +269,7 @@ pub fn report_result<'tcx>(
         let title = match info {
             &Exit { code, leak_check } => return Some((code, leak_check)),
             Abort(_) => Some("abnormal termination"),
-            Interrupted => None,
+            Interrupted | BasicBlockLimit => None,
             UnsupportedInIsolation(_) | Int2PtrWithStrictProvenance | UnsupportedForeignItem(_) =>
                 Some("unsupported operation"),
             StackedBorrowsUb { .. } | TreeBorrowsUb { .. } | DataRace { .. } =>
@@ -267,10 +283,15 @@ pub fn report_result<'tcx>(
                 None
             }
             GlobalDeadlock => {
-                // Global deadlocks are reported differently: just show all blocked threads.
-                // The "active" thread might actually be terminated, so we ignore it.
+                // Global deadlocks are reported differently: just show all blocked threads and what
+                // they are waiting for. The "active" thread might actually be terminated, so we
+                // ignore it.
+                let threads = &ecx.machine.threads;
                 let mut any_pruned = false;
-                for (thread, stack) in ecx.machine.threads.all_blocked_stacks() {
+                let mut waits_for = Vec::new();
+                for (thread, stack) in threads.all_blocked_stacks() {
+                    let (wait, holders) = ecx.describe_wait(thread);
+                    waits_for.push((thread, holders));
                     let stacktrace = Frame::generate_stacktrace_from_stack(stack, *ecx.tcx);
                     let (stacktrace, was_pruned) = prune_stacktrace(stacktrace, &ecx.machine);
                     any_pruned |= was_pruned;
@@ -278,13 +299,22 @@ pub fn report_result<'tcx>(
                         DiagLevel::Error,
                         format!("the evaluated program deadlocked"),
                         vec![format!("thread got stuck here")],
-                        vec![],
+                        vec![(None, format!("this thread is {wait}"))],
                         vec![],
                         &stacktrace,
                         Some(thread),
                         &ecx.machine,
                     )
                 }
+                if let Some(cycle) = find_wait_cycle(&waits_for) {
+                    let cycle: Vec<_> = cycle
+                        .into_iter()
+                        .map(|thread| format!("`{}`", threads.get_thread_display_name(thread)))
+                        .collect();
+                    ecx.tcx
+                        .dcx()
+                        .note(format!("the waiting threads form a cycle: {}", cycle.join(" -> ")));
+                }
                 if any_pruned {
                     ecx.tcx.dcx().note(
                         "some details are omitted, run with `MIRIFLAGS=-Zmiri-backtrace=full` for a verbose backtrace"
@@ -306,36 +336,8 @@ pub fn report_result<'tcx>(
                     note!("this means the program tried to do something Miri does not support; it does not indicate a bug in the program"),
                 ]
             }
-            StackedBorrowsUb { help, history, .. } => {
-                labels.extend(help.clone());
-                let mut helps = vec![
-                    note!("this indicates a potential bug in the program: it performed an invalid operation, but the Stacked Borrows rules it violated are still experimental"),
-                    note!("see https://github.com/rust-lang/unsafe-code-guidelines/blob/master/wip/stacked-borrows.md for further information"),
-                ];
-                if let Some(TagHistory {created, invalidated, protected}) = history.clone() {
-                    helps.push((Some(created.1), created.0));
-                    if let Some((msg, span)) = invalidated {
-                        helps.push(note_span!(span, "{msg}"));
-                    }
-                    if let Some((protector_msg, protector_span)) = protected {
-                        helps.push(note_span!(protector_span, "{protector_msg}"));
-                    }
-                }
-                helps
-            },
-            TreeBorrowsUb { title: _, details, history } => {
-                let mut helps = vec![
-                    note!("this indicates a potential bug in the program: it performed an invalid operation, but the Tree Borrows rules it violated are still experimental"),
-                    note!("see https://github.com/rust-lang/unsafe-code-guidelines/blob/master/wip/tree-borrows.md for further information"),
-                ];
-                for m in details {
-                    helps.push(note!("{m}"));
-                }
-                for event in history.events.clone() {
-                    helps.push(event);
-                }
-                helps
-            }
+            StackedBorrowsUb { .. } | TreeBorrowsUb { .. } | DataRace { .. } =>
+                termination_ub_helps(info, &mut labels),
             MultipleSymbolDefinitions { first, first_crate, second, second_crate, .. } =>
                 vec![
                     note_span!(*first, "it's first defined here, in crate `{first_crate}`"),
@@ -345,23 +347,6 @@ pub fn report_result<'tcx>(
                 vec![note_span!(*span, "the `{link_name}` symbol is defined here")],
             Int2PtrWithStrictProvenance =>
                 vec![note!("use Strict Provenance APIs (https://doc.rust-lang.org/nightly/std/ptr/index.html#strict-provenance, https://crates.io/crates/sptr) instead")],
-            DataRace { op1, extra, retag_explain, .. } => {
-                labels.push(format!("(2) just happened here"));
-                let mut helps = vec![note_span!(op1.span, "and (1) occurred earlier here")];
-                if let Some(extra) = extra {
-                    helps.push(note!("{extra}"));
-                    helps.push(note!("see https://doc.rust-lang.org/nightly/std/sync/atomic/index.html#memory-model-for-atomic-accesses for more information about the Rust memory model"));
-                }
-                if *retag_explain {
-                    helps.push(note!("retags occur on all (re)borrows and as well as when references are copied or moved"));
-                    helps.push(note!("retags permit optimizations that insert speculative reads or writes"));
-                    helps.push(note!("therefore from the perspective of data races, a retag has the same implications as a read or write"));
-                }
-                helps.push(note!("this indicates a bug in the program: it performed an invalid operation, and caused Undefined Behavior"));
-                helps.push(note!("see https://doc.rust-lang.org/nightly/reference/behavior-considered-undefined.html for further information"));
-                helps
-            }
-                ,
             _ => vec![],
         };
         (title, helps)
@@ -401,35 +386,7 @@ pub fn report_result<'tcx>(
                 vec![
                     note!("in GenMC mode, the address space is limited to 4GB per thread, and addresses cannot be reused")
                 ],
-            UndefinedBehavior(AlignmentCheckFailed { .. })
-                if ecx.machine.check_alignment == AlignmentCheck::Symbolic
-            =>
-                vec![
-                    note!("this usually indicates that your program performed an invalid operation and caused Undefined Behavior"),
-                    note!("but due to `-Zmiri-symbolic-alignment-check`, alignment errors can also be false positives"),
-                ],
-            UndefinedBehavior(info) => {
-                let mut helps = vec![
-                    note!("this indicates a bug in the program: it performed an invalid operation, and caused Undefined Behavior"),
-                    note!("see https://doc.rust-lang.org/nightly/reference/behavior-considered-undefined.html for further information"),
-                ];
-                match info {
-                    PointerUseAfterFree(alloc_id, _) | PointerOutOfBounds { alloc_id, .. } => {
-                        if let Some(span) = ecx.machine.allocated_span(*alloc_id) {
-                            helps.push(note_span!(span, "{alloc_id} was allocated here:"));
-                        }
-                        if let Some(span) = ecx.machine.deallocated_span(*alloc_id) {
-                            helps.push(note_span!(span, "{alloc_id} was deallocated here:"));
-                        }
-                    }
-                    AbiMismatchArgument { .. } | AbiMismatchReturn { .. } => {
-                        helps.push(note!("this means these two types are not *guaranteed* to be ABI-compatible across all targets"));
-                        helps.push(note!("if you think this code should be accepted anyway, please report an issue with Miri"));
-                    }
-                    _ => {},
-                }
-                helps
-            }
+            UndefinedBehavior(info) => undefined_behavior_helps(ecx, info),
             InvalidProgram(
                 InvalidProgramInfo::AlreadyReported(_)
             ) => {
@@ -508,6 +465,228 @@ pub fn report_result<'tcx>(
     None
 }
 
+/// Finds a cycle in the graph of which blocked thread waits for which other threads, searching from
+/// the threads in the given order. Returns the threads on the cycle, with the first one repeated at
+/// the end.
+fn find_wait_cycle(waits_for: &[(ThreadId, Vec<ThreadId>)]) -> Option<Vec<ThreadId>> {
+    fn visit(
+        thread: ThreadId,
+        waits_for: &[(ThreadId, Vec<ThreadId>)],
+        path: &mut Vec<ThreadId>,
+        visited: &mut FxHashSet<ThreadId>,
+    ) -> Option<Vec<ThreadId>> {
+        if let Some(start) = path.iter().position(|&other| other == thread) {
+            let mut cycle = path[start..].to_vec();
+            cycle.push(thread);
+            return Some(cycle);
+        }
+        if !visited.insert(thread) {
+            return None;
+        }
+        path.push(thread);
+        // Threads that are not blocked do not wait for anyone.
+        let holders = waits_for.iter().find(|(waiter, _)| *waiter == thread);
+        for &holder in holders.map_or(&[][..], |(_, holders)| holders) {
+            if let Some(cycle) = visit(holder, waits_for, path, visited) {
+                return Some(cycle);
+            }
+        }
+        path.pop();
+        None
+    }
+
+    let mut visited = FxHashSet::default();
+    waits_for
+        .iter()
+        .find_map(|&(thread, _)| visit(thread, waits_for, &mut Vec::new(), &mut visited))
+}
+
+/// Describes how the program failed, without anything that differs between executions (like
+/// addresses or tags), so that schedule minimization can tell whether two executions fail the same
+/// way. Returns `None` if the program did not fail.
+pub fn failure_summary<'tcx>(
+    ecx: &InterpCx<'tcx, MiriMachine<'tcx>>,
+    res: &InterpErrorInfo<'tcx>,
+) -> Option<String> {
+    use InterpErrorKind::*;
+
+    let source_map = ecx.tcx.sess.source_map();
+    let location = source_map.span_to_diagnostic_string(ecx.machine.current_user_relevant_span());
+    let info = match res.kind() {
+        MachineStop(info) =>
+            info.downcast_ref::<TerminationInfo>().expect("invalid MachineStop payload"),
+        UndefinedBehavior(_) => return Some(format!("Undefined Behavior at {location}")),
+        ResourceExhaustion(_) => return Some(format!("resource exhaustion at {location}")),
+        Unsupported(_) => return Some(format!("unsupported operation at {location}")),
+        _ => return Some(format!("error at {location}")),
+    };
+    use TerminationInfo::*;
+    Some(match info {
+        Exit { code: 0, .. } | Interrupted | BasicBlockLimit | GenmcMoot => return None,
+        Exit { code, .. } => format!("exit code {code}"),
+        StackedBorrowsUb { .. } | TreeBorrowsUb { .. } =>
+            format!("Undefined Behavior at {location}"),
+        DataRace { op1, op2, .. } =>
+            format!(
+                "race between {} at {} and {} at {}",
+                op1.action,
+                source_map.span_to_diagnostic_string(op1.span.span()),
+                op2.action,
+                source_map.span_to_diagnostic_string(op2.span.span()),
+            ),
+        GlobalDeadlock => {
+            let mut blocked: Vec<_> = ecx
+                .machine
+                .threads
+                .all_threads()
+                .filter(|(_id, thread)| !thread.is_enabled() && !thread.is_terminated())
+                .map(|(_id, thread)| {
+                    source_map.span_to_diagnostic_string(thread.current_user_relevant_span())
+                })
+                .collect();
+            blocked.sort();
+            format!("deadlock with threads blocked at {}", blocked.join(", "))
+        }
+        _ => format!("{info} at {location}"),
+    })
+}
+
+/// The message, labels, and help notes Miri reports for an Undefined Behavior error.
+pub struct UbDiagnostic {
+    /// The primary message, starting with `Undefined Behavior: `.
+    pub message: String,
+    /// Labels attached to the location where the UB occurred.
+    pub labels: Vec<String>,
+    /// Help notes, each optionally pointing at a related location.
+    pub helps: Vec<(Option<SpanData>, String)>,
+}
+
+/// Describes `err` the way `report_result` would, without printing anything, if it is Undefined
+/// Behavior (including borrow-tracker violations and data races). This lets embedders such as
+/// Priroda keep the machine around for inspection instead of ending execution.
+pub fn undefined_behavior_diagnostic<'tcx>(
+    ecx: &InterpCx<'tcx, MiriMachine<'tcx>>,
+    err: &InterpErrorInfo<'tcx>,
+) -> Option<UbDiagnostic> {
+    use TerminationInfo::*;
+
+    let mut labels = vec![];
+    let helps = match err.kind() {
+        InterpErrorKind::MachineStop(info) =>
+            match info.downcast_ref::<TerminationInfo>() {
+                Some(info @ (StackedBorrowsUb { .. } | TreeBorrowsUb { .. } | DataRace { .. })) =>
+                    termination_ub_helps(info, &mut labels),
+                _ => return None,
+            },
+        InterpErrorKind::UndefinedBehavior(info) => undefined_behavior_helps(ecx, info),
+        _ => return None,
+    };
+    Some(UbDiagnostic {
+        message: format!("Undefined Behavior: {}", err.to_string()),
+        labels,
+        helps,
+    })
+}
+
+/// Help notes for the `TerminationInfo` variants that report Undefined Behavior. Labels for the
+/// primary span are pushed to `labels`.
+#[rustfmt::skip]
+fn termination_ub_helps(
+    info: &TerminationInfo,
+    labels: &mut Vec<String>,
+) -> Vec<(Option<SpanData>, String)> {
+    use TerminationInfo::*;
+    match info {
+        StackedBorrowsUb { help, history, .. } => {
+            labels.extend(help.clone());
+            let mut helps = vec![
+                note!("this indicates a potential bug in the program: it performed an invalid operation, but the Stacked Borrows rules it violated are still experimental"),
+                note!("see https://github.com/rust-lang/unsafe-code-guidelines/blob/master/wip/stacked-borrows.md for further information"),
+            ];
+            if let Some(TagHistory {created, invalidated, protected}) = history.clone() {
+                helps.push((Some(created.1), created.0));
+                if let Some((msg, span)) = invalidated {
+                    helps.push(note_span!(span, "{msg}"));
+                }
+                if let Some((protector_msg, protector_span)) = protected {
+                    helps.push(note_span!(protector_span, "{protector_msg}"));
+                }
+            }
+            helps
+        },
+        TreeBorrowsUb { title: _, details, history } => {
+            let mut helps = vec![
+                note!("this indicates a potential bug in the program: it performed an invalid operation, but the Tree Borrows rules it violated are still experimental"),
+                note!("see https://github.com/rust-lang/unsafe-code-guidelines/blob/master/wip/tree-borrows.md for further information"),
+            ];
+            for m in details {
+                helps.push(note!("{m}"));
+            }
+            for event in history.events.clone() {
+                helps.push(event);
+            }
+            helps
+        }
+        DataRace { op1, extra, retag_explain, .. } => {
+            labels.push(format!("(2) just happened here"));
+            let mut helps = vec![note_span!(op1.span, "and (1) occurred earlier here")];
+            if let Some(extra) = extra {
+                helps.push(note!("{extra}"));
+                helps.push(note!("see https://doc.rust-lang.org/nightly/std/sync/atomic/index.html#memory-model-for-atomic-accesses for more information about the Rust memory model"));
+            }
+            if *retag_explain {
+                helps.push(note!("retags occur on all (re)borrows and as well as when references are copied or moved"));
+                helps.push(note!("retags permit optimizations that insert speculative reads or writes"));
+                helps.push(note!("therefore from the perspective of data races, a retag has the same implications as a read or write"));
+            }
+            helps.push(note!("this indicates a bug in the program: it performed an invalid operation, and caused Undefined Behavior"));
+            helps.push(note!("see https://doc.rust-lang.org/nightly/reference/behavior-considered-undefined.html for further information"));
+            helps
+        }
+        _ => bug!("not an Undefined Behavior termination: {info}"),
+    }
+}
+
+/// Help notes for an `UndefinedBehaviorInfo` error.
+#[rustfmt::skip]
+fn undefined_behavior_helps<'tcx>(
+    ecx: &InterpCx<'tcx, MiriMachine<'tcx>>,
+    info: &UndefinedBehaviorInfo<'tcx>,
+) -> Vec<(Option<SpanData>, String)> {
+    use UndefinedBehaviorInfo::*;
+    match info {
+        AlignmentCheckFailed { .. }
+            if ecx.machine.check_alignment == AlignmentCheck::Symbolic
+        =>
+            vec![
+                note!("this usually indicates that your program performed an invalid operation and caused Undefined Behavior"),
+                note!("but due to `-Zmiri-symbolic-alignment-check`, alignment errors can also be false positives"),
+            ],
+        _ => {
+            let mut helps = vec![
+                note!("this indicates a bug in the program: it performed an invalid operation, and caused Undefined Behavior"),
+                note!("see https://doc.rust-lang.org/nightly/reference/behavior-considered-undefined.html for further information"),
+            ];
+            match info {
+                PointerUseAfterFree(alloc_id, _) | PointerOutOfBounds { alloc_id, .. } => {
+                    if let Some(span) = ecx.machine.allocated_span(*alloc_id) {
+                        helps.push(note_span!(span, "{alloc_id} was allocated here:"));
+                    }
+                    if let Some(span) = ecx.machine.deallocated_span(*alloc_id) {
+                        helps.push(note_span!(span, "{alloc_id} was deallocated here:"));
+                    }
+                }
+                AbiMismatchArgument { .. } | AbiMismatchReturn { .. } => {
+                    helps.push(note!("this means these two types are not *guaranteed* to be ABI-compatible across all targets"));
+                    helps.push(note!("if you think this code should be accepted anyway, please report an issue with Miri"));
+                }
+                _ => {},
+            }
+            helps
+        }
+    }
+}
+
 pub fn report_leaks<'tcx>(
     ecx: &InterpCx<'tcx, MiriMachine<'tcx>>,
     leaks: Vec<(AllocId, MemoryKind, Allocation<Provenance, AllocExtra<'tcx>, MiriAllocBytes>)>,
@@ -639,6 +818,10 @@ impl<'tcx> MiriMachine<'tcx> {
     pub fn emit_diagnostic(&self, e: NonHaltingDiagnostic) {
         use NonHaltingDiagnostic::*;
 
+        if self.quiet {
+            return;
+        }
+
         let stacktrace =
             Frame::generate_stacktrace_from_stack(self.threads.active_thread_stack(), self.tcx);
         let (stacktrace, _was_pruned) = prune_stacktrace(stacktrace, self);
@@ -666,6 +849,10 @@ impl<'tcx> MiriMachine<'tcx> {
                 ("Called `getsockname` on connecting socket".to_string(), DiagLevel::Warning),
             SocketAddressResolution { .. } =>
                 ("error during address resolution".to_string(), DiagLevel::Warning),
+            ScheduleReplayDiverged { .. } =>
+                ("the replay diverged here".to_string(), DiagLevel::Warning),
+            LockOrderInversion { held, acquired, .. } =>
+                (format!("{acquired} acquired here while holding {held}"), DiagLevel::Warning),
         };
 
         let title = match &e {
@@ -716,6 +903,9 @@ impl<'tcx> MiriMachine<'tcx> {
             FileInProcOpened => format!("files in `/proc` can bypass the Abstract Machine and might not work properly in Miri"),
             ConnectingSocketGetsockname => format!("connecting sockets return unspecified socket addresses on Windows hosts"),
             SocketAddressResolution { error } => format!("address resolution failed: {error}"),
+            ScheduleReplayDiverged { reason, .. } => format!("schedule replay diverged: {reason}"),
+            LockOrderInversion { held, acquired, .. } =>
+                format!("potential deadlock: acquiring {acquired} while holding {held} inverts an earlier lock order"),
         };
 
         let notes = match &e {
@@ -735,6 +925,33 @@ impl<'tcx> MiriMachine<'tcx> {
                 vec![note!(
                     "Miri cannot return proper error information from this call; only a generic error code is being returned"
                 )],
+            ScheduleReplayDiverged { recorded, .. } => {
+                let mut v = vec![];
+                if let Some((number, decision, location)) = recorded {
+                    v.push(note!(
+                        "the trace expected decision {number} to be `{decision}`, recorded for {location}"
+                    ));
+                }
+                v.push(note!("all further decisions are made randomly"));
+                v
+            }
+            LockOrderInversion { held, acquired, cycle } => {
+                let mut v = vec![];
+                let mut locks = vec![held.to_string(), acquired.to_string()];
+                for LockAcquisition { thread, held, acquired, span } in cycle {
+                    let thread = self.threads.get_thread_display_name(*thread);
+                    v.push(note_span!(
+                        *span,
+                        "thread `{thread}` acquired {acquired} while holding {held} here"
+                    ));
+                    locks.push(acquired.to_string());
+                }
+                v.push(note!("the locks are acquired in a cycle: {}", locks.join(" -> ")));
+                v.push(note!(
+                    "under a different schedule, each thread can hold one of these locks while waiting for the next one, and the program deadlocks"
+                ));
+                v
+            }
             _ => vec![],
         };
 
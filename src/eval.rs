@@ -1,10 +1,11 @@
 //! Main evaluator loop and setting up the initial stack frame.
 
 use std::ffi::{OsStr, OsString};
-use std::num::NonZeroI32;
+use std::num::{NonZero, NonZeroI32};
 use std::panic::{self, AssertUnwindSafe};
 use std::path::PathBuf;
 use std::rc::Rc;
+use std::str::FromStr;
 use std::task::Poll;
 use std::{iter, thread};
 
@@ -23,7 +24,7 @@ use rustc_target::spec::Os;
 
 use crate::concurrency::GenmcCtx;
 use crate::concurrency::thread::TlsAllocAction;
-use crate::diagnostics::report_leaks;
+use crate::diagnostics::{failure_summary, report_leaks};
 use crate::helpers::is_no_core;
 use crate::shims::{global_ctor, tls};
 use crate::*;
@@ -103,6 +104,12 @@ pub struct MiriConfig {
     pub check_alignment: AlignmentCheck,
     /// Action for an op requiring communication with the host.
     pub isolated_op: IsolatedOp,
+    /// If set, file system operations use an in-memory file system seeded with these host
+    /// directory snapshots instead of the host file system.
+    pub vfs: Option<Vec<VfsMount>>,
+    /// If set, TCP sockets are connected through an in-memory loopback network instead of
+    /// being rejected under isolation.
+    pub virtual_network: bool,
     /// Determines if memory leaks should be ignored.
     pub ignore_leaks: bool,
     /// Environment variables that should always be forwarded from the host.
@@ -162,6 +169,18 @@ pub struct MiriConfig {
     pub address_reuse_cross_thread_rate: f64,
     /// Round Robin scheduling with no preemption.
     pub fixed_scheduling: bool,
+    /// Use PCT scheduling instead of random preemption.
+    pub pct_scheduling: Option<PctConfig>,
+    /// Write the scheduling decisions of the execution to this file.
+    pub record_schedule: Option<PathBuf>,
+    /// Force the scheduling decisions of this trace.
+    pub replay_schedule: Option<ScheduleTrace>,
+    /// Shrink the replayed trace to as few context switches as possible before running it.
+    pub minimize_schedule: bool,
+    /// Stop the program once it executed this many basic blocks.
+    pub max_basic_blocks: Option<u64>,
+    /// Warn about locks that get acquired in inconsistent orders.
+    pub check_lock_order: bool,
     /// Whether floating-point operations can behave non-deterministically.
     pub float_nondet: bool,
     /// Whether floating-point operations can have a non-deterministic rounding error.
@@ -180,6 +199,8 @@ impl Default for MiriConfig {
             borrow_tracker: Some(BorrowTrackerMethod::StackedBorrows),
             check_alignment: AlignmentCheck::Int,
             isolated_op: IsolatedOp::Reject(RejectOpWith::Abort),
+            vfs: None,
+            virtual_network: false,
             ignore_leaks: false,
             forwarded_env_vars: vec![],
             set_env_vars: FxHashMap::default(),
@@ -208,6 +229,12 @@ impl Default for MiriConfig {
             address_reuse_rate: 0.5,
             address_reuse_cross_thread_rate: 0.1,
             fixed_scheduling: false,
+            pct_scheduling: None,
+            record_schedule: None,
+            replay_schedule: None,
+            minimize_schedule: false,
+            max_basic_blocks: None,
+            check_lock_order: false,
             float_nondet: true,
             float_rounding_error: FloatRoundingErrorMode::Random,
             short_fd_operations: true,
@@ -216,6 +243,326 @@ impl Default for MiriConfig {
     }
 }
 
+impl MiriConfig {
+    /// Applies a `-Zmiri-...` flag to this configuration. Returns `Ok(false)` if `arg` is not a
+    /// flag that configures the interpreter; the driver then handles it itself or passes it on
+    /// to rustc.
+    pub fn parse_flag(&mut self, arg: &str) -> Result<bool, String> {
+        if arg == "-Zmiri-disable-validation" {
+            self.validation = ValidationMode::No;
+        } else if arg == "-Zmiri-recursive-validation" {
+            self.validation = ValidationMode::Deep;
+        } else if arg == "-Zmiri-disable-stacked-borrows" {
+            self.borrow_tracker = None;
+        } else if arg == "-Zmiri-tree-borrows" {
+            self.borrow_tracker = Some(BorrowTrackerMethod::TreeBorrows(TreeBorrowsParams {
+                precise_interior_mut: true,
+                implicit_writes: false,
+                // We default this to "unique" for now to keep the design space open.
+                box_custom_allocator_unique: true,
+            }));
+        } else if arg == "-Zmiri-tree-borrows-no-precise-interior-mut" {
+            match &mut self.borrow_tracker {
+                Some(BorrowTrackerMethod::TreeBorrows(params)) => {
+                    params.precise_interior_mut = false;
+                }
+                _ =>
+                    return Err(
+                        "`-Zmiri-tree-borrows` is required before `-Zmiri-tree-borrows-no-precise-interior-mut`".into(),
+                    ),
+            };
+        } else if arg == "-Zmiri-tree-borrows-implicit-writes" {
+            match &mut self.borrow_tracker {
+                Some(BorrowTrackerMethod::TreeBorrows(params)) => {
+                    params.implicit_writes = true;
+                }
+                _ =>
+                    return Err(
+                        "`-Zmiri-tree-borrows` is required before `-Zmiri-tree-borrows-implicit-writes`".into(),
+                    ),
+            };
+        } else if arg == "-Zmiri-tree-borrows-relax-custom-allocator-uniqueness" {
+            match &mut self.borrow_tracker {
+                Some(BorrowTrackerMethod::TreeBorrows(params)) => {
+                    params.box_custom_allocator_unique = false;
+                }
+                _ =>
+                    return Err(
+                        "`-Zmiri-tree-borrows` is required before `-Zmiri-tree-borrows-relax-custom-allocator-uniqueness`".into(),
+                    ),
+            };
+        } else if arg == "-Zmiri-disable-data-race-detector" {
+            self.data_race_detector = false;
+            self.weak_memory_emulation = false;
+        } else if arg == "-Zmiri-disable-alignment-check" {
+            self.check_alignment = AlignmentCheck::None;
+        } else if arg == "-Zmiri-symbolic-alignment-check" {
+            self.check_alignment = AlignmentCheck::Symbolic;
+        } else if arg == "-Zmiri-disable-isolation" {
+            self.isolated_op = IsolatedOp::Allow;
+        } else if arg == "-Zmiri-disable-leak-backtraces" {
+            self.collect_leak_backtraces = false;
+        } else if arg == "-Zmiri-disable-weak-memory-emulation" {
+            self.weak_memory_emulation = false;
+        } else if arg == "-Zmiri-track-weak-memory-loads" {
+            self.track_outdated_loads = true;
+        } else if let Some(param) = arg.strip_prefix("-Zmiri-isolation-error=") {
+            self.isolated_op = match param {
+                "abort" => IsolatedOp::Reject(RejectOpWith::Abort),
+                "hide" => IsolatedOp::Reject(RejectOpWith::NoWarning),
+                "warn" => IsolatedOp::Reject(RejectOpWith::Warning),
+                "warn-nobacktrace" => IsolatedOp::Reject(RejectOpWith::WarningWithoutBacktrace),
+                _ => return Err(
+                    "-Zmiri-isolation-error must be `abort`, `hide`, `warn`, or `warn-nobacktrace`"
+                        .into(),
+                ),
+            };
+        } else if arg == "-Zmiri-vfs" {
+            self.vfs.get_or_insert_default();
+        } else if let Some(param) = arg.strip_prefix("-Zmiri-vfs-mount=") {
+            let (param, read_only) = if let Some(param) = param.strip_suffix(":ro") {
+                (param, true)
+            } else {
+                (param.strip_suffix(":cow").unwrap_or(param), false)
+            };
+            let Some((host, guest)) = param.rsplit_once(':') else {
+                return Err("-Zmiri-vfs-mount requires `<host dir>:<guest dir>[:ro|:cow]`".into());
+            };
+            let mount = VfsMount::snapshot(std::path::Path::new(host), guest.into(), read_only)
+                .map_err(|err| format!("-Zmiri-vfs-mount: cannot copy `{host}`: {err}"))?;
+            self.vfs.get_or_insert_default().push(mount);
+        } else if arg == "-Zmiri-virtual-network" {
+            self.virtual_network = true;
+        } else if arg == "-Zmiri-ignore-leaks" {
+            self.ignore_leaks = true;
+            self.collect_leak_backtraces = false;
+        } else if arg == "-Zmiri-deterministic-floats" {
+            self.float_nondet = false;
+        } else if arg == "-Zmiri-no-extra-rounding-error" {
+            self.float_rounding_error = FloatRoundingErrorMode::None;
+        } else if arg == "-Zmiri-max-extra-rounding-error" {
+            self.float_rounding_error = FloatRoundingErrorMode::Max;
+        } else if arg == "-Zmiri-no-short-fd-operations" {
+            self.short_fd_operations = false;
+        } else if arg == "-Zmiri-strict-provenance" {
+            self.provenance_mode = ProvenanceMode::Strict;
+        } else if arg == "-Zmiri-permissive-provenance" {
+            self.provenance_mode = ProvenanceMode::Permissive;
+        } else if arg == "-Zmiri-mute-stdout-stderr" {
+            self.mute_stdout_stderr = true;
+        } else if arg == "-Zmiri-retag-fields" {
+            eprintln!(
+                "warning: `-Zmiri-retag-fields` is a NOP and will be removed in a future version of Miri.\n\
+                Field retagging has been on-by-default for a long time."
+            );
+        } else if arg == "-Zmiri-fixed-schedule" {
+            self.fixed_scheduling = true;
+        } else if arg == "-Zmiri-deterministic-concurrency" {
+            self.fixed_scheduling = true;
+            self.address_reuse_cross_thread_rate = 0.0;
+            self.cmpxchg_weak_failure_rate = 0.0;
+            self.weak_memory_emulation = false;
+        } else if let Some(param) = arg.strip_prefix("-Zmiri-scheduler=") {
+            self.pct_scheduling = if param == "random" {
+                None
+            } else if let Some(params) = param.strip_prefix("pct") {
+                Some(PctConfig::parse(params).map_err(|err| format!("-Zmiri-scheduler: {err}"))?)
+            } else {
+                return Err(
+                    "-Zmiri-scheduler must be `random` or `pct`, optionally followed by `:depth=<n>,steps=<n>`".into(),
+                );
+            };
+        } else if let Some(param) = arg.strip_prefix("-Zmiri-record-schedule=") {
+            self.record_schedule = Some(param.into());
+        } else if let Some(param) = arg.strip_prefix("-Zmiri-replay-schedule=") {
+            self.replay_schedule = Some(
+                ScheduleTrace::read(std::path::Path::new(param))
+                    .map_err(|err| format!("-Zmiri-replay-schedule: {err}"))?,
+            );
+        } else if arg == "-Zmiri-minimize-schedule" {
+            self.minimize_schedule = true;
+        } else if arg == "-Zmiri-check-lock-order" {
+            self.check_lock_order = true;
+        } else if let Some(param) = arg.strip_prefix("-Zmiri-seed=") {
+            let seed = param
+                .parse::<u64>()
+                .map_err(|_| "-Zmiri-seed must be an integer that fits into u64".to_string())?;
+            self.seed = Some(seed);
+        } else if let Some(trimmed_arg) = arg.strip_prefix("-Zmiri-genmc") {
+            GenmcConfig::parse_arg(&mut self.genmc_config, trimmed_arg)?;
+        } else if let Some(param) = arg.strip_prefix("-Zmiri-env-forward=") {
+            self.forwarded_env_vars.push(param.to_owned());
+        } else if let Some(param) = arg.strip_prefix("-Zmiri-env-set=") {
+            let Some((name, value)) = param.split_once('=') else {
+                return Err("-Zmiri-env-set requires an argument of the form <name>=<value>".into());
+            };
+            self.set_env_vars.insert(name.to_owned(), value.to_owned());
+        } else if let Some(param) = arg.strip_prefix("-Zmiri-track-pointer-tag=") {
+            let ids: Vec<u64> = parse_comma_list(param).map_err(|err| {
+                format!("-Zmiri-track-pointer-tag requires a comma separated list of valid `u64` arguments: {err}")
+            })?;
+            for id in ids.into_iter().map(BorTag::new) {
+                if let Some(id) = id {
+                    self.tracked_pointer_tags.insert(id);
+                } else {
+                    return Err("-Zmiri-track-pointer-tag requires nonzero arguments".into());
+                }
+            }
+        } else if let Some(param) = arg.strip_prefix("-Zmiri-track-alloc-id=") {
+            let ids = parse_comma_list::<NonZero<u64>>(param).map_err(|err| {
+                format!("-Zmiri-track-alloc-id requires a comma separated list of valid non-zero `u64` arguments: {err}")
+            })?;
+            self.tracked_alloc_ids.extend(ids.into_iter().map(AllocId));
+        } else if arg == "-Zmiri-track-alloc-accesses" {
+            self.track_alloc_accesses = true;
+        } else if let Some(param) = arg.strip_prefix("-Zmiri-address-reuse-rate=") {
+            self.address_reuse_rate =
+                parse_rate(param).map_err(|err| format!("-Zmiri-address-reuse-rate {err}"))?;
+        } else if let Some(param) = arg.strip_prefix("-Zmiri-address-reuse-cross-thread-rate=") {
+            self.address_reuse_cross_thread_rate = parse_rate(param)
+                .map_err(|err| format!("-Zmiri-address-reuse-cross-thread-rate {err}"))?;
+        } else if let Some(param) = arg.strip_prefix("-Zmiri-compare-exchange-weak-failure-rate=") {
+            self.cmpxchg_weak_failure_rate = parse_rate(param)
+                .map_err(|err| format!("-Zmiri-compare-exchange-weak-failure-rate {err}"))?;
+        } else if let Some(param) = arg.strip_prefix("-Zmiri-preemption-rate=") {
+            self.preemption_rate =
+                parse_rate(param).map_err(|err| format!("-Zmiri-preemption-rate {err}"))?;
+        } else if arg == "-Zmiri-report-progress" {
+            // This makes it take a few seconds between progress reports on my laptop.
+            self.report_progress = Some(1_000_000);
+        } else if let Some(param) = arg.strip_prefix("-Zmiri-report-progress=") {
+            let interval = param
+                .parse::<u32>()
+                .map_err(|err| format!("-Zmiri-report-progress requires a `u32`: {}", err))?;
+            self.report_progress = Some(interval);
+        } else if let Some(param) = arg.strip_prefix("-Zmiri-provenance-gc=") {
+            let interval = param
+                .parse::<u32>()
+                .map_err(|err| format!("-Zmiri-provenance-gc requires a `u32`: {}", err))?;
+            self.gc_interval = interval;
+        } else if let Some(param) = arg.strip_prefix("-Zmiri-measureme=") {
+            self.measureme_out = Some(param.to_string());
+        } else if let Some(param) = arg.strip_prefix("-Zmiri-backtrace=") {
+            self.backtrace_style = match param {
+                "0" => BacktraceStyle::Off,
+                "1" => BacktraceStyle::Short,
+                "full" => BacktraceStyle::Full,
+                _ => return Err("-Zmiri-backtrace may only be 0, 1, or full".into()),
+            };
+        } else if let Some(param) = arg.strip_prefix("-Zmiri-native-lib=") {
+            let filename = param.to_string();
+            let file_path = std::path::Path::new(&filename);
+            if file_path.exists() {
+                // For directories, nonrecursively add all normal files inside
+                if let Ok(dir) = file_path.read_dir() {
+                    for lib in dir.filter_map(|res| res.ok()) {
+                        if lib.file_type().unwrap().is_file() {
+                            self.native_lib.push(lib.path().to_owned());
+                        }
+                    }
+                } else {
+                    self.native_lib.push(filename.into());
+                }
+            } else {
+                return Err(format!("-Zmiri-native-lib `{}` does not exist", filename));
+            }
+        } else if arg == "-Zmiri-native-lib-enable-tracing" {
+            self.native_lib_enable_tracing = true;
+        } else if let Some(param) = arg.strip_prefix("-Zmiri-num-cpus=") {
+            let num_cpus = param
+                .parse::<u32>()
+                .map_err(|err| format!("-Zmiri-num-cpus requires a `u32`: {}", err))?;
+            if !(1..=MAX_CPUS).contains(&usize::try_from(num_cpus).unwrap()) {
+                return Err(format!("-Zmiri-num-cpus must be in the range 1..={}", MAX_CPUS));
+            }
+            self.num_cpus = num_cpus;
+        } else if let Some(param) = arg.strip_prefix("-Zmiri-force-page-size=") {
+            let page_size = param
+                .parse::<u64>()
+                .map_err(|err| format!("-Zmiri-force-page-size requires a `u64`: {}", err))?;
+            // Convert from kilobytes to bytes.
+            let page_size = if page_size.is_power_of_two() {
+                page_size * 1024
+            } else {
+                return Err(format!("-Zmiri-force-page-size requires a power of 2: {page_size}"));
+            };
+            self.page_size = Some(page_size);
+        } else if let Some(param) = arg.strip_prefix("-Zmiri-user-relevant-crates=") {
+            self.user_relevant_crates.extend(param.split(',').map(|s| s.to_owned()));
+        } else {
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    /// Checks that the flags given to [`MiriConfig::parse_flag`] fit together, and applies what
+    /// some of them imply for the others. Call this once all flags have been parsed.
+    pub fn check_flags(&mut self) -> Result<(), String> {
+        // Disabling validation also disables aliasing checks (as retags are done during validation).
+        if self.validation == ValidationMode::No {
+            self.borrow_tracker = None;
+        }
+
+        // Native calls and strict provenance are not compatible.
+        if !self.native_lib.is_empty() && self.provenance_mode == ProvenanceMode::Strict {
+            return Err("strict provenance is not compatible with calling native functions".into());
+        }
+        // PCT picks the next thread by priority, so it cannot also use a fixed schedule.
+        if self.fixed_scheduling && self.pct_scheduling.is_some() {
+            return Err(
+                "Only one of `-Zmiri-fixed-schedule` and `-Zmiri-scheduler=pct` can be set".into(),
+            );
+        }
+        // Minimization shrinks the schedule that gets replayed, one program at a time.
+        if self.minimize_schedule && self.replay_schedule.is_none() {
+            return Err("`-Zmiri-minimize-schedule` requires `-Zmiri-replay-schedule`".into());
+        }
+        // The in-memory file system replaces the host's only under isolation.
+        if self.vfs.is_some() && self.isolated_op == IsolatedOp::Allow {
+            return Err(
+                "`-Zmiri-vfs` and `-Zmiri-vfs-mount` cannot be used with `-Zmiri-disable-isolation`"
+                    .into(),
+            );
+        }
+        // Likewise, the loopback network only replaces the host's network under isolation.
+        if self.virtual_network && self.isolated_op == IsolatedOp::Allow {
+            return Err(
+                "`-Zmiri-virtual-network` cannot be used with `-Zmiri-disable-isolation`".into()
+            );
+        }
+        // We cannot emulate weak memory without the data race detector.
+        if self.weak_memory_emulation && !self.data_race_detector {
+            return Err(
+                "Weak memory emulation cannot be enabled when the data race detector is disabled"
+                    .into(),
+            );
+        };
+
+        // Validate GenMC settings.
+        if self.genmc_config.is_some()
+            && let Err(err) = GenmcConfig::validate(self)
+        {
+            return Err(format!("Invalid settings: {err}"));
+        }
+        Ok(())
+    }
+}
+
+/// Parses a comma separated list of `T` from the given string:
+/// `<value1>,<value2>,<value3>,...`
+fn parse_comma_list<T: FromStr>(input: &str) -> Result<Vec<T>, T::Err> {
+    input.split(',').map(str::parse::<T>).collect()
+}
+
+/// Parses the input as a float in the range from 0.0 to 1.0 (inclusive).
+fn parse_rate(input: &str) -> Result<f64, &'static str> {
+    match input.parse::<f64>() {
+        Ok(rate) if (0.0..=1.0).contains(&rate) => Ok(rate),
+        Ok(_) => Err("must be between `0.0` and `1.0`"),
+        Err(_) => Err("requires a `f64` between `0.0` and `1.0`"),
+    }
+}
+
 /// The state of the main thread. Implementation detail of `on_main_stack_empty`.
 #[derive(Debug)]
 enum MainThreadState<'tcx> {
@@ -266,7 +613,11 @@ impl<'tcx> MainThreadState<'tcx> {
                         } else {
                             // Give background threads a chance to finish by yielding the main thread a
                             // couple of times -- but only if we would also preempt threads randomly.
-                            if this.machine.preemption_rate > 0.0 {
+                            // With PCT, yielding would lower the priority of the main thread, so
+                            // it might not get to run again if other threads run forever.
+                            if this.machine.preemption_rate > 0.0
+                                && this.machine.threads.pct().is_none()
+                            {
                                 // There is a non-zero chance they will yield back to us often enough to
                                 // make Miri terminate eventually.
                                 *self = Yield { remaining: MAIN_THREAD_YIELDS_AT_SHUTDOWN };
@@ -542,6 +893,9 @@ pub fn eval_entry<'tcx>(
         }
         panic::resume_unwind(panic_payload)
     });
+    // Write the schedule trace before reporting the result, so that it also exists when the
+    // execution failed.
+    ecx.machine.finish_schedule_trace();
     // Obtain the result of the execution. This is always an `Err`, but that doesn't necessarily
     // indicate an error.
     let Err(res) = res.report_err();
@@ -588,6 +942,101 @@ pub fn eval_entry<'tcx>(
     Err(NonZeroI32::new(rustc_driver::EXIT_FAILURE).unwrap())
 }
 
+/// Runs the entry function without reporting anything, for schedule minimization. Returns how the
+/// program failed, if it did, the decisions of the execution, and the number of basic blocks it
+/// executed.
+fn eval_entry_quietly<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    entry_id: DefId,
+    entry_type: MiriEntryFnType,
+    config: &MiriConfig,
+) -> (Option<String>, ScheduleTrace, u64) {
+    assert!(config.minimize_schedule);
+    let mut ecx = match create_ecx(tcx, entry_id, entry_type, config, None).report_err() {
+        Ok(v) => v,
+        Err(err) => {
+            let (kind, backtrace) = err.into_parts();
+            backtrace.print_backtrace();
+            panic!("Miri initialization error: {kind:?}")
+        }
+    };
+    let res: thread::Result<InterpResult<'_, !>> =
+        panic::catch_unwind(AssertUnwindSafe(|| ecx.run_threads()));
+    let res = res.unwrap_or_else(|panic_payload| {
+        if !panic_payload.is::<FatalErrorMarker>() {
+            ecx.handle_ice();
+        }
+        panic::resume_unwind(panic_payload)
+    });
+    let Err(res) = res.report_err();
+    (
+        failure_summary(&ecx, &res),
+        ecx.machine.schedule_tracer.get_mut().take_recorded(),
+        ecx.machine.basic_block_count,
+    )
+}
+
+/// Shrinks the trace in `config.replay_schedule` to one with as few context switches as we can
+/// find, such that the program still fails the same way, and then evaluates the entry function
+/// with that trace like `eval_entry`.
+///
+/// We try the simplifications of the best trace so far one by one, and start over from the
+/// execution of the first one that has fewer context switches. This stops once no simplification
+/// helps, so the result is a local minimum. Without preemption, a simplified schedule can make a
+/// thread spin forever, so every candidate only gets twice as many basic blocks as the original
+/// execution; if it runs out, it does not count as reproducing the failure.
+pub fn minimize_schedule<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    entry_id: DefId,
+    entry_type: MiriEntryFnType,
+    config: &MiriConfig,
+) -> Result<(), NonZeroI32> {
+    let mut candidate_config = config.clone();
+    candidate_config.mute_stdout_stderr = true;
+    candidate_config.record_schedule = None;
+    // Once a simplified trace ends or diverges, the remaining decisions get made without preempting
+    // any thread, which adds as few context switches as possible.
+    candidate_config.fixed_scheduling = true;
+    candidate_config.pct_scheduling = None;
+
+    let (failure, mut best, basic_blocks) =
+        eval_entry_quietly(tcx, entry_id, entry_type, &candidate_config);
+    let Some(failure) = failure else {
+        tcx.dcx().fatal("the program does not fail when replaying the schedule to minimize");
+    };
+    candidate_config.max_basic_blocks = Some(basic_blocks.saturating_mul(2));
+    let mut run = |trace: ScheduleTrace| {
+        candidate_config.replay_schedule = Some(trace);
+        eval_entry_quietly(tcx, entry_id, entry_type, &candidate_config)
+    };
+    let original_switches = best.context_switches();
+    'minimize: loop {
+        for candidate in best.simplifications() {
+            let (candidate_failure, trace, _basic_blocks) = run(candidate);
+            if candidate_failure.as_ref() == Some(&failure)
+                && trace.context_switches() < best.context_switches()
+            {
+                best = trace;
+                continue 'minimize;
+            }
+        }
+        break;
+    }
+    tcx.dcx().note(format!(
+        "minimized the schedule from {original_switches} to {} context switches that still lead to \
+         the same failure ({failure}):\n{}",
+        best.context_switches(),
+        best.describe_interleaving(tcx.sess.source_map())
+    ));
+
+    // Run the minimized schedule again, this time reporting everything (and recording it, if
+    // requested).
+    let mut config = config.clone();
+    config.minimize_schedule = false;
+    config.replay_schedule = Some(best);
+    eval_entry(tcx, entry_id, entry_type, &config, None)
+}
+
 /// Turns an array of arguments into a Windows command line string.
 ///
 /// The string will be UTF-16 encoded and NUL terminated.
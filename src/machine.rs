@@ -38,7 +38,7 @@ use rustc_target::spec::{Arch, Os};
 use crate::alloc_addresses::EvalContextExt;
 use crate::concurrency::cpu_affinity::{self, CpuAffinityMask};
 use crate::concurrency::data_race::{self, NaReadType, NaWriteType};
-use crate::concurrency::sync::SyncObj;
+use crate::concurrency::sync::{FutexLock, SyncObj};
 use crate::concurrency::{
     AllocDataRaceHandler, GenmcCtx, GenmcEvalContextExt as _, GlobalDataRaceHandler, weak_memory,
 };
@@ -152,29 +152,47 @@ pub struct FrameExtra<'tcx> {
 
     /// Data race detector per-frame data.
     pub data_race: Option<data_race::FrameState>,
+
+    /// If this is Some(), then this is the frame of a signal handler. When it is popped, we
+    /// restore the signal mask and resume the interrupted frame.
+    pub signal_handler: Option<shims::SignalHandlerFrame>,
 }
 
 impl<'tcx> std::fmt::Debug for FrameExtra<'tcx> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         // Omitting `timing`, it does not support `Debug`.
-        let FrameExtra { borrow_tracker, catch_unwind, timing: _, user_relevance, data_race } =
-            self;
+        let FrameExtra {
+            borrow_tracker,
+            catch_unwind,
+            timing: _,
+            user_relevance,
+            data_race,
+            signal_handler,
+        } = self;
         f.debug_struct("FrameData")
             .field("borrow_tracker", borrow_tracker)
             .field("catch_unwind", catch_unwind)
             .field("user_relevance", user_relevance)
             .field("data_race", data_race)
+            .field("signal_handler", signal_handler)
             .finish()
     }
 }
 
 impl VisitProvenance for FrameExtra<'_> {
     fn visit_provenance(&self, visit: &mut VisitWith<'_>) {
-        let FrameExtra { catch_unwind, borrow_tracker, timing: _, user_relevance: _, data_race: _ } =
-            self;
+        let FrameExtra {
+            catch_unwind,
+            borrow_tracker,
+            timing: _,
+            user_relevance: _,
+            data_race: _,
+            signal_handler,
+        } = self;
 
         catch_unwind.visit_provenance(visit);
         borrow_tracker.visit_provenance(visit);
+        signal_handler.visit_provenance(visit);
     }
 }
 
@@ -398,6 +416,9 @@ pub struct AllocExtra<'tcx> {
     /// freed.
     /// This maps offsets to synchronization-primitive-specific data.
     pub sync_objs: BTreeMap<Size, Box<dyn SyncObj>>,
+    /// What we know about the locks stored in the futex words in this allocation, by offset. Only
+    /// words that were used with `futex_wait` or `futex_wake` have an entry.
+    pub futex_locks: BTreeMap<Size, FutexLock>,
 }
 
 // We need a `Clone` impl because the machine passes `Allocation` through `Cow`...
@@ -410,7 +431,8 @@ impl<'tcx> Clone for AllocExtra<'tcx> {
 
 impl VisitProvenance for AllocExtra<'_> {
     fn visit_provenance(&self, visit: &mut VisitWith<'_>) {
-        let AllocExtra { borrow_tracker, data_race, backtrace: _, sync_objs: _ } = self;
+        let AllocExtra { borrow_tracker, data_race, backtrace: _, sync_objs: _, futex_locks: _ } =
+            self;
 
         borrow_tracker.visit_provenance(visit);
         data_race.visit_provenance(visit);
@@ -485,6 +507,43 @@ impl<'tcx> PrimitiveLayouts<'tcx> {
     }
 }
 
+/// The live allocations created while the program runs, numbered in the order they were created.
+///
+/// Alloc ids are shared with the compiler, so running the program again yields different ones; the
+/// order however stays the same, which lets tools that re-run the program find an allocation
+/// again. Freed allocations are forgotten, so this does not grow with the length of the run.
+#[derive(Default, Debug)]
+pub struct LocalAllocIds {
+    /// How many allocations the program created so far.
+    created: usize,
+    by_index: FxHashMap<usize, AllocId>,
+    by_id: FxHashMap<AllocId, usize>,
+}
+
+impl LocalAllocIds {
+    /// The live allocation that was created `index`-th.
+    pub fn get(&self, index: usize) -> Option<AllocId> {
+        self.by_index.get(&index).copied()
+    }
+
+    /// The position of `alloc_id` in the order allocations were created, if it is live.
+    pub fn index_of(&self, alloc_id: AllocId) -> Option<usize> {
+        self.by_id.get(&alloc_id).copied()
+    }
+
+    fn push(&mut self, alloc_id: AllocId) {
+        self.by_index.insert(self.created, alloc_id);
+        self.by_id.insert(alloc_id, self.created);
+        self.created += 1;
+    }
+
+    fn remove(&mut self, alloc_id: AllocId) {
+        if let Some(index) = self.by_id.remove(&alloc_id) {
+            self.by_index.remove(&index);
+        }
+    }
+}
+
 /// The machine itself.
 ///
 /// If you add anything here that stores machine values, remember to update
@@ -532,11 +591,32 @@ pub struct MiriMachine<'tcx> {
 
     /// The table of file descriptors.
     pub(crate) fds: shims::FdTable,
+    /// Whether writes to the host's stdout and stderr are dropped. Unlike
+    /// `-Zmiri-mute-stdout-stderr`, this can be toggled while the program runs and does not change
+    /// what the program observes: the written bytes are still read and the write still succeeds.
+    /// Debuggers use this to re-execute a prefix of the program without repeating its output.
+    pub discard_stdout_stderr: bool,
     /// The table of directory descriptors.
     pub(crate) dirs: shims::DirTable,
+    /// The in-memory file system that file system shims use instead of the host's, if enabled.
+    pub(crate) vfs: Option<shims::vfs::Vfs>,
+    /// Signal dispositions, masks, and pending signals.
+    pub(crate) signals: shims::SignalState,
+    /// The `signalfd` file descriptions, which are notified when signals become pending.
+    pub(crate) signalfds: shims::SignalFds,
+    /// The `inotify` instances, which are notified of changes made through the file system shims.
+    pub(crate) inotify_instances: shims::InotifyInstances,
+    /// Child processes that have not been reaped yet.
+    pub(crate) children: shims::process::ChildProcesses,
+    /// The names Unix domain sockets are bound to.
+    pub(crate) unix_socket_names: shims::UnixSocketNamespace,
+    /// The in-memory network that TCP sockets use under isolation, if enabled.
+    pub(crate) loopback_network: Option<shims::LoopbackNetwork>,
 
     /// Managing file descriptors whose readiness needs to be updated.
     pub(crate) delayed_readiness_updates: Rc<DelayedReadinessUpdates>,
+    /// The `timerfd` timers the scheduler needs to fire.
+    pub(crate) armed_timerfds: shims::ArmedTimerFds,
 
     /// This machine's monotone clock.
     pub(crate) monotonic_clock: MonotonicClock,
@@ -544,6 +624,12 @@ pub struct MiriMachine<'tcx> {
     /// The set of threads.
     pub(crate) threads: ThreadManager<'tcx>,
 
+    /// The order in which locks got acquired, if `-Zmiri-check-lock-order` is set.
+    pub(crate) lock_order: Option<LockOrder>,
+    /// The allocations that contain a word that was used with `futex_wait` or `futex_wake`. Atomic
+    /// operations on other allocations do not have to check `AllocExtra::futex_locks`.
+    pub(crate) futex_allocs: FxHashSet<AllocId>,
+
     /// Handles blocking I/O and polling for completion.
     pub(crate) blocking_io: BlockingIoManager,
 
@@ -588,6 +674,12 @@ pub struct MiriMachine<'tcx> {
     /// Needs to be queried by ptr_to_int, hence needs interior mutability.
     pub(crate) rng: RefCell<StdRng>,
 
+    /// The state of recording and replaying the decisions that `rng` would otherwise make.
+    pub(crate) schedule_tracer: RefCell<ScheduleTracer>,
+    /// Whether to suppress non-halting diagnostics. Set for the executions that schedule
+    /// minimization tries out.
+    pub(crate) quiet: bool,
+
     /// The allocator used for the machine's `AllocBytes` in native-libs mode.
     pub(crate) allocator: Option<Rc<RefCell<crate::alloc::isolated_alloc::IsolatedAlloc>>>,
 
@@ -596,6 +688,15 @@ pub struct MiriMachine<'tcx> {
     pub(crate) tracked_alloc_ids: FxHashSet<AllocId>,
     /// For the tracked alloc ids, also report read/write accesses.
     track_alloc_accesses: bool,
+    /// The allocation IDs whose read/write accesses are recorded in `watched_accesses` instead of
+    /// being reported. This lets tools driving the interpreter, like debuggers, observe accesses.
+    pub watched_alloc_ids: FxHashSet<AllocId>,
+    /// Accesses to the watched alloc ids, oldest first. Whoever watches is responsible for taking
+    /// them out again.
+    pub watched_accesses: RefCell<Vec<(AllocId, AllocRange, borrow_tracker::AccessKind)>>,
+    /// If set, the live allocations created while the program runs, in the order they were
+    /// created. See [`LocalAllocIds`].
+    pub local_alloc_ids: Option<RefCell<LocalAllocIds>>,
 
     /// Controls whether alignment of memory accesses is being checked.
     pub(crate) check_alignment: AlignmentCheck,
@@ -610,6 +711,8 @@ pub struct MiriMachine<'tcx> {
     pub(crate) report_progress: Option<u32>,
     // The total number of blocks that have been executed.
     pub(crate) basic_block_count: u64,
+    /// If `Some`, we stop the program once `basic_block_count` exceeds this.
+    pub(crate) max_basic_blocks: Option<u64>,
 
     /// Handle of the optional shared object file for native functions.
     #[cfg(all(feature = "native-lib", unix))]
@@ -697,7 +800,7 @@ impl<'tcx> MiriMachine<'tcx> {
             let path = Path::new(out).join(filename);
             measureme::Profiler::new(path).expect("Couldn't create `measureme` profiler")
         });
-        let rng = StdRng::seed_from_u64(config.seed.unwrap_or(0));
+        let mut rng = StdRng::seed_from_u64(config.seed.unwrap_or(0));
         let borrow_tracker = config.borrow_tracker.map(|bt| bt.instantiate_global_state(config));
         let data_race = if config.genmc_config.is_some() {
             // `genmc_ctx` persists across executions, so we don't create a new one here.
@@ -735,7 +838,7 @@ impl<'tcx> MiriMachine<'tcx> {
             cpu_affinity::MAX_CPUS,
             config.num_cpus
         );
-        let threads = ThreadManager::new(config);
+        let threads = ThreadManager::new(config, &mut rng);
         let thread_cpu_affinity =
             if matches!(&tcx.sess.target.os, Os::Linux | Os::FreeBsd | Os::Android)
                 && !is_no_core(tcx)
@@ -769,10 +872,21 @@ impl<'tcx> MiriMachine<'tcx> {
             isolated_op: config.isolated_op,
             validation: config.validation,
             fds: shims::FdTable::init(config.mute_stdout_stderr),
+            discard_stdout_stderr: false,
             delayed_readiness_updates: Rc::new(DelayedReadinessUpdates::default()),
+            armed_timerfds: Default::default(),
             dirs: Default::default(),
+            vfs: config.vfs.as_deref().map(shims::vfs::Vfs::new),
+            signals: Default::default(),
+            signalfds: Default::default(),
+            inotify_instances: Default::default(),
+            children: Default::default(),
+            unix_socket_names: Default::default(),
+            loopback_network: config.virtual_network.then(shims::LoopbackNetwork::new),
             layouts,
             threads,
+            lock_order: config.check_lock_order.then(LockOrder::default),
+            futex_allocs: FxHashSet::default(),
             thread_cpu_affinity,
             blocking_io,
             static_roots: Vec::new(),
@@ -785,15 +899,21 @@ impl<'tcx> MiriMachine<'tcx> {
             extern_statics_imports: FxHashMap::default(),
             extern_static_weak_import_default: None,
             rng: RefCell::new(rng),
+            schedule_tracer: RefCell::new(ScheduleTracer::new(config)),
+            quiet: config.minimize_schedule,
             allocator: (!config.native_lib.is_empty())
                 .then(|| Rc::new(RefCell::new(crate::alloc::isolated_alloc::IsolatedAlloc::new()))),
             tracked_alloc_ids: config.tracked_alloc_ids.clone(),
             track_alloc_accesses: config.track_alloc_accesses,
+            watched_alloc_ids: FxHashSet::default(),
+            watched_accesses: RefCell::new(Vec::new()),
+            local_alloc_ids: None,
             check_alignment: config.check_alignment,
             cmpxchg_weak_failure_rate: config.cmpxchg_weak_failure_rate,
             preemption_rate: config.preemption_rate,
             report_progress: config.report_progress,
             basic_block_count: 0,
+            max_basic_blocks: config.max_basic_blocks,
             monotonic_clock: MonotonicClock::new(config.isolated_op == IsolatedOp::Allow),
             #[cfg(all(feature = "native-lib", unix))]
             native_lib: config.native_lib.iter().map(|lib_file_path| {
@@ -946,6 +1066,21 @@ impl<'tcx> MiriMachine<'tcx> {
             .map(Span::data)
     }
 
+    /// Report or record an access to `alloc_id`, if that allocation is tracked or watched.
+    fn track_alloc_access(
+        &self,
+        alloc_id: AllocId,
+        range: AllocRange,
+        access_kind: borrow_tracker::AccessKind,
+    ) {
+        if self.track_alloc_accesses && self.tracked_alloc_ids.contains(&alloc_id) {
+            self.emit_diagnostic(NonHaltingDiagnostic::AccessedAlloc(alloc_id, range, access_kind));
+        }
+        if self.watched_alloc_ids.contains(&alloc_id) {
+            self.watched_accesses.borrow_mut().push((alloc_id, range, access_kind));
+        }
+    }
+
     fn init_allocation(
         ecx: &MiriInterpCx<'tcx>,
         id: AllocId,
@@ -1004,6 +1139,7 @@ impl<'tcx> MiriMachine<'tcx> {
             data_race,
             backtrace,
             sync_objs: BTreeMap::default(),
+            futex_locks: BTreeMap::default(),
         })
     }
 }
@@ -1013,6 +1149,8 @@ impl VisitProvenance for MiriMachine<'_> {
         #[rustfmt::skip]
         let MiriMachine {
             threads,
+            lock_order: _,
+            futex_allocs: _,
             thread_cpu_affinity: _,
             tls,
             env_vars,
@@ -1024,12 +1162,20 @@ impl VisitProvenance for MiriMachine<'_> {
             extern_statics_imports,
             extern_static_weak_import_default,
             dirs,
+            vfs: _,
+            signals,
+            signalfds: _,
+            inotify_instances: _,
+            children: _,
+            unix_socket_names: _,
+            loopback_network: _,
             borrow_tracker,
             data_race,
             alloc_addresses,
             fds,
             blocking_io:_,
             delayed_readiness_updates: _,
+            armed_timerfds: _,
             tcx: _,
             isolated_op: _,
             validation: _,
@@ -1042,14 +1188,21 @@ impl VisitProvenance for MiriMachine<'_> {
             backtrace_style: _,
             user_relevant_crates: _,
             rng: _,
+            schedule_tracer: _,
+            quiet: _,
             allocator: _,
             tracked_alloc_ids: _,
             track_alloc_accesses: _,
+            watched_alloc_ids: _,
+            watched_accesses: _,
+            local_alloc_ids: _,
+            discard_stdout_stderr: _,
             check_alignment: _,
             cmpxchg_weak_failure_rate: _,
             preemption_rate: _,
             report_progress: _,
             basic_block_count: _,
+            max_basic_blocks: _,
             native_lib: _,
             #[cfg(all(feature = "native-lib", unix))]
             native_lib_ecx_interchange: _,
@@ -1077,6 +1230,7 @@ impl VisitProvenance for MiriMachine<'_> {
         tls.visit_provenance(visit);
         env_vars.visit_provenance(visit);
         dirs.visit_provenance(visit);
+        signals.visit_provenance(visit);
         fds.visit_provenance(visit);
         data_race.visit_provenance(visit);
         borrow_tracker.visit_provenance(visit);
@@ -1362,7 +1516,8 @@ impl<'tcx> Machine<'tcx> for MiriMachine<'tcx> {
         val: &ImmTy<'tcx>,
         ordering: AtomicOrdering,
     ) -> InterpResult<'tcx> {
-        ecx.write_scalar_atomic(val.to_scalar(), place, AtomicWriteOrd::from(ordering))
+        ecx.write_scalar_atomic(val.to_scalar(), place, AtomicWriteOrd::from(ordering))?;
+        ecx.futex_owner_update(place, None)
     }
 
     fn atomic_rmw(
@@ -1372,7 +1527,10 @@ impl<'tcx> Machine<'tcx> for MiriMachine<'tcx> {
         operand: &ImmTy<'tcx>,
         ordering: AtomicOrdering,
     ) -> InterpResult<'tcx, Scalar> {
-        ecx.atomic_rmw(place, operand, op, AtomicRwOrd::from(ordering))
+        let old = ecx.atomic_rmw(place, operand, op, AtomicRwOrd::from(ordering))?;
+        let exchange = matches!(op, AtomicRmwOp::Swap).then(|| (old, operand.to_scalar()));
+        ecx.futex_owner_update(place, exchange)?;
+        interp_ok(old)
     }
 
     fn atomic_compare_exchange(
@@ -1384,14 +1542,18 @@ impl<'tcx> Machine<'tcx> for MiriMachine<'tcx> {
         success_ordering: AtomicOrdering,
         failure_ordering: AtomicOrdering,
     ) -> InterpResult<'tcx, (Scalar, bool)> {
-        ecx.atomic_compare_exchange(
+        let (old, success) = ecx.atomic_compare_exchange(
             place,
             expected_old,
             new.to_scalar(),
             AtomicRwOrd::from(success_ordering),
             AtomicReadOrd::from(failure_ordering),
             can_fail_spuriously,
-        )
+        )?;
+        if success {
+            ecx.futex_owner_update(place, Some((old, new.to_scalar())))?;
+        }
+        interp_ok((old, success))
     }
 
     fn atomic_fence(
@@ -1572,6 +1734,9 @@ impl<'tcx> Machine<'tcx> for MiriMachine<'tcx> {
         align: Align,
     ) -> InterpResult<'tcx, Self::AllocExtra> {
         assert!(kind != MiriMemoryKind::Global.into());
+        if let Some(local_alloc_ids) = &ecx.machine.local_alloc_ids {
+            local_alloc_ids.borrow_mut().push(id);
+        }
         MiriMachine::init_allocation(ecx, id, kind, size, align)
     }
 
@@ -1681,13 +1846,7 @@ impl<'tcx> Machine<'tcx> for MiriMachine<'tcx> {
         (alloc_id, prov_extra): (AllocId, Self::ProvenanceExtra),
         range: AllocRange,
     ) -> InterpResult<'tcx> {
-        if machine.track_alloc_accesses && machine.tracked_alloc_ids.contains(&alloc_id) {
-            machine.emit_diagnostic(NonHaltingDiagnostic::AccessedAlloc(
-                alloc_id,
-                range,
-                borrow_tracker::AccessKind::Read,
-            ));
-        }
+        machine.track_alloc_access(alloc_id, range, borrow_tracker::AccessKind::Read);
         // The order of checks is deliberate, to prefer reporting a data race over a borrow tracker error.
         match &machine.data_race {
             GlobalDataRaceHandler::None => {}
@@ -1722,13 +1881,7 @@ impl<'tcx> Machine<'tcx> for MiriMachine<'tcx> {
         (alloc_id, prov_extra): (AllocId, Self::ProvenanceExtra),
         range: AllocRange,
     ) -> InterpResult<'tcx> {
-        if machine.track_alloc_accesses && machine.tracked_alloc_ids.contains(&alloc_id) {
-            machine.emit_diagnostic(NonHaltingDiagnostic::AccessedAlloc(
-                alloc_id,
-                range,
-                borrow_tracker::AccessKind::Write,
-            ));
-        }
+        machine.track_alloc_access(alloc_id, range, borrow_tracker::AccessKind::Write);
         match &machine.data_race {
             GlobalDataRaceHandler::None => {}
             GlobalDataRaceHandler::Genmc(genmc_ctx) =>
@@ -1761,7 +1914,12 @@ impl<'tcx> Machine<'tcx> for MiriMachine<'tcx> {
                 }
             }
             for offset in to_delete {
-                alloc_extra.sync_objs.remove(&offset);
+                let obj = alloc_extra.sync_objs.remove(&offset).unwrap();
+                if let Some(lock_order) = &mut machine.lock_order
+                    && let Some(lock) = obj.lock_order_id()
+                {
+                    lock_order.forget(lock);
+                }
             }
         }
         interp_ok(())
@@ -1804,11 +1962,25 @@ impl<'tcx> Machine<'tcx> for MiriMachine<'tcx> {
         for obj in alloc_extra.sync_objs.values() {
             obj.on_access(concurrency::sync::AccessKind::Dealloc)?;
         }
+        if !alloc_extra.futex_locks.is_empty() {
+            machine.futex_allocs.remove(&alloc_id);
+        }
+        // The locks stored in this allocation are gone, so they cannot deadlock any more.
+        if let Some(lock_order) = &mut machine.lock_order {
+            let sync_locks = alloc_extra.sync_objs.values().filter_map(|obj| obj.lock_order_id());
+            let futex_locks = alloc_extra.futex_locks.values().filter_map(FutexLock::lock_order_id);
+            for lock in sync_locks.chain(futex_locks) {
+                lock_order.forget(lock);
+            }
+        }
 
         if let Some((_, deallocated_at)) = machine.allocation_spans.borrow_mut().get_mut(&alloc_id)
         {
             *deallocated_at = Some(machine.current_user_relevant_span());
         }
+        if let Some(local_alloc_ids) = &mut machine.local_alloc_ids {
+            local_alloc_ids.get_mut().remove(alloc_id);
+        }
         machine.free_alloc_id(alloc_id, size, align, kind);
         interp_ok(())
     }
@@ -1888,6 +2060,7 @@ impl<'tcx> Machine<'tcx> for MiriMachine<'tcx> {
                 .data_race
                 .as_vclocks_ref()
                 .map(|_| data_race::FrameState::default()),
+            signal_handler: None,
         };
 
         interp_ok(frame.with_extra(extra))
@@ -1907,6 +2080,9 @@ impl<'tcx> Machine<'tcx> for MiriMachine<'tcx> {
 
     fn before_terminator(ecx: &mut InterpCx<'tcx, Self>) -> InterpResult<'tcx> {
         ecx.machine.basic_block_count += 1u64; // a u64 that is only incremented by 1 will "never" overflow
+        if ecx.machine.max_basic_blocks.is_some_and(|max| ecx.machine.basic_block_count > max) {
+            throw_machine_stop!(TerminationInfo::BasicBlockLimit);
+        }
         ecx.machine.since_gc += 1;
         // Possibly report our progress. This will point at the terminator we are about to execute.
         if let Some(report_progress) = ecx.machine.report_progress {
@@ -1986,7 +2162,10 @@ impl<'tcx> Machine<'tcx> for MiriMachine<'tcx> {
             // Move `frame` into a sub-scope so we control when it will be dropped.
             let mut frame = frame;
             let timing = frame.extra.timing.take();
-            let res = ecx.handle_stack_pop_unwind(frame.extra, unwinding);
+            let res = match frame.extra.signal_handler.take() {
+                Some(signal_handler) => ecx.return_from_signal_handler(signal_handler, unwinding),
+                None => ecx.handle_stack_pop_unwind(frame.extra, unwinding),
+            };
             if let Some(profiler) = ecx.machine.profiler.as_ref() {
                 profiler.finish_recording_interval_event(timing.unwrap());
             }
@@ -2086,10 +2265,25 @@ impl<'tcx> Machine<'tcx> for MiriMachine<'tcx> {
         };
         // Always use the same salt if the allocation is unique.
         if unique {
-            CTFE_ALLOC_SALT
-        } else {
-            ecx.machine.rng.borrow_mut().random_range(0..ADDRS_PER_ANON_GLOBAL)
+            return CTFE_ALLOC_SALT;
         }
+        // The salt decides whether this gets a new allocation, so it has to be part of the schedule
+        // trace to keep the address decisions in sync.
+        let salt = match ecx.machine.replay_decision("a global allocation", |decision| {
+            match decision {
+                Decision::Salt(salt) => Some(salt),
+                _ => None,
+            }
+        }) {
+            Some(salt) if salt < ADDRS_PER_ANON_GLOBAL => salt,
+            Some(salt) => {
+                ecx.machine.replay_rejected(format!("{salt} is not a valid salt"));
+                ecx.machine.rng.borrow_mut().random_range(0..ADDRS_PER_ANON_GLOBAL)
+            }
+            None => ecx.machine.rng.borrow_mut().random_range(0..ADDRS_PER_ANON_GLOBAL),
+        };
+        ecx.machine.record_decision(Decision::Salt(salt));
+        salt
     }
 
     fn cached_union_data_range<'e>(
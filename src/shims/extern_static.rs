@@ -60,12 +60,31 @@ impl<'tcx> MiriMachine<'tcx> {
 
         match &ecx.tcx.sess.target.os {
             Os::Linux => {
-                Self::weak_fn_symbols(ecx, &["getrandom", "gettid", "statx", "strlen"])?;
+                Self::weak_fn_symbols(
+                    ecx,
+                    &[
+                        "getrandom",
+                        "gettid",
+                        "statx",
+                        "strlen",
+                        "copy_file_range",
+                        "posix_spawn_file_actions_addchdir_np",
+                    ],
+                )?;
             }
             Os::Android => {
                 Self::weak_fn_symbols(
                     ecx,
-                    &["signal", "getrandom", "gettid", "futimens", "preadv", "pwritev"],
+                    &[
+                        "signal",
+                        "getrandom",
+                        "gettid",
+                        "futimens",
+                        "preadv",
+                        "pwritev",
+                        "copy_file_range",
+                        "splice",
+                    ],
                 )?;
             }
             Os::Windows => {
@@ -4,12 +4,14 @@ use std::fs::{Dir, File};
 use std::io::{ErrorKind, IsTerminal, Read, Seek, SeekFrom, Write};
 use std::marker::CoercePointee;
 use std::ops::Deref;
+use std::path::PathBuf;
 use std::rc::{Rc, Weak};
 use std::{fs, io};
 
 use rustc_abi::Size;
 
-use crate::shims::unix::UnixFileDescription;
+use crate::shims::unix::{FsEvent, InotifyEvalContextExt as _, UnixFileDescription};
+use crate::shims::vfs::{VfsFile, VfsMetadata};
 use crate::*;
 
 /// A unique id for file descriptions. While we could use the address, considering that
@@ -98,7 +100,7 @@ impl<T: ?Sized> WeakFileDescriptionRef<T> {
     }
 }
 
-impl<T> VisitProvenance for FileDescriptionRef<T> {
+impl<T: ?Sized> VisitProvenance for FileDescriptionRef<T> {
     fn visit_provenance(&self, _visit: &mut VisitWith<'_>) {
         // All our FileDescription instances do not have any provenance.
     }
@@ -182,10 +184,12 @@ pub trait FileDescription: std::fmt::Debug + FileDescriptionExt {
     }
 
     /// Returns the metadata for this FD, if available.
-    /// This is either host metadata, or a non-file-backed-FD type.
+    /// This is either the metadata of a file, or a non-file-backed-FD type.
     /// The latter is for new represented as a string storing a `libc` name so we only
     /// support that kind of metadata on Unix targets.
-    fn metadata<'tcx>(&self) -> InterpResult<'tcx, Either<io::Result<fs::Metadata>, &'static str>> {
+    fn metadata<'tcx>(
+        &self,
+    ) -> InterpResult<'tcx, Either<io::Result<HandleMetadata>, &'static str>> {
         throw_unsup_format!("obtaining metadata is only supported on file-backed file descriptors");
     }
 
@@ -228,7 +232,7 @@ pub trait FileDescription: std::fmt::Debug + FileDescriptionExt {
 }
 
 #[derive(Debug)]
-struct Stdin {
+pub struct Stdin {
     stdin: io::Stdin,
     watched: ReadinessWatched,
 }
@@ -281,7 +285,7 @@ impl FileDescription for Stdin {
 }
 
 #[derive(Debug)]
-struct Stdout {
+pub struct Stdout {
     stdout: io::Stdout,
     watched: ReadinessWatched,
 }
@@ -306,7 +310,11 @@ impl FileDescription for Stdout {
         finish: DynMachineCallback<'tcx, Result<usize, IoError>>,
     ) -> InterpResult<'tcx> {
         // We allow writing to stdout even with isolation enabled.
-        let result = ecx.write_to_host(&self.stdout, len, ptr)?;
+        let result = if ecx.machine.discard_stdout_stderr {
+            ecx.write_to_host(io::sink(), len, ptr)?
+        } else {
+            ecx.write_to_host(&self.stdout, len, ptr)?
+        };
         // Stdout is buffered, flush to make sure it appears on the
         // screen.  This is the write() syscall of the interpreted
         // program, we want it to correspond to a write() syscall on
@@ -334,7 +342,7 @@ impl FileDescription for Stdout {
 }
 
 #[derive(Debug)]
-struct Stderr {
+pub struct Stderr {
     stderr: io::Stderr,
     watched: ReadinessWatched,
 }
@@ -359,7 +367,11 @@ impl FileDescription for Stderr {
         finish: DynMachineCallback<'tcx, Result<usize, IoError>>,
     ) -> InterpResult<'tcx> {
         // We allow writing to stderr even with isolation enabled.
-        let result = ecx.write_to_host(&self.stderr, len, ptr)?;
+        let result = if ecx.machine.discard_stdout_stderr {
+            ecx.write_to_host(io::sink(), len, ptr)?
+        } else {
+            ecx.write_to_host(&self.stderr, len, ptr)?
+        };
         // No need to flush, stderr is not buffered.
         finish.call(ecx, result)
     }
@@ -421,9 +433,125 @@ impl FileDescription for NullOutput {
     }
 }
 
+/// Where the contents of a [`FileHandle`] or [`DirHandle`] live.
+#[derive(Debug)]
+pub enum Backing<H> {
+    /// An open file or directory of the host.
+    Host(H),
+    /// An open file or directory of the in-memory file system.
+    Virtual(VfsFile),
+}
+
+impl<H> Backing<H> {
+    /// Files of the in-memory file system can be accessed under isolation.
+    pub fn is_virtual(&self) -> bool {
+        matches!(self, Backing::Virtual(_))
+    }
+}
+
+impl Backing<File> {
+    pub fn metadata(&self) -> io::Result<HandleMetadata> {
+        match self {
+            Backing::Host(file) => file.metadata().map(HandleMetadata::Host),
+            Backing::Virtual(file) => Ok(HandleMetadata::Virtual(file.metadata())),
+        }
+    }
+
+    pub fn set_len(&self, len: u64) -> io::Result<()> {
+        match self {
+            Backing::Host(file) => file.set_len(len),
+            Backing::Virtual(file) => file.set_len(len),
+        }
+    }
+
+    /// Flushes the data of the file to disk. Files in memory need no flushing.
+    pub fn sync(&self, operation: fn(&File) -> io::Result<()>) -> io::Result<()> {
+        match self {
+            Backing::Host(file) => operation(file),
+            Backing::Virtual(_) => Ok(()),
+        }
+    }
+}
+
+impl Read for &Backing<File> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Backing::Host(file) => (&*file).read(buf),
+            Backing::Virtual(file) => (&*file).read(buf),
+        }
+    }
+}
+
+impl Write for &Backing<File> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Backing::Host(file) => (&*file).write(buf),
+            Backing::Virtual(file) => (&*file).write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Backing::Host(file) => (&*file).flush(),
+            Backing::Virtual(file) => (&*file).flush(),
+        }
+    }
+}
+
+impl Seek for &Backing<File> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match self {
+            Backing::Host(file) => (&*file).seek(pos),
+            Backing::Virtual(file) => (&*file).seek(pos),
+        }
+    }
+}
+
+/// The metadata of a file-backed FD.
+#[derive(Debug)]
+pub enum HandleMetadata {
+    Host(fs::Metadata),
+    Virtual(VfsMetadata),
+}
+
+impl HandleMetadata {
+    pub fn len(&self) -> u64 {
+        match self {
+            HandleMetadata::Host(metadata) => metadata.len(),
+            HandleMetadata::Virtual(metadata) => metadata.len,
+        }
+    }
+
+    pub fn is_dir(&self) -> bool {
+        match self {
+            HandleMetadata::Host(metadata) => metadata.is_dir(),
+            HandleMetadata::Virtual(metadata) => metadata.is_dir,
+        }
+    }
+
+    /// Whether both metadata describe the same file, or `None` if the host cannot tell.
+    pub fn is_same_file(&self, other: &HandleMetadata) -> Option<bool> {
+        match (self, other) {
+            #[cfg(unix)]
+            (HandleMetadata::Host(a), HandleMetadata::Host(b)) => {
+                use std::os::unix::fs::MetadataExt;
+                Some(a.dev() == b.dev() && a.ino() == b.ino())
+            }
+            #[cfg(not(unix))]
+            (HandleMetadata::Host(_), HandleMetadata::Host(_)) => None,
+            (HandleMetadata::Virtual(a), HandleMetadata::Virtual(b)) => Some(a.ino == b.ino),
+            _ => Some(false),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct FileHandle {
-    pub(crate) file: File,
+    pub(crate) file: Backing<File>,
+    /// The path the file was opened with. On Unix targets, this is made absolute so that the
+    /// `*at` functions can resolve paths relative to an open directory. Moving the directory
+    /// afterwards is not reflected here, so those functions then report it as unsupported.
+    pub(crate) path: PathBuf,
     pub(crate) readable: bool,
     pub(crate) writable: bool,
 }
@@ -441,7 +569,10 @@ impl FileDescription for FileHandle {
         ecx: &mut MiriInterpCx<'tcx>,
         finish: DynMachineCallback<'tcx, Result<usize, IoError>>,
     ) -> InterpResult<'tcx> {
-        assert!(communicate_allowed, "isolation should have prevented even opening a file");
+        assert!(
+            communicate_allowed || self.file.is_virtual(),
+            "isolation should have prevented even opening a file"
+        );
 
         if !self.readable {
             return finish.call(ecx, Err(ErrorKind::PermissionDenied.into()));
@@ -460,7 +591,10 @@ impl FileDescription for FileHandle {
         ecx: &mut MiriInterpCx<'tcx>,
         finish: DynMachineCallback<'tcx, Result<usize, IoError>>,
     ) -> InterpResult<'tcx> {
-        assert!(communicate_allowed, "isolation should have prevented even opening a file");
+        assert!(
+            communicate_allowed || self.file.is_virtual(),
+            "isolation should have prevented even opening a file"
+        );
 
         if !self.writable {
             // Linux hosts return EBADF here which we can't translate via the platform-independent
@@ -472,6 +606,9 @@ impl FileDescription for FileHandle {
             return finish.call(ecx, Err(ErrorKind::PermissionDenied.into()));
         }
         let result = ecx.write_to_host(&self.file, len, ptr)?;
+        if result.as_ref().is_ok_and(|&written| written > 0) {
+            ecx.report_fs_event(FsEvent::Modify(&self.path))?;
+        }
         finish.call(ecx, result)
     }
 
@@ -480,16 +617,24 @@ impl FileDescription for FileHandle {
         communicate_allowed: bool,
         offset: SeekFrom,
     ) -> InterpResult<'tcx, io::Result<u64>> {
-        assert!(communicate_allowed, "isolation should have prevented even opening a file");
+        assert!(
+            communicate_allowed || self.file.is_virtual(),
+            "isolation should have prevented even opening a file"
+        );
         interp_ok((&mut &self.file).seek(offset))
     }
 
-    fn metadata<'tcx>(&self) -> InterpResult<'tcx, Either<io::Result<fs::Metadata>, &'static str>> {
+    fn metadata<'tcx>(
+        &self,
+    ) -> InterpResult<'tcx, Either<io::Result<HandleMetadata>, &'static str>> {
         interp_ok(Either::Left(self.file.metadata()))
     }
 
     fn is_tty(&self, communicate_allowed: bool) -> bool {
-        communicate_allowed && self.file.is_terminal()
+        match &self.file {
+            Backing::Host(file) => communicate_allowed && file.is_terminal(),
+            Backing::Virtual(_) => false,
+        }
     }
 
     fn short_fd_operations(&self) -> bool {
@@ -513,7 +658,7 @@ impl FileDescription for FileHandle {
 
 #[derive(Debug)]
 pub struct DirHandle {
-    pub(crate) dir: Dir,
+    pub(crate) dir: Backing<Dir>,
 }
 
 impl FileDescription for DirHandle {
@@ -523,8 +668,11 @@ impl FileDescription for DirHandle {
 
     fn metadata<'tcx>(
         &self,
-    ) -> InterpResult<'tcx, Either<io::Result<std::fs::Metadata>, &'static str>> {
-        interp_ok(Either::Left(self.dir.metadata()))
+    ) -> InterpResult<'tcx, Either<io::Result<HandleMetadata>, &'static str>> {
+        interp_ok(Either::Left(match &self.dir {
+            Backing::Host(dir) => dir.metadata().map(HandleMetadata::Host),
+            Backing::Virtual(dir) => Ok(HandleMetadata::Virtual(dir.metadata())),
+        }))
     }
 }
 
@@ -615,6 +763,15 @@ impl FdTable {
     pub fn is_fd_num(&self, fd_num: FdNum) -> bool {
         self.fds.contains_key(&fd_num)
     }
+
+    /// Make all file descriptors referring to the file description `old` refer to `new` instead.
+    pub fn replace_description(&mut self, old: FdId, new: DynFileDescriptionRef) {
+        for fd in self.fds.values_mut() {
+            if fd.id() == old {
+                *fd = new.clone();
+            }
+        }
+    }
 }
 
 impl<'tcx> EvalContextExt<'tcx> for crate::MiriInterpCx<'tcx> {}
@@ -593,8 +593,11 @@ trait EvalContextExtPriv<'tcx>: crate::MiriInterpCxExt<'tcx> {
                 throw_machine_stop!(TerminationInfo::Exit { code, leak_check: false });
             }
             "abort" => {
-                // FIXME: This does not have a direct test (#3179).
                 let [] = this.check_shim_sig_lenient(abi, CanonAbi::C, link_name, args)?;
+                // `abort` raises `SIGABRT`, so a handler for that gets to run first.
+                if this.target_os_is_unix() && this.run_abort_handler()? {
+                    return interp_ok(EmulateItemResult::AlreadyJumped);
+                }
                 throw_machine_stop!(TerminationInfo::Abort(
                     "the program aborted execution".to_owned()
                 ));
@@ -16,16 +16,23 @@ pub mod global_ctor;
 pub mod io_error;
 pub mod os_str;
 pub mod panic;
+pub mod process;
 pub mod readiness;
 pub mod sig;
 pub mod time;
 pub mod tls;
 pub mod unwind;
+pub mod vfs;
 
-pub use self::files::{FdId, FdTable, FileDescription, FileDescriptionRef, WeakFileDescriptionRef};
+pub use self::files::{
+    FdId, FdTable, FileDescription, FileDescriptionRef, HandleMetadata, WeakFileDescriptionRef,
+};
 #[cfg(all(feature = "native-lib", unix))]
 pub use self::native_lib::trace::{init_sv, register_retcode_sv};
-pub use self::unix::DirTable;
+pub use self::unix::{
+    ArmedTimerFds, DirTable, InotifyInstances, LoopbackNetwork, SignalEvalContextExt, SignalFds,
+    SignalHandlerFrame, SignalState, TimerFdEvalContextExt, UnixSocketNamespace,
+};
 
 /// What needs to be done after emulating an item (a shim or an intrinsic) is done.
 pub enum EmulateItemResult {
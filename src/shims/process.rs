@@ -0,0 +1,226 @@
+//! Child processes of the interpreted program.
+//!
+//! Children are real host processes, so spawning them requires isolation to be disabled. A child
+//! whose executable was built by `cargo miri` is not a native binary but a JSON file describing
+//! how to interpret that crate; such children are run in a nested Miri instance through
+//! `cargo-miri runner`. Only the standard streams are passed on to a child. The target-specific
+//! shims decide what those are; so far that is only `posix_spawn`, since neither `fork` nor
+//! Windows' `CreateProcessW` is supported.
+
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::{self, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::time::Duration;
+
+use crate::*;
+
+/// How long a thread waiting for a child process sleeps before checking on it again.
+const CHILD_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+#[derive(Debug)]
+struct ChildProcess {
+    child: Child,
+    /// The exit status, once we have observed it.
+    status: Option<ExitStatus>,
+}
+
+/// The child processes that have not been reaped yet, keyed by their host process ID.
+#[derive(Debug, Default)]
+pub struct ChildProcesses {
+    children: BTreeMap<u32, ChildProcess>,
+}
+
+impl ChildProcesses {
+    /// Returns the exit status of `pid` if it has exited, without reaping it.
+    fn try_wait(&mut self, pid: u32) -> io::Result<Option<ExitStatus>> {
+        let process = self.children.get_mut(&pid).unwrap();
+        if process.status.is_none() {
+            process.status = process.child.try_wait()?;
+        }
+        Ok(process.status)
+    }
+}
+
+/// A child process the program wants to spawn.
+#[derive(Debug)]
+pub struct SpawnRequest {
+    pub program: PathBuf,
+    /// The arguments, starting with `argv[0]`.
+    pub args: Vec<OsString>,
+    /// The complete environment of the child.
+    pub env: Vec<(OsString, OsString)>,
+    pub cwd: Option<PathBuf>,
+    pub stdin: Stdio,
+    pub stdout: Stdio,
+    pub stderr: Stdio,
+    /// The process group to move the child into, if any (`0` means a new group).
+    pub process_group: Option<i32>,
+}
+
+/// `cargo miri` "builds" binaries into JSON files that describe how to interpret the crate. We
+/// only check that `path` holds a JSON object: what goes into it is up to `cargo-miri runner`,
+/// which complains if it cannot make sense of the file.
+fn is_cargo_miri_binary(path: &Path) -> bool {
+    File::open(path).is_ok_and(|file| {
+        serde_json::from_reader::<_, serde_json::Value>(BufReader::new(file))
+            .is_ok_and(|value| value.is_object())
+    })
+}
+
+impl<'tcx> EvalContextExt<'tcx> for crate::MiriInterpCx<'tcx> {}
+pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
+    /// Spawns a child process and returns its process ID.
+    fn spawn_child(&mut self, request: SpawnRequest) -> InterpResult<'tcx, io::Result<u32>> {
+        let this = self.eval_context_mut();
+        assert!(this.machine.communicate(), "cannot spawn processes with isolation enabled");
+
+        let SpawnRequest { program, args, env, cwd, stdin, stdout, stderr, process_group } =
+            request;
+        let program_path = match &cwd {
+            Some(cwd) if program.components().count() > 1 => cwd.join(&program),
+            _ => program.clone(),
+        };
+        let nested = is_cargo_miri_binary(&program_path);
+        let mut cmd = if nested {
+            let Some(cargo_miri) = std::env::var_os("MIRI_CARGO_MIRI") else {
+                throw_unsup_format!(
+                    "cannot run `{}`: it was built by `cargo miri`, but this Miri instance was not started by `cargo miri`",
+                    program.display()
+                );
+            };
+            let mut cmd = Command::new(cargo_miri);
+            cmd.arg("runner").arg(&program_path).args(args.iter().skip(1));
+            cmd
+        } else {
+            let mut cmd = Command::new(&program);
+            if let Some(arg0) = args.first() {
+                #[cfg(unix)]
+                std::os::unix::process::CommandExt::arg0(&mut cmd, arg0);
+                #[cfg(not(unix))]
+                let _ = arg0;
+            }
+            cmd.args(args.iter().skip(1));
+            cmd
+        };
+        cmd.env_clear().envs(env.iter().map(|(name, val)| (name, val)));
+        if nested {
+            // The nested Miri needs to find its sysroot, and it must not start a debugger even if
+            // we are being debugged.
+            if !env.iter().any(|(name, _)| name == "MIRI_SYSROOT")
+                && let Some(sysroot) = std::env::var_os("MIRI_SYSROOT")
+            {
+                cmd.env("MIRI_SYSROOT", sysroot);
+            }
+            cmd.env_remove("MIRI_PRIRODA_FLAGS");
+        }
+        if let Some(cwd) = cwd {
+            cmd.current_dir(cwd);
+        }
+        if let Some(pgroup) = process_group {
+            #[cfg(unix)]
+            std::os::unix::process::CommandExt::process_group(&mut cmd, pgroup);
+            #[cfg(not(unix))]
+            throw_unsup_format!("process groups are not supported on this host (group {pgroup})");
+        }
+        cmd.stdin(stdin).stdout(stdout).stderr(stderr);
+
+        // Make sure everything the program printed so far appears before what the child prints.
+        if let Err(e) = io::stdout().flush() {
+            return interp_ok(Err(e));
+        }
+
+        let child = match cmd.spawn() {
+            Ok(child) => child,
+            Err(e) => return interp_ok(Err(e)),
+        };
+        let pid = child.id();
+        this.machine.children.children.insert(pid, ChildProcess { child, status: None });
+        interp_ok(Ok(pid))
+    }
+
+    /// Returns whether `pid` is a child process that has not been reaped yet.
+    fn is_child_process(&self, pid: u32) -> bool {
+        let this = self.eval_context_ref();
+        this.machine.children.children.contains_key(&pid)
+    }
+
+    /// Returns whether there are any child processes that have not been reaped yet.
+    fn has_child_processes(&self) -> bool {
+        let this = self.eval_context_ref();
+        !this.machine.children.children.is_empty()
+    }
+
+    /// Checks whether the child `pid` (or any child, if `None`) has exited, without reaping it.
+    fn try_wait_child(
+        &mut self,
+        pid: Option<u32>,
+    ) -> InterpResult<'tcx, io::Result<Option<(u32, ExitStatus)>>> {
+        let this = self.eval_context_mut();
+        let children = &mut this.machine.children;
+        let pids = match pid {
+            Some(pid) => vec![pid],
+            None => children.children.keys().copied().collect(),
+        };
+        for pid in pids {
+            match children.try_wait(pid) {
+                Ok(Some(status)) => return interp_ok(Ok(Some((pid, status)))),
+                Ok(None) => {}
+                Err(e) => return interp_ok(Err(e)),
+            }
+        }
+        interp_ok(Ok(None))
+    }
+
+    /// Blocks the active thread until the child `pid` (or any child, if `None`) has exited, and
+    /// then calls `finish` with its process ID and exit status. The child is not reaped.
+    fn wait_for_child(
+        &mut self,
+        pid: Option<u32>,
+        finish: DynMachineCallback<'tcx, io::Result<(u32, ExitStatus)>>,
+    ) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+        match this.try_wait_child(pid)? {
+            Ok(Some(exited)) => return finish.call(this, Ok(exited)),
+            Ok(None) => {}
+            Err(e) => return finish.call(this, Err(e)),
+        }
+        // There is no portable way to get notified when a child exits, so we check again after
+        // a short while. Other threads keep running in the meantime.
+        let deadline = this.machine.monotonic_clock.now().add_lossy(CHILD_POLL_INTERVAL);
+        this.block_thread(
+            BlockReason::ChildProcess,
+            Some(deadline.into()),
+            callback!(
+                @capture<'tcx> {
+                    pid: Option<u32>,
+                    finish: DynMachineCallback<'tcx, io::Result<(u32, ExitStatus)>>,
+                }
+                |this, unblock: UnblockKind| {
+                    assert_eq!(unblock, UnblockKind::TimedOut);
+                    this.wait_for_child(pid, finish)
+                }
+            ),
+        );
+        interp_ok(())
+    }
+
+    /// Forgets about an exited child process.
+    fn reap_child(&mut self, pid: u32) {
+        let this = self.eval_context_mut();
+        let process = this.machine.children.children.remove(&pid).unwrap();
+        assert!(process.status.is_some(), "cannot reap a child that is still running");
+    }
+
+    /// Forcibly terminates the child `pid`. Does nothing if it has already exited.
+    fn kill_child(&mut self, pid: u32) -> io::Result<()> {
+        let this = self.eval_context_mut();
+        let process = this.machine.children.children.get_mut(&pid).unwrap();
+        if process.status.is_some() {
+            return Ok(());
+        }
+        process.child.kill()
+    }
+}
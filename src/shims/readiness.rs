@@ -428,6 +428,11 @@ impl ReadinessWatched {
         watchers.remove(idx);
     }
 
+    /// Returns whether any readiness watcher is interested in us.
+    pub fn is_watched(&self) -> bool {
+        !self.watchers.borrow().is_empty()
+    }
+
     /// Returns whether the watched FD has any readiness watcher with a blocked thread watching it.
     pub fn has_watcher_with_blocked_thread(&self) -> bool {
         let watchers = self.watchers.borrow();
@@ -455,6 +460,9 @@ impl ReadinessWatched {
 #[derive(Default, Debug)]
 pub struct DelayedReadinessUpdates {
     to_update: RefCell<Vec<DynFileDescriptionRef>>,
+    /// Threads which were blocked on a file description that changed, and which need to
+    /// be woken up so they can retry.
+    to_unblock: RefCell<Vec<(ThreadId, BlockReason)>>,
 }
 
 impl DelayedReadinessUpdates {
@@ -462,7 +470,16 @@ impl DelayedReadinessUpdates {
         self.to_update.borrow_mut().push(fd);
     }
 
+    pub fn add_unblock(&self, threads: impl IntoIterator<Item = ThreadId>, reason: BlockReason) {
+        self.to_unblock.borrow_mut().extend(threads.into_iter().map(|thread| (thread, reason)));
+    }
+
     pub fn process<'tcx>(ecx: &mut MiriInterpCx<'tcx>) -> InterpResult<'tcx> {
+        let to_unblock =
+            std::mem::take(&mut *ecx.machine.delayed_readiness_updates.to_unblock.borrow_mut());
+        for (thread, reason) in to_unblock {
+            ecx.unblock_thread(thread, reason)?;
+        }
         loop {
             // Avoid keeping the RefCell open over the `update_fd_readiness` as that can invoke
             // arbitrary code via the unblock callback.
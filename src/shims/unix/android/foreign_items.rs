@@ -4,15 +4,18 @@ use rustc_span::Symbol;
 use rustc_target::callconv::FnAbi;
 
 use crate::shims::unix::env::EvalContextExt as _;
+use crate::shims::unix::linux_like::copy::EvalContextExt as _;
 use crate::shims::unix::linux_like::epoll::EvalContextExt as _;
 use crate::shims::unix::linux_like::eventfd::EvalContextExt as _;
+use crate::shims::unix::linux_like::signalfd::EvalContextExt as _;
 use crate::shims::unix::linux_like::syscall::syscall;
 use crate::shims::unix::linux_like::thread::prctl;
+use crate::shims::unix::linux_like::timerfd::EvalContextExt as _;
 use crate::shims::unix::*;
 use crate::*;
 
 pub fn is_dyn_sym(name: &str) -> bool {
-    matches!(name, "gettid")
+    matches!(name, "gettid" | "copy_file_range" | "splice")
 }
 
 impl<'tcx> EvalContextExt<'tcx> for crate::MiriInterpCx<'tcx> {}
@@ -81,8 +84,33 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
                 let result = this.ftruncate64(fd, length)?;
                 this.write_scalar(result, dest)?;
             }
+            "copy_file_range" => {
+                let [fd_in, off_in, fd_out, off_out, len, flags] = this.check_shim_sig(
+                    shim_sig!(extern "C" fn(i32, *mut _, i32, *mut _, usize, u32) -> isize),
+                    link_name,
+                    abi,
+                    args,
+                )?;
+                this.copy_file_range(fd_in, off_in, fd_out, off_out, len, flags, dest)?;
+            }
+            "sendfile" | "sendfile64" => {
+                let [out_fd, in_fd, offset, count] = this.check_shim_sig(
+                    shim_sig!(extern "C" fn(i32, i32, *mut _, usize) -> isize),
+                    link_name,
+                    abi,
+                    args,
+                )?;
+                let off_t = if link_name.as_str() == "sendfile" { "off_t" } else { "off64_t" };
+                this.sendfile(out_fd, in_fd, offset, count, off_t, dest)?;
+            }
+            "splice" => {
+                // std declares `flags` as `c_int` rather than `c_uint` here.
+                let [fd_in, off_in, fd_out, off_out, len, flags] =
+                    this.check_shim_sig_lenient(abi, CanonAbi::C, link_name, args)?;
+                this.splice(fd_in, off_in, fd_out, off_out, len, flags, dest)?;
+            }
 
-            // epoll, eventfd
+            // epoll, eventfd, timerfd, signalfd, inotify
             "epoll_create1" => {
                 let [flag] = this.check_shim_sig_lenient(abi, CanonAbi::C, link_name, args)?;
                 let result = this.epoll_create1(flag)?;
@@ -104,6 +132,52 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
                 let result = this.eventfd(val, flag)?;
                 this.write_scalar(result, dest)?;
             }
+            "timerfd_create" => {
+                let [clockid, flags] =
+                    this.check_shim_sig_lenient(abi, CanonAbi::C, link_name, args)?;
+                let result = this.timerfd_create(clockid, flags)?;
+                this.write_scalar(result, dest)?;
+            }
+            "timerfd_settime" => {
+                let [fd, flags, new_value, old_value] =
+                    this.check_shim_sig_lenient(abi, CanonAbi::C, link_name, args)?;
+                let result = this.timerfd_settime(fd, flags, new_value, old_value)?;
+                this.write_scalar(result, dest)?;
+            }
+            "timerfd_gettime" => {
+                let [fd, curr_value] =
+                    this.check_shim_sig_lenient(abi, CanonAbi::C, link_name, args)?;
+                let result = this.timerfd_gettime(fd, curr_value)?;
+                this.write_scalar(result, dest)?;
+            }
+            "signalfd" => {
+                let [fd, mask, flags] =
+                    this.check_shim_sig_lenient(abi, CanonAbi::C, link_name, args)?;
+                let result = this.signalfd(fd, mask, flags)?;
+                this.write_scalar(result, dest)?;
+            }
+            "inotify_init" => {
+                let [] = this.check_shim_sig_lenient(abi, CanonAbi::C, link_name, args)?;
+                let result = this.inotify_init1(0)?;
+                this.write_scalar(result, dest)?;
+            }
+            "inotify_init1" => {
+                let [flags] = this.check_shim_sig_lenient(abi, CanonAbi::C, link_name, args)?;
+                let flags = this.read_scalar(flags)?.to_i32()?;
+                let result = this.inotify_init1(flags)?;
+                this.write_scalar(result, dest)?;
+            }
+            "inotify_add_watch" => {
+                let [fd, pathname, mask] =
+                    this.check_shim_sig_lenient(abi, CanonAbi::C, link_name, args)?;
+                let result = this.inotify_add_watch(fd, pathname, mask)?;
+                this.write_scalar(result, dest)?;
+            }
+            "inotify_rm_watch" => {
+                let [fd, wd] = this.check_shim_sig_lenient(abi, CanonAbi::C, link_name, args)?;
+                let result = this.inotify_rm_watch(fd, wd)?;
+                this.write_scalar(result, dest)?;
+            }
 
             // Miscellaneous
             "__errno" => {
@@ -1,6 +1,7 @@
 use std::env;
 use std::ffi::{OsStr, OsString};
 use std::io::ErrorKind;
+use std::path::PathBuf;
 
 use rustc_abi::{FieldIdx, Size};
 use rustc_data_structures::fx::FxHashMap;
@@ -200,14 +201,20 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         let buf = this.read_pointer(buf_op)?;
         let size = this.read_target_usize(size_op)?;
 
-        if let IsolatedOp::Reject(reject_with) = this.machine.isolated_op {
-            this.reject_in_isolation("`getcwd`", reject_with)?;
-            this.set_last_error(ErrorKind::PermissionDenied)?;
-            return interp_ok(Pointer::null());
-        }
+        // Relative paths in the in-memory file system are relative to its root.
+        let cwd = if this.machine.vfs.is_some() {
+            Ok(PathBuf::from("/"))
+        } else {
+            if let IsolatedOp::Reject(reject_with) = this.machine.isolated_op {
+                this.reject_in_isolation("`getcwd`", reject_with)?;
+                this.set_last_error(ErrorKind::PermissionDenied)?;
+                return interp_ok(Pointer::null());
+            }
+            env::current_dir()
+        };
 
         // If we cannot get the current directory, we return null
-        match env::current_dir() {
+        match cwd {
             Ok(cwd) => {
                 if this.write_path_to_c_str(&cwd, buf, size)?.0 {
                     return interp_ok(buf);
@@ -2,14 +2,14 @@
 //! standard file descriptors (stdin/stdout/stderr).
 
 use std::io;
-use std::io::ErrorKind;
+use std::io::{ErrorKind, SeekFrom};
 
 use rand::RngExt;
 use rustc_abi::{Align, Size};
 use rustc_target::spec::Os;
 
 use crate::shims::FileDescriptionRef;
-use crate::shims::files::{DynFileDescriptionRef, FdNum, FileDescription};
+use crate::shims::files::{DynFileDescriptionRef, FdNum, FileDescription, FileHandle};
 use crate::shims::sig::check_min_vararg_count;
 use crate::shims::unix::socket::UnixSocketFileDescription;
 use crate::shims::unix::*;
@@ -458,26 +458,8 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
             return this.set_errno_and_return_neg1(LibcError("EBADF"), dest);
         };
 
-        let iovec_layout = this.libc_array_ty_layout("iovec", iovcnt);
-        let iov_ptr_mplace = this.ptr_to_mplace(iov_ptr, iovec_layout);
-
         // Read list of buffers from `iov`.
-        let mut buffers = Vec::new();
-
-        let mut array = this.project_array_fields(&iov_ptr_mplace)?;
-        while let Some((_idx, iovec)) = array.next(this)? {
-            let iov_len_field = this.project_field_named(&iovec, "iov_len")?;
-            let iov_len: u64 = this
-                .read_scalar(&iov_len_field)?
-                .to_int(iov_len_field.layout.size)?
-                .try_into()
-                .unwrap();
-
-            let iov_base_field = this.project_field_named(&iovec, "iov_base")?;
-            let iov_base_ptr = this.read_pointer(&iov_base_field)?;
-
-            buffers.push((iov_base_ptr, iov_len));
-        }
+        let buffers = this.read_iovecs(iov_ptr, iovcnt)?;
 
         let total_bytes = buffers.iter().map(|(_, len)| len).sum::<u64>();
 
@@ -513,36 +495,7 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
                             return this.set_errno_and_return_neg1(e, &dest)
                         }
                     };
-                    let mut remaining_bytes = bytes_read;
-
-                    // Split the bytes from the temporary buffer into the buffers provided in `iov`.
-                    // We start at the first buffer and fill them in order, until we reach the end of the
-                    // initialized bytes in the temporary buffer.
-                    for (buffer_ptr, buffer_len) in buffers {
-                        // Offset temporary buffer by the amount of bytes we already copied into previous buffers.
-                        let tmp_ptr_with_offset =
-                            this.ptr_offset_inbounds(tmp_ptr, i64::try_from(bytes_read.strict_sub(remaining_bytes)).unwrap())?;
-
-                        // Copy at most as many bytes as the buffer fits but without reading
-                        // any uninitialized bytes from the temporary buffer.
-                        let copy_amount = buffer_len.min(remaining_bytes);
-                        this.mem_copy(
-                            tmp_ptr_with_offset,
-                            buffer_ptr,
-                            Size::from_bytes(copy_amount),
-                            // The buffers are guaranteed to not overlap because we just newly allocated
-                            // the `tmp_ptr`, and `tmp_ptr_with_offset` is guaranteed to be
-                            // within those boundaries.
-                            true,
-                        )?;
-
-                        remaining_bytes = remaining_bytes.strict_sub(copy_amount);
-                        if remaining_bytes == 0 {
-                            // We don't have anything left to copy; exit the loop.
-                            break;
-                        }
-                    }
-
+                    this.scatter_into_iovecs(tmp_ptr, bytes_read, &buffers)?;
                     this.deallocate_ptr(tmp_ptr, None, MemoryKind::Stack)
                 }),
         )
@@ -581,10 +534,45 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
             return this.set_errno_and_return_neg1(LibcError("EBADF"), dest);
         };
 
+        // Read list of buffers from `iov`.
+        let buffers = this.read_iovecs(iov_ptr, iovcnt)?;
+
+        // Copy the bytes from all buffers provided in `iov` into a temporary buffer.
+        let (tmp_ptr, total_bytes) = this.gather_iovecs(&buffers)?;
+
+        let dest = dest.clone();
+        // Write bytes from the temporary buffer. This ensures the write is atomic.
+        this.write_to_fd(
+            fd,
+            tmp_ptr,
+            usize::try_from(total_bytes).unwrap(),
+            offset,
+            callback!(
+                @capture<'tcx> {
+                    tmp_ptr: Pointer,
+                    dest: MPlaceTy<'tcx>,
+                }
+                |this, result: Result<usize, IoError>| {
+                    this.deallocate_ptr(tmp_ptr, None, MemoryKind::Stack)?;
+                    match result {
+                        Ok(size) => this.write_scalar(Scalar::from_target_isize(size.try_into().unwrap(), this), &dest),
+                        Err(e) => this.set_errno_and_return_neg1(e, &dest)
+                    }
+            }),
+        )
+    }
+
+    /// Read the list of buffers described by the `iovcnt` `iovec` structs at `iov_ptr`.
+    fn read_iovecs(
+        &self,
+        iov_ptr: Pointer,
+        iovcnt: u64,
+    ) -> InterpResult<'tcx, Vec<(Pointer, u64)>> {
+        let this = self.eval_context_ref();
+
         let iovec_layout = this.libc_array_ty_layout("iovec", iovcnt);
         let iov_ptr_mplace = this.ptr_to_mplace(iov_ptr, iovec_layout);
 
-        // Read list of buffers from `iov`.
         let mut buffers = Vec::new();
 
         let mut array = this.project_array_fields(&iov_ptr_mplace)?;
@@ -602,9 +590,18 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
             buffers.push((iov_base_ptr, iov_len));
         }
 
+        interp_ok(buffers)
+    }
+
+    /// Copy the bytes of all `buffers` into a newly allocated temporary buffer, returning
+    /// a pointer to it and its size. The caller has to deallocate the temporary buffer
+    /// with [`MemoryKind::Stack`].
+    fn gather_iovecs(&mut self, buffers: &[(Pointer, u64)]) -> InterpResult<'tcx, (Pointer, u64)> {
+        let this = self.eval_context_mut();
+
         let total_bytes = buffers.iter().map(|(_, len)| len).sum::<u64>();
 
-        // Allocate a temporary buffer which has the combined size of all buffers provided in `iov`.
+        // Allocate a temporary buffer which has the combined size of all buffers.
         let tmp_ptr: Pointer = this
             .allocate_ptr(
                 Size::from_bytes(total_bytes),
@@ -614,10 +611,9 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
             )?
             .into();
 
-        // Copy the bytes from all buffers provided in `iov` into the temporary buffer.
         // We start at the first buffer and then continue buffer by buffer.
         let mut bytes_copied: u64 = 0;
-        for (buffer_ptr, buffer_len) in buffers {
+        for &(buffer_ptr, buffer_len) in buffers {
             // Offset temporary buffer by the amount of bytes we already copied from previous buffers.
             let tmp_ptr_with_offset =
                 this.ptr_offset_inbounds(tmp_ptr, i64::try_from(bytes_copied).unwrap())?;
@@ -635,29 +631,154 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
             bytes_copied = bytes_copied.strict_add(buffer_len);
         }
 
-        let dest = dest.clone();
-        // Write bytes from the temporary buffer. This ensures the write is atomic.
-        this.write_to_fd(
-            fd,
+        interp_ok((tmp_ptr, total_bytes))
+    }
+
+    /// Split the first `len` bytes of the temporary buffer at `tmp_ptr` into `buffers`.
+    fn scatter_into_iovecs(
+        &mut self,
+        tmp_ptr: Pointer,
+        len: u64,
+        buffers: &[(Pointer, u64)],
+    ) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+
+        let mut remaining_bytes = len;
+
+        // We start at the first buffer and fill them in order, until we reach the end of the
+        // initialized bytes in the temporary buffer.
+        for &(buffer_ptr, buffer_len) in buffers {
+            if remaining_bytes == 0 {
+                // We don't have anything left to copy; exit the loop.
+                break;
+            }
+
+            // Offset temporary buffer by the amount of bytes we already copied into previous buffers.
+            let tmp_ptr_with_offset = this.ptr_offset_inbounds(
+                tmp_ptr,
+                i64::try_from(len.strict_sub(remaining_bytes)).unwrap(),
+            )?;
+
+            // Copy at most as many bytes as the buffer fits but without reading
+            // any uninitialized bytes from the temporary buffer.
+            let copy_amount = buffer_len.min(remaining_bytes);
+            this.mem_copy(
+                tmp_ptr_with_offset,
+                buffer_ptr,
+                Size::from_bytes(copy_amount),
+                // The buffers are guaranteed to not overlap because we just newly allocated
+                // the `tmp_ptr`, and `tmp_ptr_with_offset` is guaranteed to be
+                // within those boundaries.
+                true,
+            )?;
+
+            remaining_bytes = remaining_bytes.strict_sub(copy_amount);
+        }
+
+        interp_ok(())
+    }
+
+    /// Moves up to `len` bytes from `fd_in` to `fd_out`, as done by `copy_file_range`,
+    /// `sendfile`, `splice` and `fcopyfile`. The data goes through a temporary buffer, so this is
+    /// just a read followed by a write.
+    ///
+    /// `offset_in` and `offset_out` behave like the offsets of `pread` and `pwrite`. A file
+    /// input without an offset is still read at its current position, which is then only
+    /// advanced by the number of bytes that were written, so a short write does not lose any
+    /// data. For all other inputs, the bytes that were read are gone, so we keep writing until
+    /// all of them made it to the output.
+    ///
+    /// `finish` is called with the number of transferred bytes, 0 meaning the end of the input.
+    fn transfer_between_fds(
+        &mut self,
+        fd_in: DynFileDescriptionRef,
+        offset_in: Option<u64>,
+        fd_out: DynFileDescriptionRef,
+        offset_out: Option<u64>,
+        len: usize,
+        finish: DynMachineCallback<'tcx, Result<usize, IoError>>,
+    ) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+
+        // Like the kernel, we move at most a bounded amount of data at once and let the
+        // caller come back for the rest.
+        let len = len.min(TRANSFER_CHUNK_SIZE);
+        if len == 0 {
+            return finish.call(this, Ok(0));
+        }
+
+        let (read_offset, in_position) = match offset_in {
+            Some(offset) => (Some(offset), None),
+            None if fd_in.clone().downcast::<FileHandle>().is_some() => {
+                let position = match fd_in.seek(this.machine.communicate(), SeekFrom::Current(0))? {
+                    Ok(position) => position,
+                    Err(e) => return finish.call(this, Err(e.into())),
+                };
+                (Some(position), Some(position))
+            }
+            None => (None, None),
+        };
+
+        let tmp_ptr: Pointer = this
+            .allocate_ptr(Size::from_bytes(len), Align::ONE, MemoryKind::Stack, AllocInit::Uninit)?
+            .into();
+
+        let transfer = Transfer {
+            fd_in: fd_in.clone(),
+            seekable_in: read_offset.is_some(),
+            in_position,
+            fd_out,
+            offset_out,
             tmp_ptr,
-            usize::try_from(total_bytes).unwrap(),
-            offset,
+            read: 0,
+        };
+        this.read_from_fd(
+            fd_in,
+            tmp_ptr,
+            len,
+            read_offset.map(i128::from),
             callback!(
                 @capture<'tcx> {
-                    tmp_ptr: Pointer,
-                    dest: MPlaceTy<'tcx>,
+                    transfer: Transfer,
+                    finish: DynMachineCallback<'tcx, Result<usize, IoError>>,
                 }
                 |this, result: Result<usize, IoError>| {
-                    this.deallocate_ptr(tmp_ptr, None, MemoryKind::Stack)?;
                     match result {
-                        Ok(size) => this.write_scalar(Scalar::from_target_isize(size.try_into().unwrap(), this), &dest),
-                        Err(e) => this.set_errno_and_return_neg1(e, &dest)
+                        Ok(0) => this.finish_transfer(transfer, Ok(0), finish),
+                        Ok(read) => this.transfer_write(Transfer { read, ..transfer }, 0, finish),
+                        Err(e) => this.finish_transfer(transfer, Err(e), finish),
                     }
-            }),
+                }
+            ),
         )
     }
 }
 
+/// The largest number of bytes [`EvalContextExt::transfer_between_fds`] moves at once. This
+/// matches the default capacity of a Linux pipe.
+const TRANSFER_CHUNK_SIZE: usize = 64 * 1024;
+
+/// A transfer between two file descriptions whose data sits in a temporary buffer.
+struct Transfer {
+    fd_in: DynFileDescriptionRef,
+    /// Whether the input can be read again at the same position, i.e., whether data that
+    /// does not get written is still available afterwards.
+    seekable_in: bool,
+    /// The position of a file input before the transfer, if that needs to be advanced.
+    in_position: Option<u64>,
+    fd_out: DynFileDescriptionRef,
+    offset_out: Option<u64>,
+    tmp_ptr: Pointer,
+    /// The number of bytes in the temporary buffer.
+    read: usize,
+}
+
+impl VisitProvenance for Transfer {
+    fn visit_provenance(&self, visit: &mut VisitWith<'_>) {
+        self.tmp_ptr.visit_provenance(visit);
+    }
+}
+
 impl<'tcx> EvalContextPrivExt<'tcx> for crate::MiriInterpCx<'tcx> {}
 trait EvalContextPrivExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
     /// Read `len` bytes from the `fd` file description at `offset` into the buffer
@@ -773,4 +894,73 @@ trait EvalContextPrivExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         };
         interp_ok(())
     }
+
+    /// Writes the bytes of the temporary buffer of `transfer`, starting after the first `written`.
+    fn transfer_write(
+        &mut self,
+        transfer: Transfer,
+        written: usize,
+        finish: DynMachineCallback<'tcx, Result<usize, IoError>>,
+    ) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+
+        let ptr = transfer.tmp_ptr.wrapping_offset(Size::from_bytes(written), this);
+        let offset = transfer
+            .offset_out
+            .map(|offset| i128::from(offset).strict_add(i128::try_from(written).unwrap()));
+        this.write_to_fd(
+            transfer.fd_out.clone(),
+            ptr,
+            transfer.read.strict_sub(written),
+            offset,
+            callback!(
+                @capture<'tcx> {
+                    transfer: Transfer,
+                    written: usize,
+                    finish: DynMachineCallback<'tcx, Result<usize, IoError>>,
+                }
+                |this, result: Result<usize, IoError>| {
+                    let (written, result) = match result {
+                        Ok(size) => (written.strict_add(size), Ok(size)),
+                        Err(e) => (written, Err(e)),
+                    };
+                    if written == transfer.read || transfer.seekable_in {
+                        // Whatever was not written can be read again.
+                        let result = if written > 0 { Ok(written) } else { result.map(|_| 0) };
+                        return this.finish_transfer(transfer, result, finish);
+                    }
+                    match result {
+                        Ok(size) if size > 0 => this.transfer_write(transfer, written, finish),
+                        _ =>
+                            throw_unsup_format!(
+                                "cannot write all the data read from a {} to a {}: Miri cannot put the rest back",
+                                transfer.fd_in.name(),
+                                transfer.fd_out.name(),
+                            ),
+                    }
+                }
+            ),
+        )
+    }
+
+    /// Cleans up after a transfer and reports its `result`.
+    fn finish_transfer(
+        &mut self,
+        transfer: Transfer,
+        result: Result<usize, IoError>,
+        finish: DynMachineCallback<'tcx, Result<usize, IoError>>,
+    ) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+
+        this.deallocate_ptr(transfer.tmp_ptr, None, MemoryKind::Stack)?;
+        if let (Ok(written), Some(position)) = (&result, transfer.in_position) {
+            let position = position.strict_add(u64::try_from(*written).unwrap());
+            if let Err(e) =
+                transfer.fd_in.seek(this.machine.communicate(), SeekFrom::Start(position))?
+            {
+                return finish.call(this, Err(e.into()));
+            }
+        }
+        finish.call(this, result)
+    }
 }
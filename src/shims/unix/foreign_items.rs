@@ -390,7 +390,16 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
                 // has O_CREAT (or on linux O_TMPFILE, but miri doesn't support that) set
                 let ([path_raw, flag], varargs) =
                     this.check_shim_sig_variadic_lenient(abi, CanonAbi::C, link_name, args)?;
-                let result = this.open(path_raw, flag, varargs)?;
+                let at_fdcwd = this.eval_libc_i32("AT_FDCWD");
+                let result = this.open(at_fdcwd, path_raw, flag, varargs)?;
+                this.write_scalar(result, dest)?;
+            }
+            "openat" => {
+                // `openat` is variadic just like `open`.
+                let ([dirfd, path_raw, flag], varargs) =
+                    this.check_shim_sig_variadic_lenient(abi, CanonAbi::C, link_name, args)?;
+                let dirfd = this.read_scalar(dirfd)?.to_i32()?;
+                let result = this.open(dirfd, path_raw, flag, varargs)?;
                 this.write_scalar(result, dest)?;
             }
             "unlink" => {
@@ -401,7 +410,20 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
                     abi,
                     args,
                 )?;
-                let result = this.unlink(path)?;
+                let at_fdcwd = this.eval_libc_i32("AT_FDCWD");
+                let result = this.unlink(at_fdcwd, path, 0)?;
+                this.write_scalar(result, dest)?;
+            }
+            "unlinkat" => {
+                let [dirfd, path, flags] = this.check_shim_sig(
+                    shim_sig!(extern "C" fn(i32, *const _, i32) -> i32),
+                    link_name,
+                    abi,
+                    args,
+                )?;
+                let dirfd = this.read_scalar(dirfd)?.to_i32()?;
+                let flags = this.read_scalar(flags)?.to_i32()?;
+                let result = this.unlink(dirfd, path, flags)?;
                 this.write_scalar(result, dest)?;
             }
             "symlink" => {
@@ -440,6 +462,12 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
                 let result = this.stat(path, buf)?;
                 this.write_scalar(result, dest)?;
             }
+            "fstatat" => {
+                let [dirfd, path, buf, flags] =
+                    this.check_shim_sig_lenient(abi, CanonAbi::C, link_name, args)?;
+                let result = this.fstatat(dirfd, path, buf, flags)?;
+                this.write_scalar(result, dest)?;
+            }
             "chmod" => {
                 let [path, mode] = this.check_shim_sig(
                     shim_sig!(extern "C" fn(*const _, libc::mode_t) -> i32),
@@ -447,7 +475,20 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
                     abi,
                     args,
                 )?;
-                let result = this.chmod(path, mode)?;
+                let at_fdcwd = this.eval_libc_i32("AT_FDCWD");
+                let result = this.chmod(at_fdcwd, path, mode, 0)?;
+                this.write_scalar(result, dest)?;
+            }
+            "fchmodat" => {
+                let [dirfd, path, mode, flags] = this.check_shim_sig(
+                    shim_sig!(extern "C" fn(i32, *const _, libc::mode_t, i32) -> i32),
+                    link_name,
+                    abi,
+                    args,
+                )?;
+                let dirfd = this.read_scalar(dirfd)?.to_i32()?;
+                let flags = this.read_scalar(flags)?.to_i32()?;
+                let result = this.chmod(dirfd, path, mode, flags)?;
                 this.write_scalar(result, dest)?;
             }
             "fchmod" => {
@@ -468,7 +509,20 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
                     abi,
                     args,
                 )?;
-                let result = this.rename(oldpath, newpath)?;
+                let at_fdcwd = this.eval_libc_i32("AT_FDCWD");
+                let result = this.rename(at_fdcwd, oldpath, at_fdcwd, newpath, 0)?;
+                this.write_scalar(result, dest)?;
+            }
+            "renameat" => {
+                let [olddirfd, oldpath, newdirfd, newpath] = this.check_shim_sig(
+                    shim_sig!(extern "C" fn(i32, *const _, i32, *const _) -> i32),
+                    link_name,
+                    abi,
+                    args,
+                )?;
+                let olddirfd = this.read_scalar(olddirfd)?.to_i32()?;
+                let newdirfd = this.read_scalar(newdirfd)?.to_i32()?;
+                let result = this.rename(olddirfd, oldpath, newdirfd, newpath, 0)?;
                 this.write_scalar(result, dest)?;
             }
             "mkdir" => {
@@ -479,7 +533,19 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
                     abi,
                     args,
                 )?;
-                let result = this.mkdir(path, mode)?;
+                let at_fdcwd = this.eval_libc_i32("AT_FDCWD");
+                let result = this.mkdir(at_fdcwd, path, mode)?;
+                this.write_scalar(result, dest)?;
+            }
+            "mkdirat" => {
+                let [dirfd, path, mode] = this.check_shim_sig(
+                    shim_sig!(extern "C" fn(i32, *const _, libc::mode_t) -> i32),
+                    link_name,
+                    abi,
+                    args,
+                )?;
+                let dirfd = this.read_scalar(dirfd)?.to_i32()?;
+                let result = this.mkdir(dirfd, path, mode)?;
                 this.write_scalar(result, dest)?;
             }
             "rmdir" => {
@@ -490,7 +556,8 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
                     abi,
                     args,
                 )?;
-                let result = this.rmdir(path)?;
+                let at_fdcwd = this.eval_libc_i32("AT_FDCWD");
+                let result = this.rmdir(at_fdcwd, path)?;
                 this.write_scalar(result, dest)?;
             }
             "opendir" => {
@@ -581,7 +648,19 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
                     abi,
                     args,
                 )?;
-                let result = this.readlink(pathname, buf, bufsize)?;
+                let at_fdcwd = this.eval_libc_i32("AT_FDCWD");
+                let result = this.readlink(at_fdcwd, pathname, buf, bufsize)?;
+                this.write_scalar(Scalar::from_target_isize(result, this), dest)?;
+            }
+            "readlinkat" => {
+                let [dirfd, pathname, buf, bufsize] = this.check_shim_sig(
+                    shim_sig!(extern "C" fn(i32, *const _, *mut _, usize) -> isize),
+                    link_name,
+                    abi,
+                    args,
+                )?;
+                let dirfd = this.read_scalar(dirfd)?.to_i32()?;
+                let result = this.readlink(dirfd, pathname, buf, bufsize)?;
                 this.write_scalar(Scalar::from_target_isize(result, this), dest)?;
             }
             "posix_fadvise" => {
@@ -769,6 +848,60 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
                 )?;
                 this.recv(socket, buffer, length, flags, dest)?;
             }
+            "sendto" => {
+                let [socket, buffer, length, flags, address, address_len] = this.check_shim_sig(
+                    shim_sig!(
+                        extern "C" fn(
+                            i32,
+                            *const _,
+                            libc::size_t,
+                            i32,
+                            *const _,
+                            libc::socklen_t,
+                        ) -> libc::ssize_t
+                    ),
+                    link_name,
+                    abi,
+                    args,
+                )?;
+                this.sendto(socket, buffer, length, flags, address, address_len, dest)?;
+            }
+            "recvfrom" => {
+                let [socket, buffer, length, flags, address, address_len] = this.check_shim_sig(
+                    shim_sig!(
+                        extern "C" fn(
+                            i32,
+                            *mut _,
+                            libc::size_t,
+                            i32,
+                            *mut _,
+                            *mut _,
+                        ) -> libc::ssize_t
+                    ),
+                    link_name,
+                    abi,
+                    args,
+                )?;
+                this.recvfrom(socket, buffer, length, flags, address, address_len, dest)?;
+            }
+            "sendmsg" => {
+                let [socket, message, flags] = this.check_shim_sig(
+                    shim_sig!(extern "C" fn(i32, *const _, i32) -> libc::ssize_t),
+                    link_name,
+                    abi,
+                    args,
+                )?;
+                this.sendmsg(socket, message, flags, dest)?;
+            }
+            "recvmsg" => {
+                let [socket, message, flags] = this.check_shim_sig(
+                    shim_sig!(extern "C" fn(i32, *mut _, i32) -> libc::ssize_t),
+                    link_name,
+                    abi,
+                    args,
+                )?;
+                this.recvmsg(socket, message, flags, dest)?;
+            }
             "setsockopt" => {
                 let [socket, level, option_name, option_value, option_len] = this.check_shim_sig(
                     shim_sig!(extern "C" fn(i32, i32, i32, *const _, libc::socklen_t) -> i32),
@@ -1320,6 +1453,297 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
                 }
             }
 
+            // Signals
+            "sigaction" => {
+                let [signum, act, oldact] = this.check_shim_sig(
+                    shim_sig!(extern "C" fn(i32, *const _, *mut _) -> i32),
+                    link_name,
+                    abi,
+                    args,
+                )?;
+                let result = this.sigaction(signum, act, oldact)?;
+                this.write_scalar(result, dest)?;
+            }
+            "signal" => {
+                let [signum, handler] = this.check_shim_sig(
+                    shim_sig!(extern "C" fn(i32, libc::sighandler_t) -> libc::sighandler_t),
+                    link_name,
+                    abi,
+                    args,
+                )?;
+                let result = this.signal(signum, handler)?;
+                this.write_scalar(result, dest)?;
+            }
+            "sigprocmask" => {
+                let [how, set, oldset] = this.check_shim_sig(
+                    shim_sig!(extern "C" fn(i32, *const _, *mut _) -> i32),
+                    link_name,
+                    abi,
+                    args,
+                )?;
+                match this.sigprocmask(how, set, oldset)? {
+                    Ok(()) => this.write_null(dest)?,
+                    Err(err) => this.set_errno_and_return_neg1(err, dest)?,
+                }
+            }
+            "pthread_sigmask" => {
+                let [how, set, oldset] = this.check_shim_sig(
+                    shim_sig!(extern "C" fn(i32, *const _, *mut _) -> i32),
+                    link_name,
+                    abi,
+                    args,
+                )?;
+                // Unlike `sigprocmask`, this returns the error number instead of setting `errno`.
+                let result = match this.sigprocmask(how, set, oldset)? {
+                    Ok(()) => Scalar::from_i32(0),
+                    Err(err) => this.io_error_to_errnum(err)?,
+                };
+                this.write_scalar(result, dest)?;
+            }
+            "sigpending" => {
+                let [set] = this.check_shim_sig(
+                    shim_sig!(extern "C" fn(*mut _) -> i32),
+                    link_name,
+                    abi,
+                    args,
+                )?;
+                let result = this.sigpending(set)?;
+                this.write_scalar(result, dest)?;
+            }
+            "sigaltstack" => {
+                let [ss, old_ss] = this.check_shim_sig(
+                    shim_sig!(extern "C" fn(*const _, *mut _) -> i32),
+                    link_name,
+                    abi,
+                    args,
+                )?;
+                let result = this.sigaltstack(ss, old_ss)?;
+                this.write_scalar(result, dest)?;
+            }
+            "sigemptyset" | "sigfillset" => {
+                let [set] = this.check_shim_sig(
+                    shim_sig!(extern "C" fn(*mut _) -> i32),
+                    link_name,
+                    abi,
+                    args,
+                )?;
+                let result = this.sigset_op(link_name.as_str(), set, None)?;
+                this.write_scalar(result, dest)?;
+            }
+            "sigaddset" | "sigdelset" => {
+                let [set, signum] = this.check_shim_sig(
+                    shim_sig!(extern "C" fn(*mut _, i32) -> i32),
+                    link_name,
+                    abi,
+                    args,
+                )?;
+                let result = this.sigset_op(link_name.as_str(), set, Some(signum))?;
+                this.write_scalar(result, dest)?;
+            }
+            "sigismember" => {
+                let [set, signum] = this.check_shim_sig(
+                    shim_sig!(extern "C" fn(*const _, i32) -> i32),
+                    link_name,
+                    abi,
+                    args,
+                )?;
+                let result = this.sigset_op(link_name.as_str(), set, Some(signum))?;
+                this.write_scalar(result, dest)?;
+            }
+            "raise" => {
+                let [signum] = this.check_shim_sig(
+                    shim_sig!(extern "C" fn(i32) -> i32),
+                    link_name,
+                    abi,
+                    args,
+                )?;
+                let result = this.raise(signum)?;
+                this.write_scalar(result, dest)?;
+            }
+            "kill" => {
+                let [pid, signum] = this.check_shim_sig(
+                    shim_sig!(extern "C" fn(libc::pid_t, i32) -> i32),
+                    link_name,
+                    abi,
+                    args,
+                )?;
+                let result = this.kill(pid, signum)?;
+                this.write_scalar(result, dest)?;
+            }
+            "pthread_kill" => {
+                let [thread, signum] = this.check_shim_sig(
+                    shim_sig!(extern "C" fn(libc::pthread_t, i32) -> i32),
+                    link_name,
+                    abi,
+                    args,
+                )?;
+                let result = this.pthread_kill(thread, signum)?;
+                this.write_scalar(result, dest)?;
+            }
+
+            // Child processes
+            "posix_spawn_file_actions_init" => {
+                let [file_actions] = this.check_shim_sig(
+                    shim_sig!(extern "C" fn(*mut _) -> i32),
+                    link_name,
+                    abi,
+                    args,
+                )?;
+                let result = this.posix_spawn_file_actions_init(file_actions)?;
+                this.write_scalar(result, dest)?;
+            }
+            "posix_spawn_file_actions_destroy" => {
+                let [file_actions] = this.check_shim_sig(
+                    shim_sig!(extern "C" fn(*mut _) -> i32),
+                    link_name,
+                    abi,
+                    args,
+                )?;
+                let result = this.posix_spawn_file_actions_destroy(file_actions)?;
+                this.write_scalar(result, dest)?;
+            }
+            "posix_spawn_file_actions_adddup2" => {
+                let [file_actions, fd, newfd] = this.check_shim_sig(
+                    shim_sig!(extern "C" fn(*mut _, i32, i32) -> i32),
+                    link_name,
+                    abi,
+                    args,
+                )?;
+                let result = this.posix_spawn_file_actions_adddup2(file_actions, fd, newfd)?;
+                this.write_scalar(result, dest)?;
+            }
+            "posix_spawn_file_actions_addclose" => {
+                let [file_actions, fd] = this.check_shim_sig(
+                    shim_sig!(extern "C" fn(*mut _, i32) -> i32),
+                    link_name,
+                    abi,
+                    args,
+                )?;
+                let result = this.posix_spawn_file_actions_addclose(file_actions, fd)?;
+                this.write_scalar(result, dest)?;
+            }
+            "posix_spawn_file_actions_addchdir_np" | "posix_spawn_file_actions_addchdir" => {
+                let [file_actions, path] = this.check_shim_sig(
+                    shim_sig!(extern "C" fn(*mut _, *const _) -> i32),
+                    link_name,
+                    abi,
+                    args,
+                )?;
+                let result = this.posix_spawn_file_actions_addchdir(file_actions, path)?;
+                this.write_scalar(result, dest)?;
+            }
+            "posix_spawnattr_init" => {
+                let [attr] = this.check_shim_sig(
+                    shim_sig!(extern "C" fn(*mut _) -> i32),
+                    link_name,
+                    abi,
+                    args,
+                )?;
+                let result = this.posix_spawnattr_init(attr)?;
+                this.write_scalar(result, dest)?;
+            }
+            "posix_spawnattr_destroy" => {
+                let [attr] = this.check_shim_sig(
+                    shim_sig!(extern "C" fn(*mut _) -> i32),
+                    link_name,
+                    abi,
+                    args,
+                )?;
+                let result = this.posix_spawnattr_destroy(attr)?;
+                this.write_scalar(result, dest)?;
+            }
+            "posix_spawnattr_setflags" => {
+                let [attr, flags] = this.check_shim_sig(
+                    shim_sig!(extern "C" fn(*mut _, i16) -> i32),
+                    link_name,
+                    abi,
+                    args,
+                )?;
+                let result = this.posix_spawnattr_setflags(attr, flags)?;
+                this.write_scalar(result, dest)?;
+            }
+            "posix_spawnattr_getflags" => {
+                let [attr, flags] = this.check_shim_sig(
+                    shim_sig!(extern "C" fn(*const _, *mut _) -> i32),
+                    link_name,
+                    abi,
+                    args,
+                )?;
+                let result = this.posix_spawnattr_getflags(attr, flags)?;
+                this.write_scalar(result, dest)?;
+            }
+            "posix_spawnattr_setpgroup" => {
+                let [attr, pgroup] = this.check_shim_sig(
+                    shim_sig!(extern "C" fn(*mut _, libc::pid_t) -> i32),
+                    link_name,
+                    abi,
+                    args,
+                )?;
+                let result = this.posix_spawnattr_setpgroup(attr, pgroup)?;
+                this.write_scalar(result, dest)?;
+            }
+            "posix_spawnattr_getpgroup" => {
+                let [attr, pgroup] = this.check_shim_sig(
+                    shim_sig!(extern "C" fn(*const _, *mut _) -> i32),
+                    link_name,
+                    abi,
+                    args,
+                )?;
+                let result = this.posix_spawnattr_getpgroup(attr, pgroup)?;
+                this.write_scalar(result, dest)?;
+            }
+            "posix_spawnattr_setsigdefault" => {
+                let [attr, sigdefault] = this.check_shim_sig(
+                    shim_sig!(extern "C" fn(*mut _, *const _) -> i32),
+                    link_name,
+                    abi,
+                    args,
+                )?;
+                let result = this.posix_spawnattr_setsigdefault(attr, sigdefault)?;
+                this.write_scalar(result, dest)?;
+            }
+            "posix_spawn" | "posix_spawnp" => {
+                let [pid, path, file_actions, attr, argv, envp] = this.check_shim_sig(
+                    shim_sig!(
+                        extern "C" fn(
+                            *mut _,
+                            *const _,
+                            *const _,
+                            *const _,
+                            *const _,
+                            *const _,
+                        ) -> i32
+                    ),
+                    link_name,
+                    abi,
+                    args,
+                )?;
+                let search_path = link_name.as_str() == "posix_spawnp";
+                let result =
+                    this.posix_spawn(pid, path, file_actions, attr, argv, envp, search_path)?;
+                this.write_scalar(result, dest)?;
+            }
+            "waitpid" => {
+                let [pid, status, options] = this.check_shim_sig(
+                    shim_sig!(extern "C" fn(libc::pid_t, *mut _, i32) -> libc::pid_t),
+                    link_name,
+                    abi,
+                    args,
+                )?;
+                this.waitpid(pid, status, options, dest)?;
+            }
+            "fork" => {
+                let [] = this.check_shim_sig(
+                    shim_sig!(extern "C" fn() -> libc::pid_t),
+                    link_name,
+                    abi,
+                    args,
+                )?;
+                throw_unsup_format!(
+                    "`fork` is not supported; child processes can only be started with `posix_spawn`"
+                );
+            }
+
             // Miscellaneous
             "isatty" => {
                 let [fd] = this.check_shim_sig_lenient(abi, CanonAbi::C, link_name, args)?;
@@ -1333,7 +1757,8 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
                 this.read_pointer(prepare)?;
                 this.read_pointer(parent)?;
                 this.read_pointer(child)?;
-                // We do not support forking, so there is nothing to do here.
+                // We do not support forking (children are spawned with `posix_spawn`), so
+                // there is nothing to do here.
                 this.write_null(dest)?;
             }
             "getentropy" => {
@@ -1474,15 +1899,6 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
                 this.write_null(dest)?;
             }
 
-            "signal" | "sigaltstack" if this.frame_in_std() => {
-                let [_, _] = this.check_shim_sig_lenient(abi, CanonAbi::C, link_name, args)?;
-                this.write_null(dest)?;
-            }
-            "sigaction" if this.frame_in_std() => {
-                let [_, _, _] = this.check_shim_sig_lenient(abi, CanonAbi::C, link_name, args)?;
-                this.write_null(dest)?;
-            }
-
             "getpwuid_r" | "__posix_getpwuid_r" if this.frame_in_std() => {
                 // getpwuid_r is the standard name, __posix_getpwuid_r is used on solarish
                 let [uid, pwd, buf, buflen, result] =
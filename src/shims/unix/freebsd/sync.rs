@@ -143,6 +143,7 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
 
                     let dest = dest.clone();
                     this.futex_wait(
+                        obj.ptr(),
                         futex_ref,
                         u32::MAX, // we set the bitset to include all bits
                         deadline,
@@ -194,6 +195,7 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
 
                 // `_umtx_op` doesn't return the amount of woken threads.
                 let _woken = this.futex_wake(
+                    obj,
                     &futex_ref,
                     u32::MAX, // we set the bitset to include all bits
                     count,
@@ -4,7 +4,7 @@ use std::borrow::Cow;
 use std::ffi::OsString;
 use std::fs::{self, DirBuilder, File, FileTimes, FileType, OpenOptions, TryLockError};
 use std::io::{self, ErrorKind, Read, Seek, SeekFrom, Write};
-use std::path::{self, Path};
+use std::path::{self, Path, PathBuf};
 use std::time::SystemTime;
 
 use rustc_abi::{FieldIdx, Size};
@@ -13,10 +13,12 @@ use rustc_data_structures::fx::FxHashMap;
 use rustc_target::spec::Os;
 
 use self::shims::time::system_time_to_duration;
-use crate::shims::files::FileHandle;
+use crate::shims::files::{Backing, FileHandle, HandleMetadata};
 use crate::shims::os_str::bytes_to_os_str;
 use crate::shims::sig::check_min_vararg_count;
 use crate::shims::unix::fd::{FlockOp, UnixFileDescription};
+use crate::shims::unix::{FsEvent, InotifyEvalContextExt as _};
+use crate::shims::vfs::{VfsDirEntry, VfsMetadata, VfsOpenOptions};
 use crate::*;
 
 /// An open directory, tracked by DirHandler.
@@ -25,24 +27,33 @@ struct OpenDir {
     /// The "special" entries that must still be yielded by the iterator.
     /// Used for `.` and `..`.
     special_entries: Vec<&'static str>,
-    /// The directory reader on the host.
-    read_dir: fs::ReadDir,
+    /// The directory reader on the host, or the listing of an in-memory directory.
+    read_dir: Either<fs::ReadDir, std::vec::IntoIter<VfsDirEntry>>,
     /// The most recent entry returned by readdir().
     /// Will be freed by the next call.
     entry: Option<Pointer>,
 }
 
+/// An entry yielded by an [`OpenDir`].
+enum OpenDirEntry {
+    Host(fs::DirEntry),
+    Virtual(VfsDirEntry),
+    Special(&'static str),
+}
+
 impl OpenDir {
-    fn new(read_dir: fs::ReadDir) -> Self {
+    fn new(read_dir: Either<fs::ReadDir, std::vec::IntoIter<VfsDirEntry>>) -> Self {
         Self { special_entries: vec!["..", "."], read_dir, entry: None }
     }
 
-    fn next_host_entry(&mut self) -> Option<io::Result<Either<fs::DirEntry, &'static str>>> {
+    fn next_entry(&mut self) -> Option<io::Result<OpenDirEntry>> {
         if let Some(special) = self.special_entries.pop() {
-            return Some(Ok(Either::Right(special)));
+            return Some(Ok(OpenDirEntry::Special(special)));
+        }
+        match &mut self.read_dir {
+            Either::Left(read_dir) => Some(read_dir.next()?.map(OpenDirEntry::Host)),
+            Either::Right(entries) => Some(Ok(OpenDirEntry::Virtual(entries.next()?))),
         }
-        let entry = self.read_dir.next()?;
-        Some(entry.map(Either::Left))
     }
 }
 
@@ -70,7 +81,10 @@ impl UnixFileDescription for FileHandle {
         ecx: &mut MiriInterpCx<'tcx>,
         finish: DynMachineCallback<'tcx, Result<usize, IoError>>,
     ) -> InterpResult<'tcx> {
-        assert!(communicate_allowed, "isolation should have prevented even opening a file");
+        assert!(
+            communicate_allowed || self.file.is_virtual(),
+            "isolation should have prevented even opening a file"
+        );
         if !self.readable {
             return finish.call(ecx, Err(LibcError("EBADF")));
         }
@@ -111,7 +125,10 @@ impl UnixFileDescription for FileHandle {
         ecx: &mut MiriInterpCx<'tcx>,
         finish: DynMachineCallback<'tcx, Result<usize, IoError>>,
     ) -> InterpResult<'tcx> {
-        assert!(communicate_allowed, "isolation should have prevented even opening a file");
+        assert!(
+            communicate_allowed || self.file.is_virtual(),
+            "isolation should have prevented even opening a file"
+        );
         if !self.writable {
             return finish.call(ecx, Err(LibcError("EBADF")));
         }
@@ -131,6 +148,9 @@ impl UnixFileDescription for FileHandle {
             res
         };
         let result = f();
+        if result.as_ref().is_ok_and(|&written| written > 0) {
+            ecx.report_fs_event(FsEvent::Modify(&self.path))?;
+        }
         finish.call(ecx, result.map_err(IoError::HostError))
     }
 
@@ -139,15 +159,21 @@ impl UnixFileDescription for FileHandle {
         communicate_allowed: bool,
         op: FlockOp,
     ) -> InterpResult<'tcx, io::Result<()>> {
+        let file = match &self.file {
+            Backing::Host(file) => file,
+            // Only this program can see the in-memory file system, and it is a single process,
+            // so there is nobody the lock could keep out.
+            Backing::Virtual(_) => return interp_ok(Ok(())),
+        };
         assert!(communicate_allowed, "isolation should have prevented even opening a file");
 
         use FlockOp::*;
         // We must not block the interpreter loop, so we always `try_lock`.
         let (res, nonblocking) = match op {
-            SharedLock { nonblocking } => (self.file.try_lock_shared(), nonblocking),
-            ExclusiveLock { nonblocking } => (self.file.try_lock(), nonblocking),
+            SharedLock { nonblocking } => (file.try_lock_shared(), nonblocking),
+            ExclusiveLock { nonblocking } => (file.try_lock(), nonblocking),
             Unlock => {
-                return interp_ok(self.file.unlock());
+                return interp_ok(file.unlock());
             }
         };
 
@@ -185,7 +211,10 @@ pub struct DirTable {
 
 impl DirTable {
     #[expect(clippy::arithmetic_side_effects)]
-    fn insert_new(&mut self, read_dir: fs::ReadDir) -> u64 {
+    fn insert_new(
+        &mut self,
+        read_dir: Either<fs::ReadDir, std::vec::IntoIter<VfsDirEntry>>,
+    ) -> u64 {
         let id = self.next_id;
         self.next_id += 1;
         self.streams.try_insert(id, OpenDir::new(read_dir)).unwrap();
@@ -214,7 +243,7 @@ impl VisitProvenance for DirTable {
 }
 
 fn maybe_sync_file(
-    file: &File,
+    file: &Backing<File>,
     writable: bool,
     operation: fn(&File) -> std::io::Result<()>,
 ) -> std::io::Result<i32> {
@@ -224,7 +253,7 @@ fn maybe_sync_file(
         // GENERIC_WRITE right)
         Ok(0i32)
     } else {
-        let result = operation(file);
+        let result = file.sync(operation);
         result.map(|_| 0i32)
     }
 }
@@ -258,6 +287,75 @@ trait EvalContextExtPrivate<'tcx>: crate::MiriInterpCxExt<'tcx> {
         interp_ok(SystemTime::UNIX_EPOCH.checked_add(duration).map(TimeUpdate::Set))
     }
 
+    /// Resolves the `path` argument of one of the `*at` functions. Relative paths are interpreted
+    /// relative to the directory `dirfd` refers to, or to the current working directory if `dirfd`
+    /// is `AT_FDCWD`. Absolute paths ignore `dirfd`.
+    fn resolve_path_at(
+        &self,
+        dirfd: i32,
+        path: &Path,
+    ) -> InterpResult<'tcx, Result<PathBuf, IoError>> {
+        let this = self.eval_context_ref();
+
+        if path.as_os_str().is_empty() {
+            return interp_ok(Err(LibcError("ENOENT")));
+        }
+        if path.is_absolute() || dirfd == this.eval_libc_i32("AT_FDCWD") {
+            return interp_ok(Ok(path.to_owned()));
+        }
+        let Some(fd) = this.machine.fds.get(dirfd) else {
+            return interp_ok(Err(LibcError("EBADF")));
+        };
+        // Only directories opened with `open` remember where they are.
+        let Some(dir) = fd.downcast::<FileHandle>() else {
+            return interp_ok(Err(LibcError("ENOTDIR")));
+        };
+        let dir_metadata = match dir.file.metadata() {
+            Ok(metadata) if metadata.is_dir() => metadata,
+            _ => return interp_ok(Err(LibcError("ENOTDIR"))),
+        };
+        // We only know the path the directory was opened with. If the directory was moved or
+        // removed since, that path names something else (or nothing), and we cannot find out
+        // where the directory is now. Hosts that cannot compare files get the benefit of the
+        // doubt.
+        let path_metadata = match &this.machine.vfs {
+            Some(vfs) => vfs.metadata(&dir.path).map(HandleMetadata::Virtual),
+            None => fs::metadata(&dir.path).map(HandleMetadata::Host),
+        };
+        if !path_metadata.is_ok_and(|metadata| dir_metadata.is_same_file(&metadata) != Some(false))
+        {
+            throw_unsup_format!(
+                "resolving a path relative to a directory that was moved or removed after it was opened is not supported"
+            );
+        }
+        interp_ok(Ok(dir.path.join(path)))
+    }
+
+    /// Queries the metadata for `fstatat` and `statx`, which share their handling of `dirfd`,
+    /// `AT_SYMLINK_NOFOLLOW` and `AT_EMPTY_PATH`.
+    fn metadata_at(
+        &mut self,
+        dirfd: i32,
+        path: &Path,
+        follow_symlink: bool,
+        empty_path: bool,
+    ) -> InterpResult<'tcx, Result<FileMetadata, IoError>> {
+        let this = self.eval_context_mut();
+
+        // If the path is empty and `AT_EMPTY_PATH` is set, we query the file represented by
+        // `dirfd` itself, whether it's a directory or otherwise.
+        if path.as_os_str().is_empty() && empty_path {
+            if dirfd == this.eval_libc_i32("AT_FDCWD") {
+                return FileMetadata::from_path(this, Path::new("."), follow_symlink);
+            }
+            return FileMetadata::from_fd_num(this, dirfd);
+        }
+        match this.resolve_path_at(dirfd, path)? {
+            Ok(path) => FileMetadata::from_path(this, &path, follow_symlink),
+            Err(err) => interp_ok(Err(err)),
+        }
+    }
+
     fn write_stat_buf(
         &mut self,
         metadata: FileMetadata,
@@ -354,13 +452,10 @@ trait EvalContextExtPrivate<'tcx>: crate::MiriInterpCxExt<'tcx> {
         }
     }
 
-    fn dir_entry_fields(
-        &self,
-        entry: Either<fs::DirEntry, &'static str>,
-    ) -> InterpResult<'tcx, DirEntry> {
+    fn dir_entry_fields(&self, entry: OpenDirEntry) -> InterpResult<'tcx, DirEntry> {
         let this = self.eval_context_ref();
         interp_ok(match entry {
-            Either::Left(dir_entry) => {
+            OpenDirEntry::Host(dir_entry) => {
                 DirEntry {
                     name: dir_entry.file_name(),
                     d_type: this.file_type_to_d_type(dir_entry.file_type())?,
@@ -372,7 +467,15 @@ trait EvalContextExtPrivate<'tcx>: crate::MiriInterpCxExt<'tcx> {
                     ino: 0u64,
                 }
             }
-            Either::Right(special) =>
+            OpenDirEntry::Virtual(entry) => {
+                let d_type = if entry.is_dir { "DT_DIR" } else { "DT_REG" };
+                DirEntry {
+                    name: entry.name,
+                    d_type: this.eval_libc(d_type).to_u8()?.into(),
+                    ino: entry.ino,
+                }
+            }
+            OpenDirEntry::Special(special) =>
                 DirEntry {
                     name: special.into(),
                     d_type: this.eval_libc("DT_DIR").to_u8()?.into(),
@@ -391,12 +494,60 @@ trait EvalContextExtPrivate<'tcx>: crate::MiriInterpCxExt<'tcx> {
     fn host_permissions_from_mode(&self, _mode: u32) -> InterpResult<'tcx, fs::Permissions> {
         throw_unsup_format!("setting file permissions is only supported on Unix hosts")
     }
+
+    /// Sets the permission bits of the file behind `fd_num`, like `fchmod`.
+    fn set_fd_mode(&mut self, fd_num: i32, mode: u32) -> InterpResult<'tcx, Result<(), IoError>> {
+        let this = self.eval_context_mut();
+
+        let Some(fd) = this.machine.fds.get(fd_num) else {
+            return interp_ok(Err(LibcError("EBADF")));
+        };
+        let Some(file) = fd.downcast::<FileHandle>() else {
+            // The docs don't talk about what happens for non-regular files...
+            throw_unsup_format!("`fchmod` is only supported on regular files")
+        };
+        if !file.writable && !file.readable {
+            // Apparently, `fchmod` on a read-only file is fine. But let's not allow it on a
+            // path-only file.
+            return interp_ok(Err(LibcError("EBADF")));
+        }
+        let result = match &file.file {
+            Backing::Host(file) => {
+                assert!(
+                    this.machine.communicate(),
+                    "isolation should have prevented even opening a file"
+                );
+                let permissions = this.host_permissions_from_mode(mode)?;
+                file.set_permissions(permissions)
+            }
+            Backing::Virtual(file) => file.set_permissions(mode),
+        };
+        if let Err(err) = result {
+            return interp_ok(Err(err.into()));
+        }
+        this.report_fs_event(FsEvent::Attrib(&file.path))?;
+
+        interp_ok(Ok(()))
+    }
 }
 
 impl<'tcx> EvalContextExt<'tcx> for crate::MiriInterpCx<'tcx> {}
 pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
+    /// The absolute form of `path`, as recorded in a [`FileHandle`]. Relative paths in the
+    /// in-memory file system are relative to its root.
+    fn absolute_path(&self, path: &Path) -> PathBuf {
+        let this = self.eval_context_ref();
+        if this.machine.vfs.is_some() {
+            Path::new("/").join(path)
+        } else {
+            path::absolute(path).unwrap_or_else(|_| path.to_owned())
+        }
+    }
+
+    /// Implements `open` and `openat`. For `open`, `dirfd` is `AT_FDCWD`.
     fn open(
         &mut self,
+        dirfd: i32,
         path_raw: &OpTy<'tcx>,
         flag: &OpTy<'tcx>,
         varargs: &[OpTy<'tcx>],
@@ -407,6 +558,10 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         let flag = this.read_scalar(flag)?.to_i32()?;
 
         let path = this.read_path_from_c_str(path_raw)?;
+        let path = match this.resolve_path_at(dirfd, &path)? {
+            Ok(path) => path,
+            Err(err) => return this.set_errno_and_return_neg1_i32(err),
+        };
         // Files in `/proc` won't work properly.
         if matches!(this.tcx.sess.target.os, Os::Linux | Os::Android | Os::Illumos | Os::Solaris)
             && path::absolute(&path).is_ok_and(|path| path.starts_with("/proc"))
@@ -418,6 +573,31 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         let mut flag = flag;
 
         let mut options = OpenOptions::new();
+        let mut vfs_options = VfsOpenOptions::default();
+        // The flags that are passed on to the host's `open` as they are.
+        #[cfg(unix)]
+        let mut custom_flags = 0;
+
+        let o_cloexec = this.eval_libc_i32("O_CLOEXEC");
+        let o_directory = this.eval_libc_i32("O_DIRECTORY");
+        let o_nofollow = this.eval_libc_i32("O_NOFOLLOW");
+
+        // `O_PATH` opens the file only as a location in the file system, e.g. to serve as `dirfd`.
+        // All other flags are ignored, except for these three.
+        let mut path_only = false;
+        if matches!(this.tcx.sess.target.os, Os::Linux | Os::Android | Os::FreeBsd) {
+            let o_path = this.eval_libc_i32("O_PATH");
+            if flag & o_path == o_path {
+                path_only = true;
+                flag &= o_cloexec | o_directory | o_nofollow;
+                // The file doesn't need to be readable, so don't open it for reading if we can
+                // avoid it.
+                #[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd"))]
+                {
+                    custom_flags |= libc::O_PATH;
+                }
+            }
+        }
 
         let o_rdonly = this.eval_libc_i32("O_RDONLY");
         let o_wronly = this.eval_libc_i32("O_WRONLY");
@@ -441,8 +621,10 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         } else if access_mode == o_wronly {
             readable = false;
             options.write(true);
+            vfs_options.write = true;
         } else if access_mode == o_rdwr {
             options.read(true).write(true);
+            vfs_options.write = true;
         } else {
             throw_unsup_format!("unsupported access mode {:#x}", access_mode);
         }
@@ -451,11 +633,13 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         if flag & o_append == o_append {
             flag &= !o_append;
             options.append(true);
+            vfs_options.append = true;
         }
         let o_trunc = this.eval_libc_i32("O_TRUNC");
         if flag & o_trunc == o_trunc {
             flag &= !o_trunc;
             options.truncate(true);
+            vfs_options.truncate = true;
         }
         let o_creat = this.eval_libc_i32("O_CREAT");
         if flag & o_creat == o_creat {
@@ -465,6 +649,7 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
             // (see https://github.com/rust-lang/rust/issues/71915).
             let [mode] = check_min_vararg_count("open(pathname, O_CREAT, ...)", varargs)?;
             let mode = this.read_scalar(mode)?.to_u32()?;
+            vfs_options.mode = mode;
 
             #[cfg(unix)]
             {
@@ -475,7 +660,7 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
             #[cfg(not(unix))]
             {
                 // Only support default mode for non-UNIX (i.e. Windows) host
-                if mode != 0o666 {
+                if mode != 0o666 && this.machine.vfs.is_none() {
                     throw_unsup_format!(
                         "non-default mode 0o{:o} is not supported on non-Unix hosts",
                         mode
@@ -487,11 +672,12 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
             if flag & o_excl == o_excl {
                 flag &= !o_excl;
                 options.create_new(true);
+                vfs_options.create_new = true;
             } else {
                 options.create(true);
+                vfs_options.create = true;
             }
         }
-        let o_cloexec = this.eval_libc_i32("O_CLOEXEC");
         if flag & o_cloexec == o_cloexec {
             flag &= !o_cloexec;
             // We do not need to do anything for this flag because `std` already sets it.
@@ -505,13 +691,11 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
             }
         }
 
-        let o_nofollow = this.eval_libc_i32("O_NOFOLLOW");
         if flag & o_nofollow == o_nofollow {
             flag &= !o_nofollow;
             #[cfg(unix)]
             {
-                use std::os::unix::fs::OpenOptionsExt;
-                options.custom_flags(libc::O_NOFOLLOW);
+                custom_flags |= libc::O_NOFOLLOW;
             }
             // Strictly speaking, this emulation is not equivalent to the O_NOFOLLOW flag behavior:
             // the path could change between us checking it here and the later call to `open`.
@@ -520,28 +704,66 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
             {
                 // O_NOFOLLOW only fails when the trailing component is a symlink;
                 // the entire rest of the path can still contain symlinks.
-                if path.is_symlink() {
+                if this.machine.vfs.is_none() && path.is_symlink() {
                     return this.set_errno_and_return_neg1_i32(LibcError("ELOOP"));
                 }
             }
         }
 
+        // `O_DIRECTORY` is checked after opening the file. Linux rejects combining it with
+        // `O_CREAT`, so we do not have to worry about creating a regular file first.
+        let directory = flag & o_directory == o_directory;
+        if directory && (vfs_options.create || vfs_options.create_new) {
+            return this.set_errno_and_return_neg1_i32(LibcError("EINVAL"));
+        }
+        flag &= !o_directory;
+
         // If `flag` has any bits left set, those are not supported.
         if flag != 0 {
             throw_unsup_format!("unsupported flags {:#x}", flag);
         }
 
-        // Reject if isolation is enabled.
-        if let IsolatedOp::Reject(reject_with) = this.machine.isolated_op {
-            this.reject_in_isolation("`open`", reject_with)?;
-            return this.set_errno_and_return_neg1_i32(ErrorKind::PermissionDenied);
-        }
+        // Whether `open` creates the file, which inotify reports.
+        let creates = vfs_options.create || vfs_options.create_new;
+        let created;
+        let file = if let Some(vfs) = &this.machine.vfs {
+            created = creates && vfs.metadata(&path).is_err();
+            vfs.open(&path, &vfs_options).map(Backing::Virtual)
+        } else {
+            // Reject if isolation is enabled.
+            if let IsolatedOp::Reject(reject_with) = this.machine.isolated_op {
+                this.reject_in_isolation("`open`", reject_with)?;
+                return this.set_errno_and_return_neg1_i32(ErrorKind::PermissionDenied);
+            }
 
-        let fd = options
-            .open(path)
-            .map(|file| this.machine.fds.insert_new(FileHandle { file, writable, readable }));
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::OpenOptionsExt;
+                options.custom_flags(custom_flags);
+            }
+            created = creates && fs::symlink_metadata(&path).is_err();
+            options.open(&path).map(Backing::Host)
+        };
+        let file = match file {
+            Ok(file) => file,
+            Err(err) => return this.set_errno_and_return_neg1_i32(err),
+        };
+        if directory && !file.metadata().is_ok_and(|metadata| metadata.is_dir()) {
+            return this.set_errno_and_return_neg1_i32(LibcError("ENOTDIR"));
+        }
+        if path_only {
+            readable = false;
+            writable = false;
+        }
 
-        interp_ok(Scalar::from_i32(this.try_unwrap_io_result(fd)?))
+        let path = this.absolute_path(&path);
+        if created {
+            this.report_fs_event(FsEvent::Create { path: &path, is_dir: false })?;
+        } else if vfs_options.truncate && writable {
+            this.report_fs_event(FsEvent::Modify(&path))?;
+        }
+        let fd = this.machine.fds.insert_new(FileHandle { file, path, writable, readable });
+        interp_ok(Scalar::from_i32(fd))
     }
 
     fn lseek(
@@ -583,19 +805,45 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         interp_ok(())
     }
 
-    fn unlink(&mut self, path_op: &OpTy<'tcx>) -> InterpResult<'tcx, Scalar> {
+    /// Implements `unlink` and `unlinkat`. For `unlink`, `dirfd` is `AT_FDCWD` and `flags` is 0.
+    fn unlink(
+        &mut self,
+        dirfd: i32,
+        path_op: &OpTy<'tcx>,
+        flags: i32,
+    ) -> InterpResult<'tcx, Scalar> {
         let this = self.eval_context_mut();
 
+        if flags == this.eval_libc_i32("AT_REMOVEDIR") {
+            return this.rmdir(dirfd, path_op);
+        }
+        if flags != 0 {
+            throw_unsup_format!("unsupported unlinkat flags {:#x}", flags);
+        }
+
         let path = this.read_path_from_c_str(this.read_pointer(path_op)?)?;
+        let path = match this.resolve_path_at(dirfd, &path)? {
+            Ok(path) => path,
+            Err(err) => return this.set_errno_and_return_neg1_i32(err),
+        };
 
-        // Reject if isolation is enabled.
-        if let IsolatedOp::Reject(reject_with) = this.machine.isolated_op {
-            this.reject_in_isolation("`unlink`", reject_with)?;
-            return this.set_errno_and_return_neg1_i32(ErrorKind::PermissionDenied);
-        }
+        let result = if let Some(vfs) = &this.machine.vfs {
+            vfs.remove_file(&path)
+        } else {
+            // Reject if isolation is enabled.
+            if let IsolatedOp::Reject(reject_with) = this.machine.isolated_op {
+                this.reject_in_isolation("`unlink`", reject_with)?;
+                return this.set_errno_and_return_neg1_i32(ErrorKind::PermissionDenied);
+            }
 
-        let result = fs::remove_file(path).map(|_| 0);
-        interp_ok(Scalar::from_i32(this.try_unwrap_io_result(result)?))
+            fs::remove_file(&path)
+        };
+        if let Err(err) = result {
+            return this.set_errno_and_return_neg1_i32(err);
+        }
+        let path = this.absolute_path(&path);
+        this.report_fs_event(FsEvent::Delete { path: &path, is_dir: false })?;
+        interp_ok(Scalar::from_i32(0))
     }
 
     fn symlink(
@@ -618,14 +866,23 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         let target = this.read_path_from_c_str(this.read_pointer(target_op)?)?;
         let linkpath = this.read_path_from_c_str(this.read_pointer(linkpath_op)?)?;
 
+        if this.machine.vfs.is_some() {
+            // This is what Linux reports for file systems without symlinks.
+            return this.set_errno_and_return_neg1_i32(LibcError("EPERM"));
+        }
+
         // Reject if isolation is enabled.
         if let IsolatedOp::Reject(reject_with) = this.machine.isolated_op {
             this.reject_in_isolation("`symlink`", reject_with)?;
             return this.set_errno_and_return_neg1_i32(ErrorKind::PermissionDenied);
         }
 
-        let result = create_link(&target, &linkpath).map(|_| 0);
-        interp_ok(Scalar::from_i32(this.try_unwrap_io_result(result)?))
+        if let Err(err) = create_link(&target, &linkpath) {
+            return this.set_errno_and_return_neg1_i32(err);
+        }
+        let linkpath = this.absolute_path(&linkpath);
+        this.report_fs_event(FsEvent::Create { path: &linkpath, is_dir: false })?;
+        interp_ok(Scalar::from_i32(0))
     }
 
     fn linkat(
@@ -645,8 +902,10 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         let oldpath_ptr = this.read_pointer(oldpath_op)?;
         let newpath_ptr = this.read_pointer(newpath_op)?;
 
-        // Relevant libc constants
-        let at_fdcwd = this.eval_libc_i32("AT_FDCWD");
+        if this.machine.vfs.is_some() {
+            // The in-memory file system has no hard links either.
+            return this.set_errno_and_return_neg1_i32(LibcError("EPERM"));
+        }
 
         // Reject if isolation is enabled.
         if let IsolatedOp::Reject(reject_with) = this.machine.isolated_op {
@@ -660,25 +919,31 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         }
 
         // Resolve oldpath
-        if oldfd != at_fdcwd {
-            throw_unsup_format!("linkat with `olddirfd` not equal to `AT_FDCWD` is not supported");
-        }
         if oldpath_ptr == Pointer::null() {
             return this.set_errno_and_return_neg1_i32(LibcError("EFAULT"));
         }
-        let oldpath = this.read_path_from_c_str(oldpath_ptr)?.into_owned();
+        let oldpath = this.read_path_from_c_str(oldpath_ptr)?;
+        let oldpath = match this.resolve_path_at(oldfd, &oldpath)? {
+            Ok(path) => path,
+            Err(err) => return this.set_errno_and_return_neg1_i32(err),
+        };
 
         // Resolve newpath
-        if newfd != at_fdcwd {
-            throw_unsup_format!("linkat with `newdirfd` not equal to `AT_FDCWD` is not supported");
-        }
         if newpath_ptr == Pointer::null() {
             return this.set_errno_and_return_neg1_i32(LibcError("EFAULT"));
         }
-        let newpath = this.read_path_from_c_str(newpath_ptr)?.into_owned();
+        let newpath = this.read_path_from_c_str(newpath_ptr)?;
+        let newpath = match this.resolve_path_at(newfd, &newpath)? {
+            Ok(path) => path,
+            Err(err) => return this.set_errno_and_return_neg1_i32(err),
+        };
 
-        let result = fs::hard_link(&oldpath, &newpath).map(|()| 0);
-        interp_ok(Scalar::from_i32(this.try_unwrap_io_result(result)?))
+        if let Err(err) = fs::hard_link(&oldpath, &newpath) {
+            return this.set_errno_and_return_neg1_i32(err);
+        }
+        let newpath = this.absolute_path(&newpath);
+        this.report_fs_event(FsEvent::Create { path: &newpath, is_dir: false })?;
+        interp_ok(Scalar::from_i32(0))
     }
 
     fn stat(&mut self, path_op: &OpTy<'tcx>, buf_op: &OpTy<'tcx>) -> InterpResult<'tcx, Scalar> {
@@ -694,8 +959,10 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         let path_scalar = this.read_pointer(path_op)?;
         let path = this.read_path_from_c_str(path_scalar)?.into_owned();
 
-        // Reject if isolation is enabled.
-        if let IsolatedOp::Reject(reject_with) = this.machine.isolated_op {
+        // Reject if isolation is enabled, unless the in-memory file system is used.
+        if let IsolatedOp::Reject(reject_with) = this.machine.isolated_op
+            && this.machine.vfs.is_none()
+        {
             this.reject_in_isolation("`stat`", reject_with)?;
             return this.set_errno_and_return_neg1_i32(LibcError("EACCES"));
         }
@@ -723,8 +990,10 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         let path_scalar = this.read_pointer(path_op)?;
         let path = this.read_path_from_c_str(path_scalar)?.into_owned();
 
-        // Reject if isolation is enabled.
-        if let IsolatedOp::Reject(reject_with) = this.machine.isolated_op {
+        // Reject if isolation is enabled, unless the in-memory file system is used.
+        if let IsolatedOp::Reject(reject_with) = this.machine.isolated_op
+            && this.machine.vfs.is_none()
+        {
             this.reject_in_isolation("`lstat`", reject_with)?;
             return this.set_errno_and_return_neg1_i32(LibcError("EACCES"));
         }
@@ -749,8 +1018,11 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
 
         let fd = this.read_scalar(fd_op)?.to_i32()?;
 
-        // Reject if isolation is enabled.
-        if let IsolatedOp::Reject(reject_with) = this.machine.isolated_op {
+        // Reject if isolation is enabled, unless the in-memory file system is used. Then all
+        // files are in memory, and the other kinds of FDs do not expose the host in `fstat`.
+        if let IsolatedOp::Reject(reject_with) = this.machine.isolated_op
+            && this.machine.vfs.is_none()
+        {
             this.reject_in_isolation("`fstat`", reject_with)?;
             // Set error code as "EBADF" (bad fd)
             return this.set_errno_and_return_neg1_i32(LibcError("EBADF"));
@@ -763,6 +1035,53 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         interp_ok(Scalar::from_i32(this.write_stat_buf(metadata, buf_op)?))
     }
 
+    fn fstatat(
+        &mut self,
+        dirfd_op: &OpTy<'tcx>,    // Should be an `int`
+        pathname_op: &OpTy<'tcx>, // Should be a `const char *`
+        buf_op: &OpTy<'tcx>,      // Should be a `struct stat *`
+        flags_op: &OpTy<'tcx>,    // Should be an `int`
+    ) -> InterpResult<'tcx, Scalar> {
+        let this = self.eval_context_mut();
+
+        let dirfd = this.read_scalar(dirfd_op)?.to_i32()?;
+        let pathname_ptr = this.read_pointer(pathname_op)?;
+        let flags = this.read_scalar(flags_op)?.to_i32()?;
+
+        let at_symlink_nofollow = this.eval_libc_i32("AT_SYMLINK_NOFOLLOW");
+        let at_empty_path =
+            if matches!(this.tcx.sess.target.os, Os::Linux | Os::Android | Os::FreeBsd) {
+                this.eval_libc_i32("AT_EMPTY_PATH")
+            } else {
+                0
+            };
+        if flags & !(at_symlink_nofollow | at_empty_path) != 0 {
+            throw_unsup_format!("unsupported fstatat flags {:#x}", flags);
+        }
+
+        if this.ptr_is_null(pathname_ptr)? {
+            return this.set_errno_and_return_neg1_i32(LibcError("EFAULT"));
+        }
+        let path = this.read_path_from_c_str(pathname_ptr)?.into_owned();
+
+        // Reject if isolation is enabled, unless the in-memory file system is used.
+        if let IsolatedOp::Reject(reject_with) = this.machine.isolated_op
+            && this.machine.vfs.is_none()
+        {
+            this.reject_in_isolation("`fstatat`", reject_with)?;
+            return this.set_errno_and_return_neg1_i32(LibcError("EACCES"));
+        }
+
+        let follow_symlink = flags & at_symlink_nofollow == 0;
+        let empty_path = flags & at_empty_path != 0;
+        let metadata = match this.metadata_at(dirfd, &path, follow_symlink, empty_path)? {
+            Ok(metadata) => metadata,
+            Err(err) => return this.set_errno_and_return_neg1_i32(err),
+        };
+
+        interp_ok(Scalar::from_i32(this.write_stat_buf(metadata, buf_op)?))
+    }
+
     fn linux_statx(
         &mut self,
         dirfd_op: &OpTy<'tcx>,    // Should be an `int`
@@ -792,37 +1111,19 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         // See <https://github.com/rust-lang/rust/pull/79196> for a discussion of argument sizes.
         let at_empty_path = this.eval_libc_i32("AT_EMPTY_PATH");
         let empty_path_flag = flags & at_empty_path == at_empty_path;
-        // We only support:
-        // * interpreting `path` as an absolute directory,
-        // * interpreting `path` as a path relative to `dirfd` when the latter is `AT_FDCWD`, or
-        // * interpreting `dirfd` as any file descriptor when `path` is empty and AT_EMPTY_PATH is
-        // set.
-        // Other behaviors cannot be tested from `libstd` and thus are not implemented. If you
-        // found this error, please open an issue reporting it.
-        if !(path.is_absolute()
-            || dirfd == this.eval_libc_i32("AT_FDCWD")
-            || (path.as_os_str().is_empty() && empty_path_flag))
-        {
-            throw_unsup_format!(
-                "using statx is only supported with absolute paths, relative paths with the file \
-                descriptor `AT_FDCWD`, and empty paths with the `AT_EMPTY_PATH` flag set and any \
-                file descriptor"
-            )
-        }
 
-        // Reject if isolation is enabled.
-        if let IsolatedOp::Reject(reject_with) = this.machine.isolated_op {
+        // Reject if isolation is enabled, unless the in-memory file system is used.
+        if let IsolatedOp::Reject(reject_with) = this.machine.isolated_op
+            && this.machine.vfs.is_none()
+        {
             this.reject_in_isolation("`statx`", reject_with)?;
-            let ecode = if path.is_absolute() || dirfd == this.eval_libc_i32("AT_FDCWD") {
-                // since `path` is provided, either absolute or
-                // relative to CWD, `EACCES` is the most relevant.
-                LibcError("EACCES")
-            } else {
-                // `dirfd` is set to target file, and `path` is empty
-                // (or we would have hit the `throw_unsup_format`
-                // above). `EACCES` would violate the spec.
-                assert!(empty_path_flag);
+            let ecode = if path.as_os_str().is_empty() && empty_path_flag {
+                // `dirfd` is set to target file, and `path` is empty.
+                // `EACCES` would violate the spec.
                 LibcError("EBADF")
+            } else {
+                // since `path` is provided, `EACCES` is the most relevant.
+                LibcError("EACCES")
             };
             return this.set_errno_and_return_neg1_i32(ecode);
         }
@@ -831,13 +1132,7 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         // symbolic links.
         let follow_symlink = flags & this.eval_libc_i32("AT_SYMLINK_NOFOLLOW") == 0;
 
-        // If the path is empty, and the AT_EMPTY_PATH flag is set, we query the open file
-        // represented by dirfd, whether it's a directory or otherwise.
-        let metadata = if path.as_os_str().is_empty() && empty_path_flag {
-            FileMetadata::from_fd_num(this, dirfd)?
-        } else {
-            FileMetadata::from_path(this, &path, follow_symlink)?
-        };
+        let metadata = this.metadata_at(dirfd, &path, follow_symlink, empty_path_flag)?;
         let metadata = match metadata {
             Ok(metadata) => metadata,
             Err(err) => return this.set_errno_and_return_neg1_i32(err),
@@ -951,16 +1246,43 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         interp_ok(Scalar::from_i32(0))
     }
 
-    fn chmod(&mut self, path_op: &OpTy<'tcx>, mode_op: &OpTy<'tcx>) -> InterpResult<'tcx, Scalar> {
+    /// Implements `chmod` and `fchmodat`. For `chmod`, `dirfd` is `AT_FDCWD` and `flags` is 0.
+    fn chmod(
+        &mut self,
+        dirfd: i32,
+        path_op: &OpTy<'tcx>,
+        mode_op: &OpTy<'tcx>,
+        flags: i32,
+    ) -> InterpResult<'tcx, Scalar> {
         let this = self.eval_context_mut();
 
         let path_ptr = this.read_pointer(path_op)?;
         let mode = this.read_scalar(mode_op)?.to_uint(this.libc_ty_layout("mode_t").size)?;
 
+        let at_symlink_nofollow = this.eval_libc_i32("AT_SYMLINK_NOFOLLOW");
+        if flags & !at_symlink_nofollow != 0 {
+            throw_unsup_format!("unsupported fchmodat flags {:#x}", flags);
+        }
+        let follow_symlink = flags & at_symlink_nofollow == 0;
+
         if this.ptr_is_null(path_ptr)? {
             return this.set_errno_and_return_neg1_i32(LibcError("EFAULT"));
         }
         let path = this.read_path_from_c_str(path_ptr)?;
+        let path = match this.resolve_path_at(dirfd, &path)? {
+            Ok(path) => path,
+            Err(err) => return this.set_errno_and_return_neg1_i32(err),
+        };
+
+        // There are no symlinks in the in-memory file system.
+        if let Some(vfs) = &this.machine.vfs {
+            if let Err(err) = vfs.set_permissions(&path, mode.try_into().unwrap()) {
+                return this.set_errno_and_return_neg1_i32(err);
+            }
+            let path = this.absolute_path(&path);
+            this.report_fs_event(FsEvent::Attrib(&path))?;
+            return interp_ok(Scalar::from_i32(0));
+        }
 
         // Reject if isolation is enabled.
         if let IsolatedOp::Reject(reject_with) = this.machine.isolated_op {
@@ -968,10 +1290,23 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
             return this.set_errno_and_return_neg1_i32(LibcError("EACCES"));
         }
 
+        // The permissions of a symlink cannot be changed on Linux. The other targets change the
+        // permissions of the link itself, like `lchmod`, which `std` cannot do on the host.
+        if !follow_symlink && fs::symlink_metadata(&path).is_ok_and(|meta| meta.is_symlink()) {
+            if matches!(&this.tcx.sess.target.os, Os::Linux | Os::Android) {
+                return this.set_errno_and_return_neg1_i32(LibcError("EOPNOTSUPP"));
+            }
+            throw_unsup_format!(
+                "`fchmodat` with `AT_SYMLINK_NOFOLLOW` on a symbolic link is not supported"
+            );
+        }
+
         let permissions = this.host_permissions_from_mode(mode.try_into().unwrap())?;
-        if let Err(err) = fs::set_permissions(path, permissions) {
+        if let Err(err) = fs::set_permissions(&path, permissions) {
             return this.set_errno_and_return_neg1_i32(err);
         }
+        let path = this.absolute_path(&path);
+        this.report_fs_event(FsEvent::Attrib(&path))?;
 
         interp_ok(Scalar::from_i32(0))
     }
@@ -982,35 +1317,45 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         let fd_num = this.read_scalar(fd_op)?.to_i32()?;
         let mode = this.read_scalar(mode_op)?.to_uint(this.libc_ty_layout("mode_t").size)?;
 
-        let Some(fd) = this.machine.fds.get(fd_num) else {
-            return this.set_errno_and_return_neg1_i32(LibcError("EBADF"));
-        };
-        let Some(file) = fd.downcast::<FileHandle>() else {
-            // The docs don't talk about what happens for non-regular files...
-            throw_unsup_format!("`fchmod` is only supported on regular files")
-        };
-        if !file.writable && !file.readable {
-            // Apparently, `fchmod` on a read-only file is fine. But let's not allow it on a
-            // path-only file.
-            return this.set_errno_and_return_neg1_i32(LibcError("EBADF"));
-        }
-        assert!(this.machine.communicate(), "isolation should have prevented even opening a file");
-
-        let permissions = this.host_permissions_from_mode(mode.try_into().unwrap())?;
-        if let Err(err) = file.file.set_permissions(permissions) {
+        if let Err(err) = this.set_fd_mode(fd_num, mode.try_into().unwrap())? {
             return this.set_errno_and_return_neg1_i32(err);
         }
 
         interp_ok(Scalar::from_i32(0))
     }
 
+    /// Gives the file behind `to` the permission bits of the file behind `from`, as `fcopyfile`
+    /// does with `COPYFILE_STAT`.
+    fn copy_fd_mode(&mut self, from: i32, to: i32) -> InterpResult<'tcx, Result<(), IoError>> {
+        let this = self.eval_context_mut();
+
+        let metadata = match FileMetadata::from_fd_num(this, from)? {
+            Ok(metadata) => metadata,
+            Err(err) => return interp_ok(Err(err)),
+        };
+        this.set_fd_mode(to, metadata.mode & 0o7777)
+    }
+
+    /// Implements `rename`, `renameat` and `renameat2`. For `rename`, both directories are
+    /// `AT_FDCWD`, and only `renameat2` passes any `flags`.
     fn rename(
         &mut self,
+        olddirfd: i32,
         oldpath_op: &OpTy<'tcx>,
+        newdirfd: i32,
         newpath_op: &OpTy<'tcx>,
+        flags: u32,
     ) -> InterpResult<'tcx, Scalar> {
         let this = self.eval_context_mut();
 
+        let no_replace = match flags {
+            0 => false,
+            _ if flags == this.eval_libc_u32("RENAME_NOREPLACE") => true,
+            _ if flags == this.eval_libc_u32("RENAME_EXCHANGE") =>
+                throw_unsup_format!("`renameat2` with `RENAME_EXCHANGE` is not supported"),
+            _ => throw_unsup_format!("unsupported renameat2 flags {:#x}", flags),
+        };
+
         let oldpath_ptr = this.read_pointer(oldpath_op)?;
         let newpath_ptr = this.read_pointer(newpath_op)?;
 
@@ -1019,23 +1364,57 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         }
 
         let oldpath = this.read_path_from_c_str(oldpath_ptr)?;
+        let oldpath = match this.resolve_path_at(olddirfd, &oldpath)? {
+            Ok(path) => path,
+            Err(err) => return this.set_errno_and_return_neg1_i32(err),
+        };
         let newpath = this.read_path_from_c_str(newpath_ptr)?;
+        let newpath = match this.resolve_path_at(newdirfd, &newpath)? {
+            Ok(path) => path,
+            Err(err) => return this.set_errno_and_return_neg1_i32(err),
+        };
 
-        // Reject if isolation is enabled.
-        if let IsolatedOp::Reject(reject_with) = this.machine.isolated_op {
-            this.reject_in_isolation("`rename`", reject_with)?;
-            return this.set_errno_and_return_neg1_i32(ErrorKind::PermissionDenied);
-        }
+        let is_dir = if let Some(vfs) = &this.machine.vfs {
+            if no_replace && vfs.metadata(&newpath).is_ok() {
+                return this.set_errno_and_return_neg1_i32(LibcError("EEXIST"));
+            }
+            if let Err(err) = vfs.rename(&oldpath, &newpath) {
+                return this.set_errno_and_return_neg1_i32(err);
+            }
+            vfs.metadata(&newpath).is_ok_and(|metadata| metadata.is_dir)
+        } else {
+            // Reject if isolation is enabled.
+            if let IsolatedOp::Reject(reject_with) = this.machine.isolated_op {
+                this.reject_in_isolation("`rename`", reject_with)?;
+                return this.set_errno_and_return_neg1_i32(ErrorKind::PermissionDenied);
+            }
 
-        let result = fs::rename(oldpath, newpath).map(|_| 0);
+            let result = if no_replace {
+                rename_noreplace(&oldpath, &newpath)
+            } else {
+                fs::rename(&oldpath, &newpath)
+            };
+            if let Err(err) = result {
+                return this.set_errno_and_return_neg1_i32(err);
+            }
+            fs::symlink_metadata(&newpath).is_ok_and(|metadata| metadata.is_dir())
+        };
 
-        interp_ok(Scalar::from_i32(this.try_unwrap_io_result(result)?))
+        let from = this.absolute_path(&oldpath);
+        let to = this.absolute_path(&newpath);
+        this.report_fs_event(FsEvent::Rename { from: &from, to: &to, is_dir })?;
+        interp_ok(Scalar::from_i32(0))
     }
 
-    fn mkdir(&mut self, path_op: &OpTy<'tcx>, mode_op: &OpTy<'tcx>) -> InterpResult<'tcx, Scalar> {
+    /// Implements `mkdir` and `mkdirat`. For `mkdir`, `dirfd` is `AT_FDCWD`.
+    fn mkdir(
+        &mut self,
+        dirfd: i32,
+        path_op: &OpTy<'tcx>,
+        mode_op: &OpTy<'tcx>,
+    ) -> InterpResult<'tcx, Scalar> {
         let this = self.eval_context_mut();
 
-        #[cfg_attr(not(unix), allow(unused_variables))]
         let mode = if matches!(&this.tcx.sess.target.os, Os::MacOs | Os::FreeBsd) {
             u32::from(this.read_scalar(mode_op)?.to_u16()?)
         } else {
@@ -1043,43 +1422,69 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         };
 
         let path = this.read_path_from_c_str(this.read_pointer(path_op)?)?;
+        let path = match this.resolve_path_at(dirfd, &path)? {
+            Ok(path) => path,
+            Err(err) => return this.set_errno_and_return_neg1_i32(err),
+        };
 
-        // Reject if isolation is enabled.
-        if let IsolatedOp::Reject(reject_with) = this.machine.isolated_op {
-            this.reject_in_isolation("`mkdir`", reject_with)?;
-            return this.set_errno_and_return_neg1_i32(ErrorKind::PermissionDenied);
-        }
-
-        #[cfg_attr(not(unix), allow(unused_mut))]
-        let mut builder = DirBuilder::new();
+        let result = if let Some(vfs) = &this.machine.vfs {
+            vfs.create_dir(&path, mode)
+        } else {
+            // Reject if isolation is enabled.
+            if let IsolatedOp::Reject(reject_with) = this.machine.isolated_op {
+                this.reject_in_isolation("`mkdir`", reject_with)?;
+                return this.set_errno_and_return_neg1_i32(ErrorKind::PermissionDenied);
+            }
 
-        // If the host supports it, forward on the mode of the directory
-        // (i.e. permission bits and the sticky bit)
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::DirBuilderExt;
-            builder.mode(mode);
-        }
+            #[cfg_attr(not(unix), allow(unused_mut))]
+            let mut builder = DirBuilder::new();
 
-        let result = builder.create(path).map(|_| 0i32);
+            // If the host supports it, forward on the mode of the directory
+            // (i.e. permission bits and the sticky bit)
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::DirBuilderExt;
+                builder.mode(mode);
+            }
 
-        interp_ok(Scalar::from_i32(this.try_unwrap_io_result(result)?))
+            builder.create(&path)
+        };
+        if let Err(err) = result {
+            return this.set_errno_and_return_neg1_i32(err);
+        }
+        let path = this.absolute_path(&path);
+        this.report_fs_event(FsEvent::Create { path: &path, is_dir: true })?;
+        interp_ok(Scalar::from_i32(0))
     }
 
-    fn rmdir(&mut self, path_op: &OpTy<'tcx>) -> InterpResult<'tcx, Scalar> {
+    /// Implements `rmdir`, and `unlinkat` with `AT_REMOVEDIR`. For `rmdir`, `dirfd` is
+    /// `AT_FDCWD`.
+    fn rmdir(&mut self, dirfd: i32, path_op: &OpTy<'tcx>) -> InterpResult<'tcx, Scalar> {
         let this = self.eval_context_mut();
 
         let path = this.read_path_from_c_str(this.read_pointer(path_op)?)?;
+        let path = match this.resolve_path_at(dirfd, &path)? {
+            Ok(path) => path,
+            Err(err) => return this.set_errno_and_return_neg1_i32(err),
+        };
 
-        // Reject if isolation is enabled.
-        if let IsolatedOp::Reject(reject_with) = this.machine.isolated_op {
-            this.reject_in_isolation("`rmdir`", reject_with)?;
-            return this.set_errno_and_return_neg1_i32(ErrorKind::PermissionDenied);
-        }
-
-        let result = fs::remove_dir(path).map(|_| 0i32);
+        let result = if let Some(vfs) = &this.machine.vfs {
+            vfs.remove_dir(&path)
+        } else {
+            // Reject if isolation is enabled.
+            if let IsolatedOp::Reject(reject_with) = this.machine.isolated_op {
+                this.reject_in_isolation("`rmdir`", reject_with)?;
+                return this.set_errno_and_return_neg1_i32(ErrorKind::PermissionDenied);
+            }
 
-        interp_ok(Scalar::from_i32(this.try_unwrap_io_result(result)?))
+            fs::remove_dir(&path)
+        };
+        if let Err(err) = result {
+            return this.set_errno_and_return_neg1_i32(err);
+        }
+        let path = this.absolute_path(&path);
+        this.report_fs_event(FsEvent::Delete { path: &path, is_dir: true })?;
+        interp_ok(Scalar::from_i32(0))
     }
 
     fn opendir(&mut self, name_op: &OpTy<'tcx>) -> InterpResult<'tcx, Scalar> {
@@ -1087,14 +1492,18 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
 
         let name = this.read_path_from_c_str(this.read_pointer(name_op)?)?;
 
-        // Reject if isolation is enabled.
-        if let IsolatedOp::Reject(reject_with) = this.machine.isolated_op {
-            this.reject_in_isolation("`opendir`", reject_with)?;
-            this.set_last_error(LibcError("EACCES"))?;
-            return interp_ok(Scalar::null_ptr(this));
-        }
+        let result = if let Some(vfs) = &this.machine.vfs {
+            vfs.read_dir(&name).map(|entries| Either::Right(entries.into_iter()))
+        } else {
+            // Reject if isolation is enabled.
+            if let IsolatedOp::Reject(reject_with) = this.machine.isolated_op {
+                this.reject_in_isolation("`opendir`", reject_with)?;
+                this.set_last_error(LibcError("EACCES"))?;
+                return interp_ok(Scalar::null_ptr(this));
+            }
 
-        let result = fs::read_dir(name);
+            fs::read_dir(name).map(Either::Left)
+        };
 
         match result {
             Ok(dir_iter) => {
@@ -1124,8 +1533,10 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
 
         let dirp = this.read_target_usize(dirp_op)?;
 
-        // Reject if isolation is enabled.
-        if let IsolatedOp::Reject(reject_with) = this.machine.isolated_op {
+        // Reject if isolation is enabled, unless the in-memory file system is used.
+        if let IsolatedOp::Reject(reject_with) = this.machine.isolated_op
+            && this.machine.vfs.is_none()
+        {
             this.reject_in_isolation("`readdir`", reject_with)?;
             this.set_last_error(LibcError("EBADF"))?;
             this.write_null(dest)?;
@@ -1136,7 +1547,7 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
             err_ub_format!("the DIR pointer passed to `readdir` did not come from opendir")
         })?;
 
-        let entry = match open_dir.next_host_entry() {
+        let entry = match open_dir.next_entry() {
             Some(Ok(dir_entry)) => {
                 let dir_entry = this.dir_entry_fields(dir_entry)?;
 
@@ -1260,8 +1671,10 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
 
         let dirp = this.read_target_usize(dirp_op)?;
 
-        // Reject if isolation is enabled.
-        if let IsolatedOp::Reject(reject_with) = this.machine.isolated_op {
+        // Reject if isolation is enabled, unless the in-memory file system is used.
+        if let IsolatedOp::Reject(reject_with) = this.machine.isolated_op
+            && this.machine.vfs.is_none()
+        {
             this.reject_in_isolation("`closedir`", reject_with)?;
             return this.set_errno_and_return_neg1_i32(LibcError("EBADF"));
         }
@@ -1293,15 +1706,19 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
             // man page says "EBADF or EINVAL", Linux seems to use EINVAL.
             return this.set_errno_and_return_neg1_i32(LibcError("EINVAL"));
         }
-        assert!(this.machine.communicate(), "isolation should have prevented even opening a file");
+        assert!(
+            this.machine.communicate() || file.file.is_virtual(),
+            "isolation should have prevented even opening a file"
+        );
 
-        if let Ok(length) = length.try_into() {
-            let result = file.file.set_len(length);
-            let result = this.try_unwrap_io_result(result.map(|_| 0i32))?;
-            interp_ok(Scalar::from_i32(result))
-        } else {
-            this.set_errno_and_return_neg1_i32(LibcError("EINVAL"))
+        let Ok(length) = length.try_into() else {
+            return this.set_errno_and_return_neg1_i32(LibcError("EINVAL"));
+        };
+        if let Err(err) = file.file.set_len(length) {
+            return this.set_errno_and_return_neg1_i32(err);
         }
+        this.report_fs_event(FsEvent::Modify(&file.path))?;
+        interp_ok(Scalar::from_i32(0))
     }
 
     /// NOTE: According to the man page of `possix_fallocate`, it returns the error code instead
@@ -1314,8 +1731,10 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
     ) -> InterpResult<'tcx, Scalar> {
         let this = self.eval_context_mut();
 
-        // Reject if isolation is enabled.
-        if let IsolatedOp::Reject(reject_with) = this.machine.isolated_op {
+        // Reject if isolation is enabled, unless the in-memory file system is used.
+        if let IsolatedOp::Reject(reject_with) = this.machine.isolated_op
+            && this.machine.vfs.is_none()
+        {
             this.reject_in_isolation("`posix_fallocate`", reject_with)?;
             // Return error code "EBADF" (bad fd).
             return interp_ok(this.eval_libc("EBADF"));
@@ -1337,8 +1756,10 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         // This is mostly a copy of `posix_fallocate` except that errors are returned via errno.
         let this = self.eval_context_mut();
 
-        // Reject if isolation is enabled.
-        if let IsolatedOp::Reject(reject_with) = this.machine.isolated_op {
+        // Reject if isolation is enabled, unless the in-memory file system is used.
+        if let IsolatedOp::Reject(reject_with) = this.machine.isolated_op
+            && this.machine.vfs.is_none()
+        {
             this.reject_in_isolation("`fallocate`", reject_with)?;
             // Set error code "EBADF" (bad fd).
             return this.set_errno_and_return_neg1_i32(LibcError("EBADF"));
@@ -1397,13 +1818,12 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         // If the size of the file is less than offset+size, then the file is increased to this
         // size; otherwise the file size is left unchanged.
         if current_size < new_size {
-            match file.file.set_len(new_size) {
-                Ok(()) => interp_ok(Ok(())),
-                Err(err) => interp_ok(Err(err.into())),
+            if let Err(err) = file.file.set_len(new_size) {
+                return interp_ok(Err(err.into()));
             }
-        } else {
-            interp_ok(Ok(()))
+            this.report_fs_event(FsEvent::Modify(&file.path))?;
         }
+        interp_ok(Ok(()))
     }
 
     fn fsync(&mut self, fd_op: &OpTy<'tcx>) -> InterpResult<'tcx, Scalar> {
@@ -1428,7 +1848,10 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         let file = fd.downcast::<FileHandle>().ok_or_else(|| {
             err_unsup_format!("`fsync` is only supported on file-backed file descriptors")
         })?;
-        assert!(this.machine.communicate(), "isolation should have prevented even opening a file");
+        assert!(
+            this.machine.communicate() || file.file.is_virtual(),
+            "isolation should have prevented even opening a file"
+        );
 
         let io_result = maybe_sync_file(&file.file, file.writable, File::sync_all);
         interp_ok(Scalar::from_i32(this.try_unwrap_io_result(io_result)?))
@@ -1446,7 +1869,10 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         let file = fd.downcast::<FileHandle>().ok_or_else(|| {
             err_unsup_format!("`fdatasync` is only supported on file-backed file descriptors")
         })?;
-        assert!(this.machine.communicate(), "isolation should have prevented even opening a file");
+        assert!(
+            this.machine.communicate() || file.file.is_virtual(),
+            "isolation should have prevented even opening a file"
+        );
 
         let io_result = maybe_sync_file(&file.file, file.writable, File::sync_data);
         interp_ok(Scalar::from_i32(this.try_unwrap_io_result(io_result)?))
@@ -1470,6 +1896,14 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         let file = fd.downcast::<FileHandle>().ok_or_else(|| {
             err_unsup_format!("`futimens` is only supported on file-backed file descriptors")
         })?;
+        let host_file = match &file.file {
+            Backing::Host(file) => file,
+            // The in-memory file system does not track timestamps.
+            Backing::Virtual(_) => {
+                this.report_fs_event(FsEvent::Attrib(&file.path))?;
+                return interp_ok(Scalar::from_i32(0));
+            }
+        };
         assert!(this.machine.communicate(), "isolation should have prevented even opening a file");
 
         let (access, modified) = if this.ptr_is_null(times_ptr)? {
@@ -1495,8 +1929,11 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         if let TimeUpdate::Set(modified) = modified {
             filetimes = filetimes.set_modified(modified);
         }
-        let result = file.file.set_times(filetimes);
-        interp_ok(Scalar::from_i32(this.try_unwrap_io_result(result.map(|()| 0i32))?))
+        if let Err(err) = host_file.set_times(filetimes) {
+            return this.set_errno_and_return_neg1_i32(err);
+        }
+        this.report_fs_event(FsEvent::Attrib(&file.path))?;
+        interp_ok(Scalar::from_i32(0))
     }
 
     fn sync_file_range(
@@ -1530,14 +1967,19 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         let file = fd.downcast::<FileHandle>().ok_or_else(|| {
             err_unsup_format!("`sync_data_range` is only supported on file-backed file descriptors")
         })?;
-        assert!(this.machine.communicate(), "isolation should have prevented even opening a file");
+        assert!(
+            this.machine.communicate() || file.file.is_virtual(),
+            "isolation should have prevented even opening a file"
+        );
 
         let io_result = maybe_sync_file(&file.file, file.writable, File::sync_data);
         interp_ok(Scalar::from_i32(this.try_unwrap_io_result(io_result)?))
     }
 
+    /// Implements `readlink` and `readlinkat`. For `readlink`, `dirfd` is `AT_FDCWD`.
     fn readlink(
         &mut self,
+        dirfd: i32,
         pathname_op: &OpTy<'tcx>,
         buf_op: &OpTy<'tcx>,
         bufsize_op: &OpTy<'tcx>,
@@ -1548,6 +1990,24 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         let buf = this.read_pointer(buf_op)?;
         let bufsize = this.read_target_usize(bufsize_op)?;
 
+        let pathname = match this.resolve_path_at(dirfd, &pathname)? {
+            Ok(path) => path,
+            Err(err) => {
+                this.set_last_error(err)?;
+                return interp_ok(-1);
+            }
+        };
+
+        if let Some(vfs) = &this.machine.vfs {
+            // Nothing in the in-memory file system is a symlink.
+            let err = match vfs.metadata(&pathname) {
+                Ok(_) => LibcError("EINVAL"),
+                Err(err) => err.into(),
+            };
+            this.set_last_error(err)?;
+            return interp_ok(-1);
+        }
+
         // Reject if isolation is enabled.
         if let IsolatedOp::Reject(reject_with) = this.machine.isolated_op {
             this.reject_in_isolation("`readlink`", reject_with)?;
@@ -1610,14 +2070,18 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         let pathname = this.read_path_from_c_str(this.read_pointer(path_op)?)?;
         let processed_ptr = this.read_pointer(processed_path_op)?;
 
-        // Reject if isolation is enabled.
-        if let IsolatedOp::Reject(reject_with) = this.machine.isolated_op {
-            this.reject_in_isolation("`realpath`", reject_with)?;
-            this.set_last_error(LibcError("EACCES"))?;
-            return interp_ok(Scalar::from_target_usize(0, this));
-        }
+        let result = if let Some(vfs) = &this.machine.vfs {
+            vfs.canonicalize(&pathname)
+        } else {
+            // Reject if isolation is enabled.
+            if let IsolatedOp::Reject(reject_with) = this.machine.isolated_op {
+                this.reject_in_isolation("`realpath`", reject_with)?;
+                this.set_last_error(LibcError("EACCES"))?;
+                return interp_ok(Scalar::from_target_usize(0, this));
+            }
 
-        let result = std::fs::canonicalize(pathname);
+            std::fs::canonicalize(pathname)
+        };
         match result {
             Ok(resolved) => {
                 let path_max = this
@@ -1683,8 +2147,10 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         let mut template = this.eval_context_ref().read_c_str(template_ptr)?.to_owned();
         let template_bytes = template.as_mut_slice();
 
-        // Reject if isolation is enabled.
-        if let IsolatedOp::Reject(reject_with) = this.machine.isolated_op {
+        // Reject if isolation is enabled, unless the in-memory file system is used.
+        if let IsolatedOp::Reject(reject_with) = this.machine.isolated_op
+            && this.machine.vfs.is_none()
+        {
             this.reject_in_isolation("`mkstemp`", reject_with)?;
             return this.set_errno_and_return_neg1_i32(LibcError("EACCES"));
         }
@@ -1752,11 +2218,25 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
             this.write_bytes_ptr(template_ptr, template_bytes.iter().copied())?;
 
             // See if we can create and open this file.
-            let file = fopts.open(bytes_to_os_str(template_bytes)?);
+            let path = Path::new(bytes_to_os_str(template_bytes)?);
+            let file = if let Some(vfs) = &this.machine.vfs {
+                let options = VfsOpenOptions {
+                    write: true,
+                    create_new: true,
+                    mode: 0o600,
+                    ..Default::default()
+                };
+                vfs.open(path, &options).map(Backing::Virtual)
+            } else {
+                fopts.open(path).map(Backing::Host)
+            };
             match file {
-                Ok(f) => {
+                Ok(file) => {
+                    let path = this.absolute_path(path);
+                    this.report_fs_event(FsEvent::Create { path: &path, is_dir: false })?;
                     let fd = this.machine.fds.insert_new(FileHandle {
-                        file: f,
+                        file,
+                        path,
                         writable: true,
                         readable: true,
                     });
@@ -1784,6 +2264,38 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
 /// Extracts the number of seconds and nanoseconds elapsed between `time` and the unix epoch when
 /// `time` is Ok. Returns `None` if `time` is an error. Fails if `time` happens before the unix
 /// epoch.
+/// Renames `from` to `to` on the host, failing with `EEXIST` if `to` already exists. Linux hosts
+/// do this atomically. Elsewhere we check for `to` first, so `to` still gets replaced if another
+/// host process creates it between the check and the rename.
+fn rename_noreplace(from: &Path, to: &Path) -> io::Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        use std::ffi::CString;
+        use std::os::unix::ffi::OsStrExt;
+
+        let from = CString::new(from.as_os_str().as_bytes())?;
+        let to = CString::new(to.as_os_str().as_bytes())?;
+        // SAFETY: both paths are valid NUL-terminated strings.
+        let ret = unsafe {
+            libc::renameat2(
+                libc::AT_FDCWD,
+                from.as_ptr(),
+                libc::AT_FDCWD,
+                to.as_ptr(),
+                libc::RENAME_NOREPLACE,
+            )
+        };
+        if ret == 0 { Ok(()) } else { Err(io::Error::last_os_error()) }
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        if fs::symlink_metadata(to).is_ok() {
+            return Err(ErrorKind::AlreadyExists.into());
+        }
+        fs::rename(from, to)
+    }
+}
+
 fn extract_sec_and_nsec<'tcx>(
     time: std::io::Result<SystemTime>,
 ) -> InterpResult<'tcx, Option<(u64, u32)>> {
@@ -1853,6 +2365,11 @@ impl FileMetadata {
         path: &Path,
         follow_symlink: bool,
     ) -> InterpResult<'tcx, Result<FileMetadata, IoError>> {
+        if let Some(vfs) = &ecx.machine.vfs {
+            // There are no symlinks in the in-memory file system.
+            let metadata = vfs.metadata(path);
+            return FileMetadata::from_vfs(ecx, metadata);
+        }
         let metadata =
             if follow_symlink { std::fs::metadata(path) } else { std::fs::symlink_metadata(path) };
 
@@ -1867,11 +2384,41 @@ impl FileMetadata {
             return interp_ok(Err(LibcError("EBADF")));
         };
         match fd.metadata()? {
-            Either::Left(host) => Self::from_meta(ecx, host),
+            Either::Left(Ok(HandleMetadata::Host(host))) => Self::from_meta(ecx, Ok(host)),
+            Either::Left(Ok(HandleMetadata::Virtual(metadata))) =>
+                Self::from_vfs(ecx, Ok(metadata)),
+            Either::Left(Err(err)) => interp_ok(Err(err.into())),
             Either::Right(name) => Self::synthetic(ecx, name),
         }
     }
 
+    fn from_vfs<'tcx>(
+        ecx: &mut MiriInterpCx<'tcx>,
+        metadata: io::Result<VfsMetadata>,
+    ) -> InterpResult<'tcx, Result<FileMetadata, IoError>> {
+        let metadata = match metadata {
+            Ok(metadata) => metadata,
+            Err(e) => return interp_ok(Err(e.into())),
+        };
+        let file_type = ecx.eval_libc(if metadata.is_dir { "S_IFDIR" } else { "S_IFREG" });
+        let file_type: u32 =
+            file_type.to_uint(ecx.libc_ty_layout("mode_t").size)?.try_into().unwrap();
+        interp_ok(Ok(FileMetadata {
+            mode: file_type | metadata.mode,
+            size: metadata.len,
+            created: None,
+            accessed: None,
+            modified: None,
+            dev: None,
+            ino: Some(metadata.ino),
+            nlink: Some(metadata.nlink),
+            uid: None,
+            gid: None,
+            blksize: None,
+            blocks: None,
+        }))
+    }
+
     fn synthetic<'tcx>(
         ecx: &mut MiriInterpCx<'tcx>,
         mode_name: &str,
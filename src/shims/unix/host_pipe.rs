@@ -0,0 +1,331 @@
+//! Pipes that are backed by a pipe of the host. The program's own `pipe` calls create virtual
+//! pipes (see `virtual_socket.rs`); those are turned into host pipes once one of their ends is
+//! passed to a child process.
+
+use std::cell::{Cell, RefCell, RefMut};
+use std::io::{self, Read};
+use std::os::fd::{AsFd, AsRawFd, OwnedFd};
+
+use mio::event::Source;
+use mio::unix::SourceFd;
+use mio::unix::pipe::{Receiver, Sender};
+use rustc_target::spec::Os;
+
+use crate::concurrency::blocking_io::SourceFileDescription;
+use crate::shims::files::{EvalContextExt as _, FileDescription, FileDescriptionRef};
+use crate::shims::unix::UnixFileDescription;
+use crate::*;
+
+#[derive(Debug)]
+enum PipeEnd {
+    Read(Receiver),
+    Write(Sender),
+}
+
+#[derive(Debug)]
+pub struct HostPipe {
+    end: PipeEnd,
+    /// Whether a child process has a copy of this end. The child expects it to be blocking, and
+    /// blocking host file descriptions cannot be polled, so all the program can still do with
+    /// such an end is close it.
+    shared_with_child: Cell<bool>,
+    /// Whether this fd is non-blocking or not.
+    is_non_block: Cell<bool>,
+    /// The current blocking I/O readiness of the file description.
+    io_readiness: RefCell<Readiness>,
+    /// State for being watched by epoll.
+    watched: ReadinessWatched,
+}
+
+impl HostPipe {
+    /// Wraps one end of a host pipe. The host file description must be in blocking mode.
+    pub fn new(fd: OwnedFd, is_read_end: bool, is_non_block: bool) -> Self {
+        let end = if is_read_end { PipeEnd::Read(fd.into()) } else { PipeEnd::Write(fd.into()) };
+        HostPipe {
+            end,
+            shared_with_child: Cell::new(true),
+            is_non_block: Cell::new(is_non_block),
+            io_readiness: RefCell::new(Readiness::EMPTY),
+            watched: ReadinessWatched::default(),
+        }
+    }
+
+    fn ensure_not_shared<'tcx>(&self, op: &str) -> InterpResult<'tcx> {
+        if self.shared_with_child.get() {
+            throw_unsup_format!("cannot {op} a pipe after passing it to a child process");
+        }
+        interp_ok(())
+    }
+}
+
+impl FileDescription for HostPipe {
+    fn name(&self) -> &'static str {
+        "pipe"
+    }
+
+    fn metadata<'tcx>(
+        &self,
+    ) -> InterpResult<'tcx, Either<io::Result<shims::HandleMetadata>, &'static str>> {
+        interp_ok(Either::Right("S_IFIFO"))
+    }
+
+    fn read<'tcx>(
+        self: FileDescriptionRef<Self>,
+        communicate_allowed: bool,
+        ptr: Pointer,
+        len: usize,
+        ecx: &mut MiriInterpCx<'tcx>,
+        finish: DynMachineCallback<'tcx, Result<usize, IoError>>,
+    ) -> InterpResult<'tcx> {
+        assert!(communicate_allowed, "cannot have `HostPipe` with isolation enabled!");
+        self.ensure_not_shared("read from")?;
+        if !matches!(self.end, PipeEnd::Read(_)) {
+            return finish.call(ecx, Err(IoError::LibcError("EBADF")));
+        }
+        if self.is_non_block.get() {
+            let result = ecx.try_non_block_pipe_io(&self, ptr, len)?;
+            finish.call(ecx, result)
+        } else {
+            ecx.block_for_pipe_io(self, ptr, len, finish)
+        }
+    }
+
+    fn write<'tcx>(
+        self: FileDescriptionRef<Self>,
+        communicate_allowed: bool,
+        ptr: Pointer,
+        len: usize,
+        ecx: &mut MiriInterpCx<'tcx>,
+        finish: DynMachineCallback<'tcx, Result<usize, IoError>>,
+    ) -> InterpResult<'tcx> {
+        assert!(communicate_allowed, "cannot have `HostPipe` with isolation enabled!");
+        self.ensure_not_shared("write to")?;
+        if !matches!(self.end, PipeEnd::Write(_)) {
+            return finish.call(ecx, Err(IoError::LibcError("EBADF")));
+        }
+        if self.is_non_block.get() {
+            let result = ecx.try_non_block_pipe_io(&self, ptr, len)?;
+            finish.call(ecx, result)
+        } else {
+            ecx.block_for_pipe_io(self, ptr, len, finish)
+        }
+    }
+
+    fn short_fd_operations(&self) -> bool {
+        // Like for virtual pipes, a short read or write means that the buffer is empty or full.
+        false
+    }
+
+    fn as_unix<'tcx>(
+        self: FileDescriptionRef<Self>,
+        _ecx: &MiriInterpCx<'tcx>,
+    ) -> FileDescriptionRef<dyn UnixFileDescription> {
+        self
+    }
+
+    fn get_flags<'tcx>(&self, ecx: &mut MiriInterpCx<'tcx>) -> InterpResult<'tcx, Scalar> {
+        let mut flags = match self.end {
+            PipeEnd::Read(_) => ecx.eval_libc_i32("O_RDONLY"),
+            PipeEnd::Write(_) => ecx.eval_libc_i32("O_WRONLY"),
+        };
+        if self.is_non_block.get() {
+            flags |= ecx.eval_libc_i32("O_NONBLOCK");
+        }
+        interp_ok(Scalar::from_i32(flags))
+    }
+
+    fn set_flags<'tcx>(
+        &self,
+        mut flag: i32,
+        ecx: &mut MiriInterpCx<'tcx>,
+    ) -> InterpResult<'tcx, Scalar> {
+        let o_nonblock = ecx.eval_libc_i32("O_NONBLOCK");
+
+        // O_NONBLOCK flag can be set / unset by user.
+        if flag & o_nonblock == o_nonblock {
+            self.is_non_block.set(true);
+            flag &= !o_nonblock;
+        } else {
+            self.is_non_block.set(false);
+        }
+
+        // Throw error if there is any unsupported flag.
+        if flag != 0 {
+            throw_unsup_format!(
+                "fcntl: only O_NONBLOCK is supported for F_SETFL on socketpairs and pipes"
+            )
+        }
+
+        interp_ok(Scalar::from_i32(0))
+    }
+
+    fn readiness_watched(&self) -> Option<&ReadinessWatched> {
+        Some(&self.watched)
+    }
+
+    fn readiness(&self) -> Readiness {
+        let mut readiness = *self.io_readiness.borrow();
+        // Once the other end is closed, reads return EOF and writes fail, so neither blocks.
+        readiness.readable |= readiness.read_closed;
+        readiness.writable |= readiness.write_closed;
+        readiness
+    }
+}
+
+impl UnixFileDescription for HostPipe {
+    fn ioctl<'tcx>(
+        &self,
+        op: Scalar,
+        arg: Option<&OpTy<'tcx>>,
+        ecx: &mut MiriInterpCx<'tcx>,
+    ) -> InterpResult<'tcx, i32> {
+        let fionbio = ecx.eval_libc("FIONBIO");
+
+        // On Linux, the standard library uses this to make the pipes of `Command::output`
+        // non-blocking.
+        if op == fionbio && matches!(ecx.tcx.sess.target.os, Os::Linux | Os::Android) {
+            let Some(value_ptr) = arg else {
+                throw_ub_format!("ioctl: setting FIONBIO on pipes requires a third argument");
+            };
+            let value = ecx.deref_pointer_as(value_ptr, ecx.machine.layouts.i32)?;
+            let non_block = ecx.read_scalar(&value)?.to_i32()? != 0;
+            self.is_non_block.set(non_block);
+            return interp_ok(0);
+        }
+
+        throw_unsup_format!("ioctl: unsupported operation {op:#x} on pipe");
+    }
+}
+
+impl SourceFileDescription for HostPipe {
+    fn with_source(&self, f: &mut dyn FnMut(&mut dyn Source) -> io::Result<()>) -> io::Result<()> {
+        let fd = match &self.end {
+            PipeEnd::Read(receiver) => receiver.as_raw_fd(),
+            PipeEnd::Write(sender) => sender.as_raw_fd(),
+        };
+        f(&mut SourceFd(&fd))
+    }
+
+    fn get_readiness_mut(&self) -> RefMut<'_, Readiness> {
+        self.io_readiness.borrow_mut()
+    }
+}
+
+impl<'tcx> EvalContextExt<'tcx> for crate::MiriInterpCx<'tcx> {}
+pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
+    /// Makes the program's copy of a host pipe end pollable. Only ends that are not passed to
+    /// a child process may be registered.
+    fn register_host_pipe(&mut self, pipe: FileDescriptionRef<HostPipe>) -> io::Result<()> {
+        let this = self.eval_context_mut();
+        match &pipe.end {
+            PipeEnd::Read(receiver) => receiver.set_nonblocking(true)?,
+            PipeEnd::Write(sender) => sender.set_nonblocking(true)?,
+        }
+        pipe.shared_with_child.set(false);
+        this.machine.blocking_io.register(pipe);
+        Ok(())
+    }
+
+    /// Returns a copy of `pipe` to pass to a child process. From now on, the program itself can
+    /// no longer use the pipe.
+    fn host_pipe_for_child(
+        &mut self,
+        pipe: &FileDescriptionRef<HostPipe>,
+    ) -> InterpResult<'tcx, io::Result<OwnedFd>> {
+        let this = self.eval_context_mut();
+        if !pipe.shared_with_child.get() {
+            if pipe.watched.is_watched() {
+                throw_unsup_format!(
+                    "passing a pipe that is watched by epoll or poll to a child process is not supported"
+                );
+            }
+            if !this.machine.blocking_io.deregister(pipe.clone()) {
+                throw_unsup_format!(
+                    "passing a pipe that a thread is blocked on to a child process is not supported"
+                );
+            }
+            let result = match &pipe.end {
+                PipeEnd::Read(receiver) => receiver.set_nonblocking(false),
+                PipeEnd::Write(sender) => sender.set_nonblocking(false),
+            };
+            if let Err(e) = result {
+                return interp_ok(Err(e));
+            }
+            pipe.shared_with_child.set(true);
+        }
+        let fd = match &pipe.end {
+            PipeEnd::Read(receiver) => receiver.as_fd(),
+            PipeEnd::Write(sender) => sender.as_fd(),
+        };
+        interp_ok(fd.try_clone_to_owned())
+    }
+
+    /// Reads from or writes to a non-blocking host pipe end, depending on which end it is.
+    fn try_non_block_pipe_io(
+        &mut self,
+        pipe: &FileDescriptionRef<HostPipe>,
+        ptr: Pointer,
+        len: usize,
+    ) -> InterpResult<'tcx, Result<usize, IoError>> {
+        let this = self.eval_context_mut();
+
+        let result = match &pipe.end {
+            PipeEnd::Read(receiver) =>
+                this.read_from_host(|buf| (&*receiver).read(buf), len, ptr)?,
+            PipeEnd::Write(sender) => this.write_to_host(sender, len, ptr)?,
+        };
+
+        if let Err(IoError::HostError(e)) = &result
+            && e.kind() == io::ErrorKind::WouldBlock
+        {
+            // We know that the pipe is not ready so we need to update its readiness.
+            let mut readiness = pipe.io_readiness.borrow_mut();
+            match pipe.end {
+                PipeEnd::Read(_) => readiness.readable = false,
+                PipeEnd::Write(_) => readiness.writable = false,
+            }
+            drop(readiness);
+            this.update_fd_readiness(pipe.clone(), ReadinessUpdateFlags::DEFAULT)?;
+        }
+        interp_ok(result)
+    }
+
+    /// Block the thread until the host pipe end can be read from or written to, and then do
+    /// that. This recursively calls itself should the operation still block.
+    fn block_for_pipe_io(
+        &mut self,
+        pipe: FileDescriptionRef<HostPipe>,
+        ptr: Pointer,
+        len: usize,
+        finish: DynMachineCallback<'tcx, Result<usize, IoError>>,
+    ) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+        let interest = match pipe.end {
+            PipeEnd::Read(_) => BlockingIoInterest::Read,
+            PipeEnd::Write(_) => BlockingIoInterest::Write,
+        };
+        this.block_thread_for_io(
+            pipe.clone(),
+            interest,
+            None,
+            callback!(@capture<'tcx> {
+                pipe: FileDescriptionRef<HostPipe>,
+                ptr: Pointer,
+                len: usize,
+                finish: DynMachineCallback<'tcx, Result<usize, IoError>>,
+            } |this, kind: UnblockKind| {
+                // Remove the blocking I/O interest for unblocking this thread.
+                this.machine.blocking_io.remove_blocked_thread(pipe.id(), this.machine.threads.active_thread());
+                assert_eq!(kind, UnblockKind::Ready);
+
+                match this.try_non_block_pipe_io(&pipe, ptr, len)? {
+                    Err(IoError::HostError(e)) if e.kind() == io::ErrorKind::WouldBlock => {
+                        // We need to block the thread again as it would still block.
+                        this.block_for_pipe_io(pipe, ptr, len, finish)
+                    },
+                    result => finish.call(this, result)
+                }
+            }),
+        )
+    }
+}
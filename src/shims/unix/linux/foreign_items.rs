@@ -5,9 +5,11 @@ use rustc_target::callconv::FnAbi;
 
 use self::shims::unix::linux::mem::EvalContextExt as _;
 use self::shims::unix::linux_like::eventfd::EvalContextExt as _;
+use self::shims::unix::linux_like::signalfd::EvalContextExt as _;
 use self::shims::unix::linux_like::syscall::syscall;
 use crate::machine::{SIGRTMAX, SIGRTMIN};
 use crate::shims::unix::foreign_items::EvalContextExt as _;
+use crate::shims::unix::linux_like::copy::EvalContextExt as _;
 use crate::shims::unix::linux_like::epoll::EvalContextExt as _;
 use crate::shims::unix::linux_like::thread::prctl;
 use crate::shims::unix::*;
@@ -19,7 +21,7 @@ use crate::*;
 const TASK_COMM_LEN: u64 = 16;
 
 pub fn is_dyn_sym(name: &str) -> bool {
-    matches!(name, "gettid" | "statx")
+    matches!(name, "gettid" | "statx" | "copy_file_range" | "posix_spawn_file_actions_addchdir_np")
 }
 
 impl<'tcx> EvalContextExt<'tcx> for crate::MiriInterpCx<'tcx> {}
@@ -42,7 +44,15 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
                 // has O_CREAT (or on linux O_TMPFILE, but miri doesn't support that) set
                 let ([path_raw, flag], varargs) =
                     this.check_shim_sig_variadic_lenient(abi, CanonAbi::C, link_name, args)?;
-                let result = this.open(path_raw, flag, varargs)?;
+                let at_fdcwd = this.eval_libc_i32("AT_FDCWD");
+                let result = this.open(at_fdcwd, path_raw, flag, varargs)?;
+                this.write_scalar(result, dest)?;
+            }
+            "openat64" => {
+                let ([dirfd, path_raw, flag], varargs) =
+                    this.check_shim_sig_variadic_lenient(abi, CanonAbi::C, link_name, args)?;
+                let dirfd = this.read_scalar(dirfd)?.to_i32()?;
+                let result = this.open(dirfd, path_raw, flag, varargs)?;
                 this.write_scalar(result, dest)?;
             }
             "pread64" => {
@@ -150,6 +160,34 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
                 let result = this.linux_fallocate(fd, mode, offset, len)?;
                 this.write_scalar(result, dest)?;
             }
+            "copy_file_range" => {
+                let [fd_in, off_in, fd_out, off_out, len, flags] = this.check_shim_sig(
+                    shim_sig!(extern "C" fn(i32, *mut _, i32, *mut _, usize, u32) -> isize),
+                    link_name,
+                    abi,
+                    args,
+                )?;
+                this.copy_file_range(fd_in, off_in, fd_out, off_out, len, flags, dest)?;
+            }
+            "sendfile" | "sendfile64" => {
+                let [out_fd, in_fd, offset, count] = this.check_shim_sig(
+                    shim_sig!(extern "C" fn(i32, i32, *mut _, usize) -> isize),
+                    link_name,
+                    abi,
+                    args,
+                )?;
+                let off_t = if link_name.as_str() == "sendfile" { "off_t" } else { "off64_t" };
+                this.sendfile(out_fd, in_fd, offset, count, off_t, dest)?;
+            }
+            "splice" => {
+                let [fd_in, off_in, fd_out, off_out, len, flags] = this.check_shim_sig(
+                    shim_sig!(extern "C" fn(i32, *mut _, i32, *mut _, usize, u32) -> isize),
+                    link_name,
+                    abi,
+                    args,
+                )?;
+                this.splice(fd_in, off_in, fd_out, off_out, len, flags, dest)?;
+            }
 
             "readdir64" => {
                 let [dirp] = this.check_shim_sig_lenient(abi, CanonAbi::C, link_name, args)?;
@@ -167,7 +205,20 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
                 let result = this.linux_statx(dirfd, pathname, flags, mask, statxbuf)?;
                 this.write_scalar(result, dest)?;
             }
-            // epoll, eventfd
+            "renameat2" => {
+                let [olddirfd, oldpath, newdirfd, newpath, flags] = this.check_shim_sig(
+                    shim_sig!(extern "C" fn(i32, *const _, i32, *const _, u32) -> i32),
+                    link_name,
+                    abi,
+                    args,
+                )?;
+                let olddirfd = this.read_scalar(olddirfd)?.to_i32()?;
+                let newdirfd = this.read_scalar(newdirfd)?.to_i32()?;
+                let flags = this.read_scalar(flags)?.to_u32()?;
+                let result = this.rename(olddirfd, oldpath, newdirfd, newpath, flags)?;
+                this.write_scalar(result, dest)?;
+            }
+            // epoll, eventfd, timerfd, signalfd, inotify
             "epoll_create1" => {
                 let [flag] = this.check_shim_sig_lenient(abi, CanonAbi::C, link_name, args)?;
                 let result = this.epoll_create1(flag)?;
@@ -189,6 +240,52 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
                 let result = this.eventfd(val, flag)?;
                 this.write_scalar(result, dest)?;
             }
+            "timerfd_create" => {
+                let [clockid, flags] =
+                    this.check_shim_sig_lenient(abi, CanonAbi::C, link_name, args)?;
+                let result = this.timerfd_create(clockid, flags)?;
+                this.write_scalar(result, dest)?;
+            }
+            "timerfd_settime" => {
+                let [fd, flags, new_value, old_value] =
+                    this.check_shim_sig_lenient(abi, CanonAbi::C, link_name, args)?;
+                let result = this.timerfd_settime(fd, flags, new_value, old_value)?;
+                this.write_scalar(result, dest)?;
+            }
+            "timerfd_gettime" => {
+                let [fd, curr_value] =
+                    this.check_shim_sig_lenient(abi, CanonAbi::C, link_name, args)?;
+                let result = this.timerfd_gettime(fd, curr_value)?;
+                this.write_scalar(result, dest)?;
+            }
+            "signalfd" => {
+                let [fd, mask, flags] =
+                    this.check_shim_sig_lenient(abi, CanonAbi::C, link_name, args)?;
+                let result = this.signalfd(fd, mask, flags)?;
+                this.write_scalar(result, dest)?;
+            }
+            "inotify_init" => {
+                let [] = this.check_shim_sig_lenient(abi, CanonAbi::C, link_name, args)?;
+                let result = this.inotify_init1(0)?;
+                this.write_scalar(result, dest)?;
+            }
+            "inotify_init1" => {
+                let [flags] = this.check_shim_sig_lenient(abi, CanonAbi::C, link_name, args)?;
+                let flags = this.read_scalar(flags)?.to_i32()?;
+                let result = this.inotify_init1(flags)?;
+                this.write_scalar(result, dest)?;
+            }
+            "inotify_add_watch" => {
+                let [fd, pathname, mask] =
+                    this.check_shim_sig_lenient(abi, CanonAbi::C, link_name, args)?;
+                let result = this.inotify_add_watch(fd, pathname, mask)?;
+                this.write_scalar(result, dest)?;
+            }
+            "inotify_rm_watch" => {
+                let [fd, wd] = this.check_shim_sig_lenient(abi, CanonAbi::C, link_name, args)?;
+                let result = this.inotify_rm_watch(fd, wd)?;
+                this.write_scalar(result, dest)?;
+            }
 
             // Threading
             "pthread_setname_np" => {
@@ -0,0 +1,282 @@
+//! Implements `copy_file_range`, `sendfile` and `splice`, which `std::fs::copy` and
+//! `std::io::copy` use to move data between file descriptors without going through userspace.
+//! We do go through a buffer, see [`EvalContextExt::transfer_between_fds`].
+
+use crate::shims::files::{DirHandle, DynFileDescriptionRef, FileHandle};
+use crate::shims::unix::*;
+use crate::*;
+
+/// Where the data of one side of a transfer comes from or goes to.
+struct TransferEnd<'tcx> {
+    fd: DynFileDescriptionRef,
+    /// The offset to use instead of the file position, and where to store the updated offset.
+    offset: Option<(u64, MPlaceTy<'tcx>)>,
+}
+
+impl<'tcx> EvalContextExt<'tcx> for crate::MiriInterpCx<'tcx> {}
+pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
+    /// Copies data between two regular files.
+    ///
+    /// <https://man7.org/linux/man-pages/man2/copy_file_range.2.html>
+    fn copy_file_range(
+        &mut self,
+        fd_in: &OpTy<'tcx>,
+        off_in: &OpTy<'tcx>,
+        fd_out: &OpTy<'tcx>,
+        off_out: &OpTy<'tcx>,
+        len: &OpTy<'tcx>,
+        flags: &OpTy<'tcx>,
+        dest: &MPlaceTy<'tcx>,
+    ) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+
+        let fd_in = this.read_scalar(fd_in)?.to_i32()?;
+        let fd_out = this.read_scalar(fd_out)?.to_i32()?;
+        let len = this.read_target_usize(len)?;
+        let flags = this.read_scalar(flags)?.to_u32()?;
+
+        if flags != 0 {
+            return this.set_errno_and_return_neg1(LibcError("EINVAL"), dest);
+        }
+        let (Some(fd_in), Some(fd_out)) =
+            (this.machine.fds.get(fd_in), this.machine.fds.get(fd_out))
+        else {
+            return this.set_errno_and_return_neg1(LibcError("EBADF"), dest);
+        };
+        let (Some(file_in), Some(file_out)) =
+            (fd_in.clone().downcast::<FileHandle>(), fd_out.clone().downcast::<FileHandle>())
+        else {
+            let is_dir = |fd: &DynFileDescriptionRef| fd.clone().downcast::<DirHandle>().is_some();
+            let error = if is_dir(&fd_in) || is_dir(&fd_out) { "EISDIR" } else { "EINVAL" };
+            return this.set_errno_and_return_neg1(LibcError(error), dest);
+        };
+        if !file_in.readable || !file_out.writable {
+            return this.set_errno_and_return_neg1(LibcError("EBADF"), dest);
+        }
+
+        // Unlike the other calls, Linux reports negative offsets as an overflow here.
+        let Some(off_in) = this.read_transfer_offset(off_in, "off64_t")? else {
+            return this.set_errno_and_return_neg1(LibcError("EOVERFLOW"), dest);
+        };
+        let Some(off_out) = this.read_transfer_offset(off_out, "off64_t")? else {
+            return this.set_errno_and_return_neg1(LibcError("EOVERFLOW"), dest);
+        };
+
+        // Copying within a file is fine, but the ranges must not overlap.
+        if file_in.path == file_out.path {
+            let communicate = this.machine.communicate();
+            let start = |fd: &DynFileDescriptionRef, offset: &Option<(u64, MPlaceTy<'tcx>)>| {
+                match offset {
+                    Some((offset, _)) => interp_ok(Ok(*offset)),
+                    None => fd.seek(communicate, std::io::SeekFrom::Current(0)),
+                }
+            };
+            let start_in = match start(&fd_in, &off_in)? {
+                Ok(start) => start,
+                Err(e) => return this.set_errno_and_return_neg1(e, dest),
+            };
+            let start_out = match start(&fd_out, &off_out)? {
+                Ok(start) => start,
+                Err(e) => return this.set_errno_and_return_neg1(e, dest),
+            };
+            if start_in.abs_diff(start_out) < len {
+                return this.set_errno_and_return_neg1(LibcError("EINVAL"), dest);
+            }
+        }
+
+        this.transfer(
+            TransferEnd { fd: fd_in, offset: off_in },
+            TransferEnd { fd: fd_out, offset: off_out },
+            len,
+            dest,
+        )
+    }
+
+    /// Copies data from a file to any file descriptor. `sendfile64` only differs in the type of
+    /// `offset`, given by `off_t`.
+    ///
+    /// <https://man7.org/linux/man-pages/man2/sendfile.2.html>
+    fn sendfile(
+        &mut self,
+        out_fd: &OpTy<'tcx>,
+        in_fd: &OpTy<'tcx>,
+        offset: &OpTy<'tcx>,
+        count: &OpTy<'tcx>,
+        off_t: &str,
+        dest: &MPlaceTy<'tcx>,
+    ) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+
+        let out_fd = this.read_scalar(out_fd)?.to_i32()?;
+        let in_fd = this.read_scalar(in_fd)?.to_i32()?;
+        let count = this.read_target_usize(count)?;
+
+        let (Some(fd_in), Some(fd_out)) =
+            (this.machine.fds.get(in_fd), this.machine.fds.get(out_fd))
+        else {
+            return this.set_errno_and_return_neg1(LibcError("EBADF"), dest);
+        };
+        // The input has to support `mmap`, which among the file descriptions we have only
+        // regular files do.
+        let Some(file_in) = fd_in.clone().downcast::<FileHandle>() else {
+            return this.set_errno_and_return_neg1(LibcError("EINVAL"), dest);
+        };
+        if !file_in.readable
+            || fd_out.clone().downcast::<FileHandle>().is_some_and(|file| !file.writable)
+        {
+            return this.set_errno_and_return_neg1(LibcError("EBADF"), dest);
+        }
+
+        let Some(offset) = this.read_transfer_offset(offset, off_t)? else {
+            return this.set_errno_and_return_neg1(LibcError("EINVAL"), dest);
+        };
+
+        this.transfer(
+            TransferEnd { fd: fd_in, offset },
+            TransferEnd { fd: fd_out, offset: None },
+            count,
+            dest,
+        )
+    }
+
+    /// Moves data from or to a pipe.
+    ///
+    /// <https://man7.org/linux/man-pages/man2/splice.2.html>
+    fn splice(
+        &mut self,
+        fd_in: &OpTy<'tcx>,
+        off_in: &OpTy<'tcx>,
+        fd_out: &OpTy<'tcx>,
+        off_out: &OpTy<'tcx>,
+        len: &OpTy<'tcx>,
+        flags: &OpTy<'tcx>,
+        dest: &MPlaceTy<'tcx>,
+    ) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+
+        let fd_in = this.read_scalar(fd_in)?.to_i32()?;
+        let fd_out = this.read_scalar(fd_out)?.to_i32()?;
+        let len = this.read_target_usize(len)?;
+        let flags = this.read_scalar(flags)?.to_u32()?;
+
+        let splice_f_move = this.eval_libc_u32("SPLICE_F_MOVE");
+        let splice_f_nonblock = this.eval_libc_u32("SPLICE_F_NONBLOCK");
+        let splice_f_more = this.eval_libc_u32("SPLICE_F_MORE");
+        let splice_f_gift = this.eval_libc_u32("SPLICE_F_GIFT");
+        if flags & !(splice_f_move | splice_f_nonblock | splice_f_more | splice_f_gift) != 0 {
+            return this.set_errno_and_return_neg1(LibcError("EINVAL"), dest);
+        }
+        if flags & splice_f_nonblock != 0 {
+            // This would have to make the pipe operations non-blocking regardless of the flags
+            // of the pipe itself.
+            throw_unsup_format!("splice: SPLICE_F_NONBLOCK is not supported");
+        }
+        // `SPLICE_F_MOVE`, `SPLICE_F_MORE` and `SPLICE_F_GIFT` are mere hints.
+
+        let (Some(fd_in), Some(fd_out)) =
+            (this.machine.fds.get(fd_in), this.machine.fds.get(fd_out))
+        else {
+            return this.set_errno_and_return_neg1(LibcError("EBADF"), dest);
+        };
+        let is_pipe = |fd: &DynFileDescriptionRef| fd.name() == "pipe";
+        if !is_pipe(&fd_in) && !is_pipe(&fd_out) {
+            return this.set_errno_and_return_neg1(LibcError("EINVAL"), dest);
+        }
+        if fd_in.clone().downcast::<FileHandle>().is_some_and(|file| !file.readable)
+            || fd_out.clone().downcast::<FileHandle>().is_some_and(|file| !file.writable)
+        {
+            return this.set_errno_and_return_neg1(LibcError("EBADF"), dest);
+        }
+
+        let Some(off_in) = this.read_transfer_offset(off_in, "off64_t")? else {
+            return this.set_errno_and_return_neg1(LibcError("EINVAL"), dest);
+        };
+        let Some(off_out) = this.read_transfer_offset(off_out, "off64_t")? else {
+            return this.set_errno_and_return_neg1(LibcError("EINVAL"), dest);
+        };
+        // Only files have offsets.
+        let is_file = |fd: &DynFileDescriptionRef| fd.clone().downcast::<FileHandle>().is_some();
+        if off_in.is_some() && !is_file(&fd_in) || off_out.is_some() && !is_file(&fd_out) {
+            return this.set_errno_and_return_neg1(LibcError("ESPIPE"), dest);
+        }
+
+        this.transfer(
+            TransferEnd { fd: fd_in, offset: off_in },
+            TransferEnd { fd: fd_out, offset: off_out },
+            len,
+            dest,
+        )
+    }
+}
+
+impl<'tcx> EvalContextPrivExt<'tcx> for crate::MiriInterpCx<'tcx> {}
+trait EvalContextPrivExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
+    /// Reads an optional pointer to an offset of type `off_t`. The outer `None` means the offset
+    /// is negative; the inner one that the pointer is null.
+    fn read_transfer_offset(
+        &mut self,
+        ptr: &OpTy<'tcx>,
+        off_t: &str,
+    ) -> InterpResult<'tcx, Option<Option<(u64, MPlaceTy<'tcx>)>>> {
+        let this = self.eval_context_mut();
+
+        let ptr = this.read_pointer(ptr)?;
+        if this.ptr_is_null(ptr)? {
+            return interp_ok(Some(None));
+        }
+        let place = this.ptr_to_mplace(ptr, this.libc_ty_layout(off_t));
+        let offset = this.read_scalar(&place)?.to_int(place.layout.size)?;
+        interp_ok(u64::try_from(offset).ok().map(|offset| Some((offset, place))))
+    }
+
+    /// Moves up to `len` bytes from `input` to `output`, storing the number of bytes that were
+    /// moved in `dest` and updating the explicit offsets.
+    fn transfer(
+        &mut self,
+        input: TransferEnd<'tcx>,
+        output: TransferEnd<'tcx>,
+        len: u64,
+        dest: &MPlaceTy<'tcx>,
+    ) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+
+        // Like `read` and `write`, we never move more than fits in the return type.
+        let len = len
+            .min(u64::try_from(this.target_isize_max()).unwrap())
+            .min(u64::try_from(isize::MAX).unwrap());
+        let len = usize::try_from(len).unwrap();
+
+        let (offset_in, place_in) = input.offset.unzip();
+        let (offset_out, place_out) = output.offset.unzip();
+        let dest = dest.clone();
+        this.transfer_between_fds(
+            input.fd,
+            offset_in,
+            output.fd,
+            offset_out,
+            len,
+            callback!(
+                @capture<'tcx> {
+                    offset_in: Option<u64>,
+                    place_in: Option<MPlaceTy<'tcx>>,
+                    offset_out: Option<u64>,
+                    place_out: Option<MPlaceTy<'tcx>>,
+                    dest: MPlaceTy<'tcx>,
+                }
+                |this, result: Result<usize, IoError>| {
+                    let size = match result {
+                        Ok(size) => size,
+                        Err(e) => return this.set_errno_and_return_neg1(e, &dest),
+                    };
+                    let moved = u64::try_from(size).unwrap();
+                    for (offset, place) in [(offset_in, place_in), (offset_out, place_out)] {
+                        if let (Some(offset), Some(place)) = (offset, place) {
+                            this.write_int(offset.strict_add(moved), &place)?;
+                        }
+                    }
+                    this.write_int(moved, &dest)
+                }
+            ),
+        )
+    }
+}
@@ -23,7 +23,7 @@ impl FileDescription for Epoll {
 
     fn metadata<'tcx>(
         &self,
-    ) -> InterpResult<'tcx, Either<io::Result<std::fs::Metadata>, &'static str>> {
+    ) -> InterpResult<'tcx, Either<io::Result<shims::HandleMetadata>, &'static str>> {
         // On Linux, epoll is an "anonymous inode" reported as S_IFREG.
         interp_ok(Either::Right("S_IFREG"))
     }
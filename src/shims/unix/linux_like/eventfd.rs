@@ -40,7 +40,7 @@ impl FileDescription for EventFd {
 
     fn metadata<'tcx>(
         &self,
-    ) -> InterpResult<'tcx, Either<io::Result<std::fs::Metadata>, &'static str>> {
+    ) -> InterpResult<'tcx, Either<io::Result<shims::HandleMetadata>, &'static str>> {
         // On Linux, eventfd is an "anonymous inode" reported as S_IFREG.
         interp_ok(Either::Right("S_IFREG"))
     }
@@ -0,0 +1,618 @@
+//! Linux `inotify` implementation.
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::ffi::OsString;
+use std::io;
+use std::io::ErrorKind;
+use std::path::{Component, Path, PathBuf};
+
+use rustc_abi::Size;
+
+use crate::concurrency::VClock;
+use crate::shims::files::{FileDescription, FileDescriptionRef, WeakFileDescriptionRef};
+use crate::shims::unix::UnixFileDescription;
+use crate::shims::unix::fs::EvalContextExt as _;
+use crate::*;
+
+/// The default of `/proc/sys/fs/inotify/max_queued_events`. Further events are dropped and
+/// replaced by a single `IN_Q_OVERFLOW` event.
+const MAX_QUEUED_EVENTS: usize = 16384;
+
+/// A change to the file system, made through one of Miri's file system shims. All paths are
+/// absolute.
+#[derive(Clone, Copy, Debug)]
+pub enum FsEvent<'a> {
+    /// A file or directory was created.
+    Create { path: &'a Path, is_dir: bool },
+    /// The contents of a file changed.
+    Modify(&'a Path),
+    /// The metadata of a file or directory changed.
+    Attrib(&'a Path),
+    /// A file or directory was removed.
+    Delete { path: &'a Path, is_dir: bool },
+    /// A file or directory was moved from `from` to `to`.
+    Rename { from: &'a Path, to: &'a Path, is_dir: bool },
+}
+
+/// A watch added by `inotify_add_watch`.
+#[derive(Debug)]
+struct Watch {
+    wd: i32,
+    /// The watched file or directory. Moving it, or one of the directories above it, updates
+    /// this path.
+    path: PathBuf,
+    /// The events to report, and the `IN_ONESHOT` flag.
+    mask: u32,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct InotifyEvent {
+    wd: i32,
+    mask: u32,
+    cookie: u32,
+    /// The name of the file inside a watched directory the event is about.
+    name: Option<OsString>,
+}
+
+/// A file descriptor created by `inotify_init`, from which the program can read events about
+/// the files and directories it watches.
+///
+/// Only changes made through Miri's own file system shims are reported, so this works the same
+/// with the host file system and with the in-memory one, but never sees changes made by other
+/// processes. Watches are identified by their path rather than by their inode: a file that is
+/// reachable through several paths, e.g. via a symlink, is watched separately for each of them.
+/// Access, open and close events are never reported.
+///
+/// <https://man7.org/linux/man-pages/man7/inotify.7.html>
+#[derive(Debug)]
+struct Inotify {
+    watches: RefCell<Vec<Watch>>,
+    /// The watch descriptor of the next watch.
+    next_wd: Cell<i32>,
+    events: RefCell<VecDeque<InotifyEvent>>,
+    is_nonblock: bool,
+    /// The clocks of all threads that caused one of the queued events. Readers synchronize with
+    /// them.
+    clock: RefCell<VClock>,
+    /// A list of thread ids blocked on reading from this file descriptor.
+    blocked_read_tid: RefCell<Vec<ThreadId>>,
+    /// State for being watched by epoll.
+    watched: ReadinessWatched,
+}
+
+impl FileDescription for Inotify {
+    fn name(&self) -> &'static str {
+        "inotify"
+    }
+
+    fn metadata<'tcx>(
+        &self,
+    ) -> InterpResult<'tcx, Either<io::Result<shims::HandleMetadata>, &'static str>> {
+        // Like eventfd, an inotify instance is an "anonymous inode" reported as S_IFREG.
+        interp_ok(Either::Right("S_IFREG"))
+    }
+
+    /// Reads as many queued events as fit into the buffer. If there are none, this blocks until
+    /// an event is queued, or fails with `EAGAIN` if the file descriptor is nonblocking.
+    fn read<'tcx>(
+        self: FileDescriptionRef<Self>,
+        _communicate_allowed: bool,
+        ptr: Pointer,
+        len: usize,
+        ecx: &mut MiriInterpCx<'tcx>,
+        finish: DynMachineCallback<'tcx, Result<usize, IoError>>,
+    ) -> InterpResult<'tcx> {
+        inotify_read(ptr, len, self, ecx, finish)
+    }
+
+    fn readiness_watched(&self) -> Option<&ReadinessWatched> {
+        Some(&self.watched)
+    }
+
+    fn readiness(&self) -> Readiness {
+        Readiness { readable: !self.events.borrow().is_empty(), ..Readiness::EMPTY }
+    }
+
+    fn as_unix<'tcx>(
+        self: FileDescriptionRef<Self>,
+        _ecx: &MiriInterpCx<'tcx>,
+    ) -> FileDescriptionRef<dyn UnixFileDescription> {
+        self
+    }
+}
+
+impl UnixFileDescription for Inotify {}
+
+/// All open inotify instances, which need to be notified of changes to the file system.
+/// Closed instances are removed lazily.
+#[derive(Debug, Default)]
+pub struct InotifyInstances {
+    instances: Vec<WeakFileDescriptionRef<Inotify>>,
+    /// The last cookie that connected an `IN_MOVED_FROM` event to its `IN_MOVED_TO` event.
+    last_cookie: u32,
+}
+
+/// Removes `.` and `..` from `path` without looking at the file system, so that events and
+/// watches agree on the path of a file.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+impl<'tcx> EvalContextExtPrivate<'tcx> for crate::MiriInterpCx<'tcx> {}
+trait EvalContextExtPrivate<'tcx>: crate::MiriInterpCxExt<'tcx> {
+    /// Returns all open inotify instances, and forgets the closed ones.
+    fn inotify_instances(&mut self) -> Vec<FileDescriptionRef<Inotify>> {
+        let this = self.eval_context_mut();
+        let instances = &mut this.machine.inotify_instances.instances;
+        let open = instances.iter().filter_map(|inotify| inotify.upgrade()).collect::<Vec<_>>();
+        instances.retain(|inotify| inotify.upgrade().is_some());
+        open
+    }
+
+    /// Queues an event on `inotify`, merging it with the previous one if they are identical, and
+    /// wakes up the readers.
+    fn queue_inotify_event(
+        &mut self,
+        inotify: &FileDescriptionRef<Inotify>,
+        event: InotifyEvent,
+    ) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+
+        {
+            let mut events = inotify.events.borrow_mut();
+            if events.back() == Some(&event) {
+                return interp_ok(());
+            }
+            if events.len() >= MAX_QUEUED_EVENTS {
+                let overflow = InotifyEvent {
+                    wd: -1,
+                    mask: this.eval_libc_u32("IN_Q_OVERFLOW"),
+                    cookie: 0,
+                    name: None,
+                };
+                if events.back() == Some(&overflow) {
+                    return interp_ok(());
+                }
+                events.push_back(overflow);
+            } else {
+                events.push_back(event);
+            }
+        }
+
+        // Future `read` calls will synchronize with the change that caused this event.
+        this.release_clock(|clock| {
+            inotify.clock.borrow_mut().join(clock);
+        })?;
+
+        // Unblock *all* threads previously blocked on `read`.
+        // We need to take out the blocked thread ids and unblock them together,
+        // because `unblock_threads` may block them again and end up re-adding the
+        // thread to the blocked list.
+        let waiting_threads = std::mem::take(&mut *inotify.blocked_read_tid.borrow_mut());
+        for thread_id in waiting_threads {
+            this.unblock_thread(thread_id, BlockReason::Inotify)?;
+        }
+        this.update_fd_readiness(inotify.clone(), ReadinessUpdateFlags::DEFAULT)
+    }
+
+    /// Queues `self_mask` on all watches of `inotify` whose path is `path`, and `child_mask`
+    /// with the file name of `path` on all watches of its parent directory. Events not in the mask of
+    /// a watch are skipped, and watches with `IN_ONESHOT` are removed after their first event.
+    fn notify_watches(
+        &mut self,
+        inotify: &FileDescriptionRef<Inotify>,
+        path: &Path,
+        self_mask: u32,
+        child_mask: u32,
+        cookie: u32,
+    ) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+        let in_oneshot = this.eval_libc_u32("IN_ONESHOT");
+
+        let parent = path.parent();
+        let name = path.file_name().map(|name| name.to_owned());
+        // Linux reports the event on the parent directory first.
+        let mut queued = Vec::new();
+        let mut oneshot = Vec::new();
+        for watch in inotify.watches.borrow().iter() {
+            if Some(watch.path.as_path()) == parent && child_mask & watch.mask != 0 {
+                queued.push(InotifyEvent {
+                    wd: watch.wd,
+                    mask: child_mask,
+                    cookie,
+                    name: name.clone(),
+                });
+                if watch.mask & in_oneshot != 0 {
+                    oneshot.push(watch.wd);
+                }
+            }
+        }
+        for watch in inotify.watches.borrow().iter() {
+            if watch.path == path && self_mask & watch.mask != 0 {
+                queued.push(InotifyEvent { wd: watch.wd, mask: self_mask, cookie: 0, name: None });
+                if watch.mask & in_oneshot != 0 {
+                    oneshot.push(watch.wd);
+                }
+            }
+        }
+        for event in queued {
+            this.queue_inotify_event(inotify, event)?;
+        }
+        for wd in oneshot {
+            this.remove_inotify_watch(inotify, wd)?;
+        }
+        interp_ok(())
+    }
+
+    /// Removes the watch `wd` and queues `IN_IGNORED` for it. Returns whether the watch existed.
+    fn remove_inotify_watch(
+        &mut self,
+        inotify: &FileDescriptionRef<Inotify>,
+        wd: i32,
+    ) -> InterpResult<'tcx, bool> {
+        let this = self.eval_context_mut();
+        {
+            let mut watches = inotify.watches.borrow_mut();
+            let Some(idx) = watches.iter().position(|watch| watch.wd == wd) else {
+                return interp_ok(false);
+            };
+            watches.remove(idx);
+        }
+        let ignored =
+            InotifyEvent { wd, mask: this.eval_libc_u32("IN_IGNORED"), cookie: 0, name: None };
+        this.queue_inotify_event(inotify, ignored)?;
+        interp_ok(true)
+    }
+
+    /// Removes all watches of `inotify` on `path`, after reporting `IN_DELETE_SELF` for them.
+    fn delete_inotify_watches(
+        &mut self,
+        inotify: &FileDescriptionRef<Inotify>,
+        path: &Path,
+    ) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+        let in_delete_self = this.eval_libc_u32("IN_DELETE_SELF");
+        let deleted = inotify
+            .watches
+            .borrow()
+            .iter()
+            .filter(|watch| watch.path == path)
+            .map(|watch| (watch.wd, watch.mask & in_delete_self != 0))
+            .collect::<Vec<_>>();
+        for (wd, report) in deleted {
+            if report {
+                let event = InotifyEvent { wd, mask: in_delete_self, cookie: 0, name: None };
+                this.queue_inotify_event(inotify, event)?;
+            }
+            this.remove_inotify_watch(inotify, wd)?;
+        }
+        interp_ok(())
+    }
+}
+
+impl<'tcx> EvalContextExt<'tcx> for crate::MiriInterpCx<'tcx> {}
+pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
+    /// This function creates an inotify instance, without any watches. `inotify_init` is the
+    /// same as `inotify_init1` without flags.
+    ///
+    /// The `flags` may be bitwise ORed to change the behavior of `inotify_init1`:
+    /// `IN_CLOEXEC` - Set the close-on-exec (`FD_CLOEXEC`) flag on the new file descriptor.
+    /// `IN_NONBLOCK` - Set the `O_NONBLOCK` file status flag on the new open file description.
+    ///
+    /// <https://man7.org/linux/man-pages/man2/inotify_init.2.html>
+    fn inotify_init1(&mut self, mut flags: i32) -> InterpResult<'tcx, Scalar> {
+        let this = self.eval_context_mut();
+
+        let in_cloexec = this.eval_libc_i32("IN_CLOEXEC");
+        let in_nonblock = this.eval_libc_i32("IN_NONBLOCK");
+
+        let mut is_nonblock = false;
+        if flags & in_cloexec == in_cloexec {
+            // cloexec is ignored because Miri does not support exec.
+            flags &= !in_cloexec;
+        }
+        if flags & in_nonblock == in_nonblock {
+            flags &= !in_nonblock;
+            is_nonblock = true;
+        }
+        if flags != 0 {
+            return this.set_errno_and_return_neg1_i32(LibcError("EINVAL"));
+        }
+
+        let inotify = this.machine.fds.new_ref(Inotify {
+            watches: RefCell::new(Vec::new()),
+            next_wd: Cell::new(1),
+            events: RefCell::new(VecDeque::new()),
+            is_nonblock,
+            clock: RefCell::new(VClock::default()),
+            blocked_read_tid: RefCell::new(Vec::new()),
+            watched: ReadinessWatched::default(),
+        });
+        this.machine.inotify_instances.instances.push(FileDescriptionRef::downgrade(&inotify));
+        let fd_value = this.machine.fds.insert(inotify);
+
+        interp_ok(Scalar::from_i32(fd_value))
+    }
+
+    /// This function adds a watch for the events in `mask` on the file or directory at
+    /// `pathname`, or changes the mask of the existing watch on it. Returns the watch
+    /// descriptor, which identifies the watch in the events read from `fd`.
+    ///
+    /// <https://man7.org/linux/man-pages/man2/inotify_add_watch.2.html>
+    fn inotify_add_watch(
+        &mut self,
+        fd: &OpTy<'tcx>,
+        pathname: &OpTy<'tcx>,
+        mask: &OpTy<'tcx>,
+    ) -> InterpResult<'tcx, Scalar> {
+        let this = self.eval_context_mut();
+
+        let fd = this.read_scalar(fd)?.to_i32()?;
+        let pathname = this.read_pointer(pathname)?;
+        let mask = this.read_scalar(mask)?.to_u32()?;
+
+        let in_all_events = this.eval_libc_u32("IN_ALL_EVENTS");
+        let in_oneshot = this.eval_libc_u32("IN_ONESHOT");
+        let in_onlydir = this.eval_libc_u32("IN_ONLYDIR");
+        let in_dont_follow = this.eval_libc_u32("IN_DONT_FOLLOW");
+        let in_excl_unlink = this.eval_libc_u32("IN_EXCL_UNLINK");
+        let in_mask_add = this.eval_libc_u32("IN_MASK_ADD");
+        let in_mask_create = this.eval_libc_u32("IN_MASK_CREATE");
+
+        // We do not support any other flags.
+        let known = in_all_events
+            | in_oneshot
+            | in_onlydir
+            | in_dont_follow
+            | in_excl_unlink
+            | in_mask_add
+            | in_mask_create;
+        if mask & !known != 0 {
+            throw_unsup_format!("inotify_add_watch: unsupported mask {:#x}", mask & !known);
+        }
+        if mask & in_all_events == 0 || mask & in_mask_add != 0 && mask & in_mask_create != 0 {
+            return this.set_errno_and_return_neg1_i32(LibcError("EINVAL"));
+        }
+
+        let Some(inotify) = this.machine.fds.get(fd) else {
+            return this.set_errno_and_return_neg1_i32(LibcError("EBADF"));
+        };
+        let Some(inotify) = inotify.downcast::<Inotify>() else {
+            return this.set_errno_and_return_neg1_i32(LibcError("EINVAL"));
+        };
+
+        if this.ptr_is_null(pathname)? {
+            return this.set_errno_and_return_neg1_i32(LibcError("EFAULT"));
+        }
+        let path = this.read_path_from_c_str(pathname)?;
+        let is_dir = if let Some(vfs) = &this.machine.vfs {
+            // There are no symlinks in the in-memory file system.
+            vfs.metadata(&path).map(|metadata| metadata.is_dir)
+        } else {
+            // Reject if isolation is enabled.
+            if let IsolatedOp::Reject(reject_with) = this.machine.isolated_op {
+                this.reject_in_isolation("`inotify_add_watch`", reject_with)?;
+                return this.set_errno_and_return_neg1_i32(LibcError("EACCES"));
+            }
+            let metadata = if mask & in_dont_follow != 0 {
+                std::fs::symlink_metadata(&path)
+            } else {
+                std::fs::metadata(&path)
+            };
+            metadata.map(|metadata| metadata.is_dir())
+        };
+        let is_dir = match is_dir {
+            Ok(is_dir) => is_dir,
+            Err(err) => return this.set_errno_and_return_neg1_i32(err),
+        };
+        if mask & in_onlydir != 0 && !is_dir {
+            return this.set_errno_and_return_neg1_i32(LibcError("ENOTDIR"));
+        }
+        let path = normalize(&this.absolute_path(&path));
+
+        // Only the events and `IN_ONESHOT` matter after the watch was added.
+        let new_mask = mask & (in_all_events | in_oneshot);
+        let mut watches = inotify.watches.borrow_mut();
+        if let Some(watch) = watches.iter_mut().find(|watch| watch.path == path) {
+            if mask & in_mask_create != 0 {
+                return this.set_errno_and_return_neg1_i32(LibcError("EEXIST"));
+            }
+            if mask & in_mask_add != 0 {
+                watch.mask |= new_mask;
+            } else {
+                watch.mask = new_mask;
+            }
+            return interp_ok(Scalar::from_i32(watch.wd));
+        }
+        let wd = inotify.next_wd.get();
+        inotify.next_wd.set(wd.strict_add(1));
+        watches.push(Watch { wd, path, mask: new_mask });
+
+        interp_ok(Scalar::from_i32(wd))
+    }
+
+    /// This function removes the watch `wd` from the inotify instance `fd`, which queues an
+    /// `IN_IGNORED` event for it.
+    ///
+    /// <https://man7.org/linux/man-pages/man2/inotify_rm_watch.2.html>
+    fn inotify_rm_watch(&mut self, fd: &OpTy<'tcx>, wd: &OpTy<'tcx>) -> InterpResult<'tcx, Scalar> {
+        let this = self.eval_context_mut();
+
+        let fd = this.read_scalar(fd)?.to_i32()?;
+        let wd = this.read_scalar(wd)?.to_i32()?;
+
+        let Some(inotify) = this.machine.fds.get(fd) else {
+            return this.set_errno_and_return_neg1_i32(LibcError("EBADF"));
+        };
+        let Some(inotify) = inotify.downcast::<Inotify>() else {
+            return this.set_errno_and_return_neg1_i32(LibcError("EINVAL"));
+        };
+        if !this.remove_inotify_watch(&inotify, wd)? {
+            return this.set_errno_and_return_neg1_i32(LibcError("EINVAL"));
+        }
+
+        interp_ok(Scalar::from_i32(0))
+    }
+
+    /// Reports a change made through one of the file system shims to all inotify instances
+    /// that watch the file or its parent directory. Must be called after the change succeeded.
+    fn report_fs_event(&mut self, event: FsEvent<'_>) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+        if this.machine.inotify_instances.instances.is_empty() {
+            return interp_ok(());
+        }
+
+        let in_isdir = this.eval_libc_u32("IN_ISDIR");
+        let isdir = |is_dir: bool| if is_dir { in_isdir } else { 0 };
+        // The masks for the watches on the file itself, and on its parent directory.
+        let (path, self_mask, child_mask) = match event {
+            FsEvent::Create { path, is_dir } =>
+                (path, 0, this.eval_libc_u32("IN_CREATE") | isdir(is_dir)),
+            FsEvent::Modify(path) => {
+                let in_modify = this.eval_libc_u32("IN_MODIFY");
+                (path, in_modify, in_modify)
+            }
+            FsEvent::Attrib(path) => {
+                let in_attrib = this.eval_libc_u32("IN_ATTRIB");
+                (path, in_attrib, in_attrib)
+            }
+            FsEvent::Delete { path, is_dir } =>
+                (path, 0, this.eval_libc_u32("IN_DELETE") | isdir(is_dir)),
+            // Renaming a file to itself does nothing.
+            FsEvent::Rename { from, to, .. } if from == to => return interp_ok(()),
+            FsEvent::Rename { from, to, is_dir } => {
+                let from = normalize(from);
+                let to = normalize(to);
+                let in_move_self = this.eval_libc_u32("IN_MOVE_SELF");
+                let in_moved_from = this.eval_libc_u32("IN_MOVED_FROM") | isdir(is_dir);
+                let in_moved_to = this.eval_libc_u32("IN_MOVED_TO") | isdir(is_dir);
+                let instances = &mut this.machine.inotify_instances;
+                instances.last_cookie = instances.last_cookie.wrapping_add(1).max(1);
+                let cookie = instances.last_cookie;
+                for inotify in this.inotify_instances() {
+                    this.notify_watches(&inotify, &from, 0, in_moved_from, cookie)?;
+                    this.notify_watches(&inotify, &to, 0, in_moved_to, cookie)?;
+                    // A file that got replaced by the rename is gone.
+                    this.delete_inotify_watches(&inotify, &to)?;
+                    // The watches on the moved file, and on everything inside a moved directory,
+                    // follow it to its new path.
+                    let mut moved_self = false;
+                    for watch in inotify.watches.borrow_mut().iter_mut() {
+                        if let Ok(rest) = watch.path.strip_prefix(&from) {
+                            moved_self |= rest.as_os_str().is_empty();
+                            watch.path = to.join(rest);
+                        }
+                    }
+                    if moved_self {
+                        this.notify_watches(&inotify, &to, in_move_self, 0, 0)?;
+                    }
+                }
+                return interp_ok(());
+            }
+        };
+        let path = normalize(path);
+        for inotify in this.inotify_instances() {
+            this.notify_watches(&inotify, &path, self_mask, child_mask, 0)?;
+            if matches!(event, FsEvent::Delete { .. }) {
+                this.delete_inotify_watches(&inotify, &path)?;
+            }
+        }
+        interp_ok(())
+    }
+}
+
+/// Block thread if there are no events, else read as many events as fit into the buffer.
+fn inotify_read<'tcx>(
+    ptr: Pointer,
+    len: usize,
+    inotify: FileDescriptionRef<Inotify>,
+    ecx: &mut MiriInterpCx<'tcx>,
+    finish: DynMachineCallback<'tcx, Result<usize, IoError>>,
+) -> InterpResult<'tcx> {
+    if inotify.events.borrow().is_empty() {
+        if inotify.is_nonblock {
+            return finish.call(ecx, Err(ErrorKind::WouldBlock.into()));
+        }
+
+        inotify.blocked_read_tid.borrow_mut().push(ecx.active_thread());
+
+        ecx.block_thread(
+            BlockReason::Inotify,
+            None,
+            callback!(
+                @capture<'tcx> {
+                    ptr: Pointer,
+                    len: usize,
+                    finish: DynMachineCallback<'tcx, Result<usize, IoError>>,
+                    inotify: FileDescriptionRef<Inotify>,
+                }
+                |this, unblock: UnblockKind| {
+                    assert_eq!(unblock, UnblockKind::Ready);
+                    inotify_read(ptr, len, inotify, this, finish)
+                }
+            ),
+        );
+        return interp_ok(());
+    }
+
+    let layout = ecx.libc_ty_layout("inotify_event");
+    let header_size = layout.size.bytes_usize();
+    let mut written = 0usize;
+    loop {
+        let Some(event) = inotify.events.borrow_mut().pop_front() else { break };
+        // The name is null-terminated and padded to a multiple of the header size.
+        let name = event.name.as_ref().map(|name| name.as_encoded_bytes()).unwrap_or_default();
+        let name_len = if event.name.is_some() {
+            name.len().strict_add(1).next_multiple_of(header_size)
+        } else {
+            0
+        };
+        let size = header_size.strict_add(name_len);
+        if written.strict_add(size) > len {
+            inotify.events.borrow_mut().push_front(event);
+            break;
+        }
+
+        let header = ecx
+            .ptr_to_mplace_unaligned(ptr.wrapping_offset(Size::from_bytes(written), ecx), layout);
+        ecx.write_int_fields_named(
+            &[
+                ("wd", event.wd.into()),
+                ("mask", event.mask.into()),
+                ("cookie", event.cookie.into()),
+                ("len", name_len.try_into().unwrap()),
+            ],
+            &header,
+        )?;
+        let name_ptr = ptr.wrapping_offset(Size::from_bytes(written.strict_add(header_size)), ecx);
+        ecx.write_bytes_ptr(
+            name_ptr,
+            name.iter().copied().chain(std::iter::repeat(0)).take(name_len),
+        )?;
+        written = written.strict_add(size);
+    }
+
+    // The buffer must fit at least the first event.
+    if written == 0 {
+        return finish.call(ecx, Err(ErrorKind::InvalidInput.into()));
+    }
+
+    // Synchronize with the changes that caused the events.
+    ecx.acquire_clock(&inotify.clock.borrow())?;
+    if inotify.events.borrow().is_empty() {
+        ecx.update_fd_readiness(inotify, ReadinessUpdateFlags::DEFAULT)?;
+    }
+
+    // Tell userspace how many bytes we put into the buffer.
+    finish.call(ecx, Ok(written))
+}
@@ -1,5 +1,9 @@
+pub mod copy;
 pub mod epoll;
 pub mod eventfd;
+pub mod inotify;
+pub mod signalfd;
 pub mod sync;
 pub mod syscall;
 pub mod thread;
+pub mod timerfd;
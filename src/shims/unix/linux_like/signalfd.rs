@@ -0,0 +1,252 @@
+//! Linux `signalfd` implementation.
+use std::cell::{Cell, RefCell};
+use std::io;
+use std::io::ErrorKind;
+
+use crate::shims::files::{FileDescription, FileDescriptionRef, WeakFileDescriptionRef};
+use crate::shims::unix::signal::SigSet;
+use crate::shims::unix::{SignalEvalContextExt as _, UnixFileDescription};
+use crate::*;
+
+/// A file descriptor created by `signalfd`, from which the program can read the signals in
+/// `mask` instead of having them delivered. The program has to block those signals for that to
+/// work, otherwise they get delivered as usual.
+///
+/// On Linux, a signalfd is readable when a signal in its mask is pending for the thread that
+/// polls it. Readiness in Miri does not depend on the polling thread, so we consider a signalfd
+/// readable when such a signal is pending for any thread. A `read` only accepts signals that
+/// are pending for the reading thread or for the whole process.
+///
+/// <https://man7.org/linux/man-pages/man2/signalfd.2.html>
+#[derive(Debug)]
+struct SignalFd {
+    /// The signals that can be read from this file descriptor.
+    mask: Cell<SigSet>,
+    is_nonblock: bool,
+    /// Whether a signal in `mask` is pending. Kept up to date by `update_signalfds`.
+    readable: Cell<bool>,
+    /// A list of thread ids blocked on reading from this file descriptor.
+    blocked_read_tid: RefCell<Vec<ThreadId>>,
+    /// State for being watched by epoll.
+    watched: ReadinessWatched,
+}
+
+impl FileDescription for SignalFd {
+    fn name(&self) -> &'static str {
+        "signal"
+    }
+
+    fn metadata<'tcx>(
+        &self,
+    ) -> InterpResult<'tcx, Either<io::Result<shims::HandleMetadata>, &'static str>> {
+        // Like eventfd, a signalfd is an "anonymous inode" reported as S_IFREG.
+        interp_ok(Either::Right("S_IFREG"))
+    }
+
+    /// Reads as many `signalfd_siginfo` structures as fit into the buffer, one for each accepted
+    /// signal. If no signal in the mask is pending, this blocks until one is, or fails with
+    /// `EAGAIN` if the file descriptor is nonblocking.
+    fn read<'tcx>(
+        self: FileDescriptionRef<Self>,
+        _communicate_allowed: bool,
+        ptr: Pointer,
+        len: usize,
+        ecx: &mut MiriInterpCx<'tcx>,
+        finish: DynMachineCallback<'tcx, Result<usize, IoError>>,
+    ) -> InterpResult<'tcx> {
+        let size = ecx.libc_ty_layout("signalfd_siginfo").size.bytes_usize();
+        if len < size {
+            return finish.call(ecx, Err(ErrorKind::InvalidInput.into()));
+        }
+
+        signalfd_read(ptr, len.strict_div(size), self, ecx, finish)
+    }
+
+    fn readiness_watched(&self) -> Option<&ReadinessWatched> {
+        Some(&self.watched)
+    }
+
+    fn readiness(&self) -> Readiness {
+        Readiness { readable: self.readable.get(), ..Readiness::EMPTY }
+    }
+
+    fn as_unix<'tcx>(
+        self: FileDescriptionRef<Self>,
+        _ecx: &MiriInterpCx<'tcx>,
+    ) -> FileDescriptionRef<dyn UnixFileDescription> {
+        self
+    }
+}
+
+impl UnixFileDescription for SignalFd {}
+
+/// All open signalfds, which need to be notified when the set of pending signals changes.
+/// Closed signalfds are removed lazily.
+#[derive(Debug, Default)]
+pub struct SignalFds(Vec<WeakFileDescriptionRef<SignalFd>>);
+
+impl<'tcx> EvalContextExt<'tcx> for crate::MiriInterpCx<'tcx> {}
+pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
+    /// This function creates a file descriptor from which the signals in `mask` can be read, or
+    /// changes the mask of the existing signalfd `fd` if it is not -1. `SIGKILL` and `SIGSTOP`
+    /// are silently removed from the mask.
+    ///
+    /// The `flags` may be bitwise ORed to change the behavior of `signalfd`:
+    /// `SFD_CLOEXEC` - Set the close-on-exec (`FD_CLOEXEC`) flag on the new file descriptor.
+    /// `SFD_NONBLOCK` - Set the `O_NONBLOCK` file status flag on the new open file description.
+    ///
+    /// <https://man7.org/linux/man-pages/man2/signalfd.2.html>
+    fn signalfd(
+        &mut self,
+        fd: &OpTy<'tcx>,
+        mask: &OpTy<'tcx>,
+        flags: &OpTy<'tcx>,
+    ) -> InterpResult<'tcx, Scalar> {
+        let this = self.eval_context_mut();
+
+        let fd = this.read_scalar(fd)?.to_i32()?;
+        let mask = this.read_pointer(mask)?;
+        let mut flags = this.read_scalar(flags)?.to_i32()?;
+
+        let sfd_cloexec = this.eval_libc_i32("SFD_CLOEXEC");
+        let sfd_nonblock = this.eval_libc_i32("SFD_NONBLOCK");
+
+        let mut is_nonblock = false;
+        if flags & sfd_cloexec == sfd_cloexec {
+            // cloexec is ignored because Miri does not support exec.
+            flags &= !sfd_cloexec;
+        }
+        if flags & sfd_nonblock == sfd_nonblock {
+            flags &= !sfd_nonblock;
+            is_nonblock = true;
+        }
+        if flags != 0 {
+            return this.set_errno_and_return_neg1_i32(LibcError("EINVAL"));
+        }
+        let mask = this.deref_blockable_sigset(mask)?;
+
+        let fd_value = if fd == -1 {
+            let signalfd = this.machine.fds.new_ref(SignalFd {
+                mask: Cell::new(mask),
+                is_nonblock,
+                readable: Cell::new(false),
+                blocked_read_tid: RefCell::new(Vec::new()),
+                watched: ReadinessWatched::default(),
+            });
+            this.machine.signalfds.0.push(FileDescriptionRef::downgrade(&signalfd));
+            this.machine.fds.insert(signalfd)
+        } else {
+            // The flags only apply to new file descriptors.
+            let Some(signalfd) = this.machine.fds.get(fd) else {
+                return this.set_errno_and_return_neg1_i32(LibcError("EBADF"));
+            };
+            let Some(signalfd) = signalfd.downcast::<SignalFd>() else {
+                return this.set_errno_and_return_neg1_i32(LibcError("EINVAL"));
+            };
+            signalfd.mask.set(mask);
+            fd
+        };
+        // Signals in the new mask may already be pending.
+        this.update_signalfds()?;
+
+        interp_ok(Scalar::from_i32(fd_value))
+    }
+
+    /// Updates the readiness of all signalfds, and wakes up the threads blocked on reading from
+    /// those that became readable. Must be called whenever the set of pending signals changes.
+    fn update_signalfds(&mut self) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+        if this.machine.signalfds.0.is_empty() {
+            return interp_ok(());
+        }
+
+        let pending = this.machine.signals.all_pending();
+        let signalfds = std::mem::take(&mut this.machine.signalfds.0);
+        let mut open = Vec::with_capacity(signalfds.len());
+        for signalfd in signalfds {
+            let Some(signalfd) = signalfd.upgrade() else { continue };
+            open.push(FileDescriptionRef::downgrade(&signalfd));
+            let readable = pending & signalfd.mask.get() != 0;
+            if readable {
+                // Unblock *all* threads previously blocked on `read`.
+                // We need to take out the blocked thread ids and unblock them together,
+                // because `unblock_threads` may block them again and end up re-adding the
+                // thread to the blocked list.
+                let waiting_threads = std::mem::take(&mut *signalfd.blocked_read_tid.borrow_mut());
+                for thread_id in waiting_threads {
+                    this.unblock_thread(thread_id, BlockReason::SignalFd)?;
+                }
+            }
+            if signalfd.readable.replace(readable) != readable {
+                this.update_fd_readiness(signalfd, ReadinessUpdateFlags::DEFAULT)?;
+            }
+        }
+        // Waking up threads may have created further signalfds, so we must not overwrite the list.
+        this.machine.signalfds.0.extend(open);
+
+        interp_ok(())
+    }
+}
+
+/// Block thread if no signal in the mask is pending for it,
+/// else accept as many of those signals as fit into the buffer.
+fn signalfd_read<'tcx>(
+    ptr: Pointer,
+    count: usize,
+    signalfd: FileDescriptionRef<SignalFd>,
+    ecx: &mut MiriInterpCx<'tcx>,
+    finish: DynMachineCallback<'tcx, Result<usize, IoError>>,
+) -> InterpResult<'tcx> {
+    let layout = ecx.libc_ty_layout("signalfd_siginfo");
+    let mut accepted = 0;
+    while accepted < count
+        && let Some(signum) = ecx.accept_pending_signal(signalfd.mask.get())?
+    {
+        let offset = layout.size.checked_mul(u64::try_from(accepted).unwrap(), ecx).unwrap();
+        let info = ecx.ptr_to_mplace_unaligned(ptr.wrapping_offset(offset, ecx), layout);
+        ecx.write_bytes_ptr(
+            info.ptr(),
+            std::iter::repeat_n(0, usize::try_from(layout.size.bytes()).unwrap()),
+        )?;
+        // All signals are sent by this process, so the code is `SI_USER`.
+        ecx.write_int_fields_named(
+            &[
+                ("ssi_signo", signum.into()),
+                ("ssi_code", 0),
+                ("ssi_pid", ecx.get_pid().into()),
+                ("ssi_uid", crate::shims::unix::UID.into()),
+            ],
+            &info,
+        )?;
+        accepted = accepted.strict_add(1);
+    }
+
+    if accepted == 0 {
+        if signalfd.is_nonblock {
+            return finish.call(ecx, Err(ErrorKind::WouldBlock.into()));
+        }
+
+        signalfd.blocked_read_tid.borrow_mut().push(ecx.active_thread());
+
+        ecx.block_thread(
+            BlockReason::SignalFd,
+            None,
+            callback!(
+                @capture<'tcx> {
+                    ptr: Pointer,
+                    count: usize,
+                    finish: DynMachineCallback<'tcx, Result<usize, IoError>>,
+                    signalfd: FileDescriptionRef<SignalFd>,
+                }
+                |this, unblock: UnblockKind| {
+                    assert_eq!(unblock, UnblockKind::Ready);
+                    signalfd_read(ptr, count, signalfd, this, finish)
+                }
+            ),
+        );
+        return interp_ok(());
+    }
+
+    // Tell userspace how many bytes we put into the buffer.
+    finish.call(ecx, Ok(layout.size.bytes_usize().strict_mul(accepted)))
+}
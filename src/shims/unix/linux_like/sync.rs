@@ -152,6 +152,7 @@ pub fn futex<'tcx>(
 
                 let dest = dest.clone();
                 ecx.futex_wait(
+                    addr.ptr(),
                     futex_ref,
                     bitset,
                     deadline,
@@ -211,7 +212,7 @@ pub fn futex<'tcx>(
             // will see the latest value on addr which could be changed by our caller
             // before doing the syscall.
             ecx.atomic_fence(AtomicFenceOrd::SeqCst)?;
-            let woken = ecx.futex_wake(&futex_ref, bitset, val.try_into().unwrap())?;
+            let woken = ecx.futex_wake(addr.ptr(), &futex_ref, bitset, val.try_into().unwrap())?;
             ecx.write_scalar(Scalar::from_target_isize(woken.try_into().unwrap(), ecx), dest)?;
         }
         op => throw_unsup_format!("Miri does not support `futex` syscall with op={}", op),
@@ -0,0 +1,438 @@
+//! Linux `timerfd` implementation.
+use std::cell::{Cell, RefCell};
+use std::io;
+use std::io::ErrorKind;
+use std::time::{Duration, SystemTime};
+
+use crate::concurrency::VClock;
+use crate::shims::files::{FileDescription, FileDescriptionRef, WeakFileDescriptionRef};
+use crate::shims::time::system_time_to_duration;
+use crate::shims::unix::UnixFileDescription;
+use crate::*;
+
+/// A timer that notifies expirations via a file descriptor, created by `timerfd_create`.
+///
+/// Timers are not fired by a thread of their own: whenever the scheduler runs, it accounts for
+/// all expirations of armed timers that happened since the last time it checked (see
+/// [`EvalContextExt::fire_expired_timerfds`]). Reading from a timer also brings it up to date.
+///
+/// <https://man7.org/linux/man-pages/man2/timerfd_create.2.html>
+#[derive(Debug)]
+struct TimerFd {
+    /// The clock this timer measures time against.
+    clock: TimeoutClock,
+    /// The next expiration as a duration since the epoch of `clock`, or `None` if the timer is
+    /// disarmed.
+    deadline: Cell<Option<Duration>>,
+    /// The period of the timer after its first expiration. Zero for a one-shot timer.
+    interval: Cell<Duration>,
+    /// The number of expirations since the timer was last set or read.
+    expirations: Cell<u64>,
+    is_nonblock: bool,
+    /// Reads synchronize with the `timerfd_settime` that armed the timer.
+    sync_clock: RefCell<VClock>,
+    /// A list of thread ids blocked on reading the timer.
+    blocked_read_tid: RefCell<Vec<ThreadId>>,
+    /// State for being watched by epoll.
+    watched: ReadinessWatched,
+}
+
+impl TimerFd {
+    /// Accounts for all expirations up to `now`, and advances the deadline past `now` for
+    /// periodic timers. Returns whether the timer expired at least once.
+    fn expire(&self, now: Duration) -> bool {
+        let Some(deadline) = self.deadline.get() else { return false };
+        if now < deadline {
+            return false;
+        }
+        let interval = self.interval.get().as_nanos();
+        let count = if interval == 0 {
+            self.deadline.set(None);
+            1
+        } else {
+            // The timer expires at `deadline`, and once more for every full interval after that.
+            let overdue = now.saturating_sub(deadline).as_nanos();
+            let count = overdue.strict_div(interval).strict_add(1);
+            let next = deadline.as_nanos().strict_add(count.strict_mul(interval));
+            self.deadline.set(Some(Duration::from_nanos_u128(next)));
+            count
+        };
+        let count = u64::try_from(count).unwrap_or(u64::MAX);
+        self.expirations.set(self.expirations.get().saturating_add(count));
+        true
+    }
+}
+
+impl FileDescription for TimerFd {
+    fn name(&self) -> &'static str {
+        "timer"
+    }
+
+    fn metadata<'tcx>(
+        &self,
+    ) -> InterpResult<'tcx, Either<io::Result<shims::HandleMetadata>, &'static str>> {
+        // Like eventfd, a timerfd is an "anonymous inode" reported as S_IFREG.
+        interp_ok(Either::Right("S_IFREG"))
+    }
+
+    /// Reads the number of expirations since the timer was last set or read as a `u64`, and
+    /// resets it to zero. If there were none, this blocks until the timer expires, or fails with
+    /// `EAGAIN` if the file descriptor is nonblocking.
+    fn read<'tcx>(
+        self: FileDescriptionRef<Self>,
+        _communicate_allowed: bool,
+        ptr: Pointer,
+        len: usize,
+        ecx: &mut MiriInterpCx<'tcx>,
+        finish: DynMachineCallback<'tcx, Result<usize, IoError>>,
+    ) -> InterpResult<'tcx> {
+        // We're treating the buffer as a `u64`.
+        let ty = ecx.machine.layouts.u64;
+        if len < ty.size.bytes_usize() {
+            return finish.call(ecx, Err(ErrorKind::InvalidInput.into()));
+        }
+
+        // Turn the pointer into a place at the right type.
+        let buf_place = ecx.ptr_to_mplace_unaligned(ptr, ty);
+
+        timerfd_read(buf_place, self, ecx, finish)
+    }
+
+    fn readiness_watched(&self) -> Option<&ReadinessWatched> {
+        Some(&self.watched)
+    }
+
+    fn readiness(&self) -> Readiness {
+        Readiness { readable: self.expirations.get() != 0, ..Readiness::EMPTY }
+    }
+
+    fn as_unix<'tcx>(
+        self: FileDescriptionRef<Self>,
+        _ecx: &MiriInterpCx<'tcx>,
+    ) -> FileDescriptionRef<dyn UnixFileDescription> {
+        self
+    }
+}
+
+impl UnixFileDescription for TimerFd {}
+
+/// The timers that may currently be armed, so that the scheduler can fire them. Timers that were
+/// closed or disarmed are removed lazily.
+#[derive(Debug, Default)]
+pub struct ArmedTimerFds(Vec<WeakFileDescriptionRef<TimerFd>>);
+
+impl<'tcx> EvalContextExtPrivate<'tcx> for crate::MiriInterpCx<'tcx> {}
+trait EvalContextExtPrivate<'tcx>: crate::MiriInterpCxExt<'tcx> {
+    /// The current time of `clock`, as a duration since its epoch.
+    fn timerfd_now(&self, clock: TimeoutClock) -> InterpResult<'tcx, Duration> {
+        let this = self.eval_context_ref();
+        match clock {
+            TimeoutClock::Monotonic =>
+                interp_ok(
+                    this.machine
+                        .monotonic_clock
+                        .now()
+                        .duration_since(this.machine.monotonic_clock.epoch()),
+                ),
+            TimeoutClock::RealTime => system_time_to_duration(&SystemTime::now()),
+        }
+    }
+
+    /// Brings `timer` up to date, and wakes up everyone waiting for it if it expired.
+    fn update_timerfd(&mut self, timer: &FileDescriptionRef<TimerFd>) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+        let now = this.timerfd_now(timer.clock)?;
+        if !timer.expire(now) {
+            return interp_ok(());
+        }
+
+        // Unblock *all* threads previously blocked on `read`.
+        // We need to take out the blocked thread ids and unblock them together,
+        // because `unblock_threads` may block them again and end up re-adding the
+        // thread to the blocked list.
+        let waiting_threads = std::mem::take(&mut *timer.blocked_read_tid.borrow_mut());
+        for thread_id in waiting_threads {
+            this.unblock_thread(thread_id, BlockReason::TimerFd)?;
+        }
+        // The expiration is not an action of whichever thread happens to be active.
+        this.update_fd_readiness(timer.clone(), ReadinessUpdateFlags::NO_RELEASE_CLOCK)
+    }
+
+    /// Reads an `itimerspec` as its interval and initial expiration. Returns `None` if either of
+    /// them is not a valid `timespec`.
+    fn read_itimerspec(&self, ptr: Pointer) -> InterpResult<'tcx, Option<(Duration, Duration)>> {
+        let this = self.eval_context_ref();
+        let spec = this.ptr_to_mplace(ptr, this.libc_ty_layout("itimerspec"));
+        let interval = this.read_timespec(&this.project_field_named(&spec, "it_interval")?)?;
+        let value = this.read_timespec(&this.project_field_named(&spec, "it_value")?)?;
+        interp_ok(interval.zip(value))
+    }
+
+    /// Writes the current setting of `timer` as an `itimerspec`: its interval, and the time
+    /// until its next expiration.
+    fn write_itimerspec(
+        &mut self,
+        timer: &TimerFd,
+        now: Duration,
+        ptr: Pointer,
+    ) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+        let spec = this.ptr_to_mplace(ptr, this.libc_ty_layout("itimerspec"));
+        let remaining = timer.deadline.get().map_or(Duration::ZERO, |d| d.saturating_sub(now));
+        for (field, duration) in [("it_interval", timer.interval.get()), ("it_value", remaining)] {
+            let timespec = this.project_field_named(&spec, field)?;
+            this.write_int_fields(
+                &[duration.as_secs().into(), duration.subsec_nanos().into()],
+                &timespec,
+            )?;
+        }
+        interp_ok(())
+    }
+}
+
+impl<'tcx> EvalContextExt<'tcx> for crate::MiriInterpCx<'tcx> {}
+pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
+    /// This function creates a disarmed timer that measures time against `clockid`, and returns
+    /// a file descriptor referring to it.
+    ///
+    /// The `flags` may be bitwise ORed to change the behavior of `timerfd_create`:
+    /// `TFD_CLOEXEC` - Set the close-on-exec (`FD_CLOEXEC`) flag on the new file descriptor.
+    /// `TFD_NONBLOCK` - Set the `O_NONBLOCK` file status flag on the new open file description.
+    ///
+    /// <https://man7.org/linux/man-pages/man2/timerfd_create.2.html>
+    fn timerfd_create(
+        &mut self,
+        clockid: &OpTy<'tcx>,
+        flags: &OpTy<'tcx>,
+    ) -> InterpResult<'tcx, Scalar> {
+        let this = self.eval_context_mut();
+
+        let clockid = this.read_scalar(clockid)?;
+        let mut flags = this.read_scalar(flags)?.to_i32()?;
+
+        let Some(clock) = this.parse_clockid(clockid) else {
+            return this.set_errno_and_return_neg1_i32(LibcError("EINVAL"));
+        };
+        if clock == TimeoutClock::RealTime {
+            this.check_no_isolation("`timerfd_create` with `CLOCK_REALTIME`")?;
+        }
+
+        let tfd_cloexec = this.eval_libc_i32("TFD_CLOEXEC");
+        let tfd_nonblock = this.eval_libc_i32("TFD_NONBLOCK");
+
+        let mut is_nonblock = false;
+        if flags & tfd_cloexec == tfd_cloexec {
+            // cloexec is ignored because Miri does not support exec.
+            flags &= !tfd_cloexec;
+        }
+        if flags & tfd_nonblock == tfd_nonblock {
+            flags &= !tfd_nonblock;
+            is_nonblock = true;
+        }
+        if flags != 0 {
+            return this.set_errno_and_return_neg1_i32(LibcError("EINVAL"));
+        }
+
+        let fd_value = this.machine.fds.insert_new(TimerFd {
+            clock,
+            deadline: Cell::new(None),
+            interval: Cell::new(Duration::ZERO),
+            expirations: Cell::new(0),
+            is_nonblock,
+            sync_clock: RefCell::new(VClock::default()),
+            blocked_read_tid: RefCell::new(Vec::new()),
+            watched: ReadinessWatched::default(),
+        });
+
+        interp_ok(Scalar::from_i32(fd_value))
+    }
+
+    /// Arms or disarms the timer referred to by `fd`. A zero `it_value` in `new_value` disarms
+    /// the timer; otherwise it expires after `it_value` (or at `it_value` if `flags` contains
+    /// `TFD_TIMER_ABSTIME`), and then every `it_interval`. Setting a timer resets its
+    /// expiration count. If `old_value` is not null, the previous setting is written to it as by
+    /// `timerfd_gettime`.
+    ///
+    /// <https://man7.org/linux/man-pages/man2/timerfd_settime.2.html>
+    fn timerfd_settime(
+        &mut self,
+        fd: &OpTy<'tcx>,
+        flags: &OpTy<'tcx>,
+        new_value: &OpTy<'tcx>,
+        old_value: &OpTy<'tcx>,
+    ) -> InterpResult<'tcx, Scalar> {
+        let this = self.eval_context_mut();
+
+        let fd = this.read_scalar(fd)?.to_i32()?;
+        let flags = this.read_scalar(flags)?.to_i32()?;
+        let new_value = this.read_pointer(new_value)?;
+        let old_value = this.read_pointer(old_value)?;
+
+        let Some(timer) = this.machine.fds.get(fd) else {
+            return this.set_errno_and_return_neg1_i32(LibcError("EBADF"));
+        };
+        let Some(timer) = timer.downcast::<TimerFd>() else {
+            return this.set_errno_and_return_neg1_i32(LibcError("EINVAL"));
+        };
+
+        let tfd_timer_abstime = this.eval_libc_i32("TFD_TIMER_ABSTIME");
+        let tfd_timer_cancel_on_set = this.eval_libc_i32("TFD_TIMER_CANCEL_ON_SET");
+        if flags & tfd_timer_cancel_on_set != 0 {
+            throw_unsup_format!("timerfd_settime: TFD_TIMER_CANCEL_ON_SET is unsupported");
+        }
+        if flags & !tfd_timer_abstime != 0 {
+            return this.set_errno_and_return_neg1_i32(LibcError("EINVAL"));
+        }
+        let Some((interval, value)) = this.read_itimerspec(new_value)? else {
+            return this.set_errno_and_return_neg1_i32(LibcError("EINVAL"));
+        };
+
+        // Account for expirations up to now, so that the old value is accurate.
+        this.update_timerfd(&timer)?;
+        let now = this.timerfd_now(timer.clock)?;
+        if !this.ptr_is_null(old_value)? {
+            this.write_itimerspec(&timer, now, old_value)?;
+        }
+
+        let deadline = if value.is_zero() {
+            None
+        } else if flags & tfd_timer_abstime != 0 {
+            Some(value)
+        } else {
+            Some(now.saturating_add(value))
+        };
+        timer.deadline.set(deadline);
+        timer.interval.set(interval);
+        timer.expirations.set(0);
+
+        // Future `read` calls will synchronize with this call.
+        this.release_clock(|clock| {
+            timer.sync_clock.borrow_mut().join(clock);
+        })?;
+        if deadline.is_some() {
+            this.machine.armed_timerfds.0.push(FileDescriptionRef::downgrade(&timer));
+        }
+        // An absolute deadline may already be in the past.
+        this.update_timerfd(&timer)?;
+        this.update_fd_readiness(timer, ReadinessUpdateFlags::DEFAULT)?;
+
+        interp_ok(Scalar::from_i32(0))
+    }
+
+    /// Writes the interval of the timer referred to by `fd` and the time until its next
+    /// expiration to `curr_value`. Both are zero for a disarmed timer.
+    ///
+    /// <https://man7.org/linux/man-pages/man2/timerfd_gettime.2.html>
+    fn timerfd_gettime(
+        &mut self,
+        fd: &OpTy<'tcx>,
+        curr_value: &OpTy<'tcx>,
+    ) -> InterpResult<'tcx, Scalar> {
+        let this = self.eval_context_mut();
+
+        let fd = this.read_scalar(fd)?.to_i32()?;
+        let curr_value = this.read_pointer(curr_value)?;
+
+        let Some(timer) = this.machine.fds.get(fd) else {
+            return this.set_errno_and_return_neg1_i32(LibcError("EBADF"));
+        };
+        let Some(timer) = timer.downcast::<TimerFd>() else {
+            return this.set_errno_and_return_neg1_i32(LibcError("EINVAL"));
+        };
+
+        this.update_timerfd(&timer)?;
+        let now = this.timerfd_now(timer.clock)?;
+        this.write_itimerspec(&timer, now, curr_value)?;
+
+        interp_ok(Scalar::from_i32(0))
+    }
+
+    /// Accounts for the expirations of all armed timers, and wakes up everyone waiting for them.
+    /// Called by the scheduler.
+    ///
+    /// This method returns the minimum duration until the next expiration of an armed timer that
+    /// a blocked thread is waiting for. If there is no such timer, [`None`] is returned: other
+    /// timers must not keep a deadlocked program alive.
+    fn fire_expired_timerfds(&mut self) -> InterpResult<'tcx, Option<Duration>> {
+        let this = self.eval_context_mut();
+        if this.machine.armed_timerfds.0.is_empty() {
+            return interp_ok(None);
+        }
+
+        let timers = std::mem::take(&mut this.machine.armed_timerfds.0);
+        let mut armed: Vec<FileDescriptionRef<TimerFd>> = Vec::new();
+        let mut min_wait_time = Option::<Duration>::None;
+        for timer in timers {
+            let Some(timer) = timer.upgrade() else { continue };
+            // A timer that was set multiple times may be in the list more than once.
+            if armed.iter().any(|other| other.id() == timer.id()) {
+                continue;
+            }
+            this.update_timerfd(&timer)?;
+            if let Some(deadline) = timer.deadline.get() {
+                if !timer.blocked_read_tid.borrow().is_empty()
+                    || timer.watched.has_watcher_with_blocked_thread()
+                {
+                    let wait_time = deadline.saturating_sub(this.timerfd_now(timer.clock)?);
+                    min_wait_time = Some(wait_time.min(min_wait_time.unwrap_or(Duration::MAX)));
+                }
+                armed.push(timer);
+            }
+        }
+        // Waking up threads may have armed further timers, so we must not overwrite the list.
+        this.machine.armed_timerfds.0.extend(armed.iter().map(FileDescriptionRef::downgrade));
+
+        interp_ok(min_wait_time)
+    }
+}
+
+/// Block thread if the timer has not expired since it was last read,
+/// else return the number of expirations to the caller and reset it to 0.
+fn timerfd_read<'tcx>(
+    buf_place: MPlaceTy<'tcx>,
+    timer: FileDescriptionRef<TimerFd>,
+    ecx: &mut MiriInterpCx<'tcx>,
+    finish: DynMachineCallback<'tcx, Result<usize, IoError>>,
+) -> InterpResult<'tcx> {
+    ecx.update_timerfd(&timer)?;
+    let expirations = timer.expirations.replace(0);
+
+    if expirations == 0 {
+        if timer.is_nonblock {
+            return finish.call(ecx, Err(ErrorKind::WouldBlock.into()));
+        }
+
+        // If the timer is disarmed, this blocks until another thread arms it. The scheduler
+        // wakes us up once the timer expires.
+        timer.blocked_read_tid.borrow_mut().push(ecx.active_thread());
+
+        ecx.block_thread(
+            BlockReason::TimerFd,
+            None,
+            callback!(
+                @capture<'tcx> {
+                    buf_place: MPlaceTy<'tcx>,
+                    finish: DynMachineCallback<'tcx, Result<usize, IoError>>,
+                    timer: FileDescriptionRef<TimerFd>,
+                }
+                |this, unblock: UnblockKind| {
+                    assert_eq!(unblock, UnblockKind::Ready);
+                    timerfd_read(buf_place, timer, this, finish)
+                }
+            ),
+        );
+        return interp_ok(());
+    }
+
+    // Synchronize with the `timerfd_settime` call that armed the timer.
+    ecx.acquire_clock(&timer.sync_clock.borrow())?;
+
+    // Return the number of expirations into user-space buffer.
+    ecx.write_int(expirations, &buf_place)?;
+
+    // The timer is no longer readable.
+    ecx.update_fd_readiness(timer, ReadinessUpdateFlags::DEFAULT)?;
+
+    // Tell userspace how many bytes we put into the buffer.
+    finish.call(ecx, Ok(buf_place.layout.size.bytes_usize()))
+}
@@ -0,0 +1,487 @@
+//! This implements TCP sockets for `-Zmiri-virtual-network`: instead of going to the host, they
+//! are connected through a loopback network that only exists inside Miri. Only loopback (and
+//! unspecified) addresses can be used. Connecting a socket turns it into one end of a virtual
+//! socketpair whose other end is handed out by `accept`; the buffer sizes of that socketpair and
+//! the amount of data each read returns are chosen by Miri's random number generator, so every
+//! seed explores a different, but reproducible, way for the data to arrive.
+
+use std::cell::{Cell, OnceCell};
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::rc::Rc;
+
+use rustc_data_structures::fx::FxHashMap;
+use rustc_target::spec::Os;
+
+use crate::shims::files::{FdNum, FileDescription, FileDescriptionRef, WeakFileDescriptionRef};
+use crate::shims::unix::UnixFileDescription;
+use crate::shims::unix::socket::{EvalContextExt as _, SocketFamily, UnixSocketFileDescription};
+use crate::shims::unix::socket_address::SocketAddress;
+use crate::shims::unix::virtual_socket::{Delivery, EvalContextExt as _, Listener};
+use crate::*;
+
+/// The first port handed out to sockets which are bound to port 0. This is the start of the
+/// range IANA reserves for ephemeral ports.
+const FIRST_EPHEMERAL_PORT: u16 = 49152;
+
+/// The addresses the sockets on the loopback network are bound to.
+#[derive(Debug)]
+pub struct LoopbackNetwork {
+    /// The sockets bound to each address. The references are weak since closing a socket
+    /// frees its address.
+    sockets: FxHashMap<SocketAddr, WeakFileDescriptionRef<LoopbackSocket>>,
+    /// The ephemeral port to try next.
+    next_ephemeral_port: u16,
+}
+
+impl LoopbackNetwork {
+    pub fn new() -> Self {
+        LoopbackNetwork { sockets: FxHashMap::default(), next_ephemeral_port: FIRST_EPHEMERAL_PORT }
+    }
+
+    /// The socket which is bound to `address`, if it is still open.
+    fn lookup(&self, address: &SocketAddr) -> Option<FileDescriptionRef<LoopbackSocket>> {
+        self.sockets.get(address).and_then(WeakFileDescriptionRef::upgrade)
+    }
+
+    /// Whether binding to `address` would conflict with a socket that is still open. Binding to
+    /// the unspecified address claims the port on every address of the family.
+    fn is_in_use(&self, address: &SocketAddr) -> bool {
+        self.sockets.iter().any(|(bound, socket)| {
+            bound.port() == address.port()
+                && bound.is_ipv4() == address.is_ipv4()
+                && (bound.ip() == address.ip()
+                    || bound.ip().is_unspecified()
+                    || address.ip().is_unspecified())
+                && socket.upgrade().is_some()
+        })
+    }
+
+    /// Pick an ephemeral port for `ip` that is not in use.
+    fn ephemeral_address(&mut self, ip: IpAddr) -> Option<SocketAddr> {
+        for _ in FIRST_EPHEMERAL_PORT..=u16::MAX {
+            let address = SocketAddr::new(ip, self.next_ephemeral_port);
+            self.next_ephemeral_port =
+                self.next_ephemeral_port.checked_add(1).unwrap_or(FIRST_EPHEMERAL_PORT);
+            if !self.is_in_use(&address) {
+                return Some(address);
+            }
+        }
+        None
+    }
+
+    /// The listening socket that a connection to `address` reaches.
+    fn listener(&self, address: &SocketAddr) -> Option<FileDescriptionRef<LoopbackSocket>> {
+        let unspecified =
+            SocketAddr::new(unspecified_ip(SocketFamily::of(address)), address.port());
+        self.lookup(address)
+            .or_else(|| self.lookup(&unspecified))
+            .filter(|socket| socket.listener.get().is_some())
+    }
+}
+
+fn unspecified_ip(family: SocketFamily) -> IpAddr {
+    match family {
+        SocketFamily::IPv4 => Ipv4Addr::UNSPECIFIED.into(),
+        SocketFamily::IPv6 => Ipv6Addr::UNSPECIFIED.into(),
+    }
+}
+
+fn loopback_ip(family: SocketFamily) -> IpAddr {
+    match family {
+        SocketFamily::IPv4 => Ipv4Addr::LOCALHOST.into(),
+        SocketFamily::IPv6 => Ipv6Addr::LOCALHOST.into(),
+    }
+}
+
+/// A TCP socket on the loopback network which isn't connected (yet).
+#[derive(Debug)]
+pub struct LoopbackSocket {
+    family: SocketFamily,
+    /// The address the socket is bound to.
+    address: Cell<Option<SocketAddr>>,
+    /// The connections waiting to be accepted. This is unset as long as the socket isn't
+    /// listening.
+    listener: OnceCell<Rc<Listener>>,
+    /// Whether this fd is non-blocking or not.
+    is_nonblock: Cell<bool>,
+    /// State for being watched by epoll.
+    watched: ReadinessWatched,
+}
+
+impl FileDescription for LoopbackSocket {
+    fn name(&self) -> &'static str {
+        "socket"
+    }
+
+    fn metadata<'tcx>(
+        &self,
+    ) -> InterpResult<'tcx, Either<io::Result<shims::HandleMetadata>, &'static str>> {
+        interp_ok(Either::Right("S_IFSOCK"))
+    }
+
+    fn read<'tcx>(
+        self: FileDescriptionRef<Self>,
+        _communicate_allowed: bool,
+        _ptr: Pointer,
+        _len: usize,
+        ecx: &mut MiriInterpCx<'tcx>,
+        finish: DynMachineCallback<'tcx, Result<usize, IoError>>,
+    ) -> InterpResult<'tcx> {
+        // Connected sockets are virtual sockets instead.
+        finish.call(ecx, Err(LibcError("ENOTCONN")))
+    }
+
+    fn write<'tcx>(
+        self: FileDescriptionRef<Self>,
+        _communicate_allowed: bool,
+        _ptr: Pointer,
+        _len: usize,
+        ecx: &mut MiriInterpCx<'tcx>,
+        finish: DynMachineCallback<'tcx, Result<usize, IoError>>,
+    ) -> InterpResult<'tcx> {
+        finish.call(ecx, Err(LibcError("ENOTCONN")))
+    }
+
+    fn as_unix<'tcx>(
+        self: FileDescriptionRef<Self>,
+        _ecx: &MiriInterpCx<'tcx>,
+    ) -> FileDescriptionRef<dyn UnixFileDescription> {
+        self
+    }
+
+    fn get_flags<'tcx>(&self, ecx: &mut MiriInterpCx<'tcx>) -> InterpResult<'tcx, Scalar> {
+        let mut flags = ecx.eval_libc_i32("O_RDWR");
+
+        if self.is_nonblock.get() {
+            flags |= ecx.eval_libc_i32("O_NONBLOCK");
+        }
+
+        interp_ok(Scalar::from_i32(flags))
+    }
+
+    fn set_flags<'tcx>(
+        &self,
+        mut flag: i32,
+        ecx: &mut MiriInterpCx<'tcx>,
+    ) -> InterpResult<'tcx, Scalar> {
+        let o_nonblock = ecx.eval_libc_i32("O_NONBLOCK");
+
+        // O_NONBLOCK flag can be set / unset by user.
+        if flag & o_nonblock == o_nonblock {
+            self.is_nonblock.set(true);
+            flag &= !o_nonblock;
+        } else {
+            self.is_nonblock.set(false);
+        }
+
+        // Throw error if there is any unsupported flag.
+        if flag != 0 {
+            throw_unsup_format!("fcntl: only O_NONBLOCK is supported for sockets")
+        }
+
+        interp_ok(Scalar::from_i32(0))
+    }
+
+    fn readiness_watched(&self) -> Option<&ReadinessWatched> {
+        Some(&self.watched)
+    }
+
+    fn readiness(&self) -> Readiness {
+        // A listening socket is readable when there is a connection to accept.
+        // Unconnected sockets can neither be read nor written.
+        let mut readiness = Readiness::EMPTY;
+        if let Some(listener) = self.listener.get() {
+            readiness.readable = listener.has_pending_connection();
+        }
+        readiness
+    }
+}
+
+impl UnixFileDescription for LoopbackSocket {
+    fn ioctl<'tcx>(
+        &self,
+        op: Scalar,
+        arg: Option<&OpTy<'tcx>>,
+        ecx: &mut MiriInterpCx<'tcx>,
+    ) -> InterpResult<'tcx, i32> {
+        ecx.socket_ioctl(op, arg, &self.is_nonblock)
+    }
+
+    fn as_socket<'tcx>(
+        self: FileDescriptionRef<Self>,
+        _ecx: &MiriInterpCx<'tcx>,
+    ) -> Option<FileDescriptionRef<dyn UnixSocketFileDescription>> {
+        Some(self)
+    }
+}
+
+impl UnixSocketFileDescription for LoopbackSocket {
+    fn bind<'tcx>(
+        self: FileDescriptionRef<Self>,
+        _communicate_allowed: bool,
+        address: SocketAddress,
+        ecx: &mut MiriInterpCx<'tcx>,
+    ) -> InterpResult<'tcx, Result<(), IoError>> {
+        let address = match address {
+            SocketAddress::Inet(address) if SocketFamily::of(&address) == self.family => address,
+            _ => return interp_ok(Err(ecx.bind_family_mismatch_error())),
+        };
+
+        if self.address.get().is_some() {
+            // The socket is already bound.
+            return interp_ok(Err(LibcError("EINVAL")));
+        }
+        if !address.ip().is_loopback() && !address.ip().is_unspecified() {
+            // The loopback network has no other interfaces.
+            return interp_ok(Err(LibcError("EADDRNOTAVAIL")));
+        }
+
+        interp_ok(ecx.bind_loopback_socket(&self, address))
+    }
+
+    fn listen<'tcx>(
+        self: FileDescriptionRef<Self>,
+        _communicate_allowed: bool,
+        // We never reject connections, so the backlog size doesn't matter.
+        _backlog: i32,
+        ecx: &mut MiriInterpCx<'tcx>,
+    ) -> InterpResult<'tcx, Result<(), IoError>> {
+        if self.address.get().is_none() {
+            // Like the kernel, we bind the socket to an ephemeral port first.
+            let address = SocketAddr::new(unspecified_ip(self.family), 0);
+            if let Err(e) = ecx.bind_loopback_socket(&self, address) {
+                return interp_ok(Err(e));
+            }
+        }
+
+        self.listener.get_or_init(Default::default);
+
+        interp_ok(Ok(()))
+    }
+
+    fn accept<'tcx>(
+        self: FileDescriptionRef<Self>,
+        _communicate_allowed: bool,
+        is_client_sock_non_block: bool,
+        ecx: &mut MiriInterpCx<'tcx>,
+        finish: DynMachineCallback<'tcx, Result<(FdNum, SocketAddress), IoError>>,
+    ) -> InterpResult<'tcx> {
+        let Some(listener) = self.listener.get().cloned() else {
+            return finish.call(ecx, Err(LibcError("EINVAL")));
+        };
+
+        let is_nonblock = self.is_nonblock.get();
+        ecx.accept_connection(self, listener, is_nonblock, is_client_sock_non_block, finish)
+    }
+
+    fn connect<'tcx>(
+        self: FileDescriptionRef<Self>,
+        _communicate_allowed: bool,
+        address: SocketAddress,
+        ecx: &mut MiriInterpCx<'tcx>,
+        finish: DynMachineCallback<'tcx, Result<(), IoError>>,
+    ) -> InterpResult<'tcx> {
+        let mut address = match address {
+            SocketAddress::Inet(address) if SocketFamily::of(&address) == self.family => address,
+            _ => return finish.call(ecx, Err(LibcError("EAFNOSUPPORT"))),
+        };
+        if self.listener.get().is_some() {
+            // The socket is listening.
+            return finish.call(ecx, Err(LibcError("EINVAL")));
+        }
+        if address.ip().is_unspecified() {
+            // Connecting to the unspecified address reaches the local host.
+            address.set_ip(loopback_ip(self.family));
+        } else if !address.ip().is_loopback() {
+            return finish.call(ecx, Err(LibcError("ENETUNREACH")));
+        }
+
+        let network = ecx.machine.loopback_network.as_ref().unwrap();
+        let Some(target) = network.listener(&address) else {
+            return finish.call(ecx, Err(LibcError("ECONNREFUSED")));
+        };
+        if self.watched.is_watched() {
+            throw_unsup_format!(
+                "connect: connecting a socket that is watched by epoll or poll is unsupported"
+            );
+        }
+
+        if self.address.get().is_none() {
+            let local_address = SocketAddr::new(address.ip(), 0);
+            if let Err(e) = ecx.bind_loopback_socket(&self, local_address) {
+                return finish.call(ecx, Err(e));
+            }
+        }
+        let mut local_address = self.address.get().unwrap();
+        if local_address.ip().is_unspecified() {
+            local_address.set_ip(address.ip());
+        }
+
+        let (client, server) = ecx.new_stream_socket_pair(
+            local_address.into(),
+            address.into(),
+            self.is_nonblock.get(),
+            Delivery::Chunked,
+        );
+        // From now on, every file descriptor of this socket refers to the connected socket.
+        ecx.machine.fds.replace_description(self.id(), client);
+
+        let listener = Rc::clone(target.listener.get().unwrap());
+        ecx.queue_connection(target, &listener, server)?;
+        finish.call(ecx, Ok(()))
+    }
+
+    fn recv<'tcx>(
+        self: FileDescriptionRef<Self>,
+        _communicate_allowed: bool,
+        _ptr: Pointer,
+        _len: usize,
+        _is_peek: bool,
+        _is_non_block: bool,
+        ecx: &mut MiriInterpCx<'tcx>,
+        finish: DynMachineCallback<'tcx, Result<usize, IoError>>,
+    ) -> InterpResult<'tcx> {
+        finish.call(ecx, Err(LibcError("ENOTCONN")))
+    }
+
+    fn send<'tcx>(
+        self: FileDescriptionRef<Self>,
+        _communicate_allowed: bool,
+        _ptr: Pointer,
+        _len: usize,
+        _is_non_block: bool,
+        ecx: &mut MiriInterpCx<'tcx>,
+        finish: DynMachineCallback<'tcx, Result<usize, IoError>>,
+    ) -> InterpResult<'tcx> {
+        finish.call(ecx, Err(LibcError("ENOTCONN")))
+    }
+
+    fn setsockopt<'tcx>(
+        self: FileDescriptionRef<Self>,
+        level: i32,
+        option: i32,
+        value_ptr: Pointer,
+        value_len: u64,
+        ecx: &mut MiriInterpCx<'tcx>,
+    ) -> InterpResult<'tcx, Result<(), IoError>> {
+        ecx.loopback_setsockopt(level, option, value_ptr, value_len)
+    }
+
+    fn getsockopt<'tcx>(
+        self: FileDescriptionRef<Self>,
+        level: i32,
+        option: i32,
+        ecx: &mut MiriInterpCx<'tcx>,
+    ) -> InterpResult<'tcx, Result<MPlaceTy<'tcx>, IoError>> {
+        if level != ecx.eval_libc_i32("SOL_SOCKET") || option != ecx.eval_libc_i32("SO_ERROR") {
+            throw_unsup_format!(
+                "getsockopt: only SO_ERROR on level SOL_SOCKET is supported for loopback sockets"
+            );
+        }
+
+        // Connecting happens instantly, so there never is a pending error.
+        let value_buffer = ecx.allocate(ecx.machine.layouts.i32, MemoryKind::Stack)?;
+        ecx.write_int(0, &value_buffer)?;
+        interp_ok(Ok(value_buffer))
+    }
+
+    fn getsockname<'tcx>(
+        self: FileDescriptionRef<Self>,
+        _communicate_allowed: bool,
+        _ecx: &mut MiriInterpCx<'tcx>,
+    ) -> InterpResult<'tcx, Result<SocketAddress, IoError>> {
+        let address =
+            self.address.get().unwrap_or_else(|| SocketAddr::new(unspecified_ip(self.family), 0));
+        interp_ok(Ok(address.into()))
+    }
+
+    fn getpeername<'tcx>(
+        self: FileDescriptionRef<Self>,
+        _communicate_allowed: bool,
+        ecx: &mut MiriInterpCx<'tcx>,
+        finish: DynMachineCallback<'tcx, Result<SocketAddress, IoError>>,
+    ) -> InterpResult<'tcx> {
+        finish.call(ecx, Err(LibcError("ENOTCONN")))
+    }
+}
+
+impl<'tcx> EvalContextPrivExt<'tcx> for crate::MiriInterpCx<'tcx> {}
+trait EvalContextPrivExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
+    /// Bind `socket` to `address`, picking an ephemeral port if the port is 0.
+    fn bind_loopback_socket(
+        &mut self,
+        socket: &FileDescriptionRef<LoopbackSocket>,
+        address: SocketAddr,
+    ) -> Result<(), IoError> {
+        let this = self.eval_context_mut();
+
+        let network = this.machine.loopback_network.as_mut().unwrap();
+        let address = if address.port() == 0 {
+            network.ephemeral_address(address.ip()).ok_or(LibcError("EADDRINUSE"))?
+        } else if network.is_in_use(&address) {
+            return Err(LibcError("EADDRINUSE"));
+        } else {
+            address
+        };
+
+        // Forget about the sockets which were closed in the meantime.
+        network.sockets.retain(|_, socket| socket.upgrade().is_some());
+        network.sockets.insert(address, FileDescriptionRef::downgrade(socket));
+        socket.address.set(Some(address));
+        Ok(())
+    }
+}
+
+impl<'tcx> EvalContextExt<'tcx> for crate::MiriInterpCx<'tcx> {}
+pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
+    /// Create a new TCP socket on the loopback network and insert it into the file
+    /// descriptor table.
+    fn new_loopback_socket(&mut self, family: SocketFamily, is_nonblock: bool) -> FdNum {
+        let this = self.eval_context_mut();
+
+        this.machine.fds.insert_new(LoopbackSocket {
+            family,
+            address: Cell::new(None),
+            listener: OnceCell::new(),
+            is_nonblock: Cell::new(is_nonblock),
+            watched: ReadinessWatched::default(),
+        })
+    }
+
+    /// Set a socket option on a loopback socket, connected or not. All supported options only
+    /// affect performance or signals on a real network, so their values are ignored.
+    fn loopback_setsockopt(
+        &mut self,
+        level: i32,
+        option: i32,
+        value_ptr: Pointer,
+        value_len: u64,
+    ) -> InterpResult<'tcx, Result<(), IoError>> {
+        let this = self.eval_context_mut();
+
+        let is_supported = if level == this.eval_libc_i32("SOL_SOCKET") {
+            option == this.eval_libc_i32("SO_REUSEADDR")
+                // SO_NOSIGPIPE only exists on MacOS, FreeBSD, and NetBSD.
+                || (matches!(this.tcx.sess.target.os, Os::MacOs | Os::FreeBsd | Os::NetBsd)
+                    && option == this.eval_libc_i32("SO_NOSIGPIPE"))
+        } else if level == this.eval_libc_i32("IPPROTO_TCP") {
+            option == this.eval_libc_i32("TCP_NODELAY")
+        } else {
+            false
+        };
+        if !is_supported {
+            throw_unsup_format!(
+                "setsockopt: option {option:#x} on level {level:#x} is unsupported for loopback sockets"
+            );
+        }
+
+        if value_len != 4 {
+            // Option value should be C-int which is usually 4 bytes.
+            return interp_ok(Err(LibcError("EINVAL")));
+        }
+        let option_value = this.ptr_to_mplace(value_ptr, this.machine.layouts.i32);
+        let _val = this.read_scalar(&option_value)?.to_i32()?;
+        interp_ok(Ok(()))
+    }
+}
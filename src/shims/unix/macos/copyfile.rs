@@ -0,0 +1,165 @@
+//! Implements the parts of `copyfile.h` that `std::fs::copy` uses on macOS.
+
+use crate::shims::files::{DynFileDescriptionRef, FileHandle};
+use crate::shims::unix::*;
+use crate::*;
+
+impl<'tcx> EvalContextExt<'tcx> for crate::MiriInterpCx<'tcx> {}
+pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
+    /// Allocates a `copyfile_state_t`. The only thing we keep in there is the number of bytes
+    /// copied, as an `off_t`.
+    fn copyfile_state_alloc(&mut self) -> InterpResult<'tcx, Pointer> {
+        let this = self.eval_context_mut();
+
+        let layout = this.libc_ty_layout("off_t");
+        let state = this.allocate(layout, MiriMemoryKind::C.into())?;
+        this.write_int(0, &state)?;
+        interp_ok(state.ptr())
+    }
+
+    fn copyfile_state_free(&mut self, state: &OpTy<'tcx>) -> InterpResult<'tcx, Scalar> {
+        let this = self.eval_context_mut();
+
+        let state = this.read_pointer(state)?;
+        this.deallocate_ptr(state, None, MiriMemoryKind::C.into())?;
+        interp_ok(Scalar::from_i32(0))
+    }
+
+    fn copyfile_state_get(
+        &mut self,
+        state: &OpTy<'tcx>,
+        flag: &OpTy<'tcx>,
+        dst: &OpTy<'tcx>,
+    ) -> InterpResult<'tcx, Scalar> {
+        let this = self.eval_context_mut();
+
+        let state = this.deref_pointer_as(state, this.libc_ty_layout("off_t"))?;
+        let flag = this.read_scalar(flag)?.to_u32()?;
+        let dst = this.deref_pointer_as(dst, this.libc_ty_layout("off_t"))?;
+
+        if flag != this.eval_libc_u32("COPYFILE_STATE_COPIED") {
+            throw_unsup_format!("copyfile_state_get: unsupported flag {flag:#x}");
+        }
+        this.copy_op(&state, &dst)?;
+        interp_ok(Scalar::from_i32(0))
+    }
+
+    /// Copies the contents and, with `COPYFILE_STAT`, the permissions of one file to another.
+    /// We have neither ACLs nor extended attributes, so `COPYFILE_ACL` and `COPYFILE_XATTR` have
+    /// nothing to copy.
+    ///
+    /// <https://developer.apple.com/library/archive/documentation/System/Conceptual/ManPages_iPhoneOS/man3/copyfile.3.html>
+    fn fcopyfile(
+        &mut self,
+        from: &OpTy<'tcx>,
+        to: &OpTy<'tcx>,
+        state: &OpTy<'tcx>,
+        flags: &OpTy<'tcx>,
+        dest: &MPlaceTy<'tcx>,
+    ) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+
+        let from = this.read_scalar(from)?.to_i32()?;
+        let to = this.read_scalar(to)?.to_i32()?;
+        let state = this.read_pointer(state)?;
+        let flags = this.read_scalar(flags)?.to_u32()?;
+
+        let copyfile_acl = this.eval_libc_u32("COPYFILE_ACL");
+        let copyfile_stat = this.eval_libc_u32("COPYFILE_STAT");
+        let copyfile_xattr = this.eval_libc_u32("COPYFILE_XATTR");
+        let copyfile_data = this.eval_libc_u32("COPYFILE_DATA");
+        let known = copyfile_acl | copyfile_stat | copyfile_xattr | copyfile_data;
+        if flags & !known != 0 {
+            throw_unsup_format!("fcopyfile: unsupported flags {:#x}", flags & !known);
+        }
+
+        let (Some(fd_from), Some(fd_to)) = (this.machine.fds.get(from), this.machine.fds.get(to))
+        else {
+            return this.set_errno_and_return_neg1(LibcError("EBADF"), dest);
+        };
+        let is_file = |fd: &DynFileDescriptionRef| fd.clone().downcast::<FileHandle>().is_some();
+        if !is_file(&fd_from) || !is_file(&fd_to) {
+            throw_unsup_format!("fcopyfile: only regular files are supported");
+        }
+
+        let state = if this.ptr_is_null(state)? {
+            None
+        } else {
+            Some(this.ptr_to_mplace(state, this.libc_ty_layout("off_t")))
+        };
+        let copy_stat = flags & copyfile_stat != 0;
+        if flags & copyfile_data == 0 {
+            return this.fcopyfile_finish(from, to, copy_stat, state, 0, dest);
+        }
+        this.fcopyfile_data(from, to, copy_stat, state, 0, dest.clone())
+    }
+}
+
+impl<'tcx> EvalContextPrivExt<'tcx> for crate::MiriInterpCx<'tcx> {}
+trait EvalContextPrivExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
+    /// Copies the rest of the data of `from` to `to`, after `copied` bytes were already copied.
+    fn fcopyfile_data(
+        &mut self,
+        from: i32,
+        to: i32,
+        copy_stat: bool,
+        state: Option<MPlaceTy<'tcx>>,
+        copied: u64,
+        dest: MPlaceTy<'tcx>,
+    ) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+
+        let (Some(fd_from), Some(fd_to)) = (this.machine.fds.get(from), this.machine.fds.get(to))
+        else {
+            return this.set_errno_and_return_neg1(LibcError("EBADF"), &dest);
+        };
+        this.transfer_between_fds(
+            fd_from,
+            None,
+            fd_to,
+            None,
+            usize::MAX,
+            callback!(
+                @capture<'tcx> {
+                    from: i32,
+                    to: i32,
+                    copy_stat: bool,
+                    state: Option<MPlaceTy<'tcx>>,
+                    copied: u64,
+                    dest: MPlaceTy<'tcx>,
+                }
+                |this, result: Result<usize, IoError>| {
+                    match result {
+                        Ok(0) => this.fcopyfile_finish(from, to, copy_stat, state, copied, &dest),
+                        Ok(size) => {
+                            let copied = copied.strict_add(u64::try_from(size).unwrap());
+                            this.fcopyfile_data(from, to, copy_stat, state, copied, dest)
+                        }
+                        Err(e) => this.set_errno_and_return_neg1(e, &dest),
+                    }
+                }
+            ),
+        )
+    }
+
+    /// Completes `fcopyfile` after the data was copied.
+    fn fcopyfile_finish(
+        &mut self,
+        from: i32,
+        to: i32,
+        copy_stat: bool,
+        state: Option<MPlaceTy<'tcx>>,
+        copied: u64,
+        dest: &MPlaceTy<'tcx>,
+    ) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+
+        if copy_stat && let Err(err) = this.copy_fd_mode(from, to)? {
+            return this.set_errno_and_return_neg1(err, dest);
+        }
+        if let Some(state) = state {
+            this.write_int(copied, &state)?;
+        }
+        this.write_int(0, dest)
+    }
+}
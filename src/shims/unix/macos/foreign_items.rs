@@ -3,6 +3,7 @@ use rustc_middle::ty::Ty;
 use rustc_span::Symbol;
 use rustc_target::callconv::FnAbi;
 
+use super::copyfile::EvalContextExt as _;
 use super::sync::{EvalContextExt as _, MacOsFutexTimeout};
 use crate::shims::unix::*;
 use crate::*;
@@ -15,6 +16,8 @@ pub fn is_dyn_sym(name: &str) -> bool {
         | "os_sync_wait_on_address_with_timeout"
         | "os_sync_wake_by_address_any"
         | "os_sync_wake_by_address_all" => true,
+        // std looks this up dynamically to support `Command::current_dir` with `posix_spawn`.
+        "posix_spawn_file_actions_addchdir_np" => true,
         _ => false,
     }
 }
@@ -62,6 +65,12 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
                 let result = this.fstat(fd, buf)?;
                 this.write_scalar(result, dest)?;
             }
+            "fstatat$INODE64" => {
+                let [dirfd, path, buf, flags] =
+                    this.check_shim_sig_lenient(abi, CanonAbi::C, link_name, args)?;
+                let result = this.fstatat(dirfd, path, buf, flags)?;
+                this.write_scalar(result, dest)?;
+            }
             "opendir$INODE64" => {
                 let [name] = this.check_shim_sig_lenient(abi, CanonAbi::C, link_name, args)?;
                 let result = this.opendir(name)?;
@@ -77,6 +86,57 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
                 let result = this.realpath(path, resolved_path)?;
                 this.write_scalar(result, dest)?;
             }
+            "fclonefileat" => {
+                let [_srcfd, _dst_dirfd, _dst, _flags] = this.check_shim_sig(
+                    shim_sig!(extern "C" fn(i32, i32, *const _, u32) -> i32),
+                    link_name,
+                    abi,
+                    args,
+                )?;
+                // Cloning needs support from the file system, which we pretend not to have.
+                // Callers fall back to copying the data, e.g. with `fcopyfile`.
+                let result = this.set_errno_and_return_neg1_i32(LibcError("ENOTSUP"))?;
+                this.write_scalar(result, dest)?;
+            }
+            "copyfile_state_alloc" => {
+                let [] = this.check_shim_sig(
+                    shim_sig!(extern "C" fn() -> *mut _),
+                    link_name,
+                    abi,
+                    args,
+                )?;
+                let state = this.copyfile_state_alloc()?;
+                this.write_pointer(state, dest)?;
+            }
+            "copyfile_state_free" => {
+                let [state] = this.check_shim_sig(
+                    shim_sig!(extern "C" fn(*mut _) -> i32),
+                    link_name,
+                    abi,
+                    args,
+                )?;
+                let result = this.copyfile_state_free(state)?;
+                this.write_scalar(result, dest)?;
+            }
+            "copyfile_state_get" => {
+                let [state, flag, dst] = this.check_shim_sig(
+                    shim_sig!(extern "C" fn(*mut _, u32, *mut _) -> i32),
+                    link_name,
+                    abi,
+                    args,
+                )?;
+                let result = this.copyfile_state_get(state, flag, dst)?;
+                this.write_scalar(result, dest)?;
+            }
+            "fcopyfile" => {
+                let [from, to, state, flags] = this.check_shim_sig(
+                    shim_sig!(extern "C" fn(i32, i32, *mut _, u32) -> i32),
+                    link_name,
+                    abi,
+                    args,
+                )?;
+                this.fcopyfile(from, to, state, flags, dest)?;
+            }
 
             // Environment related shims
             "_NSGetEnviron" => {
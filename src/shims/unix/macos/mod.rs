@@ -1,2 +1,3 @@
+pub mod copyfile;
 pub mod foreign_items;
 pub mod sync;
@@ -45,6 +45,13 @@ impl SyncObj for MacOsUnfairLock {
     fn delete_on_write(&self) -> bool {
         true
     }
+
+    fn lock_order_id(&self) -> Option<LockId> {
+        match self {
+            MacOsUnfairLock::Active { mutex_ref } => mutex_ref.lock_order_id(),
+            MacOsUnfairLock::PermanentlyLockedByUnknown => None,
+        }
+    }
 }
 
 pub enum MacOsFutexTimeout<'a, 'tcx> {
@@ -211,6 +218,7 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
             let futex_ref = futex.futex.clone();
             let dest = dest.clone();
             this.futex_wait(
+                ptr,
                 futex_ref.clone(),
                 u32::MAX, // bitset
                 deadline,
@@ -302,7 +310,7 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
 
         // See the Linux futex implementation for why this fence exists.
         this.atomic_fence(AtomicFenceOrd::SeqCst)?;
-        this.futex_wake(&futex_ref, u32::MAX, if all { usize::MAX } else { 1 })?;
+        this.futex_wake(ptr, &futex_ref, u32::MAX, if all { usize::MAX } else { 1 })?;
         this.write_scalar(Scalar::from_i32(0), dest)?;
         interp_ok(())
     }
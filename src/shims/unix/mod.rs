@@ -3,13 +3,20 @@ pub mod foreign_items;
 mod env;
 mod fd;
 mod fs;
+#[cfg(unix)]
+mod host_pipe;
+mod loopback_socket;
 mod mem;
 mod poll;
+mod signal;
 mod socket;
 mod socket_address;
+mod spawn;
 mod sync;
 mod tcp_socket;
 mod thread;
+mod udp_socket;
+mod unix_socket;
 mod virtual_socket;
 
 mod android;
@@ -24,12 +31,21 @@ mod solarish;
 pub use self::env::{EvalContextExt as _, UnixEnvVars};
 pub use self::fd::{EvalContextExt as _, UnixFileDescription};
 pub use self::fs::{DirTable, EvalContextExt as _};
+pub use self::linux_like::inotify::{
+    EvalContextExt as InotifyEvalContextExt, FsEvent, InotifyInstances,
+};
+pub use self::linux_like::signalfd::SignalFds;
+pub use self::linux_like::timerfd::{ArmedTimerFds, EvalContextExt as TimerFdEvalContextExt};
+pub use self::loopback_socket::LoopbackNetwork;
 pub use self::mem::EvalContextExt as _;
 pub use self::poll::EvalContextExt as _;
+pub use self::signal::{EvalContextExt as SignalEvalContextExt, SignalHandlerFrame, SignalState};
 pub use self::socket::EvalContextExt as _;
 pub use self::socket_address::EvalContextExt as _;
+pub use self::spawn::EvalContextExt as _;
 pub use self::sync::EvalContextExt as _;
 pub use self::thread::{EvalContextExt as _, ThreadNameResult};
+pub use self::unix_socket::UnixSocketNamespace;
 pub use self::virtual_socket::EvalContextExt as _;
 
 // Make up some constants.
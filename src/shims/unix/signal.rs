@@ -0,0 +1,886 @@
+//! Signal dispositions, signal masks, and signal delivery.
+//!
+//! All signals originate from within the program itself (`raise`, `kill` on our own process,
+//! `pthread_kill`, `abort`), so delivery is fully deterministic. Generating a signal marks it as
+//! pending, and pending signals are delivered right before the target thread takes its next step.
+//! For a signal that a thread sends to itself this means the handler runs before the call that
+//! generated the signal returns to its caller, just like on a real system. A handler runs in a
+//! new stack frame pushed on top of the interrupted one; when it returns, the interrupted code
+//! continues where it left off.
+//!
+//! Limitations: blocking operations cannot be interrupted, so they never fail with `EINTR`, and
+//! generating a signal that only a blocked thread could receive is reported as unsupported;
+//! handlers do not actually run on the alternate signal stack (but `sigaltstack` reports that
+//! they do); and the `ucontext_t` argument of `SA_SIGINFO` handlers is a null pointer.
+
+use std::cell::RefCell;
+
+use rustc_abi::{ExternAbi, FieldIdx, Size};
+use rustc_data_structures::fx::FxHashMap;
+use rustc_middle::mir;
+use rustc_target::spec::Os;
+
+use crate::concurrency::VClock;
+use crate::shims::unix::linux_like::signalfd::EvalContextExt as _;
+use crate::shims::unix::spawn::EvalContextExt as _;
+use crate::*;
+
+/// A set of signals. Bit `n - 1` represents signal `n`.
+pub type SigSet = u128;
+
+fn sig_bit(signum: i32) -> SigSet {
+    let shift = u32::try_from(signum.strict_sub(1)).unwrap();
+    SigSet::from(1u8).strict_shl(shift)
+}
+
+/// What to do when a signal is delivered.
+#[derive(Clone, Copy, Debug)]
+enum SigHandler {
+    /// `SIG_DFL`.
+    Default,
+    /// `SIG_IGN`.
+    Ignore,
+    /// A function pointer.
+    Handler(Pointer),
+}
+
+/// The disposition of a signal, as set with `sigaction` or `signal`.
+#[derive(Clone, Copy, Debug)]
+struct SigAction {
+    handler: SigHandler,
+    /// Signals that are blocked in addition to the thread's mask while the handler runs.
+    mask: SigSet,
+    flags: i32,
+}
+
+impl Default for SigAction {
+    fn default() -> Self {
+        SigAction { handler: SigHandler::Default, mask: 0, flags: 0 }
+    }
+}
+
+/// Per-thread signal state.
+#[derive(Clone, Debug, Default)]
+struct ThreadSignals {
+    /// The signals this thread currently blocks.
+    mask: SigSet,
+    /// The signals that were sent to this thread and not yet delivered.
+    pending: SigSet,
+    /// The alternate signal stack set with `sigaltstack`, as base pointer and size.
+    alt_stack: Option<(Pointer, u64)>,
+    /// How many handlers on this thread's stack run "on" the alternate signal stack.
+    on_alt_stack: u32,
+}
+
+/// The signal state of the process.
+#[derive(Debug, Default)]
+pub struct SignalState {
+    /// Signal dispositions. Signals not in this map have the default disposition.
+    actions: FxHashMap<i32, SigAction>,
+    /// The signals that were sent to the process as a whole and not yet delivered.
+    pending: SigSet,
+    threads: FxHashMap<ThreadId, ThreadSignals>,
+    /// Installing a handler and generating a signal both release into this clock, and delivering
+    /// a signal acquires it. The kernel establishes this synchronization on real systems.
+    clock: RefCell<VClock>,
+}
+
+impl SignalState {
+    fn action(&self, signum: i32) -> SigAction {
+        self.actions.get(&signum).copied().unwrap_or_default()
+    }
+
+    fn thread(&mut self, thread: ThreadId) -> &mut ThreadSignals {
+        self.threads.entry(thread).or_default()
+    }
+
+    /// The lowest-numbered signal that can be delivered to `thread` right now.
+    fn next_deliverable(&self, thread: ThreadId) -> Option<i32> {
+        let (thread_pending, mask) =
+            self.threads.get(&thread).map_or((0, 0), |state| (state.pending, state.mask));
+        let deliverable = (thread_pending | self.pending) & !mask;
+        (deliverable != 0)
+            .then(|| i32::try_from(deliverable.trailing_zeros()).unwrap().strict_add(1))
+    }
+
+    /// The signals that are pending for the process or for any of its threads.
+    pub fn all_pending(&self) -> SigSet {
+        self.threads.values().fold(self.pending, |pending, state| pending | state.pending)
+    }
+
+    /// Drop the given signals wherever they are pending.
+    fn discard(&mut self, set: SigSet) {
+        self.pending &= !set;
+        for state in self.threads.values_mut() {
+            state.pending &= !set;
+        }
+    }
+
+    /// New threads inherit the signal mask of the thread that created them.
+    pub fn inherit_mask(&mut self, parent: ThreadId, child: ThreadId) {
+        let mask = self.thread(parent).mask;
+        self.thread(child).mask = mask;
+    }
+}
+
+impl VisitProvenance for SignalState {
+    fn visit_provenance(&self, visit: &mut VisitWith<'_>) {
+        let SignalState { actions, pending: _, threads, clock: _ } = self;
+        for action in actions.values() {
+            if let SigHandler::Handler(ptr) = action.handler {
+                ptr.visit_provenance(visit);
+            }
+        }
+        for state in threads.values() {
+            if let Some((ptr, _size)) = state.alt_stack {
+                ptr.visit_provenance(visit);
+            }
+        }
+    }
+}
+
+/// Extra data for the frame of a running signal handler.
+#[derive(Debug)]
+pub struct SignalHandlerFrame {
+    /// The signal mask of the thread before the handler started.
+    saved_mask: SigSet,
+    /// The `siginfo_t` passed to an `SA_SIGINFO` handler, freed when the handler returns.
+    siginfo: Option<Pointer>,
+    /// Whether this handler counts as running on the alternate signal stack.
+    on_alt_stack: bool,
+    /// Whether this is the `SIGABRT` handler run by `abort`, which terminates the program once the
+    /// handler returns.
+    abort: bool,
+}
+
+impl VisitProvenance for SignalHandlerFrame {
+    fn visit_provenance(&self, visit: &mut VisitWith<'_>) {
+        let SignalHandlerFrame { saved_mask: _, siginfo, on_alt_stack: _, abort: _ } = self;
+        siginfo.visit_provenance(visit);
+    }
+}
+
+impl<'tcx> EvalContextExtPriv<'tcx> for crate::MiriInterpCx<'tcx> {}
+trait EvalContextExtPriv<'tcx>: crate::MiriInterpCxExt<'tcx> {
+    /// The highest signal number on this target.
+    fn max_signal(&self) -> i32 {
+        let this = self.eval_context_ref();
+        let os_max = match this.tcx.sess.target.os {
+            Os::Linux | Os::Android => 64,
+            Os::MacOs => 31,
+            Os::NetBsd => 63,
+            _ => 128,
+        };
+        let capacity = this.libc_ty_layout("sigset_t").size.bits();
+        os_max.min(i32::try_from(capacity).unwrap())
+    }
+
+    fn is_valid_signal(&self, signum: i32) -> bool {
+        (1..=self.max_signal()).contains(&signum)
+    }
+
+    /// The signals that cannot be caught, ignored, or blocked.
+    fn unblockable_signals(&self) -> SigSet {
+        let this = self.eval_context_ref();
+        sig_bit(this.eval_libc_i32("SIGKILL")) | sig_bit(this.eval_libc_i32("SIGSTOP"))
+    }
+
+    fn signal_name(&self, signum: i32) -> String {
+        let this = self.eval_context_ref();
+        const NAMES: &[&str] = &[
+            "SIGHUP",
+            "SIGINT",
+            "SIGQUIT",
+            "SIGILL",
+            "SIGTRAP",
+            "SIGABRT",
+            "SIGBUS",
+            "SIGFPE",
+            "SIGKILL",
+            "SIGUSR1",
+            "SIGSEGV",
+            "SIGUSR2",
+            "SIGPIPE",
+            "SIGALRM",
+            "SIGTERM",
+            "SIGCHLD",
+            "SIGCONT",
+            "SIGSTOP",
+            "SIGTSTP",
+            "SIGTTIN",
+            "SIGTTOU",
+            "SIGURG",
+            "SIGXCPU",
+            "SIGXFSZ",
+            "SIGVTALRM",
+            "SIGPROF",
+            "SIGWINCH",
+            "SIGIO",
+            "SIGSYS",
+        ];
+        NAMES
+            .iter()
+            .find(|name| this.eval_libc_i32(name) == signum)
+            .map_or_else(|| format!("signal {signum}"), |name| name.to_string())
+    }
+
+    /// Whether the default action of this signal is to ignore it.
+    fn is_ignored_by_default(&self, signum: i32) -> bool {
+        let this = self.eval_context_ref();
+        let mut ignored = vec!["SIGCHLD", "SIGCONT", "SIGURG", "SIGWINCH"];
+        if matches!(this.tcx.sess.target.os, Os::MacOs | Os::FreeBsd | Os::NetBsd) {
+            ignored.extend(["SIGIO", "SIGINFO"]);
+        }
+        ignored.iter().any(|name| this.eval_libc_i32(name) == signum)
+    }
+
+    fn is_ignored(&self, signum: i32) -> bool {
+        let this = self.eval_context_ref();
+        match this.machine.signals.action(signum).handler {
+            SigHandler::Ignore => true,
+            SigHandler::Default => this.is_ignored_by_default(signum),
+            SigHandler::Handler(_) => false,
+        }
+    }
+
+    fn read_sigset(&self, set: &MPlaceTy<'tcx>) -> InterpResult<'tcx, SigSet> {
+        let this = self.eval_context_ref();
+        let mut result: SigSet = 0;
+        let mut offset = 0;
+        for word in this.sigset_words(set)? {
+            let bits = this.read_scalar(&word)?.to_bits(word.layout.size)?;
+            if offset < SigSet::BITS {
+                result |= bits << offset;
+            }
+            offset = offset.strict_add(u32::try_from(word.layout.size.bits()).unwrap());
+        }
+        interp_ok(result)
+    }
+
+    fn write_sigset(&mut self, set: SigSet, dest: &MPlaceTy<'tcx>) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+        let mut offset = 0;
+        for word in this.sigset_words(dest)? {
+            let size = word.layout.size;
+            let bits = set.checked_shr(offset).unwrap_or(0) & size.unsigned_int_max();
+            this.write_scalar(Scalar::from_uint(bits, size), &word)?;
+            offset = offset.strict_add(u32::try_from(size.bits()).unwrap());
+        }
+        interp_ok(())
+    }
+
+    /// A `sigset_t` is either a single integer or a struct wrapping an array of integers,
+    /// depending on the target. Returns the integers in order.
+    fn sigset_words(&self, set: &MPlaceTy<'tcx>) -> InterpResult<'tcx, Vec<MPlaceTy<'tcx>>> {
+        let this = self.eval_context_ref();
+        if set.layout.ty.is_integral() {
+            return interp_ok(vec![set.clone()]);
+        }
+        let array = this.project_field(set, FieldIdx::ZERO)?;
+        let len = array.len(this)?;
+        (0..len).map(|idx| this.project_index(&array, idx)).collect()
+    }
+
+    /// Reads the signal set that `ptr` points to.
+    fn deref_sigset(&self, ptr: Pointer) -> InterpResult<'tcx, SigSet> {
+        let this = self.eval_context_ref();
+        this.read_sigset(&this.ptr_to_mplace(ptr, this.libc_ty_layout("sigset_t")))
+    }
+
+    fn read_sigaction(&self, act: &MPlaceTy<'tcx>) -> InterpResult<'tcx, SigAction> {
+        let this = self.eval_context_ref();
+        let handler = this.read_sighandler(&this.project_field_named(act, "sa_sigaction")?)?;
+        let mask = this.read_sigset(&this.project_field_named(act, "sa_mask")?)?;
+        let flags = this.read_scalar(&this.project_field_named(act, "sa_flags")?)?.to_i32()?;
+
+        let supported = [
+            "SA_SIGINFO",
+            "SA_NODEFER",
+            "SA_RESETHAND",
+            "SA_ONSTACK",
+            "SA_RESTART",
+            "SA_NOCLDSTOP",
+            "SA_NOCLDWAIT",
+        ]
+        .iter()
+        .fold(0, |acc, name| acc | this.eval_libc_i32(name));
+        if flags & !supported != 0 {
+            throw_unsup_format!("sigaction: unsupported flags {:#x}", flags & !supported);
+        }
+        interp_ok(SigAction { handler, mask, flags })
+    }
+
+    fn write_sigaction(&mut self, action: SigAction, dest: &MPlaceTy<'tcx>) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+        let handler = this.sighandler_to_scalar(action.handler);
+        this.write_scalar(handler, &this.project_field_named(dest, "sa_sigaction")?)?;
+        this.write_sigset(action.mask, &this.project_field_named(dest, "sa_mask")?)?;
+        this.write_int(action.flags, &this.project_field_named(dest, "sa_flags")?)?;
+        if let Some(restorer) = this.try_project_field_named(dest, "sa_restorer")? {
+            this.write_null(&restorer)?;
+        }
+        interp_ok(())
+    }
+
+    /// Reads a `sighandler_t`. On most targets that is an integer type, so programs store an
+    /// exposed function pointer in it. The kernel turns that back into a pointer, so we do the
+    /// same with a wildcard pointer (without the int-to-ptr warning; the program did not cast).
+    fn read_sighandler(
+        &self,
+        op: &impl Projectable<'tcx, Provenance>,
+    ) -> InterpResult<'tcx, SigHandler> {
+        let this = self.eval_context_ref();
+        let ptr = this.read_pointer(op)?;
+        let sig_ign = this.eval_libc("SIG_IGN").to_target_usize(this)?;
+        interp_ok(match ptr.addr().bytes() {
+            0 => SigHandler::Default,
+            addr if addr == sig_ign => SigHandler::Ignore,
+            addr if ptr.provenance.is_none() =>
+                SigHandler::Handler(Pointer::new(
+                    Some(Provenance::Wildcard),
+                    Size::from_bytes(addr),
+                )),
+            _ => SigHandler::Handler(ptr),
+        })
+    }
+
+    /// The inverse of `read_sighandler`: the program gets back the plain address.
+    fn sighandler_to_scalar(&self, handler: SigHandler) -> Scalar {
+        let this = self.eval_context_ref();
+        match handler {
+            SigHandler::Default => Scalar::from_target_usize(0, this),
+            SigHandler::Ignore => this.eval_libc("SIG_IGN"),
+            SigHandler::Handler(ptr) => Scalar::from_target_usize(ptr.addr().bytes(), this),
+        }
+    }
+
+    fn set_sigaction(&mut self, signum: i32, action: SigAction) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+        this.release_clock(|clock| this.machine.signals.clock.borrow_mut().join(clock))?;
+        this.machine.signals.actions.insert(signum, action);
+        // Signals that are now ignored are discarded, even if they are blocked.
+        if this.is_ignored(signum) {
+            this.machine.signals.discard(sig_bit(signum));
+            this.update_signalfds()?;
+        }
+        interp_ok(())
+    }
+
+    /// Generates a signal for the given thread, or for the whole process if `thread` is `None`.
+    fn generate_signal(&mut self, signum: i32, thread: Option<ThreadId>) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+        // Ignored signals are discarded right away, even if they are blocked.
+        if this.is_ignored(signum) {
+            return interp_ok(());
+        }
+        this.check_signal_receiver(signum, thread)?;
+        this.release_clock(|clock| this.machine.signals.clock.borrow_mut().join(clock))?;
+        let signals = &mut this.machine.signals;
+        match thread {
+            Some(thread) => signals.thread(thread).pending |= sig_bit(signum),
+            None => signals.pending |= sig_bit(signum),
+        }
+        this.update_signalfds()
+    }
+
+    /// Reports `signum` as unsupported if only a blocked thread can receive it. A real system would
+    /// interrupt the blocking operation right away, which we cannot do; delivering the signal only
+    /// once the thread is unblocked would run the handler too late.
+    fn check_signal_receiver(&self, signum: i32, target: Option<ThreadId>) -> InterpResult<'tcx> {
+        let this = self.eval_context_ref();
+        let blocks_signal = |thread: ThreadId| {
+            this.machine
+                .signals
+                .threads
+                .get(&thread)
+                .is_some_and(|state| state.mask & sig_bit(signum) != 0)
+        };
+        let receiver = match target {
+            Some(thread) => (!blocks_signal(thread)).then_some(thread),
+            None => {
+                // Any thread that does not block the signal may receive it, so we only have a
+                // problem if none of them can run.
+                let candidates: Vec<_> = this
+                    .machine
+                    .threads
+                    .all_threads()
+                    .filter(|&(id, thread)| !thread.is_terminated() && !blocks_signal(id))
+                    .collect();
+                if candidates.iter().any(|(_id, thread)| thread.is_enabled()) {
+                    None
+                } else {
+                    candidates.first().map(|&(id, _thread)| id)
+                }
+            }
+        };
+        if let Some(thread) = receiver
+            && !this.machine.threads.thread_ref(thread).is_enabled()
+        {
+            throw_unsup_format!(
+                "sending {} to thread `{}` while it is blocked is not supported, \
+                since Miri cannot interrupt blocking operations",
+                this.signal_name(signum),
+                this.machine.threads.get_thread_display_name(thread),
+            );
+        }
+        interp_ok(())
+    }
+
+    /// Takes the action for a signal that is being delivered to the active thread.
+    fn deliver_signal(&mut self, signum: i32, abort: bool) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+        let action = this.machine.signals.action(signum);
+        let handler = match action.handler {
+            SigHandler::Ignore => return interp_ok(()),
+            SigHandler::Default => {
+                if this.is_ignored_by_default(signum) {
+                    return interp_ok(());
+                }
+                let stop = ["SIGSTOP", "SIGTSTP", "SIGTTIN", "SIGTTOU"];
+                if stop.iter().any(|name| this.eval_libc_i32(name) == signum) {
+                    throw_unsup_format!(
+                        "stopping the process with {} is not supported",
+                        this.signal_name(signum)
+                    );
+                }
+                throw_machine_stop!(TerminationInfo::Abort(format!(
+                    "the program was terminated by signal {}",
+                    this.signal_name(signum)
+                )));
+            }
+            SigHandler::Handler(handler) => handler,
+        };
+        let handler = this.get_ptr_fn(handler)?.as_instance()?;
+        this.acquire_clock(&this.machine.signals.clock.borrow())?;
+
+        // Block the handler's signals while it runs, including the signal itself unless
+        // `SA_NODEFER` is set.
+        let mut handler_mask = action.mask;
+        if action.flags & this.eval_libc_i32("SA_NODEFER") == 0 {
+            handler_mask |= sig_bit(signum);
+        }
+        let unblockable = this.unblockable_signals();
+        let on_alt_stack = action.flags & this.eval_libc_i32("SA_ONSTACK") != 0;
+        let state = this.machine.signals.thread(this.machine.threads.active_thread());
+        let saved_mask = state.mask;
+        state.mask |= handler_mask & !unblockable;
+        let on_alt_stack = on_alt_stack && state.alt_stack.is_some();
+        if on_alt_stack {
+            state.on_alt_stack = state.on_alt_stack.strict_add(1);
+        }
+        if action.flags & this.eval_libc_i32("SA_RESETHAND") != 0 {
+            this.machine.signals.actions.remove(&signum);
+        }
+
+        let signum_arg = ImmTy::from_int(signum, this.machine.layouts.i32);
+        let (args, siginfo) = if action.flags & this.eval_libc_i32("SA_SIGINFO") != 0 {
+            let siginfo = this.allocate_siginfo(signum)?;
+            let siginfo_arg = ImmTy::from_scalar(
+                Scalar::from_maybe_pointer(siginfo, this),
+                this.machine.layouts.mut_raw_ptr,
+            );
+            let context_arg = ImmTy::from_scalar(
+                Scalar::from_target_usize(0, this),
+                this.machine.layouts.mut_raw_ptr,
+            );
+            (vec![signum_arg, siginfo_arg, context_arg], Some(siginfo))
+        } else {
+            (vec![signum_arg], None)
+        };
+
+        this.call_function(
+            handler,
+            ExternAbi::C { unwind: false },
+            &args,
+            None,
+            // The handler does not return to a particular block; `return_from_signal_handler`
+            // makes the interrupted frame continue where it was.
+            ReturnContinuation::Goto { ret: None, unwind: mir::UnwindAction::Unreachable },
+        )?;
+        this.frame_mut().extra.signal_handler =
+            Some(SignalHandlerFrame { saved_mask, siginfo, on_alt_stack, abort });
+        interp_ok(())
+    }
+
+    /// Allocates the `siginfo_t` for a signal that was sent by this process.
+    fn allocate_siginfo(&mut self, signum: i32) -> InterpResult<'tcx, Pointer> {
+        let this = self.eval_context_mut();
+        let siginfo =
+            this.allocate(this.libc_ty_layout("siginfo_t"), MiriMemoryKind::Machine.into())?;
+        this.write_bytes_ptr(
+            siginfo.ptr(),
+            std::iter::repeat_n(0, usize::try_from(siginfo.layout.size.bytes()).unwrap()),
+        )?;
+        // `SI_USER` is only available in the `libc` crate for some targets.
+        let si_user = match this.tcx.sess.target.os {
+            Os::MacOs | Os::FreeBsd => 0x10001,
+            _ => 0,
+        };
+        this.write_int_fields_named(
+            &[("si_signo", signum.into()), ("si_code", si_user)],
+            &siginfo,
+        )?;
+        // Not all targets expose the sender in a named field.
+        if let Some(pid) = this.try_project_field_named(&siginfo, "si_pid")? {
+            this.write_int(this.get_pid(), &pid)?;
+        }
+        if let Some(uid) = this.try_project_field_named(&siginfo, "si_uid")? {
+            this.write_int(super::UID, &uid)?;
+        }
+        interp_ok(siginfo.ptr())
+    }
+}
+
+impl<'tcx> EvalContextExt<'tcx> for crate::MiriInterpCx<'tcx> {}
+pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
+    fn sigaction(
+        &mut self,
+        signum: &OpTy<'tcx>,
+        act: &OpTy<'tcx>,
+        oldact: &OpTy<'tcx>,
+    ) -> InterpResult<'tcx, Scalar> {
+        let this = self.eval_context_mut();
+
+        let signum = this.read_scalar(signum)?.to_i32()?;
+        let act = this.read_pointer(act)?;
+        let oldact = this.read_pointer(oldact)?;
+
+        if !this.is_valid_signal(signum) {
+            return this.set_errno_and_return_neg1_i32(LibcError("EINVAL"));
+        }
+        let sigaction_layout = this.libc_ty_layout("sigaction");
+        let new = if this.ptr_is_null(act)? {
+            None
+        } else {
+            if this.unblockable_signals() & sig_bit(signum) != 0 {
+                return this.set_errno_and_return_neg1_i32(LibcError("EINVAL"));
+            }
+            Some(this.read_sigaction(&this.ptr_to_mplace(act, sigaction_layout))?)
+        };
+
+        if !this.ptr_is_null(oldact)? {
+            let old = this.machine.signals.action(signum);
+            this.write_sigaction(old, &this.ptr_to_mplace(oldact, sigaction_layout))?;
+        }
+        if let Some(new) = new {
+            this.set_sigaction(signum, new)?;
+        }
+        interp_ok(Scalar::from_i32(0))
+    }
+
+    fn signal(&mut self, signum: &OpTy<'tcx>, handler: &OpTy<'tcx>) -> InterpResult<'tcx, Scalar> {
+        let this = self.eval_context_mut();
+
+        let signum = this.read_scalar(signum)?.to_i32()?;
+        let handler = this.read_sighandler(handler)?;
+
+        if !this.is_valid_signal(signum) || this.unblockable_signals() & sig_bit(signum) != 0 {
+            this.set_last_error(LibcError("EINVAL"))?;
+            return interp_ok(this.eval_libc("SIG_ERR"));
+        }
+        let old = this.machine.signals.action(signum);
+        // `signal` has BSD semantics on all our targets: the handler stays installed, and
+        // interrupted system calls are restarted.
+        this.set_sigaction(
+            signum,
+            SigAction { handler, mask: 0, flags: this.eval_libc_i32("SA_RESTART") },
+        )?;
+        interp_ok(this.sighandler_to_scalar(old.handler))
+    }
+
+    /// Implements `sigprocmask` and `pthread_sigmask`, which behave the same in Miri since it
+    /// only emulates a single process.
+    fn sigprocmask(
+        &mut self,
+        how: &OpTy<'tcx>,
+        set: &OpTy<'tcx>,
+        oldset: &OpTy<'tcx>,
+    ) -> InterpResult<'tcx, Result<(), IoError>> {
+        let this = self.eval_context_mut();
+
+        let how = this.read_scalar(how)?.to_i32()?;
+        let set = this.read_pointer(set)?;
+        let oldset = this.read_pointer(oldset)?;
+
+        let thread = this.machine.threads.active_thread();
+        let old = this.machine.signals.thread(thread).mask;
+        if !this.ptr_is_null(set)? {
+            let set = this.deref_sigset(set)?;
+            let new = if how == this.eval_libc_i32("SIG_BLOCK") {
+                old | set
+            } else if how == this.eval_libc_i32("SIG_UNBLOCK") {
+                old & !set
+            } else if how == this.eval_libc_i32("SIG_SETMASK") {
+                set
+            } else {
+                return interp_ok(Err(LibcError("EINVAL")));
+            };
+            // Any signals this unblocks are delivered before the next step of this thread.
+            this.machine.signals.thread(thread).mask = new & !this.unblockable_signals();
+        }
+        if !this.ptr_is_null(oldset)? {
+            let oldset = this.ptr_to_mplace(oldset, this.libc_ty_layout("sigset_t"));
+            this.write_sigset(old, &oldset)?;
+        }
+        interp_ok(Ok(()))
+    }
+
+    fn sigpending(&mut self, set: &OpTy<'tcx>) -> InterpResult<'tcx, Scalar> {
+        let this = self.eval_context_mut();
+
+        let set = this.deref_pointer_as(set, this.libc_ty_layout("sigset_t"))?;
+        let thread = this.machine.threads.active_thread();
+        let process_pending = this.machine.signals.pending;
+        let state = this.machine.signals.thread(thread);
+        let pending = (state.pending | process_pending) & state.mask;
+        this.write_sigset(pending, &set)?;
+        interp_ok(Scalar::from_i32(0))
+    }
+
+    fn sigaltstack(&mut self, ss: &OpTy<'tcx>, old_ss: &OpTy<'tcx>) -> InterpResult<'tcx, Scalar> {
+        let this = self.eval_context_mut();
+
+        let ss = this.read_pointer(ss)?;
+        let old_ss = this.read_pointer(old_ss)?;
+        let stack_t = this.libc_ty_layout("stack_t");
+        let thread = this.machine.threads.active_thread();
+        let ss_disable = this.eval_libc_i32("SS_DISABLE");
+
+        // Read the new stack before writing the old one, the two may overlap.
+        let new = if this.ptr_is_null(ss)? {
+            None
+        } else {
+            let ss = this.ptr_to_mplace(ss, stack_t);
+            let sp = this.read_pointer(&this.project_field_named(&ss, "ss_sp")?)?;
+            let size = this.read_target_usize(&this.project_field_named(&ss, "ss_size")?)?;
+            let flags = this.read_scalar(&this.project_field_named(&ss, "ss_flags")?)?.to_i32()?;
+            if flags == ss_disable {
+                Some(None)
+            } else if flags == 0 {
+                Some(Some((sp, size)))
+            } else {
+                return this.set_errno_and_return_neg1_i32(LibcError("EINVAL"));
+            }
+        };
+
+        let state = this.machine.signals.thread(thread).clone();
+        if !this.ptr_is_null(old_ss)? {
+            let old_ss = this.ptr_to_mplace(old_ss, stack_t);
+            let flags = if state.on_alt_stack > 0 {
+                this.eval_libc_i32("SS_ONSTACK")
+            } else if state.alt_stack.is_none() {
+                ss_disable
+            } else {
+                0
+            };
+            let (sp, size) = state.alt_stack.unwrap_or((Pointer::null(), 0));
+            this.write_pointer(sp, &this.project_field_named(&old_ss, "ss_sp")?)?;
+            this.write_int(size, &this.project_field_named(&old_ss, "ss_size")?)?;
+            this.write_int(flags, &this.project_field_named(&old_ss, "ss_flags")?)?;
+        }
+        if let Some(new) = new {
+            // The stack cannot be changed while a handler is running on it.
+            if state.on_alt_stack > 0 {
+                return this.set_errno_and_return_neg1_i32(LibcError("EPERM"));
+            }
+            this.machine.signals.thread(thread).alt_stack = new;
+        }
+        interp_ok(Scalar::from_i32(0))
+    }
+
+    /// Implements `sigemptyset`, `sigfillset`, `sigaddset`, `sigdelset`, and `sigismember`.
+    fn sigset_op(
+        &mut self,
+        name: &str,
+        set: &OpTy<'tcx>,
+        signum: Option<&OpTy<'tcx>>,
+    ) -> InterpResult<'tcx, Scalar> {
+        let this = self.eval_context_mut();
+
+        let set = this.deref_pointer_as(set, this.libc_ty_layout("sigset_t"))?;
+        let bit = match signum {
+            Some(signum) => {
+                let signum = this.read_scalar(signum)?.to_i32()?;
+                if !this.is_valid_signal(signum) {
+                    return this.set_errno_and_return_neg1_i32(LibcError("EINVAL"));
+                }
+                sig_bit(signum)
+            }
+            None => 0,
+        };
+        let result = match name {
+            "sigemptyset" => 0,
+            "sigfillset" => sig_bit(this.max_signal()) | sig_bit(this.max_signal()).strict_sub(1),
+            "sigaddset" => this.read_sigset(&set)? | bit,
+            "sigdelset" => this.read_sigset(&set)? & !bit,
+            "sigismember" => {
+                let is_member = this.read_sigset(&set)? & bit != 0;
+                return interp_ok(Scalar::from_i32(is_member.into()));
+            }
+            _ => bug!("unknown signal set operation {name}"),
+        };
+        this.write_sigset(result, &set)?;
+        interp_ok(Scalar::from_i32(0))
+    }
+
+    fn raise(&mut self, signum: &OpTy<'tcx>) -> InterpResult<'tcx, Scalar> {
+        let this = self.eval_context_mut();
+
+        let signum = this.read_scalar(signum)?.to_i32()?;
+        if !this.is_valid_signal(signum) {
+            return this.set_errno_and_return_neg1_i32(LibcError("EINVAL"));
+        }
+        this.generate_signal(signum, Some(this.machine.threads.active_thread()))?;
+        interp_ok(Scalar::from_i32(0))
+    }
+
+    fn kill(&mut self, pid: &OpTy<'tcx>, signum: &OpTy<'tcx>) -> InterpResult<'tcx, Scalar> {
+        let this = self.eval_context_mut();
+
+        let pid = this.read_scalar(pid)?.to_i32()?;
+        let signum = this.read_scalar(signum)?.to_i32()?;
+
+        if let Ok(child) = u32::try_from(pid)
+            && this.is_child_process(child)
+        {
+            if signum == 0 {
+                return interp_ok(Scalar::from_i32(0));
+            }
+            if !this.is_valid_signal(signum) {
+                return this.set_errno_and_return_neg1_i32(LibcError("EINVAL"));
+            }
+            let name = this.signal_name(signum);
+            return this.kill_child_process(child, &name);
+        }
+        // `0` addresses our process group, which we assume to consist of just us.
+        if pid != 0 && u32::try_from(pid).ok() != Some(this.get_pid()) {
+            throw_unsup_format!(
+                "`kill` is only supported on the current process and its child processes"
+            );
+        }
+        if signum == 0 {
+            // This only checks whether the process exists.
+            return interp_ok(Scalar::from_i32(0));
+        }
+        if !this.is_valid_signal(signum) {
+            return this.set_errno_and_return_neg1_i32(LibcError("EINVAL"));
+        }
+        this.generate_signal(signum, None)?;
+        interp_ok(Scalar::from_i32(0))
+    }
+
+    fn pthread_kill(
+        &mut self,
+        thread: &OpTy<'tcx>,
+        signum: &OpTy<'tcx>,
+    ) -> InterpResult<'tcx, Scalar> {
+        let this = self.eval_context_mut();
+
+        let thread = this.read_scalar(thread)?.to_int(this.libc_ty_layout("pthread_t").size)?;
+        let signum = this.read_scalar(signum)?.to_i32()?;
+
+        let Ok(thread) = this.thread_id_try_from(thread) else {
+            return interp_ok(this.eval_libc("ESRCH"));
+        };
+        if signum == 0 {
+            return interp_ok(Scalar::from_i32(0));
+        }
+        if !this.is_valid_signal(signum) {
+            return interp_ok(this.eval_libc("EINVAL"));
+        }
+        this.generate_signal(signum, Some(thread))?;
+        interp_ok(Scalar::from_i32(0))
+    }
+
+    /// Reads the signal set that `ptr` points to, without the signals that cannot be blocked.
+    fn deref_blockable_sigset(&self, ptr: Pointer) -> InterpResult<'tcx, SigSet> {
+        let this = self.eval_context_ref();
+        interp_ok(this.deref_sigset(ptr)? & !this.unblockable_signals())
+    }
+
+    /// Accepts a signal in `set` that is pending for the active thread without delivering it,
+    /// which is what reading from a `signalfd` does. Like Linux, this prefers signals directed
+    /// at the thread over those sent to the whole process, and lower-numbered signals otherwise.
+    fn accept_pending_signal(&mut self, set: SigSet) -> InterpResult<'tcx, Option<i32>> {
+        let this = self.eval_context_mut();
+        let signals = &mut this.machine.signals;
+        let thread = signals.thread(this.machine.threads.active_thread());
+        let pending = if thread.pending & set != 0 {
+            &mut thread.pending
+        } else if signals.pending & set != 0 {
+            &mut signals.pending
+        } else {
+            return interp_ok(None);
+        };
+        let signum = i32::try_from((*pending & set).trailing_zeros()).unwrap().strict_add(1);
+        *pending &= !sig_bit(signum);
+        this.acquire_clock(&this.machine.signals.clock.borrow())?;
+        this.update_signalfds()?;
+        interp_ok(Some(signum))
+    }
+
+    /// Delivers the lowest-numbered pending signal that the active thread does not block, if any.
+    /// Called right before the active thread takes a step.
+    fn deliver_pending_signal(&mut self) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+        let thread = this.machine.threads.active_thread();
+        let Some(signum) = this.machine.signals.next_deliverable(thread) else {
+            return interp_ok(());
+        };
+        if this.active_thread_stack().is_empty() {
+            // There is no frame to interrupt.
+            return interp_ok(());
+        }
+        let signals = &mut this.machine.signals;
+        if signals.thread(thread).pending & sig_bit(signum) != 0 {
+            signals.thread(thread).pending &= !sig_bit(signum);
+        } else {
+            signals.pending &= !sig_bit(signum);
+        }
+        this.update_signalfds()?;
+        this.deliver_signal(signum, /* abort */ false)
+    }
+
+    /// Called by `abort`: runs the `SIGABRT` handler if there is one, and returns whether it did.
+    /// Like on real systems, the handler runs even if `SIGABRT` is blocked, and the program is
+    /// terminated once it returns.
+    fn run_abort_handler(&mut self) -> InterpResult<'tcx, bool> {
+        let this = self.eval_context_mut();
+        let sigabrt = this.eval_libc_i32("SIGABRT");
+        if !matches!(this.machine.signals.action(sigabrt).handler, SigHandler::Handler(_)) {
+            return interp_ok(false);
+        }
+        let thread = this.machine.threads.active_thread();
+        this.machine.signals.thread(thread).mask &= !sig_bit(sigabrt);
+        this.deliver_signal(sigabrt, /* abort */ true)?;
+        interp_ok(true)
+    }
+
+    /// Called when the frame of a signal handler is popped.
+    fn return_from_signal_handler(
+        &mut self,
+        frame: SignalHandlerFrame,
+        unwinding: bool,
+    ) -> InterpResult<'tcx, ReturnAction> {
+        let this = self.eval_context_mut();
+        let SignalHandlerFrame { saved_mask, siginfo, on_alt_stack, abort } = frame;
+
+        let state = this.machine.signals.thread(this.machine.threads.active_thread());
+        state.mask = saved_mask;
+        if on_alt_stack {
+            state.on_alt_stack = state.on_alt_stack.strict_sub(1);
+        }
+        if let Some(siginfo) = siginfo {
+            this.deallocate_ptr(siginfo, None, MiriMemoryKind::Machine.into())?;
+        }
+        if unwinding {
+            // Unwinding out of a handler is UB, which the `Unreachable` unwind action reports.
+            return interp_ok(ReturnAction::Normal);
+        }
+        if abort {
+            throw_machine_stop!(TerminationInfo::Abort("the program aborted execution".to_owned()));
+        }
+        // Continue the interrupted frame where it left off.
+        interp_ok(ReturnAction::NoJump)
+    }
+}
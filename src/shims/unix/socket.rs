@@ -1,16 +1,22 @@
+use std::cell::Cell;
 use std::net::{Shutdown, SocketAddr};
+use std::time::Duration;
 
-use rustc_abi::Size;
+use rustc_abi::{Align, Size};
 use rustc_target::spec::Os;
 
 use crate::shims::FileDescriptionRef;
 use crate::shims::files::FdNum;
 use crate::shims::unix::UnixFileDescription;
-use crate::shims::unix::socket_address::EvalContextExt as _;
+use crate::shims::unix::fd::EvalContextExt as _;
+use crate::shims::unix::loopback_socket::EvalContextExt as _;
+use crate::shims::unix::socket_address::{EvalContextExt as _, SocketAddress};
 use crate::shims::unix::tcp_socket::TcpSocket;
+use crate::shims::unix::udp_socket::UdpSocket;
+use crate::shims::unix::unix_socket::EvalContextExt as _;
 use crate::*;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum SocketFamily {
     // IPv4 internet protocols
     IPv4,
@@ -18,15 +24,23 @@ pub enum SocketFamily {
     IPv6,
 }
 
-/// Represents unix-specific socket file descriptions.
-///
-/// Not to be confused with Unix domain sockets.
+impl SocketFamily {
+    /// The family `address` belongs to.
+    pub fn of(address: &SocketAddr) -> Self {
+        match address {
+            SocketAddr::V4(_) => SocketFamily::IPv4,
+            SocketAddr::V6(_) => SocketFamily::IPv6,
+        }
+    }
+}
+
+/// Represents unix-specific socket file descriptions, of any address family.
 pub trait UnixSocketFileDescription: UnixFileDescription {
     /// Bind the socket to `address`.
     fn bind<'tcx>(
         self: FileDescriptionRef<Self>,
         _communicate_allowed: bool,
-        _address: SocketAddr,
+        _address: SocketAddress,
         _ecx: &mut MiriInterpCx<'tcx>,
     ) -> InterpResult<'tcx, Result<(), IoError>> {
         throw_unsup_format!("cannot bind {}", self.name());
@@ -54,7 +68,7 @@ pub trait UnixSocketFileDescription: UnixFileDescription {
         _communicate_allowed: bool,
         _is_client_sock_non_block: bool,
         _ecx: &mut MiriInterpCx<'tcx>,
-        _finish: DynMachineCallback<'tcx, Result<(FdNum, SocketAddr), IoError>>,
+        _finish: DynMachineCallback<'tcx, Result<(FdNum, SocketAddress), IoError>>,
     ) -> InterpResult<'tcx> {
         throw_unsup_format!("cannot accept {}", self.name());
     }
@@ -63,7 +77,7 @@ pub trait UnixSocketFileDescription: UnixFileDescription {
     fn connect<'tcx>(
         self: FileDescriptionRef<Self>,
         _communicate_allowed: bool,
-        _address: SocketAddr,
+        _address: SocketAddress,
         _ecx: &mut MiriInterpCx<'tcx>,
         _finish: DynMachineCallback<'tcx, Result<(), IoError>>,
     ) -> InterpResult<'tcx> {
@@ -105,6 +119,53 @@ pub trait UnixSocketFileDescription: UnixFileDescription {
         throw_unsup_format!("cannot send to {}", self.name());
     }
 
+    /// Receive a message on the socket into the given buffer `ptr`, like [`Self::recv`].
+    /// After a successful receive, `finish` should be called with the amount of bytes
+    /// received and the address the message was sent from. Connection-oriented sockets
+    /// don't report that address, so by default this is just [`Self::recv`] with [`None`]
+    /// as the address.
+    fn recv_from<'tcx>(
+        self: FileDescriptionRef<Self>,
+        communicate_allowed: bool,
+        ptr: Pointer,
+        len: usize,
+        is_peek: bool,
+        is_non_block: bool,
+        ecx: &mut MiriInterpCx<'tcx>,
+        finish: DynMachineCallback<'tcx, Result<(usize, Option<SocketAddress>), IoError>>,
+    ) -> InterpResult<'tcx> {
+        self.recv(
+            communicate_allowed,
+            ptr,
+            len,
+            is_peek,
+            is_non_block,
+            ecx,
+            callback!(
+                @capture<'tcx> {
+                    finish: DynMachineCallback<'tcx, Result<(usize, Option<SocketAddress>), IoError>>,
+                } |this, result: Result<usize, IoError>| {
+                    finish.call(this, result.map(|bytes_received| (bytes_received, None)))
+                }
+            ),
+        )
+    }
+
+    /// Send data from the given buffer `ptr` to `address`, like [`Self::send`].
+    /// This is only meaningful for connectionless sockets.
+    fn send_to<'tcx>(
+        self: FileDescriptionRef<Self>,
+        _communicate_allowed: bool,
+        _ptr: Pointer,
+        _len: usize,
+        _address: SocketAddress,
+        _is_non_block: bool,
+        _ecx: &mut MiriInterpCx<'tcx>,
+        _finish: DynMachineCallback<'tcx, Result<usize, IoError>>,
+    ) -> InterpResult<'tcx> {
+        throw_unsup_format!("cannot send to an explicit address on {}", self.name());
+    }
+
     /// Set the socket option `option` on `level`.
     /// `value_ptr` points to the new value of the socket option, and `value_len` contains
     /// the amount of bytes the value uses at `value_ptr`.
@@ -134,7 +195,7 @@ pub trait UnixSocketFileDescription: UnixFileDescription {
         self: FileDescriptionRef<Self>,
         _communicate_allowed: bool,
         _ecx: &mut MiriInterpCx<'tcx>,
-    ) -> InterpResult<'tcx, Result<SocketAddr, IoError>> {
+    ) -> InterpResult<'tcx, Result<SocketAddress, IoError>> {
         throw_unsup_format!("cannot get socket name for {}", self.name());
     }
 
@@ -143,7 +204,7 @@ pub trait UnixSocketFileDescription: UnixFileDescription {
         self: FileDescriptionRef<Self>,
         _communicate_allowed: bool,
         _ecx: &mut MiriInterpCx<'tcx>,
-        _finish: DynMachineCallback<'tcx, Result<SocketAddr, IoError>>,
+        _finish: DynMachineCallback<'tcx, Result<SocketAddress, IoError>>,
     ) -> InterpResult<'tcx> {
         throw_unsup_format!("cannot get peer name for {}", self.name());
     }
@@ -175,12 +236,6 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         let mut flags = this.read_scalar(type_)?.to_i32()?;
         let protocol = this.read_scalar(protocol)?.to_i32()?;
 
-        // Reject if isolation is enabled
-        if let IsolatedOp::Reject(reject_with) = this.machine.isolated_op {
-            this.reject_in_isolation("`socket`", reject_with)?;
-            return this.set_errno_and_return_neg1_i32(LibcError("EACCES"));
-        }
-
         let mut is_non_block = false;
 
         // Interpret the flag. Every flag we recognize is "subtracted" from `flags`, so
@@ -203,36 +258,94 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
             }
         }
 
+        // AF_UNIX and AF_LOCAL are synonyms, so we accept both in case
+        // their values differ.
+        if domain == this.eval_libc_i32("AF_UNIX") || domain == this.eval_libc_i32("AF_LOCAL") {
+            if protocol != 0 {
+                throw_unsup_format!(
+                    "socket: socket protocol {protocol} is unsupported for AF_UNIX, \
+                only 0 is allowed"
+                );
+            }
+            // Unix domain sockets never communicate with the host, so they are
+            // also available with isolation enabled.
+            let Some(fd_num) = this.new_unix_socket(flags, is_non_block) else {
+                throw_unsup_format!(
+                    "socket: type {:#x} is unsupported for AF_UNIX, only SOCK_STREAM, \
+                SOCK_DGRAM, SOCK_CLOEXEC and SOCK_NONBLOCK are allowed",
+                    flags
+                );
+            };
+            return interp_ok(Scalar::from_i32(fd_num));
+        }
+
+        // With `-Zmiri-virtual-network`, TCP sockets use the in-memory loopback network,
+        // which is only available with isolation enabled.
+        if this.machine.loopback_network.is_some() && flags == this.eval_libc_i32("SOCK_STREAM") {
+            let family = if domain == this.eval_libc_i32("AF_INET") {
+                SocketFamily::IPv4
+            } else if domain == this.eval_libc_i32("AF_INET6") {
+                SocketFamily::IPv6
+            } else {
+                throw_unsup_format!(
+                    "socket: domain {:#x} is unsupported for SOCK_STREAM on the virtual network, \
+                only AF_INET, AF_INET6 and AF_UNIX are allowed.",
+                    domain
+                );
+            };
+            if protocol != 0 && protocol != this.eval_libc_i32("IPPROTO_TCP") {
+                throw_unsup_format!(
+                    "socket: socket protocol {protocol} is unsupported for SOCK_STREAM, \
+                only IPPROTO_TCP and 0 are allowed"
+                );
+            }
+            let fd_num = this.new_loopback_socket(family, is_non_block);
+            return interp_ok(Scalar::from_i32(fd_num));
+        }
+
+        // Reject if isolation is enabled
+        if let IsolatedOp::Reject(reject_with) = this.machine.isolated_op {
+            this.reject_in_isolation("`socket`", reject_with)?;
+            return this.set_errno_and_return_neg1_i32(LibcError("EACCES"));
+        }
+
         let family = if domain == this.eval_libc_i32("AF_INET") {
             SocketFamily::IPv4
         } else if domain == this.eval_libc_i32("AF_INET6") {
             SocketFamily::IPv6
         } else {
             throw_unsup_format!(
-                "socket: domain {:#x} is unsupported, only AF_INET and \
-            AF_INET6 are allowed.",
+                "socket: domain {:#x} is unsupported, only AF_INET, \
+            AF_INET6 and AF_UNIX are allowed.",
                 domain
             );
         };
 
-        if flags != this.eval_libc_i32("SOCK_STREAM") {
+        let fd_num = if flags == this.eval_libc_i32("SOCK_STREAM") {
+            if protocol != 0 && protocol != this.eval_libc_i32("IPPROTO_TCP") {
+                throw_unsup_format!(
+                    "socket: socket protocol {protocol} is unsupported for SOCK_STREAM, \
+                only IPPROTO_TCP and 0 are allowed"
+                );
+            }
+            this.machine.fds.insert_new(TcpSocket::new(family, is_non_block))
+        } else if flags == this.eval_libc_i32("SOCK_DGRAM") {
+            if protocol != 0 && protocol != this.eval_libc_i32("IPPROTO_UDP") {
+                throw_unsup_format!(
+                    "socket: socket protocol {protocol} is unsupported for SOCK_DGRAM, \
+                only IPPROTO_UDP and 0 are allowed"
+                );
+            }
+            this.machine.fds.insert_new(UdpSocket::new(family, is_non_block))
+        } else {
             throw_unsup_format!(
-                "socket: type {:#x} is unsupported, only SOCK_STREAM, \
+                "socket: type {:#x} is unsupported, only SOCK_STREAM, SOCK_DGRAM, \
             SOCK_CLOEXEC and SOCK_NONBLOCK are allowed",
                 flags
             );
-        }
-        if protocol != 0 && protocol != this.eval_libc_i32("IPPROTO_TCP") {
-            throw_unsup_format!(
-                "socket: socket protocol {protocol} is unsupported, \
-            only IPPROTO_TCP and 0 are allowed"
-            );
-        }
-
-        let fds = &mut this.machine.fds;
-        let fd = fds.new_ref(TcpSocket::new(family, is_non_block));
+        };
 
-        interp_ok(Scalar::from_i32(fds.insert(fd)))
+        interp_ok(Scalar::from_i32(fd_num))
     }
 
     fn bind(
@@ -352,7 +465,7 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
                     address_ptr: Pointer,
                     address_len_ptr: Pointer,
                     dest: MPlaceTy<'tcx>
-                } |this, result: Result<(FdNum, SocketAddr), IoError>| {
+                } |this, result: Result<(FdNum, SocketAddress), IoError>| {
                     let (client_sockfd, address) = match result {
                         Ok(data) => data,
                         Err(e) => return this.set_errno_and_return_neg1(e, &dest),
@@ -435,7 +548,7 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         let size_layout = this.libc_ty_layout("size_t");
         let length: usize =
             this.read_scalar(length)?.to_uint(size_layout.size)?.try_into().unwrap();
-        let mut flags = this.read_scalar(flags)?.to_i32()?;
+        let flags = this.read_scalar(flags)?.to_i32()?;
 
         // Get the file handle
         let Some(fd) = this.machine.fds.get(socket) else {
@@ -446,35 +559,7 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
             return this.set_errno_and_return_neg1(LibcError("ENOTSOCK"), dest);
         };
 
-        let mut is_non_block = false;
-
-        // Interpret the flag. Every flag we recognize is "subtracted" from `flags`, so
-        // if there is anything left at the end, that's an unsupported flag.
-        if matches!(
-            this.tcx.sess.target.os,
-            Os::Linux | Os::Android | Os::FreeBsd | Os::Solaris | Os::Illumos
-        ) {
-            // MSG_NOSIGNAL and MSG_DONTWAIT only exist on Linux, Android, FreeBSD,
-            // Solaris, and Illumos targets.
-            let msg_nosignal = this.eval_libc_i32("MSG_NOSIGNAL");
-            let msg_dontwait = this.eval_libc_i32("MSG_DONTWAIT");
-            if flags & msg_nosignal == msg_nosignal {
-                // This is only needed to ensure that no EPIPE signal is sent when
-                // trying to send into a stream which is no longer connected.
-                // Since we don't support signals, we can ignore this.
-                flags &= !msg_nosignal;
-            }
-            if flags & msg_dontwait == msg_dontwait {
-                flags &= !msg_dontwait;
-                is_non_block = true;
-            }
-        }
-
-        if flags != 0 {
-            throw_unsup_format!(
-                "send: flag {flags:#x} is unsupported, only MSG_NOSIGNAL and MSG_DONTWAIT are allowed",
-            );
-        }
+        let is_non_block = this.parse_send_flags(flags, "send")?;
 
         let dest = dest.clone();
 
@@ -498,12 +583,16 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         )
     }
 
-    fn recv(
+    /// For more information on the arguments see the sendto manpage:
+    /// <https://man7.org/linux/man-pages/man2/sendto.2.html>
+    fn sendto(
         &mut self,
         socket: &OpTy<'tcx>,
         buffer: &OpTy<'tcx>,
         length: &OpTy<'tcx>,
         flags: &OpTy<'tcx>,
+        address: &OpTy<'tcx>,
+        address_len: &OpTy<'tcx>,
         // Location where the output scalar is written to.
         dest: &MPlaceTy<'tcx>,
     ) -> InterpResult<'tcx> {
@@ -514,7 +603,16 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         let size_layout = this.libc_ty_layout("size_t");
         let length: usize =
             this.read_scalar(length)?.to_uint(size_layout.size)?.try_into().unwrap();
-        let mut flags = this.read_scalar(flags)?.to_i32()?;
+        let flags = this.read_scalar(flags)?.to_i32()?;
+        // A null address means that this is just a `send`.
+        let address = if this.ptr_is_null(this.read_pointer(address)?)? {
+            None
+        } else {
+            match this.read_socket_address(address, address_len, "sendto")? {
+                Ok(address) => Some(address),
+                Err(e) => return this.set_errno_and_return_neg1(e, dest),
+            }
+        };
 
         // Get the file handle
         let Some(fd) = this.machine.fds.get(socket) else {
@@ -525,47 +623,131 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
             return this.set_errno_and_return_neg1(LibcError("ENOTSOCK"), dest);
         };
 
-        let mut is_peek = false;
-        let mut is_non_block = false;
+        let is_non_block = this.parse_send_flags(flags, "sendto")?;
 
-        // Interpret the flag. Every flag we recognize is "subtracted" from `flags`, so
-        // if there is anything left at the end, that's an unsupported flag.
+        let dest = dest.clone();
 
-        let msg_peek = this.eval_libc_i32("MSG_PEEK");
-        if flags & msg_peek == msg_peek {
-            is_peek = true;
-            flags &= !msg_peek;
-        }
+        this.send_to_socket(
+            socket,
+            buffer_ptr,
+            length,
+            address,
+            is_non_block,
+            callback!(
+                @capture<'tcx> {
+                    dest: MPlaceTy<'tcx>,
+                } |this, result: Result<usize, IoError>| {
+                    match result {
+                        Ok(bytes_sent) =>
+                            this.write_scalar(Scalar::from_target_usize(bytes_sent.try_into().unwrap(), this), &dest),
+                        Err(e) => this.set_errno_and_return_neg1(e, &dest)
+                    }
+                }
+            ),
+        )
+    }
 
-        if matches!(this.tcx.sess.target.os, Os::Linux | Os::Android | Os::FreeBsd | Os::Illumos) {
-            // MSG_CMSG_CLOEXEC only exists on Linux, Android, FreeBSD,
-            // and Illumos targets.
-            let msg_cmsg_cloexec = this.eval_libc_i32("MSG_CMSG_CLOEXEC");
-            if flags & msg_cmsg_cloexec == msg_cmsg_cloexec {
-                // We don't support `exec` so we can ignore this.
-                flags &= !msg_cmsg_cloexec;
-            }
+    /// For more information on the arguments see the sendmsg manpage:
+    /// <https://man7.org/linux/man-pages/man2/sendmsg.2.html>
+    fn sendmsg(
+        &mut self,
+        socket: &OpTy<'tcx>,
+        message: &OpTy<'tcx>,
+        flags: &OpTy<'tcx>,
+        // Location where the output scalar is written to.
+        dest: &MPlaceTy<'tcx>,
+    ) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+
+        let socket = this.read_scalar(socket)?.to_i32()?;
+        let message = this.deref_pointer_as(message, this.libc_ty_layout("msghdr"))?;
+        let flags = this.read_scalar(flags)?.to_i32()?;
+
+        // Get the file handle
+        let Some(fd) = this.machine.fds.get(socket) else {
+            return this.set_errno_and_return_neg1(LibcError("EBADF"), dest);
+        };
+
+        let Some(socket) = fd.as_unix(this).as_socket(this) else {
+            return this.set_errno_and_return_neg1(LibcError("ENOTSOCK"), dest);
+        };
+
+        let is_non_block = this.parse_send_flags(flags, "sendmsg")?;
+
+        let control_len_field = this.project_field_named(&message, "msg_controllen")?;
+        if this.read_scalar(&control_len_field)?.to_int(control_len_field.layout.size)? != 0 {
+            throw_unsup_format!("sendmsg: sending ancillary data is unsupported");
         }
 
-        if matches!(
-            this.tcx.sess.target.os,
-            Os::Linux | Os::Android | Os::FreeBsd | Os::Solaris | Os::Illumos
-        ) {
-            // MSG_DONTWAIT only exists on Linux, Android, FreeBSD,
-            // Solaris, and Illumos targets.
-            let msg_dontwait = this.eval_libc_i32("MSG_DONTWAIT");
-            if flags & msg_dontwait == msg_dontwait {
-                flags &= !msg_dontwait;
-                is_non_block = true;
+        let name_field = this.project_field_named(&message, "msg_name")?;
+        let name_len_field = this.project_field_named(&message, "msg_namelen")?;
+        // A null address means that the message is sent to the connected peer.
+        let address = if this.ptr_is_null(this.read_pointer(&name_field)?)? {
+            None
+        } else {
+            match this.read_socket_address(&name_field.into(), &name_len_field.into(), "sendmsg")? {
+                Ok(address) => Some(address),
+                Err(e) => return this.set_errno_and_return_neg1(e, dest),
             }
-        }
+        };
 
-        if flags != 0 {
-            throw_unsup_format!(
-                "recv: flag {flags:#x} is unsupported, only MSG_PEEK, MSG_DONTWAIT \
-                and MSG_CMSG_CLOEXEC are allowed",
-            );
-        }
+        let (iov_ptr, iovcnt) = this.read_msghdr_iov(&message)?;
+        let buffers = this.read_iovecs(iov_ptr, iovcnt)?;
+        // Collect all buffers into one temporary buffer such that the message is sent as a whole.
+        let (tmp_ptr, total_bytes) = this.gather_iovecs(&buffers)?;
+
+        let dest = dest.clone();
+
+        this.send_to_socket(
+            socket,
+            tmp_ptr,
+            usize::try_from(total_bytes).unwrap(),
+            address,
+            is_non_block,
+            callback!(
+                @capture<'tcx> {
+                    tmp_ptr: Pointer,
+                    dest: MPlaceTy<'tcx>,
+                } |this, result: Result<usize, IoError>| {
+                    this.deallocate_ptr(tmp_ptr, None, MemoryKind::Stack)?;
+                    match result {
+                        Ok(bytes_sent) =>
+                            this.write_scalar(Scalar::from_target_usize(bytes_sent.try_into().unwrap(), this), &dest),
+                        Err(e) => this.set_errno_and_return_neg1(e, &dest)
+                    }
+                }
+            ),
+        )
+    }
+
+    fn recv(
+        &mut self,
+        socket: &OpTy<'tcx>,
+        buffer: &OpTy<'tcx>,
+        length: &OpTy<'tcx>,
+        flags: &OpTy<'tcx>,
+        // Location where the output scalar is written to.
+        dest: &MPlaceTy<'tcx>,
+    ) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+
+        let socket = this.read_scalar(socket)?.to_i32()?;
+        let buffer_ptr = this.read_pointer(buffer)?;
+        let size_layout = this.libc_ty_layout("size_t");
+        let length: usize =
+            this.read_scalar(length)?.to_uint(size_layout.size)?.try_into().unwrap();
+        let flags = this.read_scalar(flags)?.to_i32()?;
+
+        // Get the file handle
+        let Some(fd) = this.machine.fds.get(socket) else {
+            return this.set_errno_and_return_neg1(LibcError("EBADF"), dest);
+        };
+
+        let Some(socket) = fd.as_unix(this).as_socket(this) else {
+            return this.set_errno_and_return_neg1(LibcError("ENOTSOCK"), dest);
+        };
+
+        let (is_peek, is_non_block) = this.parse_recv_flags(flags, "recv")?;
 
         let dest = dest.clone();
 
@@ -590,6 +772,165 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         )
     }
 
+    /// For more information on the arguments see the recvfrom manpage:
+    /// <https://man7.org/linux/man-pages/man2/recvfrom.2.html>
+    fn recvfrom(
+        &mut self,
+        socket: &OpTy<'tcx>,
+        buffer: &OpTy<'tcx>,
+        length: &OpTy<'tcx>,
+        flags: &OpTy<'tcx>,
+        address: &OpTy<'tcx>,
+        address_len: &OpTy<'tcx>,
+        // Location where the output scalar is written to.
+        dest: &MPlaceTy<'tcx>,
+    ) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+
+        let socket = this.read_scalar(socket)?.to_i32()?;
+        let buffer_ptr = this.read_pointer(buffer)?;
+        let size_layout = this.libc_ty_layout("size_t");
+        let length: usize =
+            this.read_scalar(length)?.to_uint(size_layout.size)?.try_into().unwrap();
+        let flags = this.read_scalar(flags)?.to_i32()?;
+        let address_ptr = this.read_pointer(address)?;
+        let address_len_ptr = this.read_pointer(address_len)?;
+
+        // Get the file handle
+        let Some(fd) = this.machine.fds.get(socket) else {
+            return this.set_errno_and_return_neg1(LibcError("EBADF"), dest);
+        };
+
+        let Some(socket) = fd.as_unix(this).as_socket(this) else {
+            return this.set_errno_and_return_neg1(LibcError("ENOTSOCK"), dest);
+        };
+
+        let (is_peek, is_non_block) = this.parse_recv_flags(flags, "recvfrom")?;
+
+        let dest = dest.clone();
+
+        socket.recv_from(
+            this.machine.communicate(),
+            buffer_ptr,
+            length,
+            is_peek,
+            is_non_block,
+            this,
+            callback!(
+                @capture<'tcx> {
+                    address_ptr: Pointer,
+                    address_len_ptr: Pointer,
+                    dest: MPlaceTy<'tcx>,
+                } |this, result: Result<(usize, Option<SocketAddress>), IoError>| {
+                    let (bytes_received, address) = match result {
+                        Ok(data) => data,
+                        Err(e) => return this.set_errno_and_return_neg1(e, &dest),
+                    };
+
+                    if address_ptr != Pointer::null() {
+                        // We only attempt a write if the address pointer is not a null pointer.
+                        // If the address pointer is a null pointer the user isn't interested in the
+                        // address and we don't need to write anything.
+                        this.write_received_address(address, address_ptr, address_len_ptr, "recvfrom")?;
+                    }
+
+                    this.write_scalar(Scalar::from_target_usize(bytes_received.try_into().unwrap(), this), &dest)
+                }
+            ),
+        )
+    }
+
+    /// For more information on the arguments see the recvmsg manpage:
+    /// <https://man7.org/linux/man-pages/man2/recvmsg.2.html>
+    ///
+    /// Ancillary data is never received, i.e., `msg_controllen` is always set to zero.
+    fn recvmsg(
+        &mut self,
+        socket: &OpTy<'tcx>,
+        message: &OpTy<'tcx>,
+        flags: &OpTy<'tcx>,
+        // Location where the output scalar is written to.
+        dest: &MPlaceTy<'tcx>,
+    ) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+
+        let socket = this.read_scalar(socket)?.to_i32()?;
+        let message = this.deref_pointer_as(message, this.libc_ty_layout("msghdr"))?;
+        let flags = this.read_scalar(flags)?.to_i32()?;
+
+        // Get the file handle
+        let Some(fd) = this.machine.fds.get(socket) else {
+            return this.set_errno_and_return_neg1(LibcError("EBADF"), dest);
+        };
+
+        let Some(socket) = fd.as_unix(this).as_socket(this) else {
+            return this.set_errno_and_return_neg1(LibcError("ENOTSOCK"), dest);
+        };
+
+        let (is_peek, is_non_block) = this.parse_recv_flags(flags, "recvmsg")?;
+
+        let (iov_ptr, iovcnt) = this.read_msghdr_iov(&message)?;
+        let buffers = this.read_iovecs(iov_ptr, iovcnt)?;
+        let total_bytes = buffers.iter().map(|(_, len)| len).sum::<u64>();
+
+        // Receive into a temporary buffer which has the combined size of all buffers provided
+        // in `msg_iov` such that the message is received as a whole.
+        let tmp_ptr: Pointer = this
+            .allocate_ptr(
+                Size::from_bytes(total_bytes),
+                Align::ONE,
+                MemoryKind::Stack,
+                AllocInit::Uninit,
+            )?
+            .into();
+
+        let dest = dest.clone();
+
+        socket.recv_from(
+            this.machine.communicate(),
+            tmp_ptr,
+            usize::try_from(total_bytes).unwrap(),
+            is_peek,
+            is_non_block,
+            this,
+            callback!(
+                @capture<'tcx> {
+                    message: MPlaceTy<'tcx>,
+                    tmp_ptr: Pointer,
+                    buffers: Vec<(Pointer, u64)>,
+                    dest: MPlaceTy<'tcx>,
+                } |this, result: Result<(usize, Option<SocketAddress>), IoError>| {
+                    let (bytes_received, address) = match result {
+                        Ok(data) => data,
+                        Err(e) => {
+                            this.deallocate_ptr(tmp_ptr, None, MemoryKind::Stack)?;
+                            return this.set_errno_and_return_neg1(e, &dest)
+                        }
+                    };
+
+                    this.scatter_into_iovecs(tmp_ptr, u64::try_from(bytes_received).unwrap(), &buffers)?;
+                    this.deallocate_ptr(tmp_ptr, None, MemoryKind::Stack)?;
+
+                    let name_field = this.project_field_named(&message, "msg_name")?;
+                    let name_ptr = this.read_pointer(&name_field)?;
+                    if name_ptr != Pointer::null() {
+                        let name_len_field = this.project_field_named(&message, "msg_namelen")?;
+                        this.write_received_address(address, name_ptr, name_len_field.ptr(), "recvmsg")?;
+                    }
+
+                    // We never receive any ancillary data, and none of the flags we could report
+                    // (like a truncated datagram) are tracked.
+                    let control_len_field = this.project_field_named(&message, "msg_controllen")?;
+                    this.write_int(0, &control_len_field)?;
+                    let flags_field = this.project_field_named(&message, "msg_flags")?;
+                    this.write_int(0, &flags_field)?;
+
+                    this.write_scalar(Scalar::from_target_usize(bytes_received.try_into().unwrap(), this), &dest)
+                }
+            ),
+        )
+    }
+
     fn setsockopt(
         &mut self,
         socket: &OpTy<'tcx>,
@@ -764,7 +1105,7 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
                     address_ptr: Pointer,
                     address_len_ptr: Pointer,
                     dest: MPlaceTy<'tcx>,
-                } |this, result: Result<SocketAddr, IoError>| {
+                } |this, result: Result<SocketAddress, IoError>| {
                     let address = match result {
                         Ok(address) => address,
                         Err(e) => return this.set_errno_and_return_neg1(e, &dest)
@@ -814,4 +1155,290 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
             Err(e) => this.set_errno_and_return_neg1_i32(e),
         }
     }
+
+    /// Perform the `ioctl` operation `op` on a socket which stores whether it is
+    /// non-blocking in `is_non_block`. Only `FIONBIO` is supported.
+    fn socket_ioctl(
+        &mut self,
+        op: Scalar,
+        arg: Option<&OpTy<'tcx>>,
+        is_non_block: &Cell<bool>,
+    ) -> InterpResult<'tcx, i32> {
+        let this = self.eval_context_mut();
+
+        let fionbio = this.eval_libc("FIONBIO");
+
+        if op == fionbio {
+            // On these OSes, Rust uses the ioctl, so we trust that it is reasonable and controls
+            // the same internal flag as fcntl.
+            if !matches!(this.tcx.sess.target.os, Os::Linux | Os::Android | Os::MacOs | Os::FreeBsd)
+            {
+                // FIONBIO cannot be used to change the blocking mode of a socket on solarish targets:
+                // <https://github.com/rust-lang/rust/commit/dda5c97675b4f5b1f6fdab64606c8a1f21021b0a>
+                // Since there might be more targets which do weird things with this option, we use
+                // an allowlist instead of just denying solarish targets.
+                throw_unsup_format!(
+                    "ioctl: setting FIONBIO on sockets is unsupported on target {}",
+                    this.tcx.sess.target.os
+                );
+            }
+
+            let Some(value_ptr) = arg else {
+                throw_ub_format!("ioctl: setting FIONBIO on sockets requires a third argument");
+            };
+            let value = this.deref_pointer_as(value_ptr, this.machine.layouts.i32)?;
+            let non_block = this.read_scalar(&value)?.to_i32()? != 0;
+            is_non_block.set(non_block);
+            return interp_ok(0);
+        }
+
+        throw_unsup_format!("ioctl: unsupported operation {op:#x} on socket");
+    }
+
+    /// The error `bind` returns when the address family doesn't match
+    /// the family of the socket.
+    fn bind_family_mismatch_error(&self) -> IoError {
+        let this = self.eval_context_ref();
+
+        if matches!(this.tcx.sess.target.os, Os::Linux | Os::Android) {
+            // Linux man page states that `EINVAL` is used when there is an address family mismatch.
+            // See <https://man7.org/linux/man-pages/man2/bind.2.html>
+            LibcError("EINVAL")
+        } else {
+            // POSIX man page states that `EAFNOSUPPORT` should be used when there is an address
+            // family mismatch.
+            // See <https://man7.org/linux/man-pages/man3/bind.3p.html>
+            LibcError("EAFNOSUPPORT")
+        }
+    }
+
+    /// Get the deadline for an action (e.g. reading or writing).
+    /// When `is_non_block` is [`true`], the returned deadline is "now", i.e.,
+    /// we wake up immediately if the action cannot be completed.
+    /// If `action_timeout` is `Some(duration)`, the returned deadline is in the
+    /// future be the specified `duration`. Otherwise, no deadline ([`None`]) is
+    /// returned, indicating that the action can block indefinitely.
+    fn action_deadline(
+        &self,
+        is_non_block: bool,
+        action_timeout: Option<Duration>,
+    ) -> Option<Deadline> {
+        let this = self.eval_context_ref();
+
+        if is_non_block {
+            // Non-blocking sockets always have a zero timeout.
+            Some(this.machine.monotonic_clock.now().into())
+        } else {
+            action_timeout
+                .map(|duration| this.machine.monotonic_clock.now().add_lossy(duration).into())
+        }
+    }
+
+    /// Read the value of the `SO_RCVTIMEO` or `SO_SNDTIMEO` socket option from `value_ptr`.
+    /// A zero timeout means that the socket can block indefinitely, which is represented
+    /// by [`None`].
+    fn read_sockopt_timeout(
+        &mut self,
+        value_ptr: Pointer,
+    ) -> InterpResult<'tcx, Result<Option<Duration>, IoError>> {
+        let this = self.eval_context_mut();
+
+        let timeval_layout = this.libc_ty_layout("timeval");
+        let option_value = this.ptr_to_mplace(value_ptr, timeval_layout);
+
+        interp_ok(match this.read_timeval(&option_value)? {
+            None => Err(LibcError("EINVAL")),
+            Some(Duration::ZERO) => Ok(None),
+            Some(duration) => Ok(Some(duration)),
+        })
+    }
+
+    /// Allocate a buffer holding the value of the `SO_RCVTIMEO` or `SO_SNDTIMEO` socket
+    /// option for `getsockopt`.
+    fn sockopt_timeout_value(
+        &mut self,
+        timeout: Option<Duration>,
+    ) -> InterpResult<'tcx, MPlaceTy<'tcx>> {
+        let this = self.eval_context_mut();
+
+        let timeout = timeout.unwrap_or_default();
+        let secs = timeout.as_secs();
+        let usecs = timeout.subsec_micros();
+
+        let timeval_layout = this.libc_ty_layout("timeval");
+        // Allocate new buffer on the stack with the `timeval` layout.
+        let timeval_buffer = this.allocate(timeval_layout, MemoryKind::Stack)?;
+
+        let sec_field = this.project_field_named(&timeval_buffer, "tv_sec")?;
+        this.write_int(secs, &sec_field)?;
+
+        let usec_field = this.project_field_named(&timeval_buffer, "tv_usec")?;
+        this.write_int(usecs, &usec_field)?;
+
+        interp_ok(timeval_buffer)
+    }
+}
+
+impl<'tcx> EvalContextPrivExt<'tcx> for crate::MiriInterpCx<'tcx> {}
+trait EvalContextPrivExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
+    /// Interpret the `flags` of a send operation and return whether
+    /// the operation is non-blocking.
+    fn parse_send_flags(
+        &self,
+        mut flags: i32,
+        foreign_name: &'static str,
+    ) -> InterpResult<'tcx, bool> {
+        let this = self.eval_context_ref();
+
+        let mut is_non_block = false;
+
+        // Interpret the flag. Every flag we recognize is "subtracted" from `flags`, so
+        // if there is anything left at the end, that's an unsupported flag.
+        if matches!(
+            this.tcx.sess.target.os,
+            Os::Linux | Os::Android | Os::FreeBsd | Os::Solaris | Os::Illumos
+        ) {
+            // MSG_NOSIGNAL and MSG_DONTWAIT only exist on Linux, Android, FreeBSD,
+            // Solaris, and Illumos targets.
+            let msg_nosignal = this.eval_libc_i32("MSG_NOSIGNAL");
+            let msg_dontwait = this.eval_libc_i32("MSG_DONTWAIT");
+            if flags & msg_nosignal == msg_nosignal {
+                // This is only needed to ensure that no EPIPE signal is sent when
+                // trying to send into a stream which is no longer connected.
+                // Since we don't support signals, we can ignore this.
+                flags &= !msg_nosignal;
+            }
+            if flags & msg_dontwait == msg_dontwait {
+                flags &= !msg_dontwait;
+                is_non_block = true;
+            }
+        }
+
+        if flags != 0 {
+            throw_unsup_format!(
+                "{foreign_name}: flag {flags:#x} is unsupported, only MSG_NOSIGNAL and MSG_DONTWAIT are allowed",
+            );
+        }
+
+        interp_ok(is_non_block)
+    }
+
+    /// Interpret the `flags` of a receive operation and return whether the
+    /// operation only peeks and whether it is non-blocking.
+    fn parse_recv_flags(
+        &self,
+        mut flags: i32,
+        foreign_name: &'static str,
+    ) -> InterpResult<'tcx, (bool, bool)> {
+        let this = self.eval_context_ref();
+
+        let mut is_peek = false;
+        let mut is_non_block = false;
+
+        // Interpret the flag. Every flag we recognize is "subtracted" from `flags`, so
+        // if there is anything left at the end, that's an unsupported flag.
+
+        let msg_peek = this.eval_libc_i32("MSG_PEEK");
+        if flags & msg_peek == msg_peek {
+            is_peek = true;
+            flags &= !msg_peek;
+        }
+
+        if matches!(this.tcx.sess.target.os, Os::Linux | Os::Android | Os::FreeBsd | Os::Illumos) {
+            // MSG_CMSG_CLOEXEC only exists on Linux, Android, FreeBSD,
+            // and Illumos targets.
+            let msg_cmsg_cloexec = this.eval_libc_i32("MSG_CMSG_CLOEXEC");
+            if flags & msg_cmsg_cloexec == msg_cmsg_cloexec {
+                // We don't support `exec` so we can ignore this.
+                flags &= !msg_cmsg_cloexec;
+            }
+        }
+
+        if matches!(
+            this.tcx.sess.target.os,
+            Os::Linux | Os::Android | Os::FreeBsd | Os::Solaris | Os::Illumos
+        ) {
+            // MSG_DONTWAIT only exists on Linux, Android, FreeBSD,
+            // Solaris, and Illumos targets.
+            let msg_dontwait = this.eval_libc_i32("MSG_DONTWAIT");
+            if flags & msg_dontwait == msg_dontwait {
+                flags &= !msg_dontwait;
+                is_non_block = true;
+            }
+        }
+
+        if flags != 0 {
+            throw_unsup_format!(
+                "{foreign_name}: flag {flags:#x} is unsupported, only MSG_PEEK, MSG_DONTWAIT \
+                and MSG_CMSG_CLOEXEC are allowed",
+            );
+        }
+
+        interp_ok((is_peek, is_non_block))
+    }
+
+    /// Send data to `address`, or to the connected peer if there is no address.
+    fn send_to_socket(
+        &mut self,
+        socket: FileDescriptionRef<dyn UnixSocketFileDescription>,
+        ptr: Pointer,
+        len: usize,
+        address: Option<SocketAddress>,
+        is_non_block: bool,
+        finish: DynMachineCallback<'tcx, Result<usize, IoError>>,
+    ) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+
+        match address {
+            Some(address) =>
+                socket.send_to(
+                    this.machine.communicate(),
+                    ptr,
+                    len,
+                    address,
+                    is_non_block,
+                    this,
+                    finish,
+                ),
+            None => socket.send(this.machine.communicate(), ptr, len, is_non_block, this, finish),
+        }
+    }
+
+    /// Read the `msg_iov` and `msg_iovlen` fields of a `msghdr`.
+    fn read_msghdr_iov(&self, message: &MPlaceTy<'tcx>) -> InterpResult<'tcx, (Pointer, u64)> {
+        let this = self.eval_context_ref();
+
+        let iov_field = this.project_field_named(message, "msg_iov")?;
+        let iov_ptr = this.read_pointer(&iov_field)?;
+        // The type of `msg_iovlen` differs between targets.
+        let iovcnt_field = this.project_field_named(message, "msg_iovlen")?;
+        let iovcnt = this.read_scalar(&iovcnt_field)?.to_int(iovcnt_field.layout.size)?;
+        let Ok(iovcnt) = u64::try_from(iovcnt) else {
+            throw_ub_format!("msg_iovlen must not be negative");
+        };
+
+        interp_ok((iov_ptr, iovcnt))
+    }
+
+    /// Write the address a message was received from. Connection-oriented sockets
+    /// don't report an address, in which case the address length is set to zero.
+    fn write_received_address(
+        &mut self,
+        address: Option<SocketAddress>,
+        address_ptr: Pointer,
+        address_len_ptr: Pointer,
+        foreign_name: &'static str,
+    ) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+
+        match address {
+            Some(address) =>
+                this.write_socket_address(&address, address_ptr, address_len_ptr, foreign_name),
+            None => {
+                let socklen_layout = this.libc_ty_layout("socklen_t");
+                let address_len_place = this.ptr_to_mplace(address_len_ptr, socklen_layout);
+                this.write_int(0, &address_len_place)
+            }
+        }
+    }
 }
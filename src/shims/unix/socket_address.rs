@@ -2,10 +2,37 @@ use std::iter;
 use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6, ToSocketAddrs};
 
 use rustc_abi::Size;
-use rustc_target::spec::Env;
+use rustc_target::spec::{Env, Os};
 
 use crate::*;
 
+/// The address of a socket of one of the supported address families.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SocketAddress {
+    /// The address of an IPv4 or IPv6 socket.
+    Inet(SocketAddr),
+    /// The address of a Unix domain socket.
+    Unix(UnixSocketAddress),
+}
+
+impl From<SocketAddr> for SocketAddress {
+    fn from(address: SocketAddr) -> Self {
+        SocketAddress::Inet(address)
+    }
+}
+
+/// The address of a Unix domain socket.
+/// See <https://man7.org/linux/man-pages/man7/unix.7.html>.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum UnixSocketAddress {
+    /// The socket isn't bound to a name.
+    Unnamed,
+    /// The socket is bound to a path in the file system.
+    Pathname(Vec<u8>),
+    /// The socket is bound to a name in the abstract namespace, which only exists on Linux.
+    Abstract(Vec<u8>),
+}
+
 impl<'tcx> EvalContextExt<'tcx> for crate::MiriInterpCx<'tcx> {}
 pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
     fn getaddrinfo(
@@ -123,7 +150,7 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         this.free_address_infos(res_ptr)
     }
 
-    /// Attempt to turn an address and length operand into a standard library socket address.
+    /// Attempt to turn an address and length operand into a [`SocketAddress`].
     ///
     /// Returns an IO error should the address length not match the address family length.
     fn read_socket_address(
@@ -131,7 +158,7 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         address: &OpTy<'tcx>,
         address_len: &OpTy<'tcx>,
         foreign_name: &'static str,
-    ) -> InterpResult<'tcx, Result<SocketAddr, IoError>> {
+    ) -> InterpResult<'tcx, Result<SocketAddress, IoError>> {
         let this = self.eval_context_ref();
 
         let socklen_layout = this.libc_ty_layout("socklen_t");
@@ -142,19 +169,24 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
 
         // Initially, treat address as generic sockaddr just to extract the family field.
         let sockaddr_layout = this.libc_ty_layout("sockaddr");
-        if address_len < sockaddr_layout.size.bytes() {
-            // Address length should be at least as big as the generic sockaddr
-            return interp_ok(Err(LibcError("EINVAL")));
-        }
         let address = this.deref_pointer_as(address, sockaddr_layout)?;
 
         let family_field = this.project_field_named(&address, "sa_family")?;
+        // Unix domain socket addresses can be shorter than the generic sockaddr, but every
+        // address needs to at least contain the family.
+        let family_end =
+            family_field.ptr().addr() - address.ptr().addr() + family_field.layout.size;
+        if address_len < family_end.bytes() {
+            return interp_ok(Err(LibcError("EINVAL")));
+        }
         let family_layout = this.libc_ty_layout("sa_family_t");
         let family = this.read_scalar(&family_field)?.to_int(family_layout.size)?;
 
         // Depending on the family, decide whether it's IPv4 or IPv6 and use specialized layout
         // to extract address and port.
-        let socket_addr = if family == this.eval_libc_i32("AF_INET").into() {
+        let socket_addr = if family == this.eval_libc_i32("AF_UNIX").into() {
+            return this.read_unix_socket_address(&address, address_len);
+        } else if family == this.eval_libc_i32("AF_INET").into() {
             let sockaddr_in_layout = this.libc_ty_layout("sockaddr_in");
             if address_len != sockaddr_in_layout.size.bytes() {
                 // Address length should be exactly the length of an IPv4 address.
@@ -229,14 +261,14 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
             // thus also no address family of another type should be supported.
             throw_unsup_format!(
                 "{foreign_name}: address family {family:#x} is unsupported, \
-                only AF_INET and AF_INET6 are allowed"
+                only AF_INET, AF_INET6 and AF_UNIX are allowed"
             );
         };
 
-        interp_ok(Ok(socket_addr))
+        interp_ok(Ok(SocketAddress::Inet(socket_addr)))
     }
 
-    /// Attempt to write a standard library socket address into a pointer.
+    /// Attempt to write a [`SocketAddress`] into a pointer.
     ///
     /// The `address_len_ptr` parameter serves both as input and output parameter.
     /// On input, it points to the size of the buffer `address_ptr` points to, and
@@ -247,7 +279,7 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
     /// overflow the buffer.
     fn write_socket_address(
         &mut self,
-        address: &SocketAddr,
+        address: &SocketAddress,
         address_ptr: Pointer,
         address_len_ptr: Pointer,
         foreign_name: &'static str,
@@ -266,14 +298,17 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         let address_buffer_len_place = this.ptr_to_mplace(address_len_ptr, socklen_layout);
         // We only support buffer lengths which can be stored in a u64 since the
         // size of a layout in bytes is also stored in a u64.
-        let address_buffer_len: u64 = this
-            .read_scalar(&address_buffer_len_place)?
-            .to_int(socklen_layout.size)?
-            .try_into()
-            .unwrap();
-
         let address_buffer = match address {
-            SocketAddr::V4(address) => {
+            SocketAddress::Unix(address) => {
+                let (address_buffer, address_len) = this.allocate_unix_socket_address(address)?;
+                return this.copy_socket_address_buffer(
+                    address_buffer,
+                    address_len,
+                    address_ptr,
+                    address_buffer_len_place,
+                );
+            }
+            SocketAddress::Inet(SocketAddr::V4(address)) => {
                 // IPv4 address bytes; already stored in network byte order.
                 let address_bytes = address.ip().octets();
                 // Port needs to be manually turned into network byte order.
@@ -312,7 +347,7 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
 
                 address_buffer
             }
-            SocketAddr::V6(address) => {
+            SocketAddress::Inet(SocketAddr::V6(address)) => {
                 // IPv6 address bytes; already stored in network byte order.
                 let address_bytes = address.ip().octets();
                 // Port needs to be manually turned into network byte order.
@@ -367,12 +402,126 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
             }
         };
 
+        let address_len = address_buffer.layout.size;
+        this.copy_socket_address_buffer(
+            address_buffer,
+            address_len,
+            address_ptr,
+            address_buffer_len_place,
+        )
+    }
+}
+
+impl<'tcx> EvalContextPrivExt<'tcx> for crate::MiriInterpCx<'tcx> {}
+trait EvalContextPrivExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
+    /// Read a Unix domain socket address. `address` points to the start of a `sockaddr_un`
+    /// whose family is `AF_UNIX` and `address_len` is the length provided by the program.
+    fn read_unix_socket_address(
+        &self,
+        address: &MPlaceTy<'tcx>,
+        address_len: u64,
+    ) -> InterpResult<'tcx, Result<SocketAddress, IoError>> {
+        let this = self.eval_context_ref();
+
+        let sockaddr_un_layout = this.libc_ty_layout("sockaddr_un");
+        if address_len > sockaddr_un_layout.size.bytes() {
+            return interp_ok(Err(LibcError("EINVAL")));
+        }
+        // We cannot transmute since the `sockaddr_un` layout is bigger than the `sockaddr` layout.
+        let address = address.offset(Size::ZERO, sockaddr_un_layout, this)?;
+
+        let sun_path_field = this.project_field_named(&address, "sun_path")?;
+        let sun_path_offset = sun_path_field.ptr().addr() - address.ptr().addr();
+        // The path is whatever follows the family, which might be nothing at all.
+        let path_len = address_len.saturating_sub(sun_path_offset.bytes());
+        let path =
+            this.read_bytes_ptr_strip_provenance(sun_path_field.ptr(), Size::from_bytes(path_len))?;
+
+        let address = match path.split_first() {
+            None => UnixSocketAddress::Unnamed,
+            Some((0, name)) => {
+                if !matches!(this.tcx.sess.target.os, Os::Linux | Os::Android) {
+                    // Only Linux has an abstract namespace; elsewhere this is an empty path.
+                    return interp_ok(Err(LibcError("EINVAL")));
+                }
+                // Abstract names are not null-terminated; all bytes are significant.
+                UnixSocketAddress::Abstract(name.to_vec())
+            }
+            Some(_) => {
+                // The path doesn't need to be null-terminated if it fills the whole buffer.
+                let path = path.split(|&byte| byte == 0).next().unwrap();
+                UnixSocketAddress::Pathname(path.to_vec())
+            }
+        };
+
+        interp_ok(Ok(SocketAddress::Unix(address)))
+    }
+
+    /// Allocate a `sockaddr_un` on the stack and write `address` into it.
+    /// Returns the buffer together with the length of the address inside of it.
+    fn allocate_unix_socket_address(
+        &mut self,
+        address: &UnixSocketAddress,
+    ) -> InterpResult<'tcx, (MPlaceTy<'tcx>, Size)> {
+        let this = self.eval_context_mut();
+
+        let sockaddr_un_layout = this.libc_ty_layout("sockaddr_un");
+        let address_buffer = this.allocate(sockaddr_un_layout, MemoryKind::Stack)?;
+        this.write_bytes_ptr(
+            address_buffer.ptr(),
+            iter::repeat_n(0, address_buffer.layout.size.bytes_usize()),
+        )?;
+
+        let sun_family_field = this.project_field_named(&address_buffer, "sun_family")?;
+        let af_unix = this.eval_libc("AF_UNIX");
+        let address_family =
+            Scalar::from_int(af_unix.to_int(af_unix.size())?, sun_family_field.layout.size);
+        this.write_scalar(address_family, &sun_family_field)?;
+
+        let mut path = match address {
+            UnixSocketAddress::Unnamed => Vec::new(),
+            UnixSocketAddress::Pathname(path) => path.iter().copied().chain([0]).collect(),
+            UnixSocketAddress::Abstract(name) =>
+                [0].into_iter().chain(name.iter().copied()).collect(),
+        };
+        let sun_path_field = this.project_field_named(&address_buffer, "sun_path")?;
+        // A path which fills the whole buffer is stored without the null terminator.
+        path.truncate(sun_path_field.layout.size.bytes_usize());
+        this.write_bytes_ptr(sun_path_field.ptr(), path.iter().copied())?;
+
+        let sun_path_offset = sun_path_field.ptr().addr() - address_buffer.ptr().addr();
+        let address_len = sun_path_offset + Size::from_bytes(path.len());
+        interp_ok((address_buffer, address_len))
+    }
+
+    /// Copy the first `address_len` bytes of the temporary `address_buffer` into the buffer
+    /// pointed to by `address_ptr`, truncating it to the buffer length stored in
+    /// `address_buffer_len_place`, and deallocate the temporary buffer. Afterwards,
+    /// `address_buffer_len_place` contains the non-truncated length of the address.
+    fn copy_socket_address_buffer(
+        &mut self,
+        address_buffer: MPlaceTy<'tcx>,
+        address_len: Size,
+        address_ptr: Pointer,
+        address_buffer_len_place: MPlaceTy<'tcx>,
+    ) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+
+        let socklen_layout = this.libc_ty_layout("socklen_t");
+        // We only support buffer lengths which can be stored in a u64 since the
+        // size of a layout in bytes is also stored in a u64.
+        let address_buffer_len: u64 = this
+            .read_scalar(&address_buffer_len_place)?
+            .to_int(socklen_layout.size)?
+            .try_into()
+            .unwrap();
+
         // Copy the truncated address into the pointer pointed to by `address_ptr`.
         this.mem_copy(
             address_buffer.ptr(),
             address_ptr,
             // Truncate the address to fit the provided buffer.
-            address_buffer.layout.size.min(Size::from_bytes(address_buffer_len)),
+            address_len.min(Size::from_bytes(address_buffer_len)),
             // The buffers are guaranteed to not overlap since the `address_buffer`
             // was just newly allocated on the stack.
             true,
@@ -380,20 +529,16 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         // Deallocate the address buffer as it was only needed to construct the address and
         // copy it into the buffer pointed to by `address_ptr`.
         this.deallocate_ptr(address_buffer.ptr(), None, MemoryKind::Stack)?;
-        // Size of the non-truncated address.
-        let address_len = address_buffer.layout.size.bytes();
 
+        // Size of the non-truncated address.
         this.write_scalar(
-            Scalar::from_uint(address_len, socklen_layout.size),
+            Scalar::from_uint(address_len.bytes(), socklen_layout.size),
             &address_buffer_len_place,
         )?;
 
         interp_ok(())
     }
-}
 
-impl<'tcx> EvalContextPrivExt<'tcx> for crate::MiriInterpCx<'tcx> {}
-trait EvalContextPrivExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
     /// Allocate a linked list of address info structs from an iterator of [`SocketAddr`]s.
     /// Returns a pointer pointing to the head of the linked list.
     fn allocate_address_infos(
@@ -453,7 +598,7 @@ trait EvalContextPrivExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
             iter::repeat_n(0, sockaddr_mplace.layout.size.bytes_usize()),
         )?;
         this.write_socket_address(
-            &address,
+            &SocketAddress::Inet(address),
             sockaddr_mplace.ptr(),
             addrlen_mplace.ptr(),
             "getaddrinfo",
@@ -0,0 +1,672 @@
+//! The `posix_spawn` family of functions, and waiting for the spawned children.
+//!
+//! Spawn file actions and attributes are opaque to the program, so we keep their contents in
+//! synchronization objects attached to their memory (like we do for mutexes). The children
+//! themselves are host processes, see `shims::process`.
+
+use std::cell::{Cell, RefCell};
+use std::ffi::OsString;
+use std::io;
+use std::path::PathBuf;
+use std::process::{ExitStatus, Stdio};
+
+use rustc_abi::Size;
+
+use crate::concurrency::sync::SyncObj;
+use crate::shims::files::{
+    Backing, DynFileDescriptionRef, FileHandle, NullOutput, Stderr, Stdin, Stdout,
+};
+use crate::shims::process::{EvalContextExt as _, SpawnRequest};
+#[cfg(unix)]
+use crate::shims::unix::host_pipe::{EvalContextExt as _, HostPipe};
+#[cfg(unix)]
+use crate::shims::unix::virtual_socket::{EvalContextExt as _, VirtualSocket};
+use crate::*;
+
+/// The value of the first byte of an initialized `posix_spawn_file_actions_t` or
+/// `posix_spawnattr_t`. Before initialization, their contents are unspecified.
+const SPAWN_INIT: u8 = 1;
+
+/// A file action that `posix_spawn` performs in the child before running the new program.
+#[derive(Debug, Clone)]
+enum FileAction {
+    Dup2 { fd: i32, newfd: i32 },
+    Close { fd: i32 },
+    Chdir { path: PathBuf },
+}
+
+#[derive(Debug, Default)]
+struct PosixSpawnFileActions {
+    actions: RefCell<Vec<FileAction>>,
+}
+
+impl SyncObj for PosixSpawnFileActions {
+    fn delete_on_write(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Debug, Default)]
+struct PosixSpawnAttr {
+    flags: Cell<i16>,
+    pgroup: Cell<i32>,
+}
+
+impl SyncObj for PosixSpawnAttr {
+    fn delete_on_write(&self) -> bool {
+        true
+    }
+}
+
+/// Returns the file actions stored at the address that `file_actions_op` points to.
+fn file_actions_get_data<'tcx, 'a>(
+    ecx: &'a mut MiriInterpCx<'tcx>,
+    file_actions_op: &OpTy<'tcx>,
+) -> InterpResult<'tcx, &'a PosixSpawnFileActions>
+where
+    'tcx: 'a,
+{
+    let file_actions =
+        ecx.deref_pointer_as(file_actions_op, ecx.libc_ty_layout("posix_spawn_file_actions_t"))?;
+    ecx.get_immovable_sync_with_static_init(&file_actions, Size::ZERO, 0, SPAWN_INIT, |_| {
+        throw_ub_format!(
+            "`posix_spawn_file_actions_t` can't be used before it was initialized or after it was destroyed or moved"
+        )
+    })
+}
+
+/// Returns the attributes stored at the address that `attr_op` points to.
+fn attr_get_data<'tcx, 'a>(
+    ecx: &'a mut MiriInterpCx<'tcx>,
+    attr_op: &OpTy<'tcx>,
+) -> InterpResult<'tcx, &'a PosixSpawnAttr>
+where
+    'tcx: 'a,
+{
+    let attr = ecx.deref_pointer_as(attr_op, ecx.libc_ty_layout("posix_spawnattr_t"))?;
+    ecx.get_immovable_sync_with_static_init(&attr, Size::ZERO, 0, SPAWN_INIT, |_| {
+        throw_ub_format!(
+            "`posix_spawnattr_t` can't be used before it was initialized or after it was destroyed or moved"
+        )
+    })
+}
+
+/// Encodes an exit status the way `waitpid` reports it. All our Unix targets agree on this.
+fn encode_wait_status<'tcx>(
+    ecx: &MiriInterpCx<'tcx>,
+    status: ExitStatus,
+) -> InterpResult<'tcx, i32> {
+    if let Some(code) = status.code() {
+        return interp_ok((code & 0xff) << 8);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(host_signal) = status.signal() {
+            let Some(name) = host_signal_name(host_signal) else {
+                throw_unsup_format!(
+                    "child process was killed by unknown host signal {host_signal}"
+                );
+            };
+            let signal = ecx.eval_libc_i32(name);
+            return interp_ok(if status.core_dumped() { signal | 0x80 } else { signal });
+        }
+    }
+    let _ = ecx;
+    throw_unsup_format!("child process stopped or continued, which is not supported")
+}
+
+/// The host signals we can forward to children or report to the program, by name.
+#[cfg(unix)]
+const HOST_SIGNALS: &[(&str, i32)] = &[
+    ("SIGHUP", libc::SIGHUP),
+    ("SIGINT", libc::SIGINT),
+    ("SIGQUIT", libc::SIGQUIT),
+    ("SIGILL", libc::SIGILL),
+    ("SIGTRAP", libc::SIGTRAP),
+    ("SIGABRT", libc::SIGABRT),
+    ("SIGBUS", libc::SIGBUS),
+    ("SIGFPE", libc::SIGFPE),
+    ("SIGKILL", libc::SIGKILL),
+    ("SIGUSR1", libc::SIGUSR1),
+    ("SIGSEGV", libc::SIGSEGV),
+    ("SIGUSR2", libc::SIGUSR2),
+    ("SIGPIPE", libc::SIGPIPE),
+    ("SIGALRM", libc::SIGALRM),
+    ("SIGTERM", libc::SIGTERM),
+    ("SIGCHLD", libc::SIGCHLD),
+    ("SIGCONT", libc::SIGCONT),
+    ("SIGSTOP", libc::SIGSTOP),
+    ("SIGTSTP", libc::SIGTSTP),
+    ("SIGTTIN", libc::SIGTTIN),
+    ("SIGTTOU", libc::SIGTTOU),
+    ("SIGURG", libc::SIGURG),
+    ("SIGXCPU", libc::SIGXCPU),
+    ("SIGXFSZ", libc::SIGXFSZ),
+    ("SIGVTALRM", libc::SIGVTALRM),
+    ("SIGPROF", libc::SIGPROF),
+    ("SIGWINCH", libc::SIGWINCH),
+    ("SIGIO", libc::SIGIO),
+    ("SIGSYS", libc::SIGSYS),
+];
+
+#[cfg(unix)]
+fn host_signal_name(signal: i32) -> Option<&'static str> {
+    HOST_SIGNALS.iter().find(|&&(_, host)| host == signal).map(|&(name, _)| name)
+}
+
+impl<'tcx> EvalContextExtPriv<'tcx> for crate::MiriInterpCx<'tcx> {}
+trait EvalContextExtPriv<'tcx>: crate::MiriInterpCxExt<'tcx> {
+    /// Reads a null-terminated array of C strings, like `argv` and `envp`.
+    fn read_c_str_array(&self, ptr: Pointer) -> InterpResult<'tcx, Vec<OsString>> {
+        let this = self.eval_context_ref();
+        let ptr_layout = this.machine.layouts.const_raw_ptr;
+        let mut place = this.ptr_to_mplace(ptr, ptr_layout);
+        let mut strings = Vec::new();
+        loop {
+            let str_ptr = this.read_pointer(&place)?;
+            if this.ptr_is_null(str_ptr)? {
+                return interp_ok(strings);
+            }
+            strings.push(this.read_os_str_from_c_str(str_ptr)?.to_os_string());
+            place = place.offset(ptr_layout.size, ptr_layout, this)?;
+        }
+    }
+
+    /// Replaces the virtual pipes among the child's standard streams with host pipes, so
+    /// that they can be passed to the child.
+    #[cfg(unix)]
+    fn virtual_pipes_to_host(
+        &mut self,
+        child_fds: &mut [Option<DynFileDescriptionRef>],
+    ) -> InterpResult<'tcx, io::Result<()>> {
+        let this = self.eval_context_mut();
+        for i in 0..child_fds.len() {
+            let Some(socket) = child_fds[i].clone().and_then(|fd| fd.downcast::<VirtualSocket>())
+            else {
+                continue;
+            };
+            let id = socket.id();
+            let pipe: DynFileDescriptionRef = match this.virtual_pipe_to_host(socket)? {
+                Ok(pipe) => pipe,
+                Err(e) => return interp_ok(Err(e)),
+            };
+            // The same pipe might be passed as several streams.
+            for fd in child_fds.iter_mut().flatten() {
+                if fd.id() == id {
+                    *fd = pipe.clone();
+                }
+            }
+        }
+        interp_ok(Ok(()))
+    }
+
+    /// Determines how the child should see the file description that ends up as its standard
+    /// stream `child_fd`.
+    fn stdio_for_child(
+        &mut self,
+        fd: Option<DynFileDescriptionRef>,
+        child_fd: i32,
+    ) -> InterpResult<'tcx, io::Result<Stdio>> {
+        let this = self.eval_context_mut();
+        let Some(fd) = fd else {
+            // The child is not supposed to have this stream at all, which we approximate.
+            return interp_ok(Ok(Stdio::null()));
+        };
+        if fd.clone().downcast::<Stdin>().is_some() {
+            if child_fd != 0 {
+                throw_unsup_format!("passing stdin to a child process as fd {child_fd}");
+            }
+            return interp_ok(Ok(Stdio::inherit()));
+        }
+        let is_stdout = fd.clone().downcast::<Stdout>().is_some();
+        if is_stdout || fd.clone().downcast::<Stderr>().is_some() {
+            if this.machine.discard_stdout_stderr {
+                return interp_ok(Ok(Stdio::null()));
+            }
+            return interp_ok(Ok(if is_stdout {
+                io::stdout().into()
+            } else {
+                io::stderr().into()
+            }));
+        }
+        if fd.clone().downcast::<NullOutput>().is_some() {
+            return interp_ok(Ok(Stdio::null()));
+        }
+        if let Some(file) = fd.clone().downcast::<FileHandle>() {
+            return match &file.file {
+                Backing::Host(file) => interp_ok(file.try_clone().map(Stdio::from)),
+                Backing::Virtual(_) =>
+                    throw_unsup_format!(
+                        "passing a file of the in-memory file system to a child process is not supported"
+                    ),
+            };
+        }
+        #[cfg(unix)]
+        if let Some(pipe) = fd.clone().downcast::<HostPipe>() {
+            return interp_ok(this.host_pipe_for_child(&pipe)?.map(Stdio::from));
+        }
+        throw_unsup_format!("passing a {} to a child process is not supported", fd.name())
+    }
+}
+
+impl<'tcx> EvalContextExt<'tcx> for crate::MiriInterpCx<'tcx> {}
+pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
+    fn posix_spawn_file_actions_init(
+        &mut self,
+        file_actions_op: &OpTy<'tcx>,
+    ) -> InterpResult<'tcx, Scalar> {
+        let this = self.eval_context_mut();
+
+        let file_actions = this
+            .deref_pointer_as(file_actions_op, this.libc_ty_layout("posix_spawn_file_actions_t"))?;
+        this.init_immovable_sync(
+            &file_actions,
+            Size::ZERO,
+            SPAWN_INIT,
+            PosixSpawnFileActions::default(),
+        )?;
+        interp_ok(Scalar::from_i32(0))
+    }
+
+    fn posix_spawn_file_actions_destroy(
+        &mut self,
+        file_actions_op: &OpTy<'tcx>,
+    ) -> InterpResult<'tcx, Scalar> {
+        let this = self.eval_context_mut();
+
+        // Reading the data also detects double-`destroy`, since we make the memory uninit below.
+        file_actions_get_data(this, file_actions_op)?;
+        // This write also deletes the interpreter state.
+        let file_actions = this
+            .deref_pointer_as(file_actions_op, this.libc_ty_layout("posix_spawn_file_actions_t"))?;
+        this.write_uninit(&file_actions)?;
+        interp_ok(Scalar::from_i32(0))
+    }
+
+    fn posix_spawn_file_actions_adddup2(
+        &mut self,
+        file_actions_op: &OpTy<'tcx>,
+        fd_op: &OpTy<'tcx>,
+        newfd_op: &OpTy<'tcx>,
+    ) -> InterpResult<'tcx, Scalar> {
+        let this = self.eval_context_mut();
+
+        let fd = this.read_scalar(fd_op)?.to_i32()?;
+        let newfd = this.read_scalar(newfd_op)?.to_i32()?;
+        if fd < 0 || newfd < 0 {
+            return interp_ok(this.eval_libc("EBADF"));
+        }
+        file_actions_get_data(this, file_actions_op)?
+            .actions
+            .borrow_mut()
+            .push(FileAction::Dup2 { fd, newfd });
+        interp_ok(Scalar::from_i32(0))
+    }
+
+    fn posix_spawn_file_actions_addclose(
+        &mut self,
+        file_actions_op: &OpTy<'tcx>,
+        fd_op: &OpTy<'tcx>,
+    ) -> InterpResult<'tcx, Scalar> {
+        let this = self.eval_context_mut();
+
+        let fd = this.read_scalar(fd_op)?.to_i32()?;
+        if fd < 0 {
+            return interp_ok(this.eval_libc("EBADF"));
+        }
+        file_actions_get_data(this, file_actions_op)?
+            .actions
+            .borrow_mut()
+            .push(FileAction::Close { fd });
+        interp_ok(Scalar::from_i32(0))
+    }
+
+    fn posix_spawn_file_actions_addchdir(
+        &mut self,
+        file_actions_op: &OpTy<'tcx>,
+        path_op: &OpTy<'tcx>,
+    ) -> InterpResult<'tcx, Scalar> {
+        let this = self.eval_context_mut();
+
+        let path = this.read_path_from_c_str(this.read_pointer(path_op)?)?.into_owned();
+        file_actions_get_data(this, file_actions_op)?
+            .actions
+            .borrow_mut()
+            .push(FileAction::Chdir { path });
+        interp_ok(Scalar::from_i32(0))
+    }
+
+    fn posix_spawnattr_init(&mut self, attr_op: &OpTy<'tcx>) -> InterpResult<'tcx, Scalar> {
+        let this = self.eval_context_mut();
+
+        let attr = this.deref_pointer_as(attr_op, this.libc_ty_layout("posix_spawnattr_t"))?;
+        this.init_immovable_sync(&attr, Size::ZERO, SPAWN_INIT, PosixSpawnAttr::default())?;
+        interp_ok(Scalar::from_i32(0))
+    }
+
+    fn posix_spawnattr_destroy(&mut self, attr_op: &OpTy<'tcx>) -> InterpResult<'tcx, Scalar> {
+        let this = self.eval_context_mut();
+
+        attr_get_data(this, attr_op)?;
+        let attr = this.deref_pointer_as(attr_op, this.libc_ty_layout("posix_spawnattr_t"))?;
+        this.write_uninit(&attr)?;
+        interp_ok(Scalar::from_i32(0))
+    }
+
+    fn posix_spawnattr_setflags(
+        &mut self,
+        attr_op: &OpTy<'tcx>,
+        flags_op: &OpTy<'tcx>,
+    ) -> InterpResult<'tcx, Scalar> {
+        let this = self.eval_context_mut();
+
+        let flags = this.read_scalar(flags_op)?.to_i16()?;
+        attr_get_data(this, attr_op)?.flags.set(flags);
+        interp_ok(Scalar::from_i32(0))
+    }
+
+    fn posix_spawnattr_getflags(
+        &mut self,
+        attr_op: &OpTy<'tcx>,
+        flags_op: &OpTy<'tcx>,
+    ) -> InterpResult<'tcx, Scalar> {
+        let this = self.eval_context_mut();
+
+        let flags = attr_get_data(this, attr_op)?.flags.get();
+        let flags_place = this.deref_pointer_as(flags_op, this.machine.layouts.i16)?;
+        this.write_scalar(Scalar::from_i16(flags), &flags_place)?;
+        interp_ok(Scalar::from_i32(0))
+    }
+
+    fn posix_spawnattr_setpgroup(
+        &mut self,
+        attr_op: &OpTy<'tcx>,
+        pgroup_op: &OpTy<'tcx>,
+    ) -> InterpResult<'tcx, Scalar> {
+        let this = self.eval_context_mut();
+
+        let pgroup = this.read_scalar(pgroup_op)?.to_i32()?;
+        attr_get_data(this, attr_op)?.pgroup.set(pgroup);
+        interp_ok(Scalar::from_i32(0))
+    }
+
+    fn posix_spawnattr_getpgroup(
+        &mut self,
+        attr_op: &OpTy<'tcx>,
+        pgroup_op: &OpTy<'tcx>,
+    ) -> InterpResult<'tcx, Scalar> {
+        let this = self.eval_context_mut();
+
+        let pgroup = attr_get_data(this, attr_op)?.pgroup.get();
+        let pgroup_place = this.deref_pointer_as(pgroup_op, this.libc_ty_layout("pid_t"))?;
+        this.write_scalar(Scalar::from_i32(pgroup), &pgroup_place)?;
+        interp_ok(Scalar::from_i32(0))
+    }
+
+    fn posix_spawnattr_setsigdefault(
+        &mut self,
+        attr_op: &OpTy<'tcx>,
+        sigdefault_op: &OpTy<'tcx>,
+    ) -> InterpResult<'tcx, Scalar> {
+        let this = self.eval_context_mut();
+
+        // Miri itself does not change the disposition of any signal the child could inherit, so
+        // the child always starts out with the default dispositions anyway.
+        this.deref_pointer_as(sigdefault_op, this.libc_ty_layout("sigset_t"))?;
+        attr_get_data(this, attr_op)?;
+        interp_ok(Scalar::from_i32(0))
+    }
+
+    /// Implements `posix_spawn`, and `posix_spawnp` if `search_path` is set.
+    fn posix_spawn(
+        &mut self,
+        pid_op: &OpTy<'tcx>,
+        path_op: &OpTy<'tcx>,
+        file_actions_op: &OpTy<'tcx>,
+        attr_op: &OpTy<'tcx>,
+        argv_op: &OpTy<'tcx>,
+        envp_op: &OpTy<'tcx>,
+        search_path: bool,
+    ) -> InterpResult<'tcx, Scalar> {
+        let this = self.eval_context_mut();
+        let link_name = if search_path { "posix_spawnp" } else { "posix_spawn" };
+
+        let pid_ptr = this.read_pointer(pid_op)?;
+        let mut program = this.read_path_from_c_str(this.read_pointer(path_op)?)?.into_owned();
+        let file_actions = this.read_pointer(file_actions_op)?;
+        let attr = this.read_pointer(attr_op)?;
+        let args = this.read_c_str_array(this.read_pointer(argv_op)?)?;
+        let env = this.read_c_str_array(this.read_pointer(envp_op)?)?;
+
+        // Reject if isolation is enabled.
+        if let IsolatedOp::Reject(reject_with) = this.machine.isolated_op {
+            this.reject_in_isolation(&format!("`{link_name}`"), reject_with)?;
+            return interp_ok(this.eval_libc("EPERM"));
+        }
+
+        // `posix_spawn` does not search `PATH`, so a bare file name refers to the current directory.
+        if !search_path && program.components().count() == 1 {
+            program = PathBuf::from(".").join(program);
+        }
+        let env = env
+            .into_iter()
+            .map(|var| {
+                let var = var.into_encoded_bytes();
+                let (name, val) = match var.iter().position(|&b| b == b'=') {
+                    Some(pos) => (var[..pos].to_vec(), var[pos.strict_add(1)..].to_vec()),
+                    None => (var, Vec::new()),
+                };
+                // SAFETY: we split the bytes of an `OsString` at an ASCII character.
+                unsafe {
+                    (
+                        OsString::from_encoded_bytes_unchecked(name),
+                        OsString::from_encoded_bytes_unchecked(val),
+                    )
+                }
+            })
+            .collect();
+
+        let mut process_group = None;
+        if !this.ptr_is_null(attr)? {
+            let attr = attr_get_data(this, attr_op)?;
+            let (mut flags, pgroup) = (attr.flags.get(), attr.pgroup.get());
+            let setsigdef = i16::try_from(this.eval_libc_i32("POSIX_SPAWN_SETSIGDEF")).unwrap();
+            let setpgroup = i16::try_from(this.eval_libc_i32("POSIX_SPAWN_SETPGROUP")).unwrap();
+            // See `posix_spawnattr_setsigdefault`.
+            flags &= !setsigdef;
+            if flags & setpgroup != 0 {
+                process_group = Some(pgroup);
+                flags &= !setpgroup;
+            }
+            if flags != 0 {
+                throw_unsup_format!("`{link_name}`: unsupported flags {flags:#x}");
+            }
+        }
+
+        // Work out what the child's standard streams refer to. We do not pass any other file
+        // descriptors to the child, so we only need to track those three.
+        let mut child_fds: [Option<DynFileDescriptionRef>; 3] =
+            std::array::from_fn(|fd| this.machine.fds.get(i32::try_from(fd).unwrap()));
+        let mut cwd: Option<PathBuf> = None;
+        if !this.ptr_is_null(file_actions)? {
+            let actions = file_actions_get_data(this, file_actions_op)?.actions.borrow().clone();
+            for action in actions {
+                match action {
+                    FileAction::Dup2 { fd, newfd } => {
+                        let source = match usize::try_from(fd).ok().and_then(|fd| child_fds.get(fd))
+                        {
+                            Some(child_fd) => child_fd.clone(),
+                            None => this.machine.fds.get(fd),
+                        };
+                        let Some(source) = source else {
+                            return interp_ok(this.eval_libc("EBADF"));
+                        };
+                        if let Some(slot) =
+                            usize::try_from(newfd).ok().and_then(|fd| child_fds.get_mut(fd))
+                        {
+                            *slot = Some(source);
+                        }
+                    }
+                    FileAction::Close { fd } => {
+                        if let Some(slot) =
+                            usize::try_from(fd).ok().and_then(|fd| child_fds.get_mut(fd))
+                        {
+                            *slot = None;
+                        }
+                    }
+                    FileAction::Chdir { path } => {
+                        cwd = Some(match cwd {
+                            Some(cwd) => cwd.join(path),
+                            None => path,
+                        });
+                    }
+                }
+            }
+        }
+        #[cfg(unix)]
+        if let Err(e) = this.virtual_pipes_to_host(&mut child_fds)? {
+            return this.io_error_to_errnum(e);
+        }
+        let mut stdio = Vec::with_capacity(3);
+        for (child_fd, fd) in child_fds.into_iter().enumerate() {
+            let child_fd = i32::try_from(child_fd).unwrap();
+            match this.stdio_for_child(fd, child_fd)? {
+                Ok(s) => stdio.push(s),
+                Err(e) => return this.io_error_to_errnum(e),
+            }
+        }
+        let [stdin, stdout, stderr] = <[Stdio; 3]>::try_from(stdio).unwrap();
+
+        let request =
+            SpawnRequest { program, args, env, cwd, stdin, stdout, stderr, process_group };
+        let pid = match this.spawn_child(request)? {
+            Ok(pid) => pid,
+            Err(e) => return this.io_error_to_errnum(e),
+        };
+        if !this.ptr_is_null(pid_ptr)? {
+            let pid_place = this.ptr_to_mplace(pid_ptr, this.libc_ty_layout("pid_t"));
+            this.write_scalar(Scalar::from_u32(pid), &pid_place)?;
+        }
+        interp_ok(Scalar::from_i32(0))
+    }
+
+    fn waitpid(
+        &mut self,
+        pid_op: &OpTy<'tcx>,
+        status_op: &OpTy<'tcx>,
+        options_op: &OpTy<'tcx>,
+        dest: &MPlaceTy<'tcx>,
+    ) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+
+        let pid = this.read_scalar(pid_op)?.to_i32()?;
+        let status_ptr = this.read_pointer(status_op)?;
+        let mut options = this.read_scalar(options_op)?.to_i32()?;
+
+        let wnohang = this.eval_libc_i32("WNOHANG");
+        let nohang = options & wnohang != 0;
+        options &= !wnohang;
+        if options != 0 {
+            throw_unsup_format!("`waitpid`: unsupported options {options:#x}");
+        }
+
+        // We do not have process groups, so `0` (our process group) means any child, too.
+        let pid = match pid {
+            -1 | 0 => None,
+            pid if pid > 0 => Some(u32::try_from(pid).unwrap()),
+            _ =>
+                throw_unsup_format!(
+                    "`waitpid`: waiting for process group {} is not supported",
+                    pid.strict_neg()
+                ),
+        };
+        let has_child = match pid {
+            Some(pid) => this.is_child_process(pid),
+            None => this.has_child_processes(),
+        };
+        if !has_child {
+            return this.set_errno_and_return_neg1(LibcError("ECHILD"), dest);
+        }
+
+        if nohang {
+            match this.try_wait_child(pid)? {
+                Ok(Some((pid, status))) => this.finish_waitpid(pid, status, status_ptr, dest)?,
+                Ok(None) => this.write_null(dest)?,
+                Err(e) => this.set_errno_and_return_neg1(e, dest)?,
+            }
+            return interp_ok(());
+        }
+        let dest = dest.clone();
+        this.wait_for_child(
+            pid,
+            callback!(
+                @capture<'tcx> {
+                    status_ptr: Pointer,
+                    dest: MPlaceTy<'tcx>,
+                }
+                |this, result: io::Result<(u32, ExitStatus)>| {
+                    match result {
+                        Ok((pid, status)) => this.finish_waitpid(pid, status, status_ptr, &dest),
+                        Err(e) => this.set_errno_and_return_neg1(e, &dest),
+                    }
+                }
+            ),
+        )
+    }
+
+    /// Reports that the child `pid` exited with `status` from `waitpid`, and reaps it.
+    fn finish_waitpid(
+        &mut self,
+        pid: u32,
+        status: ExitStatus,
+        status_ptr: Pointer,
+        dest: &MPlaceTy<'tcx>,
+    ) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+        if !this.ptr_is_null(status_ptr)? {
+            let status = encode_wait_status(this, status)?;
+            let status_place = this.ptr_to_mplace(status_ptr, this.machine.layouts.i32);
+            this.write_scalar(Scalar::from_i32(status), &status_place)?;
+        }
+        this.reap_child(pid);
+        this.write_scalar(Scalar::from_u32(pid), dest)
+    }
+
+    /// Sends the signal `signal_name` to the child process `pid`.
+    fn kill_child_process(&mut self, pid: u32, signal_name: &str) -> InterpResult<'tcx, Scalar> {
+        let this = self.eval_context_mut();
+
+        // Signalling a child that already exited has no effect. Only the exit status would
+        // keep its process ID from being reused, but we already consumed that.
+        match this.try_wait_child(Some(pid))? {
+            Ok(Some(_)) => return interp_ok(Scalar::from_i32(0)),
+            Ok(None) => {}
+            Err(e) => return this.set_errno_and_return_neg1_i32(e),
+        }
+        let result = if signal_name == "SIGKILL" {
+            this.kill_child(pid)
+        } else {
+            #[cfg(unix)]
+            {
+                let Some(&(_, host_signal)) =
+                    HOST_SIGNALS.iter().find(|&&(name, _)| name == signal_name)
+                else {
+                    throw_unsup_format!("`kill`: cannot send {signal_name} to a child process");
+                };
+                // SAFETY: `pid` is a child process that has not been reaped, so it still refers to
+                // that child.
+                let ret = unsafe { libc::kill(i32::try_from(pid).unwrap(), host_signal) };
+                if ret == 0 { Ok(()) } else { Err(io::Error::last_os_error()) }
+            }
+            #[cfg(not(unix))]
+            throw_unsup_format!(
+                "`kill`: sending {signal_name} to a child process is not supported on this host"
+            )
+        };
+        match result {
+            Ok(()) => interp_ok(Scalar::from_i32(0)),
+            Err(e) => this.set_errno_and_return_neg1_i32(e),
+        }
+    }
+}
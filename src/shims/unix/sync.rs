@@ -133,6 +133,10 @@ impl SyncObj for PthreadMutex {
     fn delete_on_write(&self) -> bool {
         true
     }
+
+    fn lock_order_id(&self) -> Option<LockId> {
+        self.mutex_ref.lock_order_id()
+    }
 }
 
 /// To ensure an initialized mutex that was moved somewhere else can be distinguished from
@@ -269,6 +273,10 @@ impl SyncObj for PthreadRwLock {
     fn delete_on_write(&self) -> bool {
         true
     }
+
+    fn lock_order_id(&self) -> Option<LockId> {
+        self.rwlock_ref.lock_order_id()
+    }
 }
 
 fn rwlock_init_offset<'tcx>(ecx: &MiriInterpCx<'tcx>) -> InterpResult<'tcx, Size> {
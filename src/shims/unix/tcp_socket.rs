@@ -13,7 +13,8 @@ use rustc_target::spec::Os;
 
 use crate::shims::files::{EvalContextExt as _, FdNum, FileDescription, FileDescriptionRef};
 use crate::shims::unix::UnixFileDescription;
-use crate::shims::unix::socket::{SocketFamily, UnixSocketFileDescription};
+use crate::shims::unix::socket::{EvalContextExt as _, SocketFamily, UnixSocketFileDescription};
+use crate::shims::unix::socket_address::SocketAddress;
 use crate::*;
 
 #[derive(Debug)]
@@ -189,34 +190,7 @@ impl UnixFileDescription for TcpSocket {
         ecx: &mut MiriInterpCx<'tcx>,
     ) -> InterpResult<'tcx, i32> {
         assert!(ecx.machine.communicate(), "cannot have `TcpSocket` with isolation enabled!");
-
-        let fionbio = ecx.eval_libc("FIONBIO");
-
-        if op == fionbio {
-            // On these OSes, Rust uses the ioctl, so we trust that it is reasonable and controls
-            // the same internal flag as fcntl.
-            if !matches!(ecx.tcx.sess.target.os, Os::Linux | Os::Android | Os::MacOs | Os::FreeBsd)
-            {
-                // FIONBIO cannot be used to change the blocking mode of a socket on solarish targets:
-                // <https://github.com/rust-lang/rust/commit/dda5c97675b4f5b1f6fdab64606c8a1f21021b0a>
-                // Since there might be more targets which do weird things with this option, we use
-                // an allowlist instead of just denying solarish targets.
-                throw_unsup_format!(
-                    "ioctl: setting FIONBIO on sockets is unsupported on target {}",
-                    ecx.tcx.sess.target.os
-                );
-            }
-
-            let Some(value_ptr) = arg else {
-                throw_ub_format!("ioctl: setting FIONBIO on sockets requires a third argument");
-            };
-            let value = ecx.deref_pointer_as(value_ptr, ecx.machine.layouts.i32)?;
-            let non_block = ecx.read_scalar(&value)?.to_i32()? != 0;
-            self.is_non_block.set(non_block);
-            return interp_ok(0);
-        }
-
-        throw_unsup_format!("ioctl: unsupported operation {op:#x} on socket");
+        ecx.socket_ioctl(op, arg, &self.is_non_block)
     }
 
     fn as_socket<'tcx>(
@@ -231,7 +205,7 @@ impl UnixSocketFileDescription for TcpSocket {
     fn bind<'tcx>(
         self: FileDescriptionRef<TcpSocket>,
         communicate_allowed: bool,
-        address: SocketAddr,
+        address: SocketAddress,
         ecx: &mut MiriInterpCx<'tcx>,
     ) -> InterpResult<'tcx, Result<(), IoError>> {
         assert!(communicate_allowed, "cannot have `TcpSocket` with isolation enabled!");
@@ -241,26 +215,13 @@ impl UnixSocketFileDescription for TcpSocket {
 
         match *state {
             SocketState::Initial => {
-                let address_family = match &address {
-                    SocketAddr::V4(_) => SocketFamily::IPv4,
-                    SocketAddr::V6(_) => SocketFamily::IPv6,
-                };
-
-                if self.family != address_family {
+                let address = match address {
+                    SocketAddress::Inet(address) if SocketFamily::of(&address) == self.family =>
+                        address,
                     // Attempted to bind an address from a family that doesn't match
                     // the family of the socket.
-                    let err = if matches!(ecx.tcx.sess.target.os, Os::Linux | Os::Android) {
-                        // Linux man page states that `EINVAL` is used when there is an address family mismatch.
-                        // See <https://man7.org/linux/man-pages/man2/bind.2.html>
-                        LibcError("EINVAL")
-                    } else {
-                        // POSIX man page states that `EAFNOSUPPORT` should be used when there is an address
-                        // family mismatch.
-                        // See <https://man7.org/linux/man-pages/man3/bind.3p.html>
-                        LibcError("EAFNOSUPPORT")
-                    };
-                    return interp_ok(Err(err));
-                }
+                    _ => return interp_ok(Err(ecx.bind_family_mismatch_error())),
+                };
 
                 *state = SocketState::Bound(address);
             }
@@ -328,7 +289,7 @@ impl UnixSocketFileDescription for TcpSocket {
         communicate_allowed: bool,
         is_client_sock_non_block: bool,
         ecx: &mut MiriInterpCx<'tcx>,
-        finish: DynMachineCallback<'tcx, Result<(FdNum, SocketAddr), IoError>>,
+        finish: DynMachineCallback<'tcx, Result<(FdNum, SocketAddress), IoError>>,
     ) -> InterpResult<'tcx> {
         assert!(communicate_allowed, "cannot have `TcpSocket` with isolation enabled!");
 
@@ -364,13 +325,17 @@ impl UnixSocketFileDescription for TcpSocket {
     fn connect<'tcx>(
         self: FileDescriptionRef<Self>,
         communicate_allowed: bool,
-        address: SocketAddr,
+        address: SocketAddress,
         ecx: &mut MiriInterpCx<'tcx>,
         finish: DynMachineCallback<'tcx, Result<(), IoError>>,
     ) -> InterpResult<'tcx> {
         assert!(communicate_allowed, "cannot have `TcpSocket` with isolation enabled!");
         ecx.ensure_not_failed(&self, "connect")?;
 
+        let SocketAddress::Inet(address) = address else {
+            return finish.call(ecx, Err(LibcError("EAFNOSUPPORT")));
+        };
+
         match &*self.state.borrow() {
             SocketState::Initial => { /* fall-through to below */ }
             // The socket is already in a connecting state.
@@ -564,20 +529,16 @@ impl UnixSocketFileDescription for TcpSocket {
                     }
                     let option_value = ecx.ptr_to_mplace(value_ptr, ecx.machine.layouts.i32);
                     let _val = ecx.read_scalar(&option_value)?.to_i32()?;
-                    // We entirely ignore this value since we do not support signals anyway.
+                    // Miri never raises SIGPIPE for sockets, so there is nothing to suppress.
 
                     return interp_ok(Ok(()));
                 }
             }
 
             if option == opt_so_rcvtimeo || option == opt_so_sndtimeo {
-                let timeval_layout = ecx.libc_ty_layout("timeval");
-                let option_value = ecx.ptr_to_mplace(value_ptr, timeval_layout);
-
-                let timeout = match ecx.read_timeval(&option_value)? {
-                    None => return interp_ok(Err(LibcError("EINVAL"))),
-                    Some(Duration::ZERO) => None,
-                    Some(duration) => Some(duration),
+                let timeout = match ecx.read_sockopt_timeout(value_ptr)? {
+                    Ok(timeout) => timeout,
+                    Err(e) => return interp_ok(Err(e)),
                 };
 
                 if option == opt_so_rcvtimeo {
@@ -714,23 +675,8 @@ impl UnixSocketFileDescription for TcpSocket {
                     self.read_timeout.get()
                 } else {
                     self.write_timeout.get()
-                }
-                .unwrap_or_default();
-
-                let secs = timeout.as_secs();
-                let usecs = timeout.subsec_micros();
-
-                let timeval_layout = ecx.libc_ty_layout("timeval");
-                // Allocate new buffer on the stack with the `timeval` layout.
-                let timeval_buffer = ecx.allocate(timeval_layout, MemoryKind::Stack)?;
-
-                let sec_field = ecx.project_field_named(&timeval_buffer, "tv_sec")?;
-                ecx.write_int(secs, &sec_field)?;
-
-                let usec_field = ecx.project_field_named(&timeval_buffer, "tv_usec")?;
-                ecx.write_int(usecs, &usec_field)?;
-
-                interp_ok(Ok(timeval_buffer))
+                };
+                interp_ok(Ok(ecx.sockopt_timeout_value(timeout)?))
             } else {
                 throw_unsup_format!(
                     "getsockopt: option {option:#x} is unsupported for level SOL_SOCKET",
@@ -807,7 +753,7 @@ impl UnixSocketFileDescription for TcpSocket {
         self: FileDescriptionRef<Self>,
         communicate_allowed: bool,
         ecx: &mut MiriInterpCx<'tcx>,
-    ) -> InterpResult<'tcx, Result<SocketAddr, IoError>> {
+    ) -> InterpResult<'tcx, Result<SocketAddress, IoError>> {
         assert!(communicate_allowed, "cannot have `TcpSocket` with isolation enabled!");
         ecx.ensure_not_failed(&self, "getsockname")?;
 
@@ -856,14 +802,14 @@ impl UnixSocketFileDescription for TcpSocket {
             SocketState::ConnectionFailed(_) => unreachable!(),
         };
 
-        interp_ok(Ok(address))
+        interp_ok(Ok(address.into()))
     }
 
     fn getpeername<'tcx>(
         self: FileDescriptionRef<Self>,
         communicate_allowed: bool,
         ecx: &mut MiriInterpCx<'tcx>,
-        finish: DynMachineCallback<'tcx, Result<SocketAddr, IoError>>,
+        finish: DynMachineCallback<'tcx, Result<SocketAddress, IoError>>,
     ) -> InterpResult<'tcx> {
         assert!(communicate_allowed, "cannot have `TcpSocket` with isolation enabled!");
 
@@ -878,7 +824,7 @@ impl UnixSocketFileDescription for TcpSocket {
             callback!(
                 @capture<'tcx> {
                     socket: FileDescriptionRef<TcpSocket>,
-                    finish: DynMachineCallback<'tcx, Result<SocketAddr, IoError>>,
+                    finish: DynMachineCallback<'tcx, Result<SocketAddress, IoError>>,
                 } |this, result: Result<(), ()>| {
                     if result.is_err() {
                         return finish.call(this, Err(LibcError("ENOTCONN")))
@@ -888,7 +834,7 @@ impl UnixSocketFileDescription for TcpSocket {
                         unreachable!()
                     };
 
-                    let result = stream.peer_addr().map_err(IoError::HostError);
+                    let result = stream.peer_addr().map(Into::into).map_err(IoError::HostError);
                     finish.call(this, result)
                 }
             ),
@@ -942,28 +888,6 @@ impl UnixSocketFileDescription for TcpSocket {
 
 impl<'tcx> EvalContextPrivExt<'tcx> for crate::MiriInterpCx<'tcx> {}
 trait EvalContextPrivExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
-    /// Get the deadline for an action (e.g. reading or writing).
-    /// When `is_non_block` is [`true`], the returned deadline is "now", i.e.,
-    /// we wake up immediately if the action cannot be completed.
-    /// If `action_timeout` is `Some(duration)`, the returned deadline is in the
-    /// future be the specified `duration`. Otherwise, no deadline ([`None`]) is
-    /// returned, indicating that the action can block indefinitely.
-    fn action_deadline(
-        &self,
-        is_non_block: bool,
-        action_timeout: Option<Duration>,
-    ) -> Option<Deadline> {
-        let this = self.eval_context_ref();
-
-        if is_non_block {
-            // Non-blocking sockets always have a zero timeout.
-            Some(this.machine.monotonic_clock.now().into())
-        } else {
-            action_timeout
-                .map(|duration| this.machine.monotonic_clock.now().add_lossy(duration).into())
-        }
-    }
-
     /// Block the thread until there's an incoming connection or an error occurred.
     /// After a successful accept, `finish` is called with a tuple containing the
     /// file descriptor of the peer socket and it's address.
@@ -976,7 +900,7 @@ trait EvalContextPrivExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         &mut self,
         socket: FileDescriptionRef<TcpSocket>,
         is_client_sock_nonblock: bool,
-        finish: DynMachineCallback<'tcx, Result<(FdNum, SocketAddr), IoError>>,
+        finish: DynMachineCallback<'tcx, Result<(FdNum, SocketAddress), IoError>>,
     ) -> InterpResult<'tcx> {
         let this = self.eval_context_mut();
         // Since the callback holds a strong reference to the socket, the file description
@@ -989,7 +913,7 @@ trait EvalContextPrivExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
             callback!(@capture<'tcx> {
                 socket: FileDescriptionRef<TcpSocket>,
                 is_client_sock_nonblock: bool,
-                finish: DynMachineCallback<'tcx, Result<(FdNum, SocketAddr), IoError>>,
+                finish: DynMachineCallback<'tcx, Result<(FdNum, SocketAddress), IoError>>,
             } |this, kind: UnblockKind| {
                 // Remove the blocking I/O interest for unblocking this thread.
                 this.machine.blocking_io.remove_blocked_thread(socket.id(), this.machine.threads.active_thread());
@@ -1022,7 +946,7 @@ trait EvalContextPrivExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         &mut self,
         socket: &FileDescriptionRef<TcpSocket>,
         is_client_sock_nonblock: bool,
-    ) -> InterpResult<'tcx, Result<(FdNum, SocketAddr), IoError>> {
+    ) -> InterpResult<'tcx, Result<(FdNum, SocketAddress), IoError>> {
         let this = self.eval_context_mut();
 
         let state = socket.state.borrow();
@@ -1044,13 +968,8 @@ trait EvalContextPrivExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
             Err(e) => return interp_ok(Err(IoError::HostError(e))),
         };
 
-        let family = match addr {
-            SocketAddr::V4(_) => SocketFamily::IPv4,
-            SocketAddr::V6(_) => SocketFamily::IPv6,
-        };
-
         let fd = this.machine.fds.new_ref(TcpSocket {
-            family,
+            family: SocketFamily::of(&addr),
             state: RefCell::new(SocketState::Connected(stream)),
             is_non_block: Cell::new(is_client_sock_nonblock),
             io_readiness: RefCell::new(Readiness::EMPTY),
@@ -1063,7 +982,7 @@ trait EvalContextPrivExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         // there is an associated host socket.
         this.machine.blocking_io.register(fd.clone());
         let sockfd = this.machine.fds.insert(fd);
-        interp_ok(Ok((sockfd, addr)))
+        interp_ok(Ok((sockfd, addr.into())))
     }
 
     /// Block the thread until we can send bytes into the connected socket
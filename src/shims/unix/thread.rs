@@ -27,13 +27,14 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
 
         let func_arg = this.read_immediate(arg)?;
 
-        this.start_regular_thread(
+        let new_thread = this.start_regular_thread(
             Some(thread_info_place),
             start_routine,
             ExternAbi::C { unwind: false },
             func_arg,
             this.machine.layouts.mut_raw_ptr,
         )?;
+        this.machine.signals.inherit_mask(this.active_thread(), new_thread);
 
         interp_ok(())
     }
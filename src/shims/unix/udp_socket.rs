@@ -0,0 +1,1060 @@
+use std::cell::{Cell, RefCell, RefMut};
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr, Shutdown, SocketAddr, SocketAddrV4, SocketAddrV6};
+use std::time::Duration;
+
+use mio::event::Source;
+use rustc_abi::Size;
+use rustc_const_eval::interpret::{InterpResult, interp_ok};
+use rustc_middle::throw_unsup_format;
+use rustc_target::spec::Os;
+
+use crate::shims::files::{EvalContextExt as _, FdNum, FileDescription, FileDescriptionRef};
+use crate::shims::unix::UnixFileDescription;
+use crate::shims::unix::socket::{EvalContextExt as _, SocketFamily, UnixSocketFileDescription};
+use crate::shims::unix::socket_address::SocketAddress;
+use crate::*;
+
+/// A datagram socket of the IPv4 or IPv6 family.
+///
+/// Unlike [`TcpSocket`](super::tcp_socket::TcpSocket)s, which only get a host socket once they
+/// listen or connect, a UDP socket gets its host socket as soon as it is bound: either explicitly
+/// via `bind`, or implicitly to the unspecified address when it first sends or connects.
+#[derive(Debug)]
+pub(super) struct UdpSocket {
+    /// Family of the socket, used to ensure socket only binds/connects to address of
+    /// same family.
+    family: SocketFamily,
+    /// The underlying host socket. This is [`None`] until the socket is bound.
+    host_socket: RefCell<Option<mio::net::UdpSocket>>,
+    /// The address of the peer the socket is connected to, if any.
+    peer: Cell<Option<SocketAddr>>,
+    /// Whether this fd is non-blocking or not.
+    is_non_block: Cell<bool>,
+    /// The current blocking I/O readiness of the file description.
+    io_readiness: RefCell<Readiness>,
+    /// Read timeout of the socket. [`None`] means that reads can block indefinitely.
+    /// This is ignored when the socket is non-blocking.
+    read_timeout: Cell<Option<Duration>>,
+    /// Write timeout of the socket. [`None`] means that writes can block indefinitely.
+    /// This is ignored when the socket is non-blocking.
+    write_timeout: Cell<Option<Duration>>,
+    /// State for being watched by epoll.
+    watched: ReadinessWatched,
+}
+
+impl UdpSocket {
+    pub fn new(family: SocketFamily, is_non_block: bool) -> Self {
+        UdpSocket {
+            family,
+            host_socket: RefCell::new(None),
+            peer: Cell::new(None),
+            is_non_block: Cell::new(is_non_block),
+            io_readiness: RefCell::new(Readiness::EMPTY),
+            read_timeout: Cell::new(None),
+            write_timeout: Cell::new(None),
+            watched: ReadinessWatched::default(),
+        }
+    }
+
+    /// The unspecified address of the socket's family, which is what
+    /// an unbound socket gets implicitly bound to.
+    fn unspecified_address(&self) -> SocketAddr {
+        match self.family {
+            SocketFamily::IPv4 => SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0)),
+            SocketFamily::IPv6 => SocketAddr::V6(SocketAddrV6::new(Ipv6Addr::UNSPECIFIED, 0, 0, 0)),
+        }
+    }
+}
+
+impl FileDescription for UdpSocket {
+    fn name(&self) -> &'static str {
+        "socket"
+    }
+
+    fn read<'tcx>(
+        self: FileDescriptionRef<Self>,
+        communicate_allowed: bool,
+        ptr: Pointer,
+        len: usize,
+        ecx: &mut MiriInterpCx<'tcx>,
+        finish: DynMachineCallback<'tcx, Result<usize, IoError>>,
+    ) -> InterpResult<'tcx> {
+        self.recv(
+            communicate_allowed,
+            ptr,
+            len,
+            /* is_peek */ false,
+            /* is_non_block */ false,
+            ecx,
+            finish,
+        )
+    }
+
+    fn write<'tcx>(
+        self: FileDescriptionRef<Self>,
+        communicate_allowed: bool,
+        ptr: Pointer,
+        len: usize,
+        ecx: &mut MiriInterpCx<'tcx>,
+        finish: DynMachineCallback<'tcx, Result<usize, IoError>>,
+    ) -> InterpResult<'tcx> {
+        self.send(communicate_allowed, ptr, len, /* is_non_block */ false, ecx, finish)
+    }
+
+    fn short_fd_operations(&self) -> bool {
+        // Every read or write transfers exactly one datagram; shortening
+        // it would truncate the datagram instead.
+        false
+    }
+
+    fn as_unix<'tcx>(
+        self: FileDescriptionRef<Self>,
+        _ecx: &MiriInterpCx<'tcx>,
+    ) -> FileDescriptionRef<dyn UnixFileDescription> {
+        self
+    }
+
+    fn get_flags<'tcx>(&self, ecx: &mut MiriInterpCx<'tcx>) -> InterpResult<'tcx, Scalar> {
+        let mut flags = ecx.eval_libc_i32("O_RDWR");
+
+        if self.is_non_block.get() {
+            flags |= ecx.eval_libc_i32("O_NONBLOCK");
+        }
+
+        interp_ok(Scalar::from_i32(flags))
+    }
+
+    fn set_flags<'tcx>(
+        &self,
+        mut flag: i32,
+        ecx: &mut MiriInterpCx<'tcx>,
+    ) -> InterpResult<'tcx, Scalar> {
+        let o_nonblock = ecx.eval_libc_i32("O_NONBLOCK");
+
+        // O_NONBLOCK flag can be set / unset by user.
+        if flag & o_nonblock == o_nonblock {
+            self.is_non_block.set(true);
+            flag &= !o_nonblock;
+        } else {
+            self.is_non_block.set(false);
+        }
+
+        // Throw error if there is any unsupported flag.
+        if flag != 0 {
+            throw_unsup_format!("fcntl: only O_NONBLOCK is supported for sockets")
+        }
+
+        interp_ok(Scalar::from_i32(0))
+    }
+
+    fn readiness_watched(&self) -> Option<&ReadinessWatched> {
+        Some(&self.watched)
+    }
+
+    fn readiness(&self) -> Readiness {
+        *self.io_readiness.borrow()
+    }
+}
+
+impl UnixFileDescription for UdpSocket {
+    fn ioctl<'tcx>(
+        &self,
+        op: Scalar,
+        arg: Option<&OpTy<'tcx>>,
+        ecx: &mut MiriInterpCx<'tcx>,
+    ) -> InterpResult<'tcx, i32> {
+        assert!(ecx.machine.communicate(), "cannot have `UdpSocket` with isolation enabled!");
+        ecx.socket_ioctl(op, arg, &self.is_non_block)
+    }
+
+    fn as_socket<'tcx>(
+        self: FileDescriptionRef<Self>,
+        _ecx: &MiriInterpCx<'tcx>,
+    ) -> Option<FileDescriptionRef<dyn UnixSocketFileDescription>> {
+        Some(self)
+    }
+}
+
+impl UnixSocketFileDescription for UdpSocket {
+    fn bind<'tcx>(
+        self: FileDescriptionRef<Self>,
+        communicate_allowed: bool,
+        address: SocketAddress,
+        ecx: &mut MiriInterpCx<'tcx>,
+    ) -> InterpResult<'tcx, Result<(), IoError>> {
+        assert!(communicate_allowed, "cannot have `UdpSocket` with isolation enabled!");
+
+        let address = match address {
+            SocketAddress::Inet(address) if SocketFamily::of(&address) == self.family => address,
+            _ => return interp_ok(Err(ecx.bind_family_mismatch_error())),
+        };
+
+        if self.host_socket.borrow().is_some() {
+            // The socket is already bound, either explicitly or implicitly.
+            return interp_ok(Err(LibcError("EINVAL")));
+        }
+
+        ecx.bind_udp_socket(self, address)
+    }
+
+    fn listen<'tcx>(
+        self: FileDescriptionRef<Self>,
+        _communicate_allowed: bool,
+        _backlog: i32,
+        _ecx: &mut MiriInterpCx<'tcx>,
+    ) -> InterpResult<'tcx, Result<(), IoError>> {
+        // Datagram sockets are connectionless.
+        interp_ok(Err(LibcError("EOPNOTSUPP")))
+    }
+
+    fn accept<'tcx>(
+        self: FileDescriptionRef<Self>,
+        _communicate_allowed: bool,
+        _is_client_sock_non_block: bool,
+        ecx: &mut MiriInterpCx<'tcx>,
+        finish: DynMachineCallback<'tcx, Result<(FdNum, SocketAddress), IoError>>,
+    ) -> InterpResult<'tcx> {
+        // Datagram sockets are connectionless.
+        finish.call(ecx, Err(LibcError("EOPNOTSUPP")))
+    }
+
+    fn connect<'tcx>(
+        self: FileDescriptionRef<Self>,
+        communicate_allowed: bool,
+        address: SocketAddress,
+        ecx: &mut MiriInterpCx<'tcx>,
+        finish: DynMachineCallback<'tcx, Result<(), IoError>>,
+    ) -> InterpResult<'tcx> {
+        assert!(communicate_allowed, "cannot have `UdpSocket` with isolation enabled!");
+
+        let address = match address {
+            SocketAddress::Inet(address) if SocketFamily::of(&address) == self.family => address,
+            _ => return finish.call(ecx, Err(LibcError("EAFNOSUPPORT"))),
+        };
+
+        if let Err(e) = ecx.ensure_udp_bound(&self)? {
+            return finish.call(ecx, Err(e));
+        }
+
+        // Connecting a datagram socket only sets its default peer, so this never blocks.
+        let result = self.host_socket.borrow().as_ref().unwrap().connect(address);
+        match result {
+            Ok(()) => {
+                self.peer.set(Some(address));
+                finish.call(ecx, Ok(()))
+            }
+            Err(e) => finish.call(ecx, Err(IoError::HostError(e))),
+        }
+    }
+
+    fn send<'tcx>(
+        self: FileDescriptionRef<Self>,
+        communicate_allowed: bool,
+        ptr: Pointer,
+        len: usize,
+        is_non_block: bool,
+        ecx: &mut MiriInterpCx<'tcx>,
+        finish: DynMachineCallback<'tcx, Result<usize, IoError>>,
+    ) -> InterpResult<'tcx> {
+        assert!(communicate_allowed, "cannot have `UdpSocket` with isolation enabled!");
+
+        if self.peer.get().is_none() {
+            // Without a peer, there is no destination for the datagram.
+            return finish.call(ecx, Err(LibcError("EDESTADDRREQ")));
+        }
+
+        ecx.send_datagram(self, ptr, len, /* address */ None, is_non_block, finish)
+    }
+
+    fn send_to<'tcx>(
+        self: FileDescriptionRef<Self>,
+        communicate_allowed: bool,
+        ptr: Pointer,
+        len: usize,
+        address: SocketAddress,
+        is_non_block: bool,
+        ecx: &mut MiriInterpCx<'tcx>,
+        finish: DynMachineCallback<'tcx, Result<usize, IoError>>,
+    ) -> InterpResult<'tcx> {
+        assert!(communicate_allowed, "cannot have `UdpSocket` with isolation enabled!");
+
+        let address = match address {
+            SocketAddress::Inet(address) if SocketFamily::of(&address) == self.family => address,
+            _ => return finish.call(ecx, Err(LibcError("EAFNOSUPPORT"))),
+        };
+
+        if let Err(e) = ecx.ensure_udp_bound(&self)? {
+            return finish.call(ecx, Err(e));
+        }
+
+        ecx.send_datagram(self, ptr, len, Some(address), is_non_block, finish)
+    }
+
+    fn recv<'tcx>(
+        self: FileDescriptionRef<Self>,
+        communicate_allowed: bool,
+        ptr: Pointer,
+        len: usize,
+        is_peek: bool,
+        is_non_block: bool,
+        ecx: &mut MiriInterpCx<'tcx>,
+        finish: DynMachineCallback<'tcx, Result<usize, IoError>>,
+    ) -> InterpResult<'tcx> {
+        self.recv_from(
+            communicate_allowed,
+            ptr,
+            len,
+            is_peek,
+            is_non_block,
+            ecx,
+            callback!(
+                @capture<'tcx> {
+                    finish: DynMachineCallback<'tcx, Result<usize, IoError>>,
+                } |this, result: Result<(usize, Option<SocketAddress>), IoError>| {
+                    finish.call(this, result.map(|(bytes_received, _address)| bytes_received))
+                }
+            ),
+        )
+    }
+
+    fn recv_from<'tcx>(
+        self: FileDescriptionRef<Self>,
+        communicate_allowed: bool,
+        ptr: Pointer,
+        len: usize,
+        is_peek: bool,
+        is_non_block: bool,
+        ecx: &mut MiriInterpCx<'tcx>,
+        finish: DynMachineCallback<'tcx, Result<(usize, Option<SocketAddress>), IoError>>,
+    ) -> InterpResult<'tcx> {
+        assert!(communicate_allowed, "cannot have `UdpSocket` with isolation enabled!");
+
+        if self.host_socket.borrow().is_none() {
+            // An unbound socket has no port that datagrams could be sent to.
+            throw_unsup_format!("recv: receiving on a udp socket which isn't bound is unsupported")
+        }
+
+        let is_non_block = is_non_block || self.is_non_block.get();
+        if is_non_block {
+            // We have a non-blocking operation or a non-blocking socket and
+            // thus don't want to block until we can receive.
+            let result = ecx.try_non_block_recv(&self, ptr, len, is_peek)?;
+            finish.call(ecx, result)
+        } else {
+            // The socket is in blocking mode and thus the receive call should block
+            // until a datagram arrives or the timeout exceeded.
+            let deadline = ecx.action_deadline(is_non_block, self.read_timeout.get());
+            ecx.block_for_recv(self, deadline, ptr, len, is_peek, finish)
+        }
+    }
+
+    fn setsockopt<'tcx>(
+        self: FileDescriptionRef<Self>,
+        level: i32,
+        option: i32,
+        value_ptr: Pointer,
+        value_len: u64,
+        ecx: &mut MiriInterpCx<'tcx>,
+    ) -> InterpResult<'tcx, Result<(), IoError>> {
+        if level == ecx.eval_libc_i32("SOL_SOCKET") {
+            let opt_so_rcvtimeo = ecx.eval_libc_i32("SO_RCVTIMEO");
+            let opt_so_sndtimeo = ecx.eval_libc_i32("SO_SNDTIMEO");
+            let opt_so_reuseaddr = ecx.eval_libc_i32("SO_REUSEADDR");
+            let opt_so_broadcast = ecx.eval_libc_i32("SO_BROADCAST");
+
+            if option == opt_so_rcvtimeo || option == opt_so_sndtimeo {
+                let timeout = match ecx.read_sockopt_timeout(value_ptr)? {
+                    Ok(timeout) => timeout,
+                    Err(e) => return interp_ok(Err(e)),
+                };
+
+                if option == opt_so_rcvtimeo {
+                    self.read_timeout.set(timeout);
+                } else {
+                    self.write_timeout.set(timeout);
+                }
+
+                return interp_ok(Ok(()));
+            }
+
+            if option == opt_so_reuseaddr {
+                if let Err(e) = ecx.read_int_sockopt(value_ptr, value_len)? {
+                    return interp_ok(Err(e));
+                }
+                // Like for TCP sockets, we ignore this: it only affects whether other
+                // sockets may bind to the same address.
+                return interp_ok(Ok(()));
+            }
+
+            if option == opt_so_broadcast {
+                let broadcast = match ecx.read_int_sockopt(value_ptr, value_len)? {
+                    Ok(value) => value != 0,
+                    Err(e) => return interp_ok(Err(e)),
+                };
+                let result = self.bound_host_socket("setsockopt", "SO_BROADCAST")?;
+                return interp_ok(result.set_broadcast(broadcast).map_err(IoError::HostError));
+            }
+
+            // SO_NOSIGPIPE only exists on MacOS, FreeBSD, and NetBSD.
+            if matches!(ecx.tcx.sess.target.os, Os::MacOs | Os::FreeBsd | Os::NetBsd)
+                && option == ecx.eval_libc_i32("SO_NOSIGPIPE")
+            {
+                if let Err(e) = ecx.read_int_sockopt(value_ptr, value_len)? {
+                    return interp_ok(Err(e));
+                }
+                // Miri never raises SIGPIPE for sockets, so there is nothing to suppress.
+                return interp_ok(Ok(()));
+            }
+
+            throw_unsup_format!(
+                "setsockopt: option {option:#x} is unsupported for level SOL_SOCKET on udp sockets",
+            );
+        } else if level == ecx.eval_libc_i32("IPPROTO_IP") {
+            let opt_ip_ttl = ecx.eval_libc_i32("IP_TTL");
+            let opt_ip_multicast_ttl = ecx.eval_libc_i32("IP_MULTICAST_TTL");
+            let opt_ip_multicast_loop = ecx.eval_libc_i32("IP_MULTICAST_LOOP");
+            let opt_ip_add_membership = ecx.eval_libc_i32("IP_ADD_MEMBERSHIP");
+            let opt_ip_drop_membership = ecx.eval_libc_i32("IP_DROP_MEMBERSHIP");
+
+            if option == opt_ip_ttl {
+                let ttl = match ecx.read_int_sockopt(value_ptr, value_len)? {
+                    Ok(ttl) => ttl,
+                    Err(e) => return interp_ok(Err(e)),
+                };
+                let Ok(ttl) = u32::try_from(ttl) else {
+                    return interp_ok(Err(LibcError("EINVAL")));
+                };
+                let host_socket = self.bound_host_socket("setsockopt", "IP_TTL")?;
+                return interp_ok(host_socket.set_ttl(ttl).map_err(IoError::HostError));
+            }
+
+            if option == opt_ip_multicast_ttl || option == opt_ip_multicast_loop {
+                let value = match ecx.read_multicast_sockopt(value_ptr, value_len)? {
+                    Ok(value) => value,
+                    Err(e) => return interp_ok(Err(e)),
+                };
+                let result = if option == opt_ip_multicast_ttl {
+                    let host_socket = self.bound_host_socket("setsockopt", "IP_MULTICAST_TTL")?;
+                    host_socket.set_multicast_ttl_v4(value)
+                } else {
+                    let host_socket = self.bound_host_socket("setsockopt", "IP_MULTICAST_LOOP")?;
+                    host_socket.set_multicast_loop_v4(value != 0)
+                };
+                return interp_ok(result.map_err(IoError::HostError));
+            }
+
+            if option == opt_ip_add_membership || option == opt_ip_drop_membership {
+                let (multiaddr, interface) = match ecx.read_ip_mreq(value_ptr, value_len)? {
+                    Ok(mreq) => mreq,
+                    Err(e) => return interp_ok(Err(e)),
+                };
+                let result = if option == opt_ip_add_membership {
+                    let host_socket = self.bound_host_socket("setsockopt", "IP_ADD_MEMBERSHIP")?;
+                    host_socket.join_multicast_v4(&multiaddr, &interface)
+                } else {
+                    let host_socket = self.bound_host_socket("setsockopt", "IP_DROP_MEMBERSHIP")?;
+                    host_socket.leave_multicast_v4(&multiaddr, &interface)
+                };
+                return interp_ok(result.map_err(IoError::HostError));
+            }
+
+            throw_unsup_format!(
+                "setsockopt: option {option:#x} is unsupported for level IPPROTO_IP on udp sockets",
+            );
+        } else if level == ecx.eval_libc_i32("IPPROTO_IPV6") {
+            let opt_ipv6_multicast_loop = ecx.eval_libc_i32("IPV6_MULTICAST_LOOP");
+            let (opt_ipv6_join_group, opt_ipv6_leave_group) = ecx.ipv6_membership_options();
+
+            if option == opt_ipv6_multicast_loop {
+                let multicast_loop = match ecx.read_int_sockopt(value_ptr, value_len)? {
+                    Ok(value) => value != 0,
+                    Err(e) => return interp_ok(Err(e)),
+                };
+                let host_socket = self.bound_host_socket("setsockopt", "IPV6_MULTICAST_LOOP")?;
+                return interp_ok(
+                    host_socket.set_multicast_loop_v6(multicast_loop).map_err(IoError::HostError),
+                );
+            }
+
+            if option == opt_ipv6_join_group || option == opt_ipv6_leave_group {
+                let (multiaddr, interface) = match ecx.read_ipv6_mreq(value_ptr, value_len)? {
+                    Ok(mreq) => mreq,
+                    Err(e) => return interp_ok(Err(e)),
+                };
+                let host_socket = self.bound_host_socket("setsockopt", "IPV6 group membership")?;
+                let result = if option == opt_ipv6_join_group {
+                    host_socket.join_multicast_v6(&multiaddr, interface)
+                } else {
+                    host_socket.leave_multicast_v6(&multiaddr, interface)
+                };
+                return interp_ok(result.map_err(IoError::HostError));
+            }
+
+            throw_unsup_format!(
+                "setsockopt: option {option:#x} is unsupported for level IPPROTO_IPV6 on udp sockets",
+            );
+        }
+
+        throw_unsup_format!(
+            "setsockopt: level {level:#x} is unsupported for udp sockets, only SOL_SOCKET, \
+            IPPROTO_IP and IPPROTO_IPV6 are allowed"
+        );
+    }
+
+    fn getsockopt<'tcx>(
+        self: FileDescriptionRef<Self>,
+        level: i32,
+        option: i32,
+        ecx: &mut MiriInterpCx<'tcx>,
+    ) -> InterpResult<'tcx, Result<MPlaceTy<'tcx>, IoError>> {
+        // The value of every supported option other than the timeouts is an integer.
+        let value = if level == ecx.eval_libc_i32("SOL_SOCKET") {
+            let opt_so_error = ecx.eval_libc_i32("SO_ERROR");
+            let opt_so_rcvtimeo = ecx.eval_libc_i32("SO_RCVTIMEO");
+            let opt_so_sndtimeo = ecx.eval_libc_i32("SO_SNDTIMEO");
+            let opt_so_broadcast = ecx.eval_libc_i32("SO_BROADCAST");
+
+            if option == opt_so_rcvtimeo || option == opt_so_sndtimeo {
+                let timeout = if option == opt_so_rcvtimeo {
+                    self.read_timeout.get()
+                } else {
+                    self.write_timeout.get()
+                };
+                return interp_ok(Ok(ecx.sockopt_timeout_value(timeout)?));
+            } else if option == opt_so_error {
+                let error = match self.host_socket.borrow().as_ref() {
+                    Some(host_socket) =>
+                        host_socket.take_error().expect("Reading SO_ERROR should not fail"),
+                    None => None,
+                };
+                // We know there is no longer an async error and thus we need to update the
+                // I/O and fd readiness of the socket.
+                self.io_readiness.borrow_mut().error = false;
+                ecx.update_fd_readiness(self.clone(), ReadinessUpdateFlags::DEFAULT)?;
+
+                match error {
+                    Some(err) => ecx.io_error_to_errnum(err)?.to_i32()?,
+                    // If there is no error, we return 0 as the option value.
+                    None => 0,
+                }
+            } else if option == opt_so_broadcast {
+                match self.bound_host_socket("getsockopt", "SO_BROADCAST")?.broadcast() {
+                    Ok(broadcast) => i32::from(broadcast),
+                    Err(e) => return interp_ok(Err(IoError::HostError(e))),
+                }
+            } else {
+                throw_unsup_format!(
+                    "getsockopt: option {option:#x} is unsupported for level SOL_SOCKET on udp sockets",
+                );
+            }
+        } else if level == ecx.eval_libc_i32("IPPROTO_IP") {
+            let opt_ip_ttl = ecx.eval_libc_i32("IP_TTL");
+            let opt_ip_multicast_ttl = ecx.eval_libc_i32("IP_MULTICAST_TTL");
+            let opt_ip_multicast_loop = ecx.eval_libc_i32("IP_MULTICAST_LOOP");
+
+            if option == opt_ip_ttl {
+                match self.bound_host_socket("getsockopt", "IP_TTL")?.ttl() {
+                    Ok(ttl) => ttl.try_into().unwrap(),
+                    Err(e) => return interp_ok(Err(IoError::HostError(e))),
+                }
+            } else if option == opt_ip_multicast_ttl || option == opt_ip_multicast_loop {
+                let value = if option == opt_ip_multicast_ttl {
+                    self.bound_host_socket("getsockopt", "IP_MULTICAST_TTL")?.multicast_ttl_v4()
+                } else {
+                    self.bound_host_socket("getsockopt", "IP_MULTICAST_LOOP")?
+                        .multicast_loop_v4()
+                        .map(u32::from)
+                };
+                let value = match value {
+                    Ok(value) => value,
+                    Err(e) => return interp_ok(Err(IoError::HostError(e))),
+                };
+                return interp_ok(Ok(ecx.multicast_sockopt_value(value)?));
+            } else {
+                throw_unsup_format!(
+                    "getsockopt: option {option:#x} is unsupported for level IPPROTO_IP on udp sockets",
+                );
+            }
+        } else if level == ecx.eval_libc_i32("IPPROTO_IPV6") {
+            let opt_ipv6_multicast_loop = ecx.eval_libc_i32("IPV6_MULTICAST_LOOP");
+            let opt_ipv6_v6only = ecx.eval_libc_i32("IPV6_V6ONLY");
+
+            let value = if option == opt_ipv6_multicast_loop {
+                self.bound_host_socket("getsockopt", "IPV6_MULTICAST_LOOP")?.multicast_loop_v6()
+            } else if option == opt_ipv6_v6only {
+                self.bound_host_socket("getsockopt", "IPV6_V6ONLY")?.only_v6()
+            } else {
+                throw_unsup_format!(
+                    "getsockopt: option {option:#x} is unsupported for level IPPROTO_IPV6 on udp sockets",
+                );
+            };
+            match value {
+                Ok(value) => i32::from(value),
+                Err(e) => return interp_ok(Err(IoError::HostError(e))),
+            }
+        } else {
+            throw_unsup_format!(
+                "getsockopt: level {level:#x} is unsupported for udp sockets, only SOL_SOCKET, \
+                IPPROTO_IP and IPPROTO_IPV6 are allowed"
+            )
+        };
+
+        // Allocate new buffer on the stack with the `i32` layout.
+        let value_buffer = ecx.allocate(ecx.machine.layouts.i32, MemoryKind::Stack)?;
+        ecx.write_int(value, &value_buffer)?;
+        interp_ok(Ok(value_buffer))
+    }
+
+    fn getsockname<'tcx>(
+        self: FileDescriptionRef<Self>,
+        communicate_allowed: bool,
+        _ecx: &mut MiriInterpCx<'tcx>,
+    ) -> InterpResult<'tcx, Result<SocketAddress, IoError>> {
+        assert!(communicate_allowed, "cannot have `UdpSocket` with isolation enabled!");
+
+        let address = match self.host_socket.borrow().as_ref() {
+            Some(host_socket) =>
+                match host_socket.local_addr() {
+                    Ok(address) => address,
+                    Err(e) => return interp_ok(Err(IoError::HostError(e))),
+                },
+            // An unbound socket reports the unspecified address, just like it does on Linux.
+            None => self.unspecified_address(),
+        };
+
+        interp_ok(Ok(address.into()))
+    }
+
+    fn getpeername<'tcx>(
+        self: FileDescriptionRef<Self>,
+        communicate_allowed: bool,
+        ecx: &mut MiriInterpCx<'tcx>,
+        finish: DynMachineCallback<'tcx, Result<SocketAddress, IoError>>,
+    ) -> InterpResult<'tcx> {
+        assert!(communicate_allowed, "cannot have `UdpSocket` with isolation enabled!");
+
+        let result = self.peer.get().map(Into::into).ok_or(LibcError("ENOTCONN"));
+        finish.call(ecx, result)
+    }
+
+    fn shutdown<'tcx>(
+        self: FileDescriptionRef<Self>,
+        communicate_allowed: bool,
+        _how: Shutdown,
+        _ecx: &mut MiriInterpCx<'tcx>,
+    ) -> InterpResult<'tcx, Result<(), IoError>> {
+        assert!(communicate_allowed, "cannot have `UdpSocket` with isolation enabled!");
+
+        if self.peer.get().is_none() {
+            return interp_ok(Err(LibcError("ENOTCONN")));
+        }
+
+        throw_unsup_format!("shutdown: shutting down a connected udp socket is unsupported")
+    }
+}
+
+impl UdpSocket {
+    /// Get the host socket for reading or changing the option `option_name`, which is only
+    /// supported once the socket is bound.
+    fn bound_host_socket<'tcx>(
+        &self,
+        foreign_name: &'static str,
+        option_name: &'static str,
+    ) -> InterpResult<'tcx, std::cell::Ref<'_, mio::net::UdpSocket>> {
+        let host_socket = self.host_socket.borrow();
+        if host_socket.is_none() {
+            throw_unsup_format!(
+                "{foreign_name}: accessing option {option_name} is only supported on bound udp sockets"
+            )
+        }
+        interp_ok(std::cell::Ref::map(host_socket, |host_socket| host_socket.as_ref().unwrap()))
+    }
+}
+
+impl<'tcx> EvalContextPrivExt<'tcx> for crate::MiriInterpCx<'tcx> {}
+trait EvalContextPrivExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
+    /// Bind the host socket of `socket` to `address` and register it
+    /// to the blocking I/O manager.
+    fn bind_udp_socket(
+        &mut self,
+        socket: FileDescriptionRef<UdpSocket>,
+        address: SocketAddr,
+    ) -> InterpResult<'tcx, Result<(), IoError>> {
+        let this = self.eval_context_mut();
+
+        match mio::net::UdpSocket::bind(address) {
+            Ok(host_socket) => {
+                socket.host_socket.replace(Some(host_socket));
+                // Register the socket to the blocking I/O manager because
+                // we now have an associated host socket.
+                this.machine.blocking_io.register(socket);
+                interp_ok(Ok(()))
+            }
+            Err(e) => interp_ok(Err(IoError::HostError(e))),
+        }
+    }
+
+    /// Bind `socket` to the unspecified address of its family, unless it is already bound.
+    /// This is what the kernel does when sending on or connecting an unbound socket.
+    fn ensure_udp_bound(
+        &mut self,
+        socket: &FileDescriptionRef<UdpSocket>,
+    ) -> InterpResult<'tcx, Result<(), IoError>> {
+        let this = self.eval_context_mut();
+
+        if socket.host_socket.borrow().is_some() {
+            return interp_ok(Ok(()));
+        }
+        this.bind_udp_socket(socket.clone(), socket.unspecified_address())
+    }
+
+    /// Send a datagram to `address`, or to the connected peer if `address` is [`None`].
+    ///
+    /// **Note**: This function is only safe to call when having previously ensured
+    /// that the socket is bound.
+    fn send_datagram(
+        &mut self,
+        socket: FileDescriptionRef<UdpSocket>,
+        buffer_ptr: Pointer,
+        length: usize,
+        address: Option<SocketAddr>,
+        is_non_block: bool,
+        finish: DynMachineCallback<'tcx, Result<usize, IoError>>,
+    ) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+
+        let is_non_block = is_non_block || socket.is_non_block.get();
+        if is_non_block {
+            // We have a non-blocking operation or a non-blocking socket and
+            // thus don't want to block until we can send.
+            let result = this.try_non_block_send(&socket, buffer_ptr, length, address)?;
+            finish.call(this, result)
+        } else {
+            // The socket is in blocking mode and thus the send call should block
+            // until the datagram can be sent or the timeout exceeded.
+            let deadline = this.action_deadline(is_non_block, socket.write_timeout.get());
+            this.block_for_send(socket, deadline, buffer_ptr, length, address, finish)
+        }
+    }
+
+    /// Block the thread until the datagram can be sent or an error occurred.
+    ///
+    /// This recursively calls itself should the operation still block for some reason.
+    ///
+    /// **Note**: This function is only safe to call when having previously ensured
+    /// that the socket is bound.
+    fn block_for_send(
+        &mut self,
+        socket: FileDescriptionRef<UdpSocket>,
+        deadline: Option<Deadline>,
+        buffer_ptr: Pointer,
+        length: usize,
+        address: Option<SocketAddr>,
+        finish: DynMachineCallback<'tcx, Result<usize, IoError>>,
+    ) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+        // Since the callback holds a strong reference to the socket, the file description
+        // won't be closed as long as some thread is blocked on it. While this reflects
+        // what Linux does, for other Unix systems this might differ from the native behavior.
+        this.block_thread_for_io(
+            socket.clone(),
+            BlockingIoInterest::Write,
+            deadline.clone(),
+            callback!(@capture<'tcx> {
+                socket: FileDescriptionRef<UdpSocket>,
+                deadline: Option<Deadline>,
+                buffer_ptr: Pointer,
+                length: usize,
+                address: Option<SocketAddr>,
+                finish: DynMachineCallback<'tcx, Result<usize, IoError>>,
+            } |this, kind: UnblockKind| {
+                // Remove the blocking I/O interest for unblocking this thread.
+                this.machine.blocking_io.remove_blocked_thread(socket.id(), this.machine.threads.active_thread());
+
+                match kind {
+                    UnblockKind::Ready => { /* fall-through to below */ },
+                    // When the write timeout is exceeded EAGAIN/EWOULDBLOCK is returned.
+                    UnblockKind::TimedOut => return finish.call(this, Err(LibcError("EWOULDBLOCK")))
+                }
+
+                match this.try_non_block_send(&socket, buffer_ptr, length, address)? {
+                    Err(IoError::HostError(e)) if e.kind() == io::ErrorKind::WouldBlock => {
+                        // We need to block the thread again as it would still block.
+                        this.block_for_send(socket, deadline, buffer_ptr, length, address, finish)
+                    },
+                    result => finish.call(this, result)
+                }
+            }),
+        )
+    }
+
+    /// Attempt to send a datagram in a non-blocking manner.
+    fn try_non_block_send(
+        &mut self,
+        socket: &FileDescriptionRef<UdpSocket>,
+        buffer_ptr: Pointer,
+        length: usize,
+        address: Option<SocketAddr>,
+    ) -> InterpResult<'tcx, Result<usize, IoError>> {
+        let this = self.eval_context_mut();
+
+        let host_socket = socket.host_socket.borrow();
+        let host_socket = host_socket.as_ref().unwrap();
+
+        let bytes = this.read_bytes_ptr_strip_provenance(buffer_ptr, Size::from_bytes(length))?;
+        let result = match address {
+            Some(address) => host_socket.send_to(bytes, address),
+            None => host_socket.send(bytes),
+        };
+
+        match result {
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                // We know that the source is not writable so we need to update its readiness.
+                socket.io_readiness.borrow_mut().writable = false;
+                this.update_fd_readiness(socket.clone(), ReadinessUpdateFlags::DEFAULT)?;
+                interp_ok(Err(IoError::HostError(e)))
+            }
+            result => interp_ok(result.map_err(IoError::HostError)),
+        }
+    }
+
+    /// Block the thread until a datagram can be received or an error occurred.
+    ///
+    /// This recursively calls itself should the operation still block for some reason.
+    ///
+    /// **Note**: This function is only safe to call when having previously ensured
+    /// that the socket is bound.
+    fn block_for_recv(
+        &mut self,
+        socket: FileDescriptionRef<UdpSocket>,
+        deadline: Option<Deadline>,
+        buffer_ptr: Pointer,
+        length: usize,
+        should_peek: bool,
+        finish: DynMachineCallback<'tcx, Result<(usize, Option<SocketAddress>), IoError>>,
+    ) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+        // Since the callback holds a strong reference to the socket, the file description
+        // won't be closed as long as some thread is blocked on it. While this reflects
+        // what Linux does, for other Unix systems this might differ from the native behavior.
+        this.block_thread_for_io(
+            socket.clone(),
+            BlockingIoInterest::Read,
+            deadline.clone(),
+            callback!(@capture<'tcx> {
+                socket: FileDescriptionRef<UdpSocket>,
+                deadline: Option<Deadline>,
+                buffer_ptr: Pointer,
+                length: usize,
+                should_peek: bool,
+                finish: DynMachineCallback<'tcx, Result<(usize, Option<SocketAddress>), IoError>>,
+            } |this, kind: UnblockKind| {
+                // Remove the blocking I/O interest for unblocking this thread.
+                this.machine.blocking_io.remove_blocked_thread(socket.id(), this.machine.threads.active_thread());
+
+                match kind {
+                    UnblockKind::Ready => { /* fall-through to below */ },
+                    // When the read timeout is exceeded EAGAIN/EWOULDBLOCK is returned.
+                    UnblockKind::TimedOut => return finish.call(this, Err(LibcError("EWOULDBLOCK")))
+                }
+
+                match this.try_non_block_recv(&socket, buffer_ptr, length, should_peek)? {
+                    Err(IoError::HostError(e)) if e.kind() == io::ErrorKind::WouldBlock => {
+                        // We need to block the thread again as it would still block.
+                        this.block_for_recv(socket, deadline, buffer_ptr, length, should_peek, finish)
+                    },
+                    result => finish.call(this, result)
+                }
+            }),
+        )
+    }
+
+    /// Attempt to receive a datagram in a non-blocking manner.
+    fn try_non_block_recv(
+        &mut self,
+        socket: &FileDescriptionRef<UdpSocket>,
+        buffer_ptr: Pointer,
+        length: usize,
+        should_peek: bool,
+    ) -> InterpResult<'tcx, Result<(usize, Option<SocketAddress>), IoError>> {
+        let this = self.eval_context_mut();
+
+        let host_socket = socket.host_socket.borrow();
+        let host_socket = host_socket.as_ref().unwrap();
+
+        let mut source = None;
+        // This is a *non-blocking* read/peek.
+        let result = this.read_from_host(
+            |buf| {
+                let (bytes_read, address) = if should_peek {
+                    host_socket.peek_from(buf)
+                } else {
+                    host_socket.recv_from(buf)
+                }?;
+                source = Some(address);
+                Ok(bytes_read)
+            },
+            length,
+            buffer_ptr,
+        )?;
+
+        match result {
+            Err(IoError::HostError(e)) if e.kind() == io::ErrorKind::WouldBlock => {
+                // We know that the source is not readable so we need to update its readiness.
+                socket.io_readiness.borrow_mut().readable = false;
+                this.update_fd_readiness(socket.clone(), ReadinessUpdateFlags::DEFAULT)?;
+                interp_ok(Err(IoError::HostError(e)))
+            }
+            result => interp_ok(result.map(|bytes_read| (bytes_read, source.map(Into::into)))),
+        }
+    }
+
+    /// Read an integer socket option value.
+    fn read_int_sockopt(
+        &self,
+        value_ptr: Pointer,
+        value_len: u64,
+    ) -> InterpResult<'tcx, Result<i32, IoError>> {
+        let this = self.eval_context_ref();
+
+        if value_len != 4 {
+            // Option value should be C-int which is usually 4 bytes.
+            return interp_ok(Err(LibcError("EINVAL")));
+        }
+        let option_value = this.ptr_to_mplace(value_ptr, this.machine.layouts.i32);
+        interp_ok(Ok(this.read_scalar(&option_value)?.to_i32()?))
+    }
+
+    /// Read the value of the `IP_MULTICAST_TTL` or `IP_MULTICAST_LOOP` socket option.
+    /// Just like the kernel, we accept both a single byte and a C-int.
+    fn read_multicast_sockopt(
+        &self,
+        value_ptr: Pointer,
+        value_len: u64,
+    ) -> InterpResult<'tcx, Result<u32, IoError>> {
+        let this = self.eval_context_ref();
+
+        let value = match value_len {
+            1 => {
+                let option_value = this.ptr_to_mplace(value_ptr, this.machine.layouts.u8);
+                u32::from(this.read_scalar(&option_value)?.to_u8()?)
+            }
+            4 => {
+                let option_value = this.ptr_to_mplace(value_ptr, this.machine.layouts.i32);
+                let Ok(value) = u32::try_from(this.read_scalar(&option_value)?.to_i32()?) else {
+                    return interp_ok(Err(LibcError("EINVAL")));
+                };
+                value
+            }
+            _ => return interp_ok(Err(LibcError("EINVAL"))),
+        };
+        interp_ok(Ok(value))
+    }
+
+    /// Allocate a buffer holding the value of the `IP_MULTICAST_TTL` or `IP_MULTICAST_LOOP`
+    /// socket option for `getsockopt`. Some targets use a single byte for these options.
+    fn multicast_sockopt_value(&mut self, value: u32) -> InterpResult<'tcx, MPlaceTy<'tcx>> {
+        let this = self.eval_context_mut();
+
+        let layout = if matches!(
+            this.tcx.sess.target.os,
+            Os::FreeBsd | Os::NetBsd | Os::Solaris | Os::Illumos
+        ) {
+            this.machine.layouts.u8
+        } else {
+            this.machine.layouts.i32
+        };
+        // Allocate new buffer on the stack with the chosen layout.
+        let value_buffer = this.allocate(layout, MemoryKind::Stack)?;
+        this.write_int(value, &value_buffer)?;
+        interp_ok(value_buffer)
+    }
+
+    /// Read an `ip_mreq` struct and return the multicast group address
+    /// and the address of the local interface.
+    fn read_ip_mreq(
+        &self,
+        value_ptr: Pointer,
+        value_len: u64,
+    ) -> InterpResult<'tcx, Result<(Ipv4Addr, Ipv4Addr), IoError>> {
+        let this = self.eval_context_ref();
+
+        let ip_mreq_layout = this.libc_ty_layout("ip_mreq");
+        if value_len < ip_mreq_layout.size.bytes() {
+            return interp_ok(Err(LibcError("EINVAL")));
+        }
+        let ip_mreq = this.ptr_to_mplace(value_ptr, ip_mreq_layout);
+
+        let mut addresses = [Ipv4Addr::UNSPECIFIED; 2];
+        for (address, field) in addresses.iter_mut().zip(["imr_multiaddr", "imr_interface"]) {
+            let in_addr = this.project_field_named(&ip_mreq, field)?;
+            let s_addr = this.project_field_named(&in_addr, "s_addr")?;
+            // Read bytes and treat them as big endian since address is stored in network byte order.
+            let addr_bytes: [u8; 4] = this
+                .read_bytes_ptr_strip_provenance(s_addr.ptr(), Size::from_bytes(4))?
+                .try_into()
+                .unwrap();
+            *address = Ipv4Addr::from(addr_bytes);
+        }
+
+        interp_ok(Ok((addresses[0], addresses[1])))
+    }
+
+    /// Read an `ipv6_mreq` struct and return the multicast group address
+    /// and the index of the local interface.
+    fn read_ipv6_mreq(
+        &self,
+        value_ptr: Pointer,
+        value_len: u64,
+    ) -> InterpResult<'tcx, Result<(Ipv6Addr, u32), IoError>> {
+        let this = self.eval_context_ref();
+
+        let ipv6_mreq_layout = this.libc_ty_layout("ipv6_mreq");
+        if value_len < ipv6_mreq_layout.size.bytes() {
+            return interp_ok(Err(LibcError("EINVAL")));
+        }
+        let ipv6_mreq = this.ptr_to_mplace(value_ptr, ipv6_mreq_layout);
+
+        let in6_addr = this.project_field_named(&ipv6_mreq, "ipv6mr_multiaddr")?;
+        let s6_addr = this.project_field_named(&in6_addr, "s6_addr")?;
+        // Read bytes and treat them as big endian since address is stored in network byte order.
+        let addr_bytes: [u8; 16] = this
+            .read_bytes_ptr_strip_provenance(s6_addr.ptr(), Size::from_bytes(16))?
+            .try_into()
+            .unwrap();
+
+        // The type of the interface index differs between targets.
+        let interface_field = this.project_field_named(&ipv6_mreq, "ipv6mr_interface")?;
+        let interface = this.read_scalar(&interface_field)?.to_int(interface_field.layout.size)?;
+        let Ok(interface) = u32::try_from(interface) else {
+            return interp_ok(Err(LibcError("EINVAL")));
+        };
+
+        interp_ok(Ok((Ipv6Addr::from(addr_bytes), interface)))
+    }
+
+    /// The options for joining and leaving an IPv6 multicast group. Linux calls them
+    /// `IPV6_ADD_MEMBERSHIP` and `IPV6_DROP_MEMBERSHIP`; everyone else uses the RFC 3493 names.
+    fn ipv6_membership_options(&self) -> (i32, i32) {
+        let this = self.eval_context_ref();
+
+        if matches!(this.tcx.sess.target.os, Os::Linux | Os::Android) {
+            (this.eval_libc_i32("IPV6_ADD_MEMBERSHIP"), this.eval_libc_i32("IPV6_DROP_MEMBERSHIP"))
+        } else {
+            (this.eval_libc_i32("IPV6_JOIN_GROUP"), this.eval_libc_i32("IPV6_LEAVE_GROUP"))
+        }
+    }
+}
+
+impl SourceFileDescription for UdpSocket {
+    fn with_source(&self, f: &mut dyn FnMut(&mut dyn Source) -> io::Result<()>) -> io::Result<()> {
+        let mut host_socket = self.host_socket.borrow_mut();
+        // We never try adding a socket which is not backed by a real socket to the poll registry.
+        f(host_socket.as_mut().unwrap())
+    }
+
+    fn get_readiness_mut(&self) -> RefMut<'_, Readiness> {
+        self.io_readiness.borrow_mut()
+    }
+}
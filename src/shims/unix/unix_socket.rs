@@ -0,0 +1,635 @@
+//! This implements Unix domain sockets created with `socket`, which can be bound to a name other
+//! sockets use to reach them. They are entirely implemented inside Miri, so they also work with
+//! isolation enabled: names live in a namespace that only exists inside the interpreted program,
+//! and no socket file is ever created on the host. Connecting a stream socket turns it into one
+//! end of a virtual socketpair, whose other end is handed out by `accept`.
+
+use std::cell::{Cell, OnceCell, RefCell};
+use std::collections::VecDeque;
+use std::io::{self, ErrorKind};
+use std::rc::Rc;
+
+use rustc_abi::Size;
+use rustc_data_structures::fx::FxHashMap;
+use rustc_target::spec::Os;
+
+use crate::concurrency::VClock;
+use crate::shims::files::{FdNum, FileDescription, FileDescriptionRef, WeakFileDescriptionRef};
+use crate::shims::unix::UnixFileDescription;
+use crate::shims::unix::socket::{EvalContextExt as _, UnixSocketFileDescription};
+use crate::shims::unix::socket_address::{SocketAddress, UnixSocketAddress};
+use crate::shims::unix::virtual_socket::{Delivery, EvalContextExt as _, Listener};
+use crate::*;
+
+/// The names Unix domain sockets are bound to.
+#[derive(Debug, Default)]
+pub struct UnixSocketNamespace {
+    /// The sockets bound to each name. The references are weak since closing a socket
+    /// frees its name.
+    names: FxHashMap<UnixSocketAddress, WeakFileDescriptionRef<UnixSocket>>,
+}
+
+impl UnixSocketNamespace {
+    /// The socket which is bound to `address`, if it is still open.
+    fn lookup(&self, address: &UnixSocketAddress) -> Option<FileDescriptionRef<UnixSocket>> {
+        self.names.get(address).and_then(WeakFileDescriptionRef::upgrade)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum UnixSocketType {
+    Stream,
+    Datagram,
+}
+
+/// A Unix domain socket which isn't connected to a stream socket (yet).
+#[derive(Debug)]
+pub struct UnixSocket {
+    socket_type: UnixSocketType,
+    /// The name the socket is bound to.
+    address: RefCell<UnixSocketAddress>,
+    /// The connections waiting to be accepted. This is unset as long as the socket isn't
+    /// listening.
+    listener: OnceCell<Rc<Listener>>,
+    /// Datagrams which were sent to this socket but not received yet.
+    datagrams: RefCell<VecDeque<Datagram>>,
+    /// The address and the socket that datagrams are sent to when no address is given.
+    peer: RefCell<Option<(UnixSocketAddress, WeakFileDescriptionRef<UnixSocket>)>>,
+    /// A list of thread ids blocked while receiving a datagram.
+    blocked_tids: RefCell<Vec<ThreadId>>,
+    /// Whether this fd is non-blocking or not.
+    is_nonblock: Cell<bool>,
+    /// State for being watched by epoll.
+    watched: ReadinessWatched,
+}
+
+#[derive(Debug)]
+struct Datagram {
+    data: Vec<u8>,
+    /// The address of the socket which sent the datagram.
+    source: UnixSocketAddress,
+    /// The clock of the sending thread.
+    clock: VClock,
+}
+
+impl UnixSocket {
+    fn new(socket_type: UnixSocketType, is_nonblock: bool) -> Self {
+        UnixSocket {
+            socket_type,
+            address: RefCell::new(UnixSocketAddress::Unnamed),
+            listener: OnceCell::new(),
+            datagrams: RefCell::new(VecDeque::new()),
+            peer: RefCell::new(None),
+            blocked_tids: RefCell::new(Vec::new()),
+            is_nonblock: Cell::new(is_nonblock),
+            watched: ReadinessWatched::default(),
+        }
+    }
+}
+
+impl FileDescription for UnixSocket {
+    fn name(&self) -> &'static str {
+        "socket"
+    }
+
+    fn metadata<'tcx>(
+        &self,
+    ) -> InterpResult<'tcx, Either<io::Result<shims::HandleMetadata>, &'static str>> {
+        interp_ok(Either::Right("S_IFSOCK"))
+    }
+
+    fn read<'tcx>(
+        self: FileDescriptionRef<Self>,
+        communicate_allowed: bool,
+        ptr: Pointer,
+        len: usize,
+        ecx: &mut MiriInterpCx<'tcx>,
+        finish: DynMachineCallback<'tcx, Result<usize, IoError>>,
+    ) -> InterpResult<'tcx> {
+        self.recv(
+            communicate_allowed,
+            ptr,
+            len,
+            /* is_peek */ false,
+            /* is_non_block */ false,
+            ecx,
+            finish,
+        )
+    }
+
+    fn write<'tcx>(
+        self: FileDescriptionRef<Self>,
+        communicate_allowed: bool,
+        ptr: Pointer,
+        len: usize,
+        ecx: &mut MiriInterpCx<'tcx>,
+        finish: DynMachineCallback<'tcx, Result<usize, IoError>>,
+    ) -> InterpResult<'tcx> {
+        self.send(communicate_allowed, ptr, len, /* is_non_block */ false, ecx, finish)
+    }
+
+    fn short_fd_operations(&self) -> bool {
+        // Every read or write transfers exactly one datagram; shortening
+        // it would truncate the datagram instead.
+        false
+    }
+
+    fn as_unix<'tcx>(
+        self: FileDescriptionRef<Self>,
+        _ecx: &MiriInterpCx<'tcx>,
+    ) -> FileDescriptionRef<dyn UnixFileDescription> {
+        self
+    }
+
+    fn get_flags<'tcx>(&self, ecx: &mut MiriInterpCx<'tcx>) -> InterpResult<'tcx, Scalar> {
+        let mut flags = ecx.eval_libc_i32("O_RDWR");
+
+        if self.is_nonblock.get() {
+            flags |= ecx.eval_libc_i32("O_NONBLOCK");
+        }
+
+        interp_ok(Scalar::from_i32(flags))
+    }
+
+    fn set_flags<'tcx>(
+        &self,
+        mut flag: i32,
+        ecx: &mut MiriInterpCx<'tcx>,
+    ) -> InterpResult<'tcx, Scalar> {
+        let o_nonblock = ecx.eval_libc_i32("O_NONBLOCK");
+
+        // O_NONBLOCK flag can be set / unset by user.
+        if flag & o_nonblock == o_nonblock {
+            self.is_nonblock.set(true);
+            flag &= !o_nonblock;
+        } else {
+            self.is_nonblock.set(false);
+        }
+
+        // Throw error if there is any unsupported flag.
+        if flag != 0 {
+            throw_unsup_format!("fcntl: only O_NONBLOCK is supported for sockets")
+        }
+
+        interp_ok(Scalar::from_i32(0))
+    }
+
+    fn readiness_watched(&self) -> Option<&ReadinessWatched> {
+        Some(&self.watched)
+    }
+
+    fn readiness(&self) -> Readiness {
+        let mut readiness = Readiness::EMPTY;
+        match self.socket_type {
+            UnixSocketType::Stream => {
+                // A listening socket is readable when there is a connection to accept.
+                // Unconnected stream sockets can neither be read nor written.
+                if let Some(listener) = self.listener.get() {
+                    readiness.readable = listener.has_pending_connection();
+                }
+            }
+            UnixSocketType::Datagram => {
+                readiness.readable = !self.datagrams.borrow().is_empty();
+                // The datagram queue is unbounded, so sending never blocks.
+                readiness.writable = true;
+            }
+        }
+        readiness
+    }
+}
+
+impl UnixFileDescription for UnixSocket {
+    fn ioctl<'tcx>(
+        &self,
+        op: Scalar,
+        arg: Option<&OpTy<'tcx>>,
+        ecx: &mut MiriInterpCx<'tcx>,
+    ) -> InterpResult<'tcx, i32> {
+        ecx.socket_ioctl(op, arg, &self.is_nonblock)
+    }
+
+    fn as_socket<'tcx>(
+        self: FileDescriptionRef<Self>,
+        _ecx: &MiriInterpCx<'tcx>,
+    ) -> Option<FileDescriptionRef<dyn UnixSocketFileDescription>> {
+        Some(self)
+    }
+}
+
+impl UnixSocketFileDescription for UnixSocket {
+    fn bind<'tcx>(
+        self: FileDescriptionRef<Self>,
+        _communicate_allowed: bool,
+        address: SocketAddress,
+        ecx: &mut MiriInterpCx<'tcx>,
+    ) -> InterpResult<'tcx, Result<(), IoError>> {
+        let SocketAddress::Unix(address) = address else {
+            return interp_ok(Err(ecx.bind_family_mismatch_error()));
+        };
+
+        if *self.address.borrow() != UnixSocketAddress::Unnamed {
+            // The socket is already bound.
+            return interp_ok(Err(LibcError("EINVAL")));
+        }
+        if address == UnixSocketAddress::Unnamed {
+            // On Linux, this would bind the socket to a random name in the abstract namespace.
+            throw_unsup_format!("bind: binding a unix socket to an empty address is unsupported");
+        }
+
+        let names = &mut ecx.machine.unix_socket_names;
+        if names.lookup(&address).is_some() {
+            return interp_ok(Err(LibcError("EADDRINUSE")));
+        }
+        names.names.insert(address.clone(), FileDescriptionRef::downgrade(&self));
+        *self.address.borrow_mut() = address;
+
+        interp_ok(Ok(()))
+    }
+
+    fn listen<'tcx>(
+        self: FileDescriptionRef<Self>,
+        _communicate_allowed: bool,
+        // We never reject connections, so the backlog size doesn't matter.
+        _backlog: i32,
+        _ecx: &mut MiriInterpCx<'tcx>,
+    ) -> InterpResult<'tcx, Result<(), IoError>> {
+        if self.socket_type == UnixSocketType::Datagram {
+            return interp_ok(Err(LibcError("EOPNOTSUPP")));
+        }
+        if *self.address.borrow() == UnixSocketAddress::Unnamed {
+            return interp_ok(Err(LibcError("EINVAL")));
+        }
+
+        self.listener.get_or_init(Default::default);
+
+        interp_ok(Ok(()))
+    }
+
+    fn accept<'tcx>(
+        self: FileDescriptionRef<Self>,
+        _communicate_allowed: bool,
+        is_client_sock_non_block: bool,
+        ecx: &mut MiriInterpCx<'tcx>,
+        finish: DynMachineCallback<'tcx, Result<(FdNum, SocketAddress), IoError>>,
+    ) -> InterpResult<'tcx> {
+        if self.socket_type == UnixSocketType::Datagram {
+            return finish.call(ecx, Err(LibcError("EOPNOTSUPP")));
+        }
+        let Some(listener) = self.listener.get().cloned() else {
+            return finish.call(ecx, Err(LibcError("EINVAL")));
+        };
+
+        let is_nonblock = self.is_nonblock.get();
+        ecx.accept_connection(self, listener, is_nonblock, is_client_sock_non_block, finish)
+    }
+
+    fn connect<'tcx>(
+        self: FileDescriptionRef<Self>,
+        _communicate_allowed: bool,
+        address: SocketAddress,
+        ecx: &mut MiriInterpCx<'tcx>,
+        finish: DynMachineCallback<'tcx, Result<(), IoError>>,
+    ) -> InterpResult<'tcx> {
+        let SocketAddress::Unix(address) = address else {
+            return finish.call(ecx, Err(LibcError("EAFNOSUPPORT")));
+        };
+        let target = match ecx.lookup_unix_socket(&address) {
+            Ok(target) => target,
+            Err(e) => return finish.call(ecx, Err(e)),
+        };
+        if target.socket_type != self.socket_type {
+            return finish.call(ecx, Err(LibcError("EPROTOTYPE")));
+        }
+
+        match self.socket_type {
+            UnixSocketType::Datagram => {
+                // Connecting a datagram socket only sets its default peer.
+                *self.peer.borrow_mut() = Some((address, FileDescriptionRef::downgrade(&target)));
+                finish.call(ecx, Ok(()))
+            }
+            UnixSocketType::Stream => {
+                if self.listener.get().is_some() {
+                    // The socket is listening.
+                    return finish.call(ecx, Err(LibcError("EINVAL")));
+                }
+                let Some(listener) = target.listener.get().cloned() else {
+                    // The socket exists but doesn't accept connections.
+                    return finish.call(ecx, Err(LibcError("ECONNREFUSED")));
+                };
+                if self.watched.is_watched() {
+                    throw_unsup_format!(
+                        "connect: connecting a unix socket that is watched by epoll or poll is unsupported"
+                    );
+                }
+
+                let (client, server) = ecx.new_stream_socket_pair(
+                    SocketAddress::Unix(self.address.borrow().clone()),
+                    SocketAddress::Unix(address),
+                    self.is_nonblock.get(),
+                    Delivery::Immediate,
+                );
+                // From now on, every file descriptor of this socket refers to the connected socket.
+                ecx.machine.fds.replace_description(self.id(), client);
+
+                ecx.queue_connection(target, &listener, server)?;
+                finish.call(ecx, Ok(()))
+            }
+        }
+    }
+
+    fn recv<'tcx>(
+        self: FileDescriptionRef<Self>,
+        communicate_allowed: bool,
+        ptr: Pointer,
+        len: usize,
+        is_peek: bool,
+        is_non_block: bool,
+        ecx: &mut MiriInterpCx<'tcx>,
+        finish: DynMachineCallback<'tcx, Result<usize, IoError>>,
+    ) -> InterpResult<'tcx> {
+        self.recv_from(
+            communicate_allowed,
+            ptr,
+            len,
+            is_peek,
+            is_non_block,
+            ecx,
+            callback!(
+                @capture<'tcx> {
+                    finish: DynMachineCallback<'tcx, Result<usize, IoError>>,
+                } |this, result: Result<(usize, Option<SocketAddress>), IoError>| {
+                    finish.call(this, result.map(|(bytes_received, _)| bytes_received))
+                }
+            ),
+        )
+    }
+
+    fn send<'tcx>(
+        self: FileDescriptionRef<Self>,
+        _communicate_allowed: bool,
+        ptr: Pointer,
+        len: usize,
+        _is_non_block: bool,
+        ecx: &mut MiriInterpCx<'tcx>,
+        finish: DynMachineCallback<'tcx, Result<usize, IoError>>,
+    ) -> InterpResult<'tcx> {
+        if self.socket_type == UnixSocketType::Stream {
+            // Connected stream sockets are virtual sockets instead.
+            return finish.call(ecx, Err(LibcError("ENOTCONN")));
+        }
+        let peer = self.peer.borrow().as_ref().map(|(_, peer)| peer.upgrade());
+        let Some(peer) = peer else {
+            return finish.call(ecx, Err(LibcError("ENOTCONN")));
+        };
+        let Some(peer) = peer else {
+            // The peer was closed.
+            return finish.call(ecx, Err(LibcError("ECONNREFUSED")));
+        };
+
+        let result = ecx.send_unix_datagram(&self, peer, ptr, len)?;
+        finish.call(ecx, result)
+    }
+
+    fn recv_from<'tcx>(
+        self: FileDescriptionRef<Self>,
+        _communicate_allowed: bool,
+        ptr: Pointer,
+        len: usize,
+        is_peek: bool,
+        is_non_block: bool,
+        ecx: &mut MiriInterpCx<'tcx>,
+        finish: DynMachineCallback<'tcx, Result<(usize, Option<SocketAddress>), IoError>>,
+    ) -> InterpResult<'tcx> {
+        if self.socket_type == UnixSocketType::Stream {
+            // Connected stream sockets are virtual sockets instead.
+            return finish.call(ecx, Err(LibcError("EINVAL")));
+        }
+
+        ecx.recv_unix_datagram(self, ptr, len, is_peek, is_non_block, finish)
+    }
+
+    fn send_to<'tcx>(
+        self: FileDescriptionRef<Self>,
+        _communicate_allowed: bool,
+        ptr: Pointer,
+        len: usize,
+        address: SocketAddress,
+        _is_non_block: bool,
+        ecx: &mut MiriInterpCx<'tcx>,
+        finish: DynMachineCallback<'tcx, Result<usize, IoError>>,
+    ) -> InterpResult<'tcx> {
+        if self.socket_type == UnixSocketType::Stream {
+            return finish.call(ecx, Err(LibcError("EOPNOTSUPP")));
+        }
+        let SocketAddress::Unix(address) = address else {
+            return finish.call(ecx, Err(LibcError("EINVAL")));
+        };
+        let target = match ecx.lookup_unix_socket(&address) {
+            Ok(target) if target.socket_type == UnixSocketType::Datagram => target,
+            Ok(_) => return finish.call(ecx, Err(LibcError("EPROTOTYPE"))),
+            Err(e) => return finish.call(ecx, Err(e)),
+        };
+
+        let result = ecx.send_unix_datagram(&self, target, ptr, len)?;
+        finish.call(ecx, result)
+    }
+
+    fn setsockopt<'tcx>(
+        self: FileDescriptionRef<Self>,
+        level: i32,
+        option: i32,
+        value_ptr: Pointer,
+        value_len: u64,
+        ecx: &mut MiriInterpCx<'tcx>,
+    ) -> InterpResult<'tcx, Result<(), IoError>> {
+        // SO_NOSIGPIPE only exists on MacOS, FreeBSD, and NetBSD, where the standard library
+        // sets it on every new socket.
+        if matches!(ecx.tcx.sess.target.os, Os::MacOs | Os::FreeBsd | Os::NetBsd)
+            && level == ecx.eval_libc_i32("SOL_SOCKET")
+            && option == ecx.eval_libc_i32("SO_NOSIGPIPE")
+        {
+            if value_len != 4 {
+                // Option value should be C-int which is usually 4 bytes.
+                return interp_ok(Err(LibcError("EINVAL")));
+            }
+            let option_value = ecx.ptr_to_mplace(value_ptr, ecx.machine.layouts.i32);
+            let _val = ecx.read_scalar(&option_value)?.to_i32()?;
+            // Writing to a closed virtual socket never raises SIGPIPE, so we ignore this.
+            return interp_ok(Ok(()));
+        }
+
+        throw_unsup_format!(
+            "setsockopt: option {option:#x} on level {level:#x} is unsupported for unix sockets"
+        )
+    }
+
+    fn getsockname<'tcx>(
+        self: FileDescriptionRef<Self>,
+        _communicate_allowed: bool,
+        _ecx: &mut MiriInterpCx<'tcx>,
+    ) -> InterpResult<'tcx, Result<SocketAddress, IoError>> {
+        interp_ok(Ok(SocketAddress::Unix(self.address.borrow().clone())))
+    }
+
+    fn getpeername<'tcx>(
+        self: FileDescriptionRef<Self>,
+        _communicate_allowed: bool,
+        ecx: &mut MiriInterpCx<'tcx>,
+        finish: DynMachineCallback<'tcx, Result<SocketAddress, IoError>>,
+    ) -> InterpResult<'tcx> {
+        let result = match &*self.peer.borrow() {
+            Some((address, _)) => Ok(SocketAddress::Unix(address.clone())),
+            None => Err(LibcError("ENOTCONN")),
+        };
+        finish.call(ecx, result)
+    }
+}
+
+impl<'tcx> EvalContextPrivExt<'tcx> for crate::MiriInterpCx<'tcx> {}
+trait EvalContextPrivExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
+    /// Find the socket bound to `address`.
+    fn lookup_unix_socket(
+        &self,
+        address: &UnixSocketAddress,
+    ) -> Result<FileDescriptionRef<UnixSocket>, IoError> {
+        let this = self.eval_context_ref();
+
+        match this.machine.unix_socket_names.lookup(address) {
+            Some(socket) => Ok(socket),
+            // Since we don't create socket files, we can't tell a path which never existed
+            // apart from one whose socket was closed.
+            None if matches!(address, UnixSocketAddress::Pathname(_)) => Err(LibcError("ENOENT")),
+            None => Err(LibcError("ECONNREFUSED")),
+        }
+    }
+
+    /// Wake up all threads blocked on receiving a datagram on `socket`.
+    fn unblock_unix_socket_waiters(&mut self, socket: &UnixSocket) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+
+        let waiting_threads = std::mem::take(&mut *socket.blocked_tids.borrow_mut());
+        for thread_id in waiting_threads {
+            this.unblock_thread(thread_id, BlockReason::VirtualSocket)?;
+        }
+        interp_ok(())
+    }
+
+    /// Queue the `len` bytes at `ptr` as a datagram from `socket` on `target`.
+    fn send_unix_datagram(
+        &mut self,
+        socket: &UnixSocket,
+        target: FileDescriptionRef<UnixSocket>,
+        ptr: Pointer,
+        len: usize,
+    ) -> InterpResult<'tcx, Result<usize, IoError>> {
+        let this = self.eval_context_mut();
+
+        let data = this.read_bytes_ptr_strip_provenance(ptr, Size::from_bytes(len))?.to_vec();
+        // Whoever receives the datagram synchronizes with us.
+        let clock = this.release_clock(|clock| clock.clone())?.unwrap_or_default();
+        target.datagrams.borrow_mut().push_back(Datagram {
+            data,
+            source: socket.address.borrow().clone(),
+            clock,
+        });
+
+        this.unblock_unix_socket_waiters(&target)?;
+        this.update_fd_readiness(target, ReadinessUpdateFlags::FORCE_EDGE)?;
+        interp_ok(Ok(len))
+    }
+
+    /// Receive a datagram on `socket` into the buffer of `len` bytes at `ptr`, blocking until
+    /// there is one unless the socket or the operation is non-blocking. If the buffer is too
+    /// small, the rest of the datagram is discarded.
+    fn recv_unix_datagram(
+        &mut self,
+        socket: FileDescriptionRef<UnixSocket>,
+        ptr: Pointer,
+        len: usize,
+        is_peek: bool,
+        is_non_block: bool,
+        finish: DynMachineCallback<'tcx, Result<(usize, Option<SocketAddress>), IoError>>,
+    ) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+
+        let mut datagrams = socket.datagrams.borrow_mut();
+        let Some(datagram) = datagrams.front() else {
+            drop(datagrams);
+            if socket.is_nonblock.get() || is_non_block {
+                return finish.call(this, Err(ErrorKind::WouldBlock.into()));
+            }
+            socket.blocked_tids.borrow_mut().push(this.active_thread());
+            this.block_thread(
+                BlockReason::VirtualSocket,
+                None,
+                callback!(
+                    @capture<'tcx> {
+                        socket: FileDescriptionRef<UnixSocket>,
+                        ptr: Pointer,
+                        len: usize,
+                        is_peek: bool,
+                        is_non_block: bool,
+                        finish: DynMachineCallback<'tcx, Result<(usize, Option<SocketAddress>), IoError>>,
+                    }
+                    |this, unblock: UnblockKind| {
+                        assert_eq!(unblock, UnblockKind::Ready);
+                        this.recv_unix_datagram(socket, ptr, len, is_peek, is_non_block, finish)
+                    }
+                ),
+            );
+            return interp_ok(());
+        };
+
+        this.acquire_clock(&datagram.clock)?;
+        let bytes_received = len.min(datagram.data.len());
+        this.write_bytes_ptr(ptr, datagram.data[..bytes_received].iter().copied())?;
+        let source = SocketAddress::Unix(datagram.source.clone());
+        if !is_peek {
+            datagrams.pop_front();
+        }
+        drop(datagrams);
+        // We might be no longer readable.
+        this.update_fd_readiness(socket, ReadinessUpdateFlags::DEFAULT)?;
+
+        finish.call(this, Ok((bytes_received, Some(source))))
+    }
+}
+
+impl<'tcx> EvalContextExt<'tcx> for crate::MiriInterpCx<'tcx> {}
+pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
+    /// Create a new unix socket of type `socket_type` and insert it into the file
+    /// descriptor table. Returns [`None`] if the type isn't supported.
+    fn new_unix_socket(&mut self, socket_type: i32, is_nonblock: bool) -> Option<FdNum> {
+        let this = self.eval_context_mut();
+
+        let socket_type = if socket_type == this.eval_libc_i32("SOCK_STREAM") {
+            UnixSocketType::Stream
+        } else if socket_type == this.eval_libc_i32("SOCK_DGRAM") {
+            UnixSocketType::Datagram
+        } else {
+            return None;
+        };
+        Some(this.machine.fds.insert_new(UnixSocket::new(socket_type, is_nonblock)))
+    }
+
+    /// Create a pair of unnamed datagram sockets which are connected to each other,
+    /// and which are not yet in the file descriptor table.
+    fn new_datagram_socket_pair(
+        &mut self,
+        is_nonblock: bool,
+    ) -> (FileDescriptionRef<UnixSocket>, FileDescriptionRef<UnixSocket>) {
+        let this = self.eval_context_mut();
+
+        let fds = &mut this.machine.fds;
+        let fd0 = fds.new_ref(UnixSocket::new(UnixSocketType::Datagram, is_nonblock));
+        let fd1 = fds.new_ref(UnixSocket::new(UnixSocketType::Datagram, is_nonblock));
+
+        // Make the sockets each other's peers.
+        *fd0.peer.borrow_mut() =
+            Some((UnixSocketAddress::Unnamed, FileDescriptionRef::downgrade(&fd1)));
+        *fd1.peer.borrow_mut() =
+            Some((UnixSocketAddress::Unnamed, FileDescriptionRef::downgrade(&fd0)));
+
+        (fd0, fd1)
+    }
+}
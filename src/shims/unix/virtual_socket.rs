@@ -1,21 +1,30 @@
 //! This implements "virtual" sockets, that do not correspond to anything on the host system and
 //! are entirely implemented inside Miri.
-//! This is used to implement `socketpair` and `pipe`.
+//! This is used to implement `socketpair`, `pipe`, connected Unix domain stream sockets, and TCP
+//! connections on the in-memory loopback network.
 
 use std::cell::{Cell, OnceCell, RefCell};
 use std::collections::VecDeque;
 use std::io::{self, ErrorKind, Read};
+use std::net::Shutdown;
 use std::rc::Rc;
 
+use rand::RngExt;
 use rustc_target::spec::Os;
 
 use crate::concurrency::VClock;
 use crate::shims::files::{
-    EvalContextExt as _, FileDescription, FileDescriptionRef, WeakFileDescriptionRef,
+    DynFileDescriptionRef, EvalContextExt as _, FdNum, FileDescription, FileDescriptionRef,
+    WeakFileDescriptionRef,
 };
 use crate::shims::readiness::DelayedReadinessUpdates;
 use crate::shims::unix::UnixFileDescription;
+#[cfg(unix)]
+use crate::shims::unix::host_pipe::{EvalContextExt as _, HostPipe};
+use crate::shims::unix::loopback_socket::EvalContextExt as _;
 use crate::shims::unix::socket::UnixSocketFileDescription;
+use crate::shims::unix::socket_address::{SocketAddress, UnixSocketAddress};
+use crate::shims::unix::unix_socket::EvalContextExt as _;
 use crate::*;
 
 /// The maximum capacity of the socketpair buffer in bytes.
@@ -33,9 +42,20 @@ enum VirtualSocketType {
     PipeWrite,
 }
 
+/// How the data written to a pair of stream sockets reaches the other end.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Delivery {
+    /// Both buffers hold [`MAX_SOCKETPAIR_BUFFER_CAPACITY`] bytes, and every read returns as much
+    /// of the buffered data as fits.
+    Immediate,
+    /// The buffer sizes are chosen randomly, and so is the amount of buffered data each read
+    /// returns, to mimic a TCP connection whose data arrives in segments.
+    Chunked,
+}
+
 /// One end of a pair of connected virtual sockets.
 #[derive(Debug)]
-struct VirtualSocket {
+pub struct VirtualSocket {
     /// The buffer we are reading from, or `None` if this is the writing end of a pipe.
     /// (In that case, the peer FD will be the reading end of that pipe.)
     readbuf: Option<RefCell<Buffer>>,
@@ -53,10 +73,23 @@ struct VirtualSocket {
     /// A list of thread ids blocked because the buffer was full.
     /// Once another thread reads some bytes, these threads will be unblocked.
     blocked_write_tid: RefCell<Vec<ThreadId>>,
+    /// The maximum amount of bytes `readbuf` can hold.
+    capacity: usize,
+    /// How the data in `readbuf` is handed out to reads.
+    delivery: Delivery,
+    /// Whether the reading direction was shut down with `shutdown`.
+    read_shutdown: Cell<bool>,
+    /// Whether the writing direction was shut down with `shutdown`.
+    write_shutdown: Cell<bool>,
     /// Whether this fd is non-blocking or not.
     is_nonblock: Cell<bool>,
     /// Differentiate between different virtual socket fd types.
     fd_type: VirtualSocketType,
+    /// The address of this end of a socketpair, which is only named if this is a
+    /// connected Unix domain socket or a loopback TCP connection.
+    address: SocketAddress,
+    /// The address of the peer, which stays the same even when the peer is closed.
+    peer_address: SocketAddress,
     /// We need to update the peer_fd readiness when we get dropped, so we keep a reference
     /// to the readiness update queue
     delayed_readiness_updates: Rc<DelayedReadinessUpdates>,
@@ -77,9 +110,60 @@ impl Buffer {
 }
 
 impl VirtualSocket {
+    fn new(
+        has_readbuf: bool,
+        is_nonblock: bool,
+        fd_type: VirtualSocketType,
+        address: SocketAddress,
+        peer_address: SocketAddress,
+        capacity: usize,
+        delivery: Delivery,
+        delayed_readiness_updates: &Rc<DelayedReadinessUpdates>,
+    ) -> Self {
+        VirtualSocket {
+            readbuf: has_readbuf.then(|| RefCell::new(Buffer::new())),
+            peer_fd: OnceCell::new(),
+            peer_lost_data: Cell::new(false),
+            blocked_read_tid: RefCell::new(Vec::new()),
+            blocked_write_tid: RefCell::new(Vec::new()),
+            capacity,
+            delivery,
+            read_shutdown: Cell::new(false),
+            write_shutdown: Cell::new(false),
+            is_nonblock: Cell::new(is_nonblock),
+            fd_type,
+            address,
+            peer_address,
+            delayed_readiness_updates: Rc::clone(delayed_readiness_updates),
+            watched: ReadinessWatched::default(),
+        }
+    }
+
     fn peer_fd(&self) -> &WeakFileDescriptionRef<VirtualSocket> {
         self.peer_fd.get().unwrap()
     }
+
+    fn set_nonblock(&self, is_nonblock: bool) {
+        self.is_nonblock.set(is_nonblock);
+    }
+
+    fn peer_address(&self) -> &SocketAddress {
+        &self.peer_address
+    }
+
+    /// Whether reading has reached the end of the stream once the buffer is empty: the peer
+    /// was closed or stopped writing, or we stopped reading.
+    fn read_closed(&self) -> bool {
+        self.read_shutdown.get()
+            || self.peer_fd().upgrade().is_none_or(|peer_fd| peer_fd.write_shutdown.get())
+    }
+
+    /// Whether writing fails with EPIPE: the peer was closed or stopped reading, or we
+    /// stopped writing.
+    fn write_closed(&self) -> bool {
+        self.write_shutdown.get()
+            || self.peer_fd().upgrade().is_none_or(|peer_fd| peer_fd.read_shutdown.get())
+    }
 }
 
 impl Drop for VirtualSocket {
@@ -93,6 +177,9 @@ impl Drop for VirtualSocket {
                 }
             }
             // Notify peer fd that close has happened, since that can unblock reads and writes.
+            let mut waiting_threads = peer_fd.blocked_read_tid.take();
+            waiting_threads.append(&mut peer_fd.blocked_write_tid.borrow_mut());
+            self.delayed_readiness_updates.add_unblock(waiting_threads, BlockReason::VirtualSocket);
             self.delayed_readiness_updates.add(peer_fd);
         }
     }
@@ -108,7 +195,7 @@ impl FileDescription for VirtualSocket {
 
     fn metadata<'tcx>(
         &self,
-    ) -> InterpResult<'tcx, Either<io::Result<std::fs::Metadata>, &'static str>> {
+    ) -> InterpResult<'tcx, Either<io::Result<shims::HandleMetadata>, &'static str>> {
         let mode_name = match self.fd_type {
             VirtualSocketType::Socketpair => "S_IFSOCK",
             VirtualSocketType::PipeRead | VirtualSocketType::PipeWrite => "S_IFIFO",
@@ -229,7 +316,7 @@ impl FileDescription for VirtualSocket {
         if let Some(peer_fd) = self.peer_fd().upgrade() {
             if let Some(writebuf) = &peer_fd.readbuf {
                 let data_size = writebuf.borrow().buf.len();
-                let available_space = MAX_SOCKETPAIR_BUFFER_CAPACITY.strict_sub(data_size);
+                let available_space = peer_fd.capacity.strict_sub(data_size);
                 if available_space != 0 {
                     readiness.writable = true;
                 }
@@ -237,20 +324,20 @@ impl FileDescription for VirtualSocket {
                 // Without a write buffer, writing never blocks.
                 readiness.writable = true;
             }
-        } else {
-            // Peer FD has been closed. This always sets both the "read closed" and "write closed" flags
-            // as we do not support `shutdown` that could be used to partially close the stream.
+        } else if self.peer_lost_data.get() {
+            // If there is data lost in peer_fd, set error readiness.
+            readiness.error = true;
+        }
+
+        // Once the peer is closed or a direction is shut down, reads return EOF and writes
+        // return EPIPE. In other words, they won't block, so we mark this as ready for them.
+        if self.read_closed() {
             readiness.read_closed = true;
-            readiness.write_closed = true;
-            // Since the peer is closed, even if no data is available reads will return EOF and
-            // writes will return EPIPE. In other words, they won't block, so we mark this as ready
-            // for read and write.
             readiness.readable = true;
+        }
+        if self.write_closed() {
+            readiness.write_closed = true;
             readiness.writable = true;
-            // If there is data lost in peer_fd, set error readiness.
-            if self.peer_lost_data.get() {
-                readiness.error = true;
-            }
         }
         readiness
     }
@@ -342,6 +429,105 @@ impl UnixSocketFileDescription for VirtualSocket {
 
         ecx.virtual_socket_read(self, ptr, len, is_non_block, finish)
     }
+
+    fn shutdown<'tcx>(
+        self: FileDescriptionRef<Self>,
+        _communicate_allowed: bool,
+        how: Shutdown,
+        ecx: &mut MiriInterpCx<'tcx>,
+    ) -> InterpResult<'tcx, Result<(), IoError>> {
+        if matches!(how, Shutdown::Read | Shutdown::Both) {
+            self.read_shutdown.set(true);
+        }
+        if matches!(how, Shutdown::Write | Shutdown::Both) {
+            self.write_shutdown.set(true);
+        }
+
+        // Blocked reads and writes on either end might now return EOF or EPIPE.
+        let peer_fd = self.peer_fd().upgrade();
+        for socket in std::iter::once(self).chain(peer_fd) {
+            let mut waiting_threads = std::mem::take(&mut *socket.blocked_read_tid.borrow_mut());
+            waiting_threads.append(&mut socket.blocked_write_tid.borrow_mut());
+            for thread_id in waiting_threads {
+                ecx.unblock_thread(thread_id, BlockReason::VirtualSocket)?;
+            }
+            ecx.update_fd_readiness(socket, ReadinessUpdateFlags::DEFAULT)?;
+        }
+        interp_ok(Ok(()))
+    }
+
+    fn setsockopt<'tcx>(
+        self: FileDescriptionRef<Self>,
+        level: i32,
+        option: i32,
+        value_ptr: Pointer,
+        value_len: u64,
+        ecx: &mut MiriInterpCx<'tcx>,
+    ) -> InterpResult<'tcx, Result<(), IoError>> {
+        if !matches!(self.address, SocketAddress::Inet(_)) {
+            throw_unsup_format!("setsockopt: socket options are unsupported for socketpairs");
+        }
+        ecx.loopback_setsockopt(level, option, value_ptr, value_len)
+    }
+
+    fn getsockopt<'tcx>(
+        self: FileDescriptionRef<Self>,
+        level: i32,
+        option: i32,
+        ecx: &mut MiriInterpCx<'tcx>,
+    ) -> InterpResult<'tcx, Result<MPlaceTy<'tcx>, IoError>> {
+        if level != ecx.eval_libc_i32("SOL_SOCKET") || option != ecx.eval_libc_i32("SO_ERROR") {
+            throw_unsup_format!(
+                "getsockopt: only SO_ERROR on level SOL_SOCKET is supported for socketpairs"
+            );
+        }
+
+        // Connecting a virtual socket happens instantly, so there never is a pending error.
+        let value_buffer = ecx.allocate(ecx.machine.layouts.i32, MemoryKind::Stack)?;
+        ecx.write_int(0, &value_buffer)?;
+        interp_ok(Ok(value_buffer))
+    }
+
+    fn getsockname<'tcx>(
+        self: FileDescriptionRef<Self>,
+        _communicate_allowed: bool,
+        _ecx: &mut MiriInterpCx<'tcx>,
+    ) -> InterpResult<'tcx, Result<SocketAddress, IoError>> {
+        interp_ok(Ok(self.address.clone()))
+    }
+
+    fn getpeername<'tcx>(
+        self: FileDescriptionRef<Self>,
+        _communicate_allowed: bool,
+        ecx: &mut MiriInterpCx<'tcx>,
+        finish: DynMachineCallback<'tcx, Result<SocketAddress, IoError>>,
+    ) -> InterpResult<'tcx> {
+        finish.call(ecx, Ok(self.peer_address.clone()))
+    }
+}
+
+/// The state of a listening stream socket: the connections waiting to be accepted, and the threads
+/// blocked in `accept`. Shared by all kinds of sockets whose connections are virtual sockets.
+#[derive(Debug, Default)]
+pub struct Listener {
+    /// Connections which are established but not accepted yet, together with the clock of
+    /// the thread that connected.
+    backlog: RefCell<VecDeque<(FileDescriptionRef<VirtualSocket>, VClock)>>,
+    /// A list of thread ids blocked in `accept`.
+    blocked_tids: RefCell<Vec<ThreadId>>,
+}
+
+impl Listener {
+    /// Whether there is a connection to accept.
+    pub fn has_pending_connection(&self) -> bool {
+        !self.backlog.borrow().is_empty()
+    }
+}
+
+impl VisitProvenance for Listener {
+    fn visit_provenance(&self, _visit: &mut VisitWith<'_>) {
+        // Neither the queued sockets nor the clocks contain provenance.
+    }
 }
 
 impl<'tcx> EvalContextPrivExt<'tcx> for crate::MiriInterpCx<'tcx> {}
@@ -373,6 +559,10 @@ trait EvalContextPrivExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
             // closed. It is an error to write even if there would be space.
             return finish.call(this, Err(ErrorKind::BrokenPipe.into()));
         };
+        if socket.write_closed() {
+            // One of the directions we would need was shut down.
+            return finish.call(this, Err(ErrorKind::BrokenPipe.into()));
+        }
 
         let Some(writebuf) = &peer_fd.readbuf else {
             // Writing to the read end of a pipe.
@@ -380,8 +570,7 @@ trait EvalContextPrivExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         };
 
         // Let's see if we can write.
-        let available_space =
-            MAX_SOCKETPAIR_BUFFER_CAPACITY.strict_sub(writebuf.borrow().buf.len());
+        let available_space = peer_fd.capacity.strict_sub(writebuf.borrow().buf.len());
         if available_space == 0 {
             if socket.is_nonblock.get() || is_non_block {
                 // Non-blocking socketpair with a full buffer.
@@ -466,8 +655,8 @@ trait EvalContextPrivExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         };
 
         if readbuf.borrow_mut().buf.is_empty() {
-            if socket.peer_fd().upgrade().is_none() {
-                // Socketpair with no peer and empty buffer.
+            if socket.read_closed() {
+                // Socketpair with no peer (or no more data coming) and empty buffer.
                 // 0 bytes successfully read indicates end-of-file.
                 return finish.call(this, Ok(0));
             } else if socket.is_nonblock.get() || is_non_block {
@@ -505,6 +694,14 @@ trait EvalContextPrivExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
             // only sync with the writes whose data we will read.
             this.acquire_clock(&readbuf.clock)?;
 
+            // With chunked delivery, only a random prefix of the buffered data has "arrived".
+            let len = match socket.delivery {
+                Delivery::Immediate => len,
+                Delivery::Chunked => {
+                    let available = len.min(readbuf.buf.len());
+                    this.machine.rng.get_mut().random_range(1..=available)
+                }
+            };
             // Do full read / partial read based on the space available.
             // Conveniently, `read` exists on `VecDeque` and has exactly the desired behavior.
             let read_size = this.read_from_host(|buf| readbuf.buf.read(buf), len, ptr)?.unwrap();
@@ -541,7 +738,14 @@ trait EvalContextPrivExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
                 )?;
             };
             // Notify readiness watchers: we might be no longer readable.
-            this.update_fd_readiness(socket, ReadinessUpdateFlags::DEFAULT)?;
+            // A short read promises that the buffer is empty (see `short_fd_operations`), so if
+            // chunked delivery left data behind, that data counts as newly arrived.
+            let flags = if socket.delivery == Delivery::Chunked && !readbuf_now_empty {
+                ReadinessUpdateFlags::FORCE_EDGE
+            } else {
+                ReadinessUpdateFlags::DEFAULT
+            };
+            this.update_fd_readiness(socket, flags)?;
 
             return finish.call(this, Ok(read_size));
         }
@@ -598,12 +802,6 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
                                  and AF_LOCAL are allowed",
                 domain
             );
-        } else if flags != this.eval_libc_i32("SOCK_STREAM") {
-            throw_unsup_format!(
-                "socketpair: type {:#x} is unsupported, only SOCK_STREAM, \
-                                 SOCK_CLOEXEC and SOCK_NONBLOCK are allowed",
-                flags
-            );
         } else if protocol != 0 {
             throw_unsup_format!(
                 "socketpair: socket protocol {protocol} is unsupported, \
@@ -612,37 +810,29 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         }
 
         // Generate file descriptions.
-        let fds = &mut this.machine.fds;
-        let fd0 = fds.new_ref(VirtualSocket {
-            readbuf: Some(RefCell::new(Buffer::new())),
-            peer_fd: OnceCell::new(),
-            peer_lost_data: Cell::new(false),
-            blocked_read_tid: RefCell::new(Vec::new()),
-            blocked_write_tid: RefCell::new(Vec::new()),
-            is_nonblock: Cell::new(is_sock_nonblock),
-            fd_type: VirtualSocketType::Socketpair,
-            delayed_readiness_updates: Rc::clone(&this.machine.delayed_readiness_updates),
-            watched: ReadinessWatched::default(),
-        });
-        let fd1 = fds.new_ref(VirtualSocket {
-            readbuf: Some(RefCell::new(Buffer::new())),
-            peer_fd: OnceCell::new(),
-            peer_lost_data: Cell::new(false),
-            blocked_read_tid: RefCell::new(Vec::new()),
-            blocked_write_tid: RefCell::new(Vec::new()),
-            is_nonblock: Cell::new(is_sock_nonblock),
-            fd_type: VirtualSocketType::Socketpair,
-            delayed_readiness_updates: Rc::clone(&this.machine.delayed_readiness_updates),
-            watched: ReadinessWatched::default(),
-        });
-
-        // Make the file descriptions point to each other.
-        fd0.peer_fd.set(FileDescriptionRef::downgrade(&fd1)).unwrap();
-        fd1.peer_fd.set(FileDescriptionRef::downgrade(&fd0)).unwrap();
+        let (fd0, fd1): (DynFileDescriptionRef, DynFileDescriptionRef) =
+            if flags == this.eval_libc_i32("SOCK_STREAM") {
+                let (fd0, fd1) = this.new_stream_socket_pair(
+                    SocketAddress::Unix(UnixSocketAddress::Unnamed),
+                    SocketAddress::Unix(UnixSocketAddress::Unnamed),
+                    is_sock_nonblock,
+                    Delivery::Immediate,
+                );
+                (fd0, fd1)
+            } else if flags == this.eval_libc_i32("SOCK_DGRAM") {
+                let (fd0, fd1) = this.new_datagram_socket_pair(is_sock_nonblock);
+                (fd0, fd1)
+            } else {
+                throw_unsup_format!(
+                    "socketpair: type {:#x} is unsupported, only SOCK_STREAM, SOCK_DGRAM, \
+                                 SOCK_CLOEXEC and SOCK_NONBLOCK are allowed",
+                    flags
+                );
+            };
 
         // Insert the file description to the fd table, generating the file descriptors.
-        let sv0 = fds.insert(fd0);
-        let sv1 = fds.insert(fd1);
+        let sv0 = this.machine.fds.insert(fd0);
+        let sv1 = this.machine.fds.insert(fd1);
 
         // Return socketpair file descriptors to the caller.
         let sv0 = Scalar::from_int(sv0, sv.layout.size);
@@ -687,28 +877,26 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         // Generate file descriptions.
         // pipefd[0] refers to the read end of the pipe.
         let fds = &mut this.machine.fds;
-        let fd0 = fds.new_ref(VirtualSocket {
-            readbuf: Some(RefCell::new(Buffer::new())),
-            peer_fd: OnceCell::new(),
-            peer_lost_data: Cell::new(false),
-            blocked_read_tid: RefCell::new(Vec::new()),
-            blocked_write_tid: RefCell::new(Vec::new()),
-            is_nonblock: Cell::new(is_nonblock),
-            fd_type: VirtualSocketType::PipeRead,
-            delayed_readiness_updates: Rc::clone(&this.machine.delayed_readiness_updates),
-            watched: ReadinessWatched::default(),
-        });
-        let fd1 = fds.new_ref(VirtualSocket {
-            readbuf: None,
-            peer_fd: OnceCell::new(),
-            peer_lost_data: Cell::new(false),
-            blocked_read_tid: RefCell::new(Vec::new()),
-            blocked_write_tid: RefCell::new(Vec::new()),
-            is_nonblock: Cell::new(is_nonblock),
-            fd_type: VirtualSocketType::PipeWrite,
-            delayed_readiness_updates: Rc::clone(&this.machine.delayed_readiness_updates),
-            watched: ReadinessWatched::default(),
-        });
+        let fd0 = fds.new_ref(VirtualSocket::new(
+            /* has_readbuf */ true,
+            is_nonblock,
+            VirtualSocketType::PipeRead,
+            SocketAddress::Unix(UnixSocketAddress::Unnamed),
+            SocketAddress::Unix(UnixSocketAddress::Unnamed),
+            MAX_SOCKETPAIR_BUFFER_CAPACITY,
+            Delivery::Immediate,
+            &this.machine.delayed_readiness_updates,
+        ));
+        let fd1 = fds.new_ref(VirtualSocket::new(
+            /* has_readbuf */ false,
+            is_nonblock,
+            VirtualSocketType::PipeWrite,
+            SocketAddress::Unix(UnixSocketAddress::Unnamed),
+            SocketAddress::Unix(UnixSocketAddress::Unnamed),
+            MAX_SOCKETPAIR_BUFFER_CAPACITY,
+            Delivery::Immediate,
+            &this.machine.delayed_readiness_updates,
+        ));
 
         // Make the file descriptions point to each other.
         fd0.peer_fd.set(FileDescriptionRef::downgrade(&fd1)).unwrap();
@@ -726,4 +914,195 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
 
         interp_ok(Scalar::from_i32(0))
     }
+
+    /// Create a pair of connected stream sockets which are not yet in the file descriptor table.
+    /// The first socket has the address `address`, and the second one has `peer_address`.
+    fn new_stream_socket_pair(
+        &mut self,
+        address: SocketAddress,
+        peer_address: SocketAddress,
+        is_nonblock: bool,
+        delivery: Delivery,
+    ) -> (FileDescriptionRef<VirtualSocket>, FileDescriptionRef<VirtualSocket>) {
+        let this = self.eval_context_mut();
+
+        let mut capacity = || {
+            match delivery {
+                Delivery::Immediate => MAX_SOCKETPAIR_BUFFER_CAPACITY,
+                // Anything from 64 bytes to 128 KiB.
+                Delivery::Chunked => 1 << this.machine.rng.get_mut().random_range(6..=17),
+            }
+        };
+        let (capacity0, capacity1) = (capacity(), capacity());
+
+        let fds = &mut this.machine.fds;
+        let fd0 = fds.new_ref(VirtualSocket::new(
+            /* has_readbuf */ true,
+            is_nonblock,
+            VirtualSocketType::Socketpair,
+            address.clone(),
+            peer_address.clone(),
+            capacity0,
+            delivery,
+            &this.machine.delayed_readiness_updates,
+        ));
+        let fd1 = fds.new_ref(VirtualSocket::new(
+            /* has_readbuf */ true,
+            is_nonblock,
+            VirtualSocketType::Socketpair,
+            peer_address,
+            address,
+            capacity1,
+            delivery,
+            &this.machine.delayed_readiness_updates,
+        ));
+
+        // Make the file descriptions point to each other.
+        fd0.peer_fd.set(FileDescriptionRef::downgrade(&fd1)).unwrap();
+        fd1.peer_fd.set(FileDescriptionRef::downgrade(&fd0)).unwrap();
+
+        (fd0, fd1)
+    }
+
+    /// Queue `server`, the accepting end of a new connection, on the listening socket `socket`
+    /// whose state is `listener`, and wake up the threads blocked in `accept` on it.
+    fn queue_connection(
+        &mut self,
+        socket: DynFileDescriptionRef,
+        listener: &Listener,
+        server: FileDescriptionRef<VirtualSocket>,
+    ) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+
+        // Whoever accepts the connection synchronizes with us.
+        let clock = this.release_clock(|clock| clock.clone())?.unwrap_or_default();
+        listener.backlog.borrow_mut().push_back((server, clock));
+
+        let waiting_threads = std::mem::take(&mut *listener.blocked_tids.borrow_mut());
+        for thread_id in waiting_threads {
+            this.unblock_thread(thread_id, BlockReason::VirtualSocket)?;
+        }
+        this.update_fd_readiness(socket, ReadinessUpdateFlags::FORCE_EDGE)
+    }
+
+    /// Accept a connection on the listening socket `socket` whose state is `listener`, blocking
+    /// until there is one unless `is_nonblock` is set.
+    fn accept_connection(
+        &mut self,
+        socket: DynFileDescriptionRef,
+        listener: Rc<Listener>,
+        is_nonblock: bool,
+        is_client_sock_non_block: bool,
+        finish: DynMachineCallback<'tcx, Result<(FdNum, SocketAddress), IoError>>,
+    ) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+
+        let connection = listener.backlog.borrow_mut().pop_front();
+        let Some((connection, clock)) = connection else {
+            if is_nonblock {
+                return finish.call(this, Err(ErrorKind::WouldBlock.into()));
+            }
+            listener.blocked_tids.borrow_mut().push(this.active_thread());
+            this.block_thread(
+                BlockReason::VirtualSocket,
+                None,
+                callback!(
+                    @capture<'tcx> {
+                        socket: DynFileDescriptionRef,
+                        listener: Rc<Listener>,
+                        is_client_sock_non_block: bool,
+                        finish: DynMachineCallback<'tcx, Result<(FdNum, SocketAddress), IoError>>,
+                    }
+                    |this, unblock: UnblockKind| {
+                        assert_eq!(unblock, UnblockKind::Ready);
+                        // Like on Linux, whether `accept` blocks is decided when it is called.
+                        this.accept_connection(
+                            socket,
+                            listener,
+                            /* is_nonblock */ false,
+                            is_client_sock_non_block,
+                            finish,
+                        )
+                    }
+                ),
+            );
+            return interp_ok(());
+        };
+
+        this.acquire_clock(&clock)?;
+        connection.set_nonblock(is_client_sock_non_block);
+        let peer_address = connection.peer_address().clone();
+        let fd = this.machine.fds.insert(connection);
+        // We might be no longer readable.
+        this.update_fd_readiness(socket, ReadinessUpdateFlags::DEFAULT)?;
+
+        finish.call(this, Ok((fd, peer_address)))
+    }
+
+    /// Turns the virtual pipe that `end` belongs to into a host pipe, so that `end` can be passed
+    /// to a child process. Both ends are replaced in the file descriptor table, and the new
+    /// version of `end` is returned.
+    #[cfg(unix)]
+    fn virtual_pipe_to_host(
+        &mut self,
+        end: FileDescriptionRef<VirtualSocket>,
+    ) -> InterpResult<'tcx, io::Result<FileDescriptionRef<HostPipe>>> {
+        let this = self.eval_context_mut();
+
+        if end.fd_type == VirtualSocketType::Socketpair {
+            throw_unsup_format!("passing a socketpair to a child process is not supported");
+        }
+        let peer = end.peer_fd().upgrade();
+        // The host pipe starts out empty and without anyone waiting on it, so we can only make the
+        // switch while that is also true for the virtual pipe.
+        for socket in std::iter::once(&end).chain(peer.as_ref()) {
+            if let Some(readbuf) = &socket.readbuf
+                && !readbuf.borrow().buf.is_empty()
+            {
+                throw_unsup_format!(
+                    "passing a pipe that contains unread data to a child process is not supported"
+                );
+            }
+            if !socket.blocked_read_tid.borrow().is_empty()
+                || !socket.blocked_write_tid.borrow().is_empty()
+            {
+                throw_unsup_format!(
+                    "passing a pipe that a thread is blocked on to a child process is not supported"
+                );
+            }
+            if socket.watched.is_watched() {
+                throw_unsup_format!(
+                    "passing a pipe that is watched by epoll or poll to a child process is not supported"
+                );
+            }
+        }
+
+        let (reader, writer) = match io::pipe() {
+            Ok(pipe) => pipe,
+            Err(e) => return interp_ok(Err(e)),
+        };
+        let is_read_end = end.fd_type == VirtualSocketType::PipeRead;
+        let (end_fd, peer_fd) = if is_read_end {
+            (reader.into(), writer.into())
+        } else {
+            (writer.into(), reader.into())
+        };
+
+        let host_end =
+            this.machine.fds.new_ref(HostPipe::new(end_fd, is_read_end, end.is_nonblock.get()));
+        this.machine.fds.replace_description(end.id(), host_end.clone());
+        // If the peer was already closed, dropping its host counterpart closes that as well.
+        if let Some(peer) = peer {
+            let host_peer = this.machine.fds.new_ref(HostPipe::new(
+                peer_fd,
+                !is_read_end,
+                peer.is_nonblock.get(),
+            ));
+            if let Err(e) = this.register_host_pipe(host_peer.clone()) {
+                return interp_ok(Err(e));
+            }
+            this.machine.fds.replace_description(peer.id(), host_peer);
+        }
+        interp_ok(Ok(host_end))
+    }
 }
@@ -0,0 +1,556 @@
+//! An in-memory file system that stands in for the host file system when isolation is enabled
+//! and `-Zmiri-vfs` or `-Zmiri-vfs-mount` is set.
+//!
+//! All state lives in the interpreter, so programs that create, write and delete files run
+//! deterministically. The file system starts out with an empty `/tmp` plus whatever host
+//! directories were mounted; those are copied when Miri starts, so later changes on the host are
+//! never observed. There are no symlinks, no hard links and no timestamps, and permission bits
+//! are stored but not enforced (everything acts as if it was done by `root`).
+//!
+//! Paths are resolved lexically: `.` and `..` are removed before lookup, relative paths are
+//! relative to `/`, and a leading Windows drive like `C:` is ignored, so `C:\tmp` and `/tmp` are
+//! the same directory.
+
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::ffi::{OsStr, OsString};
+use std::io::{self, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf, Prefix};
+use std::rc::Rc;
+use std::{fmt, fs};
+
+/// The largest file the in-memory file system lets a program create. File contents are kept in
+/// Miri's own memory, so this keeps a stray `ftruncate` from aborting the interpreter.
+const MAX_FILE_SIZE: u64 = 1 << 32;
+
+/// The process umask applied to the mode of new files and directories.
+const UMASK: u32 = 0o022;
+
+/// A snapshot of a host directory, to be mounted into the in-memory file system.
+#[derive(Clone, Debug)]
+pub struct VfsMount {
+    guest: PathBuf,
+    read_only: bool,
+    tree: Snapshot,
+}
+
+impl VfsMount {
+    /// Copies the host directory `host` so that it can be mounted at `guest`. If `read_only` is
+    /// not set, the program can modify the copy; the host directory is never written to.
+    pub fn snapshot(host: &Path, guest: PathBuf, read_only: bool) -> io::Result<VfsMount> {
+        let metadata = fs::metadata(host)?;
+        if !metadata.is_dir() {
+            return Err(ErrorKind::NotADirectory.into());
+        }
+        Ok(VfsMount { guest, read_only, tree: Snapshot::read(host, &metadata)? })
+    }
+}
+
+#[derive(Clone)]
+enum Snapshot {
+    File { mode: u32, data: Vec<u8> },
+    Dir { mode: u32, entries: BTreeMap<OsString, Snapshot> },
+}
+
+impl fmt::Debug for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Do not dump the contents of all files.
+        match self {
+            Snapshot::File { data, .. } => write!(f, "<{} bytes>", data.len()),
+            Snapshot::Dir { entries, .. } => f.debug_map().entries(entries).finish(),
+        }
+    }
+}
+
+impl Snapshot {
+    fn read(path: &Path, metadata: &fs::Metadata) -> io::Result<Snapshot> {
+        let mode = host_mode(metadata);
+        if !metadata.is_dir() {
+            return Ok(Snapshot::File { mode, data: fs::read(path)? });
+        }
+        let mut entries = BTreeMap::new();
+        for entry in fs::read_dir(path)? {
+            let entry = entry?;
+            let path = entry.path();
+            // Symlinks to files are copied as regular files. Symlinks to directories are skipped
+            // so that a cycle cannot make us recurse forever; anything that is neither a file
+            // nor a directory (sockets, devices, ...) has nothing we could copy.
+            let is_symlink = entry.file_type()?.is_symlink();
+            let Ok(metadata) = fs::metadata(&path) else { continue };
+            if (metadata.is_dir() && is_symlink) || !(metadata.is_dir() || metadata.is_file()) {
+                continue;
+            }
+            entries.insert(entry.file_name(), Snapshot::read(&path, &metadata)?);
+        }
+        Ok(Snapshot::Dir { mode, entries })
+    }
+}
+
+#[cfg(unix)]
+fn host_mode(metadata: &fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o7777
+}
+
+#[cfg(not(unix))]
+fn host_mode(metadata: &fs::Metadata) -> u32 {
+    match (metadata.is_dir(), metadata.permissions().readonly()) {
+        (true, true) => 0o555,
+        (true, false) => 0o755,
+        (false, true) => 0o444,
+        (false, false) => 0o644,
+    }
+}
+
+type NodeRef = Rc<RefCell<Node>>;
+
+#[derive(Debug)]
+struct Node {
+    ino: u64,
+    /// The permission bits, without the file type.
+    mode: u32,
+    /// Set for everything inside a read-only mount, including the mount point itself.
+    read_only: bool,
+    kind: NodeKind,
+}
+
+enum NodeKind {
+    File(Vec<u8>),
+    Dir(BTreeMap<OsString, NodeRef>),
+}
+
+impl fmt::Debug for NodeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NodeKind::File(data) => write!(f, "<{} bytes>", data.len()),
+            NodeKind::Dir(entries) => f.debug_list().entries(entries.keys()).finish(),
+        }
+    }
+}
+
+impl Node {
+    fn is_dir(&self) -> bool {
+        matches!(self.kind, NodeKind::Dir(_))
+    }
+
+    fn check_writable(&self) -> io::Result<()> {
+        if self.read_only { Err(ErrorKind::ReadOnlyFilesystem.into()) } else { Ok(()) }
+    }
+
+    fn entry(&self, name: &OsStr) -> io::Result<Option<NodeRef>> {
+        match &self.kind {
+            NodeKind::Dir(entries) => Ok(entries.get(name).cloned()),
+            NodeKind::File(_) => Err(ErrorKind::NotADirectory.into()),
+        }
+    }
+
+    fn entries_mut(&mut self) -> &mut BTreeMap<OsString, NodeRef> {
+        match &mut self.kind {
+            NodeKind::Dir(entries) => entries,
+            NodeKind::File(_) => panic!("not a directory"),
+        }
+    }
+
+    fn metadata(&self) -> VfsMetadata {
+        let (len, nlink) = match &self.kind {
+            NodeKind::File(data) => (data.len().try_into().unwrap(), 1),
+            NodeKind::Dir(entries) => {
+                let subdirs = entries.values().filter(|node| node.borrow().is_dir()).count();
+                (0, u64::try_from(subdirs).unwrap().strict_add(2))
+            }
+        };
+        VfsMetadata { is_dir: self.is_dir(), mode: self.mode, len, ino: self.ino, nlink }
+    }
+}
+
+/// What `stat` reports about a file in the in-memory file system.
+#[derive(Clone, Copy, Debug)]
+pub struct VfsMetadata {
+    pub is_dir: bool,
+    /// The permission bits, without the file type.
+    pub mode: u32,
+    pub len: u64,
+    pub ino: u64,
+    pub nlink: u64,
+}
+
+/// One entry returned when listing a directory.
+#[derive(Debug)]
+pub struct VfsDirEntry {
+    pub name: OsString,
+    pub ino: u64,
+    pub is_dir: bool,
+}
+
+/// How to open a file, mirroring [`fs::OpenOptions`]. Every file can be read.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct VfsOpenOptions {
+    pub write: bool,
+    pub append: bool,
+    pub truncate: bool,
+    pub create: bool,
+    pub create_new: bool,
+    /// The permission bits of a newly created file.
+    pub mode: u32,
+}
+
+/// The in-memory file system.
+#[derive(Debug)]
+pub struct Vfs {
+    root: NodeRef,
+    next_ino: Cell<u64>,
+}
+
+impl Vfs {
+    pub fn new(mounts: &[VfsMount]) -> Vfs {
+        let root = Rc::new(RefCell::new(Node {
+            ino: 1,
+            mode: 0o755,
+            read_only: false,
+            kind: NodeKind::Dir(BTreeMap::new()),
+        }));
+        let mut vfs = Vfs { root, next_ino: Cell::new(2) };
+        let tmp = vfs.new_node(0o1777, false, NodeKind::Dir(BTreeMap::new()));
+        vfs.root.borrow_mut().entries_mut().insert("tmp".into(), tmp);
+        for mount in mounts {
+            vfs.mount(mount);
+        }
+        vfs
+    }
+
+    fn new_node(&self, mode: u32, read_only: bool, kind: NodeKind) -> NodeRef {
+        let ino = self.next_ino.get();
+        self.next_ino.set(ino.strict_add(1));
+        Rc::new(RefCell::new(Node { ino, mode, read_only, kind }))
+    }
+
+    fn instantiate(&self, snapshot: &Snapshot, read_only: bool) -> NodeRef {
+        match snapshot {
+            Snapshot::File { mode, data } =>
+                self.new_node(*mode, read_only, NodeKind::File(data.clone())),
+            Snapshot::Dir { mode, entries } => {
+                let entries = entries
+                    .iter()
+                    .map(|(name, entry)| (name.clone(), self.instantiate(entry, read_only)))
+                    .collect();
+                self.new_node(*mode, read_only, NodeKind::Dir(entries))
+            }
+        }
+    }
+
+    /// Puts `mount` into the tree, creating the directories leading up to it and replacing
+    /// whatever was at the mount point before.
+    fn mount(&mut self, mount: &VfsMount) {
+        let node = self.instantiate(&mount.tree, mount.read_only);
+        let mut components = components(&mount.guest);
+        let Some(name) = components.pop() else {
+            self.root = node;
+            return;
+        };
+        let mut dir = self.root.clone();
+        for component in components {
+            let existing = dir.borrow().entry(&component).unwrap();
+            let next = match existing {
+                Some(next) if next.borrow().is_dir() => next,
+                _ => {
+                    let next = self.new_node(0o755, false, NodeKind::Dir(BTreeMap::new()));
+                    dir.borrow_mut().entries_mut().insert(component, next.clone());
+                    next
+                }
+            };
+            dir = next;
+        }
+        dir.borrow_mut().entries_mut().insert(name, node);
+    }
+
+    fn lookup(&self, path: &Path) -> io::Result<NodeRef> {
+        let mut node = self.root.clone();
+        for name in components(path) {
+            let next = node.borrow().entry(&name)?.ok_or(ErrorKind::NotFound)?;
+            node = next;
+        }
+        Ok(node)
+    }
+
+    /// Returns the directory containing `path`, and the last component of `path`.
+    fn lookup_parent(&self, path: &Path) -> io::Result<(NodeRef, OsString)> {
+        let mut components = components(path);
+        // The root is the only path without a last component, and it cannot be replaced.
+        let name = components.pop().ok_or(ErrorKind::ResourceBusy)?;
+        let mut dir = self.root.clone();
+        for component in components {
+            let next = dir.borrow().entry(&component)?.ok_or(ErrorKind::NotFound)?;
+            dir = next;
+        }
+        if !dir.borrow().is_dir() {
+            return Err(ErrorKind::NotADirectory.into());
+        }
+        Ok((dir, name))
+    }
+
+    pub fn open(&self, path: &Path, options: &VfsOpenOptions) -> io::Result<VfsFile> {
+        let node = match self.lookup(path) {
+            Ok(_) if options.create_new => return Err(ErrorKind::AlreadyExists.into()),
+            Ok(node) => node,
+            Err(err)
+                if err.kind() == ErrorKind::NotFound && options.create_new | options.create =>
+            {
+                let (dir, name) = self.lookup_parent(path)?;
+                dir.borrow().check_writable()?;
+                let node = self.new_node(options.mode & !UMASK, false, NodeKind::File(Vec::new()));
+                dir.borrow_mut().entries_mut().insert(name, node.clone());
+                node
+            }
+            Err(err) => return Err(err),
+        };
+        if options.write || options.append || options.truncate {
+            let mut node = node.borrow_mut();
+            if node.is_dir() {
+                return Err(ErrorKind::IsADirectory.into());
+            }
+            node.check_writable()?;
+            if let NodeKind::File(data) = &mut node.kind
+                && options.truncate
+            {
+                data.clear();
+            }
+        }
+        Ok(VfsFile { node, pos: Cell::new(0), append: options.append })
+    }
+
+    pub fn create_dir(&self, path: &Path, mode: u32) -> io::Result<()> {
+        if self.lookup(path).is_ok() {
+            return Err(ErrorKind::AlreadyExists.into());
+        }
+        let (dir, name) = self.lookup_parent(path)?;
+        dir.borrow().check_writable()?;
+        let node = self.new_node(mode & !UMASK, false, NodeKind::Dir(BTreeMap::new()));
+        dir.borrow_mut().entries_mut().insert(name, node);
+        Ok(())
+    }
+
+    pub fn remove_file(&self, path: &Path) -> io::Result<()> {
+        let (dir, name) = self.lookup_parent(path).map_err(|err| {
+            if err.kind() == ErrorKind::ResourceBusy { ErrorKind::IsADirectory.into() } else { err }
+        })?;
+        let node = dir.borrow().entry(&name)?.ok_or(ErrorKind::NotFound)?;
+        if node.borrow().is_dir() {
+            return Err(ErrorKind::IsADirectory.into());
+        }
+        dir.borrow().check_writable()?;
+        dir.borrow_mut().entries_mut().remove(&name);
+        Ok(())
+    }
+
+    pub fn remove_dir(&self, path: &Path) -> io::Result<()> {
+        let (dir, name) = self.lookup_parent(path)?;
+        let node = dir.borrow().entry(&name)?.ok_or(ErrorKind::NotFound)?;
+        match &node.borrow().kind {
+            NodeKind::File(_) => return Err(ErrorKind::NotADirectory.into()),
+            NodeKind::Dir(entries) if !entries.is_empty() =>
+                return Err(ErrorKind::DirectoryNotEmpty.into()),
+            NodeKind::Dir(_) => {}
+        }
+        dir.borrow().check_writable()?;
+        node.borrow().check_writable()?;
+        dir.borrow_mut().entries_mut().remove(&name);
+        Ok(())
+    }
+
+    pub fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        let (from_dir, from_name) = self.lookup_parent(from)?;
+        let node = from_dir.borrow().entry(&from_name)?.ok_or(ErrorKind::NotFound)?;
+        let (to_dir, to_name) = self.lookup_parent(to)?;
+        let (from_components, to_components) = (components(from), components(to));
+        if from_components == to_components {
+            return Ok(());
+        }
+        let is_dir = node.borrow().is_dir();
+        if is_dir && to_components.starts_with(&from_components) {
+            // A directory cannot become its own subdirectory.
+            return Err(ErrorKind::InvalidInput.into());
+        }
+        if let Some(existing) = to_dir.borrow().entry(&to_name)? {
+            match (is_dir, &existing.borrow().kind) {
+                (false, NodeKind::Dir(_)) => return Err(ErrorKind::IsADirectory.into()),
+                (true, NodeKind::File(_)) => return Err(ErrorKind::NotADirectory.into()),
+                (true, NodeKind::Dir(entries)) if !entries.is_empty() =>
+                    return Err(ErrorKind::DirectoryNotEmpty.into()),
+                _ => {}
+            }
+        }
+        from_dir.borrow().check_writable()?;
+        to_dir.borrow().check_writable()?;
+        if is_dir {
+            // This would move a read-only mount point.
+            node.borrow().check_writable()?;
+        }
+        from_dir.borrow_mut().entries_mut().remove(&from_name);
+        to_dir.borrow_mut().entries_mut().insert(to_name, node);
+        Ok(())
+    }
+
+    pub fn metadata(&self, path: &Path) -> io::Result<VfsMetadata> {
+        Ok(self.lookup(path)?.borrow().metadata())
+    }
+
+    pub fn set_permissions(&self, path: &Path, mode: u32) -> io::Result<()> {
+        let node = self.lookup(path)?;
+        let mut node = node.borrow_mut();
+        node.check_writable()?;
+        node.mode = mode & 0o7777;
+        Ok(())
+    }
+
+    /// Lists the entries of the directory at `path` in the order of their names, without `.`
+    /// and `..`.
+    pub fn read_dir(&self, path: &Path) -> io::Result<Vec<VfsDirEntry>> {
+        let node = self.lookup(path)?;
+        let node = node.borrow();
+        let NodeKind::Dir(entries) = &node.kind else {
+            return Err(ErrorKind::NotADirectory.into());
+        };
+        Ok(entries
+            .iter()
+            .map(|(name, entry)| {
+                let entry = entry.borrow();
+                VfsDirEntry { name: name.clone(), ino: entry.ino, is_dir: entry.is_dir() }
+            })
+            .collect())
+    }
+
+    /// The absolute path without `.` and `..` that `path` resolves to, if it exists.
+    pub fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        self.lookup(path)?;
+        let mut canonical = PathBuf::from("/");
+        canonical.extend(components(path));
+        Ok(canonical)
+    }
+}
+
+/// Splits `path` into the names it consists of, after resolving `.` and `..`.
+fn components(path: &Path) -> Vec<OsString> {
+    let mut components: Vec<OsString> = Vec::new();
+    let mut at_start = true;
+    for component in path.components() {
+        match component {
+            // Absolute Unix paths reach us as `\\?\tmp\...` on Windows hosts, see `convert_path`.
+            Component::Prefix(prefix) =>
+                if let Prefix::Verbatim(name) = prefix.kind() {
+                    components.push(name.to_owned());
+                },
+            Component::RootDir | Component::CurDir => {}
+            Component::ParentDir => {
+                components.pop();
+            }
+            // Windows paths reach us as `/C:/...` on Unix hosts.
+            Component::Normal(name) if at_start && is_drive(name) => {}
+            Component::Normal(name) => components.push(name.to_owned()),
+        }
+        at_start &= matches!(component, Component::Prefix(_) | Component::RootDir);
+    }
+    components
+}
+
+fn is_drive(name: &OsStr) -> bool {
+    matches!(name.as_encoded_bytes(), [letter, b':'] if letter.is_ascii_alphabetic())
+}
+
+/// An open file (or directory) of the in-memory file system, with its own cursor.
+#[derive(Debug)]
+pub struct VfsFile {
+    node: NodeRef,
+    pos: Cell<u64>,
+    append: bool,
+}
+
+impl VfsFile {
+    pub fn metadata(&self) -> VfsMetadata {
+        self.node.borrow().metadata()
+    }
+
+    pub fn set_permissions(&self, mode: u32) -> io::Result<()> {
+        let mut node = self.node.borrow_mut();
+        node.check_writable()?;
+        node.mode = mode & 0o7777;
+        Ok(())
+    }
+
+    pub fn set_len(&self, len: u64) -> io::Result<()> {
+        let mut node = self.node.borrow_mut();
+        node.check_writable()?;
+        if len > MAX_FILE_SIZE {
+            return Err(ErrorKind::FileTooLarge.into());
+        }
+        match &mut node.kind {
+            NodeKind::File(data) => data.resize(len.try_into().unwrap(), 0),
+            NodeKind::Dir(_) => return Err(ErrorKind::IsADirectory.into()),
+        }
+        Ok(())
+    }
+
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        let node = self.node.borrow();
+        let NodeKind::File(data) = &node.kind else {
+            return Err(ErrorKind::IsADirectory.into());
+        };
+        let start = usize::try_from(offset).unwrap_or(usize::MAX).min(data.len());
+        let len = buf.len().min(data.len().strict_sub(start));
+        buf[..len].copy_from_slice(&data[start..][..len]);
+        Ok(len)
+    }
+
+    fn write_at(&self, buf: &[u8], offset: u64) -> io::Result<usize> {
+        let mut node = self.node.borrow_mut();
+        node.check_writable()?;
+        let NodeKind::File(data) = &mut node.kind else {
+            return Err(ErrorKind::IsADirectory.into());
+        };
+        let end = offset.checked_add(buf.len().try_into().unwrap());
+        let Some(end) = end.filter(|&end| end <= MAX_FILE_SIZE) else {
+            return Err(ErrorKind::FileTooLarge.into());
+        };
+        let (start, end) = (usize::try_from(offset).unwrap(), usize::try_from(end).unwrap());
+        if data.len() < end {
+            data.resize(end, 0);
+        }
+        data[start..end].copy_from_slice(buf);
+        Ok(buf.len())
+    }
+}
+
+impl Read for &VfsFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.read_at(buf, self.pos.get())?;
+        self.pos.set(self.pos.get().strict_add(len.try_into().unwrap()));
+        Ok(len)
+    }
+}
+
+impl Write for &VfsFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.append {
+            self.pos.set(self.metadata().len);
+        }
+        let len = self.write_at(buf, self.pos.get())?;
+        self.pos.set(self.pos.get().strict_add(len.try_into().unwrap()));
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for &VfsFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let pos = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(offset) => self.pos.get().checked_add_signed(offset),
+            SeekFrom::End(offset) => self.metadata().len.checked_add_signed(offset),
+        };
+        let pos = pos.ok_or(ErrorKind::InvalidInput)?;
+        self.pos.set(pos);
+        Ok(pos)
+    }
+}
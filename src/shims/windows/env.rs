@@ -1,6 +1,7 @@
 use std::env;
 use std::ffi::{OsStr, OsString};
 use std::io::ErrorKind;
+use std::path::PathBuf;
 
 use rustc_data_structures::fx::FxHashMap;
 use rustc_target::spec::Os;
@@ -266,12 +267,17 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
     {
         let this = self.eval_context_mut();
         this.assert_target_os(Os::Windows, "GetTempPathW");
-        this.check_no_isolation("`GetTempPathW`")?;
 
         let buflen = this.read_scalar(buflen)?.to_u32()?;
         let buf = this.read_pointer(buf)?;
 
-        let temp_dir = env::temp_dir();
+        let temp_dir = if this.machine.vfs.is_some() {
+            // The in-memory file system always has a `/tmp`.
+            PathBuf::from("/tmp/")
+        } else {
+            this.check_no_isolation("`GetTempPathW`")?;
+            env::temp_dir()
+        };
 
         return interp_ok(Scalar::from_u32(windows_check_buffer_size(
             this.write_path_to_wide_str(&temp_dir, buf, buflen.into())?,
@@ -340,7 +340,9 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
                     abi,
                     args,
                 )?;
-                this.check_no_isolation("`GetFullPathNameW`")?;
+                if this.machine.vfs.is_none() {
+                    this.check_no_isolation("`GetFullPathNameW`")?;
+                }
 
                 let filename = this.read_pointer(filename)?;
                 let size = this.read_scalar(size)?.to_u32()?;
@@ -351,7 +353,12 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
                     throw_unsup_format!("GetFullPathNameW: non-null `lpFilePart` is not supported");
                 }
 
-                let filename = this.read_path_from_wide_str(filename)?;
+                let mut filename = this.read_path_from_wide_str(filename)?;
+                if this.machine.vfs.is_some() {
+                    // The in-memory file system has its working directory at the root, so do not
+                    // let the host working directory leak into the result.
+                    filename = Path::new("/").join(filename);
+                }
                 let result = match win_get_full_path_name(&filename)? {
                     Err(err) => {
                         this.set_last_error(err)?;
@@ -7,7 +7,8 @@ use bitflags::bitflags;
 use rustc_abi::Size;
 use rustc_target::spec::Os;
 
-use crate::shims::files::{DirHandle, FileHandle};
+use crate::shims::files::{Backing, DirHandle, FileHandle, HandleMetadata};
+use crate::shims::vfs::VfsOpenOptions;
 use crate::shims::windows::handle::{EvalContextExt as _, Handle};
 use crate::*;
 
@@ -117,7 +118,6 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
 
         let this = self.eval_context_mut();
         this.assert_target_os(Os::Windows, "CreateFileW");
-        this.check_no_isolation("`CreateFileW`")?;
 
         // This function appears to always set the error to 0. This is important for some flag
         // combinations, which may set error code on success.
@@ -175,6 +175,55 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
             );
         }
 
+        if let Some(vfs) = &this.machine.vfs {
+            // Nothing else can change the in-memory file system, so unlike below, we need not
+            // worry about races between looking at the file and opening it.
+            let is_dir = vfs.metadata(&file_name).map(|metadata| metadata.is_dir);
+            let exists_already = is_dir.is_ok();
+            let is_dir = is_dir.unwrap_or(false);
+            if !attributes.contains(FileAttributes::BACKUP_SEMANTICS) && is_dir {
+                this.set_last_error(IoError::WindowsError("ERROR_ACCESS_DENIED"))?;
+                return interp_ok(Handle::Invalid);
+            }
+            let options = if is_dir {
+                VfsOpenOptions::default()
+            } else {
+                VfsOpenOptions {
+                    write: desired_write,
+                    truncate: matches!(creation_disposition, CreateAlways | TruncateExisting),
+                    create: matches!(creation_disposition, CreateAlways | OpenAlways),
+                    create_new: creation_disposition == CreateNew,
+                    mode: 0o666,
+                    ..Default::default()
+                }
+            };
+            let file = match vfs.open(&file_name, &options) {
+                Ok(file) => file,
+                Err(e) => {
+                    this.set_last_error(e)?;
+                    return interp_ok(Handle::Invalid);
+                }
+            };
+            // Windows communicates information via the error code on success.
+            if let CreateAlways | OpenAlways = creation_disposition
+                && exists_already
+            {
+                this.set_last_error(IoError::WindowsError("ERROR_ALREADY_EXISTS"))?;
+            }
+            let fd_num = if is_dir {
+                this.machine.fds.insert_new(DirHandle { dir: Backing::Virtual(file) })
+            } else {
+                this.machine.fds.insert_new(FileHandle {
+                    file: Backing::Virtual(file),
+                    path: file_name,
+                    writable: desired_write,
+                    readable: desired_read,
+                })
+            };
+            return interp_ok(Handle::File(fd_num));
+        }
+        this.check_no_isolation("`CreateFileW`")?;
+
         // We start a retry loop to deal with the `is_dir` and `exists_already` race, see below.
         // We add a retry counter to avoid infinite loops when things go wrong.
         let mut counter = 0u32;
@@ -223,7 +272,7 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
                     this.set_last_error(IoError::WindowsError("ERROR_ALREADY_EXISTS"))?;
                 }
 
-                let fd_num = this.machine.fds.insert_new(DirHandle { dir });
+                let fd_num = this.machine.fds.insert_new(DirHandle { dir: Backing::Host(dir) });
                 return interp_ok(Handle::File(fd_num));
             } else {
                 // Per the documentation:
@@ -313,7 +362,8 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
                     this.set_last_error(IoError::WindowsError("ERROR_ALREADY_EXISTS"))?;
                 }
                 let fd_num = this.machine.fds.insert_new(FileHandle {
-                    file,
+                    file: Backing::Host(file),
+                    path: file_name.clone(),
                     writable: desired_write,
                     readable: desired_read,
                 });
@@ -330,7 +380,9 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         // ^ Returns BOOL (i32 on Windows)
         let this = self.eval_context_mut();
         this.assert_target_os(Os::Windows, "GetFileInformationByHandle");
-        this.check_no_isolation("`GetFileInformationByHandle`")?;
+        if this.machine.vfs.is_none() {
+            this.check_no_isolation("`GetFileInformationByHandle`")?;
+        }
 
         let file = this.read_handle(file, "GetFileInformationByHandle")?;
         let file_information = this.deref_pointer_as(
@@ -358,21 +410,26 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
 
         let size = metadata.len();
 
-        let file_type = metadata.file_type();
-        let attributes = if file_type.is_dir() {
-            this.eval_windows_u32("c", "FILE_ATTRIBUTE_DIRECTORY")
-        } else if file_type.is_file() {
-            this.eval_windows_u32("c", "FILE_ATTRIBUTE_NORMAL")
-        } else {
-            this.eval_windows_u32("c", "FILE_ATTRIBUTE_DEVICE")
+        let attributes = match &metadata {
+            _ if metadata.is_dir() => this.eval_windows_u32("c", "FILE_ATTRIBUTE_DIRECTORY"),
+            HandleMetadata::Host(host) if !host.is_file() =>
+                this.eval_windows_u32("c", "FILE_ATTRIBUTE_DEVICE"),
+            _ => this.eval_windows_u32("c", "FILE_ATTRIBUTE_NORMAL"),
         };
 
         // Per the Windows documentation:
         // "If the underlying file system does not support the [...] time, this member is zero (0)."
         // https://learn.microsoft.com/en-us/windows/win32/api/fileapi/ns-fileapi-by_handle_file_information
-        let created = extract_windows_epoch(this, metadata.created())?.unwrap_or((0, 0));
-        let accessed = extract_windows_epoch(this, metadata.accessed())?.unwrap_or((0, 0));
-        let written = extract_windows_epoch(this, metadata.modified())?.unwrap_or((0, 0));
+        // The in-memory file system does not track timestamps.
+        let (created, accessed, written) = match &metadata {
+            HandleMetadata::Host(host) =>
+                (
+                    extract_windows_epoch(this, host.created())?.unwrap_or((0, 0)),
+                    extract_windows_epoch(this, host.accessed())?.unwrap_or((0, 0)),
+                    extract_windows_epoch(this, host.modified())?.unwrap_or((0, 0)),
+                ),
+            HandleMetadata::Virtual(_) => ((0, 0), (0, 0), (0, 0)),
+        };
 
         this.write_int_fields_named(&[("dwFileAttributes", attributes.into())], &file_information)?;
         write_filetime_field(this, &file_information, "ftCreationTime", created)?;
@@ -403,7 +460,9 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         // ^ Returns BOOL (i32 on Windows)
         let this = self.eval_context_mut();
         this.assert_target_os(Os::Windows, "SetFileInformationByHandle");
-        this.check_no_isolation("`SetFileInformationByHandle`")?;
+        if this.machine.vfs.is_none() {
+            this.check_no_isolation("`SetFileInformationByHandle`")?;
+        }
 
         let class = this.read_scalar(class)?.to_u32()?;
         let buffer_size = this.read_scalar(buffer_size)?.to_u32()?;
@@ -501,7 +560,7 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
             return interp_ok(this.eval_windows("c", "FALSE"));
         }
 
-        match file.file.sync_all() {
+        match file.file.sync(fs::File::sync_all) {
             Ok(_) => interp_ok(this.eval_windows("c", "TRUE")),
             Err(e) => {
                 this.set_last_error(e)?;
@@ -531,7 +590,13 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
             throw_unsup_format!("MoveFileExW: Unsupported `dwFlags` value {}", flags);
         }
 
-        match std::fs::rename(existing_name, new_name) {
+        let result = if let Some(vfs) = &this.machine.vfs {
+            vfs.rename(&existing_name, &new_name)
+        } else {
+            this.check_no_isolation("`MoveFileExW`")?;
+            std::fs::rename(existing_name, new_name)
+        };
+        match result {
             Ok(_) => interp_ok(this.eval_windows("c", "TRUE")),
             Err(e) => {
                 this.set_last_error(e)?;
@@ -547,10 +612,15 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         // ^ Returns BOOL (i32 on Windows)
         let this = self.eval_context_mut();
         this.assert_target_os(Os::Windows, "DeleteFileW");
-        this.check_no_isolation("`DeleteFileW`")?;
 
         let file_name = this.read_path_from_wide_str(this.read_pointer(file_name)?)?;
-        match std::fs::remove_file(file_name) {
+        let result = if let Some(vfs) = &this.machine.vfs {
+            vfs.remove_file(&file_name)
+        } else {
+            this.check_no_isolation("`DeleteFileW`")?;
+            std::fs::remove_file(file_name)
+        };
+        match result {
             Ok(_) => interp_ok(this.eval_windows("c", "TRUE")),
             Err(e) => {
                 this.set_last_error(e)?;
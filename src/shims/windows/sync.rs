@@ -236,6 +236,7 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
 
             let dest = dest.clone();
             this.futex_wait(
+                ptr,
                 futex_ref,
                 u32::MAX, // bitset
                 deadline,
@@ -279,7 +280,7 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         };
         let futex_ref = futex_ref.futex.clone();
 
-        this.futex_wake(&futex_ref, u32::MAX, 1)?;
+        this.futex_wake(ptr, &futex_ref, u32::MAX, 1)?;
 
         interp_ok(())
     }
@@ -299,7 +300,7 @@ pub trait EvalContextExt<'tcx>: crate::MiriInterpCxExt<'tcx> {
         };
         let futex_ref = futex_ref.futex.clone();
 
-        this.futex_wake(&futex_ref, u32::MAX, usize::MAX)?;
+        this.futex_wake(ptr, &futex_ref, u32::MAX, usize::MAX)?;
 
         interp_ok(())
     }
@@ -0,0 +1,35 @@
+//@ignore-target: windows # No pthreads on Windows
+//@error-in-other-file: deadlock
+// We are making scheduler assumptions here.
+//@compile-flags: -Zmiri-deterministic-concurrency
+
+use std::cell::UnsafeCell;
+use std::sync::Arc;
+use std::thread;
+
+struct Shared {
+    mutex: UnsafeCell<libc::pthread_mutex_t>,
+    cond: UnsafeCell<libc::pthread_cond_t>,
+}
+
+unsafe impl Send for Shared {}
+unsafe impl Sync for Shared {}
+
+// The spawned thread waits for a signal that never comes, while `main` holds the mutex the waiter
+// would have to re-lock and waits for the waiter to terminate.
+fn main() {
+    let shared = Arc::new(Shared {
+        mutex: UnsafeCell::new(libc::PTHREAD_MUTEX_INITIALIZER),
+        cond: UnsafeCell::new(libc::PTHREAD_COND_INITIALIZER),
+    });
+    let shared2 = shared.clone();
+    let handle = thread::spawn(move || unsafe {
+        libc::pthread_mutex_lock(shared2.mutex.get());
+        libc::pthread_cond_wait(shared2.cond.get(), shared2.mutex.get());
+    });
+    thread::yield_now();
+    unsafe {
+        libc::pthread_mutex_lock(shared.mutex.get());
+    }
+    handle.join().unwrap();
+}
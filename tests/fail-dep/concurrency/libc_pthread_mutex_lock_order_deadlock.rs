@@ -0,0 +1,32 @@
+//@ignore-target: windows # No pthreads on Windows
+// We are making scheduler assumptions here.
+//@compile-flags: -Zmiri-deterministic-concurrency
+
+use std::cell::UnsafeCell;
+use std::sync::Arc;
+use std::thread;
+
+struct Mutex(UnsafeCell<libc::pthread_mutex_t>);
+
+unsafe impl Send for Mutex {}
+unsafe impl Sync for Mutex {}
+
+fn new_lock() -> Arc<Mutex> {
+    Arc::new(Mutex(UnsafeCell::new(libc::PTHREAD_MUTEX_INITIALIZER)))
+}
+
+// The two threads acquire the locks in opposite order, so each ends up waiting for the other.
+fn main() {
+    let a = new_lock();
+    let b = new_lock();
+    unsafe {
+        libc::pthread_mutex_lock(a.0.get());
+        let (a2, b2) = (a.clone(), b.clone());
+        let _handle = thread::spawn(move || {
+            libc::pthread_mutex_lock(b2.0.get());
+            libc::pthread_mutex_lock(a2.0.get()); //~ ERROR: deadlock
+        });
+        thread::yield_now();
+        libc::pthread_mutex_lock(b.0.get()); //~ ERROR: deadlock
+    }
+}
@@ -0,0 +1,39 @@
+//@ignore-target: windows # No pthreads on Windows
+//@ignore-host: windows # the trace is written to `/tmp`
+// Record a schedule in which the threads keep taking turns, then shrink it. The revisions run in
+// order, so `minimize` reads the trace that `record` just wrote. The bug only needs the spawned
+// thread to run to completion before `main` reads the pointer, which takes two context switches.
+//@revisions: record minimize
+//@compile-flags: -Zmiri-fixed-schedule -Zmiri-disable-weak-memory-emulation
+//@[record] compile-flags: -Zmiri-record-schedule=/tmp/miri-test-minimize-schedule.trace
+//@[minimize] compile-flags: -Zmiri-replay-schedule=/tmp/miri-test-minimize-schedule.trace -Zmiri-minimize-schedule
+
+use std::sync::atomic::{AtomicPtr, Ordering};
+use std::{mem, ptr};
+
+static PTR: AtomicPtr<i32> = AtomicPtr::new(ptr::null_mut());
+
+extern "C" fn thread_start(_null: *mut libc::c_void) -> *mut libc::c_void {
+    for _ in 0..2 {
+        unsafe { libc::sched_yield() };
+    }
+    PTR.store(ptr::null_mut(), Ordering::Relaxed);
+    ptr::null_mut()
+}
+
+fn main() {
+    let mut value = 42;
+    PTR.store(&mut value, Ordering::Relaxed);
+    unsafe {
+        let mut native: libc::pthread_t = mem::zeroed();
+        assert_eq!(
+            libc::pthread_create(&mut native, ptr::null(), thread_start, ptr::null_mut()),
+            0
+        );
+        for _ in 0..3 {
+            libc::sched_yield();
+        }
+        let _val = *PTR.load(Ordering::Relaxed); //~ ERROR: null pointer
+        assert_eq!(libc::pthread_join(native, ptr::null_mut()), 0);
+    }
+}
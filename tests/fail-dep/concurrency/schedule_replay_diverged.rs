@@ -0,0 +1,32 @@
+//@ignore-target: windows # No pthreads on Windows
+//@ignore-host: windows # the trace is written to `/tmp`
+// Replay a schedule after changing the program. The revisions run in order, so `diverge` reads the
+// trace that `record` just wrote. Joining the thread earlier makes the scheduler switch threads
+// where the recorded execution made an allocation, and Miri reports where the replay diverged.
+//@revisions: record diverge
+//@compile-flags: -Zmiri-preemption-rate=0
+//@[record] compile-flags: -Zmiri-record-schedule=/tmp/miri-test-schedule-replay-diverged.trace
+//@[diverge] compile-flags: -Zmiri-replay-schedule=/tmp/miri-test-schedule-replay-diverged.trace
+//@normalize-stderr-test: "decision [0-9]+ to be `[^`]+`" -> "decision N to be `DECISION`"
+
+use std::{mem, ptr};
+
+extern "C" fn thread_start(_null: *mut libc::c_void) -> *mut libc::c_void {
+    ptr::null_mut()
+}
+
+fn main() {
+    unsafe {
+        let mut native: libc::pthread_t = mem::zeroed();
+        assert_eq!(
+            libc::pthread_create(&mut native, ptr::null(), thread_start, ptr::null_mut()),
+            0
+        );
+        #[cfg(diverge)]
+        assert_eq!(libc::pthread_join(native, ptr::null_mut()), 0);
+        drop(Box::new(0u64));
+        #[cfg(record)]
+        assert_eq!(libc::pthread_join(native, ptr::null_mut()), 0);
+        assert_eq!(libc::pthread_join(native, ptr::null_mut()), 0); //~ ERROR: Undefined Behavior: trying to join an already joined thread
+    }
+}
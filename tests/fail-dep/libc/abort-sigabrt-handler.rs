@@ -0,0 +1,13 @@
+//@ignore-target: windows # No signals on Windows
+
+extern "C" fn handler(_signum: libc::c_int) {
+    eprintln!("SIGABRT handler ran");
+}
+
+fn main() {
+    unsafe {
+        libc::signal(libc::SIGABRT, handler as *const () as libc::sighandler_t);
+    }
+    // The handler runs, and since it returns, the process is still aborted.
+    std::process::abort(); //~ ERROR: the program aborted execution
+}
@@ -0,0 +1,25 @@
+//@ignore-target: windows # no libc
+//@compile-flags: -Zmiri-vfs
+
+// Miri only remembers the path a directory was opened with, so it cannot resolve paths relative to
+// the directory once it has been moved.
+
+use std::fs;
+
+fn main() {
+    fs::create_dir("/tmp/miri_test_mkdirat_moved_dir").unwrap();
+    let dirfd = unsafe {
+        libc::open(c"/tmp/miri_test_mkdirat_moved_dir".as_ptr(), libc::O_RDONLY | libc::O_DIRECTORY)
+    };
+    assert!(dirfd >= 0);
+
+    // Moving the directory away and back again is fine.
+    fs::rename("/tmp/miri_test_mkdirat_moved_dir", "/tmp/miri_test_mkdirat_moved_dir_2").unwrap();
+    fs::rename("/tmp/miri_test_mkdirat_moved_dir_2", "/tmp/miri_test_mkdirat_moved_dir").unwrap();
+    assert_eq!(unsafe { libc::mkdirat(dirfd, c"sub".as_ptr(), 0o777) }, 0);
+
+    // But once another directory takes its place, paths can no longer be resolved.
+    fs::rename("/tmp/miri_test_mkdirat_moved_dir", "/tmp/miri_test_mkdirat_moved_dir_2").unwrap();
+    fs::create_dir("/tmp/miri_test_mkdirat_moved_dir").unwrap();
+    unsafe { libc::mkdirat(dirfd, c"sub".as_ptr(), 0o777) }; //~ ERROR: moved or removed
+}
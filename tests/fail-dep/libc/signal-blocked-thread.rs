@@ -0,0 +1,18 @@
+//@ignore-target: windows # No signals on Windows
+//@compile-flags: -Zmiri-deterministic-concurrency
+
+// Miri cannot interrupt a blocked thread to run a signal handler, so this is reported as
+// unsupported instead of running the handler only once the thread is unblocked.
+
+use std::thread;
+
+extern "C" fn handler(_signum: libc::c_int) {}
+
+fn main() {
+    unsafe { libc::signal(libc::SIGUSR1, handler as *const () as libc::sighandler_t) };
+    let main_thread = unsafe { libc::pthread_self() };
+    // The main thread is blocked in `join` by the time the signal is sent.
+    let child = thread::spawn(move || unsafe { libc::pthread_kill(main_thread, libc::SIGUSR1) });
+    //~^ERROR: while it is blocked is not supported
+    child.join().unwrap();
+}
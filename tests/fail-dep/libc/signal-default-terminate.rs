@@ -0,0 +1,9 @@
+//@ignore-target: windows # No signals on Windows
+
+fn main() {
+    unsafe {
+        libc::raise(libc::SIGTERM);
+    }
+    //~^^ERROR: terminated by signal SIGTERM
+    unreachable!();
+}
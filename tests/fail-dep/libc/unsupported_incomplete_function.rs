@@ -1,11 +1,13 @@
-//! `signal()` is special on Linux and macOS that it's only supported within libstd.
+//! `pthread_attr_getguardsize()` is special that it's only supported within libstd.
 //! The implementation is not complete enough to permit user code to call it.
-//@ignore-target: windows # No `libc: #signal` on Windows
+//@ignore-target: windows # No `libc::pthread_attr_getguardsize` on Windows
 //@normalize-stderr-test: "OS `.*`" -> "$$OS"
 
 fn main() {
     unsafe {
-        libc::signal(libc::SIGPIPE, libc::SIG_IGN);
-        //~^ ERROR: unsupported operation: can't call foreign function `signal`
+        let mut attr: libc::pthread_attr_t = std::mem::zeroed();
+        let mut guard = 0;
+        libc::pthread_attr_getguardsize(&mut attr, &mut guard);
+        //~^ ERROR: unsupported operation: can't call foreign function `pthread_attr_getguardsize`
     }
 }
@@ -0,0 +1,40 @@
+//@only-target: linux # other targets implement `std::sync::Mutex` without futexes or describe them differently
+//@error-in-other-file: deadlock
+// We are making scheduler assumptions here.
+//@compile-flags: -Zmiri-deterministic-concurrency
+//@normalize-stderr-test: "src/sys/.*\.rs" -> "$$FILE"
+//@normalize-stderr-test: "LL \| .*" -> "LL | $$CODE"
+//@normalize-stderr-test: "\| +\^+" -> "| ^"
+//@normalize-stderr-test: "\n *= note: stack backtrace:" -> ""
+//@normalize-stderr-test: "\n *\d+:.*\n *at .*" -> ""
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Makes another thread wait for `m`: Miri only learns which thread holds a futex-based lock once
+/// the lock has been contended.
+fn contend(m: &Mutex<()>) {
+    let guard = m.lock().unwrap();
+    thread::scope(|s| {
+        s.spawn(|| drop(m.lock().unwrap()));
+        thread::sleep(Duration::from_millis(100));
+        drop(guard);
+    });
+}
+
+fn lock_both(first: &Mutex<()>, second: &Mutex<()>) {
+    let _first = first.lock().unwrap();
+    thread::yield_now();
+    let _second = second.lock().unwrap();
+}
+
+// The two threads acquire the locks in opposite order, so each ends up waiting for the other.
+fn main() {
+    let a = Arc::new(Mutex::new(()));
+    let b = Arc::new(Mutex::new(()));
+    contend(&a);
+    contend(&b);
+    let (a2, b2) = (a.clone(), b.clone());
+    let _handle = thread::spawn(move || lock_both(&b2, &a2));
+    lock_both(&a, &b);
+}
@@ -0,0 +1,15 @@
+// The bug below only shows up if the spawned thread runs before `main` stores the pointer. With
+// `steps=1`, the only priority change point is the first step at which both threads can run. That
+// lowers the priority of `main`, so PCT finds the bug no matter which priorities the threads get.
+//@compile-flags: -Zmiri-scheduler=pct:depth=2,steps=1 -Zmiri-seed=1
+
+use std::sync::atomic::{AtomicPtr, Ordering};
+use std::{ptr, thread};
+
+static PTR: AtomicPtr<i32> = AtomicPtr::new(ptr::null_mut());
+
+fn main() {
+    let reader = thread::spawn(|| unsafe { *PTR.load(Ordering::Relaxed) }); //~ ERROR: null pointer
+    PTR.store(Box::into_raw(Box::new(42)), Ordering::Relaxed);
+    reader.join().unwrap();
+}
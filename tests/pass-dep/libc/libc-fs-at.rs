@@ -0,0 +1,250 @@
+//@ignore-target: windows # no libc
+//@revisions: host vfs
+//@[host] ignore-host: windows # opening directories does not work on Windows hosts
+//@[host] compile-flags: -Zmiri-disable-isolation
+//@[vfs] compile-flags: -Zmiri-vfs
+
+use std::ffi::{CStr, CString};
+use std::fs;
+use std::mem::MaybeUninit;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+#[path = "../../utils/libc.rs"]
+mod libc_utils;
+use libc_utils::{errno_check, errno_result, write_all};
+
+fn main() {
+    let dir = prepare_dir();
+    let dirfd = open_dir(&dir);
+
+    test_openat(&dir, dirfd);
+    test_fstatat(dirfd);
+    test_mkdirat_unlinkat(&dir, dirfd);
+    test_renameat(&dir, dirfd);
+    test_fchmodat(dirfd);
+    test_readlinkat(dirfd);
+    test_dirfd_errors(&dir);
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    test_o_path(&dir);
+
+    errno_check(unsafe { libc::close(dirfd) });
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// Creates an empty directory for the test. The in-memory file system only has `/tmp`, and
+/// `temp_dir` falls back to that under isolation.
+fn prepare_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join("miri_test_libc_fs_at");
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir(&dir).unwrap();
+    dir
+}
+
+fn cstr(path: impl AsRef<Path>) -> CString {
+    CString::new(path.as_ref().as_os_str().as_bytes()).unwrap()
+}
+
+fn open_dir(dir: &Path) -> i32 {
+    let dir = cstr(dir);
+    errno_result(unsafe { libc::open(dir.as_ptr(), libc::O_RDONLY | libc::O_DIRECTORY) }).unwrap()
+}
+
+fn stat_at(dirfd: i32, path: &CStr, flags: i32) -> std::io::Result<libc::stat> {
+    let mut stat = MaybeUninit::<libc::stat>::zeroed();
+    errno_result(unsafe { libc::fstatat(dirfd, path.as_ptr(), stat.as_mut_ptr(), flags) })?;
+    Ok(unsafe { stat.assume_init() })
+}
+
+fn test_openat(dir: &Path, dirfd: i32) {
+    let name = c"file.txt";
+    let fd = errno_result(unsafe {
+        libc::openat(dirfd, name.as_ptr(), libc::O_WRONLY | libc::O_CREAT | libc::O_EXCL, 0o644)
+    })
+    .unwrap();
+    let bytes = b"relative to a directory";
+    write_all(fd, bytes).unwrap();
+    errno_check(unsafe { libc::close(fd) });
+    assert_eq!(fs::read(dir.join("file.txt")).unwrap(), bytes);
+
+    // `AT_FDCWD` resolves relative to the working directory, and absolute paths ignore `dirfd`.
+    let absolute = cstr(dir.join("file.txt"));
+    let fd =
+        errno_result(unsafe { libc::openat(libc::AT_FDCWD, absolute.as_ptr(), libc::O_RDONLY) })
+            .unwrap();
+    errno_check(unsafe { libc::close(fd) });
+
+    // `O_DIRECTORY` only opens directories.
+    let err = errno_result(unsafe {
+        libc::openat(dirfd, name.as_ptr(), libc::O_RDONLY | libc::O_DIRECTORY)
+    })
+    .unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::ENOTDIR));
+    let fd = errno_result(unsafe {
+        libc::openat(dirfd, c".".as_ptr(), libc::O_RDONLY | libc::O_DIRECTORY)
+    })
+    .unwrap();
+    errno_check(unsafe { libc::close(fd) });
+}
+
+fn test_fstatat(dirfd: i32) {
+    let stat = stat_at(dirfd, c"file.txt", 0).unwrap();
+    assert_eq!(stat.st_mode & libc::S_IFMT, libc::S_IFREG);
+    assert_eq!(stat.st_size, 23);
+
+    let stat = stat_at(dirfd, c"file.txt", libc::AT_SYMLINK_NOFOLLOW).unwrap();
+    assert_eq!(stat.st_mode & libc::S_IFMT, libc::S_IFREG);
+
+    let err = stat_at(dirfd, c"missing.txt", 0).unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::ENOENT));
+
+    // An empty path is an error, unless `AT_EMPTY_PATH` is set.
+    let err = stat_at(dirfd, c"", 0).unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::ENOENT));
+    #[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd"))]
+    {
+        let stat = stat_at(dirfd, c"", libc::AT_EMPTY_PATH).unwrap();
+        assert_eq!(stat.st_mode & libc::S_IFMT, libc::S_IFDIR);
+    }
+}
+
+fn test_mkdirat_unlinkat(dir: &Path, dirfd: i32) {
+    errno_check(unsafe { libc::mkdirat(dirfd, c"subdir".as_ptr(), 0o755) });
+    assert!(dir.join("subdir").is_dir());
+    let err = errno_result(unsafe { libc::mkdirat(dirfd, c"subdir".as_ptr(), 0o755) }).unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EEXIST));
+
+    // A directory opened relative to another one can serve as `dirfd` itself.
+    let subdirfd = errno_result(unsafe {
+        libc::openat(dirfd, c"subdir".as_ptr(), libc::O_RDONLY | libc::O_DIRECTORY)
+    })
+    .unwrap();
+    let fd = errno_result(unsafe {
+        libc::openat(subdirfd, c"nested.txt".as_ptr(), libc::O_WRONLY | libc::O_CREAT, 0o644)
+    })
+    .unwrap();
+    errno_check(unsafe { libc::close(fd) });
+    assert!(dir.join("subdir/nested.txt").is_file());
+
+    // Removing a directory requires `AT_REMOVEDIR`, and removing a file must not have it.
+    let err = errno_result(unsafe { libc::unlinkat(dirfd, c"subdir".as_ptr(), 0) }).unwrap_err();
+    assert!(
+        [libc::EISDIR, libc::EPERM].contains(&err.raw_os_error().unwrap()),
+        "unexpected errno: {err}"
+    );
+    let err = errno_result(unsafe {
+        libc::unlinkat(subdirfd, c"nested.txt".as_ptr(), libc::AT_REMOVEDIR)
+    })
+    .unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::ENOTDIR));
+
+    errno_check(unsafe { libc::unlinkat(subdirfd, c"nested.txt".as_ptr(), 0) });
+    errno_check(unsafe { libc::close(subdirfd) });
+    errno_check(unsafe { libc::unlinkat(dirfd, c"subdir".as_ptr(), libc::AT_REMOVEDIR) });
+    assert!(!dir.join("subdir").exists());
+}
+
+fn test_renameat(dir: &Path, dirfd: i32) {
+    fs::write(dir.join("old.txt"), b"old").unwrap();
+    errno_check(unsafe { libc::renameat(dirfd, c"old.txt".as_ptr(), dirfd, c"new.txt".as_ptr()) });
+    assert!(!dir.join("old.txt").exists());
+    assert_eq!(fs::read(dir.join("new.txt")).unwrap(), b"old");
+
+    #[cfg(target_os = "linux")]
+    {
+        // `RENAME_NOREPLACE` refuses to overwrite an existing file.
+        fs::write(dir.join("other.txt"), b"other").unwrap();
+        let err = errno_result(unsafe {
+            libc::renameat2(
+                dirfd,
+                c"other.txt".as_ptr(),
+                dirfd,
+                c"new.txt".as_ptr(),
+                libc::RENAME_NOREPLACE,
+            )
+        })
+        .unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::EEXIST));
+        errno_check(unsafe {
+            libc::renameat2(dirfd, c"other.txt".as_ptr(), dirfd, c"new.txt".as_ptr(), 0)
+        });
+        assert_eq!(fs::read(dir.join("new.txt")).unwrap(), b"other");
+    }
+
+    errno_check(unsafe { libc::unlinkat(dirfd, c"new.txt".as_ptr(), 0) });
+}
+
+fn test_fchmodat(dirfd: i32) {
+    errno_check(unsafe { libc::fchmodat(dirfd, c"file.txt".as_ptr(), 0o600, 0) });
+    let stat = stat_at(dirfd, c"file.txt", 0).unwrap();
+    assert_eq!(stat.st_mode & 0o777, 0o600);
+    // Without a symlink involved, `AT_SYMLINK_NOFOLLOW` makes no difference.
+    errno_check(unsafe {
+        libc::fchmodat(dirfd, c"file.txt".as_ptr(), 0o644, libc::AT_SYMLINK_NOFOLLOW)
+    });
+    let stat = stat_at(dirfd, c"file.txt", 0).unwrap();
+    assert_eq!(stat.st_mode & 0o777, 0o644);
+}
+
+fn test_readlinkat(dirfd: i32) {
+    // Regular files are not symlinks.
+    let mut buf = [0u8; 16];
+    let err = errno_result(unsafe {
+        libc::readlinkat(dirfd, c"file.txt".as_ptr(), buf.as_mut_ptr().cast(), buf.len())
+    })
+    .unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EINVAL));
+}
+
+fn test_dirfd_errors(dir: &Path) {
+    // A file is not a directory.
+    let file = cstr(dir.join("file.txt"));
+    let filefd = errno_result(unsafe { libc::open(file.as_ptr(), libc::O_RDONLY) }).unwrap();
+    let err = stat_at(filefd, c"file.txt", 0).unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::ENOTDIR));
+    errno_check(unsafe { libc::close(filefd) });
+
+    // Neither is a pipe.
+    let mut fds = [0; 2];
+    errno_check(unsafe { libc::pipe(fds.as_mut_ptr()) });
+    let err =
+        errno_result(unsafe { libc::mkdirat(fds[0], c"subdir".as_ptr(), 0o755) }).unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::ENOTDIR));
+    errno_check(unsafe { libc::close(fds[0]) });
+    errno_check(unsafe { libc::close(fds[1]) });
+
+    // A closed file descriptor is invalid.
+    let err = stat_at(fds[0], c"file.txt", 0).unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EBADF));
+
+    // Absolute paths ignore `dirfd`, even if it is invalid.
+    stat_at(fds[0], &file, 0).unwrap();
+}
+
+/// `O_PATH` opens a file only to refer to it, e.g. as `dirfd`.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn test_o_path(dir: &Path) {
+    let dirname = cstr(dir);
+    let pathfd =
+        errno_result(unsafe { libc::open(dirname.as_ptr(), libc::O_PATH | libc::O_DIRECTORY) })
+            .unwrap();
+    let stat = stat_at(pathfd, c"file.txt", 0).unwrap();
+    assert_eq!(stat.st_mode & libc::S_IFMT, libc::S_IFREG);
+
+    // The file cannot be read through such a file descriptor.
+    let mut buf = [0u8; 4];
+    let file = cstr(dir.join("file.txt"));
+    let fd = errno_result(unsafe { libc::open(file.as_ptr(), libc::O_PATH) }).unwrap();
+    assert!(errno_result(unsafe { libc::read(fd, buf.as_mut_ptr().cast(), buf.len()) }).is_err());
+    errno_check(unsafe { libc::close(fd) });
+
+    // The other flags are ignored, so this does not create anything.
+    let missing = cstr(dir.join("missing.txt"));
+    let err = errno_result(unsafe {
+        libc::open(missing.as_ptr(), libc::O_PATH | libc::O_CREAT | libc::O_WRONLY, 0o644)
+    })
+    .unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::ENOENT));
+
+    errno_check(unsafe { libc::close(pathfd) });
+}
@@ -0,0 +1,267 @@
+//@only-target: linux android
+//@revisions: host vfs
+//@[host] compile-flags: -Zmiri-disable-isolation
+//@[vfs] compile-flags: -Zmiri-vfs
+
+use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::{fs, ptr};
+
+#[path = "../../utils/mod.rs"]
+mod utils;
+
+#[path = "../../utils/libc.rs"]
+mod libc_utils;
+use libc_utils::*;
+
+fn main() {
+    let dir = prepare_dir();
+
+    test_copy_file_range(&dir);
+    test_copy_file_range_errors(&dir);
+    test_sendfile(&dir);
+    test_splice(&dir);
+    test_splice_errors(&dir);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// Creates an empty directory for the test. The in-memory file system only has `/tmp`.
+fn prepare_dir() -> PathBuf {
+    let tmp = if cfg!(vfs) { PathBuf::from("/tmp") } else { utils::tmp() };
+    let dir = tmp.join("miri_test_libc_fs_copy");
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir(&dir).unwrap();
+    dir
+}
+
+fn open(path: &Path, flags: i32) -> i32 {
+    let path = CString::new(path.as_os_str().as_bytes()).unwrap();
+    errno_result(unsafe { libc::open(path.as_ptr(), flags | libc::O_CLOEXEC, 0o644) }).unwrap()
+}
+
+fn lseek(fd: i32) -> i64 {
+    errno_result(unsafe { libc::lseek(fd, 0, libc::SEEK_CUR) }).unwrap().into()
+}
+
+fn pipe() -> [i32; 2] {
+    let mut fds = [0; 2];
+    errno_check(unsafe { libc::pipe(fds.as_mut_ptr()) });
+    fds
+}
+
+/// Repeats a transfer of up to `len` bytes until everything was moved or the input is at its
+/// end, since Miri may shorten each step. Returns the number of bytes moved.
+fn transfer_all(len: usize, mut transfer: impl FnMut(usize) -> isize) -> usize {
+    let mut moved = 0;
+    while moved < len {
+        let step = errno_result(transfer(len - moved)).unwrap();
+        if step == 0 {
+            break;
+        }
+        moved += usize::try_from(step).unwrap();
+    }
+    moved
+}
+
+/// Android has no wrapper for `copy_file_range`, so we use the syscall there.
+unsafe fn copy_file_range(
+    fd_in: i32,
+    off_in: *mut i64,
+    fd_out: i32,
+    off_out: *mut i64,
+    len: usize,
+    flags: u32,
+) -> isize {
+    #[cfg(target_os = "linux")]
+    return unsafe { libc::copy_file_range(fd_in, off_in, fd_out, off_out, len, flags) };
+    #[cfg(target_os = "android")]
+    return unsafe {
+        libc::syscall(libc::SYS_copy_file_range, fd_in, off_in, fd_out, off_out, len, flags)
+    } as isize;
+}
+
+fn test_copy_file_range(dir: &Path) {
+    let source = dir.join("copy_file_range_source");
+    let target = dir.join("copy_file_range_target");
+    fs::write(&source, b"0123456789").unwrap();
+    let fd_in = open(&source, libc::O_RDONLY);
+    let fd_out = open(&target, libc::O_WRONLY | libc::O_CREAT | libc::O_TRUNC);
+
+    // Without offsets, the file positions are used and advanced.
+    let copied = transfer_all(4, |len| unsafe {
+        copy_file_range(fd_in, ptr::null_mut(), fd_out, ptr::null_mut(), len, 0)
+    });
+    assert_eq!(copied, 4);
+    assert_eq!(lseek(fd_in), 4);
+    assert_eq!(lseek(fd_out), 4);
+
+    // With offsets, those are used and advanced instead.
+    let mut off_in = 8;
+    let mut off_out = 20;
+    let copied = transfer_all(100, |len| unsafe {
+        copy_file_range(fd_in, &mut off_in, fd_out, &mut off_out, len, 0)
+    });
+    assert_eq!(copied, 2);
+    assert_eq!((off_in, off_out), (10, 22));
+    assert_eq!(lseek(fd_in), 4);
+    assert_eq!(lseek(fd_out), 4);
+
+    // At the end of the input, nothing is copied.
+    let copied = errno_result(unsafe {
+        copy_file_range(fd_in, &mut off_in, fd_out, ptr::null_mut(), 100, 0)
+    })
+    .unwrap();
+    assert_eq!(copied, 0);
+
+    // Copy the rest.
+    let copied = transfer_all(100, |len| unsafe {
+        copy_file_range(fd_in, ptr::null_mut(), fd_out, ptr::null_mut(), len, 0)
+    });
+    assert_eq!(copied, 6);
+    assert_eq!(fs::read(&target).unwrap(), b"0123456789\0\0\0\0\0\0\0\0\0\089");
+
+    errno_check(unsafe { libc::close(fd_in) });
+    errno_check(unsafe { libc::close(fd_out) });
+}
+
+fn test_copy_file_range_errors(dir: &Path) {
+    let file = dir.join("copy_file_range_errors");
+    fs::write(&file, b"0123456789").unwrap();
+    let fd = open(&file, libc::O_RDWR);
+    let read_only = open(&file, libc::O_RDONLY);
+    let [read_end, write_end] = pipe();
+
+    let check_error = |fd_in, off_in: *mut i64, fd_out, off_out: *mut i64, len, flags, errno| {
+        let err =
+            errno_result(unsafe { copy_file_range(fd_in, off_in, fd_out, off_out, len, flags) })
+                .unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(errno));
+    };
+    // std probes for `copy_file_range` support with invalid file descriptors.
+    check_error(-1, ptr::null_mut(), -1, ptr::null_mut(), 1, 0, libc::EBADF);
+    check_error(fd, ptr::null_mut(), read_only, ptr::null_mut(), 1, 0, libc::EBADF);
+    check_error(fd, ptr::null_mut(), fd, ptr::null_mut(), 1, 1, libc::EINVAL);
+    // Only regular files are supported.
+    check_error(read_end, ptr::null_mut(), fd, ptr::null_mut(), 1, 0, libc::EINVAL);
+    check_error(fd, ptr::null_mut(), write_end, ptr::null_mut(), 1, 0, libc::EINVAL);
+    let mut negative = -1;
+    check_error(fd, &mut negative, fd, ptr::null_mut(), 1, 0, libc::EOVERFLOW);
+    // Within a file, the ranges must not overlap.
+    let (mut off_in, mut off_out) = (0, 4);
+    check_error(fd, &mut off_in, fd, &mut off_out, 5, 0, libc::EINVAL);
+    let copied = transfer_all(4, |len| unsafe {
+        copy_file_range(fd, &mut off_in, fd, &mut off_out, len, 0)
+    });
+    assert_eq!(copied, 4);
+    assert_eq!(fs::read(&file).unwrap(), b"0123012389");
+
+    for fd in [fd, read_only, read_end, write_end] {
+        errno_check(unsafe { libc::close(fd) });
+    }
+}
+
+fn test_sendfile(dir: &Path) {
+    let source = dir.join("sendfile_source");
+    fs::write(&source, b"0123456789").unwrap();
+    let fd_in = open(&source, libc::O_RDONLY);
+    let [read_end, write_end] = pipe();
+
+    // Without an offset, the file position is used and advanced.
+    let sent =
+        transfer_all(4, |len| unsafe { libc::sendfile(write_end, fd_in, ptr::null_mut(), len) });
+    assert_eq!(sent, 4);
+    assert_eq!(lseek(fd_in), 4);
+    // With an offset, that is used and advanced instead.
+    let mut offset: libc::off_t = 7;
+    let sent =
+        transfer_all(100, |len| unsafe { libc::sendfile(write_end, fd_in, &mut offset, len) });
+    assert_eq!(sent, 3);
+    assert_eq!(offset, 10);
+    assert_eq!(lseek(fd_in), 4);
+    assert_eq!(read_exact_array::<7>(read_end).unwrap(), *b"0123789");
+
+    // The input has to be a file.
+    let err = errno_result(unsafe { libc::sendfile(write_end, read_end, ptr::null_mut(), 1) })
+        .unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EINVAL));
+    let err =
+        errno_result(unsafe { libc::sendfile(write_end, -1, ptr::null_mut(), 1) }).unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EBADF));
+    let mut negative: libc::off_t = -1;
+    let err =
+        errno_result(unsafe { libc::sendfile(write_end, fd_in, &mut negative, 1) }).unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EINVAL));
+
+    for fd in [fd_in, read_end, write_end] {
+        errno_check(unsafe { libc::close(fd) });
+    }
+}
+
+fn test_splice(dir: &Path) {
+    let source = dir.join("splice_source");
+    let target = dir.join("splice_target");
+    fs::write(&source, b"0123456789").unwrap();
+    let fd_in = open(&source, libc::O_RDONLY);
+    let fd_out = open(&target, libc::O_WRONLY | libc::O_CREAT | libc::O_TRUNC);
+    let [read_end, write_end] = pipe();
+
+    // From a file into a pipe.
+    let mut offset = 2;
+    let moved = transfer_all(5, |len| unsafe {
+        libc::splice(fd_in, &mut offset, write_end, ptr::null_mut(), len, libc::SPLICE_F_MOVE)
+    });
+    assert_eq!(moved, 5);
+    assert_eq!(offset, 7);
+    assert_eq!(lseek(fd_in), 0);
+
+    // From the pipe into a file. The data read from the pipe all gets written, even if that
+    // takes several writes.
+    let moved = errno_result(unsafe {
+        libc::splice(read_end, ptr::null_mut(), fd_out, ptr::null_mut(), 100, 0)
+    })
+    .unwrap();
+    assert_eq!(moved, 5);
+    assert_eq!(lseek(fd_out), 5);
+    assert_eq!(fs::read(&target).unwrap(), b"23456");
+
+    // Once the writing end is closed, splicing from the pipe finds the end of the data.
+    errno_check(unsafe { libc::close(write_end) });
+    let moved = errno_result(unsafe {
+        libc::splice(read_end, ptr::null_mut(), fd_out, ptr::null_mut(), 100, 0)
+    })
+    .unwrap();
+    assert_eq!(moved, 0);
+
+    for fd in [fd_in, fd_out, read_end] {
+        errno_check(unsafe { libc::close(fd) });
+    }
+}
+
+fn test_splice_errors(dir: &Path) {
+    let file = dir.join("splice_errors");
+    fs::write(&file, b"0123456789").unwrap();
+    let fd = open(&file, libc::O_RDWR);
+    let [read_end, write_end] = pipe();
+
+    let check_error = |fd_in, off_in: *mut i64, fd_out, off_out: *mut i64, flags, errno| {
+        let err = errno_result(unsafe { libc::splice(fd_in, off_in, fd_out, off_out, 1, flags) })
+            .unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(errno));
+    };
+    // One side has to be a pipe.
+    check_error(fd, ptr::null_mut(), fd, ptr::null_mut(), 0, libc::EINVAL);
+    check_error(-1, ptr::null_mut(), write_end, ptr::null_mut(), 0, libc::EBADF);
+    // Pipes do not have offsets.
+    let mut offset = 0;
+    check_error(read_end, &mut offset, fd, ptr::null_mut(), 0, libc::ESPIPE);
+    check_error(fd, ptr::null_mut(), write_end, &mut offset, 0, libc::ESPIPE);
+    let mut negative = -1;
+    check_error(fd, &mut negative, write_end, ptr::null_mut(), 0, libc::EINVAL);
+    check_error(fd, ptr::null_mut(), write_end, ptr::null_mut(), 0x100, libc::EINVAL);
+
+    for fd in [fd, read_end, write_end] {
+        errno_check(unsafe { libc::close(fd) });
+    }
+}
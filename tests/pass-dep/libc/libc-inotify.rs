@@ -0,0 +1,302 @@
+//@only-target: linux android
+//@revisions: host vfs
+//@[host] compile-flags: -Zmiri-disable-isolation
+//@[vfs] compile-flags: -Zmiri-vfs
+
+use std::ffi::CString;
+use std::fs::{self, File};
+use std::io::Write;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::{mem, thread};
+
+#[path = "../../utils/mod.rs"]
+mod utils;
+
+#[path = "../../utils/libc.rs"]
+mod libc_utils;
+use libc_utils::epoll::*;
+use libc_utils::*;
+
+const HEADER_SIZE: usize = mem::size_of::<libc::inotify_event>();
+
+fn main() {
+    let dir = prepare_dir();
+
+    test_create_modify_delete(&dir);
+    test_rename(&dir);
+    test_delete_self(&dir);
+    test_attrib(&dir);
+    test_oneshot(&dir);
+    test_rm_watch(&dir);
+    test_epoll(&dir);
+    test_blocking_read(&dir);
+    test_errors(&dir);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// Creates an empty directory for the test. The in-memory file system only has `/tmp`.
+fn prepare_dir() -> PathBuf {
+    let tmp = if cfg!(vfs) { PathBuf::from("/tmp") } else { utils::tmp() };
+    let dir = tmp.join("miri_test_libc_inotify");
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir(&dir).unwrap();
+    dir
+}
+
+fn init(flags: i32) -> i32 {
+    errno_result(unsafe { libc::inotify_init1(flags) }).unwrap()
+}
+
+fn add_watch(fd: i32, path: &Path, mask: u32) -> std::io::Result<i32> {
+    let path = CString::new(path.as_os_str().as_bytes()).unwrap();
+    errno_result(unsafe { libc::inotify_add_watch(fd, path.as_ptr(), mask) })
+}
+
+fn rm_watch(fd: i32, wd: i32) -> std::io::Result<i32> {
+    // Android takes the watch descriptor as `u32`.
+    #[allow(clippy::useless_conversion)]
+    errno_result(unsafe { libc::inotify_rm_watch(fd, wd.try_into().unwrap()) })
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Event {
+    wd: i32,
+    mask: u32,
+    cookie: u32,
+    name: String,
+}
+
+fn event(wd: i32, mask: u32, name: &str) -> Event {
+    Event { wd, mask, cookie: 0, name: name.to_owned() }
+}
+
+/// Reads all queued events.
+fn read_events(fd: i32) -> std::io::Result<Vec<Event>> {
+    #[repr(C, align(8))]
+    struct Buf([u8; 1024]);
+    let mut buf = Buf([0; 1024]);
+    let len = errno_result(unsafe { libc::read(fd, buf.0.as_mut_ptr().cast(), buf.0.len()) })?;
+    let buf = &buf.0[..usize::try_from(len).unwrap()];
+
+    let mut events = Vec::new();
+    let mut offset = 0;
+    while offset < buf.len() {
+        let header =
+            unsafe { buf[offset..].as_ptr().cast::<libc::inotify_event>().read_unaligned() };
+        let name_start = offset + HEADER_SIZE;
+        let name_len = usize::try_from(header.len).unwrap();
+        assert_eq!(name_len % HEADER_SIZE, 0, "names are padded");
+        let name = &buf[name_start..name_start + name_len];
+        let name = name.split(|&b| b == 0).next().unwrap();
+        events.push(Event {
+            wd: header.wd,
+            mask: header.mask,
+            cookie: header.cookie,
+            name: String::from_utf8(name.to_vec()).unwrap(),
+        });
+        offset = name_start + name_len;
+    }
+    Ok(events)
+}
+
+fn assert_no_events(fd: i32) {
+    let err = read_events(fd).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+}
+
+fn test_create_modify_delete(dir: &Path) {
+    let fd = init(libc::IN_NONBLOCK | libc::IN_CLOEXEC);
+    let mask = libc::IN_CREATE | libc::IN_MODIFY | libc::IN_DELETE;
+    let wd = add_watch(fd, dir, mask).unwrap();
+    assert_no_events(fd);
+
+    let path = dir.join("file");
+    let mut file = File::create(&path).unwrap();
+    // Consecutive identical events are merged.
+    file.write_all(b"hello").unwrap();
+    file.write_all(b"world").unwrap();
+    drop(file);
+    fs::remove_file(&path).unwrap();
+    fs::create_dir(dir.join("sub")).unwrap();
+    fs::remove_dir(dir.join("sub")).unwrap();
+    assert_eq!(
+        read_events(fd).unwrap(),
+        [
+            event(wd, libc::IN_CREATE, "file"),
+            event(wd, libc::IN_MODIFY, "file"),
+            event(wd, libc::IN_DELETE, "file"),
+            event(wd, libc::IN_CREATE | libc::IN_ISDIR, "sub"),
+            event(wd, libc::IN_DELETE | libc::IN_ISDIR, "sub"),
+        ]
+    );
+    assert_no_events(fd);
+
+    errno_check(unsafe { libc::close(fd) });
+}
+
+fn test_rename(dir: &Path) {
+    let fd = init(libc::IN_NONBLOCK);
+    let from = dir.join("from");
+    let to = dir.join("to");
+    fs::write(&from, b"hello").unwrap();
+    let dir_wd = add_watch(fd, dir, libc::IN_MOVE).unwrap();
+    let file_wd = add_watch(fd, &from, libc::IN_MOVE_SELF | libc::IN_MODIFY).unwrap();
+
+    fs::rename(&from, &to).unwrap();
+    let events = read_events(fd).unwrap();
+    assert_eq!(events.len(), 3);
+    let cookie = events[0].cookie;
+    assert_ne!(cookie, 0);
+    assert_eq!(
+        events,
+        [
+            Event { wd: dir_wd, mask: libc::IN_MOVED_FROM, cookie, name: "from".to_owned() },
+            Event { wd: dir_wd, mask: libc::IN_MOVED_TO, cookie, name: "to".to_owned() },
+            event(file_wd, libc::IN_MOVE_SELF, ""),
+        ]
+    );
+
+    // The watch follows the file to its new name.
+    fs::write(&to, b"world").unwrap();
+    assert_eq!(read_events(fd).unwrap(), [event(file_wd, libc::IN_MODIFY, "")]);
+    assert_eq!(add_watch(fd, &to, libc::IN_MODIFY).unwrap(), file_wd);
+
+    fs::remove_file(&to).unwrap();
+    errno_check(unsafe { libc::close(fd) });
+}
+
+fn test_delete_self(dir: &Path) {
+    let fd = init(libc::IN_NONBLOCK);
+    let path = dir.join("deleted");
+    fs::write(&path, b"hello").unwrap();
+    let wd = add_watch(fd, &path, libc::IN_DELETE_SELF).unwrap();
+
+    fs::remove_file(&path).unwrap();
+    // The watch is removed together with the file.
+    assert_eq!(
+        read_events(fd).unwrap(),
+        [event(wd, libc::IN_DELETE_SELF, ""), event(wd, libc::IN_IGNORED, ""),]
+    );
+    let err = rm_watch(fd, wd).unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EINVAL));
+
+    errno_check(unsafe { libc::close(fd) });
+}
+
+fn test_attrib(dir: &Path) {
+    let fd = init(libc::IN_NONBLOCK);
+    let path = dir.join("attrib");
+    fs::write(&path, b"hello").unwrap();
+    let dir_wd = add_watch(fd, dir, libc::IN_ATTRIB).unwrap();
+    let file_wd = add_watch(fd, &path, libc::IN_ATTRIB).unwrap();
+
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+    assert_eq!(
+        read_events(fd).unwrap(),
+        [event(dir_wd, libc::IN_ATTRIB, "attrib"), event(file_wd, libc::IN_ATTRIB, ""),]
+    );
+
+    fs::remove_file(&path).unwrap();
+    errno_check(unsafe { libc::close(fd) });
+}
+
+fn test_oneshot(dir: &Path) {
+    let fd = init(libc::IN_NONBLOCK);
+    let path = dir.join("oneshot");
+    fs::write(&path, b"hello").unwrap();
+    let wd = add_watch(fd, &path, libc::IN_MODIFY | libc::IN_ONESHOT).unwrap();
+
+    fs::write(&path, b"hello").unwrap();
+    assert_eq!(
+        read_events(fd).unwrap(),
+        [event(wd, libc::IN_MODIFY, ""), event(wd, libc::IN_IGNORED, ""),]
+    );
+    fs::write(&path, b"hello").unwrap();
+    assert_no_events(fd);
+
+    fs::remove_file(&path).unwrap();
+    errno_check(unsafe { libc::close(fd) });
+}
+
+fn test_rm_watch(dir: &Path) {
+    let fd = init(libc::IN_NONBLOCK);
+    let wd = add_watch(fd, dir, libc::IN_CREATE).unwrap();
+    // Adding the same path again changes the mask of the existing watch.
+    assert_eq!(add_watch(fd, dir, libc::IN_DELETE | libc::IN_MASK_ADD).unwrap(), wd);
+    let err = add_watch(fd, dir, libc::IN_DELETE | libc::IN_MASK_CREATE).unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EEXIST));
+
+    rm_watch(fd, wd).unwrap();
+    assert_eq!(read_events(fd).unwrap(), [event(wd, libc::IN_IGNORED, "")]);
+    let err = rm_watch(fd, wd).unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EINVAL));
+
+    // Watch descriptors are not reused.
+    assert_ne!(add_watch(fd, dir, libc::IN_CREATE).unwrap(), wd);
+
+    errno_check(unsafe { libc::close(fd) });
+}
+
+fn test_epoll(dir: &Path) {
+    let epfd = errno_result(unsafe { libc::epoll_create1(0) }).unwrap();
+    let fd = init(libc::IN_NONBLOCK);
+    add_watch(fd, dir, libc::IN_CREATE).unwrap();
+    epoll_ctl_add(epfd, fd, EPOLLIN | EPOLLOUT).unwrap();
+    check_epoll_wait_noblock(epfd, &[]);
+
+    let path = dir.join("epoll");
+    fs::write(&path, b"hello").unwrap();
+    check_epoll_wait_noblock(epfd, &[Ev { events: EPOLLIN, data: fd }]);
+    read_events(fd).unwrap();
+    check_epoll_wait_noblock(epfd, &[]);
+
+    fs::remove_file(&path).unwrap();
+    errno_check(unsafe { libc::close(fd) });
+    errno_check(unsafe { libc::close(epfd) });
+}
+
+fn test_blocking_read(dir: &Path) {
+    let fd = init(0);
+    let wd = add_watch(fd, dir, libc::IN_CREATE).unwrap();
+    let reader = thread::spawn(move || read_events(fd).unwrap());
+    let path = dir.join("blocking");
+    fs::write(&path, b"hello").unwrap();
+    assert_eq!(reader.join().unwrap(), [event(wd, libc::IN_CREATE, "blocking")]);
+
+    fs::remove_file(&path).unwrap();
+    errno_check(unsafe { libc::close(fd) });
+}
+
+fn test_errors(dir: &Path) {
+    let err = errno_result(unsafe { libc::inotify_init1(-1) }).unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EINVAL));
+
+    let fd = errno_result(unsafe { libc::inotify_init() }).unwrap();
+    let err = add_watch(fd, &dir.join("missing"), libc::IN_CREATE).unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::ENOENT));
+    let err = add_watch(fd, dir, 0).unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EINVAL));
+
+    let path = dir.join("not_a_dir");
+    fs::write(&path, b"hello").unwrap();
+    let err = add_watch(fd, &path, libc::IN_CREATE | libc::IN_ONLYDIR).unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::ENOTDIR));
+
+    // The buffer must fit at least the first event.
+    add_watch(fd, dir, libc::IN_DELETE).unwrap();
+    fs::remove_file(&path).unwrap();
+    let mut buf = [0u8; HEADER_SIZE];
+    let err =
+        errno_result(unsafe { libc::read(fd, buf.as_mut_ptr().cast(), buf.len()) }).unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EINVAL));
+    errno_check(unsafe { libc::close(fd) });
+
+    // Not an inotify instance.
+    let efd = errno_result(unsafe { libc::eventfd(0, 0) }).unwrap();
+    let err = add_watch(efd, dir, libc::IN_CREATE).unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EINVAL));
+    errno_check(unsafe { libc::close(efd) });
+}
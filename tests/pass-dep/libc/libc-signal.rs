@@ -0,0 +1,240 @@
+//@ignore-target: windows # No signals on Windows
+//@run-native
+
+use std::mem::MaybeUninit;
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicUsize, Ordering};
+use std::{ptr, thread};
+
+static CALLS: AtomicUsize = AtomicUsize::new(0);
+static LAST_SIGNAL: AtomicI32 = AtomicI32::new(0);
+
+extern "C" fn count_handler(signum: libc::c_int) {
+    CALLS.fetch_add(1, Ordering::Relaxed);
+    LAST_SIGNAL.store(signum, Ordering::Relaxed);
+}
+
+fn install(signum: libc::c_int, handler: libc::sighandler_t, flags: libc::c_int) {
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = handler;
+        action.sa_flags = flags;
+        libc::sigemptyset(&mut action.sa_mask);
+        assert_eq!(libc::sigaction(signum, &action, ptr::null_mut()), 0);
+    }
+}
+
+fn current_mask() -> libc::sigset_t {
+    unsafe {
+        let mut mask = MaybeUninit::uninit();
+        assert_eq!(libc::pthread_sigmask(libc::SIG_BLOCK, ptr::null(), mask.as_mut_ptr()), 0);
+        mask.assume_init()
+    }
+}
+
+fn set_of(signums: &[libc::c_int]) -> libc::sigset_t {
+    unsafe {
+        let mut set = MaybeUninit::uninit();
+        libc::sigemptyset(set.as_mut_ptr());
+        for &signum in signums {
+            libc::sigaddset(set.as_mut_ptr(), signum);
+        }
+        set.assume_init()
+    }
+}
+
+fn test_sigset() {
+    unsafe {
+        let mut set = set_of(&[libc::SIGUSR1]);
+        assert_eq!(libc::sigismember(&set, libc::SIGUSR1), 1);
+        assert_eq!(libc::sigismember(&set, libc::SIGUSR2), 0);
+        libc::sigdelset(&mut set, libc::SIGUSR1);
+        assert_eq!(libc::sigismember(&set, libc::SIGUSR1), 0);
+        libc::sigfillset(&mut set);
+        assert_eq!(libc::sigismember(&set, libc::SIGTERM), 1);
+        assert_eq!(libc::sigaddset(&mut set, 0), -1);
+        assert_eq!(std::io::Error::last_os_error().raw_os_error(), Some(libc::EINVAL));
+    }
+}
+
+fn test_raise() {
+    CALLS.store(0, Ordering::Relaxed);
+    // Miri does not guarantee that the same function always has the same address, so we cast
+    // only once.
+    let handler = count_handler as *const () as libc::sighandler_t;
+    install(libc::SIGUSR1, handler, 0);
+    // The handler runs before `raise` returns.
+    assert_eq!(unsafe { libc::raise(libc::SIGUSR1) }, 0);
+    assert_eq!(CALLS.load(Ordering::Relaxed), 1);
+    assert_eq!(LAST_SIGNAL.load(Ordering::Relaxed), libc::SIGUSR1);
+
+    // Same for `kill` on our own process.
+    assert_eq!(unsafe { libc::kill(libc::getpid(), libc::SIGUSR1) }, 0);
+    assert_eq!(CALLS.load(Ordering::Relaxed), 2);
+    assert_eq!(unsafe { libc::kill(libc::getpid(), 0) }, 0);
+
+    // Reading back the disposition.
+    let mut old: libc::sigaction = unsafe { std::mem::zeroed() };
+    assert_eq!(unsafe { libc::sigaction(libc::SIGUSR1, ptr::null(), &mut old) }, 0);
+    assert_eq!(old.sa_sigaction, handler);
+
+    // `signal` returns the previous handler.
+    let prev = unsafe { libc::signal(libc::SIGUSR1, libc::SIG_IGN) };
+    assert_eq!(prev, handler);
+    unsafe { libc::raise(libc::SIGUSR1) };
+    assert_eq!(CALLS.load(Ordering::Relaxed), 2);
+    assert_eq!(unsafe { libc::signal(libc::SIGUSR1, libc::SIG_DFL) }, libc::SIG_IGN);
+
+    // Signals that are ignored by default do nothing.
+    unsafe { libc::raise(libc::SIGCHLD) };
+
+    // `SIGKILL` cannot be caught.
+    install_fails(libc::SIGKILL);
+}
+
+fn install_fails(signum: libc::c_int) {
+    unsafe {
+        let action: libc::sigaction = std::mem::zeroed();
+        assert_eq!(libc::sigaction(signum, &action, ptr::null_mut()), -1);
+        assert_eq!(std::io::Error::last_os_error().raw_os_error(), Some(libc::EINVAL));
+    }
+}
+
+fn test_mask() {
+    CALLS.store(0, Ordering::Relaxed);
+    install(libc::SIGUSR1, count_handler as *const () as libc::sighandler_t, 0);
+    let set = set_of(&[libc::SIGUSR1]);
+    unsafe {
+        assert_eq!(libc::sigprocmask(libc::SIG_BLOCK, &set, ptr::null_mut()), 0);
+        libc::raise(libc::SIGUSR1);
+        // Blocked signals stay pending.
+        assert_eq!(CALLS.load(Ordering::Relaxed), 0);
+        let mut pending = MaybeUninit::uninit();
+        assert_eq!(libc::sigpending(pending.as_mut_ptr()), 0);
+        assert_eq!(libc::sigismember(pending.as_ptr(), libc::SIGUSR1), 1);
+
+        // Unblocking delivers the signal before `sigprocmask` returns.
+        assert_eq!(libc::sigprocmask(libc::SIG_UNBLOCK, &set, ptr::null_mut()), 0);
+        assert_eq!(CALLS.load(Ordering::Relaxed), 1);
+        assert_eq!(libc::sigismember(&current_mask(), libc::SIGUSR1), 0);
+
+        assert_eq!(libc::pthread_sigmask(-1, &set, ptr::null_mut()), libc::EINVAL);
+    }
+}
+
+static HANDLER_MASKED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn check_mask_handler(signum: libc::c_int) {
+    // While the handler runs, its own signal is blocked.
+    let mask = current_mask();
+    HANDLER_MASKED.store(unsafe { libc::sigismember(&mask, signum) } == 1, Ordering::Relaxed);
+}
+
+fn test_handler_mask() {
+    install(libc::SIGUSR2, check_mask_handler as *const () as libc::sighandler_t, 0);
+    unsafe { libc::raise(libc::SIGUSR2) };
+    assert!(HANDLER_MASKED.load(Ordering::Relaxed));
+    // The mask is restored afterwards.
+    assert_eq!(unsafe { libc::sigismember(&current_mask(), libc::SIGUSR2) }, 0);
+
+    // With `SA_NODEFER`, the signal is not blocked.
+    install(libc::SIGUSR2, check_mask_handler as *const () as libc::sighandler_t, libc::SA_NODEFER);
+    unsafe { libc::raise(libc::SIGUSR2) };
+    assert!(!HANDLER_MASKED.load(Ordering::Relaxed));
+}
+
+static SIGINFO_SIGNAL: AtomicI32 = AtomicI32::new(0);
+
+extern "C" fn siginfo_handler(
+    signum: libc::c_int,
+    info: *mut libc::siginfo_t,
+    _context: *mut libc::c_void,
+) {
+    let info = unsafe { &*info };
+    assert_eq!(info.si_signo, signum);
+    SIGINFO_SIGNAL.store(signum, Ordering::Relaxed);
+}
+
+fn test_siginfo() {
+    install(libc::SIGUSR1, siginfo_handler as *const () as libc::sighandler_t, libc::SA_SIGINFO);
+    unsafe { libc::raise(libc::SIGUSR1) };
+    assert_eq!(SIGINFO_SIGNAL.load(Ordering::Relaxed), libc::SIGUSR1);
+}
+
+fn test_resethand() {
+    CALLS.store(0, Ordering::Relaxed);
+    install(libc::SIGUSR1, count_handler as *const () as libc::sighandler_t, libc::SA_RESETHAND);
+    unsafe { libc::raise(libc::SIGUSR1) };
+    assert_eq!(CALLS.load(Ordering::Relaxed), 1);
+    let mut old: libc::sigaction = unsafe { std::mem::zeroed() };
+    unsafe { libc::sigaction(libc::SIGUSR1, ptr::null(), &mut old) };
+    assert_eq!(old.sa_sigaction, libc::SIG_DFL);
+}
+
+static ON_ALT_STACK: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn alt_stack_handler(_signum: libc::c_int) {
+    let mut current: libc::stack_t = unsafe { std::mem::zeroed() };
+    unsafe { libc::sigaltstack(ptr::null(), &mut current) };
+    ON_ALT_STACK.store(current.ss_flags & libc::SS_ONSTACK != 0, Ordering::Relaxed);
+}
+
+fn test_sigaltstack() {
+    let mut memory = vec![0u8; libc::SIGSTKSZ.max(64 * 1024)];
+    unsafe {
+        // Natively, std may already have installed an alternate stack for the main thread.
+        let mut original: libc::stack_t = std::mem::zeroed();
+        assert_eq!(libc::sigaltstack(ptr::null(), &mut original), 0);
+
+        let mut old: libc::stack_t = std::mem::zeroed();
+        let mut stack: libc::stack_t = std::mem::zeroed();
+        stack.ss_sp = memory.as_mut_ptr().cast();
+        stack.ss_size = memory.len();
+        assert_eq!(libc::sigaltstack(&stack, ptr::null_mut()), 0);
+        assert_eq!(libc::sigaltstack(ptr::null(), &mut old), 0);
+        assert_eq!(old.ss_sp, stack.ss_sp);
+        assert_eq!(old.ss_size, stack.ss_size);
+        assert_eq!(old.ss_flags, 0);
+
+        install(
+            libc::SIGUSR1,
+            alt_stack_handler as *const () as libc::sighandler_t,
+            libc::SA_ONSTACK,
+        );
+        libc::raise(libc::SIGUSR1);
+        assert!(ON_ALT_STACK.load(Ordering::Relaxed));
+
+        assert_eq!(libc::sigaltstack(&original, ptr::null_mut()), 0);
+    }
+}
+
+static HANDLED_ON: AtomicUsize = AtomicUsize::new(0);
+
+extern "C" fn thread_handler(_signum: libc::c_int) {
+    HANDLED_ON.store(unsafe { libc::pthread_self() } as usize, Ordering::Relaxed);
+}
+
+fn test_pthread_kill() {
+    install(libc::SIGUSR2, thread_handler as *const () as libc::sighandler_t, 0);
+    let handle = thread::spawn(|| {
+        while HANDLED_ON.load(Ordering::Relaxed) == 0 {
+            thread::yield_now();
+        }
+        (unsafe { libc::pthread_self() }) as usize
+    });
+    let target = std::os::unix::thread::JoinHandleExt::as_pthread_t(&handle);
+    assert_eq!(unsafe { libc::pthread_kill(target, libc::SIGUSR2) }, 0);
+    let target = handle.join().unwrap();
+    // The handler ran on the thread the signal was sent to.
+    assert_eq!(HANDLED_ON.load(Ordering::Relaxed), target);
+}
+
+fn main() {
+    test_sigset();
+    test_raise();
+    test_mask();
+    test_handler_mask();
+    test_siginfo();
+    test_resethand();
+    test_sigaltstack();
+    test_pthread_kill();
+}
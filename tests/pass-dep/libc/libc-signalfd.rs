@@ -0,0 +1,146 @@
+//@only-target: linux android
+//@run-native
+
+use std::mem::{self, MaybeUninit};
+use std::{ptr, thread};
+
+#[path = "../../utils/libc.rs"]
+mod libc_utils;
+use libc_utils::epoll::*;
+use libc_utils::*;
+
+const SIGINFO_SIZE: usize = mem::size_of::<libc::signalfd_siginfo>();
+
+fn main() {
+    // The signals we read must be blocked, otherwise they get delivered as usual. Threads
+    // spawned below inherit this mask.
+    let set = set_of(&[libc::SIGUSR1, libc::SIGUSR2]);
+    errno_check(unsafe { libc::pthread_sigmask(libc::SIG_BLOCK, &set, ptr::null_mut()) });
+
+    test_read();
+    test_read_multiple();
+    test_change_mask();
+    test_epoll();
+    test_blocking_read();
+    test_errors();
+}
+
+fn set_of(signums: &[libc::c_int]) -> libc::sigset_t {
+    unsafe {
+        let mut set = MaybeUninit::uninit();
+        libc::sigemptyset(set.as_mut_ptr());
+        for &signum in signums {
+            libc::sigaddset(set.as_mut_ptr(), signum);
+        }
+        set.assume_init()
+    }
+}
+
+fn signalfd(fd: i32, signums: &[libc::c_int], flags: i32) -> std::io::Result<i32> {
+    let set = set_of(signums);
+    errno_result(unsafe { libc::signalfd(fd, &set, flags) })
+}
+
+/// Reads at most `N` signals from `fd`.
+fn read_signals<const N: usize>(fd: i32) -> std::io::Result<Vec<libc::signalfd_siginfo>> {
+    let mut infos = [const { MaybeUninit::<libc::signalfd_siginfo>::uninit() }; N];
+    let len = errno_result(unsafe {
+        libc::read(fd, infos.as_mut_ptr().cast(), mem::size_of_val(&infos))
+    })?;
+    let len = usize::try_from(len).unwrap();
+    assert_eq!(len % SIGINFO_SIZE, 0);
+    Ok(infos[..len / SIGINFO_SIZE].iter().map(|info| unsafe { info.assume_init() }).collect())
+}
+
+fn test_read() {
+    let fd = signalfd(-1, &[libc::SIGUSR1], libc::SFD_NONBLOCK | libc::SFD_CLOEXEC).unwrap();
+    let err = read_signals::<1>(fd).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+
+    errno_check(unsafe { libc::kill(libc::getpid(), libc::SIGUSR1) });
+    let infos = read_signals::<1>(fd).unwrap();
+    assert_eq!(infos.len(), 1);
+    assert_eq!(infos[0].ssi_signo, libc::SIGUSR1 as u32);
+    assert_eq!(infos[0].ssi_code, 0); // SI_USER
+    assert_eq!(infos[0].ssi_pid, std::process::id());
+
+    // The signal was accepted, so it is no longer pending.
+    let err = read_signals::<1>(fd).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+    errno_check(unsafe { libc::close(fd) });
+}
+
+fn test_read_multiple() {
+    let fd = signalfd(-1, &[libc::SIGUSR1, libc::SIGUSR2], libc::SFD_NONBLOCK).unwrap();
+    errno_check(unsafe { libc::raise(libc::SIGUSR2) });
+    errno_check(unsafe { libc::kill(libc::getpid(), libc::SIGUSR1) });
+
+    // As many signals are accepted as fit into the buffer. Signals directed at the thread come
+    // first, even though their number is higher.
+    let infos = read_signals::<4>(fd).unwrap();
+    let signums = infos.iter().map(|info| info.ssi_signo).collect::<Vec<_>>();
+    assert_eq!(signums, [libc::SIGUSR2 as u32, libc::SIGUSR1 as u32]);
+    errno_check(unsafe { libc::close(fd) });
+}
+
+fn test_change_mask() {
+    let fd = signalfd(-1, &[libc::SIGUSR1], libc::SFD_NONBLOCK).unwrap();
+    errno_check(unsafe { libc::raise(libc::SIGUSR2) });
+    let err = read_signals::<1>(fd).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+
+    // Changing the mask of an existing signalfd returns the same file descriptor.
+    assert_eq!(signalfd(fd, &[libc::SIGUSR2], 0).unwrap(), fd);
+    let infos = read_signals::<1>(fd).unwrap();
+    assert_eq!(infos[0].ssi_signo, libc::SIGUSR2 as u32);
+    errno_check(unsafe { libc::close(fd) });
+}
+
+fn test_epoll() {
+    let epfd = errno_result(unsafe { libc::epoll_create1(0) }).unwrap();
+    let fd = signalfd(-1, &[libc::SIGUSR1], libc::SFD_NONBLOCK).unwrap();
+    epoll_ctl_add(epfd, fd, EPOLLIN | EPOLLOUT).unwrap();
+    check_epoll_wait_noblock(epfd, &[]);
+
+    errno_check(unsafe { libc::raise(libc::SIGUSR1) });
+    check_epoll_wait_noblock(epfd, &[Ev { events: EPOLLIN, data: fd }]);
+    read_signals::<1>(fd).unwrap();
+    check_epoll_wait_noblock(epfd, &[]);
+
+    errno_check(unsafe { libc::close(fd) });
+    errno_check(unsafe { libc::close(epfd) });
+}
+
+fn test_blocking_read() {
+    let fd = signalfd(-1, &[libc::SIGUSR1], 0).unwrap();
+    let reader = thread::spawn(move || read_signals::<1>(fd).unwrap());
+    // A signal sent to the process can be accepted by any thread.
+    errno_check(unsafe { libc::kill(libc::getpid(), libc::SIGUSR1) });
+    let infos = reader.join().unwrap();
+    assert_eq!(infos[0].ssi_signo, libc::SIGUSR1 as u32);
+    errno_check(unsafe { libc::close(fd) });
+}
+
+fn test_errors() {
+    let fd = signalfd(-1, &[libc::SIGUSR1], libc::SFD_NONBLOCK).unwrap();
+    // The buffer must fit at least one `signalfd_siginfo`.
+    let mut buf = [0u8; 8];
+    let err =
+        errno_result(unsafe { libc::read(fd, buf.as_mut_ptr().cast(), buf.len()) }).unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EINVAL));
+    errno_check(unsafe { libc::close(fd) });
+
+    // Unknown flags.
+    let err = signalfd(-1, &[libc::SIGUSR1], -1).unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EINVAL));
+
+    // Not a signalfd.
+    let efd = errno_result(unsafe { libc::eventfd(0, 0) }).unwrap();
+    let err = signalfd(efd, &[libc::SIGUSR1], 0).unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EINVAL));
+    errno_check(unsafe { libc::close(efd) });
+
+    // Closed file descriptor.
+    let err = signalfd(efd, &[libc::SIGUSR1], 0).unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EBADF));
+}
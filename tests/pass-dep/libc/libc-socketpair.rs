@@ -20,6 +20,8 @@ fn main() {
     test_blocking_read();
     test_blocking_write();
     test_unblock_after_socket_close();
+    test_unblock_after_peer_close();
+    test_shutdown();
 }
 
 fn test_socketpair() {
@@ -213,3 +215,50 @@ fn test_unblock_after_socket_close() {
 
     server_thread.join().unwrap();
 }
+
+/// Test that a thread which is blocked on reading gets an EOF once the peer is closed.
+fn test_unblock_after_peer_close() {
+    let mut fds = [-1, -1];
+    errno_check(unsafe { libc::socketpair(libc::AF_UNIX, libc::SOCK_STREAM, 0, fds.as_mut_ptr()) });
+
+    let thread1 = thread::spawn(move || {
+        // Let this thread block on read.
+        let mut buf = [0u8; 4];
+        let res = unsafe { libc::read(fds[1], buf.as_mut_ptr().cast(), buf.len()) };
+        assert_eq!(res, 0);
+    });
+    let thread2 = thread::spawn(move || {
+        if !cfg!(miri) {
+            // Ensure thread1 is blocked on reading.
+            thread::sleep(Duration::from_millis(10));
+        }
+        unsafe { errno_check(libc::close(fds[0])) };
+    });
+    thread1.join().unwrap();
+    thread2.join().unwrap();
+}
+
+fn test_shutdown() {
+    let mut fds = [-1, -1];
+    errno_check(unsafe { libc::socketpair(libc::AF_UNIX, libc::SOCK_STREAM, 0, fds.as_mut_ptr()) });
+
+    write_all(fds[0], b"abc").unwrap();
+    errno_check(unsafe { libc::shutdown(fds[0], libc::SHUT_WR) });
+
+    // Writing is no longer possible (and the Rust runtime ignores SIGPIPE)...
+    let res = unsafe { libc::write(fds[0], b"d".as_ptr().cast(), 1) };
+    assert_eq!(res, -1);
+    assert_eq!(errno(), libc::EPIPE);
+
+    // ...but the data that was already written can still be read before the EOF.
+    let buf = read_exact_array::<3>(fds[1]).unwrap();
+    assert_eq!(&buf, b"abc");
+    let mut buf = [0u8; 1];
+    let res = unsafe { libc::read(fds[1], buf.as_mut_ptr().cast(), buf.len()) };
+    assert_eq!(res, 0);
+
+    // The other direction still works.
+    write_all(fds[1], b"xyz").unwrap();
+    let buf = read_exact_array::<3>(fds[0]).unwrap();
+    assert_eq!(&buf, b"xyz");
+}
@@ -0,0 +1,211 @@
+//@only-target: linux android
+//@run-native
+
+use std::mem::MaybeUninit;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[path = "../../utils/libc.rs"]
+mod libc_utils;
+use libc_utils::epoll::*;
+use libc_utils::eventfd::read_val;
+use libc_utils::*;
+
+fn main() {
+    test_oneshot();
+    test_periodic();
+    test_abstime();
+    test_disarm();
+    test_blocking_read();
+    test_blocking_read_until_armed();
+    test_epoll();
+    test_poll();
+    test_errors();
+}
+
+fn timespec(duration: Duration) -> libc::timespec {
+    libc::timespec {
+        tv_sec: duration.as_secs().try_into().unwrap(),
+        tv_nsec: duration.subsec_nanos().try_into().unwrap(),
+    }
+}
+
+fn itimerspec(value: Duration, interval: Duration) -> libc::itimerspec {
+    libc::itimerspec { it_interval: timespec(interval), it_value: timespec(value) }
+}
+
+fn duration(timespec: libc::timespec) -> Duration {
+    Duration::new(timespec.tv_sec.try_into().unwrap(), timespec.tv_nsec.try_into().unwrap())
+}
+
+fn settime(fd: i32, flags: i32, value: Duration, interval: Duration) -> libc::itimerspec {
+    let new = itimerspec(value, interval);
+    let mut old = MaybeUninit::uninit();
+    errno_check(unsafe { libc::timerfd_settime(fd, flags, &new, old.as_mut_ptr()) });
+    unsafe { old.assume_init() }
+}
+
+fn gettime(fd: i32) -> libc::itimerspec {
+    let mut curr = MaybeUninit::uninit();
+    errno_check(unsafe { libc::timerfd_gettime(fd, curr.as_mut_ptr()) });
+    unsafe { curr.assume_init() }
+}
+
+fn create(flags: i32) -> i32 {
+    errno_result(unsafe { libc::timerfd_create(libc::CLOCK_MONOTONIC, flags) }).unwrap()
+}
+
+fn test_oneshot() {
+    let fd = create(libc::TFD_NONBLOCK | libc::TFD_CLOEXEC);
+
+    // A new timer is disarmed.
+    let curr = gettime(fd);
+    assert_eq!(duration(curr.it_value), Duration::ZERO);
+    assert_eq!(duration(curr.it_interval), Duration::ZERO);
+    let err = read_val(fd).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+
+    settime(fd, 0, Duration::from_millis(10), Duration::ZERO);
+    let remaining = duration(gettime(fd).it_value);
+    assert!(remaining > Duration::ZERO && remaining <= Duration::from_millis(10));
+
+    thread::sleep(Duration::from_millis(20));
+    assert_eq!(read_val(fd).unwrap(), 1);
+    // Reading resets the count, and a one-shot timer is disarmed after it expired.
+    let err = read_val(fd).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+    assert_eq!(duration(gettime(fd).it_value), Duration::ZERO);
+
+    errno_check(unsafe { libc::close(fd) });
+}
+
+fn test_periodic() {
+    let fd = create(libc::TFD_NONBLOCK);
+    let interval = Duration::from_millis(10);
+    settime(fd, 0, interval, interval);
+    assert_eq!(duration(gettime(fd).it_interval), interval);
+
+    thread::sleep(Duration::from_millis(55));
+    // All expirations since the timer was armed are counted.
+    let expirations = read_val(fd).unwrap();
+    assert!(expirations >= 5, "only {expirations} expirations");
+    // The timer stays armed.
+    let remaining = duration(gettime(fd).it_value);
+    assert!(remaining > Duration::ZERO && remaining <= interval);
+
+    errno_check(unsafe { libc::close(fd) });
+}
+
+fn test_abstime() {
+    let fd = create(libc::TFD_NONBLOCK);
+    let mut now = MaybeUninit::uninit();
+    errno_check(unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, now.as_mut_ptr()) });
+    let now = duration(unsafe { now.assume_init() });
+
+    // An absolute expiration time in the past expires right away.
+    settime(fd, libc::TFD_TIMER_ABSTIME, now, Duration::ZERO);
+    assert_eq!(read_val(fd).unwrap(), 1);
+
+    settime(fd, libc::TFD_TIMER_ABSTIME, now + Duration::from_millis(10), Duration::ZERO);
+    thread::sleep(Duration::from_millis(20));
+    assert_eq!(read_val(fd).unwrap(), 1);
+
+    errno_check(unsafe { libc::close(fd) });
+}
+
+fn test_disarm() {
+    let fd = create(libc::TFD_NONBLOCK);
+    let interval = Duration::from_secs(1);
+    settime(fd, 0, Duration::from_secs(10), interval);
+
+    // Disarming returns the old setting.
+    let old = settime(fd, 0, Duration::ZERO, Duration::ZERO);
+    assert!(duration(old.it_value) > Duration::from_secs(9));
+    assert_eq!(duration(old.it_interval), interval);
+    assert_eq!(duration(gettime(fd).it_value), Duration::ZERO);
+
+    // Setting the timer resets the count of expirations.
+    settime(fd, 0, Duration::from_millis(1), Duration::ZERO);
+    thread::sleep(Duration::from_millis(10));
+    settime(fd, 0, Duration::ZERO, Duration::ZERO);
+    let err = read_val(fd).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+
+    errno_check(unsafe { libc::close(fd) });
+}
+
+fn test_blocking_read() {
+    let fd = create(0);
+    let start = Instant::now();
+    settime(fd, 0, Duration::from_millis(50), Duration::ZERO);
+    assert_eq!(read_val(fd).unwrap(), 1);
+    assert!(start.elapsed() >= Duration::from_millis(50));
+    errno_check(unsafe { libc::close(fd) });
+}
+
+fn test_blocking_read_until_armed() {
+    let fd = create(0);
+    let reader = thread::spawn(move || read_val(fd).unwrap());
+    thread::sleep(Duration::from_millis(10));
+    settime(fd, 0, Duration::from_millis(10), Duration::ZERO);
+    assert_eq!(reader.join().unwrap(), 1);
+    errno_check(unsafe { libc::close(fd) });
+}
+
+fn test_epoll() {
+    let epfd = errno_result(unsafe { libc::epoll_create1(0) }).unwrap();
+    let fd = create(libc::TFD_NONBLOCK);
+    epoll_ctl_add(epfd, fd, EPOLLIN | EPOLLOUT).unwrap();
+    check_epoll_wait_noblock(epfd, &[]);
+
+    // Wait without a timeout: the timer must wake us up.
+    settime(fd, 0, Duration::from_millis(10), Duration::ZERO);
+    check_epoll_wait(epfd, &[Ev { events: EPOLLIN, data: fd }], -1);
+    assert_eq!(read_val(fd).unwrap(), 1);
+    check_epoll_wait_noblock(epfd, &[]);
+
+    errno_check(unsafe { libc::close(fd) });
+    errno_check(unsafe { libc::close(epfd) });
+}
+
+fn test_poll() {
+    let fd = create(libc::TFD_NONBLOCK);
+    settime(fd, 0, Duration::from_millis(10), Duration::ZERO);
+    let mut pollfd = libc::pollfd { fd, events: libc::POLLIN, revents: 0 };
+    let ret = errno_result(unsafe { libc::poll(&mut pollfd, 1, -1) }).unwrap();
+    assert_eq!(ret, 1);
+    assert_eq!(pollfd.revents, libc::POLLIN);
+    assert_eq!(read_val(fd).unwrap(), 1);
+    errno_check(unsafe { libc::close(fd) });
+}
+
+fn test_errors() {
+    // Unknown clocks and flags.
+    let err = errno_result(unsafe { libc::timerfd_create(-1, 0) }).unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EINVAL));
+    let err = errno_result(unsafe { libc::timerfd_create(libc::CLOCK_MONOTONIC, -1) }).unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EINVAL));
+
+    // Invalid `timespec`.
+    let fd = create(libc::TFD_NONBLOCK);
+    let mut new = itimerspec(Duration::from_secs(1), Duration::ZERO);
+    new.it_value.tv_nsec = 1_000_000_000;
+    let err = errno_result(unsafe { libc::timerfd_settime(fd, 0, &new, std::ptr::null_mut()) })
+        .unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EINVAL));
+
+    // Short buffer.
+    let mut buf = [0u8; 4];
+    let err =
+        errno_result(unsafe { libc::read(fd, buf.as_mut_ptr().cast(), buf.len()) }).unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EINVAL));
+    errno_check(unsafe { libc::close(fd) });
+
+    // Not a timer.
+    let fd = errno_result(unsafe { libc::eventfd(0, 0) }).unwrap();
+    let new = itimerspec(Duration::from_secs(1), Duration::ZERO);
+    let err = errno_result(unsafe { libc::timerfd_settime(fd, 0, &new, std::ptr::null_mut()) })
+        .unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EINVAL));
+    errno_check(unsafe { libc::close(fd) });
+}
@@ -0,0 +1,213 @@
+//@ignore-target: windows # No socket support on Windows
+//@compile-flags: -Zmiri-disable-isolation
+//@run-native
+
+#[path = "../../utils/libc.rs"]
+mod libc_utils;
+
+use std::ptr;
+
+use libc_utils::net::*;
+use libc_utils::*;
+
+const TEST_BYTES: &[u8] = b"these are some test bytes!";
+
+fn main() {
+    test_sendto_recvfrom();
+    test_recvfrom_without_address();
+    test_send_unconnected();
+    test_sendmsg_recvmsg();
+    test_recv_dontwait();
+    test_getsockname_unbound();
+    test_listen_unsupported();
+}
+
+/// Create an IPv4 UDP socket bound to a random port at the localhost address.
+/// Returns the socket file descriptor and the address the socket is bound to.
+fn make_udp_socket_ipv4() -> (libc::c_int, libc::sockaddr_in) {
+    let sockfd = unsafe { errno_result(libc::socket(libc::AF_INET, libc::SOCK_DGRAM, 0)).unwrap() };
+    let addr = sock_addr_ipv4(IPV4_LOCALHOST, 0);
+    errno_check(unsafe {
+        libc::bind(
+            sockfd,
+            (&addr as *const libc::sockaddr_in).cast(),
+            size_of::<libc::sockaddr_in>() as libc::socklen_t,
+        )
+    });
+
+    // Retrieve actual address because we used a randomized port.
+    let (_, addr_with_port) =
+        sockname_ipv4(|storage, len| unsafe { libc::getsockname(sockfd, storage, len) }).unwrap();
+    (sockfd, addr_with_port)
+}
+
+fn test_sendto_recvfrom() {
+    let (receiver, receiver_addr) = make_udp_socket_ipv4();
+    // The sender gets implicitly bound when sending.
+    let sender = unsafe {
+        errno_result(libc::socket(libc::AF_INET, libc::SOCK_DGRAM, libc::IPPROTO_UDP)).unwrap()
+    };
+
+    let bytes_sent = unsafe {
+        libc::sendto(
+            sender,
+            TEST_BYTES.as_ptr().cast(),
+            TEST_BYTES.len(),
+            0,
+            (&receiver_addr as *const libc::sockaddr_in).cast(),
+            size_of::<libc::sockaddr_in>() as libc::socklen_t,
+        )
+    };
+    assert_eq!(bytes_sent, TEST_BYTES.len() as isize);
+
+    let mut buffer = [0u8; TEST_BYTES.len()];
+    let (bytes_received, source) = sockname_ipv4(|storage, len| unsafe {
+        libc::recvfrom(receiver, buffer.as_mut_ptr().cast(), buffer.len(), 0, storage, len)
+            .try_into()
+            .unwrap()
+    })
+    .unwrap();
+    assert_eq!(bytes_received, TEST_BYTES.len() as libc::c_int);
+    assert_eq!(&buffer, TEST_BYTES);
+
+    let (_, sender_addr) =
+        sockname_ipv4(|storage, len| unsafe { libc::getsockname(sender, storage, len) }).unwrap();
+    assert_eq!(source.sin_port, sender_addr.sin_port);
+    assert_eq!(source.sin_addr.s_addr, receiver_addr.sin_addr.s_addr);
+
+    errno_check(unsafe { libc::close(sender) });
+    errno_check(unsafe { libc::close(receiver) });
+}
+
+/// `recvfrom` with a null address behaves like `recv`.
+fn test_recvfrom_without_address() {
+    let (receiver, receiver_addr) = make_udp_socket_ipv4();
+    let (sender, _) = make_udp_socket_ipv4();
+    connect_ipv4(sender, receiver_addr).unwrap();
+
+    write_all(sender, TEST_BYTES).unwrap();
+
+    let mut buffer = [0u8; TEST_BYTES.len()];
+    let bytes_received = unsafe {
+        libc::recvfrom(
+            receiver,
+            buffer.as_mut_ptr().cast(),
+            buffer.len(),
+            0,
+            ptr::null_mut(),
+            ptr::null_mut(),
+        )
+    };
+    assert_eq!(bytes_received, TEST_BYTES.len() as isize);
+    assert_eq!(&buffer, TEST_BYTES);
+
+    errno_check(unsafe { libc::close(sender) });
+    errno_check(unsafe { libc::close(receiver) });
+}
+
+/// Sending on a socket which is neither connected nor given
+/// an address fails with `EDESTADDRREQ`.
+fn test_send_unconnected() {
+    let (sockfd, _) = make_udp_socket_ipv4();
+
+    let result = unsafe { libc::send(sockfd, TEST_BYTES.as_ptr().cast(), TEST_BYTES.len(), 0) };
+    assert_eq!(result, -1);
+    assert_eq!(errno(), libc::EDESTADDRREQ);
+
+    let mut peer = std::mem::MaybeUninit::<libc::sockaddr_in>::uninit();
+    let mut peer_len = size_of::<libc::sockaddr_in>() as libc::socklen_t;
+    let result = unsafe { libc::getpeername(sockfd, peer.as_mut_ptr().cast(), &mut peer_len) };
+    assert_eq!(result, -1);
+    assert_eq!(errno(), libc::ENOTCONN);
+
+    errno_check(unsafe { libc::close(sockfd) });
+}
+
+/// Send a datagram which is gathered from several buffers and scatter
+/// it into several buffers on the receiving side.
+fn test_sendmsg_recvmsg() {
+    let (receiver, mut receiver_addr) = make_udp_socket_ipv4();
+    let (sender, sender_addr) = make_udp_socket_ipv4();
+
+    let (first, second) = TEST_BYTES.split_at(10);
+    let mut send_iov = [
+        libc::iovec { iov_base: first.as_ptr() as *mut _, iov_len: first.len() },
+        libc::iovec { iov_base: second.as_ptr() as *mut _, iov_len: second.len() },
+    ];
+    let mut message: libc::msghdr = unsafe { std::mem::zeroed() };
+    message.msg_name = (&raw mut receiver_addr).cast();
+    message.msg_namelen = size_of::<libc::sockaddr_in>() as libc::socklen_t;
+    message.msg_iov = send_iov.as_mut_ptr();
+    message.msg_iovlen = send_iov.len() as _;
+    let bytes_sent = unsafe { libc::sendmsg(sender, &message, 0) };
+    assert_eq!(bytes_sent, TEST_BYTES.len() as isize);
+
+    let mut first_buffer = [0u8; 4];
+    let mut second_buffer = [0u8; 64];
+    let mut recv_iov = [
+        libc::iovec { iov_base: first_buffer.as_mut_ptr().cast(), iov_len: first_buffer.len() },
+        libc::iovec { iov_base: second_buffer.as_mut_ptr().cast(), iov_len: second_buffer.len() },
+    ];
+    let mut source: libc::sockaddr_in = unsafe { std::mem::zeroed() };
+    let mut message: libc::msghdr = unsafe { std::mem::zeroed() };
+    message.msg_name = (&raw mut source).cast();
+    message.msg_namelen = size_of::<libc::sockaddr_in>() as libc::socklen_t;
+    message.msg_iov = recv_iov.as_mut_ptr();
+    message.msg_iovlen = recv_iov.len() as _;
+    let bytes_received = unsafe { libc::recvmsg(receiver, &mut message, 0) };
+    assert_eq!(bytes_received, TEST_BYTES.len() as isize);
+
+    assert_eq!(&first_buffer, &TEST_BYTES[..4]);
+    assert_eq!(&second_buffer[..TEST_BYTES.len() - 4], &TEST_BYTES[4..]);
+    assert_eq!(message.msg_namelen, size_of::<libc::sockaddr_in>() as libc::socklen_t);
+    assert_eq!(source.sin_port, sender_addr.sin_port);
+    assert_eq!(message.msg_flags, 0);
+
+    errno_check(unsafe { libc::close(sender) });
+    errno_check(unsafe { libc::close(receiver) });
+}
+
+/// Receiving with `MSG_DONTWAIT` on an empty socket fails with `EAGAIN`
+/// even though the socket is blocking.
+fn test_recv_dontwait() {
+    let (sockfd, _) = make_udp_socket_ipv4();
+
+    let mut buffer = [0u8; TEST_BYTES.len()];
+    let result = unsafe {
+        libc::recvfrom(
+            sockfd,
+            buffer.as_mut_ptr().cast(),
+            buffer.len(),
+            libc::MSG_DONTWAIT,
+            ptr::null_mut(),
+            ptr::null_mut(),
+        )
+    };
+    assert_eq!(result, -1);
+    assert_eq!(errno(), libc::EAGAIN);
+
+    errno_check(unsafe { libc::close(sockfd) });
+}
+
+/// An unbound socket reports the unspecified address.
+fn test_getsockname_unbound() {
+    let sockfd = unsafe { errno_result(libc::socket(libc::AF_INET, libc::SOCK_DGRAM, 0)).unwrap() };
+
+    let (_, addr) =
+        sockname_ipv4(|storage, len| unsafe { libc::getsockname(sockfd, storage, len) }).unwrap();
+    assert_eq!(addr.sin_port, 0);
+    assert_eq!(addr.sin_addr.s_addr, 0);
+
+    errno_check(unsafe { libc::close(sockfd) });
+}
+
+/// Datagram sockets are connectionless and thus can't listen.
+fn test_listen_unsupported() {
+    let (sockfd, _) = make_udp_socket_ipv4();
+
+    let result = unsafe { libc::listen(sockfd, 16) };
+    assert_eq!(result, -1);
+    assert_eq!(errno(), libc::EOPNOTSUPP);
+
+    errno_check(unsafe { libc::close(sockfd) });
+}
@@ -0,0 +1,148 @@
+//@ignore-target: windows # No libc socket on Windows
+
+#[path = "../../utils/libc.rs"]
+mod libc_utils;
+
+use std::mem::{MaybeUninit, offset_of};
+use std::ptr;
+
+use libc_utils::*;
+
+const TEST_BYTES: &[u8] = b"these are some test bytes!";
+
+fn main() {
+    test_stream_sockname_lengths();
+    test_connect_errors();
+    test_bind_errors();
+    test_datagram_socketpair();
+}
+
+/// Build a `sockaddr_un` for `path` and return it together with its length.
+fn sock_addr_unix(path: &[u8]) -> (libc::sockaddr_un, libc::socklen_t) {
+    let mut addr: libc::sockaddr_un = unsafe { std::mem::zeroed() };
+    addr.sun_family = libc::AF_UNIX as libc::sa_family_t;
+    for (dst, &src) in addr.sun_path.iter_mut().zip(path) {
+        *dst = src as libc::c_char;
+    }
+    // The length doesn't include the null terminator.
+    let len = offset_of!(libc::sockaddr_un, sun_path) + path.len();
+    (addr, len as libc::socklen_t)
+}
+
+fn bind_unix(sockfd: libc::c_int, path: &[u8]) -> std::io::Result<libc::c_int> {
+    let (addr, len) = sock_addr_unix(path);
+    errno_result(unsafe { libc::bind(sockfd, (&raw const addr).cast(), len) })
+}
+
+fn connect_unix(sockfd: libc::c_int, path: &[u8]) -> std::io::Result<libc::c_int> {
+    let (addr, len) = sock_addr_unix(path);
+    errno_result(unsafe { libc::connect(sockfd, (&raw const addr).cast(), len) })
+}
+
+/// The addresses returned by `getsockname`, `getpeername` and `accept` only
+/// include the used part of `sun_path`.
+fn test_stream_sockname_lengths() {
+    let path = b"miri-libc-sockname.sock";
+    let listener =
+        unsafe { errno_result(libc::socket(libc::AF_UNIX, libc::SOCK_STREAM, 0)).unwrap() };
+    bind_unix(listener, path).unwrap();
+    errno_check(unsafe { libc::listen(listener, 16) });
+
+    let mut addr = MaybeUninit::<libc::sockaddr_un>::zeroed();
+    let mut len = size_of::<libc::sockaddr_un>() as libc::socklen_t;
+    errno_check(unsafe { libc::getsockname(listener, addr.as_mut_ptr().cast(), &mut len) });
+    let addr = unsafe { addr.assume_init() };
+    // The reported length includes the null terminator.
+    assert_eq!(len as usize, offset_of!(libc::sockaddr_un, sun_path) + path.len() + 1);
+    assert_eq!(addr.sun_family, libc::AF_UNIX as libc::sa_family_t);
+    assert!(addr.sun_path.iter().zip(path).all(|(&a, &b)| a as u8 == b));
+
+    let client =
+        unsafe { errno_result(libc::socket(libc::AF_UNIX, libc::SOCK_STREAM, 0)).unwrap() };
+    connect_unix(client, path).unwrap();
+
+    // The client is unnamed, so only the family is returned.
+    let mut addr = MaybeUninit::<libc::sockaddr_un>::zeroed();
+    let mut len = size_of::<libc::sockaddr_un>() as libc::socklen_t;
+    let server = unsafe {
+        errno_result(libc::accept(listener, addr.as_mut_ptr().cast(), &mut len)).unwrap()
+    };
+    assert_eq!(len as usize, offset_of!(libc::sockaddr_un, sun_path));
+
+    let mut len = size_of::<libc::sockaddr_un>() as libc::socklen_t;
+    errno_check(unsafe { libc::getpeername(client, addr.as_mut_ptr().cast(), &mut len) });
+    assert_eq!(len as usize, offset_of!(libc::sockaddr_un, sun_path) + path.len() + 1);
+
+    write_all(client, TEST_BYTES).unwrap();
+    let buf = read_exact_array::<{ TEST_BYTES.len() }>(server).unwrap();
+    assert_eq!(&buf, TEST_BYTES);
+
+    errno_check(unsafe { libc::close(client) });
+    errno_check(unsafe { libc::close(server) });
+    errno_check(unsafe { libc::close(listener) });
+}
+
+fn test_connect_errors() {
+    let client =
+        unsafe { errno_result(libc::socket(libc::AF_UNIX, libc::SOCK_STREAM, 0)).unwrap() };
+
+    let err = connect_unix(client, b"miri-libc-does-not-exist.sock").unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::ENOENT));
+
+    // The socket is bound but not listening.
+    let bound = unsafe { errno_result(libc::socket(libc::AF_UNIX, libc::SOCK_STREAM, 0)).unwrap() };
+    bind_unix(bound, b"miri-libc-not-listening.sock").unwrap();
+    let err = connect_unix(client, b"miri-libc-not-listening.sock").unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::ECONNREFUSED));
+
+    // Accepting requires a listening socket.
+    let result = unsafe { libc::accept(bound, ptr::null_mut(), ptr::null_mut()) };
+    assert_eq!(result, -1);
+    assert_eq!(errno(), libc::EINVAL);
+
+    errno_check(unsafe { libc::close(bound) });
+    errno_check(unsafe { libc::close(client) });
+}
+
+fn test_bind_errors() {
+    let first = unsafe { errno_result(libc::socket(libc::AF_UNIX, libc::SOCK_DGRAM, 0)).unwrap() };
+    let second = unsafe { errno_result(libc::socket(libc::AF_UNIX, libc::SOCK_DGRAM, 0)).unwrap() };
+    bind_unix(first, b"miri-libc-bind.sock").unwrap();
+
+    let err = bind_unix(second, b"miri-libc-bind.sock").unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EADDRINUSE));
+    let err = bind_unix(first, b"miri-libc-bind-again.sock").unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EINVAL));
+
+    // Datagram sockets can't listen.
+    let result = unsafe { libc::listen(first, 16) };
+    assert_eq!(result, -1);
+    assert_eq!(errno(), libc::EOPNOTSUPP);
+
+    errno_check(unsafe { libc::close(first) });
+    errno_check(unsafe { libc::close(second) });
+}
+
+/// A datagram socketpair preserves message boundaries.
+fn test_datagram_socketpair() {
+    let mut fds = [-1, -1];
+    errno_check(unsafe { libc::socketpair(libc::AF_UNIX, libc::SOCK_DGRAM, 0, fds.as_mut_ptr()) });
+
+    write_all(fds[0], b"first").unwrap();
+    write_all(fds[0], TEST_BYTES).unwrap();
+
+    let mut buf = [0u8; 64];
+    let bytes_read = unsafe { libc::read(fds[1], buf.as_mut_ptr().cast(), buf.len()) };
+    assert_eq!(&buf[..bytes_read as usize], b"first");
+    let bytes_read = unsafe { libc::read(fds[1], buf.as_mut_ptr().cast(), buf.len()) };
+    assert_eq!(&buf[..bytes_read as usize], TEST_BYTES);
+
+    // Nothing is left, so a non-blocking receive fails.
+    let result =
+        unsafe { libc::recv(fds[1], buf.as_mut_ptr().cast(), buf.len(), libc::MSG_DONTWAIT) };
+    assert_eq!(result, -1);
+    assert_eq!(errno(), libc::EAGAIN);
+
+    errno_check(unsafe { libc::close(fds[0]) });
+    errno_check(unsafe { libc::close(fds[1]) });
+}
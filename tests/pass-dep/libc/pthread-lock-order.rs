@@ -0,0 +1,40 @@
+//@only-target: linux # std takes different locks internally on other targets
+//@compile-flags: -Zmiri-check-lock-order -Zmiri-deterministic-concurrency
+// The lock numbers depend on the locks std takes before `main`.
+//@normalize-stderr-test: "lock #\d+" -> "lock #N"
+#![feature(sync_unsafe_cell)]
+
+use std::cell::SyncUnsafeCell;
+use std::thread;
+
+static A: SyncUnsafeCell<libc::pthread_mutex_t> =
+    SyncUnsafeCell::new(libc::PTHREAD_MUTEX_INITIALIZER);
+static B: SyncUnsafeCell<libc::pthread_mutex_t> =
+    SyncUnsafeCell::new(libc::PTHREAD_MUTEX_INITIALIZER);
+
+fn main() {
+    // Both threads take `A` before `B`, which is fine.
+    thread::spawn(|| unsafe {
+        libc::pthread_mutex_lock(A.get());
+        libc::pthread_mutex_lock(B.get());
+        libc::pthread_mutex_unlock(B.get());
+        libc::pthread_mutex_unlock(A.get());
+    })
+    .join()
+    .unwrap();
+    unsafe {
+        libc::pthread_mutex_lock(A.get());
+        libc::pthread_mutex_lock(B.get());
+        libc::pthread_mutex_unlock(B.get());
+        libc::pthread_mutex_unlock(A.get());
+    }
+
+    // Taking them in the opposite order does not deadlock here because the other thread is gone,
+    // but it would have if the threads had run concurrently.
+    unsafe {
+        libc::pthread_mutex_lock(B.get());
+        libc::pthread_mutex_lock(A.get());
+        libc::pthread_mutex_unlock(A.get());
+        libc::pthread_mutex_unlock(B.get());
+    }
+}
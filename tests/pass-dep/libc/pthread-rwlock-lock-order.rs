@@ -0,0 +1,32 @@
+//@only-target: linux # std takes different locks internally on other targets
+//@compile-flags: -Zmiri-check-lock-order -Zmiri-deterministic-concurrency
+// The lock numbers depend on the locks std takes before `main`.
+//@normalize-stderr-test: "lock #\d+" -> "lock #N"
+#![feature(sync_unsafe_cell)]
+
+use std::cell::SyncUnsafeCell;
+use std::thread;
+
+static A: SyncUnsafeCell<libc::pthread_rwlock_t> =
+    SyncUnsafeCell::new(libc::PTHREAD_RWLOCK_INITIALIZER);
+static B: SyncUnsafeCell<libc::pthread_rwlock_t> =
+    SyncUnsafeCell::new(libc::PTHREAD_RWLOCK_INITIALIZER);
+
+fn main() {
+    // Read locks take part as well: under a different schedule, each thread waits to read the lock
+    // that the other one holds for writing.
+    thread::spawn(|| unsafe {
+        libc::pthread_rwlock_wrlock(A.get());
+        libc::pthread_rwlock_rdlock(B.get());
+        libc::pthread_rwlock_unlock(B.get());
+        libc::pthread_rwlock_unlock(A.get());
+    })
+    .join()
+    .unwrap();
+    unsafe {
+        libc::pthread_rwlock_wrlock(B.get());
+        libc::pthread_rwlock_rdlock(A.get());
+        libc::pthread_rwlock_unlock(A.get());
+        libc::pthread_rwlock_unlock(B.get());
+    }
+}
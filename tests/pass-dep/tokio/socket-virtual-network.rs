@@ -0,0 +1,62 @@
+//@only-target: linux # We only support tokio on Linux
+//@compile-flags: -Zmiri-virtual-network
+
+use tokio::io::{self, AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+const TEST_BYTES: &[u8] = b"these are some test bytes!";
+
+#[tokio::main]
+async fn main() {
+    test_echo_server().await;
+    test_large_transfer().await;
+}
+
+/// Copy everything the client sends back to it. With the loopback network, the data
+/// arrives in chunks of random size, so this also checks that tokio is woken up again
+/// when a short read leaves data behind.
+async fn test_echo_server() {
+    const ITER: usize = 4;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let client = tokio::spawn(async move {
+        let mut stream = TcpStream::connect(&addr).await.unwrap();
+
+        for _ in 0..ITER {
+            stream.write_all(TEST_BYTES).await.unwrap();
+            let mut buf = [0; TEST_BYTES.len()];
+            stream.read_exact(&mut buf).await.unwrap();
+            assert_eq!(&buf[..], TEST_BYTES);
+        }
+    });
+
+    let (mut stream, _) = listener.accept().await.unwrap();
+    let (mut rd, mut wr) = stream.split();
+
+    let n = io::copy(&mut rd, &mut wr).await.unwrap() as usize;
+    assert_eq!(n, ITER * TEST_BYTES.len());
+
+    client.await.unwrap();
+}
+
+/// Send more data than fits into the buffers, so that the writer has to wait for the reader.
+async fn test_large_transfer() {
+    let listener = TcpListener::bind("[::1]:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let data: Vec<u8> = (0..100_000u32).map(|i| i as u8).collect();
+
+    let expected = data.clone();
+    let server = tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut received = Vec::new();
+        stream.read_to_end(&mut received).await.unwrap();
+        assert!(received == expected);
+    });
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream.write_all(&data).await.unwrap();
+    stream.shutdown().await.unwrap();
+    server.await.unwrap();
+}
@@ -0,0 +1,51 @@
+//@only-target: linux # We only support tokio on Linux
+//@compile-flags: -Zmiri-disable-isolation
+
+use tokio::net::UdpSocket;
+
+const TEST_BYTES: &[u8] = b"these are some test bytes!";
+
+#[tokio::main]
+async fn main() {
+    test_send_to_recv_from().await;
+    test_echo_server().await;
+}
+
+/// Test sending a datagram to an explicit address.
+async fn test_send_to_recv_from() {
+    let receiver = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let sender = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+
+    sender.send_to(TEST_BYTES, receiver.local_addr().unwrap()).await.unwrap();
+
+    let mut buffer = [0; TEST_BYTES.len()];
+    let (bytes_received, source) = receiver.recv_from(&mut buffer).await.unwrap();
+    assert_eq!(&buffer[..bytes_received], TEST_BYTES);
+    assert_eq!(source, sender.local_addr().unwrap());
+}
+
+/// Test a server task which sends every datagram back to where it came from.
+async fn test_echo_server() {
+    let server = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let server_address = server.local_addr().unwrap();
+
+    let server_task = tokio::spawn(async move {
+        let mut buffer = [0; 64];
+        for _ in 0..3 {
+            let (bytes_received, source) = server.recv_from(&mut buffer).await.unwrap();
+            server.send_to(&buffer[..bytes_received], source).await.unwrap();
+        }
+    });
+
+    let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    client.connect(server_address).await.unwrap();
+
+    for chunk in TEST_BYTES.chunks(10) {
+        client.send(chunk).await.unwrap();
+        let mut buffer = [0; 64];
+        let bytes_received = client.recv(&mut buffer).await.unwrap();
+        assert_eq!(&buffer[..bytes_received], chunk);
+    }
+
+    server_task.await.unwrap();
+}
@@ -0,0 +1,54 @@
+//@only-target: linux # We only support tokio on Linux
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{UnixDatagram, UnixListener, UnixStream};
+
+const TEST_BYTES: &[u8] = b"these are some test bytes!";
+
+#[tokio::main]
+async fn main() {
+    test_echo_server().await;
+    test_datagram().await;
+}
+
+/// Test a server task which echoes everything it receives on a connection.
+async fn test_echo_server() {
+    let listener = UnixListener::bind("miri-tokio-echo.sock").unwrap();
+
+    let server_task = tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut buffer = [0; 64];
+        loop {
+            let bytes_read = stream.read(&mut buffer).await.unwrap();
+            if bytes_read == 0 {
+                break;
+            }
+            stream.write_all(&buffer[..bytes_read]).await.unwrap();
+        }
+    });
+
+    let mut client = UnixStream::connect("miri-tokio-echo.sock").await.unwrap();
+    for chunk in TEST_BYTES.chunks(10) {
+        client.write_all(chunk).await.unwrap();
+        let mut buffer = vec![0; chunk.len()];
+        client.read_exact(&mut buffer).await.unwrap();
+        assert_eq!(buffer, chunk);
+    }
+    drop(client);
+
+    server_task.await.unwrap();
+}
+
+async fn test_datagram() {
+    let receiver = UnixDatagram::bind("miri-tokio-datagram.sock").unwrap();
+    let sender = UnixDatagram::unbound().unwrap();
+
+    let receive_task = tokio::spawn(async move {
+        let mut buffer = [0; 64];
+        let (bytes_received, _) = receiver.recv_from(&mut buffer).await.unwrap();
+        assert_eq!(&buffer[..bytes_received], TEST_BYTES);
+    });
+
+    sender.send_to(TEST_BYTES, "miri-tokio-datagram.sock").await.unwrap();
+    receive_task.await.unwrap();
+}
@@ -0,0 +1,21 @@
+//@compile-flags: -Zmiri-check-lock-order -Zmiri-deterministic-concurrency
+// Integers that get exchanged from zero to something else and back look like futex-based locks.
+// As long as they are not used with futexes, they are not treated as locks, so setting them in
+// inconsistent orders does not warn.
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::thread;
+
+static X: AtomicU32 = AtomicU32::new(0);
+static Y: AtomicU32 = AtomicU32::new(0);
+
+fn set_both(first: &AtomicU32, second: &AtomicU32) {
+    first.compare_exchange(0, 1, Ordering::Acquire, Ordering::Relaxed).unwrap();
+    second.swap(1, Ordering::Acquire);
+    second.store(0, Ordering::Release);
+    first.store(0, Ordering::Release);
+}
+
+fn main() {
+    thread::spawn(|| set_both(&X, &Y)).join().unwrap();
+    set_both(&Y, &X);
+}
@@ -0,0 +1,43 @@
+//@only-target: linux # other targets implement `std::sync::Mutex` without futexes
+//@compile-flags: -Zmiri-check-lock-order -Zmiri-deterministic-concurrency
+// The lock numbers depend on the locks std takes before `main`.
+//@normalize-stderr-test: "lock #\d+" -> "lock #N"
+//@normalize-stderr-test: "\n *= note: stack backtrace.*:" -> ""
+//@normalize-stderr-test: "\n *\d+:.*\n *at .*" -> ""
+//@normalize-stderr-test: "RUSTLIB/.*\.rs" -> "RUSTLIB/$$FILE"
+//@normalize-stderr-test: "LL \| .*" -> "LL | $$CODE"
+//@normalize-stderr-test: "\| +\^+" -> "| ^"
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+static A: Mutex<()> = Mutex::new(());
+static B: Mutex<()> = Mutex::new(());
+
+/// Makes another thread wait for `m`: Miri only treats a futex word as a lock once the lock has
+/// been contended.
+fn contend(m: &Mutex<()>) {
+    let guard = m.lock().unwrap();
+    thread::scope(|s| {
+        s.spawn(|| drop(m.lock().unwrap()));
+        thread::sleep(Duration::from_millis(100));
+        drop(guard);
+    });
+}
+
+fn main() {
+    contend(&A);
+    contend(&B);
+
+    thread::spawn(|| {
+        let _a = A.lock().unwrap();
+        let _b = B.lock().unwrap();
+    })
+    .join()
+    .unwrap();
+
+    // This does not deadlock because the other thread is gone, but it would have if the threads
+    // had run concurrently.
+    let _b = B.lock().unwrap();
+    let _a = A.lock().unwrap();
+}
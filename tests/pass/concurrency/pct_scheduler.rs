@@ -0,0 +1,72 @@
+// Check that programs relying on fairness still terminate with PCT scheduling.
+//@compile-flags: -Zmiri-scheduler=pct:depth=3,steps=200
+//@revisions: s1 s2 s3
+//@[s1] compile-flags: -Zmiri-seed=1
+//@[s2] compile-flags: -Zmiri-seed=2
+//@[s3] compile-flags: -Zmiri-seed=3
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread;
+
+/// A yielding spin loop must make progress even if the spinning thread has the highest priority.
+fn spin_with_yield() {
+    static FLAG: AtomicBool = AtomicBool::new(false);
+
+    let waiter = thread::spawn(|| {
+        while !FLAG.load(Ordering::Acquire) {
+            thread::yield_now();
+        }
+    });
+    let setter = thread::spawn(|| FLAG.store(true, Ordering::Release));
+    waiter.join().unwrap();
+    setter.join().unwrap();
+}
+
+fn mutex_counter() {
+    let counter = Arc::new(Mutex::new(0));
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let counter = Arc::clone(&counter);
+            thread::spawn(move || {
+                for _ in 0..3 {
+                    *counter.lock().unwrap() += 1;
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    assert_eq!(*counter.lock().unwrap(), 12);
+}
+
+fn channel() {
+    let (tx, rx) = mpsc::channel();
+    for i in 0..3 {
+        let tx = tx.clone();
+        thread::spawn(move || tx.send(i).unwrap());
+    }
+    drop(tx);
+    let mut received: Vec<i32> = rx.iter().collect();
+    received.sort();
+    assert_eq!(received, [0, 1, 2]);
+}
+
+fn atomic_counter() {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let handles: Vec<_> =
+        (0..3).map(|_| thread::spawn(|| COUNTER.fetch_add(1, Ordering::Relaxed))).collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    assert_eq!(COUNTER.load(Ordering::Relaxed), 3);
+}
+
+fn main() {
+    spin_with_yield();
+    mutex_counter();
+    channel();
+    atomic_counter();
+}
@@ -0,0 +1,34 @@
+// Record the schedule of a program that gets preempted a lot, then replay it. The revisions run in
+// order, so `replay` reads the trace that `record` just wrote. A decision that does not match the
+// trace, or a recorded decision that never gets replayed, would show up as a warning.
+//@ignore-host: windows # the trace is written to `/tmp`
+//@revisions: record replay
+//@compile-flags: -Zmiri-preemption-rate=0.2
+//@[record] compile-flags: -Zmiri-record-schedule=/tmp/miri-test-schedule-replay.trace
+//@[replay] compile-flags: -Zmiri-replay-schedule=/tmp/miri-test-schedule-replay.trace
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+fn main() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let handles: Vec<_> = (0..3)
+        .map(|i| {
+            let log = Arc::clone(&log);
+            thread::spawn(move || {
+                for _ in 0..3 {
+                    COUNTER.fetch_add(1, Ordering::Relaxed);
+                    log.lock().unwrap().push(i);
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    assert_eq!(COUNTER.load(Ordering::Relaxed), 9);
+    assert_eq!(log.lock().unwrap().len(), 9);
+}
@@ -0,0 +1,26 @@
+//@compile-flags: -Zmiri-vfs-mount=tests/utils:/utils:ro -Zmiri-vfs-mount=tests/utils:/scratch/utils:cow
+
+use std::fs;
+use std::io::ErrorKind;
+
+fn main() {
+    // Both mounts see the contents of the host directory.
+    let original = fs::read_to_string("/utils/mod.rs").unwrap();
+    assert!(original.contains("mod fs;"));
+    assert_eq!(fs::read_to_string("/scratch/utils/mod.rs").unwrap(), original);
+
+    // The read-only mount rejects all changes.
+    assert_eq!(fs::write("/utils/mod.rs", b"").unwrap_err().kind(), ErrorKind::ReadOnlyFilesystem);
+    assert_eq!(fs::write("/utils/new.rs", b"").unwrap_err().kind(), ErrorKind::ReadOnlyFilesystem);
+    assert_eq!(fs::remove_file("/utils/mod.rs").unwrap_err().kind(), ErrorKind::ReadOnlyFilesystem);
+
+    // The copy-on-write mount can be changed without affecting the other mount.
+    fs::write("/scratch/utils/mod.rs", b"changed").unwrap();
+    assert_eq!(fs::read_to_string("/scratch/utils/mod.rs").unwrap(), "changed");
+    assert_eq!(fs::read_to_string("/utils/mod.rs").unwrap(), original);
+    fs::remove_file("/scratch/utils/mod.rs").unwrap();
+    assert!(fs::metadata("/utils/mod.rs").is_ok());
+
+    // Mounting does not remove `/tmp`.
+    fs::write("/tmp/file", b"").unwrap();
+}
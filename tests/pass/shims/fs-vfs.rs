@@ -0,0 +1,118 @@
+//@compile-flags: -Zmiri-vfs
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+fn main() {
+    let tmp = std::env::temp_dir();
+    test_file(&tmp);
+    test_errors(&tmp);
+    // Windows file handling is very incomplete.
+    #[cfg(unix)]
+    {
+        test_directory(&tmp);
+        test_temp_file_names();
+    }
+}
+
+fn test_file(tmp: &Path) {
+    let path = tmp.join("miri_test_vfs_file.txt");
+    let bytes = b"Hello, in-memory World!\n";
+
+    let mut file = File::create(&path).unwrap();
+    file.write_all(bytes).unwrap();
+    assert_eq!(file.metadata().unwrap().len(), bytes.len() as u64);
+    drop(file);
+
+    assert_eq!(fs::read(&path).unwrap(), bytes);
+    assert!(fs::metadata(&path).unwrap().is_file());
+
+    // Appending, seeking, and truncating.
+    let mut file = OpenOptions::new().write(true).open(&path).unwrap();
+    if cfg!(unix) {
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"more\n").unwrap();
+    } else {
+        // Windows shims do not support append mode.
+        file.seek(SeekFrom::End(0)).unwrap();
+        file.write_all(b"more\n").unwrap();
+    }
+    drop(file);
+    let mut file = OpenOptions::new().read(true).write(true).open(&path).unwrap();
+    file.seek(SeekFrom::End(-5)).unwrap();
+    let mut contents = String::new();
+    file.read_to_string(&mut contents).unwrap();
+    assert_eq!(contents, "more\n");
+    file.set_len(5).unwrap();
+    file.sync_all().unwrap();
+    drop(file);
+    assert_eq!(fs::read_to_string(&path).unwrap(), "Hello");
+
+    // Renaming replaces the target.
+    let renamed = tmp.join("miri_test_vfs_renamed.txt");
+    fs::write(&renamed, b"old").unwrap();
+    fs::rename(&path, &renamed).unwrap();
+    assert_eq!(fs::read_to_string(&renamed).unwrap(), "Hello");
+    assert_eq!(fs::metadata(&path).unwrap_err().kind(), ErrorKind::NotFound);
+
+    fs::remove_file(&renamed).unwrap();
+    assert_eq!(File::open(&renamed).unwrap_err().kind(), ErrorKind::NotFound);
+}
+
+fn test_errors(tmp: &Path) {
+    let path = tmp.join("miri_test_vfs_errors.txt");
+    File::create_new(&path).unwrap();
+    assert_eq!(File::create_new(&path).unwrap_err().kind(), ErrorKind::AlreadyExists);
+    assert_eq!(
+        File::create(tmp.join("does_not_exist/file.txt")).unwrap_err().kind(),
+        ErrorKind::NotFound
+    );
+    fs::remove_file(&path).unwrap();
+    assert_eq!(fs::remove_file(&path).unwrap_err().kind(), ErrorKind::NotFound);
+}
+
+#[cfg(unix)]
+fn test_directory(tmp: &Path) {
+    let dir = tmp.join("miri_test_vfs_dir");
+    fs::create_dir_all(dir.join("nested/deeper")).unwrap();
+    assert_eq!(fs::create_dir(&dir).unwrap_err().kind(), ErrorKind::AlreadyExists);
+    fs::write(dir.join("b.txt"), b"b").unwrap();
+    fs::write(dir.join("a.txt"), b"a").unwrap();
+
+    // Entries are listed in the order of their names.
+    let entries = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(entries, ["a.txt", "b.txt", "nested"]);
+
+    // `..` is resolved lexically.
+    assert_eq!(fs::read_to_string(dir.join("nested/../a.txt")).unwrap(), "a");
+    assert_eq!(fs::canonicalize(dir.join("nested/./deeper/..")).unwrap(), dir.join("nested"));
+
+    assert_eq!(fs::remove_dir(&dir).unwrap_err().kind(), ErrorKind::DirectoryNotEmpty);
+    assert_eq!(fs::remove_file(dir.join("nested")).unwrap_err().kind(), ErrorKind::IsADirectory);
+    assert_eq!(fs::remove_dir(dir.join("a.txt")).unwrap_err().kind(), ErrorKind::NotADirectory);
+    assert_eq!(
+        std::os::unix::fs::symlink("a.txt", dir.join("link")).unwrap_err().kind(),
+        ErrorKind::PermissionDenied
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+    assert!(!dir.exists());
+}
+
+/// `mkstemp` picks names from Miri's random number generator, so they only depend on the seed.
+#[cfg(unix)]
+fn test_temp_file_names() {
+    unsafe extern "C" {
+        fn mkstemp(template: *mut std::ffi::c_char) -> std::ffi::c_int;
+    }
+    let mut template = *b"/tmp/miri_test_vfs_XXXXXX\0";
+    let fd = unsafe { mkstemp(template.as_mut_ptr().cast()) };
+    assert!(fd >= 0);
+    let path = std::str::from_utf8(&template[..template.len() - 1]).unwrap();
+    assert!(!path.ends_with("XXXXXX"));
+    assert!(fs::metadata(path).unwrap().is_file());
+}
@@ -12,7 +12,7 @@ use std::fs::{
     self, File, OpenOptions, create_dir, read_dir, remove_dir, remove_dir_all, remove_file, rename,
 };
 use std::io::{
-    Error, ErrorKind, IoSlice, IoSliceMut, IsTerminal, Read, Result, Seek, SeekFrom, Write,
+    self, Error, ErrorKind, IoSlice, IoSliceMut, IsTerminal, Read, Result, Seek, SeekFrom, Write,
 };
 use std::path::Path;
 
@@ -55,6 +55,7 @@ fn main() {
         test_hard_link();
 
         test_readv_writev();
+        test_copy();
         #[cfg(unix)]
         test_pread_pwrite();
         #[cfg(all(unix, not(target_os = "solaris")))]
@@ -415,6 +416,35 @@ fn test_from_raw_os_error() {
     let _ = format!("{error:?}");
 }
 
+fn test_copy() {
+    // Large enough to need several rounds of `copy_file_range`/`sendfile`.
+    let bytes: Vec<u8> = (0..100_000u32).map(|i| i as u8).collect();
+    let path = utils::prepare_with_content("miri_test_fs_copy_source.txt", &bytes);
+    let copy_path = utils::prepare("miri_test_fs_copy_target.txt");
+
+    assert_eq!(fs::copy(&path, &copy_path).unwrap(), 100_000);
+    assert_eq!(fs::read(&copy_path).unwrap(), bytes);
+
+    // `io::copy` continues at the current position of the file.
+    let mut source = File::open(&path).unwrap();
+    source.seek(SeekFrom::Start(99_000)).unwrap();
+    let mut target = OpenOptions::new().append(true).open(&copy_path).unwrap();
+    assert_eq!(io::copy(&mut source, &mut target).unwrap(), 1000);
+    assert_eq!(fs::metadata(&copy_path).unwrap().len(), 101_000);
+
+    // Between a file and a pipe.
+    let (mut reader, mut writer) = io::pipe().unwrap();
+    let mut source = File::open(&path).unwrap();
+    assert_eq!(io::copy(&mut (&mut source).take(1000), &mut writer).unwrap(), 1000);
+    drop(writer);
+    let mut target = File::create(&copy_path).unwrap();
+    assert_eq!(io::copy(&mut reader, &mut target).unwrap(), 1000);
+    assert_eq!(fs::read(&copy_path).unwrap(), &bytes[..1000]);
+
+    remove_file(&path).unwrap();
+    remove_file(&copy_path).unwrap();
+}
+
 #[cfg(unix)]
 fn test_pread_pwrite() {
     use std::os::unix::fs::FileExt;
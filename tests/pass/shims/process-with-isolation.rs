@@ -0,0 +1,10 @@
+//@ignore-target: windows # `CreateProcessW` is not supported
+//@compile-flags: -Zmiri-isolation-error=warn-nobacktrace
+
+use std::io::ErrorKind;
+use std::process::Command;
+
+fn main() {
+    let err = Command::new("true").status().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+}
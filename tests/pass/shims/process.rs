@@ -0,0 +1,86 @@
+//@ignore-host: windows # the test runs Unix utilities
+//@ignore-target: windows # `CreateProcessW` is not supported
+//@compile-flags: -Zmiri-disable-isolation
+
+use std::io::{ErrorKind, Read, Write};
+use std::os::unix::process::ExitStatusExt;
+use std::process::{Command, Stdio};
+
+fn main() {
+    test_status();
+    test_output();
+    test_piped_stdin();
+    test_env_and_cwd();
+    test_kill();
+    test_try_wait();
+    test_not_found();
+}
+
+fn test_status() {
+    assert!(Command::new("true").status().unwrap().success());
+    assert_eq!(Command::new("false").status().unwrap().code(), Some(1));
+    let status = Command::new("sh").args(["-c", "exit 3"]).status().unwrap();
+    assert_eq!(status.code(), Some(3));
+}
+
+fn test_output() {
+    let output = Command::new("echo").arg("hello").output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"hello\n");
+    assert!(output.stderr.is_empty());
+
+    let output =
+        Command::new("sh").args(["-c", "echo out; echo err >&2; exit 7"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(7));
+    assert_eq!(output.stdout, b"out\n");
+    assert_eq!(output.stderr, b"err\n");
+}
+
+fn test_piped_stdin() {
+    let mut child =
+        Command::new("cat").stdin(Stdio::piped()).stdout(Stdio::piped()).spawn().unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(b"piped through cat").unwrap();
+    drop(stdin);
+    let mut buf = String::new();
+    child.stdout.take().unwrap().read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, "piped through cat");
+    assert!(child.wait().unwrap().success());
+}
+
+fn test_env_and_cwd() {
+    let output = Command::new("sh")
+        .args(["-c", "echo $MIRI_CHILD_VAR; pwd"])
+        .env("MIRI_CHILD_VAR", "from the parent")
+        .current_dir("/")
+        .output()
+        .unwrap();
+    assert_eq!(output.stdout, b"from the parent\n/\n");
+}
+
+fn test_kill() {
+    let mut child = Command::new("sleep").arg("10").spawn().unwrap();
+    child.kill().unwrap();
+    let status = child.wait().unwrap();
+    // `SIGKILL` is 9 on all our Unix targets.
+    assert_eq!(status.signal(), Some(9));
+    // Killing an exited child is not an error.
+    child.kill().unwrap();
+}
+
+fn test_try_wait() {
+    let mut child = Command::new("sleep").arg("0.1").spawn().unwrap();
+    assert!(child.try_wait().unwrap().is_none());
+    let status = loop {
+        if let Some(status) = child.try_wait().unwrap() {
+            break status;
+        }
+        std::thread::yield_now();
+    };
+    assert!(status.success());
+}
+
+fn test_not_found() {
+    let err = Command::new("/this/program/does/not/exist").status().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+}
@@ -0,0 +1,180 @@
+//@ignore-target: windows # No socket support on Windows
+//@compile-flags: -Zmiri-virtual-network -Zmiri-many-seeds=0..4
+
+use std::io::{ErrorKind, Read, Write};
+use std::net::{Ipv4Addr, Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::thread;
+
+const TEST_BYTES: &[u8] = b"these are some test bytes!";
+
+fn main() {
+    test_accept_and_connect();
+    test_ipv6();
+    test_echo_server();
+    test_large_transfer();
+    test_shutdown();
+    test_nonblocking_accept();
+    test_connection_refused();
+    test_bind_errors();
+    test_connect_to_unspecified();
+    test_sockopt_nodelay();
+}
+
+/// Ports are handed out deterministically, and both ends know each other's address.
+fn test_accept_and_connect() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    assert_eq!(address.ip(), Ipv4Addr::LOCALHOST);
+    assert_ne!(address.port(), 0);
+
+    let stream = TcpStream::connect(address).unwrap();
+    let (other_stream, peer_address) = listener.accept().unwrap();
+    assert_eq!(stream.peer_addr().unwrap(), address);
+    assert_eq!(stream.local_addr().unwrap(), peer_address);
+    assert_eq!(other_stream.local_addr().unwrap(), address);
+    assert_eq!(other_stream.peer_addr().unwrap(), peer_address);
+}
+
+fn test_ipv6() {
+    let listener = TcpListener::bind("[::1]:0").unwrap();
+    let address = listener.local_addr().unwrap();
+
+    let mut stream = TcpStream::connect(address).unwrap();
+    let (mut other_stream, _) = listener.accept().unwrap();
+    stream.write_all(TEST_BYTES).unwrap();
+    let mut buffer = [0; TEST_BYTES.len()];
+    other_stream.read_exact(&mut buffer).unwrap();
+    assert_eq!(&buffer, TEST_BYTES);
+}
+
+/// A server thread echoes everything a client sends back to it.
+fn test_echo_server() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+
+    let server = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut buffer = [0; 16];
+        loop {
+            let bytes_read = stream.read(&mut buffer).unwrap();
+            if bytes_read == 0 {
+                break;
+            }
+            stream.write_all(&buffer[..bytes_read]).unwrap();
+        }
+    });
+
+    let mut stream = TcpStream::connect(address).unwrap();
+    for _ in 0..3 {
+        stream.write_all(TEST_BYTES).unwrap();
+        let mut buffer = [0; TEST_BYTES.len()];
+        stream.read_exact(&mut buffer).unwrap();
+        assert_eq!(&buffer, TEST_BYTES);
+    }
+    drop(stream);
+    server.join().unwrap();
+}
+
+/// Sending more data than the buffers hold blocks the writer until the reader catches up,
+/// and the data arrives in chunks of random size.
+fn test_large_transfer() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let data: Vec<u8> = (0..100_000u32).map(|i| i as u8).collect();
+
+    let expected = data.clone();
+    let server = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut received = Vec::new();
+        stream.read_to_end(&mut received).unwrap();
+        assert!(received == expected);
+    });
+
+    let mut stream = TcpStream::connect(address).unwrap();
+    stream.write_all(&data).unwrap();
+    drop(stream);
+    server.join().unwrap();
+}
+
+/// Shutting down the writing direction signals the end of the stream to the peer, which
+/// can still answer.
+fn test_shutdown() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+
+    let mut stream = TcpStream::connect(address).unwrap();
+    let (mut other_stream, _) = listener.accept().unwrap();
+    stream.write_all(TEST_BYTES).unwrap();
+    stream.shutdown(Shutdown::Write).unwrap();
+    assert_eq!(stream.write(TEST_BYTES).unwrap_err().kind(), ErrorKind::BrokenPipe);
+
+    let mut received = Vec::new();
+    other_stream.read_to_end(&mut received).unwrap();
+    assert_eq!(received, TEST_BYTES);
+
+    other_stream.write_all(TEST_BYTES).unwrap();
+    drop(other_stream);
+    let mut received = Vec::new();
+    stream.read_to_end(&mut received).unwrap();
+    assert_eq!(received, TEST_BYTES);
+}
+
+fn test_nonblocking_accept() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    listener.set_nonblocking(true).unwrap();
+    assert_eq!(listener.accept().unwrap_err().kind(), ErrorKind::WouldBlock);
+
+    let _stream = TcpStream::connect(address).unwrap();
+    let (stream, _) = listener.accept().unwrap();
+    // The accepted socket doesn't inherit the blocking mode.
+    stream.set_nonblocking(true).unwrap();
+    let mut buffer = [0; 1];
+    assert_eq!((&stream).read(&mut buffer).unwrap_err().kind(), ErrorKind::WouldBlock);
+}
+
+fn test_connection_refused() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    drop(listener);
+    let err = TcpStream::connect(address).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::ConnectionRefused);
+
+    // Only the loopback network exists.
+    let err = TcpStream::connect("192.0.2.1:80").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NetworkUnreachable);
+}
+
+fn test_bind_errors() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let err = TcpListener::bind(address).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::AddrInUse);
+    // The unspecified address overlaps with every other address.
+    let err = TcpListener::bind(SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), address.port()))
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::AddrInUse);
+
+    // Closing the listener frees the address.
+    drop(listener);
+    let _listener = TcpListener::bind(address).unwrap();
+
+    let err = TcpListener::bind("192.0.2.1:0").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::AddrNotAvailable);
+}
+
+/// A listener on the unspecified address accepts connections to the loopback address.
+fn test_connect_to_unspecified() {
+    let listener = TcpListener::bind("0.0.0.0:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let _stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+    let (other_stream, _) = listener.accept().unwrap();
+    assert_eq!(other_stream.local_addr().unwrap(), SocketAddr::from(([127, 0, 0, 1], port)));
+}
+
+fn test_sockopt_nodelay() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    stream.set_nodelay(true).unwrap();
+}
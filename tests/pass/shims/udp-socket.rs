@@ -0,0 +1,203 @@
+//@ignore-target: windows # No socket support on Windows
+//@compile-flags: -Zmiri-disable-isolation
+//@run-native
+
+use std::io::ErrorKind;
+use std::net::{Ipv4Addr, UdpSocket};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const TEST_BYTES: &[u8] = b"these are some test bytes!";
+
+fn main() {
+    test_create_ipv4_socket();
+    test_create_ipv6_socket();
+    test_send_to_recv_from();
+    test_connect_send_recv();
+    test_peek_from();
+    test_datagram_boundaries();
+    test_blocking_recv();
+    test_sockopt_broadcast();
+    test_sockopt_multicast();
+    test_sockopt_ttl();
+    test_sockopt_read_timeout();
+    test_nonblocking();
+}
+
+fn test_create_ipv4_socket() {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    assert!(socket.local_addr().unwrap().is_ipv4());
+}
+
+fn test_create_ipv6_socket() {
+    let socket = UdpSocket::bind("[::1]:0").unwrap();
+    assert!(socket.local_addr().unwrap().is_ipv6());
+}
+
+/// Send a datagram to an explicit address and receive it together
+/// with the address of the sender.
+fn test_send_to_recv_from() {
+    let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+    let bytes_sent = sender.send_to(TEST_BYTES, receiver.local_addr().unwrap()).unwrap();
+    assert_eq!(bytes_sent, TEST_BYTES.len());
+
+    let mut buffer = [0; TEST_BYTES.len()];
+    let (bytes_received, source) = receiver.recv_from(&mut buffer).unwrap();
+    assert_eq!(bytes_received, TEST_BYTES.len());
+    assert_eq!(&buffer, TEST_BYTES);
+    assert_eq!(source, sender.local_addr().unwrap());
+}
+
+/// Connect two sockets to each other and exchange datagrams
+/// without specifying the address on every call.
+fn test_connect_send_recv() {
+    let first = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let second = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+    // Sending on an unconnected socket without an address fails.
+    first.send(TEST_BYTES).unwrap_err();
+    assert_eq!(first.peer_addr().unwrap_err().kind(), ErrorKind::NotConnected);
+
+    first.connect(second.local_addr().unwrap()).unwrap();
+    second.connect(first.local_addr().unwrap()).unwrap();
+    assert_eq!(first.peer_addr().unwrap(), second.local_addr().unwrap());
+
+    first.send(TEST_BYTES).unwrap();
+    let mut buffer = [0; TEST_BYTES.len()];
+    let bytes_received = second.recv(&mut buffer).unwrap();
+    assert_eq!(&buffer[..bytes_received], TEST_BYTES);
+
+    second.send(&TEST_BYTES[..5]).unwrap();
+    let bytes_received = first.recv(&mut buffer).unwrap();
+    assert_eq!(&buffer[..bytes_received], &TEST_BYTES[..5]);
+}
+
+/// Peeking a datagram leaves it in the receive queue.
+fn test_peek_from() {
+    let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+    sender.send_to(TEST_BYTES, receiver.local_addr().unwrap()).unwrap();
+
+    let mut buffer = [0; TEST_BYTES.len()];
+    let (bytes_peeked, source) = receiver.peek_from(&mut buffer).unwrap();
+    assert_eq!(bytes_peeked, TEST_BYTES.len());
+    assert_eq!(source, sender.local_addr().unwrap());
+
+    let mut buffer = [0; TEST_BYTES.len()];
+    let (bytes_received, _) = receiver.recv_from(&mut buffer).unwrap();
+    assert_eq!(&buffer[..bytes_received], TEST_BYTES);
+}
+
+/// Every receive returns exactly one datagram, and the rest of a datagram
+/// which doesn't fit into the buffer is discarded.
+fn test_datagram_boundaries() {
+    let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let address = receiver.local_addr().unwrap();
+
+    sender.send_to(b"first", address).unwrap();
+    sender.send_to(b"second", address).unwrap();
+    sender.send_to(TEST_BYTES, address).unwrap();
+
+    let mut buffer = [0; 64];
+    let (bytes_received, _) = receiver.recv_from(&mut buffer).unwrap();
+    assert_eq!(&buffer[..bytes_received], b"first");
+    let (bytes_received, _) = receiver.recv_from(&mut buffer).unwrap();
+    assert_eq!(&buffer[..bytes_received], b"second");
+
+    let mut small_buffer = [0; 5];
+    let (bytes_received, _) = receiver.recv_from(&mut small_buffer).unwrap();
+    assert_eq!(&small_buffer[..bytes_received], &TEST_BYTES[..5]);
+}
+
+/// A blocking receive waits until another thread sends a datagram.
+fn test_blocking_recv() {
+    let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let address = receiver.local_addr().unwrap();
+
+    let handle = thread::spawn(move || {
+        let mut buffer = [0; TEST_BYTES.len()];
+        let (bytes_received, _) = receiver.recv_from(&mut buffer).unwrap();
+        assert_eq!(&buffer[..bytes_received], TEST_BYTES);
+    });
+
+    // Make it likely that the receiving thread blocks.
+    thread::yield_now();
+
+    let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+    sender.send_to(TEST_BYTES, address).unwrap();
+
+    handle.join().unwrap();
+}
+
+fn test_sockopt_broadcast() {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    assert!(!socket.broadcast().unwrap());
+
+    socket.set_broadcast(true).unwrap();
+    assert!(socket.broadcast().unwrap());
+}
+
+fn test_sockopt_multicast() {
+    let socket = UdpSocket::bind("0.0.0.0:0").unwrap();
+
+    socket.set_multicast_ttl_v4(42).unwrap();
+    assert_eq!(socket.multicast_ttl_v4().unwrap(), 42);
+
+    socket.set_multicast_loop_v4(false).unwrap();
+    assert!(!socket.multicast_loop_v4().unwrap());
+    socket.set_multicast_loop_v4(true).unwrap();
+    assert!(socket.multicast_loop_v4().unwrap());
+
+    let group = Ipv4Addr::new(239, 255, 42, 99);
+    socket.join_multicast_v4(&group, &Ipv4Addr::LOCALHOST).unwrap();
+    socket.leave_multicast_v4(&group, &Ipv4Addr::LOCALHOST).unwrap();
+}
+
+fn test_sockopt_ttl() {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+    socket.set_ttl(12).unwrap();
+    assert_eq!(socket.ttl().unwrap(), 12);
+}
+
+/// A blocking receive with a read timeout gives up
+/// once the timeout expired.
+fn test_sockopt_read_timeout() {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+    let timeout = Duration::from_millis(20);
+    socket.set_read_timeout(Some(timeout)).unwrap();
+    assert_eq!(socket.read_timeout().unwrap(), Some(timeout));
+
+    let start = Instant::now();
+    let mut buffer = [0; TEST_BYTES.len()];
+    let err = socket.recv_from(&mut buffer).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::WouldBlock);
+    assert!(start.elapsed() >= timeout);
+}
+
+/// Non-blocking sockets return `WouldBlock` instead of blocking.
+fn test_nonblocking() {
+    let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+    receiver.set_nonblocking(true).unwrap();
+
+    let mut buffer = [0; TEST_BYTES.len()];
+    let err = receiver.recv_from(&mut buffer).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::WouldBlock);
+
+    let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+    sender.send_to(TEST_BYTES, receiver.local_addr().unwrap()).unwrap();
+
+    // The datagram might not have arrived yet, so keep trying.
+    let bytes_received = loop {
+        match receiver.recv(&mut buffer) {
+            Ok(bytes_received) => break bytes_received,
+            Err(e) if e.kind() == ErrorKind::WouldBlock => thread::yield_now(),
+            Err(e) => panic!("unexpected error: {e}"),
+        }
+    };
+    assert_eq!(&buffer[..bytes_received], TEST_BYTES);
+}
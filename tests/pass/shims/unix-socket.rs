@@ -1,13 +1,25 @@
 //@ignore-target: windows
 
 use std::io::{ErrorKind, Read, Write};
-use std::os::unix::net::UnixStream;
+use std::os::unix::net::{UnixDatagram, UnixListener, UnixStream};
+use std::path::Path;
+use std::thread;
 
 const TEST_BYTES: &[u8] = b"these are some test bytes!";
 
 fn main() {
     test_send_recv();
     test_change_blocking_mode();
+    test_listener_stream();
+    test_connect_before_accept();
+    test_connect_nonexistent();
+    test_bind_in_use();
+    test_nonblocking_accept();
+    test_datagram_send_to_recv_from();
+    test_datagram_connect();
+    test_datagram_pair();
+    #[cfg(target_os = "linux")]
+    test_abstract_namespace();
 }
 
 /// Test sending and receiving data on a pair of sockets.
@@ -32,3 +44,159 @@ fn test_change_blocking_mode() {
     let err = sock1.read(&mut buffer).unwrap_err();
     assert_eq!(err.kind(), ErrorKind::WouldBlock);
 }
+
+/// Test accepting a connection on a listener bound to a path, while the
+/// client blocks on reading the response.
+fn test_listener_stream() {
+    let path = Path::new("miri-test-listener-stream.sock");
+    let listener = UnixListener::bind(path).unwrap();
+    assert_eq!(listener.local_addr().unwrap().as_pathname(), Some(path));
+
+    let server = thread::spawn(move || {
+        let (mut stream, address) = listener.accept().unwrap();
+        assert!(address.is_unnamed());
+        let mut buffer = [0; TEST_BYTES.len()];
+        stream.read_exact(&mut buffer).unwrap();
+        stream.write_all(&buffer).unwrap();
+    });
+
+    let mut stream = UnixStream::connect(path).unwrap();
+    assert_eq!(stream.peer_addr().unwrap().as_pathname(), Some(path));
+    assert!(stream.local_addr().unwrap().is_unnamed());
+    stream.write_all(TEST_BYTES).unwrap();
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).unwrap();
+    assert_eq!(response, TEST_BYTES);
+
+    server.join().unwrap();
+}
+
+/// Connecting succeeds before the connection is accepted, and data
+/// written in the meantime is not lost.
+fn test_connect_before_accept() {
+    let listener = UnixListener::bind("miri-test-connect-before-accept.sock").unwrap();
+
+    let mut client = UnixStream::connect("miri-test-connect-before-accept.sock").unwrap();
+    client.write_all(TEST_BYTES).unwrap();
+    drop(client);
+
+    let (mut stream, _) = listener.accept().unwrap();
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).unwrap();
+    assert_eq!(response, TEST_BYTES);
+}
+
+fn test_connect_nonexistent() {
+    let err = UnixStream::connect("miri-test-does-not-exist.sock").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+
+    // A socket which is bound but not listening refuses connections.
+    let _socket = UnixDatagram::bind("miri-test-not-listening.sock").unwrap();
+    UnixStream::connect("miri-test-not-listening.sock").unwrap_err();
+}
+
+/// A name can only be bound once, but it becomes free again when the socket is closed.
+fn test_bind_in_use() {
+    let listener = UnixListener::bind("miri-test-bind-in-use.sock").unwrap();
+    let err = UnixListener::bind("miri-test-bind-in-use.sock").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::AddrInUse);
+
+    drop(listener);
+    UnixListener::bind("miri-test-bind-in-use.sock").unwrap();
+}
+
+fn test_nonblocking_accept() {
+    let listener = UnixListener::bind("miri-test-nonblocking-accept.sock").unwrap();
+    listener.set_nonblocking(true).unwrap();
+
+    let err = listener.accept().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::WouldBlock);
+
+    let _client = UnixStream::connect("miri-test-nonblocking-accept.sock").unwrap();
+    listener.accept().unwrap();
+}
+
+/// Every receive returns exactly one datagram together with the
+/// address it was sent from.
+fn test_datagram_send_to_recv_from() {
+    let receiver = UnixDatagram::bind("miri-test-datagram-receiver.sock").unwrap();
+    let sender = UnixDatagram::bind("miri-test-datagram-sender.sock").unwrap();
+
+    sender.send_to(b"first", "miri-test-datagram-receiver.sock").unwrap();
+    sender.send_to(TEST_BYTES, "miri-test-datagram-receiver.sock").unwrap();
+
+    let mut buffer = [0; 64];
+    let (bytes_received, address) = receiver.recv_from(&mut buffer).unwrap();
+    assert_eq!(&buffer[..bytes_received], b"first");
+    assert_eq!(address.as_pathname(), Some(Path::new("miri-test-datagram-sender.sock")));
+
+    // The rest of a datagram which doesn't fit into the buffer is discarded.
+    let mut small_buffer = [0; 5];
+    let bytes_received = receiver.recv(&mut small_buffer).unwrap();
+    assert_eq!(&small_buffer[..bytes_received], &TEST_BYTES[..5]);
+
+    // Datagrams from unbound sockets come from an unnamed address.
+    let unbound = UnixDatagram::unbound().unwrap();
+    unbound.send_to(TEST_BYTES, "miri-test-datagram-receiver.sock").unwrap();
+    let (_, address) = receiver.recv_from(&mut buffer).unwrap();
+    assert!(address.is_unnamed());
+
+    receiver.set_nonblocking(true).unwrap();
+    let err = receiver.recv(&mut buffer).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::WouldBlock);
+}
+
+/// Test a blocking receive on a connected datagram socket.
+fn test_datagram_connect() {
+    let receiver = UnixDatagram::bind("miri-test-datagram-connect.sock").unwrap();
+    let sender = UnixDatagram::unbound().unwrap();
+
+    let err = sender.send(TEST_BYTES).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotConnected);
+
+    sender.connect("miri-test-datagram-connect.sock").unwrap();
+    assert_eq!(
+        sender.peer_addr().unwrap().as_pathname(),
+        Some(Path::new("miri-test-datagram-connect.sock"))
+    );
+
+    let handle = thread::spawn(move || {
+        let mut buffer = [0; TEST_BYTES.len()];
+        let bytes_received = receiver.recv(&mut buffer).unwrap();
+        assert_eq!(&buffer[..bytes_received], TEST_BYTES);
+    });
+    // Make it likely that the receiving thread blocks.
+    thread::yield_now();
+    sender.send(TEST_BYTES).unwrap();
+    handle.join().unwrap();
+}
+
+fn test_datagram_pair() {
+    let (first, second) = UnixDatagram::pair().unwrap();
+    first.send(b"first").unwrap();
+    second.send(TEST_BYTES).unwrap();
+
+    let mut buffer = [0; 64];
+    let bytes_received = second.recv(&mut buffer).unwrap();
+    assert_eq!(&buffer[..bytes_received], b"first");
+    let bytes_received = first.recv(&mut buffer).unwrap();
+    assert_eq!(&buffer[..bytes_received], TEST_BYTES);
+}
+
+#[cfg(target_os = "linux")]
+fn test_abstract_namespace() {
+    use std::os::linux::net::SocketAddrExt;
+    use std::os::unix::net::SocketAddr;
+
+    let address = SocketAddr::from_abstract_name(b"miri-test-abstract").unwrap();
+    let listener = UnixListener::bind_addr(&address).unwrap();
+    assert_eq!(listener.local_addr().unwrap().as_abstract_name(), Some(&b"miri-test-abstract"[..]));
+
+    let mut client = UnixStream::connect_addr(&address).unwrap();
+    client.write_all(TEST_BYTES).unwrap();
+    let (mut stream, _) = listener.accept().unwrap();
+    let mut buffer = [0; TEST_BYTES.len()];
+    stream.read_exact(&mut buffer).unwrap();
+    assert_eq!(&buffer, TEST_BYTES);
+}